@@ -0,0 +1,323 @@
+//! Low-stock and near-expiry alerts raised by a periodic background check.
+//!
+//! Each check compares what the reorder and expiry reports show now with
+//! the alerts still unresolved: conditions that cleared are resolved, and
+//! only conditions with no unresolved alert are raised, so an alert is
+//! shown once however often the check runs. Acknowledging an alert hides
+//! it without resolving it, so it is not raised again while it holds.
+
+use std::collections::{HashMap, HashSet};
+
+use rusqlite::{params, Connection, Row};
+use serde::Serialize;
+
+use crate::auth::{self, permission};
+use crate::db::{ChangeOp, Entity, Tx};
+use crate::error::{AppError, AppResult};
+use crate::reports;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    LowStock,
+    NearExpiry,
+}
+
+impl AlertKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::LowStock => "low_stock",
+            Self::NearExpiry => "near_expiry",
+        }
+    }
+
+    pub fn parse(s: &str) -> AppResult<Self> {
+        match s {
+            "low_stock" => Ok(Self::LowStock),
+            "near_expiry" => Ok(Self::NearExpiry),
+            other => Err(AppError::validation(format!(
+                "Unknown alert kind '{other}'"
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Alert {
+    pub id: i64,
+    pub kind: AlertKind,
+    pub medicine_id: i64,
+    pub medicine_name: String,
+    /// The expiring batch; `None` for low-stock alerts.
+    pub batch_id: Option<i64>,
+    pub message: String,
+    pub created_at: String,
+    pub acknowledged_at: Option<String>,
+    pub acknowledged_by: Option<i64>,
+}
+
+/// What an alert is about; at most one unresolved alert exists per key.
+type AlertKey = (AlertKind, i64, Option<i64>);
+
+const ALERT_COLUMNS: &str = "a.id, a.kind, a.medicine_id, m.name, a.batch_id, a.message,
+     a.created_at, a.acknowledged_at, a.acknowledged_by";
+
+fn alert_from_row(row: &Row) -> AppResult<Alert> {
+    let kind: String = row.get(1)?;
+    Ok(Alert {
+        id: row.get(0)?,
+        kind: AlertKind::parse(&kind)?,
+        medicine_id: row.get(2)?,
+        medicine_name: row.get(3)?,
+        batch_id: row.get(4)?,
+        message: row.get(5)?,
+        created_at: row.get(6)?,
+        acknowledged_at: row.get(7)?,
+        acknowledged_by: row.get(8)?,
+    })
+}
+
+fn get_alert(conn: &Connection, alert_id: i64) -> AppResult<Alert> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {ALERT_COLUMNS} FROM alerts a
+         JOIN medicines m ON m.id = a.medicine_id
+         WHERE a.id = ?1"
+    ))?;
+    let mut rows = stmt.query(params![alert_id])?;
+    match rows.next()? {
+        Some(row) => alert_from_row(row),
+        None => Err(AppError::not_found(format!("Alert {alert_id}"))),
+    }
+}
+
+/// Conditions that hold now, with the message each would be raised with.
+///
+/// Stock and expiry are counted across all branches, as on the dashboard.
+fn current_conditions(conn: &Connection) -> AppResult<Vec<(AlertKey, String)>> {
+    let mut conditions = Vec::new();
+    for item in reports::reorder_report(conn, None)? {
+        conditions.push((
+            (AlertKind::LowStock, item.medicine_id, None),
+            format!(
+                "{}: {} in stock, reorder level {}",
+                item.medicine_name, item.current_stock, item.reorder_level
+            ),
+        ));
+    }
+    for batch in reports::expiry_report(conn, None)?.near_expiry {
+        conditions.push((
+            (
+                AlertKind::NearExpiry,
+                batch.medicine_id,
+                Some(batch.batch_id),
+            ),
+            format!(
+                "{} batch {} expires on {} ({} in stock)",
+                batch.medicine_name, batch.batch_number, batch.expiry_date, batch.quantity
+            ),
+        ));
+    }
+    Ok(conditions)
+}
+
+/// Resolve alerts whose condition has cleared and raise the new ones.
+///
+/// Returns the alerts raised by this check, for the caller to notify about.
+pub fn check_alerts(tx: &Tx) -> AppResult<Vec<Alert>> {
+    let conditions = current_conditions(tx)?;
+    let holding: HashSet<AlertKey> = conditions.iter().map(|(key, _)| *key).collect();
+
+    let mut unresolved = HashMap::new();
+    {
+        let mut stmt = tx.prepare(
+            "SELECT id, kind, medicine_id, batch_id FROM alerts WHERE resolved_at IS NULL",
+        )?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let kind: String = row.get(1)?;
+            let key = (AlertKind::parse(&kind)?, row.get(2)?, row.get(3)?);
+            unresolved.insert(key, row.get::<_, i64>(0)?);
+        }
+    }
+
+    for (key, &id) in &unresolved {
+        if !holding.contains(key) {
+            tx.execute(
+                "UPDATE alerts SET resolved_at = datetime('now') WHERE id = ?1",
+                params![id],
+            )?;
+            tx.changed(Entity::Alert, ChangeOp::Update, id);
+        }
+    }
+
+    let mut raised = Vec::new();
+    for ((kind, medicine_id, batch_id), message) in conditions {
+        if unresolved.contains_key(&(kind, medicine_id, batch_id)) {
+            continue;
+        }
+        tx.execute(
+            "INSERT INTO alerts (kind, medicine_id, batch_id, message) VALUES (?1, ?2, ?3, ?4)",
+            params![kind.as_str(), medicine_id, batch_id, message],
+        )?;
+        let id = tx.last_insert_rowid();
+        tx.changed(Entity::Alert, ChangeOp::Insert, id);
+        raised.push(get_alert(tx, id)?);
+    }
+    Ok(raised)
+}
+
+/// Run [`check_alerts`] now at a user's request, instead of waiting for the
+/// next background pass.
+pub fn check_alerts_now(tx: &Tx, user_id: i64) -> AppResult<Vec<Alert>> {
+    auth::require_permission(tx, user_id, permission::DASHBOARD_STOCK)?;
+    check_alerts(tx)
+}
+
+/// Unresolved alerts, newest first; acknowledged ones only when asked for.
+pub fn list_alerts(conn: &Connection, include_acknowledged: bool) -> AppResult<Vec<Alert>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {ALERT_COLUMNS} FROM alerts a
+         JOIN medicines m ON m.id = a.medicine_id
+         WHERE a.resolved_at IS NULL AND (?1 OR a.acknowledged_at IS NULL)
+         ORDER BY a.created_at DESC, a.id DESC"
+    ))?;
+    let mut rows = stmt.query(params![include_acknowledged])?;
+    let mut alerts = Vec::new();
+    while let Some(row) = rows.next()? {
+        alerts.push(alert_from_row(row)?);
+    }
+    Ok(alerts)
+}
+
+/// Mark an alert as seen by `user_id`.
+pub fn acknowledge_alert(tx: &Tx, alert_id: i64, user_id: i64) -> AppResult<Alert> {
+    auth::active_role(tx, user_id)?;
+    let alert = get_alert(tx, alert_id)?;
+    if alert.acknowledged_at.is_some() {
+        return Err(AppError::validation("Alert is already acknowledged"));
+    }
+    tx.execute(
+        "UPDATE alerts SET acknowledged_at = datetime('now'), acknowledged_by = ?2 WHERE id = ?1",
+        params![alert_id, user_id],
+    )?;
+    tx.changed(Entity::Alert, ChangeOp::Update, alert_id);
+    get_alert(tx, alert_id)
+}
+
+/// Body of the desktop notification for newly raised alerts, if any.
+pub fn notification_body(raised: &[Alert]) -> Option<String> {
+    match raised {
+        [] => None,
+        [alert] => Some(alert.message.clone()),
+        _ => {
+            let low = raised
+                .iter()
+                .filter(|a| a.kind == AlertKind::LowStock)
+                .count();
+            let expiring = raised.len() - low;
+            let mut parts = Vec::new();
+            if low > 0 {
+                parts.push(format!(
+                    "{low} medicine{} low on stock",
+                    if low == 1 { "" } else { "s" }
+                ));
+            }
+            if expiring > 0 {
+                parts.push(format!(
+                    "{expiring} batch{} expiring soon",
+                    if expiring == 1 { "" } else { "es" }
+                ));
+            }
+            Some(parts.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{insert_batch, insert_cashier, insert_medicine, migrated_db};
+
+    #[test]
+    fn alerts_are_raised_once_and_resolved_when_the_condition_clears() {
+        let (_dir, db) = migrated_db();
+        let (stocked, expiring) = db
+            .with_tx(|tx| {
+                tx.execute(
+                    "UPDATE pharmacy_settings SET near_expiry_days = 30 WHERE id = 1",
+                    [],
+                )?;
+                let stocked = insert_medicine(tx, "Paracetamol 500", 12.0);
+                insert_batch(tx, stocked, "+1 year", 1000, 50);
+                let short = insert_medicine(tx, "Amoxicillin 250", 12.0);
+                let expiring = insert_batch(tx, short, "+10 days", 1000, 4);
+                Ok((stocked, expiring))
+            })
+            .unwrap();
+
+        let raised = db.with_tx(check_alerts).unwrap();
+        let kinds: Vec<_> = raised.iter().map(|a| (a.kind, a.batch_id)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (AlertKind::LowStock, None),
+                (AlertKind::NearExpiry, Some(expiring))
+            ]
+        );
+        assert_eq!(
+            raised[0].message,
+            "Amoxicillin 250: 4 in stock, reorder level 20"
+        );
+        assert_eq!(
+            notification_body(&raised).as_deref(),
+            Some("1 medicine low on stock, 1 batch expiring soon")
+        );
+        assert!(raised.iter().all(|a| a.medicine_id != stocked));
+
+        // Nothing new to raise while the conditions hold.
+        assert!(db.with_tx(check_alerts).unwrap().is_empty());
+
+        let acked = db
+            .with_tx(|tx| acknowledge_alert(tx, raised[0].id, 1))
+            .unwrap();
+        assert_eq!(acked.acknowledged_by, Some(1));
+        assert!(db
+            .with_tx(|tx| acknowledge_alert(tx, raised[0].id, 1))
+            .is_err());
+        let open = db.with_conn(|conn| list_alerts(conn, false)).unwrap();
+        assert_eq!(open.len(), 1);
+        assert_eq!(
+            db.with_conn(|conn| list_alerts(conn, true)).unwrap().len(),
+            2
+        );
+        assert!(db.with_tx(check_alerts).unwrap().is_empty());
+
+        // Selling off the expiring batch clears its alert for good.
+        db.with_conn(|conn| {
+            conn.execute("UPDATE batches SET quantity = 0 WHERE id = ?1", [expiring])?;
+            Ok(())
+        })
+        .unwrap();
+        db.with_tx(check_alerts).unwrap();
+        let open = db.with_conn(|conn| list_alerts(conn, true)).unwrap();
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].kind, AlertKind::LowStock);
+    }
+
+    #[test]
+    fn cashier_cannot_run_the_check() {
+        let (_dir, db) = migrated_db();
+        let cashier = db
+            .with_tx(|tx| {
+                let short = insert_medicine(tx, "Amoxicillin 250", 12.0);
+                insert_batch(tx, short, "+1 year", 1000, 4);
+                Ok(insert_cashier(tx))
+            })
+            .unwrap();
+
+        let err = db.with_tx(|tx| check_alerts_now(tx, cashier)).unwrap_err();
+        assert!(matches!(err, AppError::Unauthorized(_)), "{err}");
+        assert_eq!(db.with_tx(|tx| check_alerts_now(tx, 1)).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,146 @@
+//! The audit trail of changes to medicines, batches, sales and users.
+//!
+//! Rows are written by triggers, so changes made through the SQL plugin are
+//! caught as well as the backend's own. Each row keeps the audited columns
+//! before and after the change as JSON.
+
+use rusqlite::{params, Connection, Row};
+use serde::Serialize;
+
+use crate::auth::{self, permission};
+use crate::error::{AppError, AppResult};
+use crate::reports::validate_date_range;
+
+/// Tables with audit triggers.
+const AUDITED_TABLES: &[&str] = &["medicines", "batches", "sales", "users"];
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub id: i64,
+    pub table_name: String,
+    pub row_id: i64,
+    /// `insert`, `update` or `delete`.
+    pub action: String,
+    /// Who made the change, where the row records it; only sales do.
+    pub user_id: Option<i64>,
+    /// `None` for inserts.
+    pub old_values: Option<serde_json::Value>,
+    /// `None` for deletes.
+    pub new_values: Option<serde_json::Value>,
+    pub changed_at: String,
+}
+
+fn json_column(row: &Row, idx: usize) -> AppResult<Option<serde_json::Value>> {
+    let text: Option<String> = row.get(idx)?;
+    text.map(|text| {
+        serde_json::from_str(&text)
+            .map_err(|err| AppError::validation(format!("Unreadable audit values: {err}")))
+    })
+    .transpose()
+}
+
+fn entry_from_row(row: &Row) -> AppResult<AuditEntry> {
+    Ok(AuditEntry {
+        id: row.get(0)?,
+        table_name: row.get(1)?,
+        row_id: row.get(2)?,
+        action: row.get(3)?,
+        user_id: row.get(4)?,
+        old_values: json_column(row, 5)?,
+        new_values: json_column(row, 6)?,
+        changed_at: row.get(7)?,
+    })
+}
+
+/// Changes made from `from_date` to `to_date`, inclusive, newest first,
+/// optionally only those to one table or by one user.
+pub fn query_audit_log(
+    conn: &Connection,
+    user_id: i64,
+    from_date: &str,
+    to_date: &str,
+    table_name: Option<&str>,
+    changed_by: Option<i64>,
+    limit: usize,
+) -> AppResult<Vec<AuditEntry>> {
+    auth::require_permission(conn, user_id, permission::AUDIT_VIEW)?;
+    validate_date_range(conn, from_date, to_date)?;
+    if let Some(table) = table_name {
+        if !AUDITED_TABLES.contains(&table) {
+            return Err(AppError::validation(format!(
+                "The {table} table is not audited"
+            )));
+        }
+    }
+    if limit == 0 {
+        return Err(AppError::validation("The limit must be at least 1"));
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT id, table_name, row_id, action, user_id, old_values, new_values, changed_at
+         FROM audit_log
+         WHERE date(changed_at) BETWEEN ?1 AND ?2
+           AND (?3 IS NULL OR table_name = ?3)
+           AND (?4 IS NULL OR user_id = ?4)
+         ORDER BY id DESC
+         LIMIT ?5",
+    )?;
+    let mut rows = stmt.query(params![
+        from_date,
+        to_date,
+        table_name,
+        changed_by,
+        limit as i64
+    ])?;
+    let mut entries = Vec::new();
+    while let Some(row) = rows.next()? {
+        entries.push(entry_from_row(row)?);
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{insert_batch, insert_medicine, migrated_db, paid_sale};
+    use crate::sales::{create_sale, PaymentMode};
+
+    const ADMIN: i64 = 1;
+
+    #[test]
+    fn changes_are_logged_and_filtered() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 1_120, 10);
+        conn.execute(
+            "UPDATE medicines SET name = 'Paracetamol 500' WHERE id = ?1",
+            [para],
+        )
+        .unwrap();
+        let sale = paid_sale(&conn, None, PaymentMode::Cash, batch, 1);
+        db.with_tx(|tx| create_sale(tx, &sale)).unwrap();
+        let today: String = conn
+            .query_row("SELECT date('now')", [], |row| row.get(0))
+            .unwrap();
+
+        let renames =
+            query_audit_log(&conn, ADMIN, &today, &today, Some("medicines"), None, 50).unwrap();
+        let rename = renames.iter().find(|e| e.action == "update").unwrap();
+        assert_eq!(rename.old_values.as_ref().unwrap()["name"], "Paracetamol");
+        assert_eq!(
+            rename.new_values.as_ref().unwrap()["name"],
+            "Paracetamol 500"
+        );
+
+        let by_admin =
+            query_audit_log(&conn, ADMIN, &today, &today, None, Some(ADMIN), 50).unwrap();
+        assert!(!by_admin.is_empty());
+        assert!(by_admin.iter().all(|e| e.table_name == "sales"));
+
+        let err =
+            query_audit_log(&conn, ADMIN, &today, &today, Some("customers"), None, 50).unwrap_err();
+        assert!(err.to_string().contains("not audited"), "{err}");
+    }
+}
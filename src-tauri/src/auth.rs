@@ -0,0 +1,845 @@
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::db::Tx;
+use crate::error::{AppError, AppResult};
+
+/// Hash of the `admin` password shipped in the seed migration. Its presence
+/// marks first-run setup as pending.
+pub const DEFAULT_ADMIN_HASH: &str = "$2b$10$n5vbmtk602soZ1.G8CH/6u4F69goDCXD9c.SObHhhH36QdpnVtFkG";
+
+const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// bcrypt cost.
+const BCRYPT_COST: u32 = 10;
+
+/// How long a login lasts before the user must sign in again.
+const SESSION_HOURS: i64 = 12;
+
+/// User roles, matching the `users.role` CHECK constraint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Admin,
+    Pharmacist,
+    Cashier,
+}
+
+impl Role {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Admin => "admin",
+            Self::Pharmacist => "pharmacist",
+            Self::Cashier => "cashier",
+        }
+    }
+
+    pub fn parse(s: &str) -> AppResult<Self> {
+        match s {
+            "admin" => Ok(Self::Admin),
+            "pharmacist" => Ok(Self::Pharmacist),
+            "cashier" => Ok(Self::Cashier),
+            other => Err(AppError::validation(format!("Unknown role '{other}'"))),
+        }
+    }
+}
+
+/// Re-read a user's role from the database.
+///
+/// The frontend's idea of the session is never trusted: unknown and
+/// deactivated users are rejected.
+pub fn active_role(conn: &Connection, user_id: i64) -> AppResult<Role> {
+    let row: Option<(String, bool)> = conn
+        .query_row(
+            "SELECT role, is_active FROM users WHERE id = ?1",
+            params![user_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+
+    match row {
+        Some((role, true)) => Role::parse(&role),
+        _ => Err(AppError::unauthorized("user is unknown or inactive")),
+    }
+}
+
+/// Keys of the `permissions` table that backend commands check.
+pub mod permission {
+    pub const AUDIT_VIEW: &str = "audit.view";
+    pub const BACKUP_CREATE: &str = "backup.create";
+    pub const BACKUP_RESTORE: &str = "backup.restore";
+    pub const DATABASE_ENCRYPT: &str = "database.encrypt";
+    pub const DATABASE_MAINTAIN: &str = "database.maintain";
+    pub const DASHBOARD_FINANCIALS: &str = "dashboard.financials";
+    pub const DASHBOARD_STOCK: &str = "dashboard.stock";
+    pub const EXPENSES_MANAGE: &str = "expenses.manage";
+    pub const INVENTORY_ADJUST: &str = "inventory.adjust";
+    pub const INVENTORY_DISPOSE: &str = "inventory.dispose";
+    pub const INVENTORY_REORDER: &str = "inventory.reorder";
+    pub const INVENTORY_TRANSFER: &str = "inventory.transfer";
+    pub const LOYALTY_REDEEM: &str = "loyalty.redeem";
+    pub const MEDICINES_IMPORT: &str = "medicines.import";
+    pub const MEDICINES_MANAGE: &str = "medicines.manage";
+    pub const PRESCRIPTIONS_LINK: &str = "prescriptions.link";
+    pub const PURCHASES_APPROVE: &str = "purchases.approve";
+    pub const PURCHASES_ORDER: &str = "purchases.order";
+    pub const PURCHASES_RECEIVE: &str = "purchases.receive";
+    pub const REPORTS_EXPORT: &str = "reports.export";
+    pub const SALES_AMEND: &str = "sales.amend";
+    pub const SALES_EINVOICE: &str = "sales.einvoice";
+    pub const SALES_EWAYBILL: &str = "sales.ewaybill";
+    pub const SALES_REFUND: &str = "sales.refund";
+    pub const SCHEDULER_MANAGE: &str = "scheduler.manage";
+    pub const SETTINGS_MANAGE: &str = "settings.manage";
+    pub const SHIFTS_MANAGE: &str = "shifts.manage";
+    pub const SYNC_FLUSH: &str = "sync.flush";
+    pub const USERS_MANAGE: &str = "users.manage";
+    pub const USERS_PERMISSIONS: &str = "users.permissions";
+    pub const USERS_UNLOCK: &str = "users.unlock";
+}
+
+/// Whether `role` has been granted `permission`.
+pub fn role_has_permission(conn: &Connection, role: Role, permission: &str) -> AppResult<bool> {
+    Ok(conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM role_permissions WHERE role = ?1 AND permission = ?2)",
+        params![role.as_str(), permission],
+        |row| row.get(0),
+    )?)
+}
+
+/// Whether the user is active and their role holds `permission`.
+pub fn check_permission(conn: &Connection, user_id: i64, permission: &str) -> AppResult<bool> {
+    let role = active_role(conn, user_id)?;
+    role_has_permission(conn, role, permission)
+}
+
+/// Fail unless the user is active and their role holds `permission`.
+pub fn require_permission(conn: &Connection, user_id: i64, permission: &str) -> AppResult<Role> {
+    let role = active_role(conn, user_id)?;
+    if role_has_permission(conn, role, permission)? {
+        Ok(role)
+    } else {
+        Err(AppError::unauthorized(format!(
+            "the {} role lacks the {permission} permission",
+            role.as_str()
+        )))
+    }
+}
+
+/// Replace everything `role` may do with `permissions`.
+///
+/// Admins always keep the right to change permissions, so the matrix can
+/// never lock everyone out of it.
+pub fn set_role_permissions(
+    tx: &Tx,
+    user_id: i64,
+    role: Role,
+    permissions: &[String],
+) -> AppResult<()> {
+    require_permission(tx, user_id, permission::USERS_PERMISSIONS)?;
+    if role == Role::Admin
+        && !permissions
+            .iter()
+            .any(|p| p == permission::USERS_PERMISSIONS)
+    {
+        return Err(AppError::validation(format!(
+            "The admin role must keep the {} permission",
+            permission::USERS_PERMISSIONS
+        )));
+    }
+    for key in permissions {
+        let known: bool = tx.query_row(
+            "SELECT EXISTS(SELECT 1 FROM permissions WHERE key = ?1)",
+            params![key],
+            |row| row.get(0),
+        )?;
+        if !known {
+            return Err(AppError::not_found(format!("Permission '{key}'")));
+        }
+    }
+
+    tx.execute(
+        "DELETE FROM role_permissions WHERE role = ?1",
+        params![role.as_str()],
+    )?;
+    let mut grant = tx.prepare_cached(
+        "INSERT OR IGNORE INTO role_permissions (role, permission) VALUES (?1, ?2)",
+    )?;
+    for key in permissions {
+        grant.execute(params![role.as_str(), key])?;
+    }
+    Ok(())
+}
+
+/// Reject passwords that are too short or are the publicly known default.
+pub fn check_password_strength(password: &str) -> AppResult<()> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(AppError::validation(format!(
+            "Passwords must be at least {MIN_PASSWORD_LENGTH} characters"
+        )));
+    }
+    if bcrypt::verify(password, DEFAULT_ADMIN_HASH).unwrap_or(false) {
+        return Err(AppError::validation(
+            "The password cannot be the default password",
+        ));
+    }
+    Ok(())
+}
+
+pub fn hash_password(password: &str) -> AppResult<String> {
+    bcrypt::hash(password, BCRYPT_COST)
+        .map_err(|err| AppError::validation(format!("Could not hash the password: {err}")))
+}
+
+/// Replace a user's password after checking their current one.
+///
+/// Unknown, inactive and wrong-password cases fail identically, and take
+/// as long, so the call cannot be used to probe which user ids exist.
+pub fn change_password(
+    tx: &Tx,
+    user_id: i64,
+    current_password: &str,
+    new_password: &str,
+) -> AppResult<()> {
+    let stored: Option<String> = tx
+        .query_row(
+            "SELECT password_hash FROM users WHERE id = ?1 AND is_active = 1",
+            params![user_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let verified = match &stored {
+        Some(hash) => bcrypt::verify(current_password, hash).unwrap_or(false),
+        None => {
+            let _ = bcrypt::verify(current_password, DEFAULT_ADMIN_HASH);
+            false
+        }
+    };
+    if !verified {
+        return Err(AppError::unauthorized("the current password is incorrect"));
+    }
+
+    check_password_strength(new_password)?;
+    if new_password == current_password {
+        return Err(AppError::validation(
+            "The new password must be different from the current one",
+        ));
+    }
+    tx.execute(
+        "UPDATE users SET password_hash = ?1 WHERE id = ?2",
+        params![hash_password(new_password)?, user_id],
+    )?;
+    Ok(())
+}
+
+/// The signed-in user, without their password hash.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionUser {
+    pub id: i64,
+    pub username: String,
+    pub full_name: String,
+    pub role: Role,
+    pub is_active: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    pub token: String,
+    pub expires_at: String,
+    pub user: SessionUser,
+}
+
+fn record_login(tx: &Tx, username: &str, user_id: Option<i64>, success: bool) -> AppResult<()> {
+    tx.execute(
+        "INSERT INTO login_audit (username, user_id, success) VALUES (?1, ?2, ?3)",
+        params![username, user_id, success],
+    )?;
+    Ok(())
+}
+
+/// Lock the account once it has failed `lockout_max_attempts` times within
+/// `lockout_window_minutes`, counting only since its last successful login
+/// or the end of its last lock.
+fn lock_after_failures(tx: &Tx, user_id: i64) -> AppResult<()> {
+    tx.execute(
+        "UPDATE users SET locked_until = datetime('now', '+' || (
+             SELECT lockout_minutes FROM pharmacy_settings WHERE id = 1) || ' minutes')
+         WHERE id = ?1
+           AND (SELECT COUNT(*) FROM login_audit a
+                WHERE a.user_id = ?1 AND a.success = 0
+                  AND a.created_at > datetime('now', '-' || (
+                      SELECT lockout_window_minutes FROM pharmacy_settings WHERE id = 1) || ' minutes')
+                  AND a.created_at > COALESCE(users.locked_until, '')
+                  AND a.created_at > COALESCE(
+                      (SELECT MAX(created_at) FROM login_audit
+                       WHERE user_id = ?1 AND success = 1), ''))
+               >= (SELECT lockout_max_attempts FROM pharmacy_settings WHERE id = 1)",
+        params![user_id],
+    )?;
+    Ok(())
+}
+
+/// Changes to a user account made from the users screen.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserUpdate {
+    pub full_name: String,
+    pub role: Role,
+    pub is_active: bool,
+    /// A new password, or `None` to keep the current one.
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+fn active_admins(conn: &Connection) -> AppResult<i64> {
+    Ok(conn.query_row(
+        "SELECT COUNT(*) FROM users WHERE role = 'admin' AND is_active = 1",
+        [],
+        |row| row.get(0),
+    )?)
+}
+
+/// Add a user account and return its id.
+///
+/// Without a `user_id` this only creates the first account, which must be
+/// an admin; after that it needs a session allowed to manage users.
+pub fn create_user(
+    tx: &Tx,
+    username: &str,
+    password: &str,
+    full_name: &str,
+    role: Role,
+    user_id: Option<i64>,
+) -> AppResult<i64> {
+    match user_id {
+        Some(user_id) => {
+            require_permission(tx, user_id, permission::USERS_MANAGE)?;
+        }
+        None => {
+            let users: i64 = tx.query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0))?;
+            if users > 0 || role != Role::Admin {
+                return Err(AppError::unauthorized("sign in to add users"));
+            }
+        }
+    }
+    let (username, full_name) = (username.trim(), full_name.trim());
+    if username.is_empty() || full_name.is_empty() {
+        return Err(AppError::validation(
+            "A username and full name are required",
+        ));
+    }
+    let taken: bool = tx.query_row(
+        "SELECT EXISTS(SELECT 1 FROM users WHERE username = ?1)",
+        params![username],
+        |row| row.get(0),
+    )?;
+    if taken {
+        return Err(AppError::validation(format!(
+            "The username '{username}' is already taken"
+        )));
+    }
+    check_password_strength(password)?;
+
+    tx.execute(
+        "INSERT INTO users (username, password_hash, full_name, role) VALUES (?1, ?2, ?3, ?4)",
+        params![username, hash_password(password)?, full_name, role.as_str()],
+    )?;
+    Ok(tx.last_insert_rowid())
+}
+
+/// Change another user's name, role, status or password.
+///
+/// The last active admin can be neither demoted nor deactivated, so user
+/// management can never be locked out. Deactivating a user ends their
+/// sessions.
+pub fn update_user(
+    tx: &Tx,
+    target_user_id: i64,
+    update: &UserUpdate,
+    user_id: i64,
+) -> AppResult<()> {
+    require_permission(tx, user_id, permission::USERS_MANAGE)?;
+    let full_name = update.full_name.trim();
+    if full_name.is_empty() {
+        return Err(AppError::validation("The full name is required"));
+    }
+    let (role, active): (String, bool) = tx
+        .query_row(
+            "SELECT role, is_active FROM users WHERE id = ?1",
+            params![target_user_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("User {target_user_id}")))?;
+    let stays_admin = update.role == Role::Admin && update.is_active;
+    if Role::parse(&role)? == Role::Admin && active && !stays_admin && active_admins(tx)? <= 1 {
+        return Err(AppError::validation(
+            "The last active admin cannot be demoted or deactivated",
+        ));
+    }
+
+    tx.execute(
+        "UPDATE users SET full_name = ?1, role = ?2, is_active = ?3 WHERE id = ?4",
+        params![
+            full_name,
+            update.role.as_str(),
+            update.is_active,
+            target_user_id
+        ],
+    )?;
+    if let Some(password) = &update.password {
+        check_password_strength(password)?;
+        tx.execute(
+            "UPDATE users SET password_hash = ?1 WHERE id = ?2",
+            params![hash_password(password)?, target_user_id],
+        )?;
+    }
+    if !update.is_active {
+        tx.execute(
+            "DELETE FROM sessions WHERE user_id = ?1",
+            params![target_user_id],
+        )?;
+    }
+    Ok(())
+}
+
+/// Sign a user in and open a session for them.
+///
+/// Every attempt is written to `login_audit`. Rejected attempts must still
+/// commit that record, so they come back as the inner error; the outer one
+/// is for failures that should abort the transaction.
+///
+/// As with [`change_password`], unknown, inactive and wrong-password cases
+/// fail identically so the call cannot be used to probe usernames. A locked
+/// account is refused even with the right password until its lock ends.
+pub fn login(tx: &Tx, username: &str, password: &str) -> AppResult<AppResult<Session>> {
+    let username = username.trim();
+    let stored: Option<(i64, String, bool, Option<String>)> = tx
+        .query_row(
+            "SELECT id, password_hash, is_active,
+                 CASE WHEN locked_until > datetime('now') THEN locked_until END
+             FROM users WHERE username = ?1",
+            params![username],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .optional()?;
+    let rejected = || AppError::unauthorized("invalid username or password");
+    let (user_id, hash) = match stored {
+        Some((id, _, true, Some(until))) => {
+            record_login(tx, username, Some(id), false)?;
+            return Ok(Err(AppError::unauthorized(format!(
+                "this account is locked after repeated failed logins; try again after {until} UTC"
+            ))));
+        }
+        Some((id, hash, true, None)) => (id, hash),
+        Some((id, _, false, _)) => {
+            let _ = bcrypt::verify(password, DEFAULT_ADMIN_HASH);
+            record_login(tx, username, Some(id), false)?;
+            return Ok(Err(rejected()));
+        }
+        None => {
+            let _ = bcrypt::verify(password, DEFAULT_ADMIN_HASH);
+            record_login(tx, username, None, false)?;
+            return Ok(Err(rejected()));
+        }
+    };
+    if !bcrypt::verify(password, &hash).unwrap_or(false) {
+        record_login(tx, username, Some(user_id), false)?;
+        lock_after_failures(tx, user_id)?;
+        return Ok(Err(rejected()));
+    }
+    record_login(tx, username, Some(user_id), true)?;
+
+    tx.execute(
+        "DELETE FROM sessions WHERE expires_at <= datetime('now')",
+        [],
+    )?;
+    let token: String = tx.query_row("SELECT lower(hex(randomblob(32)))", [], |row| row.get(0))?;
+    tx.execute(
+        "INSERT INTO sessions (token_hash, user_id, expires_at)
+         VALUES (?1, ?2, datetime('now', ?3))",
+        params![
+            token_hash(&token),
+            user_id,
+            format!("+{SESSION_HOURS} hours")
+        ],
+    )?;
+    let session = current_session(tx, &token)?
+        .ok_or_else(|| AppError::unauthorized("the session could not be opened"))?;
+    Ok(Ok(session))
+}
+
+/// Lift a lockout early. The lock is ended now rather than cleared, so the
+/// failures that caused it stop counting towards the next one.
+pub fn unlock_user(tx: &Tx, user_id: i64, target_user_id: i64) -> AppResult<()> {
+    require_permission(tx, user_id, permission::USERS_UNLOCK)?;
+    let updated = tx.execute(
+        "UPDATE users SET locked_until = datetime('now') WHERE id = ?1",
+        params![target_user_id],
+    )?;
+    if updated == 0 {
+        return Err(AppError::not_found(format!("User {target_user_id}")));
+    }
+    Ok(())
+}
+
+/// End a session. Unknown tokens are ignored, so logging out twice is fine.
+pub fn logout(tx: &Tx, token: &str) -> AppResult<()> {
+    tx.execute(
+        "DELETE FROM sessions WHERE token_hash = ?1",
+        params![token_hash(token)],
+    )?;
+    Ok(())
+}
+
+/// What `sessions` stores in place of a token, so reading the table does
+/// not hand out live sessions.
+fn token_hash(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+fn session_from_row(token: &str, row: &Row) -> AppResult<Session> {
+    let role: String = row.get(5)?;
+    Ok(Session {
+        token: token.to_string(),
+        expires_at: row.get(1)?,
+        user: SessionUser {
+            id: row.get(2)?,
+            username: row.get(3)?,
+            full_name: row.get(4)?,
+            role: Role::parse(&role)?,
+            is_active: row.get(6)?,
+            created_at: row.get(7)?,
+            updated_at: row.get(8)?,
+        },
+    })
+}
+
+/// The session a token belongs to, or `None` once it has expired or its
+/// user has been deactivated.
+pub fn current_session(conn: &Connection, token: &str) -> AppResult<Option<Session>> {
+    let mut stmt = conn.prepare(
+        "SELECT s.token_hash, s.expires_at, u.id, u.username, u.full_name, u.role,
+             u.is_active, u.created_at, u.updated_at
+         FROM sessions s JOIN users u ON u.id = s.user_id
+         WHERE s.token_hash = ?1 AND s.expires_at > datetime('now') AND u.is_active = 1",
+    )?;
+    let mut rows = stmt.query(params![token_hash(token)])?;
+    rows.next()?
+        .map(|row| session_from_row(token, row))
+        .transpose()
+}
+
+/// The user a command acts as. Commands take the session token rather than
+/// a user id, so the frontend cannot act as someone else; a missing,
+/// expired or deactivated session is refused.
+pub fn session_user(conn: &Connection, token: &str) -> AppResult<i64> {
+    current_session(conn, token)?
+        .map(|session| session.user.id)
+        .ok_or_else(|| AppError::unauthorized("the session has expired; sign in again"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{insert_cashier, migrated_db};
+
+    const ADMIN: i64 = 1;
+
+    #[test]
+    fn password_change_needs_the_current_password() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        conn.execute(
+            "UPDATE users SET updated_at = '2020-01-01 00:00:00' WHERE id = ?1",
+            [ADMIN],
+        )
+        .unwrap();
+
+        let wrong = db
+            .with_tx(|tx| change_password(tx, ADMIN, "not-it", "n3w-password"))
+            .unwrap_err();
+        let unknown = db
+            .with_tx(|tx| change_password(tx, 99, "not-it", "n3w-password"))
+            .unwrap_err();
+        assert!(matches!(wrong, AppError::Unauthorized(_)), "{wrong}");
+        assert_eq!(wrong.to_string(), unknown.to_string());
+
+        let default = db
+            .with_tx(|tx| change_password(tx, ADMIN, "admin123", "admin123"))
+            .unwrap_err();
+        assert!(
+            default.to_string().contains("default password"),
+            "{default}"
+        );
+
+        db.with_tx(|tx| change_password(tx, ADMIN, "admin123", "n3w-password"))
+            .unwrap();
+        let (hash, updated_at): (String, String) = conn
+            .query_row(
+                "SELECT password_hash, updated_at FROM users WHERE id = ?1",
+                [ADMIN],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert!(bcrypt::verify("n3w-password", &hash).unwrap());
+        assert_ne!(updated_at, "2020-01-01 00:00:00");
+
+        let reused = db
+            .with_tx(|tx| change_password(tx, ADMIN, "n3w-password", "n3w-password"))
+            .unwrap_err();
+        assert!(reused.to_string().contains("different"), "{reused}");
+    }
+
+    #[test]
+    fn login_opens_a_session_that_ends_on_logout_or_expiry() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+
+        let wrong = db
+            .with_tx(|tx| login(tx, "admin", "not-it"))
+            .unwrap()
+            .unwrap_err();
+        let unknown = db
+            .with_tx(|tx| login(tx, "ghost", "admin123"))
+            .unwrap()
+            .unwrap_err();
+        assert!(matches!(wrong, AppError::Unauthorized(_)), "{wrong}");
+        assert_eq!(wrong.to_string(), unknown.to_string());
+
+        let session = db
+            .with_tx(|tx| login(tx, "admin", "admin123"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(session.token.len(), 64);
+        assert_eq!(session.user.role, Role::Admin);
+        let restored = current_session(&conn, &session.token).unwrap().unwrap();
+        assert_eq!(restored.user.id, ADMIN);
+        assert_eq!(restored.token, session.token);
+        let stored: String = conn
+            .query_row("SELECT token_hash FROM sessions", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(stored, token_hash(&session.token));
+        assert_ne!(stored, session.token);
+        assert_eq!(session_user(&conn, &session.token).unwrap(), ADMIN);
+        let bogus = session_user(&conn, "not-a-token").unwrap_err();
+        assert!(matches!(bogus, AppError::Unauthorized(_)), "{bogus}");
+
+        db.with_tx(|tx| logout(tx, &session.token)).unwrap();
+        assert!(current_session(&conn, &session.token).unwrap().is_none());
+
+        let session = db
+            .with_tx(|tx| login(tx, "admin", "admin123"))
+            .unwrap()
+            .unwrap();
+        conn.execute(
+            "UPDATE sessions SET expires_at = datetime('now', '-1 minute')",
+            [],
+        )
+        .unwrap();
+        assert!(current_session(&conn, &session.token).unwrap().is_none());
+        assert!(matches!(
+            session_user(&conn, &session.token),
+            Err(AppError::Unauthorized(_))
+        ));
+    }
+
+    #[test]
+    fn permissions_follow_the_role_matrix() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        conn.execute(
+            "INSERT INTO users (username, password_hash, full_name, role)
+             VALUES ('till', 'x', 'Till', 'cashier')",
+            [],
+        )
+        .unwrap();
+        let cashier = conn.last_insert_rowid();
+
+        for key in [
+            permission::AUDIT_VIEW,
+            permission::BACKUP_CREATE,
+            permission::BACKUP_RESTORE,
+            permission::DATABASE_ENCRYPT,
+            permission::DATABASE_MAINTAIN,
+            permission::DASHBOARD_FINANCIALS,
+            permission::DASHBOARD_STOCK,
+            permission::EXPENSES_MANAGE,
+            permission::INVENTORY_ADJUST,
+            permission::INVENTORY_DISPOSE,
+            permission::INVENTORY_REORDER,
+            permission::INVENTORY_TRANSFER,
+            permission::LOYALTY_REDEEM,
+            permission::MEDICINES_IMPORT,
+            permission::MEDICINES_MANAGE,
+            permission::PRESCRIPTIONS_LINK,
+            permission::PURCHASES_APPROVE,
+            permission::PURCHASES_ORDER,
+            permission::PURCHASES_RECEIVE,
+            permission::REPORTS_EXPORT,
+            permission::SALES_AMEND,
+            permission::SALES_EINVOICE,
+            permission::SALES_EWAYBILL,
+            permission::SALES_REFUND,
+            permission::SCHEDULER_MANAGE,
+            permission::SHIFTS_MANAGE,
+            permission::SYNC_FLUSH,
+            permission::USERS_PERMISSIONS,
+            permission::USERS_UNLOCK,
+        ] {
+            assert!(check_permission(&conn, ADMIN, key).unwrap(), "{key}");
+        }
+        assert!(check_permission(&conn, cashier, permission::SALES_REFUND).unwrap());
+        for key in [
+            permission::BACKUP_CREATE,
+            permission::DATABASE_MAINTAIN,
+            permission::INVENTORY_DISPOSE,
+            permission::INVENTORY_TRANSFER,
+            permission::LOYALTY_REDEEM,
+            permission::MEDICINES_MANAGE,
+            permission::PRESCRIPTIONS_LINK,
+            permission::REPORTS_EXPORT,
+            permission::SYNC_FLUSH,
+        ] {
+            assert!(!check_permission(&conn, cashier, key).unwrap(), "{key}");
+        }
+
+        let err = db
+            .with_tx(|tx| set_role_permissions(tx, cashier, Role::Cashier, &[]))
+            .unwrap_err();
+        assert!(matches!(err, AppError::Unauthorized(_)), "{err}");
+        let err = db
+            .with_tx(|tx| set_role_permissions(tx, ADMIN, Role::Admin, &[]))
+            .unwrap_err();
+        assert!(err.to_string().contains("must keep"), "{err}");
+
+        db.with_tx(|tx| set_role_permissions(tx, ADMIN, Role::Cashier, &[]))
+            .unwrap();
+        let err = require_permission(&conn, cashier, permission::SALES_REFUND).unwrap_err();
+        assert!(err.to_string().contains("sales.refund"), "{err}");
+    }
+
+    #[test]
+    fn repeated_failures_lock_the_account_until_unlocked() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        conn.execute("UPDATE pharmacy_settings SET lockout_max_attempts = 3", [])
+            .unwrap();
+        let attempt = |password: &str| db.with_tx(|tx| login(tx, "admin", password)).unwrap();
+
+        for _ in 0..3 {
+            attempt("not-it").unwrap_err();
+        }
+        let locked = attempt("admin123").unwrap_err();
+        assert!(locked.to_string().contains("locked"), "{locked}");
+        let failures: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM login_audit WHERE user_id = ?1 AND success = 0",
+                [ADMIN],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(failures, 4);
+
+        db.with_tx(|tx| unlock_user(tx, ADMIN, ADMIN)).unwrap();
+        attempt("not-it").unwrap_err();
+        attempt("admin123").unwrap();
+    }
+
+    #[test]
+    fn users_are_managed_with_a_permission_and_the_last_admin_stays() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let cashier = insert_cashier(&conn);
+
+        let err = db
+            .with_tx(|tx| {
+                create_user(
+                    tx,
+                    "ravi",
+                    "str0ng-pass",
+                    "Ravi",
+                    Role::Pharmacist,
+                    Some(cashier),
+                )
+            })
+            .unwrap_err();
+        assert!(matches!(err, AppError::Unauthorized(_)), "{err}");
+        let err = db
+            .with_tx(|tx| create_user(tx, "ravi", "str0ng-pass", "Ravi", Role::Admin, None))
+            .unwrap_err();
+        assert!(matches!(err, AppError::Unauthorized(_)), "{err}");
+        let ravi = db
+            .with_tx(|tx| {
+                create_user(
+                    tx,
+                    " ravi ",
+                    "str0ng-pass",
+                    "Ravi",
+                    Role::Pharmacist,
+                    Some(ADMIN),
+                )
+            })
+            .unwrap();
+        let err = db
+            .with_tx(|tx| {
+                create_user(
+                    tx,
+                    "ravi",
+                    "str0ng-pass",
+                    "Ravi",
+                    Role::Cashier,
+                    Some(ADMIN),
+                )
+            })
+            .unwrap_err();
+        assert!(err.to_string().contains("already taken"), "{err}");
+
+        let demote = UserUpdate {
+            full_name: "Administrator".into(),
+            role: Role::Pharmacist,
+            is_active: true,
+            password: None,
+        };
+        let err = db
+            .with_tx(|tx| update_user(tx, ADMIN, &demote, ADMIN))
+            .unwrap_err();
+        assert!(err.to_string().contains("last active admin"), "{err}");
+
+        let session = db
+            .with_tx(|tx| login(tx, "ravi", "str0ng-pass"))
+            .unwrap()
+            .unwrap();
+        let deactivate = UserUpdate {
+            full_name: "Ravi K".into(),
+            role: Role::Pharmacist,
+            is_active: false,
+            password: Some("n3w-password".into()),
+        };
+        db.with_tx(|tx| update_user(tx, ravi, &deactivate, ADMIN))
+            .unwrap();
+        let sessions: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sessions WHERE user_id = ?1",
+                [ravi],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(sessions, 0);
+        assert!(current_session(&conn, &session.token).unwrap().is_none());
+        let (name, hash): (String, String) = conn
+            .query_row(
+                "SELECT full_name, password_hash FROM users WHERE id = ?1",
+                [ravi],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(name, "Ravi K");
+        assert!(bcrypt::verify("n3w-password", &hash).unwrap());
+    }
+}
@@ -0,0 +1,524 @@
+//! Single-file snapshots of the live database, and restoring from them.
+//!
+//! Besides snapshots the user asks for, one is taken each day at the
+//! configured time into a backups folder, which keeps only the newest few.
+
+use std::ffi::c_int;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use rusqlite::backup::{Backup, StepResult};
+use rusqlite::{Connection, OpenFlags};
+use serde::Serialize;
+
+use crate::auth::{self, permission};
+use crate::error::{AppError, AppResult};
+use crate::migrations;
+
+/// Scheduled snapshots are named `pharmacare-YYYYMMDD-HHMMSS.db`, local
+/// time, so names sort by age.
+const SNAPSHOT_PREFIX: &str = "pharmacare-";
+const SNAPSHOT_SUFFIX: &str = ".db";
+/// The online backup copies this many pages at a time and pauses between
+/// steps, so a large copy does not hold up writes at the till.
+const STEP_PAGES: c_int = 256;
+const STEP_PAUSE: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableCount {
+    pub table: String,
+    pub rows: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInfo {
+    pub path: String,
+    pub size_bytes: u64,
+    /// Row count of every table in the snapshot, by name.
+    pub tables: Vec<TableCount>,
+}
+
+/// A snapshot in the backups folder.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupFile {
+    pub path: String,
+    pub file_name: String,
+    pub size_bytes: u64,
+    /// Local time it was taken, `YYYY-MM-DD HH:MM:SS`.
+    pub taken_at: String,
+}
+
+struct BackupSchedule {
+    enabled: bool,
+    /// `HH:MM`, local time.
+    time: String,
+    keep: usize,
+    dir: PathBuf,
+}
+
+fn backup_schedule(conn: &Connection, live: &Path) -> AppResult<BackupSchedule> {
+    let (enabled, time, keep, dir): (bool, String, i64, Option<String>) = conn.query_row(
+        "SELECT auto_backup_enabled, auto_backup_time, auto_backup_keep, auto_backup_dir
+         FROM pharmacy_settings WHERE id = 1",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    )?;
+    let dir = match dir.as_deref().map(str::trim) {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => live.with_file_name("backups"),
+    };
+    Ok(BackupSchedule {
+        enabled,
+        time,
+        keep: usize::try_from(keep)
+            .map_err(|_| AppError::validation("Backups to keep must be positive"))?,
+        dir,
+    })
+}
+
+/// When a snapshot was taken, from its file name.
+fn taken_at(file_name: &str) -> Option<String> {
+    let stamp = file_name
+        .strip_prefix(SNAPSHOT_PREFIX)?
+        .strip_suffix(SNAPSHOT_SUFFIX)?
+        .as_bytes();
+    let valid = stamp.len() == 15
+        && stamp[8] == b'-'
+        && stamp
+            .iter()
+            .enumerate()
+            .all(|(i, b)| i == 8 || b.is_ascii_digit());
+    if !valid {
+        return None;
+    }
+    let part = |from: usize, to: usize| std::str::from_utf8(&stamp[from..to]).unwrap_or_default();
+    Some(format!(
+        "{}-{}-{} {}:{}:{}",
+        part(0, 4),
+        part(4, 6),
+        part(6, 8),
+        part(9, 11),
+        part(11, 13),
+        part(13, 15)
+    ))
+}
+
+/// Snapshots in `dir`, newest first. Other files are left out.
+fn snapshots_in(dir: &Path) -> AppResult<Vec<BackupFile>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        let Some(taken_at) = taken_at(&file_name) else {
+            continue;
+        };
+        let meta = entry.metadata()?;
+        if meta.is_file() {
+            files.push(BackupFile {
+                path: entry.path().to_string_lossy().into_owned(),
+                file_name,
+                size_bytes: meta.len(),
+                taken_at,
+            });
+        }
+    }
+    files.sort_by(|a, b| b.file_name.cmp(&a.file_name));
+    Ok(files)
+}
+
+/// Snapshots in the backups folder, newest first.
+pub fn list_backups(conn: &Connection, live: &Path) -> AppResult<Vec<BackupFile>> {
+    snapshots_in(&backup_schedule(conn, live)?.dir)
+}
+
+/// Copy the database behind `conn` to a new file at `dest` with SQLite's
+/// online backup API.
+fn online_backup(conn: &Connection, dest: &Path) -> AppResult<()> {
+    let mut out = Connection::open(dest)?;
+    Backup::new(conn, &mut out)?.run_to_completion(STEP_PAGES, STEP_PAUSE, None)?;
+    Ok(())
+}
+
+fn snapshot(conn: &Connection, schedule: &BackupSchedule) -> AppResult<BackupInfo> {
+    fs::create_dir_all(&schedule.dir)?;
+    let stamp: String = conn.query_row(
+        "SELECT strftime('%Y%m%d-%H%M%S', 'now', 'localtime')",
+        [],
+        |row| row.get(0),
+    )?;
+    let dest = schedule
+        .dir
+        .join(format!("{SNAPSHOT_PREFIX}{stamp}{SNAPSHOT_SUFFIX}"));
+    if dest.exists() {
+        return Err(AppError::validation(
+            "A backup was taken a moment ago; try again shortly",
+        ));
+    }
+    // Copy under a name the folder listing skips, so an interrupted copy
+    // never passes for a snapshot.
+    let partial = dest.with_extension("db.partial");
+    if let Err(err) = online_backup(conn, &partial) {
+        let _ = fs::remove_file(&partial);
+        return Err(err);
+    }
+    fs::rename(&partial, &dest)?;
+
+    for old in snapshots_in(&schedule.dir)?.iter().skip(schedule.keep) {
+        fs::remove_file(&old.path)?;
+    }
+    let snapshot = Connection::open_with_flags(&dest, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    Ok(BackupInfo {
+        size_bytes: fs::metadata(&dest)?.len(),
+        tables: table_counts(&snapshot)?,
+        path: dest.to_string_lossy().into_owned(),
+    })
+}
+
+/// Take a snapshot into the backups folder now, then delete the oldest
+/// snapshots beyond the number to keep.
+pub fn backup_now(conn: &Connection, live: &Path, user_id: i64) -> AppResult<BackupInfo> {
+    auth::require_permission(conn, user_id, permission::BACKUP_CREATE)?;
+    snapshot(conn, &backup_schedule(conn, live)?)
+}
+
+fn backup_if_due_at(conn: &Connection, live: &Path, now: &str) -> AppResult<Option<BackupInfo>> {
+    let schedule = backup_schedule(conn, live)?;
+    if !schedule.enabled {
+        return Ok(None);
+    }
+    // The most recent scheduled time: today's once it has passed, else
+    // yesterday's, so a day missed while the app was closed is caught up.
+    let slot: String = conn.query_row(
+        "SELECT CASE WHEN time(?1) >= ?2 THEN date(?1) ELSE date(?1, '-1 day') END || ' ' || ?2",
+        rusqlite::params![now, schedule.time],
+        |row| row.get(0),
+    )?;
+    let latest = snapshots_in(&schedule.dir)?.into_iter().next();
+    if latest.is_some_and(|file| file.taken_at >= slot) {
+        return Ok(None);
+    }
+    snapshot(conn, &schedule).map(Some)
+}
+
+/// Take the scheduled snapshot if one is due. Called periodically while
+/// the app runs.
+pub fn backup_if_due(conn: &Connection, live: &Path) -> AppResult<Option<BackupInfo>> {
+    let now: String =
+        conn.query_row("SELECT datetime('now', 'localtime')", [], |row| row.get(0))?;
+    backup_if_due_at(conn, live, &now)
+}
+
+/// Resolve `path` to an absolute path even if the file does not exist yet.
+fn absolute(path: &Path) -> AppResult<PathBuf> {
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.canonicalize()?,
+        _ => std::env::current_dir()?,
+    };
+    let name = path
+        .file_name()
+        .ok_or_else(|| AppError::validation("The destination must be a file"))?;
+    Ok(dir.join(name))
+}
+
+/// Row counts of every user table in the database `conn` is open on.
+fn table_counts(conn: &Connection) -> AppResult<Vec<TableCount>> {
+    let names = {
+        let mut stmt = conn.prepare(
+            "SELECT name FROM sqlite_master
+             WHERE type = 'table' AND name NOT LIKE 'sqlite_%'
+             ORDER BY name",
+        )?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        rows.collect::<Result<Vec<_>, _>>()?
+    };
+
+    names
+        .into_iter()
+        .map(|table| {
+            let sql = format!("SELECT COUNT(*) FROM \"{}\"", table.replace('"', "\"\""));
+            let rows = conn.query_row(&sql, [], |row| row.get(0))?;
+            Ok(TableCount { table, rows })
+        })
+        .collect()
+}
+
+/// Write a consistent copy of the database behind `conn` to `dest`.
+///
+/// `VACUUM INTO` reads through a single transaction, so the snapshot
+/// includes committed WAL content and is safe to take while other
+/// connections are writing. `live` is the database file itself, which may
+/// never be the destination.
+pub fn backup_database(
+    conn: &Connection,
+    live: &Path,
+    dest: &Path,
+    user_id: i64,
+) -> AppResult<BackupInfo> {
+    auth::require_permission(conn, user_id, permission::BACKUP_CREATE)?;
+    copy_database(conn, live, dest)
+}
+
+/// Resolve `dest` for a new file exported from the database at `live`.
+///
+/// The destination may be neither the live database nor its `-wal` and
+/// `-shm` files, and nothing is overwritten: it must not exist yet.
+pub(crate) fn new_destination(live: &Path, dest: &Path) -> AppResult<PathBuf> {
+    let dest = absolute(dest)?;
+    let live = live.canonicalize()?;
+    let live_name = live.to_string_lossy();
+    let dest_name = dest.to_string_lossy();
+    if dest == live
+        || dest_name == format!("{live_name}-wal")
+        || dest_name == format!("{live_name}-shm")
+    {
+        return Err(AppError::validation(
+            "The live database cannot be overwritten; choose another file",
+        ));
+    }
+    if dest.exists() {
+        return Err(AppError::validation(format!(
+            "{} already exists; choose a new file name",
+            dest.display()
+        )));
+    }
+    Ok(dest)
+}
+
+/// [`backup_database`] without the permission check, for callers that
+/// have made their own.
+pub(crate) fn copy_database(conn: &Connection, live: &Path, dest: &Path) -> AppResult<BackupInfo> {
+    let dest = new_destination(live, dest)?;
+    let dest_name = dest.to_string_lossy();
+
+    conn.execute("VACUUM INTO ?1", [dest_name.as_ref()])?;
+
+    let snapshot = Connection::open(&dest)?;
+    Ok(BackupInfo {
+        size_bytes: fs::metadata(&dest)?.len(),
+        tables: table_counts(&snapshot)?,
+        path: dest_name.into_owned(),
+    })
+}
+
+/// Check that `src` is an intact database this build knows how to migrate,
+/// and return its schema version.
+pub(crate) fn validate_backup(src: &Path) -> AppResult<i64> {
+    let invalid = |_| AppError::validation("The file is not a valid SQLite database");
+    let conn = Connection::open_with_flags(src, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let check: String = conn
+        .query_row("PRAGMA quick_check", [], |row| row.get(0))
+        .map_err(invalid)?;
+    if check != "ok" {
+        return Err(AppError::validation(format!(
+            "The backup is damaged: {check}"
+        )));
+    }
+
+    let version = migrations::applied_version(&conn)?.ok_or_else(|| {
+        AppError::validation("The file is not a PharmaCare backup: it has no migration history")
+    })?;
+    let supported = migrations::latest_version();
+    if version > supported {
+        return Err(AppError::validation(format!(
+            "The backup is from a newer version of PharmaCare (schema {version}); \
+             this app supports up to schema {supported}. Update the app before restoring."
+        )));
+    }
+    Ok(version)
+}
+
+/// Copy the database at `src` over the live one behind `conn`.
+///
+/// The copy runs through SQLite's backup API in a single step, so other
+/// connections see either the old database or the restored one.
+pub(crate) fn copy_into_live(conn: &mut Connection, src: &Path) -> AppResult<()> {
+    let source = Connection::open_with_flags(src, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let backup = Backup::new(&source, conn)?;
+    match backup.step(-1)? {
+        StepResult::Done => Ok(()),
+        _ => Err(AppError::validation(
+            "The database is busy; close other windows and try the restore again",
+        )),
+    }
+}
+
+/// Replace the contents of the live database behind `conn` with `src`.
+///
+/// The SQL plugin's pool must be closed while this runs; reopening it
+/// afterwards migrates an older backup up to the current schema.
+pub fn restore_database(
+    conn: &mut Connection,
+    live: &Path,
+    src: &Path,
+    user_id: i64,
+) -> AppResult<()> {
+    auth::require_permission(conn, user_id, permission::BACKUP_RESTORE)?;
+    let src = src.canonicalize()?;
+    if src == live.canonicalize()? {
+        return Err(AppError::validation(
+            "The live database cannot be restored onto itself",
+        ));
+    }
+    validate_backup(&src)?;
+    copy_into_live(conn, &src)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{
+        insert_batch, insert_cashier, insert_medicine, mark_migrated, migrated_db,
+    };
+
+    const ADMIN: i64 = 1;
+
+    #[test]
+    fn snapshot_has_the_live_rows() {
+        let (dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        insert_batch(&conn, para, "+90 days", 1_000, 5);
+        let dest = dir.path().join("backup.db");
+
+        let info = backup_database(&conn, db.path(), &dest, ADMIN).unwrap();
+
+        assert!(info.size_bytes > 0);
+        let rows = |name: &str| info.tables.iter().find(|t| t.table == name).map(|t| t.rows);
+        assert_eq!(rows("medicines"), Some(1));
+        assert_eq!(rows("batches"), Some(1));
+    }
+
+    #[test]
+    fn restore_replaces_the_live_data() {
+        let (dir, db) = migrated_db();
+        let mut conn = db.connect().unwrap();
+        mark_migrated(&conn, 1);
+        insert_medicine(&conn, "Paracetamol", 12.0);
+        let dest = dir.path().join("backup.db");
+        backup_database(&conn, db.path(), &dest, ADMIN).unwrap();
+        insert_medicine(&conn, "Cetirizine", 12.0);
+
+        restore_database(&mut conn, db.path(), &dest, ADMIN).unwrap();
+
+        let medicines: i64 = conn
+            .query_row("SELECT COUNT(*) FROM medicines", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(medicines, 1);
+    }
+
+    #[test]
+    fn restore_rejects_a_newer_schema() {
+        let (dir, db) = migrated_db();
+        let mut conn = db.connect().unwrap();
+        let newer = dir.path().join("newer.db");
+        mark_migrated(&Connection::open(&newer).unwrap(), 9_999);
+
+        let err = restore_database(&mut conn, db.path(), &newer, ADMIN).unwrap_err();
+        assert!(err.to_string().contains("schema 9999"), "{err}");
+    }
+
+    #[test]
+    fn restore_rejects_a_file_that_is_not_sqlite() {
+        let (dir, db) = migrated_db();
+        let mut conn = db.connect().unwrap();
+        let junk = dir.path().join("junk.db");
+        fs::write(&junk, b"definitely not a database, just some text").unwrap();
+
+        let err = restore_database(&mut conn, db.path(), &junk, ADMIN).unwrap_err();
+        assert!(err.to_string().contains("not a valid SQLite"), "{err}");
+    }
+
+    #[test]
+    fn backup_now_keeps_only_the_newest_snapshots() {
+        let (dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        insert_medicine(&conn, "Paracetamol", 12.0);
+        let folder = dir.path().join("backups");
+        fs::create_dir_all(&folder).unwrap();
+        for name in [
+            "pharmacare-20240101-210000.db",
+            "pharmacare-20240102-210000.db",
+            "notes.txt",
+        ] {
+            fs::write(folder.join(name), b"old").unwrap();
+        }
+        conn.execute(
+            "UPDATE pharmacy_settings SET auto_backup_keep = 2 WHERE id = 1",
+            [],
+        )
+        .unwrap();
+
+        let info = backup_now(&conn, db.path(), ADMIN).unwrap();
+
+        let rows = |name: &str| info.tables.iter().find(|t| t.table == name).map(|t| t.rows);
+        assert_eq!(rows("medicines"), Some(1));
+        let kept = list_backups(&conn, db.path()).unwrap();
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[0].path, info.path);
+        assert_eq!(kept[1].taken_at, "2024-01-02 21:00:00");
+        assert!(folder.join("notes.txt").exists());
+    }
+
+    #[test]
+    fn scheduled_backup_runs_once_per_slot_and_catches_up() {
+        let (dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let folder = dir.path().join("backups");
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(folder.join("pharmacare-20240309-213000.db"), b"old").unwrap();
+        let due = |now: &str| backup_if_due_at(&conn, db.path(), now).unwrap().is_some();
+
+        // Yesterday's 21:00 snapshot exists; today's is not due until 21:00.
+        assert!(!due("2024-03-10 20:59:00"));
+        fs::remove_file(folder.join("pharmacare-20240309-213000.db")).unwrap();
+        fs::write(folder.join("pharmacare-20240308-213000.db"), b"old").unwrap();
+        // The 9th was missed, so the next check catches it up.
+        assert!(due("2024-03-10 08:00:00"));
+        // The real snapshot carries today's clock time, which is later than
+        // any slot in 2024.
+        assert!(!due("2024-03-10 21:05:00"));
+
+        conn.execute(
+            "UPDATE pharmacy_settings SET auto_backup_enabled = 0 WHERE id = 1",
+            [],
+        )
+        .unwrap();
+        for file in list_backups(&conn, db.path()).unwrap() {
+            fs::remove_file(file.path).unwrap();
+        }
+        assert!(!due("2024-03-10 21:05:00"));
+    }
+
+    #[test]
+    fn live_database_cannot_be_the_destination() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+
+        let err = backup_database(&conn, db.path(), db.path(), ADMIN).unwrap_err();
+        assert!(err.to_string().contains("live database"), "{err}");
+    }
+
+    #[test]
+    fn cashier_cannot_take_a_backup() {
+        let (dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let cashier = insert_cashier(&conn);
+        let dest = dir.path().join("backup.db");
+
+        let err = backup_database(&conn, db.path(), &dest, cashier).unwrap_err();
+        assert!(matches!(err, AppError::Unauthorized(_)), "{err}");
+        assert!(!dest.exists());
+        let err = backup_now(&conn, db.path(), cashier).unwrap_err();
+        assert!(matches!(err, AppError::Unauthorized(_)), "{err}");
+        assert!(list_backups(&conn, db.path()).unwrap().is_empty());
+    }
+}
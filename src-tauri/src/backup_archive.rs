@@ -0,0 +1,367 @@
+//! Encrypted, compressed backup archives to keep off the machine.
+//!
+//! An archive is a gzip stream of named entries, for now only the database
+//! snapshot, sealed with AES-256-GCM under a key derived from a passphrase
+//! with PBKDF2-HMAC-SHA256. The header is authenticated along with the
+//! body, so nothing is decrypted from an archive that has been altered.
+//!
+//! Layout: [`MAGIC`], a format version byte, the PBKDF2 round count as a
+//! big-endian `u32`, the salt, the nonce, then the ciphertext with its tag.
+
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rusqlite::Connection;
+use serde::Serialize;
+use sha2::Sha256;
+
+use crate::auth::{self, permission};
+use crate::backup;
+use crate::error::{AppError, AppResult};
+use crate::migrations;
+
+const MAGIC: &[u8; 8] = b"PHARMBAK";
+const FORMAT_VERSION: u8 = 1;
+/// Kept low in tests, where the hashing runs unoptimised.
+const PBKDF2_ROUNDS: u32 = if cfg!(test) { 1_000 } else { 600_000 };
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+const HEADER_LEN: usize = MAGIC.len() + 1 + 4 + SALT_LEN + NONCE_LEN;
+const MIN_PASSPHRASE_CHARS: usize = 8;
+/// Entry name of the database snapshot.
+const DATABASE_ENTRY: &str = "pharmacare.db";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveInfo {
+    pub path: String,
+    /// Size of the archive file.
+    pub size_bytes: u64,
+    /// Size of the database inside it, before compression.
+    pub database_bytes: u64,
+}
+
+fn cipher(passphrase: &str, salt: &[u8], rounds: u32) -> Aes256Gcm {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, rounds, &mut key);
+    Aes256Gcm::new(&key.into())
+}
+
+fn random_bytes<const N: usize>() -> AppResult<[u8; N]> {
+    let mut bytes = [0u8; N];
+    getrandom::getrandom(&mut bytes).map_err(|err| std::io::Error::other(err.to_string()))?;
+    Ok(bytes)
+}
+
+fn pack(entries: &[(&str, &[u8])]) -> AppResult<Vec<u8>> {
+    let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+    for (name, data) in entries {
+        let name_len = u16::try_from(name.len())
+            .map_err(|_| AppError::validation("Archive entry name is too long"))?;
+        gz.write_all(&name_len.to_be_bytes())?;
+        gz.write_all(name.as_bytes())?;
+        gz.write_all(&(data.len() as u64).to_be_bytes())?;
+        gz.write_all(data)?;
+    }
+    Ok(gz.finish()?)
+}
+
+fn unpack(compressed: &[u8]) -> AppResult<Vec<(String, Vec<u8>)>> {
+    let damaged = || AppError::validation("The backup archive is damaged");
+    let mut stream = Vec::new();
+    GzDecoder::new(compressed)
+        .read_to_end(&mut stream)
+        .map_err(|_| damaged())?;
+
+    let mut entries = Vec::new();
+    let mut rest = stream.as_slice();
+    while !rest.is_empty() {
+        let (len, tail) = rest.split_first_chunk::<2>().ok_or_else(damaged)?;
+        let name_len = usize::from(u16::from_be_bytes(*len));
+        if tail.len() < name_len {
+            return Err(damaged());
+        }
+        let (name, tail) = tail.split_at(name_len);
+        let (len, tail) = tail.split_first_chunk::<8>().ok_or_else(damaged)?;
+        let data_len = usize::try_from(u64::from_be_bytes(*len)).map_err(|_| damaged())?;
+        if tail.len() < data_len {
+            return Err(damaged());
+        }
+        let (data, tail) = tail.split_at(data_len);
+        let name = String::from_utf8(name.to_vec()).map_err(|_| damaged())?;
+        entries.push((name, data.to_vec()));
+        rest = tail;
+    }
+    Ok(entries)
+}
+
+fn seal(plaintext: Vec<u8>, passphrase: &str) -> AppResult<Vec<u8>> {
+    let salt: [u8; SALT_LEN] = random_bytes()?;
+    let nonce: [u8; NONCE_LEN] = random_bytes()?;
+
+    let mut out = Vec::with_capacity(HEADER_LEN + plaintext.len() + TAG_LEN);
+    out.extend_from_slice(MAGIC);
+    out.push(FORMAT_VERSION);
+    out.extend_from_slice(&PBKDF2_ROUNDS.to_be_bytes());
+    out.extend_from_slice(&salt);
+    out.extend_from_slice(&nonce);
+    let body = cipher(passphrase, &salt, PBKDF2_ROUNDS)
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: &plaintext,
+                aad: &out,
+            },
+        )
+        .map_err(|_| AppError::validation("The backup is too large to encrypt"))?;
+    out.extend_from_slice(&body);
+    Ok(out)
+}
+
+fn open_sealed(archive: &[u8], passphrase: &str) -> AppResult<Vec<u8>> {
+    if archive.len() < HEADER_LEN + TAG_LEN || !archive.starts_with(MAGIC) {
+        return Err(AppError::validation(
+            "The file is not a PharmaCare backup archive",
+        ));
+    }
+    let version = archive[MAGIC.len()];
+    if version != FORMAT_VERSION {
+        return Err(AppError::validation(format!(
+            "The archive uses format {version}, which this version of PharmaCare cannot read"
+        )));
+    }
+    let (header, body) = archive.split_at(HEADER_LEN);
+    let mut at = MAGIC.len() + 1;
+    let rounds = u32::from_be_bytes(header[at..at + 4].try_into().expect("four bytes"));
+    at += 4;
+    let salt = &header[at..at + SALT_LEN];
+    at += SALT_LEN;
+    let nonce = &header[at..at + NONCE_LEN];
+    // The header is only authenticated once the key is derived, so a
+    // forged round count must not set off hours of hashing.
+    if rounds == 0 || rounds > PBKDF2_ROUNDS {
+        return Err(AppError::validation("The backup archive is damaged"));
+    }
+
+    cipher(passphrase, salt, rounds)
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: body,
+                aad: header,
+            },
+        )
+        .map_err(|_| {
+            AppError::validation("Wrong passphrase, or the backup archive has been altered")
+        })
+}
+
+/// An encrypted archive holding the database file `database`.
+pub(crate) fn archive_database(database: &[u8], passphrase: &str) -> AppResult<Vec<u8>> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_CHARS {
+        return Err(AppError::validation(format!(
+            "The passphrase must be at least {MIN_PASSPHRASE_CHARS} characters"
+        )));
+    }
+    seal(pack(&[(DATABASE_ENTRY, database)])?, passphrase)
+}
+
+/// Write an encrypted archive of the database behind `conn` to `dest`.
+///
+/// The snapshot is taken as for [`backup::backup_database`] and removed
+/// once it is in the archive. Like a snapshot, the archive never replaces
+/// the live database or an existing file.
+pub fn export_backup(
+    conn: &Connection,
+    live: &Path,
+    dest: &Path,
+    passphrase: &str,
+    user_id: i64,
+) -> AppResult<ArchiveInfo> {
+    auth::require_permission(conn, user_id, permission::BACKUP_CREATE)?;
+    let dest = backup::new_destination(live, dest)?;
+    let snapshot = live.with_extension("db.export");
+    let _ = fs::remove_file(&snapshot);
+    backup::copy_database(conn, live, &snapshot)?;
+    let database = fs::read(&snapshot);
+    let _ = fs::remove_file(&snapshot);
+    let database = database?;
+
+    let archive = archive_database(&database, passphrase)?;
+    fs::File::create_new(&dest)?.write_all(&archive)?;
+    Ok(ArchiveInfo {
+        path: dest.to_string_lossy().into_owned(),
+        size_bytes: archive.len() as u64,
+        database_bytes: database.len() as u64,
+    })
+}
+
+/// Replace the live database behind `conn` with the one in the archive at
+/// `src`.
+///
+/// The archive must decrypt and pass its integrity tag, and the database in
+/// it must pass `quick_check`. A backup from an older schema than the live
+/// database is only restored with `allow_older`, since whatever the newer
+/// schema holds is lost. As with [`backup::restore_database`], the SQL
+/// plugin's pool must be closed around it.
+pub fn restore_backup(
+    conn: &mut Connection,
+    live: &Path,
+    src: &Path,
+    passphrase: &str,
+    allow_older: bool,
+    user_id: i64,
+) -> AppResult<()> {
+    auth::require_permission(conn, user_id, permission::BACKUP_RESTORE)?;
+    let entries = unpack(&open_sealed(&fs::read(src)?, passphrase)?)?;
+    let (_, database) = entries
+        .into_iter()
+        .find(|(name, _)| name == DATABASE_ENTRY)
+        .ok_or_else(|| AppError::validation("The archive does not contain a database"))?;
+
+    let staged = live.with_extension("db.restore");
+    fs::write(&staged, database)?;
+    let result = check_versions(conn, &staged, allow_older)
+        .and_then(|()| backup::copy_into_live(conn, &staged));
+    let _ = fs::remove_file(&staged);
+    result
+}
+
+fn check_versions(conn: &Connection, staged: &Path, allow_older: bool) -> AppResult<()> {
+    let version = backup::validate_backup(staged)?;
+    let current = migrations::applied_version(conn)?.unwrap_or(0);
+    if version < current && !allow_older {
+        return Err(AppError::validation(format!(
+            "The backup is from schema {version}, older than this database (schema {current}). \
+             Restoring it loses anything recorded since; confirm to restore anyway."
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{insert_cashier, insert_medicine, mark_migrated, migrated_db};
+
+    const ADMIN: i64 = 1;
+
+    fn medicine_count(conn: &Connection) -> i64 {
+        conn.query_row("SELECT COUNT(*) FROM medicines", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn archive_round_trips_and_rejects_a_wrong_passphrase_or_tampering() {
+        let (dir, db) = migrated_db();
+        let mut conn = db.connect().unwrap();
+        mark_migrated(&conn, 1);
+        insert_medicine(&conn, "Paracetamol", 12.0);
+        let dest = dir.path().join("backup.pcbak");
+
+        let info = export_backup(&conn, db.path(), &dest, "correct horse", ADMIN).unwrap();
+        assert!(info.size_bytes < info.database_bytes);
+        insert_medicine(&conn, "Cetirizine", 12.0);
+
+        let err =
+            restore_backup(&mut conn, db.path(), &dest, "wrong horse", false, ADMIN).unwrap_err();
+        assert!(err.to_string().contains("Wrong passphrase"), "{err}");
+        let mut altered = fs::read(&dest).unwrap();
+        altered[HEADER_LEN + 3] ^= 1;
+        let tampered = dir.path().join("tampered.pcbak");
+        fs::write(&tampered, altered).unwrap();
+        let err = restore_backup(
+            &mut conn,
+            db.path(),
+            &tampered,
+            "correct horse",
+            false,
+            ADMIN,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("altered"), "{err}");
+        let mut altered = fs::read(&dest).unwrap();
+        altered[MAGIC.len() + 1..MAGIC.len() + 5].copy_from_slice(&u32::MAX.to_be_bytes());
+        fs::write(&tampered, altered).unwrap();
+        let err = restore_backup(
+            &mut conn,
+            db.path(),
+            &tampered,
+            "correct horse",
+            false,
+            ADMIN,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("damaged"), "{err}");
+        let mut altered = fs::read(&dest).unwrap();
+        altered[MAGIC.len() + 5] ^= 1;
+        fs::write(&tampered, altered).unwrap();
+        let err = restore_backup(
+            &mut conn,
+            db.path(),
+            &tampered,
+            "correct horse",
+            false,
+            ADMIN,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("altered"), "{err}");
+        assert_eq!(medicine_count(&conn), 2);
+
+        restore_backup(&mut conn, db.path(), &dest, "correct horse", false, ADMIN).unwrap();
+        assert_eq!(medicine_count(&conn), 1);
+    }
+
+    #[test]
+    fn older_schema_needs_confirmation() {
+        let (dir, db) = migrated_db();
+        let mut conn = db.connect().unwrap();
+        mark_migrated(&conn, 1);
+        insert_medicine(&conn, "Paracetamol", 12.0);
+        let dest = dir.path().join("backup.pcbak");
+        export_backup(&conn, db.path(), &dest, "correct horse", ADMIN).unwrap();
+        mark_migrated(&conn, 2);
+
+        let err =
+            restore_backup(&mut conn, db.path(), &dest, "correct horse", false, ADMIN).unwrap_err();
+        assert!(err.to_string().contains("schema 1, older"), "{err}");
+        restore_backup(&mut conn, db.path(), &dest, "correct horse", true, ADMIN).unwrap();
+        assert_eq!(migrations::applied_version(&conn).unwrap(), Some(1));
+    }
+
+    #[test]
+    fn cashier_cannot_export_an_archive() {
+        let (dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let cashier = insert_cashier(&conn);
+        let dest = dir.path().join("shop.pharmbak");
+
+        let err = export_backup(&conn, db.path(), &dest, "correct horse", cashier).unwrap_err();
+        assert!(matches!(err, AppError::Unauthorized(_)), "{err}");
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn archive_never_overwrites_the_database_or_an_existing_file() {
+        let (dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let wal = dir.path().join("test.db-wal");
+
+        for dest in [db.path(), wal.as_path()] {
+            let err = export_backup(&conn, db.path(), dest, "correct horse", ADMIN).unwrap_err();
+            assert!(err.to_string().contains("live database"), "{err}");
+        }
+        let existing = dir.path().join("notes.txt");
+        fs::write(&existing, "keep me").unwrap();
+        let err = export_backup(&conn, db.path(), &existing, "correct horse", ADMIN).unwrap_err();
+        assert!(err.to_string().contains("already exists"), "{err}");
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "keep me");
+    }
+}
@@ -0,0 +1,337 @@
+//! Linear barcodes for labels: Code 128 and EAN-13.
+//!
+//! A barcode is rendered as its run of modules, `true` for a bar, without
+//! quiet zones; the label leaves the margins.
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Symbology {
+    Code128,
+    Ean13,
+}
+
+/// Bar and space widths of Code 128 values 0 to 105; 103 to 105 are the
+/// start codes of sets A, B and C.
+const CODE128_WIDTHS: [&str; 106] = [
+    "212222", "222122", "222221", "121223", "121322", "131222", "122213", "122312", "132212",
+    "221213", "221312", "231212", "112232", "122132", "122231", "113222", "123122", "123221",
+    "223211", "221132", "221231", "213212", "223112", "312131", "311222", "321122", "321221",
+    "312212", "322112", "322211", "212123", "212321", "232121", "111323", "131123", "131321",
+    "112313", "132113", "132311", "211313", "231113", "231311", "112133", "112331", "132131",
+    "113123", "113321", "133121", "313121", "211331", "231131", "213113", "213311", "213131",
+    "311123", "311321", "331121", "312113", "312311", "332111", "314111", "221411", "431111",
+    "111224", "111422", "121124", "121421", "141122", "141221", "112214", "112412", "122114",
+    "122411", "142112", "142211", "241211", "221114", "413111", "241112", "134111", "111242",
+    "121142", "121241", "114212", "124112", "124211", "411212", "421112", "421211", "212141",
+    "214121", "412121", "111143", "111341", "131141", "114113", "114311", "411113", "411311",
+    "113141", "114131", "311141", "411131", "211412", "211214", "211232",
+];
+const CODE128_STOP: &str = "2331112";
+const START_B: usize = 104;
+const START_C: usize = 105;
+
+/// Alternate bars and spaces of the given widths, starting with a bar.
+fn push_widths(modules: &mut Vec<bool>, widths: &str) {
+    for (i, width) in widths.bytes().enumerate() {
+        let bar = i % 2 == 0;
+        modules.extend(std::iter::repeat_n(bar, usize::from(width - b'0')));
+    }
+}
+
+/// `data` in Code 128: set C when it is an even run of digits, which halves
+/// its width, else set B, which covers printable ASCII.
+pub fn code128(data: &str) -> AppResult<Vec<bool>> {
+    if data.is_empty() || !data.bytes().all(|b| (b' '..=b'~').contains(&b)) {
+        return Err(AppError::validation(format!(
+            "Code 128 labels take printable ASCII only, not {data:?}"
+        )));
+    }
+    let numeric =
+        data.len() >= 4 && data.len().is_multiple_of(2) && data.bytes().all(|b| b.is_ascii_digit());
+    let (start, values): (usize, Vec<usize>) = if numeric {
+        let pairs = data
+            .as_bytes()
+            .chunks(2)
+            .map(|pair| usize::from((pair[0] - b'0') * 10 + (pair[1] - b'0')))
+            .collect();
+        (START_C, pairs)
+    } else {
+        (
+            START_B,
+            data.bytes().map(|b| usize::from(b - b' ')).collect(),
+        )
+    };
+    let checksum = values
+        .iter()
+        .enumerate()
+        .fold(start, |sum, (i, value)| sum + (i + 1) * value)
+        % 103;
+
+    let mut modules = Vec::with_capacity(11 * (values.len() + 2) + 13);
+    push_widths(&mut modules, CODE128_WIDTHS[start]);
+    for value in values {
+        push_widths(&mut modules, CODE128_WIDTHS[value]);
+    }
+    push_widths(&mut modules, CODE128_WIDTHS[checksum]);
+    push_widths(&mut modules, CODE128_STOP);
+    Ok(modules)
+}
+
+/// The check digit completing the body of a GTIN of any length, EAN-13
+/// included: weights alternate 3 and 1 from the right.
+pub fn gtin_check_digit(body: &[u8]) -> u8 {
+    let sum: u32 = body
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, d)| u32::from(*d) * if i % 2 == 0 { 3 } else { 1 })
+        .sum();
+    ((10 - sum % 10) % 10) as u8
+}
+
+/// A GTIN-8, -12, -13 or -14 with a valid check digit, zero-padded to the
+/// fourteen digits of a GTIN-14; anything else gives `None`.
+pub fn normalize_gtin(code: &str) -> Option<String> {
+    let code = code.trim();
+    if !matches!(code.len(), 8 | 12 | 13 | 14) || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let digits: Vec<u8> = code.bytes().map(|b| b - b'0').collect();
+    let (body, check) = digits.split_at(digits.len() - 1);
+    (gtin_check_digit(body) == check[0]).then(|| format!("{code:0>14}"))
+}
+
+/// An EAN-13 in the 2xx prefix reserved for in-store numbering, for stock
+/// that came without a barcode.
+pub fn in_store_ean13(id: i64) -> String {
+    let body = format!("2{:011}", id.rem_euclid(100_000_000_000));
+    let mut digits = [0u8; 12];
+    for (d, b) in digits.iter_mut().zip(body.bytes()) {
+        *d = b - b'0';
+    }
+    format!("{body}{}", gtin_check_digit(&digits))
+}
+
+/// The left-hand L codes of the digits; R codes are their complement and G
+/// codes the R codes reversed.
+const EAN_L: [&str; 10] = [
+    "0001101", "0011001", "0010011", "0111101", "0100011", "0110001", "0101111", "0111011",
+    "0110111", "0001011",
+];
+/// Which of the left six digits use G codes, by the first digit.
+const EAN_PARITY: [&str; 10] = [
+    "LLLLLL", "LLGLGG", "LLGGLG", "LLGGGL", "LGLLGG", "LGGLLG", "LGGGLG", "LGLGGL", "LGLGLG",
+    "LGGLGL",
+];
+
+/// `data` in EAN-13; it must be thirteen digits with a valid check digit.
+pub fn ean13(data: &str) -> AppResult<Vec<bool>> {
+    let digits: Vec<u8> = data.bytes().map(|b| b.wrapping_sub(b'0')).collect();
+    let valid = digits.len() == 13
+        && digits.iter().all(|d| *d <= 9)
+        && gtin_check_digit(&digits[..12]) == digits[12];
+    if !valid {
+        return Err(AppError::validation(format!(
+            "{data} is not a valid EAN-13; print it as Code 128"
+        )));
+    }
+
+    let bits = |code: &str, invert: bool, reverse: bool| -> Vec<bool> {
+        let mut bits: Vec<bool> = code.bytes().map(|b| (b == b'1') != invert).collect();
+        if reverse {
+            bits.reverse();
+        }
+        bits
+    };
+    let mut modules = bits("101", false, false);
+    let parity = EAN_PARITY[usize::from(digits[0])].as_bytes();
+    for (i, digit) in digits[1..7].iter().enumerate() {
+        let g = parity[i] == b'G';
+        modules.extend(bits(EAN_L[usize::from(*digit)], g, g));
+    }
+    modules.extend(bits("01010", false, false));
+    for digit in &digits[7..] {
+        modules.extend(bits(EAN_L[usize::from(*digit)], true, false));
+    }
+    modules.extend(bits("101", false, false));
+    Ok(modules)
+}
+
+pub fn encode(symbology: Symbology, data: &str) -> AppResult<Vec<bool>> {
+    match symbology {
+        Symbology::Code128 => code128(data),
+        Symbology::Ean13 => ean13(data),
+    }
+}
+
+/// What a GS1 element string, as in the DataMatrix on newer packs,
+/// says about the pack.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Gs1Data {
+    /// AI (01), as fourteen digits.
+    pub gtin: String,
+    /// AI (10).
+    pub batch_number: Option<String>,
+    /// AI (17) as `YYYY-MM-DD`; a day of 00 is read as the month's last.
+    pub expiry_date: Option<String>,
+    /// AI (21).
+    pub serial: Option<String>,
+}
+
+/// Group separator, the FNC1 that ends a variable-length field.
+const GS: char = '\u{1d}';
+
+/// Lengths of the fixed-length application identifiers a pack may carry
+/// before or between the ones read here.
+fn fixed_ai_length(ai: &str) -> Option<usize> {
+    match ai {
+        "00" => Some(18),
+        "01" | "02" => Some(14),
+        "11" | "12" | "13" | "15" | "16" | "17" => Some(6),
+        "20" => Some(2),
+        _ => None,
+    }
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// `YYMMDD` as a date in this century.
+fn gs1_date(yymmdd: &str) -> Option<String> {
+    let field = |range: std::ops::Range<usize>| yymmdd.get(range)?.parse::<u32>().ok();
+    let (year, month, day) = (2000 + field(0..2)?, field(2..4)?, field(4..6)?);
+    if !(1..=12).contains(&month) || day > days_in_month(year, month) {
+        return None;
+    }
+    let day = if day == 0 {
+        days_in_month(year, month)
+    } else {
+        day
+    };
+    Some(format!("{year}-{month:02}-{day:02}"))
+}
+
+/// Parse a scanned GS1 element string, raw with group separators or
+/// written with bracketed AIs. `None` unless it carries a valid GTIN.
+///
+/// Unknown variable-length AIs end the parse; what was read before them is
+/// kept.
+pub fn parse_gs1(code: &str) -> Option<Gs1Data> {
+    let code = code.trim();
+    if !code.is_ascii() {
+        return None;
+    }
+    // Scanners may prefix the symbology identifier, e.g. "]d2" for DataMatrix.
+    let code = match code.strip_prefix(']') {
+        Some(rest) => rest.get(2..)?,
+        None => code,
+    };
+    let fields: Vec<(String, String)> = if code.starts_with('(') {
+        code.split('(')
+            .skip(1)
+            .map(|part| {
+                let (ai, value) = part.split_once(')')?;
+                Some((ai.to_string(), value.trim_end_matches(GS).to_string()))
+            })
+            .collect::<Option<_>>()?
+    } else {
+        let mut fields = Vec::new();
+        let mut rest = code.trim_start_matches(GS);
+        while rest.len() >= 2 {
+            let ai = &rest[..2];
+            let value = match fixed_ai_length(ai) {
+                Some(len) => rest.get(2..2 + len)?,
+                None if matches!(ai, "10" | "21") => rest[2..].split(GS).next().unwrap_or_default(),
+                None => break,
+            };
+            fields.push((ai.to_string(), value.to_string()));
+            rest = rest[2 + value.len()..].trim_start_matches(GS);
+        }
+        fields
+    };
+
+    let mut data = Gs1Data {
+        gtin: String::new(),
+        batch_number: None,
+        expiry_date: None,
+        serial: None,
+    };
+    for (ai, value) in fields {
+        match ai.as_str() {
+            "01" => data.gtin = normalize_gtin(&value)?,
+            "10" if !value.is_empty() => data.batch_number = Some(value),
+            "17" => data.expiry_date = Some(gs1_date(&value)?),
+            "21" if !value.is_empty() => data.serial = Some(value),
+            _ => {}
+        }
+    }
+    (!data.gtin.is_empty()).then_some(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ean13_is_95_modules_and_checks_its_digit() {
+        let modules = ean13("4006381333931").unwrap();
+        assert_eq!(modules.len(), 95);
+        // First digit 4 puts the second digit, 0, in an L code.
+        let second: String = modules[3..10]
+            .iter()
+            .map(|&bar| if bar { '1' } else { '0' })
+            .collect();
+        assert_eq!(second, EAN_L[0]);
+        assert!(ean13("4006381333932").is_err());
+        assert!(ean13("400638133393").is_err());
+
+        let code = in_store_ean13(42);
+        assert_eq!(code, "2000000000428");
+        assert!(ean13(&code).is_ok());
+    }
+
+    #[test]
+    fn code128_packs_digits_in_pairs() {
+        // Start, one value per character or digit pair, checksum and stop.
+        assert_eq!(code128("LOOSE-PARA").unwrap().len(), 11 * 12 + 13);
+        assert_eq!(code128("123456").unwrap().len(), 11 * 5 + 13);
+        assert_eq!(code128("12345").unwrap().len(), 11 * 7 + 13);
+        assert!(code128("").is_err());
+        assert!(code128("caf\u{e9}").is_err());
+    }
+
+    #[test]
+    fn gs1_datamatrix_gives_gtin_batch_and_expiry() {
+        let raw = "]d2010890123456789017270600\u{1d}10AB-1234\u{1d}21X99";
+        let data = parse_gs1(raw).unwrap();
+        assert_eq!(data.gtin, "08901234567890");
+        assert_eq!(data.batch_number.as_deref(), Some("AB-1234"));
+        assert_eq!(data.expiry_date.as_deref(), Some("2027-06-30"));
+        assert_eq!(data.serial.as_deref(), Some("X99"));
+
+        let bracketed = parse_gs1("(01)08901234567890(10)AB-1234(17)280229").unwrap();
+        assert_eq!(bracketed.batch_number.as_deref(), Some("AB-1234"));
+        assert_eq!(bracketed.expiry_date.as_deref(), Some("2028-02-29"));
+
+        assert_eq!(parse_gs1("8901234567890"), None);
+        assert_eq!(parse_gs1("010890123456789117270630"), None);
+        assert_eq!(
+            normalize_gtin("8901234567890").as_deref(),
+            Some("08901234567890")
+        );
+        assert_eq!(normalize_gtin("8901234567891"), None);
+    }
+}
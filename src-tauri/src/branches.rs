@@ -0,0 +1,99 @@
+//! Outlets of a chain sharing one database.
+//!
+//! Batches and sales belong to a branch; rows created without one, and
+//! everything recorded before branches existed, belong to Main. A user
+//! without a branch may sell at any of them.
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::error::{AppError, AppResult};
+
+/// The branch seeded by the migration that added branches.
+pub const MAIN_BRANCH_ID: i64 = 1;
+
+/// The branch a sale is recorded at: the one asked for, else the user's
+/// own, else Main.
+pub fn sale_branch(conn: &Connection, requested: Option<i64>, user_id: i64) -> AppResult<i64> {
+    let Some(branch_id) = requested else {
+        let home: Option<i64> = conn
+            .query_row(
+                "SELECT branch_id FROM users WHERE id = ?1",
+                params![user_id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten();
+        return Ok(home.unwrap_or(MAIN_BRANCH_ID));
+    };
+    let active: bool = conn
+        .query_row(
+            "SELECT is_active FROM branches WHERE id = ?1",
+            params![branch_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Branch {branch_id}")))?;
+    if !active {
+        return Err(AppError::validation(format!(
+            "Branch {branch_id} is inactive"
+        )));
+    }
+    Ok(branch_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{
+        batch_quantity, insert_batch, insert_medicine, migrated_db, paid_sale,
+    };
+    use crate::fefo::pick_batches_fefo;
+    use crate::inventory::{batch_movement_history, transfer_stock};
+    use crate::reports::{daily_sales_summary, inventory_valuation};
+    use crate::sales::{create_sale, PaymentMode};
+
+    #[test]
+    fn each_branch_sees_and_sells_only_its_own_stock() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        conn.execute("INSERT INTO branches (name) VALUES ('Kothrud')", [])
+            .unwrap();
+        let kothrud = conn.last_insert_rowid();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let main_batch = insert_batch(&conn, para, "+30 days", 1_000, 10);
+        let outlet_batch = insert_batch(&conn, para, "+90 days", 1_000, 4);
+        conn.execute(
+            "UPDATE batches SET branch_id = ?1 WHERE id = ?2",
+            params![kothrud, outlet_batch],
+        )
+        .unwrap();
+
+        let plan = pick_batches_fefo(&conn, para, 4, None, Some(kothrud)).unwrap();
+        assert_eq!(plan[0].batch_id, outlet_batch);
+        let mut sale = paid_sale(&conn, None, PaymentMode::Cash, main_batch, 1);
+        sale.branch_id = Some(kothrud);
+        let err = db.with_tx(|tx| create_sale(tx, &sale)).unwrap_err();
+        assert!(err.to_string().contains("another branch"), "{err}");
+        sale.branch_id = None;
+        db.with_tx(|tx| create_sale(tx, &sale)).unwrap();
+
+        let today: String = conn
+            .query_row("SELECT date('now')", [], |r| r.get(0))
+            .unwrap();
+        let all = daily_sales_summary(&conn, &today, None).unwrap();
+        let outlet = daily_sales_summary(&conn, &today, Some(kothrud)).unwrap();
+        assert_eq!((all.invoice_count, outlet.invoice_count), (1, 0));
+        let outlet_stock = inventory_valuation(&conn, Some(kothrud)).unwrap();
+        assert_eq!(outlet_stock.total.quantity, 4);
+
+        db.with_tx(|tx| transfer_stock(tx, main_batch, outlet_batch, 3, "Restock", false, 1))
+            .unwrap();
+        assert_eq!(batch_quantity(&conn, outlet_batch), 7);
+        let moved = batch_movement_history(&conn, outlet_batch).unwrap();
+        let last = moved.last().unwrap();
+        assert_eq!(
+            (last.from_branch_id, last.to_branch_id),
+            (Some(MAIN_BRANCH_ID), Some(kothrud))
+        );
+    }
+}
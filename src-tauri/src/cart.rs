@@ -0,0 +1,210 @@
+//! Read-only checks of a till cart, so problems can be shown before the
+//! cashier takes payment. `create_sale` repeats them inside its own
+//! transaction; passing here does not reserve any stock.
+
+use std::collections::HashMap;
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+use crate::fefo::{Packing, SaleUnit};
+use crate::inventory::EXPIRED;
+
+/// A cart line as the till holds it before the sale is made.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CartLine {
+    pub batch_id: i64,
+    pub quantity: i64,
+    /// Defaults to the medicine's `sale_unit`, else packs.
+    #[serde(default)]
+    pub unit: Option<SaleUnit>,
+}
+
+/// The first problem found with a line, in the order they are checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineStatus {
+    Ok,
+    NotFound,
+    Inactive,
+    /// The batch is quarantined or blocked.
+    OnHold,
+    Expired,
+    /// The selling price is not positive or exceeds the MRP.
+    PriceError,
+    InsufficientStock,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineCheck {
+    pub batch_id: i64,
+    pub status: LineStatus,
+    /// Loose units the batch holds.
+    pub available_quantity: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CartValidation {
+    /// Every line is `ok`.
+    pub valid: bool,
+    /// One entry per cart line, in cart order.
+    pub lines: Vec<LineCheck>,
+}
+
+struct BatchState {
+    medicine_id: i64,
+    is_active: bool,
+    status: String,
+    expired: bool,
+    selling_price_paise: i64,
+    mrp_paise: i64,
+    quantity: i64,
+}
+
+fn batch_state(conn: &Connection, batch_id: i64) -> AppResult<Option<BatchState>> {
+    Ok(conn
+        .query_row(
+            &format!(
+                "SELECT b.medicine_id, m.is_active, b.status, {EXPIRED},
+                     b.selling_price_paise, b.mrp_paise, b.quantity
+                 FROM batches b JOIN medicines m ON m.id = b.medicine_id
+                 WHERE b.id = ?1"
+            ),
+            params![batch_id],
+            |row| {
+                Ok(BatchState {
+                    medicine_id: row.get(0)?,
+                    is_active: row.get(1)?,
+                    status: row.get(2)?,
+                    expired: row.get(3)?,
+                    selling_price_paise: row.get(4)?,
+                    mrp_paise: row.get(5)?,
+                    quantity: row.get(6)?,
+                })
+            },
+        )
+        .optional()?)
+}
+
+/// Check every line of a cart against current stock and prices.
+///
+/// Lines drawing on the same batch are checked against its stock together,
+/// so the later of two lines that each fit but not both is short.
+pub fn validate_cart(conn: &Connection, lines: &[CartLine]) -> AppResult<CartValidation> {
+    if lines.is_empty() {
+        return Err(AppError::validation("The cart is empty"));
+    }
+    let mut taken: HashMap<i64, i64> = HashMap::new();
+    let mut checks = Vec::with_capacity(lines.len());
+    for line in lines {
+        if line.quantity <= 0 {
+            return Err(AppError::validation(
+                "Every cart quantity must be greater than 0",
+            ));
+        }
+        let Some(batch) = batch_state(conn, line.batch_id)? else {
+            checks.push(LineCheck {
+                batch_id: line.batch_id,
+                status: LineStatus::NotFound,
+                available_quantity: 0,
+            });
+            continue;
+        };
+        let packing = Packing::load(conn, batch.medicine_id)?;
+        let wanted = packing.base_quantity(line.quantity, packing.unit(line.unit))?;
+        let already = taken.entry(line.batch_id).or_insert(0);
+
+        let status = if !batch.is_active {
+            LineStatus::Inactive
+        } else if batch.status != "available" {
+            LineStatus::OnHold
+        } else if batch.expired {
+            LineStatus::Expired
+        } else if batch.selling_price_paise <= 0 || batch.selling_price_paise > batch.mrp_paise {
+            LineStatus::PriceError
+        } else if *already + wanted > batch.quantity {
+            LineStatus::InsufficientStock
+        } else {
+            *already += wanted;
+            LineStatus::Ok
+        };
+        checks.push(LineCheck {
+            batch_id: line.batch_id,
+            status,
+            available_quantity: batch.quantity,
+        });
+    }
+    Ok(CartValidation {
+        valid: checks.iter().all(|c| c.status == LineStatus::Ok),
+        lines: checks,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{insert_batch, insert_medicine, migrated_db};
+
+    fn line(batch_id: i64, quantity: i64) -> CartLine {
+        CartLine {
+            batch_id,
+            quantity,
+            unit: None,
+        }
+    }
+
+    #[test]
+    fn each_line_reports_its_first_problem() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let retired = insert_medicine(&conn, "Retired", 12.0);
+        conn.execute(
+            "UPDATE medicines SET is_active = 0 WHERE id = ?1",
+            [retired],
+        )
+        .unwrap();
+        let fresh = insert_batch(&conn, para, "+90 days", 1_000, 5);
+        let expired = insert_batch(&conn, para, "-1 day", 1_000, 5);
+        let held = insert_batch(&conn, para, "+90 days", 1_000, 5);
+        conn.execute(
+            "UPDATE batches SET status = 'quarantine' WHERE id = ?1",
+            [held],
+        )
+        .unwrap();
+        let old_stock = insert_batch(&conn, retired, "+90 days", 1_000, 5);
+
+        let result = validate_cart(
+            &conn,
+            &[
+                line(fresh, 3),
+                line(fresh, 3),
+                line(expired, 1),
+                line(held, 1),
+                line(old_stock, 1),
+                line(9_999, 1),
+            ],
+        )
+        .unwrap();
+
+        let statuses: Vec<_> = result.lines.iter().map(|c| c.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                LineStatus::Ok,
+                LineStatus::InsufficientStock,
+                LineStatus::Expired,
+                LineStatus::OnHold,
+                LineStatus::Inactive,
+                LineStatus::NotFound,
+            ]
+        );
+        assert!(!result.valid);
+        assert_eq!(result.lines[1].available_quantity, 5);
+        assert!(validate_cart(&conn, &[line(fresh, 5)]).unwrap().valid);
+    }
+}
@@ -0,0 +1,29 @@
+use tauri::State;
+
+use crate::alerts::{self, Alert};
+use crate::auth;
+use crate::db::Db;
+
+#[tauri::command]
+pub fn acknowledge_alert(db: State<'_, Db>, alert_id: i64, token: String) -> Result<Alert, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        alerts::acknowledge_alert(tx, alert_id, user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn check_alerts(db: State<'_, Db>, token: String) -> Result<Vec<Alert>, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        alerts::check_alerts_now(tx, user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn list_alerts(db: State<'_, Db>, include_acknowledged: bool) -> Result<Vec<Alert>, String> {
+    db.with_conn(|conn| alerts::list_alerts(conn, include_acknowledged))
+        .map_err(String::from)
+}
@@ -0,0 +1,30 @@
+use tauri::State;
+
+use crate::audit::{self, AuditEntry};
+use crate::auth;
+use crate::db::Db;
+
+#[tauri::command]
+pub fn query_audit_log(
+    db: State<'_, Db>,
+    token: String,
+    from_date: String,
+    to_date: String,
+    table_name: Option<String>,
+    changed_by: Option<i64>,
+    limit: usize,
+) -> Result<Vec<AuditEntry>, String> {
+    db.with_conn(|conn| {
+        let user_id = auth::session_user(conn, &token)?;
+        audit::query_audit_log(
+            conn,
+            user_id,
+            &from_date,
+            &to_date,
+            table_name.as_deref(),
+            changed_by,
+            limit,
+        )
+    })
+    .map_err(String::from)
+}
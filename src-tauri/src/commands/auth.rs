@@ -0,0 +1,105 @@
+use tauri::State;
+
+use crate::auth::{self, Role, Session, UserUpdate};
+use crate::db::Db;
+
+#[tauri::command]
+pub fn change_password(
+    db: State<'_, Db>,
+    token: String,
+    current_password: String,
+    new_password: String,
+) -> Result<(), String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        auth::change_password(tx, user_id, &current_password, &new_password)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn check_permission(
+    db: State<'_, Db>,
+    token: String,
+    permission: String,
+) -> Result<bool, String> {
+    db.with_conn(|conn| {
+        let user_id = auth::session_user(conn, &token)?;
+        auth::check_permission(conn, user_id, &permission)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn create_user(
+    db: State<'_, Db>,
+    username: String,
+    password: String,
+    full_name: String,
+    role: Role,
+    token: Option<String>,
+) -> Result<i64, String> {
+    db.with_tx(|tx| {
+        let user_id = token
+            .map(|token| auth::session_user(tx, &token))
+            .transpose()?;
+        auth::create_user(tx, &username, &password, &full_name, role, user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn current_session(db: State<'_, Db>, token: String) -> Result<Option<Session>, String> {
+    db.with_conn(|conn| auth::current_session(conn, &token))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn login(db: State<'_, Db>, username: String, password: String) -> Result<Session, String> {
+    db.with_tx(|tx| auth::login(tx, &username, &password))
+        .and_then(|attempt| attempt)
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn logout(db: State<'_, Db>, token: String) -> Result<(), String> {
+    db.with_tx(|tx| auth::logout(tx, &token))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn set_role_permissions(
+    db: State<'_, Db>,
+    token: String,
+    role: Role,
+    permissions: Vec<String>,
+) -> Result<(), String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        auth::set_role_permissions(tx, user_id, role, &permissions)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn unlock_user(db: State<'_, Db>, token: String, target_user_id: i64) -> Result<(), String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        auth::unlock_user(tx, user_id, target_user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn update_user(
+    db: State<'_, Db>,
+    target_user_id: i64,
+    user: UserUpdate,
+    token: String,
+) -> Result<(), String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        auth::update_user(tx, target_user_id, &user, user_id)
+    })
+    .map_err(String::from)
+}
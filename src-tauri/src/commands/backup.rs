@@ -0,0 +1,136 @@
+use std::path::Path;
+
+use tauri::State;
+use tauri_plugin_sql::DbInstances;
+
+use crate::auth;
+use crate::backup::{self, BackupFile, BackupInfo};
+use crate::backup_archive::{self, ArchiveInfo};
+use crate::commands::encryption::with_pool_closed;
+use crate::db::Db;
+use crate::remote_backup::{self, BackupStatus, RemoteSecretsSet};
+
+#[tauri::command]
+pub fn backup_database(
+    db: State<'_, Db>,
+    dest_path: String,
+    token: String,
+) -> Result<BackupInfo, String> {
+    db.with_conn(|conn| {
+        let user_id = auth::session_user(conn, &token)?;
+        backup::backup_database(conn, db.path(), Path::new(&dest_path), user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn backup_now(db: State<'_, Db>, token: String) -> Result<BackupInfo, String> {
+    db.with_conn(|conn| {
+        let user_id = auth::session_user(conn, &token)?;
+        backup::backup_now(conn, db.path(), user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn list_backups(db: State<'_, Db>) -> Result<Vec<BackupFile>, String> {
+    db.with_conn(|conn| backup::list_backups(conn, db.path()))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn restore_database(
+    db: State<'_, Db>,
+    pools: State<'_, DbInstances>,
+    src_path: String,
+    token: String,
+) -> Result<(), String> {
+    let user_id = db
+        .with_conn(|conn| auth::session_user(conn, &token))
+        .map_err(String::from)?;
+    with_pool_closed(&db, &pools, || {
+        let mut conn = db.connect()?;
+        backup::restore_database(&mut conn, db.path(), Path::new(&src_path), user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn backup_status(db: State<'_, Db>, token: String) -> Result<BackupStatus, String> {
+    db.with_conn(|conn| {
+        let user_id = auth::session_user(conn, &token)?;
+        remote_backup::backup_status(conn, db.path(), user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn export_backup(
+    db: State<'_, Db>,
+    dest_path: String,
+    passphrase: String,
+    token: String,
+) -> Result<ArchiveInfo, String> {
+    db.with_conn(|conn| {
+        let user_id = auth::session_user(conn, &token)?;
+        backup_archive::export_backup(conn, db.path(), Path::new(&dest_path), &passphrase, user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn restore_backup(
+    db: State<'_, Db>,
+    pools: State<'_, DbInstances>,
+    src_path: String,
+    passphrase: String,
+    allow_older: bool,
+    token: String,
+) -> Result<(), String> {
+    let user_id = db
+        .with_conn(|conn| auth::session_user(conn, &token))
+        .map_err(String::from)?;
+    with_pool_closed(&db, &pools, || {
+        let mut conn = db.connect()?;
+        backup_archive::restore_backup(
+            &mut conn,
+            db.path(),
+            Path::new(&src_path),
+            &passphrase,
+            allow_older,
+            user_id,
+        )
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn remote_backup_secrets(db: State<'_, Db>, token: String) -> Result<RemoteSecretsSet, String> {
+    db.with_conn(|conn| {
+        let user_id = auth::session_user(conn, &token)?;
+        remote_backup::remote_secrets_set(conn, db.path(), user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn set_remote_backup_secrets(
+    db: State<'_, Db>,
+    secret: Option<String>,
+    refresh_token: Option<String>,
+    passphrase: Option<String>,
+    token: String,
+) -> Result<RemoteSecretsSet, String> {
+    db.with_conn(|conn| {
+        let user_id = auth::session_user(conn, &token)?;
+        remote_backup::set_remote_secrets(
+            conn,
+            db.path(),
+            secret,
+            refresh_token,
+            passphrase,
+            user_id,
+        )
+    })
+    .map_err(String::from)
+}
@@ -0,0 +1,10 @@
+use tauri::State;
+
+use crate::cart::{self, CartLine, CartValidation};
+use crate::db::Db;
+
+#[tauri::command]
+pub fn validate_cart(db: State<'_, Db>, lines: Vec<CartLine>) -> Result<CartValidation, String> {
+    db.with_conn(|conn| cart::validate_cart(conn, &lines))
+        .map_err(String::from)
+}
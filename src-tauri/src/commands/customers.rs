@@ -0,0 +1,77 @@
+use tauri::State;
+
+use crate::auth;
+use crate::customers::{
+    self, CustomerInput, CustomerLedger, NewCustomerPayment, OpenInvoice, Outstanding,
+    ReceivableAging,
+};
+use crate::db::Db;
+
+#[tauri::command]
+pub fn create_customer(
+    db: State<'_, Db>,
+    customer: CustomerInput,
+    token: String,
+) -> Result<i64, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        customers::create_customer(tx, &customer, user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn customer_outstanding(db: State<'_, Db>, customer_id: i64) -> Result<Outstanding, String> {
+    db.with_conn(|conn| customers::customer_outstanding(conn, customer_id))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn customer_ledger(
+    db: State<'_, Db>,
+    customer_id: i64,
+    from: String,
+    to: String,
+) -> Result<CustomerLedger, String> {
+    db.with_conn(|conn| customers::customer_ledger(conn, customer_id, &from, &to))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn open_invoices(db: State<'_, Db>, customer_id: i64) -> Result<Vec<OpenInvoice>, String> {
+    db.with_conn(|conn| customers::open_invoices(conn, customer_id))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn receivables_aging(db: State<'_, Db>) -> Result<Vec<ReceivableAging>, String> {
+    db.with_conn(customers::receivables_aging)
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn record_customer_payment(
+    db: State<'_, Db>,
+    mut payload: NewCustomerPayment,
+    token: String,
+) -> Result<Outstanding, String> {
+    db.with_tx(|tx| {
+        payload.user_id = auth::session_user(tx, &token)?;
+        customers::record_customer_payment(tx, &payload)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn update_customer(
+    db: State<'_, Db>,
+    customer_id: i64,
+    customer: CustomerInput,
+    token: String,
+) -> Result<(), String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        customers::update_customer(tx, customer_id, &customer, user_id)
+    })
+    .map_err(String::from)
+}
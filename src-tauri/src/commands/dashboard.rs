@@ -0,0 +1,14 @@
+use tauri::State;
+
+use crate::auth;
+use crate::dashboard::{self, DashboardMetrics};
+use crate::db::Db;
+
+#[tauri::command]
+pub fn dashboard_metrics(db: State<'_, Db>, token: String) -> Result<DashboardMetrics, String> {
+    db.with_conn(|conn| {
+        let user_id = auth::session_user(conn, &token)?;
+        dashboard::dashboard_metrics(conn, user_id)
+    })
+    .map_err(String::from)
+}
@@ -0,0 +1,24 @@
+use tauri::State;
+
+use crate::auth;
+use crate::db::Db;
+use crate::einvoice::{self, EInvoice, IrnDetails};
+
+#[tauri::command]
+pub fn generate_einvoice_json(db: State<'_, Db>, sale_id: i64) -> Result<EInvoice, String> {
+    db.with_conn(|conn| einvoice::generate_einvoice_json(conn, sale_id))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn generate_irn(db: State<'_, Db>, sale_id: i64, token: String) -> Result<IrnDetails, String> {
+    let user_id = db
+        .with_conn(|conn| auth::session_user(conn, &token))
+        .map_err(String::from)?;
+    let irn = db
+        .with_conn(|conn| einvoice::request_irn(conn, sale_id, user_id, &mut einvoice::curl_post))
+        .map_err(String::from)?;
+    db.with_tx(|tx| einvoice::record_irn(tx, sale_id, user_id, &irn))
+        .map_err(String::from)?;
+    Ok(irn)
+}
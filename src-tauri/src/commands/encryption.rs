@@ -0,0 +1,110 @@
+use tauri::State;
+use tauri_plugin_sql::{DbInstances, DbPool};
+
+use crate::auth::{self, permission};
+use crate::db::{Db, DB_URL};
+use crate::encryption::{self, DatabaseStatus};
+use crate::error::{AppError, AppResult};
+
+/// Run `f` with the frontend's pool closed, then open it again keyed with
+/// whatever passphrase the backend holds afterwards. The plugin's lock is
+/// held throughout, so frontend queries wait rather than reach a file that
+/// is being converted or restored.
+pub(super) fn with_pool_closed(
+    db: &Db,
+    pools: &DbInstances,
+    f: impl FnOnce() -> AppResult<()>,
+) -> AppResult<()> {
+    tauri::async_runtime::block_on(async {
+        let mut pools = pools.0.write().await;
+        if let Some(DbPool::Sqlite(pool)) = pools.remove(DB_URL) {
+            pool.close().await;
+        }
+        let result = f();
+        let pool = db.open_pool().await?;
+        pools.insert(DB_URL.to_owned(), DbPool::Sqlite(pool));
+        result
+    })
+}
+
+#[tauri::command]
+pub fn database_status(db: State<'_, Db>) -> Result<DatabaseStatus, String> {
+    encryption::status(&db).map_err(String::from)
+}
+
+/// Unlock the encrypted database with `key`.
+///
+/// Nobody can sign in before the first unlock, so `token` is only needed
+/// once the database is open; unlocking it again then takes a user holding
+/// `database.encrypt`.
+#[tauri::command]
+pub fn unlock_database(
+    db: State<'_, Db>,
+    pools: State<'_, DbInstances>,
+    key: String,
+    token: Option<String>,
+) -> Result<(), String> {
+    if db.is_unlocked() {
+        let token =
+            token.ok_or_else(|| AppError::unauthorized("the database is already unlocked"))?;
+        db.with_conn(|conn| {
+            let user_id = auth::session_user(conn, &token)?;
+            auth::require_permission(conn, user_id, permission::DATABASE_ENCRYPT)
+        })
+        .map_err(String::from)?;
+    }
+    if !encryption::verify_key(db.path(), &key).map_err(String::from)? {
+        return Err(AppError::validation("The passphrase is incorrect").into());
+    }
+    with_pool_closed(&db, &pools, || {
+        db.set_key(Some(key));
+        Ok(())
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn verify_db_key(db: State<'_, Db>, key: String, token: String) -> Result<bool, String> {
+    db.with_conn(|conn| {
+        let user_id = auth::session_user(conn, &token)?;
+        encryption::check_key(conn, db.path(), &key, user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn change_db_key(
+    db: State<'_, Db>,
+    pools: State<'_, DbInstances>,
+    old_key: String,
+    new_key: String,
+    token: String,
+) -> Result<(), String> {
+    let user_id = db
+        .with_conn(|conn| auth::session_user(conn, &token))
+        .map_err(String::from)?;
+    with_pool_closed(&db, &pools, || {
+        encryption::change_key(db.path(), &old_key, &new_key, user_id)?;
+        db.set_key(Some(new_key));
+        Ok(())
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn encrypt_database(
+    db: State<'_, Db>,
+    pools: State<'_, DbInstances>,
+    key: String,
+    token: String,
+) -> Result<(), String> {
+    let user_id = db
+        .with_conn(|conn| auth::session_user(conn, &token))
+        .map_err(String::from)?;
+    with_pool_closed(&db, &pools, || {
+        encryption::encrypt_plaintext(db.path(), &key, user_id)?;
+        db.set_key(Some(key));
+        Ok(())
+    })
+    .map_err(String::from)
+}
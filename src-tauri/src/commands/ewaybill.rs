@@ -0,0 +1,52 @@
+use tauri::State;
+
+use crate::auth;
+use crate::db::Db;
+use crate::ewaybill::{self, CancelReason, Dispatch, EwayBill};
+
+#[tauri::command]
+pub fn generate_eway_bill(
+    db: State<'_, Db>,
+    sale_id: i64,
+    dispatch: Dispatch,
+    token: String,
+) -> Result<EwayBill, String> {
+    let user_id = db
+        .with_conn(|conn| auth::session_user(conn, &token))
+        .map_err(String::from)?;
+    let bill = db
+        .with_conn(|conn| {
+            ewaybill::request_eway_bill(conn, sale_id, user_id, &dispatch, &mut ewaybill::curl_post)
+        })
+        .map_err(String::from)?;
+    db.with_tx(|tx| ewaybill::record_eway_bill(tx, sale_id, user_id, &dispatch, &bill))
+        .map_err(String::from)?;
+    Ok(bill)
+}
+
+#[tauri::command]
+pub fn cancel_eway_bill(
+    db: State<'_, Db>,
+    sale_id: i64,
+    reason: CancelReason,
+    remark: String,
+    token: String,
+) -> Result<(), String> {
+    let user_id = db
+        .with_conn(|conn| auth::session_user(conn, &token))
+        .map_err(String::from)?;
+    let cancelled_at = db
+        .with_conn(|conn| {
+            ewaybill::request_cancellation(
+                conn,
+                sale_id,
+                user_id,
+                reason,
+                &remark,
+                &mut ewaybill::curl_post,
+            )
+        })
+        .map_err(String::from)?;
+    db.with_tx(|tx| ewaybill::record_cancellation(tx, sale_id, reason, &remark, &cancelled_at))
+        .map_err(String::from)
+}
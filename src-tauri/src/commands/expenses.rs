@@ -0,0 +1,95 @@
+use tauri::State;
+
+use crate::auth;
+use crate::db::Db;
+use crate::expenses::{self, Expense, ExpenseCategory, ExpenseReport, NewExpense};
+
+#[tauri::command]
+pub fn create_expense(
+    db: State<'_, Db>,
+    mut payload: NewExpense,
+    token: String,
+) -> Result<Expense, String> {
+    db.with_tx(|tx| {
+        payload.user_id = auth::session_user(tx, &token)?;
+        expenses::create_expense(tx, &payload)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn create_expense_category(
+    db: State<'_, Db>,
+    name: String,
+    token: String,
+) -> Result<ExpenseCategory, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        expenses::create_expense_category(tx, &name, user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn delete_expense(db: State<'_, Db>, expense_id: i64, token: String) -> Result<(), String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        expenses::delete_expense(tx, expense_id, user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn expense_report(
+    db: State<'_, Db>,
+    from_date: String,
+    to_date: String,
+) -> Result<ExpenseReport, String> {
+    db.with_conn(|conn| expenses::expense_report(conn, &from_date, &to_date))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn list_expense_categories(db: State<'_, Db>) -> Result<Vec<ExpenseCategory>, String> {
+    db.with_conn(expenses::list_expense_categories)
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn list_expenses(
+    db: State<'_, Db>,
+    from_date: String,
+    to_date: String,
+    category_id: Option<i64>,
+) -> Result<Vec<Expense>, String> {
+    db.with_conn(|conn| expenses::list_expenses(conn, &from_date, &to_date, category_id))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn set_expense_category_active(
+    db: State<'_, Db>,
+    category_id: i64,
+    is_active: bool,
+    token: String,
+) -> Result<(), String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        expenses::set_expense_category_active(tx, category_id, is_active, user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn update_expense(
+    db: State<'_, Db>,
+    expense_id: i64,
+    mut payload: NewExpense,
+    token: String,
+) -> Result<Expense, String> {
+    db.with_tx(|tx| {
+        payload.user_id = auth::session_user(tx, &token)?;
+        expenses::update_expense(tx, expense_id, &payload)
+    })
+    .map_err(String::from)
+}
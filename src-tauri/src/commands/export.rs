@@ -0,0 +1,52 @@
+use std::path::Path;
+
+use tauri::State;
+
+use crate::auth;
+use crate::db::Db;
+use crate::export::{self, ExportSummary};
+use crate::tally;
+
+#[tauri::command]
+pub fn export_day_book_csv(
+    db: State<'_, Db>,
+    date: String,
+    dest_path: String,
+    token: String,
+) -> Result<ExportSummary, String> {
+    db.with_conn(|conn| {
+        let user_id = auth::session_user(conn, &token)?;
+        export::export_day_book_csv(conn, &date, Path::new(&dest_path), user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn export_sales_csv(
+    db: State<'_, Db>,
+    from_date: String,
+    to_date: String,
+    dest_path: String,
+    token: String,
+) -> Result<ExportSummary, String> {
+    db.with_conn(|conn| {
+        let user_id = auth::session_user(conn, &token)?;
+        export::export_sales_csv(conn, &from_date, &to_date, Path::new(&dest_path), user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn export_tally_xml(
+    db: State<'_, Db>,
+    from_date: String,
+    to_date: String,
+    dest_path: String,
+    token: String,
+) -> Result<ExportSummary, String> {
+    db.with_conn(|conn| {
+        let user_id = auth::session_user(conn, &token)?;
+        tally::export_tally_xml(conn, &from_date, &to_date, Path::new(&dest_path), user_id)
+    })
+    .map_err(String::from)
+}
@@ -0,0 +1,16 @@
+use tauri::State;
+
+use crate::db::Db;
+use crate::fefo::{self, BatchAllocation, SaleUnit};
+
+#[tauri::command]
+pub fn pick_batches_fefo(
+    db: State<'_, Db>,
+    medicine_id: i64,
+    quantity: i64,
+    unit: Option<SaleUnit>,
+    branch_id: Option<i64>,
+) -> Result<Vec<BatchAllocation>, String> {
+    db.with_conn(|conn| fefo::pick_batches_fefo(conn, medicine_id, quantity, unit, branch_id))
+        .map_err(String::from)
+}
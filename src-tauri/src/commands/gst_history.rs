@@ -0,0 +1,20 @@
+use tauri::State;
+
+use crate::auth;
+use crate::db::Db;
+use crate::gst_history;
+
+#[tauri::command]
+pub fn set_medicine_gst(
+    db: State<'_, Db>,
+    medicine_id: i64,
+    gst_slab_id: i64,
+    effective_from: String,
+    token: String,
+) -> Result<(), String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        gst_history::set_medicine_gst(tx, medicine_id, gst_slab_id, &effective_from, user_id)
+    })
+    .map_err(String::from)
+}
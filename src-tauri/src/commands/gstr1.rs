@@ -0,0 +1,42 @@
+use std::path::Path;
+
+use tauri::State;
+
+use crate::auth;
+use crate::db::Db;
+use crate::export::ExportSummary;
+use crate::gstr1::{self, Gstr1};
+
+#[tauri::command]
+pub fn export_gstr1_csv(
+    db: State<'_, Db>,
+    period: String,
+    dest_dir: String,
+    token: String,
+) -> Result<Vec<ExportSummary>, String> {
+    db.with_conn(|conn| {
+        let user_id = auth::session_user(conn, &token)?;
+        gstr1::export_gstr1_csv(conn, &period, Path::new(&dest_dir), user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn export_gstr1_json(
+    db: State<'_, Db>,
+    period: String,
+    dest_path: String,
+    token: String,
+) -> Result<ExportSummary, String> {
+    db.with_conn(|conn| {
+        let user_id = auth::session_user(conn, &token)?;
+        gstr1::export_gstr1_json(conn, &period, Path::new(&dest_path), user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn gstr1_report(db: State<'_, Db>, period: String) -> Result<Gstr1, String> {
+    db.with_conn(|conn| gstr1::gstr1_report(conn, &period))
+        .map_err(String::from)
+}
@@ -0,0 +1,10 @@
+use tauri::State;
+
+use crate::db::Db;
+use crate::gstr3b::{self, Gstr3bSummary};
+
+#[tauri::command]
+pub fn gstr3b_summary(db: State<'_, Db>, month: String) -> Result<Gstr3bSummary, String> {
+    db.with_conn(|conn| gstr3b::gstr3b_summary(conn, &month))
+        .map_err(String::from)
+}
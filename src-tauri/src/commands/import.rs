@@ -0,0 +1,27 @@
+use std::fs;
+
+use tauri::State;
+
+use crate::auth;
+use crate::db::Db;
+use crate::error::AppError;
+use crate::import::{self, ImportSummary};
+
+#[tauri::command]
+pub fn import_medicines_csv(
+    db: State<'_, Db>,
+    path: String,
+    dry_run: bool,
+    token: String,
+) -> Result<ImportSummary, String> {
+    let user_id = db
+        .with_conn(|conn| auth::session_user(conn, &token))
+        .map_err(String::from)?;
+    let csv = fs::read_to_string(&path).map_err(AppError::from)?;
+    if dry_run {
+        db.with_conn(|conn| import::plan_medicine_import(conn, &csv, user_id))
+    } else {
+        db.with_tx(|tx| import::import_medicines(tx, &csv, user_id))
+    }
+    .map_err(String::from)
+}
@@ -0,0 +1,113 @@
+use tauri::State;
+
+use crate::auth;
+use crate::db::Db;
+use crate::inventory::{
+    self, AdjustmentReason, BatchInput, BatchStatus, DisposalSummary, StockAdjustment,
+    StockMovement,
+};
+
+#[tauri::command]
+pub fn adjust_stock(
+    db: State<'_, Db>,
+    batch_id: i64,
+    new_quantity: i64,
+    reason: AdjustmentReason,
+    remarks: String,
+    token: String,
+) -> Result<StockAdjustment, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        inventory::adjust_stock(tx, batch_id, new_quantity, reason, &remarks, user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn batch_movement_history(
+    db: State<'_, Db>,
+    batch_id: i64,
+) -> Result<Vec<StockMovement>, String> {
+    db.with_conn(|conn| inventory::batch_movement_history(conn, batch_id))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn create_batch(db: State<'_, Db>, batch: BatchInput, token: String) -> Result<i64, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        inventory::create_batch(tx, &batch, user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn dispose_expired(
+    db: State<'_, Db>,
+    batch_ids: Vec<i64>,
+    reason: String,
+    r#override: Option<bool>,
+    token: String,
+) -> Result<DisposalSummary, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        inventory::dispose_expired(
+            tx,
+            &batch_ids,
+            &reason,
+            r#override.unwrap_or(false),
+            user_id,
+        )
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn transfer_stock(
+    db: State<'_, Db>,
+    from_batch_id: i64,
+    to_batch_id: i64,
+    quantity: i64,
+    note: String,
+    allow_cross_medicine: Option<bool>,
+    token: String,
+) -> Result<(), String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        inventory::transfer_stock(
+            tx,
+            from_batch_id,
+            to_batch_id,
+            quantity,
+            &note,
+            allow_cross_medicine.unwrap_or(false),
+            user_id,
+        )
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn list_stock_adjustments(
+    db: State<'_, Db>,
+    from_date: String,
+    to_date: String,
+) -> Result<Vec<StockAdjustment>, String> {
+    db.with_conn(|conn| inventory::list_stock_adjustments(conn, &from_date, &to_date))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn set_batch_status(
+    db: State<'_, Db>,
+    batch_id: i64,
+    status: BatchStatus,
+    reason: String,
+    token: String,
+) -> Result<(), String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        inventory::set_batch_status(tx, batch_id, status, &reason, user_id)
+    })
+    .map_err(String::from)
+}
@@ -0,0 +1,28 @@
+use std::path::Path;
+
+use tauri::State;
+
+use crate::auth;
+use crate::db::Db;
+use crate::invoice::{self, InvoiceDocument};
+use crate::invoice_pdf;
+
+#[tauri::command]
+pub fn generate_invoice_pdf(
+    db: State<'_, Db>,
+    sale_id: i64,
+    dest_path: String,
+    token: String,
+) -> Result<u64, String> {
+    db.with_conn(|conn| {
+        auth::session_user(conn, &token)?;
+        invoice_pdf::generate_invoice_pdf(conn, db.path(), sale_id, Path::new(&dest_path))
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn get_invoice(db: State<'_, Db>, sale_id: i64) -> Result<InvoiceDocument, String> {
+    db.with_conn(|conn| invoice::get_invoice(conn, sale_id))
+        .map_err(String::from)
+}
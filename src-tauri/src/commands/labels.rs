@@ -0,0 +1,31 @@
+use std::path::Path;
+
+use tauri::State;
+
+use crate::auth;
+use crate::barcode::Symbology;
+use crate::db::Db;
+use crate::labels::{self, LabelRequest, LabelSheet, LabelSize};
+
+#[tauri::command]
+pub fn generate_batch_labels(
+    db: State<'_, Db>,
+    batch_id: i64,
+    copies: u32,
+    symbology: Symbology,
+    size: LabelSize,
+    dest_path: String,
+    token: String,
+) -> Result<LabelSheet, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        let request = LabelRequest {
+            batch_id,
+            copies,
+            symbology,
+            size,
+        };
+        labels::generate_batch_labels(tx, db.path(), request, Path::new(&dest_path), user_id)
+    })
+    .map_err(String::from)
+}
@@ -0,0 +1,54 @@
+use tauri::State;
+
+use crate::auth;
+use crate::db::Db;
+use crate::locations::{self, Location, LocationQuantity};
+
+#[tauri::command]
+pub fn batch_locations(db: State<'_, Db>, batch_id: i64) -> Result<Vec<LocationQuantity>, String> {
+    db.with_conn(|conn| locations::batch_locations(conn, batch_id))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn create_location(
+    db: State<'_, Db>,
+    branch_id: i64,
+    name: String,
+    token: String,
+) -> Result<Location, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        locations::create_location(tx, branch_id, &name, user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn list_locations(db: State<'_, Db>, branch_id: Option<i64>) -> Result<Vec<Location>, String> {
+    db.with_conn(|conn| locations::list_locations(conn, branch_id))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn transfer_between_locations(
+    db: State<'_, Db>,
+    batch_id: i64,
+    from_location_id: i64,
+    to_location_id: i64,
+    quantity: i64,
+    token: String,
+) -> Result<Vec<LocationQuantity>, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        locations::transfer_between_locations(
+            tx,
+            batch_id,
+            from_location_id,
+            to_location_id,
+            quantity,
+            user_id,
+        )
+    })
+    .map_err(String::from)
+}
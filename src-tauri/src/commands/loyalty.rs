@@ -0,0 +1,20 @@
+use tauri::State;
+
+use crate::auth;
+use crate::db::Db;
+use crate::loyalty::{self, RedeemResult};
+
+#[tauri::command]
+pub fn redeem_points(
+    db: State<'_, Db>,
+    customer_id: i64,
+    points: i64,
+    sale_id: i64,
+    token: String,
+) -> Result<RedeemResult, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        loyalty::redeem_points(tx, customer_id, points, sale_id, user_id)
+    })
+    .map_err(String::from)
+}
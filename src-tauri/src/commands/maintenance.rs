@@ -0,0 +1,29 @@
+use tauri::State;
+
+use crate::auth;
+use crate::db::Db;
+use crate::maintenance::{self, MaintenanceReport, MaintenanceRun, SchemaHealth};
+
+#[tauri::command]
+pub fn db_maintenance(db: State<'_, Db>, token: String) -> Result<MaintenanceRun, String> {
+    db.with_conn(|conn| {
+        let user_id = auth::session_user(conn, &token)?;
+        maintenance::db_maintenance(conn, db.path(), user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn optimize_database(db: State<'_, Db>, token: String) -> Result<MaintenanceReport, String> {
+    db.with_conn(|conn| {
+        let user_id = auth::session_user(conn, &token)?;
+        maintenance::optimize_database(conn, db.path(), user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn schema_health(db: State<'_, Db>) -> Result<SchemaHealth, String> {
+    db.with_conn(maintenance::schema_health)
+        .map_err(String::from)
+}
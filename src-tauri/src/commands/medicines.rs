@@ -0,0 +1,85 @@
+use tauri::State;
+
+use crate::auth;
+use crate::db::Db;
+use crate::medicines::{self, BarcodeMatch, MedicineHit, MedicineInput, ScanResult};
+
+#[tauri::command]
+pub fn create_medicine(
+    db: State<'_, Db>,
+    medicine: MedicineInput,
+    token: String,
+) -> Result<i64, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        medicines::create_medicine(tx, &medicine, user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn deactivate_medicine(
+    db: State<'_, Db>,
+    medicine_id: i64,
+    force: Option<bool>,
+    token: String,
+) -> Result<(), String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        medicines::deactivate_medicine(tx, medicine_id, force.unwrap_or(false), user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn lookup_barcode(
+    db: State<'_, Db>,
+    code: String,
+    branch_id: Option<i64>,
+) -> Result<Option<BarcodeMatch>, String> {
+    db.with_conn(|conn| medicines::lookup_barcode(conn, &code, branch_id))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn lookup_by_barcode(db: State<'_, Db>, code: String) -> Result<Option<ScanResult>, String> {
+    db.with_conn(|conn| medicines::lookup_by_barcode(conn, &code))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn reactivate_medicine(
+    db: State<'_, Db>,
+    medicine_id: i64,
+    token: String,
+) -> Result<(), String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        medicines::reactivate_medicine(tx, medicine_id, user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn search_medicines(
+    db: State<'_, Db>,
+    query: String,
+    limit: i64,
+) -> Result<Vec<MedicineHit>, String> {
+    db.with_conn(|conn| medicines::search_medicines(conn, &query, limit))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn update_medicine(
+    db: State<'_, Db>,
+    medicine_id: i64,
+    medicine: MedicineInput,
+    token: String,
+) -> Result<(), String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        medicines::update_medicine(tx, medicine_id, &medicine, user_id)
+    })
+    .map_err(String::from)
+}
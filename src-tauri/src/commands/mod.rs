@@ -0,0 +1,46 @@
+//! Tauri command handlers.
+//!
+//! Handlers are thin: they open a connection or transaction on the managed
+//! [`Db`](crate::db::Db) and delegate to the domain modules.
+
+pub mod alerts;
+pub mod audit;
+pub mod auth;
+pub mod backup;
+pub mod cart;
+pub mod customers;
+pub mod dashboard;
+pub mod einvoice;
+pub mod encryption;
+pub mod ewaybill;
+pub mod expenses;
+pub mod export;
+pub mod fefo;
+pub mod gst_history;
+pub mod gstr1;
+pub mod gstr3b;
+pub mod import;
+pub mod inventory;
+pub mod invoice;
+pub mod labels;
+pub mod locations;
+pub mod loyalty;
+pub mod maintenance;
+pub mod medicines;
+pub mod prescriptions;
+pub mod printing;
+pub mod purchase_orders;
+pub mod purchase_returns;
+pub mod purchases;
+pub mod registration;
+pub mod reorder;
+pub mod reports;
+pub mod returns;
+pub mod sales;
+pub mod scheduler;
+pub mod settings;
+pub mod setup;
+pub mod shifts;
+pub mod stock_take;
+pub mod suppliers;
+pub mod sync;
@@ -0,0 +1,32 @@
+use tauri::State;
+
+use crate::auth;
+use crate::db::Db;
+use crate::prescriptions::{self, NewPrescription};
+
+#[tauri::command]
+pub fn create_prescription(
+    db: State<'_, Db>,
+    prescription: NewPrescription,
+    token: String,
+) -> Result<i64, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        prescriptions::create_prescription(tx, &prescription, user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn link_prescription(
+    db: State<'_, Db>,
+    prescription_id: i64,
+    sale_id: i64,
+    token: String,
+) -> Result<(), String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        prescriptions::link_prescription(tx, prescription_id, sale_id, user_id)
+    })
+    .map_err(String::from)
+}
@@ -0,0 +1,21 @@
+use tauri::State;
+
+use crate::db::Db;
+use crate::printing::{self, PrinterConfig};
+
+#[tauri::command]
+pub fn print_receipt(db: State<'_, Db>, sale_id: i64) -> Result<(), String> {
+    db.with_conn(|conn| printing::print_receipt(conn, sale_id))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn print_shift_report(db: State<'_, Db>, shift_id: i64) -> Result<(), String> {
+    db.with_conn(|conn| printing::print_shift_report(conn, shift_id))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn printer_config(db: State<'_, Db>) -> Result<PrinterConfig, String> {
+    db.with_conn(printing::printer_config).map_err(String::from)
+}
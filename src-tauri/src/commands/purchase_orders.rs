@@ -0,0 +1,74 @@
+use tauri::State;
+
+use crate::auth;
+use crate::db::Db;
+use crate::purchase_orders::{self, PurchaseOrder, PurchaseOrderDocument, PurchaseOrderLine};
+
+#[tauri::command]
+pub fn approve_purchase_order(
+    db: State<'_, Db>,
+    order_id: i64,
+    token: String,
+) -> Result<PurchaseOrder, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        purchase_orders::approve_purchase_order(tx, order_id, user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn close_purchase_order(
+    db: State<'_, Db>,
+    order_id: i64,
+    token: String,
+) -> Result<PurchaseOrder, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        purchase_orders::close_purchase_order(tx, order_id, user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn create_purchase_order(
+    db: State<'_, Db>,
+    supplier_id: i64,
+    lines: Vec<PurchaseOrderLine>,
+    notes: Option<String>,
+    token: String,
+) -> Result<PurchaseOrder, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        purchase_orders::create_purchase_order(tx, supplier_id, &lines, notes.as_deref(), user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn get_purchase_order(db: State<'_, Db>, order_id: i64) -> Result<PurchaseOrder, String> {
+    db.with_conn(|conn| purchase_orders::get_purchase_order(conn, order_id))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn purchase_order_document(
+    db: State<'_, Db>,
+    order_id: i64,
+) -> Result<PurchaseOrderDocument, String> {
+    db.with_conn(|conn| purchase_orders::purchase_order_document(conn, order_id))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn send_purchase_order(
+    db: State<'_, Db>,
+    order_id: i64,
+    token: String,
+) -> Result<PurchaseOrderDocument, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        purchase_orders::send_purchase_order(tx, order_id, user_id)
+    })
+    .map_err(String::from)
+}
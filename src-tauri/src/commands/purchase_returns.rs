@@ -0,0 +1,60 @@
+use tauri::State;
+
+use crate::auth;
+use crate::db::Db;
+use crate::purchase_returns::{
+    self, DebitNote, DebitNoteStatus, PurchaseReturnLine, PurchaseReturnReason,
+};
+
+#[tauri::command]
+pub fn create_purchase_return(
+    db: State<'_, Db>,
+    supplier_id: i64,
+    reason: PurchaseReturnReason,
+    notes: Option<String>,
+    lines: Vec<PurchaseReturnLine>,
+    token: String,
+) -> Result<DebitNote, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        purchase_returns::create_purchase_return(
+            tx,
+            supplier_id,
+            reason,
+            notes.as_deref(),
+            &lines,
+            user_id,
+        )
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn get_debit_note(db: State<'_, Db>, return_id: i64) -> Result<DebitNote, String> {
+    db.with_conn(|conn| purchase_returns::get_debit_note(conn, return_id))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn list_debit_notes(
+    db: State<'_, Db>,
+    supplier_id: Option<i64>,
+    status: Option<DebitNoteStatus>,
+) -> Result<Vec<DebitNote>, String> {
+    db.with_conn(|conn| purchase_returns::list_debit_notes(conn, supplier_id, status))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn mark_debit_note_credited(
+    db: State<'_, Db>,
+    return_id: i64,
+    credit_reference: String,
+    token: String,
+) -> Result<DebitNote, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        purchase_returns::mark_debit_note_credited(tx, return_id, &credit_reference, user_id)
+    })
+    .map_err(String::from)
+}
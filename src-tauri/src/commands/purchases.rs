@@ -0,0 +1,43 @@
+use tauri::State;
+
+use crate::auth;
+use crate::db::Db;
+use crate::purchases::{self, PurchaseLine, PurchaseResult};
+
+#[tauri::command]
+pub fn receive_goods(
+    db: State<'_, Db>,
+    supplier_id: i64,
+    invoice_number: String,
+    purchase_order_id: Option<i64>,
+    lines: Vec<PurchaseLine>,
+    token: String,
+) -> Result<PurchaseResult, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        purchases::receive_goods(
+            tx,
+            supplier_id,
+            &invoice_number,
+            purchase_order_id,
+            &lines,
+            user_id,
+        )
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn receive_purchase(
+    db: State<'_, Db>,
+    supplier_id: i64,
+    invoice_number: String,
+    lines: Vec<PurchaseLine>,
+    token: String,
+) -> Result<PurchaseResult, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        purchases::receive_purchase(tx, supplier_id, &invoice_number, &lines, user_id)
+    })
+    .map_err(String::from)
+}
@@ -0,0 +1,22 @@
+use tauri::State;
+
+use crate::db::Db;
+use crate::registration::{self, FieldProblem, GstinDetails, Registrations};
+
+#[tauri::command]
+pub fn validate_registrations(registrations: Registrations) -> Vec<FieldProblem> {
+    registration::validate_registrations(&registrations)
+}
+
+#[tauri::command]
+pub fn lookup_gstin(db: State<'_, Db>, gstin: String) -> Result<GstinDetails, String> {
+    db.with_conn(|conn| {
+        registration::lookup_gstin(
+            conn,
+            &gstin,
+            &mut crate::einvoice::curl_post,
+            &mut registration::curl_get,
+        )
+    })
+    .map_err(String::from)
+}
@@ -0,0 +1,53 @@
+use tauri::State;
+
+use crate::auth;
+use crate::db::Db;
+use crate::purchase_orders::PurchaseOrder;
+use crate::reorder::{self, ReorderLevelUpdate, ReorderSuggestion, SupplierOrder};
+
+#[tauri::command]
+pub fn suggest_reorder_levels(
+    db: State<'_, Db>,
+    lookback_days: i64,
+) -> Result<Vec<ReorderSuggestion>, String> {
+    db.with_conn(|conn| reorder::suggest_reorder_levels(conn, lookback_days))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn apply_reorder_levels(
+    db: State<'_, Db>,
+    updates: Vec<ReorderLevelUpdate>,
+    token: String,
+) -> Result<usize, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        reorder::apply_reorder_levels(tx, &updates, user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn suggest_orders(
+    db: State<'_, Db>,
+    lookback_days: i64,
+    branch_id: Option<i64>,
+) -> Result<Vec<SupplierOrder>, String> {
+    db.with_conn(|conn| reorder::suggest_orders(conn, lookback_days, branch_id))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn draft_suggested_orders(
+    db: State<'_, Db>,
+    lookback_days: i64,
+    branch_id: Option<i64>,
+    supplier_id: Option<i64>,
+    token: String,
+) -> Result<Vec<PurchaseOrder>, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        reorder::draft_suggested_orders(tx, lookback_days, branch_id, supplier_id, user_id)
+    })
+    .map_err(String::from)
+}
@@ -0,0 +1,166 @@
+use tauri::State;
+
+use crate::auth::{self, permission};
+use crate::daybook::{self, DayBook};
+use crate::db::Db;
+use crate::reports::{
+    self, AbcReport, CostMethod, DailySummary, DeadStockReport, ExpiryReport, HsnSummary,
+    LocationStock, MarginReport, MovementAnalytics, ProfitRow, ProfitSummary, ReorderItem,
+    SalesAnalytics, StockValuation, ValuationReport,
+};
+
+#[tauri::command]
+pub fn abc_analysis(
+    db: State<'_, Db>,
+    from_date: String,
+    to_date: String,
+) -> Result<AbcReport, String> {
+    db.with_conn(|conn| reports::abc_analysis(conn, &from_date, &to_date))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn daily_sales_summary(
+    db: State<'_, Db>,
+    date: String,
+    branch_id: Option<i64>,
+) -> Result<DailySummary, String> {
+    db.with_conn(|conn| reports::daily_sales_summary(conn, &date, branch_id))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn day_book(db: State<'_, Db>, date: String) -> Result<DayBook, String> {
+    db.with_conn(|conn| daybook::day_book(conn, &date))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn dead_stock_report(
+    db: State<'_, Db>,
+    idle_days: i64,
+    branch_id: Option<i64>,
+) -> Result<DeadStockReport, String> {
+    db.with_conn(|conn| reports::dead_stock_report(conn, idle_days, branch_id))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn expiry_report(db: State<'_, Db>, branch_id: Option<i64>) -> Result<ExpiryReport, String> {
+    db.with_conn(|conn| reports::expiry_report(conn, branch_id))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn hsn_tax_summary(
+    db: State<'_, Db>,
+    from_date: String,
+    to_date: String,
+) -> Result<Vec<HsnSummary>, String> {
+    db.with_conn(|conn| reports::hsn_tax_summary(conn, &from_date, &to_date))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn inventory_valuation(
+    db: State<'_, Db>,
+    branch_id: Option<i64>,
+) -> Result<ValuationReport, String> {
+    db.with_conn(|conn| reports::inventory_valuation(conn, branch_id))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn margin_report(
+    db: State<'_, Db>,
+    token: String,
+    from_date: String,
+    to_date: String,
+) -> Result<MarginReport, String> {
+    db.with_conn(|conn| {
+        let user_id = auth::session_user(conn, &token)?;
+        auth::require_permission(conn, user_id, permission::DASHBOARD_FINANCIALS)?;
+        reports::margin_report(conn, &from_date, &to_date)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn movement_analytics(
+    db: State<'_, Db>,
+    from_date: String,
+    to_date: String,
+    limit: usize,
+) -> Result<MovementAnalytics, String> {
+    db.with_conn(|conn| reports::movement_analytics(conn, &from_date, &to_date, limit))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn profit_report(
+    db: State<'_, Db>,
+    token: String,
+    from_date: String,
+    to_date: String,
+) -> Result<Vec<ProfitRow>, String> {
+    db.with_conn(|conn| {
+        let user_id = auth::session_user(conn, &token)?;
+        auth::require_permission(conn, user_id, permission::DASHBOARD_FINANCIALS)?;
+        reports::profit_report(conn, &from_date, &to_date)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn profit_summary(
+    db: State<'_, Db>,
+    token: String,
+    from_date: String,
+    to_date: String,
+) -> Result<ProfitSummary, String> {
+    db.with_conn(|conn| {
+        let user_id = auth::session_user(conn, &token)?;
+        auth::require_permission(conn, user_id, permission::DASHBOARD_FINANCIALS)?;
+        reports::profit_summary(conn, &from_date, &to_date)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn reorder_report(
+    db: State<'_, Db>,
+    branch_id: Option<i64>,
+) -> Result<Vec<ReorderItem>, String> {
+    db.with_conn(|conn| reports::reorder_report(conn, branch_id))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn sales_analytics(
+    db: State<'_, Db>,
+    from_date: String,
+    to_date: String,
+    limit: usize,
+) -> Result<SalesAnalytics, String> {
+    db.with_conn(|conn| reports::sales_analytics(conn, &from_date, &to_date, limit))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn stock_by_location(
+    db: State<'_, Db>,
+    branch_id: Option<i64>,
+) -> Result<Vec<LocationStock>, String> {
+    db.with_conn(|conn| reports::stock_by_location(conn, branch_id))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn stock_valuation(
+    db: State<'_, Db>,
+    branch_id: Option<i64>,
+    method: Option<CostMethod>,
+) -> Result<StockValuation, String> {
+    db.with_conn(|conn| reports::stock_valuation(conn, branch_id, method.unwrap_or_default()))
+        .map_err(String::from)
+}
@@ -0,0 +1,21 @@
+use tauri::State;
+
+use crate::auth;
+use crate::db::Db;
+use crate::returns::{self, ReturnItem, ReturnResult};
+use crate::sales::PaymentMode;
+
+#[tauri::command]
+pub fn create_sale_return(
+    db: State<'_, Db>,
+    sale_id: i64,
+    items: Vec<ReturnItem>,
+    refund_mode: PaymentMode,
+    token: String,
+) -> Result<ReturnResult, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        returns::create_sale_return(tx, sale_id, &items, refund_mode, user_id)
+    })
+    .map_err(String::from)
+}
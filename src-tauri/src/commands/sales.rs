@@ -0,0 +1,38 @@
+use tauri::State;
+
+use crate::auth;
+use crate::db::Db;
+use crate::sales::{self, AmendResult, NewSale, Payment, SaleLineInput, SaleResult};
+
+#[tauri::command]
+pub fn amend_sale(
+    db: State<'_, Db>,
+    sale_id: i64,
+    new_items: Vec<SaleLineInput>,
+    token: String,
+) -> Result<AmendResult, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        sales::amend_sale(tx, sale_id, &new_items, user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn create_sale(
+    db: State<'_, Db>,
+    mut payload: NewSale,
+    token: String,
+) -> Result<SaleResult, String> {
+    db.with_tx(|tx| {
+        payload.user_id = auth::session_user(tx, &token)?;
+        sales::create_sale(tx, &payload)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn sale_payment_breakdown(db: State<'_, Db>, sale_id: i64) -> Result<Vec<Payment>, String> {
+    db.with_conn(|conn| sales::sale_payment_breakdown(conn, sale_id))
+        .map_err(String::from)
+}
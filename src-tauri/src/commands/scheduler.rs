@@ -0,0 +1,70 @@
+use tauri::State;
+
+use crate::auth;
+use crate::db::Db;
+use crate::scheduler::{self, JobRun, ScheduledJob, Scheduler};
+
+#[tauri::command]
+pub fn list_scheduled_jobs(
+    db: State<'_, Db>,
+    scheduler: State<'_, Scheduler>,
+) -> Result<Vec<ScheduledJob>, String> {
+    db.with_conn(|conn| {
+        scheduler.register(conn)?;
+        scheduler::list_jobs(conn)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn scheduled_job_runs(
+    db: State<'_, Db>,
+    job_key: String,
+    limit: i64,
+) -> Result<Vec<JobRun>, String> {
+    db.with_conn(|conn| scheduler::job_runs(conn, &job_key, limit))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn set_scheduled_job_enabled(
+    db: State<'_, Db>,
+    job_key: String,
+    enabled: bool,
+    token: String,
+) -> Result<ScheduledJob, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        scheduler::set_job_enabled(tx, &job_key, enabled, user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn set_scheduled_job_schedule(
+    db: State<'_, Db>,
+    job_key: String,
+    schedule: String,
+    token: String,
+) -> Result<ScheduledJob, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        scheduler::set_job_schedule(tx, &job_key, &schedule, user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn trigger_scheduled_job(
+    db: State<'_, Db>,
+    scheduler: State<'_, Scheduler>,
+    job_key: String,
+    token: String,
+) -> Result<JobRun, String> {
+    let user_id = db
+        .with_conn(|conn| auth::session_user(conn, &token))
+        .map_err(String::from)?;
+    scheduler
+        .trigger(&db, &job_key, user_id)
+        .map_err(String::from)
+}
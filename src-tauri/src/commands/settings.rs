@@ -0,0 +1,19 @@
+use serde_json::{Map, Value};
+use tauri::State;
+
+use crate::auth;
+use crate::db::Db;
+use crate::settings;
+
+#[tauri::command]
+pub fn update_settings(
+    db: State<'_, Db>,
+    settings: Map<String, Value>,
+    token: String,
+) -> Result<(), String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        settings::update_settings(tx, &settings, user_id)
+    })
+    .map_err(String::from)
+}
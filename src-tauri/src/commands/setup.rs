@@ -0,0 +1,14 @@
+use tauri::State;
+
+use crate::db::Db;
+use crate::setup::{self, PharmacySettingsInput};
+
+#[tauri::command]
+pub fn complete_first_run(
+    db: State<'_, Db>,
+    new_admin_password: String,
+    pharmacy: PharmacySettingsInput,
+) -> Result<(), String> {
+    db.with_tx(|tx| setup::complete_first_run(tx, &new_admin_password, &pharmacy))
+        .map_err(String::from)
+}
@@ -0,0 +1,59 @@
+use tauri::State;
+
+use crate::auth;
+use crate::db::Db;
+use crate::shifts::{self, DrawerTotals, Shift, ZReport};
+
+#[tauri::command]
+pub fn close_shift(
+    db: State<'_, Db>,
+    shift_id: i64,
+    counted: DrawerTotals,
+    notes: Option<String>,
+    token: String,
+) -> Result<ZReport, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        shifts::close_shift(tx, shift_id, counted, notes.as_deref(), user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn current_shift(db: State<'_, Db>, token: String) -> Result<Option<Shift>, String> {
+    db.with_conn(|conn| {
+        let user_id = auth::session_user(conn, &token)?;
+        shifts::current_shift(conn, user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn list_shifts(
+    db: State<'_, Db>,
+    from_date: String,
+    to_date: String,
+) -> Result<Vec<Shift>, String> {
+    db.with_conn(|conn| shifts::list_shifts(conn, &from_date, &to_date))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn open_shift(
+    db: State<'_, Db>,
+    token: String,
+    opening_float_paise: i64,
+    notes: Option<String>,
+) -> Result<Shift, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        shifts::open_shift(tx, user_id, opening_float_paise, notes.as_deref())
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn shift_report(db: State<'_, Db>, shift_id: i64) -> Result<ZReport, String> {
+    db.with_conn(|conn| shifts::shift_report(conn, shift_id))
+        .map_err(String::from)
+}
@@ -0,0 +1,77 @@
+use tauri::State;
+
+use crate::auth;
+use crate::db::Db;
+use crate::stock_take::{self, StockTake, StockTakeLine, VarianceReport};
+
+#[tauri::command]
+pub fn finalize_stock_take(
+    db: State<'_, Db>,
+    stock_take_id: i64,
+    token: String,
+) -> Result<VarianceReport, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        stock_take::finalize_stock_take(tx, stock_take_id, user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn get_stock_take(db: State<'_, Db>, stock_take_id: i64) -> Result<StockTake, String> {
+    db.with_conn(|conn| stock_take::get_stock_take(conn, stock_take_id))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn record_stock_count(
+    db: State<'_, Db>,
+    stock_take_id: i64,
+    batch_id: i64,
+    counted_quantity: i64,
+    token: String,
+) -> Result<StockTakeLine, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        stock_take::record_count(tx, stock_take_id, batch_id, counted_quantity, user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn record_scanned_count(
+    db: State<'_, Db>,
+    stock_take_id: i64,
+    barcode: String,
+    counted_quantity: i64,
+    token: String,
+) -> Result<StockTakeLine, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        stock_take::record_scanned_count(tx, stock_take_id, &barcode, counted_quantity, user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn start_stock_take(
+    db: State<'_, Db>,
+    branch_id: Option<i64>,
+    notes: Option<String>,
+    token: String,
+) -> Result<StockTake, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        stock_take::start_stock_take(tx, branch_id, notes.as_deref(), user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn stock_take_variance_report(
+    db: State<'_, Db>,
+    stock_take_id: i64,
+) -> Result<VarianceReport, String> {
+    db.with_conn(|conn| stock_take::variance_report(conn, stock_take_id))
+        .map_err(String::from)
+}
@@ -0,0 +1,68 @@
+use tauri::State;
+
+use crate::auth;
+use crate::db::Db;
+use crate::suppliers::{self, NewSupplierPayment, SupplierInput, SupplierLedger, SupplierPayables};
+
+#[tauri::command]
+pub fn create_supplier(
+    db: State<'_, Db>,
+    supplier: SupplierInput,
+    token: String,
+) -> Result<i64, String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        suppliers::create_supplier(tx, &supplier, user_id)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn payables_aging(db: State<'_, Db>) -> Result<Vec<SupplierPayables>, String> {
+    db.with_conn(suppliers::payables_aging)
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn record_supplier_payment(
+    db: State<'_, Db>,
+    mut payload: NewSupplierPayment,
+    token: String,
+) -> Result<SupplierPayables, String> {
+    db.with_tx(|tx| {
+        payload.user_id = auth::session_user(tx, &token)?;
+        suppliers::record_supplier_payment(tx, &payload)
+    })
+    .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn supplier_ledger(
+    db: State<'_, Db>,
+    supplier_id: i64,
+    from_date: String,
+    to_date: String,
+) -> Result<SupplierLedger, String> {
+    db.with_conn(|conn| suppliers::supplier_ledger(conn, supplier_id, &from_date, &to_date))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn supplier_payables(db: State<'_, Db>, supplier_id: i64) -> Result<SupplierPayables, String> {
+    db.with_conn(|conn| suppliers::supplier_payables(conn, supplier_id))
+        .map_err(String::from)
+}
+
+#[tauri::command]
+pub fn update_supplier(
+    db: State<'_, Db>,
+    supplier_id: i64,
+    supplier: SupplierInput,
+    token: String,
+) -> Result<(), String> {
+    db.with_tx(|tx| {
+        let user_id = auth::session_user(tx, &token)?;
+        suppliers::update_supplier(tx, supplier_id, &supplier, user_id)
+    })
+    .map_err(String::from)
+}
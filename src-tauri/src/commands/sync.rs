@@ -0,0 +1,19 @@
+use tauri::State;
+
+use crate::auth;
+use crate::db::Db;
+use crate::sync::{self, FlushResult};
+
+#[tauri::command]
+pub fn pending_sync_count(db: State<'_, Db>) -> Result<i64, String> {
+    db.with_conn(sync::pending_sync_count).map_err(String::from)
+}
+
+#[tauri::command]
+pub fn flush_sync_queue(db: State<'_, Db>, token: String) -> Result<FlushResult, String> {
+    db.with_conn(|conn| {
+        let user_id = auth::session_user(conn, &token)?;
+        sync::flush_sync_queue(conn, user_id)
+    })
+    .map_err(String::from)
+}
@@ -0,0 +1,858 @@
+//! Customer credit accounts: what is owed on credit sales against payments.
+//!
+//! A receipt may be allocated to particular invoices; whatever is left
+//! unallocated settles the oldest invoices first, and any excess is an
+//! advance against future credit sales.
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::auth;
+use crate::db::{ChangeOp, Entity, Tx};
+use crate::error::{AppError, AppResult};
+use crate::format::rupees;
+use crate::registration::{self, Registrations};
+use crate::reports::validate_date_range;
+use crate::sales::PaymentMode;
+use crate::sync;
+
+/// A customer's details as entered on the customer form.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomerInput {
+    pub name: String,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub address: Option<String>,
+    pub gstin: Option<String>,
+    pub state_code: Option<String>,
+    pub city: Option<String>,
+    pub pincode: Option<String>,
+    /// `None` leaves the customer's credit unlimited.
+    pub credit_limit_paise: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewCustomerPayment {
+    pub customer_id: i64,
+    pub amount_paise: i64,
+    pub payment_mode: PaymentMode,
+    /// `YYYY-MM-DD`; today when omitted.
+    #[serde(default)]
+    pub payment_date: Option<String>,
+    #[serde(default)]
+    pub reference: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// Invoices the payment settles; when empty it settles the oldest
+    /// open invoices first.
+    #[serde(default)]
+    pub allocations: Vec<Allocation>,
+    /// Set from the session by the command, never taken from the client.
+    #[serde(default, skip_deserializing)]
+    pub user_id: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Allocation {
+    pub sale_id: i64,
+    pub amount_paise: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Outstanding {
+    pub customer_id: i64,
+    /// The part of every sale put on credit.
+    pub credit_sales_paise: i64,
+    /// Returns against credit sales refunded as credit notes.
+    pub credit_returns_paise: i64,
+    pub payments_paise: i64,
+    /// Sales less returns and payments; negative when the customer has
+    /// paid in advance.
+    pub outstanding_paise: i64,
+}
+
+/// A credit invoice and what is still owed on it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenInvoice {
+    pub sale_id: i64,
+    pub invoice_number: String,
+    /// `YYYY-MM-DD`.
+    pub sale_date: String,
+    /// Days since the sale.
+    pub age_days: i64,
+    /// The part of the bill put on credit, less returns credited to it.
+    pub billed_paise: i64,
+    pub due_paise: i64,
+}
+
+/// One movement on a customer's account.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LedgerEntry {
+    /// `YYYY-MM-DD`.
+    pub date: String,
+    /// `sale`, `return` or `payment`.
+    pub kind: &'static str,
+    /// Invoice, credit note or payment reference.
+    pub reference: String,
+    /// What the entry adds to the amount owed.
+    pub debit_paise: i64,
+    /// What the entry takes off it.
+    pub credit_paise: i64,
+    /// Owed after this entry.
+    pub balance_paise: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomerLedger {
+    pub customer_id: i64,
+    /// Owed at the start of `from`.
+    pub opening_balance_paise: i64,
+    pub entries: Vec<LedgerEntry>,
+    pub closing_balance_paise: i64,
+}
+
+/// What one customer owes, by the age of the invoices it is owed on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceivableAging {
+    pub customer_id: i64,
+    pub name: String,
+    pub phone: Option<String>,
+    pub credit_limit_paise: Option<i64>,
+    /// Up to 30 days old.
+    pub current_paise: i64,
+    pub days_31_to_60_paise: i64,
+    pub days_61_to_90_paise: i64,
+    pub over_90_days_paise: i64,
+    /// Paid in advance of any invoice.
+    pub advance_paise: i64,
+    /// Owed less any advance.
+    pub outstanding_paise: i64,
+    pub oldest_invoice_date: Option<String>,
+}
+
+fn ensure_customer(conn: &Connection, customer_id: i64) -> AppResult<()> {
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM customers WHERE id = ?1)",
+        params![customer_id],
+        |row| row.get(0),
+    )?;
+    if exists {
+        Ok(())
+    } else {
+        Err(AppError::not_found(format!("Customer {customer_id}")))
+    }
+}
+
+fn check_customer(customer: &CustomerInput) -> AppResult<()> {
+    if customer.name.trim().is_empty() {
+        return Err(AppError::validation("Customer name is required"));
+    }
+    if customer.credit_limit_paise.is_some_and(|limit| limit < 0) {
+        return Err(AppError::validation("Credit limit cannot be negative"));
+    }
+    registration::check_registrations(&Registrations {
+        gstin: customer.gstin.clone(),
+        state_code: customer.state_code.clone(),
+        ..Registrations::default()
+    })
+}
+
+/// Add a customer, returning its id.
+pub fn create_customer(tx: &Tx, customer: &CustomerInput, user_id: i64) -> AppResult<i64> {
+    auth::active_role(tx, user_id)?;
+    check_customer(customer)?;
+    tx.execute(
+        "INSERT INTO customers (name, phone, email, address, gstin, state_code, city, pincode,
+             credit_limit_paise)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            customer.name.trim(),
+            customer.phone,
+            customer.email,
+            customer.address,
+            customer.gstin,
+            customer.state_code,
+            customer.city,
+            customer.pincode,
+            customer.credit_limit_paise
+        ],
+    )?;
+    let customer_id = tx.last_insert_rowid();
+    tx.changed(Entity::Customer, ChangeOp::Insert, customer_id);
+    sync::enqueue(
+        tx,
+        "create_customer",
+        &json!({ "customerId": customer_id, "name": customer.name.trim() }),
+    )?;
+    Ok(customer_id)
+}
+
+/// Replace a customer's details.
+pub fn update_customer(
+    tx: &Tx,
+    customer_id: i64,
+    customer: &CustomerInput,
+    user_id: i64,
+) -> AppResult<()> {
+    auth::active_role(tx, user_id)?;
+    check_customer(customer)?;
+    let updated = tx.execute(
+        "UPDATE customers SET name = ?1, phone = ?2, email = ?3, address = ?4, gstin = ?5,
+             state_code = ?6, city = ?7, pincode = ?8, credit_limit_paise = ?9
+         WHERE id = ?10",
+        params![
+            customer.name.trim(),
+            customer.phone,
+            customer.email,
+            customer.address,
+            customer.gstin,
+            customer.state_code,
+            customer.city,
+            customer.pincode,
+            customer.credit_limit_paise,
+            customer_id
+        ],
+    )?;
+    if updated == 0 {
+        return Err(AppError::not_found(format!("Customer {customer_id}")));
+    }
+    tx.changed(Entity::Customer, ChangeOp::Update, customer_id);
+    sync::enqueue(
+        tx,
+        "update_customer",
+        &json!({ "customerId": customer_id, "name": customer.name.trim() }),
+    )
+}
+
+/// What `customer_id` owes on credit sales; zero when they have none.
+pub fn customer_outstanding(conn: &Connection, customer_id: i64) -> AppResult<Outstanding> {
+    ensure_customer(conn, customer_id)?;
+    let (credit_sales_paise, credit_returns_paise, payments_paise): (i64, i64, i64) = conn
+        .query_row(
+            "SELECT
+                 (SELECT COALESCE(SUM(sp.amount_paise), 0) FROM sale_payments sp
+                  JOIN sales s ON s.id = sp.sale_id
+                  WHERE s.customer_id = ?1 AND sp.payment_mode = 'credit'),
+                 (SELECT COALESCE(SUM(r.refund_total_paise), 0) FROM sale_returns r
+                  JOIN sales s ON s.id = r.sale_id
+                  WHERE s.customer_id = ?1 AND r.refund_mode = 'credit'
+                    AND EXISTS (SELECT 1 FROM sale_payments sp
+                                WHERE sp.sale_id = s.id AND sp.payment_mode = 'credit')),
+                 (SELECT COALESCE(SUM(amount_paise), 0) FROM customer_payments
+                  WHERE customer_id = ?1)",
+            params![customer_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+    Ok(Outstanding {
+        customer_id,
+        credit_sales_paise,
+        credit_returns_paise,
+        payments_paise,
+        outstanding_paise: credit_sales_paise - credit_returns_paise - payments_paise,
+    })
+}
+
+/// Every credit invoice of `customer_id`, oldest first, with what is still
+/// due on it, and the payments left over as an advance.
+///
+/// Allocated payments settle their invoices; unallocated ones, and any
+/// return credited beyond what an invoice still owed, settle the oldest
+/// invoices first.
+fn settle_invoices(conn: &Connection, customer_id: i64) -> AppResult<(Vec<OpenInvoice>, i64)> {
+    let mut advance: i64 = conn.query_row(
+        "SELECT COALESCE(SUM(amount_paise), 0)
+             - (SELECT COALESCE(SUM(a.amount_paise), 0) FROM customer_payment_allocations a
+                JOIN customer_payments p ON p.id = a.payment_id
+                WHERE p.customer_id = ?1)
+         FROM customer_payments WHERE customer_id = ?1",
+        params![customer_id],
+        |row| row.get(0),
+    )?;
+    let mut stmt = conn.prepare(
+        "SELECT s.id, s.invoice_number, date(s.sale_date),
+             CAST(julianday(date('now')) - julianday(date(s.sale_date)) AS INTEGER),
+             c.credit - (SELECT COALESCE(SUM(r.refund_total_paise), 0) FROM sale_returns r
+                         WHERE r.sale_id = s.id AND r.refund_mode = 'credit'),
+             (SELECT COALESCE(SUM(a.amount_paise), 0) FROM customer_payment_allocations a
+              WHERE a.sale_id = s.id)
+         FROM sales s
+         JOIN (SELECT sale_id, SUM(amount_paise) AS credit FROM sale_payments
+               WHERE payment_mode = 'credit' GROUP BY sale_id) c ON c.sale_id = s.id
+         WHERE s.customer_id = ?1
+         ORDER BY s.sale_date, s.id",
+    )?;
+    let rows = stmt.query_map(params![customer_id], |row| {
+        Ok((
+            OpenInvoice {
+                sale_id: row.get(0)?,
+                invoice_number: row.get(1)?,
+                sale_date: row.get(2)?,
+                age_days: row.get(3)?,
+                billed_paise: row.get(4)?,
+                due_paise: 0,
+            },
+            row.get::<_, i64>(5)?,
+        ))
+    })?;
+    let mut unpaid = Vec::new();
+    for row in rows {
+        let (invoice, allocated) = row?;
+        let owed = invoice.billed_paise - allocated;
+        // Returns credited after an invoice was paid leave money over.
+        advance -= owed.min(0);
+        unpaid.push((invoice, owed.max(0)));
+    }
+    let invoices = unpaid
+        .into_iter()
+        .map(|(mut invoice, owed)| {
+            let settled = advance.min(owed);
+            advance -= settled;
+            invoice.due_paise = owed - settled;
+            invoice
+        })
+        .collect();
+    Ok((invoices, advance))
+}
+
+/// The credit invoices `customer_id` still owes on, oldest first.
+pub fn open_invoices(conn: &Connection, customer_id: i64) -> AppResult<Vec<OpenInvoice>> {
+    ensure_customer(conn, customer_id)?;
+    let (invoices, _) = settle_invoices(conn, customer_id)?;
+    Ok(invoices.into_iter().filter(|i| i.due_paise > 0).collect())
+}
+
+/// Refuse to put `credit_paise` of a sale on the account of `customer_id`
+/// when it would take what they owe past their credit limit.
+pub(crate) fn check_credit_limit(
+    conn: &Connection,
+    customer_id: Option<i64>,
+    credit_paise: i64,
+) -> AppResult<()> {
+    if credit_paise == 0 {
+        return Ok(());
+    }
+    let Some(customer_id) = customer_id else {
+        return Err(AppError::validation(
+            "Choose the customer a credit sale is billed to",
+        ));
+    };
+    let limit: Option<i64> = conn.query_row(
+        "SELECT credit_limit_paise FROM customers WHERE id = ?1",
+        params![customer_id],
+        |row| row.get(0),
+    )?;
+    let Some(limit) = limit else {
+        return Ok(());
+    };
+    let owed = customer_outstanding(conn, customer_id)?.outstanding_paise;
+    if owed + credit_paise > limit {
+        return Err(AppError::validation(format!(
+            "Rs. {} on credit would exceed the customer's limit of Rs. {}; Rs. {} is already owed",
+            rupees(credit_paise),
+            rupees(limit),
+            rupees(owed)
+        )));
+    }
+    Ok(())
+}
+
+/// Who owes what, split by how long ago the invoices were raised. Customers
+/// who owe nothing and have no advance are left out.
+pub fn receivables_aging(conn: &Connection) -> AppResult<Vec<ReceivableAging>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, phone, credit_limit_paise FROM customers c
+         WHERE EXISTS (SELECT 1 FROM sales s JOIN sale_payments sp ON sp.sale_id = s.id
+                       WHERE s.customer_id = c.id AND sp.payment_mode = 'credit')
+            OR EXISTS (SELECT 1 FROM customer_payments p WHERE p.customer_id = c.id)
+         ORDER BY name, id",
+    )?;
+    let customers = stmt
+        .query_map([], |row| {
+            Ok(ReceivableAging {
+                customer_id: row.get(0)?,
+                name: row.get(1)?,
+                phone: row.get(2)?,
+                credit_limit_paise: row.get(3)?,
+                current_paise: 0,
+                days_31_to_60_paise: 0,
+                days_61_to_90_paise: 0,
+                over_90_days_paise: 0,
+                advance_paise: 0,
+                outstanding_paise: 0,
+                oldest_invoice_date: None,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut aging = Vec::new();
+    for mut row in customers {
+        let (invoices, advance) = settle_invoices(conn, row.customer_id)?;
+        for invoice in invoices.iter().filter(|i| i.due_paise > 0) {
+            let bucket = match invoice.age_days {
+                ..=30 => &mut row.current_paise,
+                31..=60 => &mut row.days_31_to_60_paise,
+                61..=90 => &mut row.days_61_to_90_paise,
+                _ => &mut row.over_90_days_paise,
+            };
+            *bucket += invoice.due_paise;
+            row.outstanding_paise += invoice.due_paise;
+            row.oldest_invoice_date
+                .get_or_insert_with(|| invoice.sale_date.clone());
+        }
+        row.advance_paise = advance;
+        row.outstanding_paise -= advance;
+        if row.outstanding_paise != 0 {
+            aging.push(row);
+        }
+    }
+    Ok(aging)
+}
+
+/// The movements on `customer_id`'s account dated `from` to `to`, with the
+/// balance before and after each.
+pub fn customer_ledger(
+    conn: &Connection,
+    customer_id: i64,
+    from: &str,
+    to: &str,
+) -> AppResult<CustomerLedger> {
+    ensure_customer(conn, customer_id)?;
+    validate_date_range(conn, from, to)?;
+    let mut stmt = conn.prepare(
+        "SELECT day, kind, reference, debit, credit FROM (
+             SELECT date(s.sale_date) AS day, s.sale_date AS at, 0 AS kind,
+                 s.invoice_number AS reference, SUM(sp.amount_paise) AS debit, 0 AS credit
+             FROM sales s JOIN sale_payments sp ON sp.sale_id = s.id
+             WHERE s.customer_id = ?1 AND sp.payment_mode = 'credit'
+             GROUP BY s.id
+             UNION ALL
+             SELECT date(r.return_date), r.created_at, 1,
+                 COALESCE(r.credit_note_number, s.invoice_number), 0, r.refund_total_paise
+             FROM sale_returns r JOIN sales s ON s.id = r.sale_id
+             WHERE s.customer_id = ?1 AND r.refund_mode = 'credit'
+               AND EXISTS (SELECT 1 FROM sale_payments sp
+                           WHERE sp.sale_id = s.id AND sp.payment_mode = 'credit')
+             UNION ALL
+             SELECT payment_date, created_at, 2,
+                 COALESCE(NULLIF(reference, ''), payment_mode), 0, amount_paise
+             FROM customer_payments WHERE customer_id = ?1
+         )
+         WHERE day <= ?2
+         ORDER BY day, at, kind",
+    )?;
+    let rows = stmt.query_map(params![customer_id, to], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, i64>(3)?,
+            row.get::<_, i64>(4)?,
+        ))
+    })?;
+    let mut opening_balance_paise = 0;
+    let mut balance = 0;
+    let mut entries = Vec::new();
+    for row in rows {
+        let (date, kind, reference, debit_paise, credit_paise) = row?;
+        balance += debit_paise - credit_paise;
+        if date.as_str() < from {
+            opening_balance_paise = balance;
+            continue;
+        }
+        entries.push(LedgerEntry {
+            date,
+            kind: ["sale", "return", "payment"][kind as usize],
+            reference,
+            debit_paise,
+            credit_paise,
+            balance_paise: balance,
+        });
+    }
+    Ok(CustomerLedger {
+        customer_id,
+        opening_balance_paise,
+        entries,
+        closing_balance_paise: balance,
+    })
+}
+
+/// Record money received from a customer against their credit balance.
+///
+/// Returns the balance after the payment.
+pub fn record_customer_payment(tx: &Tx, payment: &NewCustomerPayment) -> AppResult<Outstanding> {
+    auth::active_role(tx, payment.user_id)?;
+    ensure_customer(tx, payment.customer_id)?;
+    if payment.amount_paise <= 0 {
+        return Err(AppError::validation(
+            "Payment amount must be greater than 0",
+        ));
+    }
+    if payment.payment_mode == PaymentMode::Credit {
+        return Err(AppError::validation(
+            "A payment against credit must be made by cash, card or UPI",
+        ));
+    }
+    let payment_date: String = match &payment.payment_date {
+        Some(date) => {
+            validate_date_range(tx, date, date)?;
+            date.clone()
+        }
+        None => tx.query_row("SELECT date('now')", [], |row| row.get(0))?,
+    };
+
+    let allocations = allocate(tx, payment)?;
+
+    tx.execute(
+        "INSERT INTO customer_payments
+             (customer_id, amount_paise, payment_date, payment_mode, reference, notes, user_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            payment.customer_id,
+            payment.amount_paise,
+            payment_date,
+            payment.payment_mode.as_str(),
+            payment.reference,
+            payment.notes,
+            payment.user_id
+        ],
+    )?;
+    let payment_id = tx.last_insert_rowid();
+    for allocation in &allocations {
+        tx.execute(
+            "INSERT INTO customer_payment_allocations (payment_id, sale_id, amount_paise)
+             VALUES (?1, ?2, ?3)",
+            params![payment_id, allocation.sale_id, allocation.amount_paise],
+        )?;
+    }
+    tx.changed(Entity::CustomerPayment, ChangeOp::Insert, payment_id);
+    sync::enqueue(tx, "record_customer_payment", &json!(payment))?;
+    customer_outstanding(tx, payment.customer_id)
+}
+
+/// The invoices `payment` settles: those it names, checked against what
+/// each still owes, or else the oldest open invoices.
+fn allocate(conn: &Connection, payment: &NewCustomerPayment) -> AppResult<Vec<Allocation>> {
+    let (invoices, _) = settle_invoices(conn, payment.customer_id)?;
+    if payment.allocations.is_empty() {
+        let mut left = payment.amount_paise;
+        return Ok(invoices
+            .iter()
+            .filter(|invoice| invoice.due_paise > 0)
+            .map_while(|invoice| {
+                let amount_paise = left.min(invoice.due_paise);
+                left -= amount_paise;
+                (amount_paise > 0).then_some(Allocation {
+                    sale_id: invoice.sale_id,
+                    amount_paise,
+                })
+            })
+            .collect());
+    }
+
+    for (i, allocation) in payment.allocations.iter().enumerate() {
+        if payment.allocations[..i]
+            .iter()
+            .any(|a| a.sale_id == allocation.sale_id)
+        {
+            return Err(AppError::validation("An invoice is listed more than once"));
+        }
+        let invoice = invoices
+            .iter()
+            .find(|invoice| invoice.sale_id == allocation.sale_id)
+            .ok_or_else(|| {
+                AppError::validation(format!(
+                    "Sale {} is not a credit invoice of this customer",
+                    allocation.sale_id
+                ))
+            })?;
+        if allocation.amount_paise <= 0 || allocation.amount_paise > invoice.due_paise {
+            return Err(AppError::validation(format!(
+                "Invoice {} has Rs. {} due; allocate between Rs. 0.01 and that",
+                invoice.invoice_number,
+                rupees(invoice.due_paise)
+            )));
+        }
+    }
+    let allocated: i64 = payment.allocations.iter().map(|a| a.amount_paise).sum();
+    if allocated > payment.amount_paise {
+        return Err(AppError::validation(format!(
+            "Rs. {} is allocated from a payment of Rs. {}",
+            rupees(allocated),
+            rupees(payment.amount_paise)
+        )));
+    }
+    Ok(payment.allocations.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{insert_batch, insert_medicine, migrated_db, paid_sale};
+    use crate::db::Db;
+    use crate::sales;
+
+    const ADMIN: i64 = 1;
+
+    fn customer(db: &Db) -> i64 {
+        let conn = db.connect().unwrap();
+        conn.execute("INSERT INTO customers (name) VALUES ('Care Clinic')", [])
+            .unwrap();
+        conn.last_insert_rowid()
+    }
+
+    fn sell(db: &Db, customer_id: i64, batch_id: i64, mode: PaymentMode) {
+        let sale = paid_sale(&db.connect().unwrap(), Some(customer_id), mode, batch_id, 1);
+        db.with_tx(|tx| sales::create_sale(tx, &sale)).unwrap();
+    }
+
+    fn pay(db: &Db, customer_id: i64, amount_paise: i64) -> AppResult<Outstanding> {
+        pay_against(db, customer_id, amount_paise, Vec::new())
+    }
+
+    fn pay_against(
+        db: &Db,
+        customer_id: i64,
+        amount_paise: i64,
+        allocations: Vec<Allocation>,
+    ) -> AppResult<Outstanding> {
+        db.with_tx(|tx| {
+            record_customer_payment(
+                tx,
+                &NewCustomerPayment {
+                    customer_id,
+                    amount_paise,
+                    payment_mode: PaymentMode::Upi,
+                    payment_date: None,
+                    reference: None,
+                    notes: None,
+                    allocations,
+                    user_id: ADMIN,
+                },
+            )
+        })
+    }
+
+    #[test]
+    fn partial_payments_net_against_credit_sales() {
+        let (_dir, db) = migrated_db();
+        let clinic = customer(&db);
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+90 days", 5_000, 10);
+        sell(&db, clinic, batch, PaymentMode::Credit);
+        sell(&db, clinic, batch, PaymentMode::Credit);
+        sell(&db, clinic, batch, PaymentMode::Cash);
+
+        pay(&db, clinic, 3_000).unwrap();
+        let after = pay(&db, clinic, 2_500).unwrap();
+
+        assert_eq!(after.credit_sales_paise, 10_000);
+        assert_eq!(after.payments_paise, 5_500);
+        assert_eq!(after.outstanding_paise, 4_500);
+        assert_eq!(customer_outstanding(&conn, clinic).unwrap(), after);
+    }
+
+    #[test]
+    fn customer_without_credit_owes_nothing() {
+        let (_dir, db) = migrated_db();
+        let clinic = customer(&db);
+        let conn = db.connect().unwrap();
+
+        let outstanding = customer_outstanding(&conn, clinic).unwrap();
+        assert_eq!(outstanding.outstanding_paise, 0);
+        assert!(matches!(
+            customer_outstanding(&conn, clinic + 1),
+            Err(AppError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn zero_and_credit_payments_are_rejected() {
+        let (_dir, db) = migrated_db();
+        let clinic = customer(&db);
+
+        assert!(pay(&db, clinic, 0).is_err());
+        let err = db
+            .with_tx(|tx| {
+                record_customer_payment(
+                    tx,
+                    &NewCustomerPayment {
+                        customer_id: clinic,
+                        amount_paise: 100,
+                        payment_mode: PaymentMode::Credit,
+                        payment_date: Some("2025-02-30".into()),
+                        reference: None,
+                        notes: None,
+                        allocations: Vec::new(),
+                        user_id: ADMIN,
+                    },
+                )
+            })
+            .unwrap_err();
+        assert!(err.to_string().contains("cash, card or UPI"), "{err}");
+    }
+
+    fn credit_sales(conn: &Connection, customer_id: i64) -> Vec<i64> {
+        let mut stmt = conn
+            .prepare("SELECT id FROM sales WHERE customer_id = ?1 ORDER BY id")
+            .unwrap();
+        stmt.query_map(params![customer_id], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    fn backdate(conn: &Connection, sale_id: i64, sale_date: &str) {
+        conn.execute(
+            "UPDATE sales SET sale_date = ?1 WHERE id = ?2",
+            params![sale_date, sale_id],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn receipts_settle_named_invoices_then_the_oldest() {
+        let (_dir, db) = migrated_db();
+        let clinic = customer(&db);
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+90 days", 5_000, 10);
+        sell(&db, clinic, batch, PaymentMode::Credit);
+        sell(&db, clinic, batch, PaymentMode::Credit);
+        let [older, newer] = credit_sales(&conn, clinic)[..] else {
+            panic!("two sales")
+        };
+        conn.execute(
+            "UPDATE sales SET sale_date = datetime('now', '-45 days') WHERE id = ?1",
+            params![older],
+        )
+        .unwrap();
+
+        pay_against(
+            &db,
+            clinic,
+            2_000,
+            vec![Allocation {
+                sale_id: newer,
+                amount_paise: 2_000,
+            }],
+        )
+        .unwrap();
+        let due: Vec<_> = open_invoices(&conn, clinic)
+            .unwrap()
+            .iter()
+            .map(|i| (i.sale_id, i.due_paise))
+            .collect();
+        assert_eq!(due, [(older, 5_000), (newer, 3_000)]);
+        let aging = receivables_aging(&conn).unwrap();
+        assert_eq!(
+            (aging[0].current_paise, aging[0].days_31_to_60_paise),
+            (3_000, 5_000)
+        );
+
+        let err = pay_against(
+            &db,
+            clinic,
+            4_000,
+            vec![Allocation {
+                sale_id: newer,
+                amount_paise: 4_000,
+            }],
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("Rs. 30.00 due"), "{err}");
+
+        pay(&db, clinic, 6_000).unwrap();
+        let due: Vec<_> = open_invoices(&conn, clinic)
+            .unwrap()
+            .iter()
+            .map(|i| (i.sale_id, i.due_paise))
+            .collect();
+        assert_eq!(due, [(newer, 2_000)]);
+
+        pay(&db, clinic, 2_500).unwrap();
+        assert!(open_invoices(&conn, clinic).unwrap().is_empty());
+        let aging = receivables_aging(&conn).unwrap();
+        assert_eq!(
+            (aging[0].advance_paise, aging[0].outstanding_paise),
+            (500, -500)
+        );
+    }
+
+    #[test]
+    fn ledger_runs_a_balance_from_the_opening() {
+        let (_dir, db) = migrated_db();
+        let clinic = customer(&db);
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+90 days", 5_000, 10);
+        sell(&db, clinic, batch, PaymentMode::Credit);
+        sell(&db, clinic, batch, PaymentMode::Credit);
+        sell(&db, clinic, batch, PaymentMode::Cash);
+        let sales = credit_sales(&conn, clinic);
+        backdate(&conn, sales[0], "2025-01-05 10:00:00");
+        backdate(&conn, sales[1], "2025-02-10 11:30:00");
+        backdate(&conn, sales[2], "2025-02-11 09:00:00");
+        db.with_tx(|tx| {
+            record_customer_payment(
+                tx,
+                &NewCustomerPayment {
+                    customer_id: clinic,
+                    amount_paise: 3_000,
+                    payment_mode: PaymentMode::Cash,
+                    payment_date: Some("2025-01-20".into()),
+                    reference: Some("RCPT-1".into()),
+                    notes: None,
+                    allocations: Vec::new(),
+                    user_id: ADMIN,
+                },
+            )
+        })
+        .unwrap();
+
+        let ledger = customer_ledger(&conn, clinic, "2025-02-01", "2025-02-28").unwrap();
+        assert_eq!(ledger.opening_balance_paise, 2_000);
+        assert_eq!(ledger.entries.len(), 1);
+        assert_eq!(ledger.entries[0].kind, "sale");
+        assert_eq!(ledger.entries[0].debit_paise, 5_000);
+        assert_eq!(ledger.closing_balance_paise, 7_000);
+
+        let january = customer_ledger(&conn, clinic, "2025-01-01", "2025-01-31").unwrap();
+        let balances: Vec<_> = january.entries.iter().map(|e| e.balance_paise).collect();
+        assert_eq!(balances, [5_000, 2_000]);
+        assert_eq!(january.entries[1].reference, "RCPT-1");
+    }
+
+    #[test]
+    fn credit_sales_stop_at_the_credit_limit() {
+        let (_dir, db) = migrated_db();
+        let clinic = customer(&db);
+        let conn = db.connect().unwrap();
+        conn.execute(
+            "UPDATE customers SET credit_limit_paise = 8000 WHERE id = ?1",
+            params![clinic],
+        )
+        .unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+90 days", 5_000, 10);
+        sell(&db, clinic, batch, PaymentMode::Credit);
+
+        let sale = paid_sale(&conn, Some(clinic), PaymentMode::Credit, batch, 1);
+        let err = db.with_tx(|tx| sales::create_sale(tx, &sale)).unwrap_err();
+        assert!(err.to_string().contains("limit of Rs. 80.00"), "{err}");
+        let walk_in = paid_sale(&conn, None, PaymentMode::Credit, batch, 1);
+        assert!(db.with_tx(|tx| sales::create_sale(tx, &walk_in)).is_err());
+
+        pay(&db, clinic, 2_000).unwrap();
+        sell(&db, clinic, batch, PaymentMode::Credit);
+    }
+}
@@ -0,0 +1,224 @@
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+use crate::auth::{self, permission, Role};
+use crate::error::AppResult;
+use crate::inventory::{EXPIRED, UNEXPIRED};
+
+/// Everything the home screen needs, in one round-trip.
+///
+/// Sections the caller's role may not see are `None`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DashboardMetrics {
+    pub role: Role,
+    /// Sales rung up today by the calling user.
+    pub my_sales_today: SalesTally,
+    pub business: Option<BusinessMetrics>,
+    pub expiry: Option<ExpiryAlerts>,
+    pub prescriptions: Option<PrescriptionAlerts>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SalesTally {
+    pub count: i64,
+    pub total_paise: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BusinessMetrics {
+    pub today: SalesTally,
+    pub month_to_date: SalesTally,
+    /// Taxable value minus batch cost for sales this month.
+    pub month_gross_profit_paise: i64,
+    /// Active medicines whose sellable stock is below their reorder level.
+    pub low_stock_count: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpiryAlerts {
+    /// In-stock batches expiring within `near_expiry_days`.
+    pub near_expiry_count: i64,
+    /// Batches past expiry that still hold quantity.
+    pub expired_with_stock_count: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrescriptionAlerts {
+    /// Prescriptions recorded but not yet dispensed against a sale.
+    pub pending_count: i64,
+    pub received_today: i64,
+}
+
+/// Build the role-appropriate dashboard for `user_id`.
+///
+/// Held bills are not tracked by the backend yet, so the cashier view is
+/// limited to their own sales for the day.
+pub fn dashboard_metrics(conn: &Connection, user_id: i64) -> AppResult<DashboardMetrics> {
+    let role = auth::active_role(conn, user_id)?;
+
+    let my_sales_today = conn.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(grand_total_paise), 0)
+         FROM sales
+         WHERE user_id = ?1 AND date(sale_date) = date('now')",
+        params![user_id],
+        |row| {
+            Ok(SalesTally {
+                count: row.get(0)?,
+                total_paise: row.get(1)?,
+            })
+        },
+    )?;
+
+    let business = if auth::role_has_permission(conn, role, permission::DASHBOARD_FINANCIALS)? {
+        Some(business_metrics(conn)?)
+    } else {
+        None
+    };
+    let (expiry, prescriptions) =
+        if auth::role_has_permission(conn, role, permission::DASHBOARD_STOCK)? {
+            (Some(expiry_alerts(conn)?), Some(prescription_alerts(conn)?))
+        } else {
+            (None, None)
+        };
+
+    Ok(DashboardMetrics {
+        role,
+        my_sales_today,
+        business,
+        expiry,
+        prescriptions,
+    })
+}
+
+fn sales_tally(conn: &Connection, period_filter: &str) -> AppResult<SalesTally> {
+    let sql = format!(
+        "SELECT COUNT(*), COALESCE(SUM(grand_total_paise), 0) FROM sales WHERE {period_filter}"
+    );
+    Ok(conn.query_row(&sql, [], |row| {
+        Ok(SalesTally {
+            count: row.get(0)?,
+            total_paise: row.get(1)?,
+        })
+    })?)
+}
+
+fn business_metrics(conn: &Connection) -> AppResult<BusinessMetrics> {
+    let today = sales_tally(conn, "date(sale_date) = date('now')")?;
+    let month_to_date = sales_tally(
+        conn,
+        "strftime('%Y-%m', sale_date) = strftime('%Y-%m', 'now')",
+    )?;
+
+    let month_gross_profit_paise = conn.query_row(
+        "SELECT COALESCE(SUM(si.taxable_amount_paise - si.quantity * b.cost_price_paise), 0)
+         FROM sale_items si
+         JOIN sales s ON si.sale_id = s.id
+         JOIN batches b ON si.batch_id = b.id
+         WHERE strftime('%Y-%m', s.sale_date) = strftime('%Y-%m', 'now')",
+        [],
+        |row| row.get(0),
+    )?;
+
+    let low_stock_count = conn.query_row(
+        &format!(
+            "SELECT COUNT(*) FROM (
+                 SELECT m.id
+                 FROM medicines m
+                 LEFT JOIN batches b ON m.id = b.medicine_id
+                     AND {UNEXPIRED} AND b.quantity > 0 AND b.status = 'available'
+                 WHERE m.is_active = 1
+                 GROUP BY m.id
+                 HAVING COALESCE(SUM(b.quantity), 0) < m.reorder_level
+             )"
+        ),
+        [],
+        |row| row.get(0),
+    )?;
+
+    Ok(BusinessMetrics {
+        today,
+        month_to_date,
+        month_gross_profit_paise,
+        low_stock_count,
+    })
+}
+
+fn expiry_alerts(conn: &Connection) -> AppResult<ExpiryAlerts> {
+    Ok(conn.query_row(
+        &format!(
+            "SELECT
+                 COALESCE(SUM({UNEXPIRED}
+                     AND julianday(expiry_date) - julianday('now')
+                         <= (SELECT near_expiry_days FROM pharmacy_settings WHERE id = 1)), 0),
+                 COALESCE(SUM({EXPIRED}), 0)
+             FROM batches
+             WHERE quantity > 0"
+        ),
+        [],
+        |row| {
+            Ok(ExpiryAlerts {
+                near_expiry_count: row.get(0)?,
+                expired_with_stock_count: row.get(1)?,
+            })
+        },
+    )?)
+}
+
+fn prescription_alerts(conn: &Connection) -> AppResult<PrescriptionAlerts> {
+    Ok(conn.query_row(
+        "SELECT
+             COALESCE(SUM(sale_id IS NULL), 0),
+             COALESCE(SUM(date(created_at) = date('now')), 0)
+         FROM prescriptions",
+        [],
+        |row| {
+            Ok(PrescriptionAlerts {
+                pending_count: row.get(0)?,
+                received_today: row.get(1)?,
+            })
+        },
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::migrated_db;
+
+    fn add_user(conn: &Connection, username: &str, role: &str, active: bool) -> i64 {
+        conn.execute(
+            "INSERT INTO users (username, password_hash, full_name, role, is_active)
+             VALUES (?1, 'x', ?1, ?2, ?3)",
+            params![username, role, active],
+        )
+        .unwrap();
+        conn.last_insert_rowid()
+    }
+
+    #[test]
+    fn cashier_does_not_see_financial_or_stock_sections() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let cashier = add_user(&conn, "till1", "cashier", true);
+
+        let metrics = dashboard_metrics(&conn, cashier).unwrap();
+        assert_eq!(metrics.role, Role::Cashier);
+        assert!(metrics.business.is_none());
+        assert!(metrics.expiry.is_none());
+        assert!(metrics.prescriptions.is_none());
+    }
+
+    #[test]
+    fn inactive_user_is_rejected() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let former = add_user(&conn, "former", "admin", false);
+
+        assert!(dashboard_metrics(&conn, former).is_err());
+    }
+}
@@ -0,0 +1,215 @@
+//! The day book: every receipt and payment of one day, by mode, with the
+//! cash in hand carried from the day before.
+//!
+//! Cash in hand starts at nothing on the first day the books were kept;
+//! each day opens with the previous day's closing cash.
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+use crate::error::AppResult;
+use crate::reports::{validate_date_range, PaymentModeTotals};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DayBookKind {
+    /// A payment taken on a sale; credit sales are listed in credit mode.
+    Sale,
+    /// Money received from a customer against their credit.
+    CustomerReceipt,
+    /// A refund on a sale return.
+    SaleReturn,
+    SupplierPayment,
+    Expense,
+}
+
+impl DayBookKind {
+    const ALL: [Self; 5] = [
+        Self::Sale,
+        Self::CustomerReceipt,
+        Self::SaleReturn,
+        Self::SupplierPayment,
+        Self::Expense,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Sale => "Sale",
+            Self::CustomerReceipt => "Customer receipt",
+            Self::SaleReturn => "Sale return",
+            Self::SupplierPayment => "Supplier payment",
+            Self::Expense => "Expense",
+        }
+    }
+}
+
+/// One receipt or payment, in one mode.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DayBookEntry {
+    pub kind: DayBookKind,
+    /// Invoice, credit note or payment reference, or an expense's category.
+    pub reference: Option<String>,
+    /// Customer, supplier or whoever an expense was paid to; none for
+    /// walk-in sales.
+    pub party: Option<String>,
+    /// `cash`, `card`, `upi` or `credit`.
+    pub mode: String,
+    pub receipt_paise: i64,
+    pub payment_paise: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DayBook {
+    pub date: String,
+    pub opening_cash_paise: i64,
+    /// In the order they were recorded.
+    pub entries: Vec<DayBookEntry>,
+    pub receipts: PaymentModeTotals,
+    pub payments: PaymentModeTotals,
+    /// Opening cash plus cash received less cash paid out.
+    pub closing_cash_paise: i64,
+}
+
+/// Every receipt and payment: `day`, the time it was recorded, its kind as
+/// an index into [`DayBookKind::ALL`], reference, party, mode and amounts.
+const BOOK: &str = "
+    WITH book (day, at, kind, reference, party, mode, receipt, payment) AS (
+        SELECT date(s.sale_date), s.sale_date, 0, s.invoice_number, c.name,
+            sp.payment_mode, sp.amount_paise, 0
+        FROM sale_payments sp
+        JOIN sales s ON s.id = sp.sale_id
+        LEFT JOIN customers c ON c.id = s.customer_id
+        UNION ALL
+        SELECT date(p.payment_date), p.created_at, 1, NULLIF(p.reference, ''), c.name,
+            p.payment_mode, p.amount_paise, 0
+        FROM customer_payments p
+        JOIN customers c ON c.id = p.customer_id
+        UNION ALL
+        SELECT date(r.return_date), r.created_at, 2,
+            COALESCE(r.credit_note_number, s.invoice_number), c.name,
+            r.refund_mode, 0, r.refund_total_paise
+        FROM sale_returns r
+        JOIN sales s ON s.id = r.sale_id
+        LEFT JOIN customers c ON c.id = s.customer_id
+        UNION ALL
+        SELECT date(p.payment_date), p.created_at, 3, NULLIF(p.reference, ''), sup.name,
+            p.payment_mode, 0, p.amount_paise
+        FROM supplier_payments p
+        JOIN suppliers sup ON sup.id = p.supplier_id
+        UNION ALL
+        SELECT e.expense_date, e.created_at, 4, c.name, NULLIF(e.paid_to, ''),
+            e.payment_mode, 0, e.amount_paise
+        FROM expenses e
+        JOIN expense_categories c ON c.id = e.category_id
+    )";
+
+/// The day book for `date`.
+pub fn day_book(conn: &Connection, date: &str) -> AppResult<DayBook> {
+    validate_date_range(conn, date, date)?;
+    let opening_cash_paise: i64 = conn.query_row(
+        &format!(
+            "{BOOK} SELECT COALESCE(SUM(receipt - payment), 0) FROM book
+             WHERE mode = 'cash' AND day < ?1"
+        ),
+        params![date],
+        |row| row.get(0),
+    )?;
+
+    let mut stmt = conn.prepare(&format!(
+        "{BOOK} SELECT kind, reference, party, mode, receipt, payment FROM book
+         WHERE day = ?1
+         ORDER BY at, kind"
+    ))?;
+    let entries = stmt
+        .query_map(params![date], |row| {
+            Ok(DayBookEntry {
+                kind: DayBookKind::ALL[row.get::<_, usize>(0)?],
+                reference: row.get(1)?,
+                party: row.get(2)?,
+                mode: row.get(3)?,
+                receipt_paise: row.get(4)?,
+                payment_paise: row.get(5)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut receipts = PaymentModeTotals::default();
+    let mut payments = PaymentModeTotals::default();
+    for entry in &entries {
+        receipts.add(&entry.mode, entry.receipt_paise);
+        payments.add(&entry.mode, entry.payment_paise);
+    }
+    Ok(DayBook {
+        date: date.to_owned(),
+        opening_cash_paise,
+        closing_cash_paise: opening_cash_paise + receipts.cash_paise - payments.cash_paise,
+        entries,
+        receipts,
+        payments,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{insert_batch, insert_medicine, migrated_db, paid_sale};
+    use crate::sales::{create_sale, PaymentMode};
+
+    #[test]
+    fn day_book_carries_cash_from_earlier_days_and_splits_modes() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+90 days", 5_000, 10);
+        for mode in [PaymentMode::Cash, PaymentMode::Cash, PaymentMode::Upi] {
+            let sale = paid_sale(&conn, None, mode, batch, 1);
+            db.with_tx(|tx| create_sale(tx, &sale)).unwrap();
+        }
+        conn.execute(
+            "UPDATE sales SET sale_date = '2026-03-01 10:00:00' WHERE id = 1",
+            [],
+        )
+        .unwrap();
+        conn.execute("INSERT INTO suppliers (name) VALUES ('Medline')", [])
+            .unwrap();
+        conn.execute(
+            "INSERT INTO supplier_payments (supplier_id, amount_paise, payment_date, payment_mode)
+             VALUES (1, 1500, date('now'), 'cash')",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO expenses (category_id, amount_paise, expense_date, payment_mode, user_id)
+             SELECT id, 1000, date('now'), 'cash', 1 FROM expense_categories
+             WHERE name = 'Electricity'",
+            [],
+        )
+        .unwrap();
+        let today: String = conn
+            .query_row("SELECT date('now')", [], |r| r.get(0))
+            .unwrap();
+
+        let book = day_book(&conn, &today).unwrap();
+
+        assert_eq!(book.opening_cash_paise, 5_000);
+        let kinds: Vec<_> = book.entries.iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                DayBookKind::Sale,
+                DayBookKind::Sale,
+                DayBookKind::SupplierPayment,
+                DayBookKind::Expense
+            ]
+        );
+        assert_eq!(
+            (book.receipts.cash_paise, book.receipts.upi_paise),
+            (5_000, 5_000)
+        );
+        assert_eq!(book.entries[3].reference.as_deref(), Some("Electricity"));
+        assert_eq!(book.payments.cash_paise, 2_500);
+        assert_eq!(book.closing_cash_paise, 7_500);
+    }
+}
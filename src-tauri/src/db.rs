@@ -0,0 +1,469 @@
+use std::cell::RefCell;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::{PoisonError, RwLock};
+use std::time::Duration;
+
+use rusqlite::{Connection, Transaction, TransactionBehavior};
+use serde::Serialize;
+use sqlx::migrate::Migrator;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool};
+
+use crate::encryption;
+use crate::error::AppResult;
+use crate::migrations;
+
+/// Connection string the SQL plugin knows the database by; relative to the
+/// app config dir.
+pub const DB_URL: &str = "sqlite:pharmacare.db";
+
+/// How long a connection waits on a locked database before giving up.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Name of the Tauri event emitted for every committed [`DataChanged`].
+pub const DATA_CHANGED_EVENT: &str = "data-changed";
+
+/// Table-level entity a change applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Entity {
+    Alert,
+    Batch,
+    Customer,
+    CustomerPayment,
+    DebitNote,
+    Expense,
+    ExpenseCategory,
+    Location,
+    Medicine,
+    Prescription,
+    Purchase,
+    PurchaseOrder,
+    Sale,
+    Shift,
+    StockTake,
+    Supplier,
+    SupplierPayment,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeOp {
+    Insert,
+    Update,
+    Delete,
+}
+
+/// Payload of the `data-changed` event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DataChanged {
+    pub entity: Entity,
+    pub op: ChangeOp,
+    pub id: i64,
+}
+
+type Notifier = Box<dyn Fn(&DataChanged) + Send + Sync>;
+
+/// Handle to the pharmacy database for backend commands.
+///
+/// The SQL plugin owns migrations and the frontend's connection pool; this
+/// opens short-lived connections to the same file so commands that must be
+/// atomic can run inside a single SQLite transaction.
+pub struct Db {
+    path: PathBuf,
+    /// SQLCipher passphrase, once the database has been unlocked.
+    key: RwLock<Option<String>>,
+    notifier: Option<Notifier>,
+}
+
+/// A write transaction that also collects the changes it makes.
+///
+/// Derefs to [`Transaction`], so it can be passed wherever a `&Connection`
+/// is expected. Mutating code calls [`Tx::changed`] for each affected row;
+/// the changes are broadcast only once the transaction commits.
+pub struct Tx<'conn> {
+    tx: Transaction<'conn>,
+    changes: RefCell<Vec<DataChanged>>,
+}
+
+impl<'conn> Deref for Tx<'conn> {
+    type Target = Transaction<'conn>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.tx
+    }
+}
+
+impl Tx<'_> {
+    pub fn changed(&self, entity: Entity, op: ChangeOp, id: i64) {
+        self.changes
+            .borrow_mut()
+            .push(DataChanged { entity, op, id });
+    }
+}
+
+impl Db {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            key: RwLock::new(None),
+            notifier: None,
+        }
+    }
+
+    /// Set the passphrase new connections are keyed with.
+    pub fn set_key(&self, key: Option<String>) {
+        *self.key.write().unwrap_or_else(PoisonError::into_inner) = key;
+    }
+
+    /// Whether a passphrase has been set since startup.
+    pub fn is_unlocked(&self) -> bool {
+        self.key().is_some()
+    }
+
+    fn key(&self) -> Option<String> {
+        self.key
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Register the callback that receives committed changes.
+    pub fn on_change(mut self, f: impl Fn(&DataChanged) + Send + Sync + 'static) -> Self {
+        self.notifier = Some(Box::new(f));
+        self
+    }
+
+    /// The database file commands connect to.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Open a connection with the per-connection pragmas applied.
+    ///
+    /// `foreign_keys` is connection-scoped and off by default in SQLite, so
+    /// it has to be set here on every connection, not once in a migration.
+    /// The same goes for the SQLCipher key, which must come first.
+    pub fn connect(&self) -> AppResult<Connection> {
+        let conn = Connection::open(&self.path)?;
+        if let Some(key) = self.key() {
+            encryption::apply_key(&conn, &key)?;
+        }
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.pragma_update(None, "foreign_keys", "ON")?;
+        Ok(conn)
+    }
+
+    /// Open a pool on the same file for the SQL plugin, keyed like
+    /// [`Db::connect`] and migrated to the latest version.
+    ///
+    /// The plugin builds its pools from a bare connection string, which
+    /// cannot carry a SQLCipher key; for an encrypted database the backend
+    /// opens the pool and hands it to the plugin instead.
+    pub async fn open_pool(&self) -> AppResult<SqlitePool> {
+        let mut options = SqliteConnectOptions::new()
+            .filename(&self.path)
+            .busy_timeout(BUSY_TIMEOUT)
+            .foreign_keys(true)
+            // As the plugin creates it; migration 1 cannot switch to WAL
+            // from inside its transaction.
+            .journal_mode(SqliteJournalMode::Wal);
+        if let Some(key) = self.key() {
+            // sqlx writes pragma values into the statement as is.
+            options = options.pragma("key", format!("'{}'", key.replace('\'', "''")));
+        }
+        let pool = SqlitePool::connect_with(options).await?;
+        Migrator::new(migrations::Source).await?.run(&pool).await?;
+        Ok(pool)
+    }
+
+    /// Run a read-only closure against a fresh connection.
+    pub fn with_conn<T>(&self, f: impl FnOnce(&Connection) -> AppResult<T>) -> AppResult<T> {
+        let conn = self.connect()?;
+        f(&conn)
+    }
+
+    /// Run a closure inside an `IMMEDIATE` transaction.
+    ///
+    /// The write lock is taken up front so concurrent writers queue on the
+    /// busy timeout instead of failing mid-transaction. The transaction is
+    /// committed only if the closure returns `Ok`; any error rolls it back
+    /// and discards the recorded changes. After a successful commit every
+    /// change is passed to the notifier, in the order it was recorded.
+    pub fn with_tx<T>(&self, f: impl FnOnce(&Tx<'_>) -> AppResult<T>) -> AppResult<T> {
+        let mut conn = self.connect()?;
+        let tx = Tx {
+            tx: conn.transaction_with_behavior(TransactionBehavior::Immediate)?,
+            changes: RefCell::new(Vec::new()),
+        };
+        let value = f(&tx)?;
+        let Tx { tx, changes } = tx;
+        tx.commit()?;
+
+        if let Some(notify) = &self.notifier {
+            for change in changes.into_inner() {
+                notify(&change);
+            }
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test_support {
+    use rusqlite::{params, Connection};
+
+    use super::Db;
+    use crate::gst;
+    use crate::sales::{NewSale, NewSaleItem, Payment, PaymentMode};
+
+    /// A fresh database file with every migration applied.
+    ///
+    /// Keep the returned `TempDir` alive for as long as the `Db` is used.
+    pub fn migrated_db() -> (tempfile::TempDir, Db) {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        let db = Db::new(dir.path().join("test.db"));
+        let conn = db.connect().expect("open test db");
+        for migration in crate::migrations::all() {
+            conn.execute_batch(migration.sql)
+                .unwrap_or_else(|e| panic!("migration {} failed: {e}", migration.version));
+        }
+        (dir, db)
+    }
+
+    /// Insert an active medicine on the slab with the given GST rate.
+    pub fn insert_medicine(conn: &Connection, name: &str, gst_rate: f64) -> i64 {
+        conn.execute(
+            "INSERT INTO medicines (name, gst_slab_id)
+             SELECT ?1, id FROM gst_slabs WHERE rate = ?2",
+            params![name, gst_rate],
+        )
+        .expect("insert medicine");
+        conn.last_insert_rowid()
+    }
+
+    /// Insert an available batch; `expiry` is a `date()` modifier such as `'+1 year'`.
+    ///
+    /// The opening stock is recorded as a purchase, as the batch form does.
+    pub fn insert_batch(
+        conn: &Connection,
+        medicine_id: i64,
+        expiry: &str,
+        price_paise: i64,
+        quantity: i64,
+    ) -> i64 {
+        conn.execute(
+            "INSERT INTO batches (medicine_id, batch_number, expiry_date, cost_price_paise,
+                 mrp_paise, selling_price_paise, quantity)
+             VALUES (?1, 'B' || (SELECT COUNT(*) + 1 FROM batches), date('now', ?2),
+                 ?3 * 7 / 10, ?3, ?3, ?4)",
+            params![medicine_id, expiry, price_paise, quantity],
+        )
+        .expect("insert batch");
+        let batch_id = conn.last_insert_rowid();
+        conn.execute(
+            "INSERT INTO stock_movements (batch_id, medicine_id, change_qty, reason)
+             VALUES (?1, ?2, ?3, 'purchase')",
+            params![batch_id, medicine_id, quantity],
+        )
+        .expect("record opening stock");
+        batch_id
+    }
+
+    /// An active cashier, who holds none of the back-office permissions.
+    pub fn insert_cashier(conn: &Connection) -> i64 {
+        conn.execute(
+            "INSERT INTO users (username, password_hash, full_name, role)
+             VALUES ('till' || (SELECT COUNT(*) + 1 FROM users), 'x', 'Till', 'cashier')",
+            [],
+        )
+        .expect("insert cashier");
+        conn.last_insert_rowid()
+    }
+
+    /// Record `version` as applied the way the SQL plugin does.
+    pub fn mark_migrated(conn: &Connection, version: i64) {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS _sqlx_migrations (
+                 version BIGINT PRIMARY KEY, success BOOLEAN NOT NULL)",
+        )
+        .expect("create migration journal");
+        conn.execute(
+            "INSERT INTO _sqlx_migrations (version, success) VALUES (?1, 1)",
+            [version],
+        )
+        .expect("record migration");
+    }
+
+    /// A sale of `quantity` from one batch, paid in full in `mode` by user 1.
+    pub fn paid_sale(
+        conn: &Connection,
+        customer_id: Option<i64>,
+        mode: PaymentMode,
+        batch_id: i64,
+        quantity: i64,
+    ) -> NewSale {
+        let (price, rate): (i64, f64) = conn
+            .query_row(
+                "SELECT b.selling_price_paise, g.rate
+                 FROM batches b
+                 JOIN medicines m ON m.id = b.medicine_id
+                 JOIN gst_slabs g ON g.id = m.gst_slab_id
+                 WHERE b.id = ?1",
+                params![batch_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .expect("batch exists");
+        let line = gst::calculate_line(price, quantity, rate, 0, false);
+        NewSale {
+            customer_id,
+            user_id: 1,
+            payments: vec![Payment {
+                mode,
+                amount_paise: gst::invoice_totals([&line]).grand_total_paise,
+            }],
+            notes: None,
+            prescription_id: None,
+            branch_id: None,
+            idempotency_key: None,
+            items: vec![NewSaleItem {
+                batch_id,
+                quantity,
+                unit: None,
+                discount_paise: 0,
+            }],
+        }
+    }
+
+    pub fn batch_quantity(conn: &Connection, batch_id: i64) -> i64 {
+        conn.query_row(
+            "SELECT quantity FROM batches WHERE id = ?1",
+            params![batch_id],
+            |row| row.get(0),
+        )
+        .expect("batch exists")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::error::AppError;
+
+    fn recording_db() -> (tempfile::TempDir, Db, Arc<Mutex<Vec<DataChanged>>>) {
+        let (dir, db) = test_support::migrated_db();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        let db = db.on_change(move |c| sink.lock().unwrap().push(c.clone()));
+        (dir, db, seen)
+    }
+
+    #[test]
+    fn changes_are_emitted_after_commit() {
+        let (_dir, db, seen) = recording_db();
+        db.with_tx(|tx| {
+            tx.changed(Entity::Batch, ChangeOp::Update, 7);
+            assert!(seen.lock().unwrap().is_empty());
+            Ok(())
+        })
+        .unwrap();
+
+        let expected = DataChanged {
+            entity: Entity::Batch,
+            op: ChangeOp::Update,
+            id: 7,
+        };
+        assert_eq!(*seen.lock().unwrap(), vec![expected]);
+    }
+
+    #[test]
+    fn rolled_back_changes_are_not_emitted() {
+        let (_dir, db, seen) = recording_db();
+        let result: AppResult<()> = db.with_tx(|tx| {
+            tx.changed(Entity::Batch, ChangeOp::Update, 7);
+            Err(AppError::validation("nope"))
+        });
+
+        assert!(result.is_err());
+        assert!(seen.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn plugin_pool_applies_every_migration() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Db::new(dir.path().join("fresh.db"));
+        db.connect().unwrap();
+
+        let pool = tauri::async_runtime::block_on(db.open_pool()).unwrap();
+        tauri::async_runtime::block_on(pool.close());
+
+        let conn = db.connect().unwrap();
+        assert_eq!(
+            migrations::applied_version(&conn).unwrap(),
+            Some(migrations::latest_version())
+        );
+    }
+
+    #[test]
+    fn every_connection_enforces_foreign_keys() {
+        let (_dir, db) = test_support::migrated_db();
+        // The pragma in migration 1 only applied to the connection that ran it.
+        let conn = db.connect().unwrap();
+
+        let err = conn
+            .execute(
+                "INSERT INTO batches (medicine_id, batch_number, expiry_date, cost_price_paise,
+                     mrp_paise, selling_price_paise, quantity)
+                 VALUES (9999, 'ORPHAN', date('now', '+30 days'), 700, 1000, 1000, 5)",
+                [],
+            )
+            .unwrap_err();
+
+        assert_eq!(
+            err.sqlite_error_code(),
+            Some(rusqlite::ErrorCode::ConstraintViolation),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn updating_a_medicine_advances_updated_at_only() {
+        let (_dir, db) = test_support::migrated_db();
+        let conn = db.connect().unwrap();
+        let id = test_support::insert_medicine(&conn, "Paracetamol", 12.0);
+        let stamps = || -> (String, String) {
+            conn.query_row(
+                "SELECT created_at, updated_at FROM medicines WHERE id = ?1",
+                [id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap()
+        };
+        conn.execute(
+            "UPDATE medicines SET created_at = '2020-01-01 00:00:00',
+                 updated_at = '2020-01-01 00:00:00'
+             WHERE id = ?1",
+            [id],
+        )
+        .unwrap();
+        assert_eq!(stamps().1, "2020-01-01 00:00:00");
+
+        conn.execute(
+            "UPDATE medicines SET reorder_level = 20 WHERE id = ?1",
+            [id],
+        )
+        .unwrap();
+        assert_eq!(stamps().1, "2020-01-01 00:00:00", "unchanged row");
+
+        conn.execute(
+            "UPDATE medicines SET reorder_level = 35 WHERE id = ?1",
+            [id],
+        )
+        .unwrap();
+        let (created, updated) = stamps();
+        assert_eq!(created, "2020-01-01 00:00:00");
+        assert!(updated > created, "{updated}");
+    }
+}
@@ -0,0 +1,96 @@
+//! Sales demand statistics shared by reorder tuning and stock analytics.
+
+use rusqlite::{params, Connection};
+
+use crate::error::{AppError, AppResult};
+
+/// Longest window demand is computed over.
+const MAX_LOOKBACK_DAYS: i64 = 730;
+
+/// Units sold per day for one medicine over a lookback window.
+///
+/// Days without a sale count as zero demand, so `mean_daily` and
+/// `std_dev_daily` describe the whole window, not just selling days.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyDemand {
+    pub medicine_id: i64,
+    pub units_sold: i64,
+    pub mean_daily: f64,
+    pub std_dev_daily: f64,
+}
+
+pub fn validate_lookback(lookback_days: i64) -> AppResult<()> {
+    if (1..=MAX_LOOKBACK_DAYS).contains(&lookback_days) {
+        Ok(())
+    } else {
+        Err(AppError::validation(format!(
+            "Lookback must be between 1 and {MAX_LOOKBACK_DAYS} days"
+        )))
+    }
+}
+
+/// Demand for every medicine sold in the last `lookback_days` days, today included.
+///
+/// Refunded sales are excluded. Medicines with no sales in the window are
+/// not returned.
+pub fn daily_demand(conn: &Connection, lookback_days: i64) -> AppResult<Vec<DailyDemand>> {
+    validate_lookback(lookback_days)?;
+    let mut stmt = conn.prepare(
+        "SELECT medicine_id, SUM(units), SUM(units * units)
+         FROM (
+             SELECT si.medicine_id, date(s.sale_date) AS day, SUM(si.quantity) AS units
+             FROM sale_items si
+             JOIN sales s ON si.sale_id = s.id
+             WHERE date(s.sale_date) > date('now', printf('-%d days', ?1))
+               AND s.status != 'refunded'
+             GROUP BY si.medicine_id, day
+         )
+         GROUP BY medicine_id
+         ORDER BY medicine_id",
+    )?;
+    let days = lookback_days as f64;
+    let rows = stmt.query_map(params![lookback_days], |row| {
+        let units_sold: i64 = row.get(1)?;
+        let sum_sq: i64 = row.get(2)?;
+        let mean = units_sold as f64 / days;
+        let variance = (sum_sq as f64 / days - mean * mean).max(0.0);
+        Ok(DailyDemand {
+            medicine_id: row.get(0)?,
+            units_sold,
+            mean_daily: mean,
+            std_dev_daily: variance.sqrt(),
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{insert_medicine, migrated_db};
+
+    #[test]
+    fn zero_days_are_part_of_the_window() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        conn.execute_batch(&format!(
+            "INSERT INTO batches (medicine_id, batch_number, expiry_date, cost_price_paise,
+                 mrp_paise, selling_price_paise, quantity)
+             VALUES ({para}, 'B1', '2099-01-01', 100, 100, 100, 0);
+             INSERT INTO sales (invoice_number, user_id, sale_date)
+             VALUES ('A', 1, datetime('now')), ('B', 1, datetime('now', '-1 day'));
+             INSERT INTO sale_items (sale_id, batch_id, medicine_id, quantity,
+                 unit_price_paise, taxable_amount_paise, total_paise)
+             VALUES (1, 1, {para}, 6, 100, 90, 100), (2, 1, {para}, 2, 100, 90, 100);"
+        ))
+        .unwrap();
+
+        let demand = daily_demand(&conn, 4).unwrap();
+        assert_eq!(demand.len(), 1);
+        // Days: 6, 2, 0, 0 → mean 2, variance (36 + 4) / 4 - 4 = 6
+        assert_eq!(demand[0].units_sold, 8);
+        assert_eq!(demand[0].mean_daily, 2.0);
+        assert!((demand[0].std_dev_daily - 6f64.sqrt()).abs() < 1e-9);
+    }
+}
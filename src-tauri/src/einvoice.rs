@@ -0,0 +1,907 @@
+//! GST e-invoice payload (schema version 1.1) for submission to an IRP.
+//!
+//! Sale lines are stored GST-inclusive in paise; the schema wants
+//! tax-exclusive item prices in rupees, so amounts are converted here.
+//! Every mandatory field is checked up front and all problems are reported
+//! together, so the user can fix settings and customer records in one pass.
+//!
+//! The payload is registered with the IRP through a GSP's API, using the
+//! credentials in settings. The IRN and signed QR code it returns are kept
+//! in `sale_einvoices` and printed on the invoice; a registered sale can no
+//! longer be amended.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::auth::{self, permission};
+use crate::db::{ChangeOp, Entity, Tx};
+use crate::error::{AppError, AppResult};
+use crate::{gst, http, sync};
+
+const SCHEMA_VERSION: &str = "1.1";
+
+/// GST rates the IRP accepts on an item.
+const VALID_GST_RATES: &[f64] = &[
+    0.0, 0.1, 0.25, 1.0, 1.5, 3.0, 5.0, 6.0, 7.5, 12.0, 18.0, 28.0,
+];
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct EInvoice {
+    pub version: &'static str,
+    pub tran_dtls: TranDtls,
+    pub doc_dtls: DocDtls,
+    pub seller_dtls: PartyDtls,
+    pub buyer_dtls: PartyDtls,
+    pub item_list: Vec<ItemDtls>,
+    pub val_dtls: ValDtls,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct TranDtls {
+    pub tax_sch: &'static str,
+    pub sup_typ: &'static str,
+    pub reg_rev: &'static str,
+    pub igst_on_intra: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DocDtls {
+    pub typ: &'static str,
+    pub no: String,
+    /// `dd/mm/yyyy`
+    pub dt: String,
+}
+
+/// Seller or buyer block; `pos` is only set for the buyer.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PartyDtls {
+    pub gstin: String,
+    pub lgl_nm: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pos: Option<String>,
+    pub addr1: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub addr2: Option<String>,
+    pub loc: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pin: Option<u32>,
+    pub stcd: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ph: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub em: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ItemDtls {
+    pub sl_no: String,
+    pub prd_desc: String,
+    pub is_servc: &'static str,
+    pub hsn_cd: String,
+    pub qty: i64,
+    pub unit: &'static str,
+    pub unit_price: f64,
+    pub tot_amt: f64,
+    pub discount: f64,
+    pub ass_amt: f64,
+    pub gst_rt: f64,
+    pub igst_amt: f64,
+    pub cgst_amt: f64,
+    pub sgst_amt: f64,
+    pub tot_item_val: f64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ValDtls {
+    pub ass_val: f64,
+    pub cgst_val: f64,
+    pub sgst_val: f64,
+    pub igst_val: f64,
+    pub rnd_off_amt: f64,
+    pub tot_inv_val: f64,
+}
+
+fn rupees(paise: i64) -> f64 {
+    paise as f64 / 100.0
+}
+
+/// `^[0-9]{2}[A-Z]{5}[0-9]{4}[A-Z][1-9A-Z]Z[0-9A-Z]$`, as in the settings form.
+fn is_valid_gstin(gstin: &str) -> bool {
+    let b = gstin.as_bytes();
+    b.len() == 15
+        && b[..2].iter().all(u8::is_ascii_digit)
+        && b[2..7].iter().all(u8::is_ascii_uppercase)
+        && b[7..11].iter().all(u8::is_ascii_digit)
+        && b[11].is_ascii_uppercase()
+        && (b[12].is_ascii_uppercase() || (b'1'..=b'9').contains(&b[12]))
+        && b[13] == b'Z'
+        && (b[14].is_ascii_uppercase() || b[14].is_ascii_digit())
+}
+
+fn parse_pin(pin: &str) -> Option<u32> {
+    if pin.len() == 6 && pin.bytes().all(|c| c.is_ascii_digit()) && !pin.starts_with('0') {
+        pin.parse().ok()
+    } else {
+        None
+    }
+}
+
+fn is_valid_state_code(code: &str) -> bool {
+    code.len() == 2 && code.bytes().all(|c| c.is_ascii_digit())
+}
+
+/// `^[A-Z1-9][A-Z0-9/-]{0,15}$`
+fn is_valid_doc_no(no: &str) -> bool {
+    let b = no.as_bytes();
+    !b.is_empty()
+        && b.len() <= 16
+        && (b[0].is_ascii_uppercase() || (b'1'..=b'9').contains(&b[0]))
+        && b[1..]
+            .iter()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || *c == b'/' || *c == b'-')
+}
+
+/// Flatten a multi-line address into the schema's two 100-character lines.
+fn split_address(address: &str) -> (String, Option<String>) {
+    let flat = address
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let addr1: String = flat.chars().take(100).collect();
+    let addr2: String = flat.chars().skip(100).take(100).collect();
+    let addr2 = addr2.trim();
+    (addr1, (!addr2.is_empty()).then(|| addr2.to_string()))
+}
+
+/// Keep a phone number only if it fits the schema's 6–12 digits.
+fn phone(raw: &str) -> Option<String> {
+    let digits: String = raw.chars().filter(char::is_ascii_digit).collect();
+    (6..=12).contains(&digits.len()).then_some(digits)
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+struct Party {
+    name: String,
+    address: String,
+    city: String,
+    pincode: String,
+    state_code: String,
+    gstin: String,
+    phone: String,
+    email: Option<String>,
+}
+
+/// Check a party's mandatory fields, appending problems to `problems`.
+fn party_dtls(
+    label: &str,
+    party: Party,
+    pin_required: bool,
+    problems: &mut Vec<String>,
+) -> PartyDtls {
+    if !is_valid_gstin(&party.gstin) {
+        problems.push(format!("{label} GSTIN is missing or invalid"));
+    }
+    if party.name.trim().chars().count() < 3 {
+        problems.push(format!("{label} name must be at least 3 characters"));
+    }
+    let (addr1, addr2) = split_address(&party.address);
+    if addr1.is_empty() {
+        problems.push(format!("{label} address is missing"));
+    }
+    if party.city.trim().chars().count() < 3 {
+        problems.push(format!("{label} city must be at least 3 characters"));
+    }
+    let pin = parse_pin(party.pincode.trim());
+    if pin.is_none() && (pin_required || !party.pincode.trim().is_empty()) {
+        problems.push(format!("{label} PIN code must be 6 digits"));
+    }
+    if !is_valid_state_code(&party.state_code) {
+        problems.push(format!("{label} state code must be 2 digits"));
+    }
+
+    PartyDtls {
+        gstin: party.gstin,
+        lgl_nm: party.name.trim().to_string(),
+        pos: None,
+        addr1,
+        addr2,
+        loc: party.city.trim().to_string(),
+        pin,
+        stcd: party.state_code,
+        ph: phone(&party.phone),
+        em: non_empty(party.email),
+    }
+}
+
+/// Build the e-invoice JSON for a B2B sale.
+///
+/// Requires e-invoicing to be enabled in settings and the sale to have a
+/// registered (GSTIN) customer. Supply is inter-state when the buyer's state
+/// differs from the pharmacy's, in which case GST is reported as IGST.
+pub fn generate_einvoice_json(conn: &Connection, sale_id: i64) -> AppResult<EInvoice> {
+    let (enabled, seller) = conn.query_row(
+        "SELECT einvoice_enabled, name, address, city, pincode, state_code, gstin, phone, email
+         FROM pharmacy_settings WHERE id = 1",
+        [],
+        |row| {
+            Ok((
+                row.get::<_, bool>(0)?,
+                Party {
+                    name: row.get(1)?,
+                    address: row.get(2)?,
+                    city: row.get(3)?,
+                    pincode: row.get(4)?,
+                    state_code: row.get(5)?,
+                    gstin: row.get(6)?,
+                    phone: row.get(7)?,
+                    email: row.get(8)?,
+                },
+            ))
+        },
+    )?;
+    if !enabled {
+        return Err(AppError::validation(
+            "E-invoicing is not enabled in settings",
+        ));
+    }
+
+    let (
+        invoice_number,
+        doc_date,
+        status,
+        customer_id,
+        recorded_interstate,
+        round_off_paise,
+        place_of_supply,
+        bill_of_supply,
+    ): (
+        String,
+        String,
+        String,
+        Option<i64>,
+        bool,
+        i64,
+        Option<String>,
+        bool,
+    ) = conn
+        .query_row(
+            "SELECT invoice_number, strftime('%d/%m/%Y', sale_date), status, customer_id,
+                 is_interstate, round_off_paise, place_of_supply, bill_of_supply
+             FROM sales WHERE id = ?1",
+            params![sale_id],
+            |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                    row.get(6)?,
+                    row.get(7)?,
+                ))
+            },
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Sale {sale_id}")))?;
+    if status == "refunded" {
+        return Err(AppError::validation(format!(
+            "Sale {invoice_number} has been refunded"
+        )));
+    }
+    if bill_of_supply {
+        return Err(AppError::validation(format!(
+            "Sale {invoice_number} is a bill of supply under the composition scheme"
+        )));
+    }
+    let customer_id = customer_id.ok_or_else(|| {
+        AppError::validation("E-invoices are only generated for sales to a registered customer")
+    })?;
+
+    let buyer = conn.query_row(
+        "SELECT name, address, city, pincode, state_code, gstin, phone, email
+         FROM customers WHERE id = ?1",
+        params![customer_id],
+        |row| {
+            let gstin = row.get::<_, Option<String>>(5)?.unwrap_or_default();
+            let state_code =
+                non_empty(row.get(4)?).unwrap_or_else(|| gstin.chars().take(2).collect());
+            Ok(Party {
+                name: row.get(0)?,
+                address: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                city: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                pincode: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                state_code,
+                gstin,
+                phone: row.get::<_, Option<String>>(6)?.unwrap_or_default(),
+                email: row.get(7)?,
+            })
+        },
+    )?;
+
+    let mut problems = Vec::new();
+    if !is_valid_doc_no(&invoice_number) {
+        problems.push(format!(
+            "invoice number {invoice_number} must be at most 16 characters of A-Z, 0-9, / or -"
+        ));
+    }
+    let seller_dtls = party_dtls("Pharmacy", seller, true, &mut problems);
+    let mut buyer_dtls = party_dtls("Customer", buyer, false, &mut problems);
+    let pos = place_of_supply.unwrap_or_else(|| buyer_dtls.stcd.clone());
+    // Sales recorded before IGST was stored carry CGST/SGST halves even when
+    // the states differ; those are reported as IGST.
+    let interstate = recorded_interstate || seller_dtls.stcd != pos;
+    buyer_dtls.pos = Some(pos);
+
+    let item_list = {
+        let mut stmt = conn.prepare(
+            "SELECT m.name, si.hsn_code, si.quantity, si.discount_paise, si.taxable_amount_paise,
+                 si.cgst_rate, si.sgst_rate, si.igst_rate, si.cgst_amount_paise,
+                 si.sgst_amount_paise, si.igst_amount_paise, si.total_paise
+             FROM sale_items si JOIN medicines m ON si.medicine_id = m.id
+             WHERE si.sale_id = ?1
+             ORDER BY si.id",
+        )?;
+        let rows = stmt.query_map(params![sale_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, i64>(3)?,
+                row.get::<_, i64>(4)?,
+                row.get::<_, f64>(5)?,
+                row.get::<_, f64>(6)?,
+                row.get::<_, f64>(7)?,
+                row.get::<_, i64>(8)?,
+                row.get::<_, i64>(9)?,
+                row.get::<_, i64>(10)?,
+                row.get::<_, i64>(11)?,
+            ))
+        })?;
+
+        let mut items = Vec::new();
+        for (i, row) in rows.enumerate() {
+            let (
+                name,
+                hsn,
+                qty,
+                discount,
+                taxable,
+                cgst_rate,
+                sgst_rate,
+                igst_rate,
+                cgst,
+                sgst,
+                igst,
+                total,
+            ) = row?;
+            let sl_no = i + 1;
+            let gst_rate = cgst_rate + sgst_rate + igst_rate;
+
+            if !(4..=8).contains(&hsn.len()) || !hsn.bytes().all(|c| c.is_ascii_digit()) {
+                problems.push(format!(
+                    "item {sl_no} ({name}) HSN code must be 4 to 8 digits"
+                ));
+            }
+            if cgst_rate != sgst_rate {
+                problems.push(format!(
+                    "item {sl_no} ({name}) has unequal CGST and SGST rates"
+                ));
+            }
+            if !VALID_GST_RATES.contains(&gst_rate) {
+                problems.push(format!(
+                    "item {sl_no} ({name}) has an invalid GST rate of {gst_rate}%"
+                ));
+            }
+
+            // Discounts are recorded inclusive of GST; the schema wants them pre-tax.
+            let discount_ex = gst::taxable_from_inclusive(discount, gst_rate);
+            let gross = taxable + discount_ex;
+            let (igst, cgst, sgst) = if interstate {
+                (igst + cgst + sgst, 0, 0)
+            } else {
+                (0, cgst, sgst)
+            };
+            items.push(ItemDtls {
+                sl_no: sl_no.to_string(),
+                prd_desc: name,
+                is_servc: "N",
+                hsn_cd: hsn,
+                qty,
+                unit: "NOS",
+                unit_price: (gross as f64 / qty as f64).round() / 100.0,
+                tot_amt: rupees(gross),
+                discount: rupees(discount_ex),
+                ass_amt: rupees(taxable),
+                gst_rt: gst_rate,
+                igst_amt: rupees(igst),
+                cgst_amt: rupees(cgst),
+                sgst_amt: rupees(sgst),
+                tot_item_val: rupees(total),
+            });
+        }
+        items
+    };
+    if item_list.is_empty() {
+        problems.push("the sale has no items".into());
+    }
+
+    if !problems.is_empty() {
+        return Err(AppError::validation(format!(
+            "Cannot generate e-invoice: {}",
+            problems.join("; ")
+        )));
+    }
+
+    let sum_paise = |f: fn(&ItemDtls) -> f64| -> i64 {
+        item_list
+            .iter()
+            .map(|i| (f(i) * 100.0).round() as i64)
+            .sum()
+    };
+    let sum = |f: fn(&ItemDtls) -> f64| rupees(sum_paise(f));
+    let val_dtls = ValDtls {
+        ass_val: sum(|i| i.ass_amt),
+        cgst_val: sum(|i| i.cgst_amt),
+        sgst_val: sum(|i| i.sgst_amt),
+        igst_val: sum(|i| i.igst_amt),
+        rnd_off_amt: rupees(round_off_paise),
+        tot_inv_val: rupees(sum_paise(|i| i.tot_item_val) + round_off_paise),
+    };
+
+    Ok(EInvoice {
+        version: SCHEMA_VERSION,
+        tran_dtls: TranDtls {
+            tax_sch: "GST",
+            sup_typ: "B2B",
+            reg_rev: "N",
+            igst_on_intra: "N",
+        },
+        doc_dtls: DocDtls {
+            typ: "INV",
+            no: invoice_number,
+            dt: doc_date,
+        },
+        seller_dtls,
+        buyer_dtls,
+        item_list,
+        val_dtls,
+    })
+}
+
+/// Login and generate endpoints under the GSP's base URL. These are the
+/// pass-through APIs GSPs offer, which take plain JSON over TLS; calling the
+/// IRP directly needs its RSA/AES payload encryption, which is not supported.
+const AUTH_PATH: &str = "/eivital/v1.04/auth";
+const GENERATE_PATH: &str = "/eicore/v1.03/Invoice";
+
+const API_TIMEOUT_SECS: u32 = 60;
+
+/// The IRP's acknowledgement of a registered invoice.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IrnDetails {
+    pub irn: String,
+    pub ack_no: String,
+    pub ack_date: String,
+    #[serde(skip)]
+    pub signed_invoice: String,
+    /// Signed JWT to print as the invoice's QR code.
+    pub signed_qr_code: String,
+}
+
+struct ApiConfig {
+    url: String,
+    client_id: String,
+    client_secret: String,
+    username: String,
+    password: String,
+    gstin: String,
+}
+
+fn api_config(conn: &Connection) -> AppResult<ApiConfig> {
+    let config = conn.query_row(
+        "SELECT einvoice_api_url, einvoice_client_id, einvoice_client_secret,
+                einvoice_username, einvoice_password, gstin
+         FROM pharmacy_settings WHERE id = 1",
+        [],
+        |row| {
+            Ok(ApiConfig {
+                url: row
+                    .get::<_, String>(0)?
+                    .trim()
+                    .trim_end_matches('/')
+                    .to_string(),
+                client_id: row.get::<_, String>(1)?.trim().to_string(),
+                client_secret: row.get(2)?,
+                username: row.get::<_, String>(3)?.trim().to_string(),
+                password: row.get(4)?,
+                gstin: row.get(5)?,
+            })
+        },
+    )?;
+    if [
+        &config.url,
+        &config.client_id,
+        &config.username,
+        &config.password,
+    ]
+    .iter()
+    .any(|v| v.is_empty())
+    {
+        return Err(AppError::validation(
+            "Set the e-invoice API URL, client id, username and password in settings",
+        ));
+    }
+    Ok(config)
+}
+
+/// The `Data` of a successful API reply. GSPs send it either as an object
+/// or as a JSON string; failures carry `ErrorDetails` instead.
+pub(crate) fn api_data(what: &str, body: &[u8]) -> AppResult<Value> {
+    let unreadable = || {
+        AppError::validation(format!(
+            "{what}: the e-invoice API sent an unreadable reply"
+        ))
+    };
+    let reply: Value = serde_json::from_slice(body).map_err(|_| unreadable())?;
+    let ok = match &reply["Status"] {
+        Value::Number(n) => n.as_i64() == Some(1),
+        Value::String(s) => s == "1",
+        _ => false,
+    };
+    if !ok {
+        let reasons = reply["ErrorDetails"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|e| {
+                format!("{} {}", text(&e["ErrorCode"]), text(&e["ErrorMessage"]))
+                    .trim()
+                    .to_string()
+            })
+            .filter(|e| !e.is_empty())
+            .collect::<Vec<_>>();
+        let reasons = if reasons.is_empty() {
+            "no reason given".to_string()
+        } else {
+            reasons.join("; ")
+        };
+        return Err(AppError::validation(format!(
+            "{what} was rejected: {reasons}"
+        )));
+    }
+    match &reply["Data"] {
+        Value::String(s) => serde_json::from_str(s).map_err(|_| unreadable()),
+        data @ Value::Object(_) => Ok(data.clone()),
+        _ => Err(unreadable()),
+    }
+}
+
+/// A string or number field as text; empty when absent.
+pub(crate) fn text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.trim().to_string(),
+        Value::Number(n) => n.to_string(),
+        _ => String::new(),
+    }
+}
+
+fn required(what: &str, data: &Value, key: &str) -> AppResult<String> {
+    let value = text(&data[key]);
+    if value.is_empty() {
+        return Err(AppError::validation(format!(
+            "{what}: the e-invoice API reply has no {key}"
+        )));
+    }
+    Ok(value)
+}
+
+/// Log in to the GSP with the credentials in settings. Returns its base URL
+/// and the headers that authorise further calls.
+pub(crate) fn login(
+    conn: &Connection,
+    post: &mut http::Post<'_>,
+) -> AppResult<(String, Vec<(&'static str, String)>)> {
+    let config = api_config(conn)?;
+    let mut headers = vec![
+        ("client_id", config.client_id),
+        ("client_secret", config.client_secret),
+        ("Gstin", config.gstin),
+        ("user_name", config.username.clone()),
+    ];
+    let body = json!({ "UserName": config.username, "Password": config.password });
+    let reply = post(
+        &format!("{}{AUTH_PATH}", config.url),
+        &headers,
+        &body.to_string(),
+    )?;
+    let what = "E-invoice login";
+    headers.push((
+        "AuthToken",
+        required(what, &api_data(what, &reply)?, "AuthToken")?,
+    ));
+    Ok((config.url, headers))
+}
+
+/// Register a sale with the IRP through the configured GSP and return the
+/// IRN it issued. Nothing is stored; pass the result to [`record_irn`].
+pub fn request_irn(
+    conn: &Connection,
+    sale_id: i64,
+    user_id: i64,
+    post: &mut http::Post<'_>,
+) -> AppResult<IrnDetails> {
+    auth::require_permission(conn, user_id, permission::SALES_EINVOICE)?;
+    if let Some(irn) = sale_irn(conn, sale_id)? {
+        return Err(AppError::validation(format!(
+            "This sale already has IRN {}",
+            irn.irn
+        )));
+    }
+    let payload = generate_einvoice_json(conn, sale_id)?;
+    let (url, headers) = login(conn, post)?;
+
+    let body = serde_json::to_string(&payload).map_err(AppError::validation)?;
+    let reply = post(&format!("{url}{GENERATE_PATH}"), &headers, &body)?;
+    let what = "IRN generation";
+    let data = api_data(what, &reply)?;
+    Ok(IrnDetails {
+        irn: required(what, &data, "Irn")?,
+        ack_no: required(what, &data, "AckNo")?,
+        ack_date: required(what, &data, "AckDt")?,
+        signed_invoice: required(what, &data, "SignedInvoice")?,
+        signed_qr_code: required(what, &data, "SignedQRCode")?,
+    })
+}
+
+/// Store the IRN the IRP issued for a sale.
+pub fn record_irn(tx: &Tx, sale_id: i64, user_id: i64, irn: &IrnDetails) -> AppResult<()> {
+    tx.execute(
+        "INSERT INTO sale_einvoices
+             (sale_id, irn, ack_no, ack_date, signed_invoice, signed_qr_code, user_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            sale_id,
+            irn.irn,
+            irn.ack_no,
+            irn.ack_date,
+            irn.signed_invoice,
+            irn.signed_qr_code,
+            user_id,
+        ],
+    )?;
+    sync::enqueue(
+        tx,
+        "record_irn",
+        &json!({
+            "saleId": sale_id,
+            "irn": irn.irn,
+            "ackNo": irn.ack_no,
+            "ackDate": irn.ack_date,
+            "signedInvoice": irn.signed_invoice,
+            "signedQrCode": irn.signed_qr_code,
+        }),
+    )?;
+    tx.changed(Entity::Sale, ChangeOp::Update, sale_id);
+    Ok(())
+}
+
+/// POST through the system curl.
+pub fn curl_post(url: &str, headers: &[(&str, String)], body: &str) -> AppResult<Vec<u8>> {
+    http::post_json("E-invoicing", API_TIMEOUT_SECS, url, headers, body)
+}
+
+/// The IRN recorded for a sale, if it has one.
+pub fn sale_irn(conn: &Connection, sale_id: i64) -> AppResult<Option<IrnDetails>> {
+    Ok(conn
+        .query_row(
+            "SELECT irn, ack_no, ack_date, signed_invoice, signed_qr_code
+             FROM sale_einvoices WHERE sale_id = ?1",
+            params![sale_id],
+            |row| {
+                Ok(IrnDetails {
+                    irn: row.get(0)?,
+                    ack_no: row.get(1)?,
+                    ack_date: row.get(2)?,
+                    signed_invoice: row.get(3)?,
+                    signed_qr_code: row.get(4)?,
+                })
+            },
+        )
+        .optional()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{insert_batch, insert_medicine, migrated_db, paid_sale};
+    use crate::db::Db;
+    use crate::sales::{self, PaymentMode};
+
+    fn configure_seller(conn: &Connection) {
+        conn.execute_batch(
+            "UPDATE pharmacy_settings SET name = 'City Pharmacy', address = '12 MG Road',
+                 city = 'Bengaluru', pincode = '560001', state_code = '29',
+                 gstin = '29ABCDE1234F1Z5', einvoice_enabled = 1
+             WHERE id = 1",
+        )
+        .unwrap();
+    }
+
+    fn b2b_sale(db: &Db, customer_gstin: &str) -> i64 {
+        let conn = db.connect().unwrap();
+        conn.execute(
+            "INSERT INTO customers (name, address, city, pincode, gstin)
+             VALUES ('Care Clinic', 'Ward 4, Civil Lines', 'Nagpur', '440001', ?1)",
+            params![customer_gstin],
+        )
+        .unwrap();
+        let customer_id = conn.last_insert_rowid();
+        let para = insert_medicine(&conn, "Paracetamol 500", 12.0);
+        let batch = insert_batch(&conn, para, "+1 year", 11_200, 10);
+
+        let sale = paid_sale(&conn, Some(customer_id), PaymentMode::Credit, batch, 2);
+        db.with_tx(|tx| sales::create_sale(tx, &sale))
+            .unwrap()
+            .sale_id
+    }
+
+    #[test]
+    fn intra_state_sale_reports_cgst_and_sgst() {
+        let (_dir, db) = migrated_db();
+        configure_seller(&db.connect().unwrap());
+        let sale_id = b2b_sale(&db, "29AAACC1234D1Z2");
+
+        let inv = generate_einvoice_json(&db.connect().unwrap(), sale_id).unwrap();
+        assert_eq!(inv.buyer_dtls.pos.as_deref(), Some("29"));
+        let item = &inv.item_list[0];
+        assert_eq!((item.ass_amt, item.gst_rt), (200.0, 12.0));
+        assert_eq!(
+            (item.cgst_amt, item.sgst_amt, item.igst_amt),
+            (12.0, 12.0, 0.0)
+        );
+        assert_eq!(inv.val_dtls.tot_inv_val, 224.0);
+
+        let json = serde_json::to_value(&inv).unwrap();
+        assert_eq!(json["Version"], "1.1");
+        assert_eq!(json["SellerDtls"]["Pin"], 560001);
+        assert_eq!(json["ItemList"][0]["HsnCd"], "3004");
+        assert_eq!(json["ValDtls"]["RndOffAmt"], 0.0);
+    }
+
+    #[test]
+    fn inter_state_sale_reports_igst() {
+        let (_dir, db) = migrated_db();
+        configure_seller(&db.connect().unwrap());
+        let sale_id = b2b_sale(&db, "27AAACC1234D1Z2");
+
+        let conn = db.connect().unwrap();
+        let (flag, igst): (bool, i64) = conn
+            .query_row(
+                "SELECT is_interstate, total_igst_paise FROM sales WHERE id = ?1",
+                [sale_id],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((flag, igst), (true, 2_400));
+
+        let inv = generate_einvoice_json(&conn, sale_id).unwrap();
+        assert_eq!(inv.buyer_dtls.stcd, "27");
+        assert_eq!((inv.val_dtls.igst_val, inv.val_dtls.cgst_val), (24.0, 0.0));
+    }
+
+    #[test]
+    fn missing_mandatory_fields_are_reported_together() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        conn.execute("UPDATE pharmacy_settings SET einvoice_enabled = 1", [])
+            .unwrap();
+        let sale_id = b2b_sale(&db, "");
+
+        let err = generate_einvoice_json(&conn, sale_id)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Pharmacy GSTIN"), "{err}");
+        assert!(err.contains("Pharmacy PIN code"), "{err}");
+        assert!(err.contains("Customer GSTIN"), "{err}");
+    }
+
+    const ADMIN: i64 = 1;
+
+    fn configure_api(conn: &Connection) {
+        conn.execute(
+            "UPDATE pharmacy_settings SET einvoice_api_url = 'https://gsp.example/api/',
+                 einvoice_client_id = 'cid', einvoice_client_secret = 'csecret',
+                 einvoice_username = 'api_user', einvoice_password = 'pw'",
+            [],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn irn_is_requested_through_the_gsp_and_stored() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        configure_seller(&conn);
+        configure_api(&conn);
+        let sale_id = b2b_sale(&db, "29AAACC1234D1Z2");
+
+        let mut calls = Vec::new();
+        let mut post = |url: &str, headers: &[(&str, String)], body: &str| {
+            let headers = headers
+                .iter()
+                .map(|(k, v)| format!("{k}: {v}"))
+                .collect::<Vec<_>>();
+            calls.push((url.to_string(), headers, body.to_string()));
+            Ok(if url.ends_with(AUTH_PATH) {
+                br#"{"Status":1,"Data":{"AuthToken":"tok","TokenExpiry":"2026-10-14 18:00:00"}}"#
+                    .to_vec()
+            } else {
+                br#"{"Status":"1","Data":"{\"AckNo\":112610000012345,\"AckDt\":\"2026-10-14 12:00:00\",\"Irn\":\"abc123\",\"SignedInvoice\":\"inv.jwt\",\"SignedQRCode\":\"qr.jwt\"}"}"#
+                    .to_vec()
+            })
+        };
+        let irn = request_irn(&conn, sale_id, ADMIN, &mut post).unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].0, "https://gsp.example/api/eivital/v1.04/auth");
+        assert!(calls[0].2.contains("\"Password\":\"pw\""));
+        assert!(calls[1].1.contains(&"AuthToken: tok".to_string()));
+        let sent: Value = serde_json::from_str(&calls[1].2).unwrap();
+        assert_eq!(sent["DocDtls"]["Typ"], "INV");
+        assert_eq!(
+            (irn.ack_no.as_str(), irn.signed_qr_code.as_str()),
+            ("112610000012345", "qr.jwt")
+        );
+
+        db.with_tx(|tx| record_irn(tx, sale_id, ADMIN, &irn))
+            .unwrap();
+        assert_eq!(sale_irn(&conn, sale_id).unwrap(), Some(irn));
+        let err = request_irn(&conn, sale_id, ADMIN, &mut |_, _, _| unreachable!())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("already has IRN abc123"), "{err}");
+    }
+
+    #[test]
+    fn rejected_invoices_report_the_portal_errors() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        configure_seller(&conn);
+        let sale_id = b2b_sale(&db, "29AAACC1234D1Z2");
+        let mut post = |url: &str, _: &[(&str, String)], _: &str| {
+            Ok(if url.ends_with(AUTH_PATH) {
+                br#"{"Status":1,"Data":{"AuthToken":"tok"}}"#.to_vec()
+            } else {
+                br#"{"Status":0,"ErrorDetails":[{"ErrorCode":"2150","ErrorMessage":"Duplicate IRN"}]}"#
+                    .to_vec()
+            })
+        };
+
+        let err = request_irn(&conn, sale_id, ADMIN, &mut post)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("in settings"), "{err}");
+
+        configure_api(&conn);
+        let err = request_irn(&conn, sale_id, ADMIN, &mut post)
+            .unwrap_err()
+            .to_string();
+        assert_eq!(err, "IRN generation was rejected: 2150 Duplicate IRN");
+        assert_eq!(sale_irn(&conn, sale_id).unwrap(), None);
+    }
+}
@@ -0,0 +1,218 @@
+//! Optional SQLCipher encryption of the database file.
+//!
+//! SQLCipher is only linked into builds made with the `sqlcipher` feature;
+//! without it every call here fails with a validation error, so plaintext
+//! builds compile and run as before.
+//!
+//! The SQL plugin cannot key the pools it opens, so once the file is
+//! encrypted the backend opens the frontend's pool itself (see
+//! [`crate::db::Db::open_pool`]); the commands swap it in after unlocking,
+//! encrypting or re-keying.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, ErrorCode, OpenFlags};
+use serde::Serialize;
+
+use crate::auth::{self, permission};
+use crate::db::Db;
+use crate::error::{AppError, AppResult};
+
+/// Whether the database needs a passphrase, and whether it has been given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseStatus {
+    pub encrypted: bool,
+    pub unlocked: bool,
+}
+
+fn ensure_available() -> AppResult<()> {
+    if cfg!(feature = "sqlcipher") {
+        Ok(())
+    } else {
+        Err(AppError::validation(
+            "This build was made without database encryption support",
+        ))
+    }
+}
+
+fn check_new_key(key: &str) -> AppResult<()> {
+    if key.is_empty() {
+        return Err(AppError::validation("The passphrase cannot be empty"));
+    }
+    Ok(())
+}
+
+/// Key a freshly opened connection; must run before anything reads it.
+pub fn apply_key(conn: &Connection, key: &str) -> AppResult<()> {
+    ensure_available()?;
+    conn.pragma_update(None, "key", key)?;
+    Ok(())
+}
+
+/// Whether `path` opens with `key`, or opens as plaintext when `key` is
+/// `None`.
+fn opens_with(path: &Path, key: Option<&str>) -> AppResult<bool> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    if let Some(key) = key {
+        apply_key(&conn, key)?;
+    }
+    match conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |_| Ok(())) {
+        Ok(()) => Ok(true),
+        Err(err) if err.sqlite_error_code() == Some(ErrorCode::NotADatabase) => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Whether `key` opens the encrypted database at `path`.
+pub fn verify_key(path: &Path, key: &str) -> AppResult<bool> {
+    ensure_available()?;
+    opens_with(path, Some(key))
+}
+
+/// Whether the database at `path` is encrypted. A file that does not exist
+/// yet will be created in plaintext.
+pub fn is_encrypted(path: &Path) -> AppResult<bool> {
+    Ok(path.exists() && !opens_with(path, None)?)
+}
+
+pub fn status(db: &Db) -> AppResult<DatabaseStatus> {
+    Ok(DatabaseStatus {
+        encrypted: is_encrypted(db.path())?,
+        unlocked: db.is_unlocked(),
+    })
+}
+
+/// [`verify_key`] for a signed-in user. Only users holding
+/// `database.encrypt` may test passphrases.
+pub fn check_key(conn: &Connection, path: &Path, key: &str, user_id: i64) -> AppResult<bool> {
+    auth::require_permission(conn, user_id, permission::DATABASE_ENCRYPT)?;
+    verify_key(path, key)
+}
+
+/// Re-encrypt the database at `path` under `new_key`. Only users holding
+/// `database.encrypt` may set the passphrase.
+pub fn change_key(path: &Path, old_key: &str, new_key: &str, user_id: i64) -> AppResult<()> {
+    ensure_available()?;
+    check_new_key(new_key)?;
+    if !opens_with(path, Some(old_key))? {
+        return Err(AppError::validation("The current passphrase is incorrect"));
+    }
+    let conn = Connection::open(path)?;
+    apply_key(&conn, old_key)?;
+    auth::require_permission(&conn, user_id, permission::DATABASE_ENCRYPT)?;
+    conn.pragma_update(None, "rekey", new_key)?;
+    Ok(())
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Convert the plaintext database at `path` into one encrypted with `key`.
+///
+/// A one-time step: the data is copied into a new encrypted file with
+/// `sqlcipher_export`, which then replaces the original. Nothing else may
+/// have the database open meanwhile, so the frontend's pool must be closed
+/// around it. SQLCipher derives the page key from `key` itself, with
+/// PBKDF2-HMAC-SHA512.
+pub fn encrypt_plaintext(path: &Path, key: &str, user_id: i64) -> AppResult<()> {
+    ensure_available()?;
+    check_new_key(key)?;
+    if !opens_with(path, None)? {
+        return Err(AppError::validation("The database is already encrypted"));
+    }
+
+    let encrypted = with_suffix(path, ".encrypting");
+    if encrypted.exists() {
+        fs::remove_file(&encrypted)?;
+    }
+    let conn = Connection::open(path)?;
+    auth::require_permission(&conn, user_id, permission::DATABASE_ENCRYPT)?;
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+    conn.execute(
+        "ATTACH DATABASE ?1 AS encrypted KEY ?2",
+        [encrypted.to_string_lossy().as_ref(), key],
+    )?;
+    conn.query_row("SELECT sqlcipher_export('encrypted')", [], |_| Ok(()))?;
+    conn.execute_batch("DETACH DATABASE encrypted")?;
+    drop(conn);
+
+    fs::rename(&encrypted, path)?;
+    for stale in ["-wal", "-shm"] {
+        let stale = with_suffix(path, stale);
+        if stale.exists() {
+            fs::remove_file(stale)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "sqlcipher")]
+    const ADMIN: i64 = 1;
+
+    #[cfg(not(feature = "sqlcipher"))]
+    #[test]
+    fn plaintext_builds_report_encryption_as_unavailable() {
+        use crate::db::test_support::migrated_db;
+
+        let (_dir, db) = migrated_db();
+
+        let err = verify_key(db.path(), "secret").unwrap_err();
+
+        assert!(
+            err.to_string().contains("without database encryption"),
+            "{err}"
+        );
+    }
+
+    #[cfg(feature = "sqlcipher")]
+    #[test]
+    fn encrypted_database_opens_only_with_its_current_key() {
+        use tauri::async_runtime::block_on;
+
+        use crate::db::test_support::insert_medicine;
+
+        // Migrated through a pool, as the SQL plugin would, so reopening it
+        // keyed must accept the recorded migrations.
+        let dir = tempfile::tempdir().unwrap();
+        let db = Db::new(dir.path().join("pharmacare.db"));
+        db.connect().unwrap();
+        block_on(async { db.open_pool().await.unwrap().close().await });
+        insert_medicine(&db.connect().unwrap(), "Paracetamol", 12.0);
+        assert!(!is_encrypted(db.path()).unwrap());
+
+        encrypt_plaintext(db.path(), "first key", ADMIN).unwrap();
+        assert!(is_encrypted(db.path()).unwrap());
+        assert!(verify_key(db.path(), "first key").unwrap());
+
+        let err = change_key(db.path(), "wrong", "second key", ADMIN).unwrap_err();
+        assert!(err.to_string().contains("incorrect"), "{err}");
+        change_key(db.path(), "first key", "second key", ADMIN).unwrap();
+        assert!(!verify_key(db.path(), "first key").unwrap());
+
+        db.set_key(Some("second key".into()));
+        let name: String = db
+            .connect()
+            .unwrap()
+            .query_row("SELECT name FROM medicines", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(name, "Paracetamol");
+
+        let name: String = block_on(async {
+            let pool = db.open_pool().await.unwrap();
+            sqlx::query_scalar("SELECT name FROM medicines")
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+        });
+        assert_eq!(name, "Paracetamol");
+    }
+}
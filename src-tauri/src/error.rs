@@ -0,0 +1,45 @@
+use std::fmt;
+
+/// Errors surfaced by backend commands.
+///
+/// Commands return `Result<T, String>` to the frontend; the `Display`
+/// text of these variants is what the UI shows.
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error("Database error: {0}")]
+    Db(#[from] rusqlite::Error),
+    #[error("Database error: {0}")]
+    Pool(#[from] sqlx::Error),
+    #[error("Database migration failed: {0}")]
+    Migrate(#[from] sqlx::migrate::MigrateError),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("{0}")]
+    Validation(String),
+    #[error("{0} not found")]
+    NotFound(String),
+    #[error("Not authorized: {0}")]
+    Unauthorized(String),
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl AppError {
+    pub fn validation(msg: impl fmt::Display) -> Self {
+        Self::Validation(msg.to_string())
+    }
+
+    pub fn not_found(what: impl fmt::Display) -> Self {
+        Self::NotFound(what.to_string())
+    }
+
+    pub fn unauthorized(msg: impl fmt::Display) -> Self {
+        Self::Unauthorized(msg.to_string())
+    }
+}
+
+impl From<AppError> for String {
+    fn from(err: AppError) -> Self {
+        err.to_string()
+    }
+}
@@ -0,0 +1,742 @@
+//! E-way bills for goods dispatched against a sale.
+//!
+//! A consignment worth more than the threshold in settings (₹50,000 by
+//! default) must carry an e-way bill. Bills are generated on the NIC e-way
+//! bill system through the e-invoicing GSP, logging in as the e-way bill
+//! API user, and can be cancelled there within a day. The number and
+//! validity are kept in `sale_eway_bills` and printed on the invoice; while
+//! a bill is active the sale cannot be amended.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::auth::{self, permission};
+use crate::db::{ChangeOp, Entity, Tx};
+use crate::einvoice::text;
+use crate::error::{AppError, AppResult};
+use crate::format::rupees;
+use crate::invoice;
+use crate::{http, sync};
+
+const AUTH_PATH: &str = "/ewaybillapi/v1.03/auth";
+const API_PATH: &str = "/ewaybillapi/v1.03/ewayapi";
+
+const API_TIMEOUT_SECS: u32 = 60;
+
+/// The longest distance the portal accepts, in kilometres.
+const MAX_DISTANCE_KM: u32 = 4000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportMode {
+    Road,
+    Rail,
+    Air,
+    Ship,
+}
+
+impl TransportMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Road => "road",
+            Self::Rail => "rail",
+            Self::Air => "air",
+            Self::Ship => "ship",
+        }
+    }
+
+    fn code(self) -> &'static str {
+        match self {
+            Self::Road => "1",
+            Self::Rail => "2",
+            Self::Air => "3",
+            Self::Ship => "4",
+        }
+    }
+}
+
+/// How the goods travel. Road dispatches need a vehicle number or a
+/// transporter; others need the transporter or the transport document.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Dispatch {
+    pub mode: TransportMode,
+    /// Approximate distance; 0 lets the portal work it out from the PIN codes.
+    pub distance_km: u32,
+    pub vehicle_no: Option<String>,
+    /// The transporter's GSTIN or enrolment id.
+    pub transporter_id: Option<String>,
+    pub transporter_name: Option<String>,
+    /// Railway receipt, airway bill or bill of lading number.
+    pub transport_doc_no: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CancelReason {
+    Duplicate,
+    OrderCancelled,
+    DataEntryMistake,
+    Others,
+}
+
+impl CancelReason {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Duplicate => "duplicate",
+            Self::OrderCancelled => "order_cancelled",
+            Self::DataEntryMistake => "data_entry_mistake",
+            Self::Others => "others",
+        }
+    }
+
+    fn code(self) -> u8 {
+        match self {
+            Self::Duplicate => 1,
+            Self::OrderCancelled => 2,
+            Self::DataEntryMistake => 3,
+            Self::Others => 4,
+        }
+    }
+}
+
+/// An e-way bill as the portal issued it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EwayBill {
+    pub ewb_no: String,
+    pub ewb_date: String,
+    pub valid_upto: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EwbItem {
+    product_name: String,
+    hsn_code: u32,
+    quantity: i64,
+    qty_unit: &'static str,
+    taxable_amount: f64,
+    cgst_rate: f64,
+    sgst_rate: f64,
+    igst_rate: f64,
+    cess_rate: f64,
+}
+
+struct ApiConfig {
+    url: String,
+    client_id: String,
+    client_secret: String,
+    username: String,
+    password: String,
+    gstin: String,
+    threshold_paise: i64,
+}
+
+fn api_config(conn: &Connection) -> AppResult<ApiConfig> {
+    let config = conn.query_row(
+        "SELECT einvoice_api_url, einvoice_client_id, einvoice_client_secret,
+                ewaybill_username, ewaybill_password, gstin, ewaybill_threshold_paise
+         FROM pharmacy_settings WHERE id = 1",
+        [],
+        |row| {
+            Ok(ApiConfig {
+                url: row
+                    .get::<_, String>(0)?
+                    .trim()
+                    .trim_end_matches('/')
+                    .to_string(),
+                client_id: row.get::<_, String>(1)?.trim().to_string(),
+                client_secret: row.get(2)?,
+                username: row.get::<_, String>(3)?.trim().to_string(),
+                password: row.get(4)?,
+                gstin: row.get(5)?,
+                threshold_paise: row.get(6)?,
+            })
+        },
+    )?;
+    if [
+        &config.url,
+        &config.client_id,
+        &config.username,
+        &config.password,
+    ]
+    .iter()
+    .any(|v| v.is_empty())
+    {
+        return Err(AppError::validation(
+            "Set the GSP API URL and client id and the e-way bill username and password in settings",
+        ));
+    }
+    Ok(config)
+}
+
+/// The reply of a successful call. The login's token is at the top level;
+/// other calls answer with `data`, as an object or a JSON string.
+fn api_reply(what: &str, body: &[u8]) -> AppResult<Value> {
+    let unreadable = || {
+        AppError::validation(format!(
+            "{what}: the e-way bill API sent an unreadable reply"
+        ))
+    };
+    let reply: Value = serde_json::from_slice(body).map_err(|_| unreadable())?;
+    if text(&reply["status"]) != "1" {
+        let error = &reply["error"];
+        let reason = [
+            text(&error["errorCodes"]),
+            text(&error["message"]),
+            text(error),
+        ]
+        .into_iter()
+        .filter(|r| !r.is_empty())
+        .map(|r| r.trim_end_matches(',').to_string())
+        .collect::<Vec<_>>();
+        let reason = if reason.is_empty() {
+            "no reason given".to_string()
+        } else {
+            reason.join(" ")
+        };
+        return Err(AppError::validation(format!(
+            "{what} was rejected: {reason}"
+        )));
+    }
+    match &reply["data"] {
+        Value::String(s) => serde_json::from_str(s).map_err(|_| unreadable()),
+        Value::Null => Ok(reply),
+        data => Ok(data.clone()),
+    }
+}
+
+fn required(what: &str, data: &Value, key: &str) -> AppResult<String> {
+    let value = text(&data[key]);
+    if value.is_empty() {
+        return Err(AppError::validation(format!(
+            "{what}: the e-way bill API reply has no {key}"
+        )));
+    }
+    Ok(value)
+}
+
+/// Log in and return the headers for further calls.
+fn login(config: &ApiConfig, post: &mut http::Post<'_>) -> AppResult<Vec<(&'static str, String)>> {
+    let mut headers = vec![
+        ("client_id", config.client_id.clone()),
+        ("client_secret", config.client_secret.clone()),
+        ("gstin", config.gstin.clone()),
+        ("username", config.username.clone()),
+    ];
+    let body = json!({
+        "action": "ACCESSTOKEN",
+        "username": config.username,
+        "password": config.password,
+    });
+    let what = "E-way bill login";
+    let reply = post(
+        &format!("{}{AUTH_PATH}", config.url),
+        &headers,
+        &body.to_string(),
+    )?;
+    headers.push((
+        "authtoken",
+        required(what, &api_reply(what, &reply)?, "authtoken")?,
+    ));
+    Ok(headers)
+}
+
+/// `YYYY-MM-DD...` as `dd/mm/yyyy`.
+fn doc_date(sale_date: &str) -> String {
+    let date = sale_date.get(..10).unwrap_or(sale_date);
+    let mut parts = date.split('-');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(y), Some(m), Some(d)) => format!("{d}/{m}/{y}"),
+        _ => date.to_string(),
+    }
+}
+
+fn pin(label: &str, pincode: &str, problems: &mut Vec<String>) -> u32 {
+    let pincode = pincode.trim();
+    match pincode.parse::<u32>() {
+        Ok(pin) if pincode.len() == 6 && !pincode.starts_with('0') => pin,
+        _ => {
+            problems.push(format!("{label} PIN code must be 6 digits"));
+            0
+        }
+    }
+}
+
+fn non_empty(value: &Option<String>) -> Option<String> {
+    value
+        .as_deref()
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+}
+
+/// Build the e-way bill request for a sale.
+fn eway_bill_request(
+    conn: &Connection,
+    sale_id: i64,
+    dispatch: &Dispatch,
+    threshold_paise: i64,
+) -> AppResult<Value> {
+    let doc = invoice::get_invoice(conn, sale_id)?;
+    if doc.status == "refunded" {
+        return Err(AppError::validation(format!(
+            "Sale {} has been refunded",
+            doc.invoice_number
+        )));
+    }
+    if doc.totals.grand_total_paise <= threshold_paise {
+        return Err(AppError::validation(format!(
+            "E-way bills are only needed for consignments above Rs. {}",
+            rupees(threshold_paise)
+        )));
+    }
+    let customer = doc.customer.as_ref().ok_or_else(|| {
+        AppError::validation("E-way bills are only generated for sales to a customer")
+    })?;
+    let (city, pincode): (Option<String>, Option<String>) = conn.query_row(
+        "SELECT city, pincode FROM customers WHERE id = ?1",
+        params![customer.id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+
+    let mut problems = Vec::new();
+    let pharmacy = &doc.pharmacy;
+    if pharmacy.gstin.trim().len() != 15 {
+        problems.push("Pharmacy GSTIN is missing".to_string());
+    }
+    let from_pin = pin("Pharmacy", &pharmacy.pincode, &mut problems);
+    let to_pin = pin(
+        "Customer",
+        pincode.as_deref().unwrap_or_default(),
+        &mut problems,
+    );
+    let to_gstin = non_empty(&customer.gstin).unwrap_or_else(|| "URP".to_string());
+    let to_state = non_empty(&customer.state_code)
+        .or_else(|| (to_gstin != "URP").then(|| to_gstin[..2].to_string()))
+        .unwrap_or_else(|| pharmacy.state_code.clone());
+
+    if dispatch.distance_km > MAX_DISTANCE_KM {
+        problems.push(format!("distance must be at most {MAX_DISTANCE_KM} km"));
+    }
+    let vehicle_no = non_empty(&dispatch.vehicle_no).map(|v| {
+        v.chars()
+            .filter(char::is_ascii_alphanumeric)
+            .collect::<String>()
+            .to_ascii_uppercase()
+    });
+    if vehicle_no
+        .as_ref()
+        .is_some_and(|v| !(7..=10).contains(&v.len()))
+    {
+        problems.push("vehicle number must be 7 to 10 letters and digits".to_string());
+    }
+    let transporter_id = non_empty(&dispatch.transporter_id);
+    let transport_doc_no = non_empty(&dispatch.transport_doc_no);
+    let part_b = match dispatch.mode {
+        TransportMode::Road => vehicle_no.is_some() || transporter_id.is_some(),
+        _ => transport_doc_no.is_some() || transporter_id.is_some(),
+    };
+    if !part_b {
+        problems.push(
+            match dispatch.mode {
+                TransportMode::Road => "a road dispatch needs a vehicle number or transporter",
+                _ => "the transporter or transport document number is required",
+            }
+            .to_string(),
+        );
+    }
+
+    let mut items = Vec::new();
+    for (i, line) in doc.items.iter().enumerate() {
+        let hsn_code = line.hsn_code.trim().parse().unwrap_or_else(|_| {
+            problems.push(format!(
+                "item {} ({}) has no valid HSN code",
+                i + 1,
+                line.medicine_name
+            ));
+            0
+        });
+        items.push(EwbItem {
+            product_name: line.medicine_name.clone(),
+            hsn_code,
+            quantity: line.quantity,
+            qty_unit: "NOS",
+            taxable_amount: line.taxable_amount_paise as f64 / 100.0,
+            cgst_rate: line.cgst_rate,
+            sgst_rate: line.sgst_rate,
+            igst_rate: line.igst_rate,
+            cess_rate: 0.0,
+        });
+    }
+    if !problems.is_empty() {
+        return Err(AppError::validation(format!(
+            "Cannot generate e-way bill: {}",
+            problems.join("; ")
+        )));
+    }
+
+    let t = &doc.totals;
+    let amount = |paise: i64| paise as f64 / 100.0;
+    Ok(json!({
+        "supplyType": "O",
+        "subSupplyType": "1",
+        "docType": "INV",
+        "docNo": doc.invoice_number,
+        "docDate": doc_date(&doc.sale_date),
+        "fromGstin": pharmacy.gstin,
+        "fromTrdName": pharmacy.name,
+        "fromAddr1": pharmacy.address,
+        "fromPlace": pharmacy.city,
+        "fromPincode": from_pin,
+        "fromStateCode": pharmacy.state_code.parse::<u8>().unwrap_or_default(),
+        "actFromStateCode": pharmacy.state_code.parse::<u8>().unwrap_or_default(),
+        "toGstin": to_gstin,
+        "toTrdName": customer.name,
+        "toAddr1": customer.address.clone().unwrap_or_default(),
+        "toPlace": city.unwrap_or_default(),
+        "toPincode": to_pin,
+        "toStateCode": to_state.parse::<u8>().unwrap_or_default(),
+        "actToStateCode": to_state.parse::<u8>().unwrap_or_default(),
+        "transactionType": 1,
+        "totalValue": amount(t.subtotal_paise - t.discount_paise),
+        "cgstValue": amount(t.total_cgst_paise),
+        "sgstValue": amount(t.total_sgst_paise),
+        "igstValue": amount(t.total_igst_paise),
+        "cessValue": 0,
+        "totInvValue": amount(t.grand_total_paise),
+        "transporterId": transporter_id.unwrap_or_default(),
+        "transporterName": non_empty(&dispatch.transporter_name).unwrap_or_default(),
+        "transMode": dispatch.mode.code(),
+        "transDistance": dispatch.distance_km.to_string(),
+        "transDocNo": transport_doc_no.unwrap_or_default(),
+        "vehicleNo": vehicle_no.unwrap_or_default(),
+        "vehicleType": "R",
+        "itemList": items,
+    }))
+}
+
+/// Generate an e-way bill for a sale on the portal. Nothing is stored;
+/// pass the result to [`record_eway_bill`].
+pub fn request_eway_bill(
+    conn: &Connection,
+    sale_id: i64,
+    user_id: i64,
+    dispatch: &Dispatch,
+    post: &mut http::Post<'_>,
+) -> AppResult<EwayBill> {
+    auth::require_permission(conn, user_id, permission::SALES_EWAYBILL)?;
+    if let Some(bill) = active_eway_bill(conn, sale_id)? {
+        return Err(AppError::validation(format!(
+            "This sale already has e-way bill {}",
+            bill.ewb_no
+        )));
+    }
+    let config = api_config(conn)?;
+    let body = eway_bill_request(conn, sale_id, dispatch, config.threshold_paise)?;
+    let headers = login(&config, post)?;
+
+    let what = "E-way bill generation";
+    let reply = post(
+        &format!("{}{API_PATH}?action=GENEWAYBILL", config.url),
+        &headers,
+        &body.to_string(),
+    )?;
+    let data = api_reply(what, &reply)?;
+    Ok(EwayBill {
+        ewb_no: required(what, &data, "ewayBillNo")?,
+        ewb_date: required(what, &data, "ewayBillDate")?,
+        valid_upto: required(what, &data, "validUpto")?,
+    })
+}
+
+/// Store an e-way bill the portal issued for a sale.
+pub fn record_eway_bill(
+    tx: &Tx,
+    sale_id: i64,
+    user_id: i64,
+    dispatch: &Dispatch,
+    bill: &EwayBill,
+) -> AppResult<()> {
+    tx.execute(
+        "INSERT INTO sale_eway_bills
+             (sale_id, ewb_no, ewb_date, valid_upto, transport_mode, distance_km,
+              vehicle_no, transporter_id, user_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            sale_id,
+            bill.ewb_no,
+            bill.ewb_date,
+            bill.valid_upto,
+            dispatch.mode.as_str(),
+            dispatch.distance_km,
+            non_empty(&dispatch.vehicle_no),
+            non_empty(&dispatch.transporter_id),
+            user_id,
+        ],
+    )?;
+    sync::enqueue(
+        tx,
+        "record_eway_bill",
+        &json!({
+            "saleId": sale_id,
+            "ewbNo": bill.ewb_no,
+            "ewbDate": bill.ewb_date,
+            "validUpto": bill.valid_upto,
+            "transportMode": dispatch.mode,
+            "distanceKm": dispatch.distance_km,
+        }),
+    )?;
+    tx.changed(Entity::Sale, ChangeOp::Update, sale_id);
+    Ok(())
+}
+
+/// Cancel a sale's active e-way bill on the portal and return when it was
+/// cancelled. Nothing is stored; pass the result to [`record_cancellation`].
+pub fn request_cancellation(
+    conn: &Connection,
+    sale_id: i64,
+    user_id: i64,
+    reason: CancelReason,
+    remark: &str,
+    post: &mut http::Post<'_>,
+) -> AppResult<String> {
+    auth::require_permission(conn, user_id, permission::SALES_EWAYBILL)?;
+    let bill = active_eway_bill(conn, sale_id)?
+        .ok_or_else(|| AppError::validation("This sale has no active e-way bill"))?;
+    let remark = remark.trim();
+    if remark.is_empty() {
+        return Err(AppError::validation("Give a remark for the cancellation"));
+    }
+    let config = api_config(conn)?;
+    let headers = login(&config, post)?;
+
+    let what = "E-way bill cancellation";
+    let body = json!({
+        "ewbNo": bill.ewb_no.parse::<u64>().ok(),
+        "cancelRsnCode": reason.code(),
+        "cancelRmrk": remark.chars().take(50).collect::<String>(),
+    });
+    let reply = post(
+        &format!("{}{API_PATH}?action=CANEWB", config.url),
+        &headers,
+        &body.to_string(),
+    )?;
+    required(what, &api_reply(what, &reply)?, "cancelDate")
+}
+
+/// Mark a sale's active e-way bill as cancelled.
+pub fn record_cancellation(
+    tx: &Tx,
+    sale_id: i64,
+    reason: CancelReason,
+    remark: &str,
+    cancelled_at: &str,
+) -> AppResult<()> {
+    let changed = tx.execute(
+        "UPDATE sale_eway_bills SET cancelled_at = ?1, cancel_reason = ?2, cancel_remark = ?3
+         WHERE sale_id = ?4 AND cancelled_at IS NULL",
+        params![cancelled_at, reason.as_str(), remark.trim(), sale_id],
+    )?;
+    if changed == 0 {
+        return Err(AppError::validation("This sale has no active e-way bill"));
+    }
+    sync::enqueue(
+        tx,
+        "cancel_eway_bill",
+        &json!({ "saleId": sale_id, "cancelledAt": cancelled_at, "reason": reason.as_str() }),
+    )?;
+    tx.changed(Entity::Sale, ChangeOp::Update, sale_id);
+    Ok(())
+}
+
+/// POST through the system curl.
+pub fn curl_post(url: &str, headers: &[(&str, String)], body: &str) -> AppResult<Vec<u8>> {
+    http::post_json("E-way bill request", API_TIMEOUT_SECS, url, headers, body)
+}
+
+/// The sale's e-way bill that has not been cancelled, if any.
+pub fn active_eway_bill(conn: &Connection, sale_id: i64) -> AppResult<Option<EwayBill>> {
+    Ok(conn
+        .query_row(
+            "SELECT ewb_no, ewb_date, valid_upto FROM sale_eway_bills
+             WHERE sale_id = ?1 AND cancelled_at IS NULL",
+            params![sale_id],
+            |row| {
+                Ok(EwayBill {
+                    ewb_no: row.get(0)?,
+                    ewb_date: row.get(1)?,
+                    valid_upto: row.get(2)?,
+                })
+            },
+        )
+        .optional()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{insert_batch, insert_medicine, migrated_db, paid_sale};
+    use crate::db::Db;
+    use crate::sales::{self, PaymentMode};
+
+    const ADMIN: i64 = 1;
+
+    fn configure(conn: &Connection) {
+        conn.execute_batch(
+            "UPDATE pharmacy_settings SET name = 'City Pharmacy', address = '12 MG Road',
+                 city = 'Bengaluru', pincode = '560001', state_code = '29',
+                 gstin = '29ABCDE1234F1Z5', einvoice_api_url = 'https://gsp.example',
+                 einvoice_client_id = 'cid', einvoice_client_secret = 'csecret',
+                 ewaybill_username = 'ewb_user', ewaybill_password = 'pw',
+                 ewaybill_threshold_paise = 10000
+             WHERE id = 1",
+        )
+        .unwrap();
+    }
+
+    fn wholesale_sale(db: &Db, quantity: i64) -> i64 {
+        let conn = db.connect().unwrap();
+        conn.execute(
+            "INSERT INTO customers (name, address, city, pincode, gstin)
+             VALUES ('Care Clinic', 'Ward 4', 'Mysuru', '570001', '29AAACC1234D1Z2')",
+            [],
+        )
+        .unwrap();
+        let customer_id = conn.last_insert_rowid();
+        let para = insert_medicine(&conn, "Paracetamol 500", 12.0);
+        let batch = insert_batch(&conn, para, "+1 year", 11_200, 100);
+        let sale = paid_sale(
+            &conn,
+            Some(customer_id),
+            PaymentMode::Credit,
+            batch,
+            quantity,
+        );
+        db.with_tx(|tx| sales::create_sale(tx, &sale))
+            .unwrap()
+            .sale_id
+    }
+
+    fn by_road() -> Dispatch {
+        Dispatch {
+            mode: TransportMode::Road,
+            distance_km: 140,
+            vehicle_no: Some("ka-01 ab 1234".into()),
+            transporter_id: None,
+            transporter_name: None,
+            transport_doc_no: None,
+        }
+    }
+
+    /// Answers like the portal, recording each request's URL and body.
+    fn portal(
+        calls: &mut Vec<(String, String)>,
+    ) -> impl FnMut(&str, &[(&str, String)], &str) -> AppResult<Vec<u8>> + '_ {
+        move |url, _, body| {
+            calls.push((url.to_string(), body.to_string()));
+            Ok(if url.ends_with(AUTH_PATH) {
+                br#"{"status":"1","authtoken":"tok"}"#.to_vec()
+            } else if url.ends_with("GENEWAYBILL") {
+                br#"{"status":"1","data":{"ewayBillNo":331001234567,"ewayBillDate":"14/10/2026 10:00:00 AM","validUpto":"15/10/2026 11:59:00 PM"}}"#.to_vec()
+            } else {
+                br#"{"status":"1","data":{"ewayBillNo":331001234567,"cancelDate":"14/10/2026 11:00:00 AM"}}"#.to_vec()
+            })
+        }
+    }
+
+    #[test]
+    fn eway_bills_are_generated_printed_and_cancelled() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        configure(&conn);
+        let sale_id = wholesale_sale(&db, 2);
+        let mut calls = Vec::new();
+
+        let dispatch = by_road();
+        let bill =
+            request_eway_bill(&conn, sale_id, ADMIN, &dispatch, &mut portal(&mut calls)).unwrap();
+        assert_eq!(bill.ewb_no, "331001234567");
+        let sent: Value = serde_json::from_str(&calls[1].1).unwrap();
+        assert_eq!(sent["toGstin"], "29AAACC1234D1Z2");
+        assert_eq!(
+            (sent["fromPincode"].clone(), sent["toPincode"].clone()),
+            (json!(560001), json!(570001))
+        );
+        assert_eq!(sent["vehicleNo"], "KA01AB1234");
+        assert_eq!(sent["itemList"][0]["hsnCode"], 3004);
+        assert_eq!(sent["totInvValue"], 224.0);
+
+        db.with_tx(|tx| record_eway_bill(tx, sale_id, ADMIN, &dispatch, &bill))
+            .unwrap();
+        let doc = invoice::get_invoice(&conn, sale_id).unwrap();
+        assert_eq!(doc.eway_bill, Some(bill.clone()));
+        let err = request_eway_bill(
+            &conn,
+            sale_id,
+            ADMIN,
+            &dispatch,
+            &mut |_, _, _| unreachable!(),
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("already has e-way bill 331001234567"), "{err}");
+
+        let reason = CancelReason::DataEntryMistake;
+        let cancelled_at = request_cancellation(
+            &conn,
+            sale_id,
+            ADMIN,
+            reason,
+            "Wrong vehicle",
+            &mut portal(&mut calls),
+        )
+        .unwrap();
+        let sent: Value = serde_json::from_str(&calls.last().unwrap().1).unwrap();
+        assert_eq!(
+            (sent["ewbNo"].clone(), sent["cancelRsnCode"].clone()),
+            (json!(331001234567u64), json!(3))
+        );
+        db.with_tx(|tx| record_cancellation(tx, sale_id, reason, "Wrong vehicle", &cancelled_at))
+            .unwrap();
+        assert_eq!(active_eway_bill(&conn, sale_id).unwrap(), None);
+    }
+
+    #[test]
+    fn dispatches_are_checked_before_calling_the_portal() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        configure(&conn);
+        let small = wholesale_sale(&db, 2);
+        conn.execute(
+            "UPDATE pharmacy_settings SET ewaybill_threshold_paise = 5000000",
+            [],
+        )
+        .unwrap();
+        let mut offline =
+            |_: &str, _: &[(&str, String)], _: &str| -> AppResult<Vec<u8>> { unreachable!() };
+
+        let err = request_eway_bill(&conn, small, ADMIN, &by_road(), &mut offline)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("above Rs. 50000.00"), "{err}");
+
+        conn.execute(
+            "UPDATE pharmacy_settings SET ewaybill_threshold_paise = 0",
+            [],
+        )
+        .unwrap();
+        let unplanned = Dispatch {
+            vehicle_no: None,
+            distance_km: 5000,
+            ..by_road()
+        };
+        let err = request_eway_bill(&conn, small, ADMIN, &unplanned, &mut offline)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("at most 4000 km"), "{err}");
+        assert!(err.contains("vehicle number or transporter"), "{err}");
+    }
+}
@@ -0,0 +1,460 @@
+//! Money the pharmacy pays out that is not for stock: rent, electricity,
+//! salaries and the like, each filed under a category.
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::auth::{self, permission};
+use crate::db::{ChangeOp, Entity, Tx};
+use crate::error::{AppError, AppResult};
+use crate::reports::validate_date_range;
+use crate::sales::PaymentMode;
+use crate::sync;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpenseCategory {
+    pub id: i64,
+    pub name: String,
+    /// Retired categories keep their expenses but take no new ones.
+    pub is_active: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewExpense {
+    pub category_id: i64,
+    pub amount_paise: i64,
+    pub payment_mode: PaymentMode,
+    /// `YYYY-MM-DD`; today when not given.
+    #[serde(default)]
+    pub expense_date: Option<String>,
+    #[serde(default)]
+    pub paid_to: Option<String>,
+    #[serde(default)]
+    pub reference: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// Set from the session by the command, never taken from the client.
+    #[serde(default, skip_deserializing)]
+    pub user_id: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Expense {
+    pub id: i64,
+    pub category_id: i64,
+    pub category: String,
+    pub amount_paise: i64,
+    pub expense_date: String,
+    pub payment_mode: String,
+    pub paid_to: Option<String>,
+    pub reference: Option<String>,
+    pub notes: Option<String>,
+    pub user_id: i64,
+    pub created_at: String,
+}
+
+/// What was spent under one category.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryExpense {
+    pub category_id: i64,
+    pub category: String,
+    pub expense_count: i64,
+    pub total_paise: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpenseReport {
+    pub from_date: String,
+    pub to_date: String,
+    /// Largest first; categories with nothing spent are left out.
+    pub categories: Vec<CategoryExpense>,
+    pub total_paise: i64,
+}
+
+const EXPENSE_COLUMNS: &str = "e.id, e.category_id, c.name, e.amount_paise, e.expense_date,
+    e.payment_mode, e.paid_to, e.reference, e.notes, e.user_id, e.created_at";
+
+fn expense_from_row(row: &Row) -> rusqlite::Result<Expense> {
+    Ok(Expense {
+        id: row.get(0)?,
+        category_id: row.get(1)?,
+        category: row.get(2)?,
+        amount_paise: row.get(3)?,
+        expense_date: row.get(4)?,
+        payment_mode: row.get(5)?,
+        paid_to: row.get(6)?,
+        reference: row.get(7)?,
+        notes: row.get(8)?,
+        user_id: row.get(9)?,
+        created_at: row.get(10)?,
+    })
+}
+
+/// Every category, active ones first.
+pub fn list_expense_categories(conn: &Connection) -> AppResult<Vec<ExpenseCategory>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, is_active FROM expense_categories ORDER BY is_active DESC, name",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(ExpenseCategory {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            is_active: row.get(2)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+/// Add a category, or bring back a retired one of the same name.
+pub fn create_expense_category(tx: &Tx, name: &str, user_id: i64) -> AppResult<ExpenseCategory> {
+    auth::require_permission(tx, user_id, permission::EXPENSES_MANAGE)?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::validation("A category name is required"));
+    }
+    tx.execute(
+        "INSERT INTO expense_categories (name) VALUES (?1)
+         ON CONFLICT (name) DO UPDATE SET is_active = 1",
+        params![name],
+    )?;
+    let category = tx.query_row(
+        "SELECT id, name, is_active FROM expense_categories WHERE name = ?1",
+        params![name],
+        |row| {
+            Ok(ExpenseCategory {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                is_active: row.get(2)?,
+            })
+        },
+    )?;
+    tx.changed(Entity::ExpenseCategory, ChangeOp::Insert, category.id);
+    sync::enqueue(
+        tx,
+        "create_expense_category",
+        &json!({ "name": category.name }),
+    )?;
+    Ok(category)
+}
+
+/// Retire a category, or bring it back.
+pub fn set_expense_category_active(
+    tx: &Tx,
+    category_id: i64,
+    is_active: bool,
+    user_id: i64,
+) -> AppResult<()> {
+    auth::require_permission(tx, user_id, permission::EXPENSES_MANAGE)?;
+    let changed = tx.execute(
+        "UPDATE expense_categories SET is_active = ?1 WHERE id = ?2",
+        params![is_active, category_id],
+    )?;
+    if changed == 0 {
+        return Err(AppError::not_found(format!(
+            "Expense category {category_id}"
+        )));
+    }
+    tx.changed(Entity::ExpenseCategory, ChangeOp::Update, category_id);
+    sync::enqueue(
+        tx,
+        "set_expense_category_active",
+        &json!({ "categoryId": category_id, "isActive": is_active }),
+    )
+}
+
+fn get_expense(conn: &Connection, expense_id: i64) -> AppResult<Expense> {
+    conn.query_row(
+        &format!(
+            "SELECT {EXPENSE_COLUMNS} FROM expenses e
+             JOIN expense_categories c ON c.id = e.category_id
+             WHERE e.id = ?1"
+        ),
+        params![expense_id],
+        expense_from_row,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::not_found(format!("Expense {expense_id}")))
+}
+
+/// Check `expense` and settle its date.
+fn validate_expense(tx: &Tx, expense: &NewExpense) -> AppResult<String> {
+    auth::require_permission(tx, expense.user_id, permission::EXPENSES_MANAGE)?;
+    if expense.amount_paise <= 0 {
+        return Err(AppError::validation(
+            "Expense amount must be greater than 0",
+        ));
+    }
+    if expense.payment_mode == PaymentMode::Credit {
+        return Err(AppError::validation(
+            "An expense must be paid by cash, card or UPI",
+        ));
+    }
+    let active: Option<bool> = tx
+        .query_row(
+            "SELECT is_active FROM expense_categories WHERE id = ?1",
+            params![expense.category_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    match active {
+        None => {
+            return Err(AppError::not_found(format!(
+                "Expense category {}",
+                expense.category_id
+            )))
+        }
+        Some(false) => {
+            return Err(AppError::validation(
+                "That expense category has been retired",
+            ))
+        }
+        Some(true) => {}
+    }
+    match &expense.expense_date {
+        Some(date) => {
+            validate_date_range(tx, date, date)?;
+            Ok(date.clone())
+        }
+        None => Ok(tx.query_row("SELECT date('now')", [], |row| row.get(0))?),
+    }
+}
+
+pub fn create_expense(tx: &Tx, expense: &NewExpense) -> AppResult<Expense> {
+    let expense_date = validate_expense(tx, expense)?;
+    tx.execute(
+        "INSERT INTO expenses (category_id, amount_paise, expense_date, payment_mode, paid_to,
+             reference, notes, user_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            expense.category_id,
+            expense.amount_paise,
+            expense_date,
+            expense.payment_mode.as_str(),
+            expense.paid_to,
+            expense.reference,
+            expense.notes,
+            expense.user_id
+        ],
+    )?;
+    let expense_id = tx.last_insert_rowid();
+    tx.changed(Entity::Expense, ChangeOp::Insert, expense_id);
+    sync::enqueue(tx, "create_expense", &json!(expense))?;
+    get_expense(tx, expense_id)
+}
+
+/// Correct a recorded expense.
+pub fn update_expense(tx: &Tx, expense_id: i64, expense: &NewExpense) -> AppResult<Expense> {
+    let expense_date = validate_expense(tx, expense)?;
+    let changed = tx.execute(
+        "UPDATE expenses SET category_id = ?1, amount_paise = ?2, expense_date = ?3,
+             payment_mode = ?4, paid_to = ?5, reference = ?6, notes = ?7,
+             updated_at = datetime('now')
+         WHERE id = ?8",
+        params![
+            expense.category_id,
+            expense.amount_paise,
+            expense_date,
+            expense.payment_mode.as_str(),
+            expense.paid_to,
+            expense.reference,
+            expense.notes,
+            expense_id
+        ],
+    )?;
+    if changed == 0 {
+        return Err(AppError::not_found(format!("Expense {expense_id}")));
+    }
+    tx.changed(Entity::Expense, ChangeOp::Update, expense_id);
+    sync::enqueue(
+        tx,
+        "update_expense",
+        &json!({ "expenseId": expense_id, "expense": expense }),
+    )?;
+    get_expense(tx, expense_id)
+}
+
+pub fn delete_expense(tx: &Tx, expense_id: i64, user_id: i64) -> AppResult<()> {
+    auth::require_permission(tx, user_id, permission::EXPENSES_MANAGE)?;
+    let changed = tx.execute("DELETE FROM expenses WHERE id = ?1", params![expense_id])?;
+    if changed == 0 {
+        return Err(AppError::not_found(format!("Expense {expense_id}")));
+    }
+    tx.changed(Entity::Expense, ChangeOp::Delete, expense_id);
+    sync::enqueue(tx, "delete_expense", &json!({ "expenseId": expense_id }))
+}
+
+/// Expenses dated `from_date` to `to_date`, inclusive, newest first.
+pub fn list_expenses(
+    conn: &Connection,
+    from_date: &str,
+    to_date: &str,
+    category_id: Option<i64>,
+) -> AppResult<Vec<Expense>> {
+    validate_date_range(conn, from_date, to_date)?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {EXPENSE_COLUMNS} FROM expenses e
+         JOIN expense_categories c ON c.id = e.category_id
+         WHERE e.expense_date BETWEEN ?1 AND ?2 AND (?3 IS NULL OR e.category_id = ?3)
+         ORDER BY e.expense_date DESC, e.id DESC"
+    ))?;
+    let rows = stmt.query_map(params![from_date, to_date, category_id], expense_from_row)?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+/// What was spent under each category from `from_date` to `to_date`,
+/// inclusive.
+pub fn expense_report(
+    conn: &Connection,
+    from_date: &str,
+    to_date: &str,
+) -> AppResult<ExpenseReport> {
+    validate_date_range(conn, from_date, to_date)?;
+    let mut stmt = conn.prepare(
+        "SELECT c.id, c.name, COUNT(*), SUM(e.amount_paise)
+         FROM expenses e
+         JOIN expense_categories c ON c.id = e.category_id
+         WHERE e.expense_date BETWEEN ?1 AND ?2
+         GROUP BY c.id
+         ORDER BY SUM(e.amount_paise) DESC, c.name",
+    )?;
+    let categories = stmt
+        .query_map(params![from_date, to_date], |row| {
+            Ok(CategoryExpense {
+                category_id: row.get(0)?,
+                category: row.get(1)?,
+                expense_count: row.get(2)?,
+                total_paise: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(ExpenseReport {
+        from_date: from_date.to_owned(),
+        to_date: to_date.to_owned(),
+        total_paise: categories.iter().map(|c| c.total_paise).sum(),
+        categories,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::db::test_support::migrated_db;
+
+    const ADMIN: i64 = 1;
+
+    fn category(conn: &Connection, name: &str) -> i64 {
+        conn.query_row(
+            "SELECT id FROM expense_categories WHERE name = ?1",
+            params![name],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    fn expense(category_id: i64, amount_paise: i64, date: &str) -> NewExpense {
+        NewExpense {
+            category_id,
+            amount_paise,
+            payment_mode: PaymentMode::Cash,
+            expense_date: Some(date.into()),
+            paid_to: None,
+            reference: None,
+            notes: None,
+            user_id: ADMIN,
+        }
+    }
+
+    #[test]
+    fn expenses_total_by_category_within_the_range() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let rent = category(&conn, "Rent");
+        let power = category(&conn, "Electricity");
+        for new in [
+            expense(rent, 1_500_000, "2026-04-01"),
+            expense(power, 320_000, "2026-04-10"),
+            expense(power, 280_000, "2026-04-25"),
+            expense(rent, 1_500_000, "2026-05-01"),
+        ] {
+            db.with_tx(|tx| create_expense(tx, &new)).unwrap();
+        }
+
+        let report = expense_report(&conn, "2026-04-01", "2026-04-30").unwrap();
+
+        let totals: Vec<_> = report
+            .categories
+            .iter()
+            .map(|c| (c.category.as_str(), c.expense_count, c.total_paise))
+            .collect();
+        assert_eq!(
+            totals,
+            [("Rent", 1, 1_500_000), ("Electricity", 2, 600_000)]
+        );
+        assert_eq!(report.total_paise, 2_100_000);
+        let april = list_expenses(&conn, "2026-04-01", "2026-04-30", Some(power)).unwrap();
+        assert_eq!(april[0].expense_date, "2026-04-25");
+    }
+
+    #[test]
+    fn expenses_need_an_active_category_and_a_till_mode() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let rent = category(&conn, "Rent");
+        let mut on_credit = expense(rent, 100, "2026-04-01");
+        on_credit.payment_mode = PaymentMode::Credit;
+        let err = db.with_tx(|tx| create_expense(tx, &on_credit)).unwrap_err();
+        assert!(err.to_string().contains("cash, card or UPI"), "{err}");
+
+        let recorded = db
+            .with_tx(|tx| create_expense(tx, &expense(rent, 100, "2026-04-01")))
+            .unwrap();
+        db.with_tx(|tx| set_expense_category_active(tx, rent, false, ADMIN))
+            .unwrap();
+        let err = db
+            .with_tx(|tx| create_expense(tx, &expense(rent, 100, "2026-04-02")))
+            .unwrap_err();
+        assert!(err.to_string().contains("retired"), "{err}");
+
+        let revived = db
+            .with_tx(|tx| create_expense_category(tx, " rent ", ADMIN))
+            .unwrap();
+        assert_eq!((revived.id, revived.is_active), (rent, true));
+        db.with_tx(|tx| delete_expense(tx, recorded.id, ADMIN))
+            .unwrap();
+        assert!(matches!(
+            db.with_tx(|tx| delete_expense(tx, recorded.id, ADMIN)),
+            Err(AppError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn recording_and_deleting_an_expense_notifies_the_frontend() {
+        let (_dir, db) = migrated_db();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        let db = db.on_change(move |c| sink.lock().unwrap().push((c.entity, c.op, c.id)));
+        let rent = category(&db.connect().unwrap(), "Rent");
+
+        let recorded = db
+            .with_tx(|tx| create_expense(tx, &expense(rent, 100, "2026-04-01")))
+            .unwrap();
+        db.with_tx(|tx| delete_expense(tx, recorded.id, ADMIN))
+            .unwrap();
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [
+                (Entity::Expense, ChangeOp::Insert, recorded.id),
+                (Entity::Expense, ChangeOp::Delete, recorded.id),
+            ]
+        );
+    }
+}
@@ -0,0 +1,230 @@
+//! Flat CSV exports for handing the books to an accountant.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use rusqlite::Connection;
+use serde::Serialize;
+
+use crate::auth::{self, permission};
+use crate::daybook;
+use crate::error::AppResult;
+use crate::format;
+use crate::reports;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportSummary {
+    pub path: String,
+    /// Data rows, not counting the header.
+    pub rows_written: u64,
+}
+
+const SALES_HEADER: &str = "Invoice Number,Date,Customer,Subtotal,Discount,CGST,SGST,IGST,\
+                            Round Off,Grand Total,Payment Mode,Status";
+
+/// Quote a field if it would otherwise break the row.
+pub(crate) fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+/// Write the sales register for sales dated `from_date` to `to_date`,
+/// inclusive, to `dest`: one row per invoice, amounts in rupees.
+///
+/// Rows are written as they are read, so the export runs in constant
+/// memory however long the range. Split tenders list every mode, e.g.
+/// `cash+upi`. Refunded sales are included and marked in the status column.
+pub fn export_sales_csv(
+    conn: &Connection,
+    from_date: &str,
+    to_date: &str,
+    dest: &Path,
+    user_id: i64,
+) -> AppResult<ExportSummary> {
+    auth::require_permission(conn, user_id, permission::REPORTS_EXPORT)?;
+    reports::validate_date_range(conn, from_date, to_date)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT s.invoice_number, date(s.sale_date), COALESCE(c.name, ''),
+             s.subtotal_paise, s.discount_paise, s.total_cgst_paise, s.total_sgst_paise,
+             s.total_igst_paise, s.round_off_paise, s.grand_total_paise,
+             COALESCE(
+                 (SELECT group_concat(payment_mode, '+')
+                  FROM (SELECT payment_mode FROM sale_payments
+                        WHERE sale_id = s.id ORDER BY id)),
+                 s.payment_mode),
+             s.status
+         FROM sales s
+         LEFT JOIN customers c ON c.id = s.customer_id
+         WHERE date(s.sale_date) BETWEEN ?1 AND ?2
+         ORDER BY s.sale_date, s.id",
+    )?;
+    let mut rows = stmt.query([from_date, to_date])?;
+
+    let mut out = BufWriter::new(File::create(dest)?);
+    writeln!(out, "{SALES_HEADER}")?;
+    let mut rows_written = 0;
+    while let Some(row) = rows.next()? {
+        let mut fields = Vec::with_capacity(12);
+        for i in 0..3 {
+            fields.push(csv_field(&row.get::<_, String>(i)?));
+        }
+        for i in 3..10 {
+            fields.push(format::rupees(row.get(i)?));
+        }
+        for i in 10..12 {
+            fields.push(csv_field(&row.get::<_, String>(i)?));
+        }
+        writeln!(out, "{}", fields.join(","))?;
+        rows_written += 1;
+    }
+    out.flush()?;
+
+    Ok(ExportSummary {
+        path: dest.to_string_lossy().into_owned(),
+        rows_written,
+    })
+}
+
+const DAY_BOOK_HEADER: &str = "Date,Type,Reference,Party,Mode,Receipt,Payment";
+
+/// Write the day book for `date` to `dest`: the opening cash, one row per
+/// receipt or payment, then the closing cash, amounts in rupees.
+pub fn export_day_book_csv(
+    conn: &Connection,
+    date: &str,
+    dest: &Path,
+    user_id: i64,
+) -> AppResult<ExportSummary> {
+    auth::require_permission(conn, user_id, permission::REPORTS_EXPORT)?;
+    let book = daybook::day_book(conn, date)?;
+
+    let mut out = BufWriter::new(File::create(dest)?);
+    writeln!(out, "{DAY_BOOK_HEADER}")?;
+    writeln!(
+        out,
+        "{date},Opening cash,,,cash,{},",
+        format::rupees(book.opening_cash_paise)
+    )?;
+    for entry in &book.entries {
+        let fields = [
+            date.to_owned(),
+            entry.kind.label().to_owned(),
+            csv_field(entry.reference.as_deref().unwrap_or_default()),
+            csv_field(entry.party.as_deref().unwrap_or_default()),
+            entry.mode.clone(),
+            format::rupees(entry.receipt_paise),
+            format::rupees(entry.payment_paise),
+        ];
+        writeln!(out, "{}", fields.join(","))?;
+    }
+    writeln!(
+        out,
+        "{date},Closing cash,,,cash,{},",
+        format::rupees(book.closing_cash_paise)
+    )?;
+    out.flush()?;
+
+    Ok(ExportSummary {
+        path: dest.to_string_lossy().into_owned(),
+        rows_written: book.entries.len() as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{
+        insert_batch, insert_cashier, insert_medicine, migrated_db, paid_sale,
+    };
+    use crate::error::AppError;
+    use crate::sales::{create_sale, PaymentMode};
+
+    #[test]
+    fn sales_register_has_one_rupee_row_per_invoice() {
+        let (dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        conn.execute(
+            "INSERT INTO customers (name) VALUES ('Sharma, Asha \"Didi\"')",
+            [],
+        )
+        .unwrap();
+        let customer = conn.last_insert_rowid();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 1_120, 10);
+        for customer in [Some(customer), None] {
+            let sale = paid_sale(&conn, customer, PaymentMode::Upi, batch, 3);
+            db.with_tx(|tx| create_sale(tx, &sale)).unwrap();
+        }
+        let today: String = conn
+            .query_row("SELECT date('now')", [], |r| r.get(0))
+            .unwrap();
+        let dest = dir.path().join("sales.csv");
+
+        let summary = export_sales_csv(&conn, &today, &today, &dest, 1).unwrap();
+
+        assert_eq!(summary.rows_written, 2);
+        let csv = std::fs::read_to_string(&dest).unwrap();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], SALES_HEADER);
+        assert!(
+            lines[1].ends_with(&format!(
+                ",{today},\"Sharma, Asha \"\"Didi\"\"\",33.60,0.00,1.80,1.80,0.00,0.40,34.00,upi,completed"
+            )),
+            "{}",
+            lines[1]
+        );
+        assert!(
+            lines[2].contains(&format!(",{today},,33.60,")),
+            "{}",
+            lines[2]
+        );
+    }
+
+    #[test]
+    fn day_book_csv_brackets_entries_with_opening_and_closing_cash() {
+        let (dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 1_120, 10);
+        let sale = paid_sale(&conn, None, PaymentMode::Cash, batch, 3);
+        db.with_tx(|tx| create_sale(tx, &sale)).unwrap();
+        let today: String = conn
+            .query_row("SELECT date('now')", [], |r| r.get(0))
+            .unwrap();
+        let dest = dir.path().join("daybook.csv");
+
+        let summary = export_day_book_csv(&conn, &today, &dest, 1).unwrap();
+
+        assert_eq!(summary.rows_written, 1);
+        let csv = std::fs::read_to_string(&dest).unwrap();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines[0], DAY_BOOK_HEADER);
+        assert_eq!(lines[1], format!("{today},Opening cash,,,cash,0.00,"));
+        assert!(lines[2].ends_with(",,cash,34.00,0.00"), "{}", lines[2]);
+        assert_eq!(lines[3], format!("{today},Closing cash,,,cash,34.00,"));
+    }
+
+    #[test]
+    fn cashier_cannot_export_the_books() {
+        let (dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let cashier = insert_cashier(&conn);
+        let today: String = conn
+            .query_row("SELECT date('now')", [], |r| r.get(0))
+            .unwrap();
+        let dest = dir.path().join("export.csv");
+
+        let err = export_sales_csv(&conn, &today, &today, &dest, cashier).unwrap_err();
+        assert!(matches!(err, AppError::Unauthorized(_)), "{err}");
+        let err = export_day_book_csv(&conn, &today, &dest, cashier).unwrap_err();
+        assert!(matches!(err, AppError::Unauthorized(_)), "{err}");
+        assert!(!dest.exists());
+    }
+}
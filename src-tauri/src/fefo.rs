@@ -0,0 +1,287 @@
+//! First-Expiry-First-Out batch allocation — the backend twin of `src/lib/fefo.ts`.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+use crate::inventory::UNEXPIRED;
+
+/// What a sale quantity counts. Stock is always held in loose units.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SaleUnit {
+    /// Whole packs (strips, bottles) of `pack_size` loose units.
+    #[default]
+    Pack,
+    Loose,
+}
+
+impl SaleUnit {
+    pub fn parse(s: &str) -> AppResult<Self> {
+        match s {
+            "pack" => Ok(Self::Pack),
+            "loose" => Ok(Self::Loose),
+            other => Err(AppError::validation(format!("Unknown sale unit '{other}'"))),
+        }
+    }
+
+    /// Loose units in one of `self`.
+    pub fn size(self, pack_size: i64) -> i64 {
+        match self {
+            Self::Pack => pack_size,
+            Self::Loose => 1,
+        }
+    }
+
+    /// Price of one of `self` given the price of a pack, to the nearest
+    /// paisa with halves rounded up.
+    pub fn price(self, pack_price_paise: i64, pack_size: i64) -> i64 {
+        match self {
+            Self::Pack => pack_price_paise,
+            Self::Loose => (pack_price_paise + pack_size / 2) / pack_size,
+        }
+    }
+}
+
+/// How a medicine is packed: loose units per pack, and the unit a line is
+/// sold in when it does not say.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Packing {
+    pub pack_size: i64,
+    pub default_unit: SaleUnit,
+}
+
+impl Packing {
+    pub fn load(conn: &Connection, medicine_id: i64) -> AppResult<Self> {
+        let (pack_size, sale_unit): (i64, Option<String>) = conn
+            .query_row(
+                "SELECT pack_size, sale_unit FROM medicines WHERE id = ?1",
+                params![medicine_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?
+            .ok_or_else(|| AppError::not_found(format!("Medicine {medicine_id}")))?;
+        Ok(Self {
+            pack_size,
+            default_unit: sale_unit
+                .as_deref()
+                .map(SaleUnit::parse)
+                .transpose()?
+                .unwrap_or_default(),
+        })
+    }
+
+    /// The unit a line is sold in: the one asked for, else the default.
+    pub fn unit(self, requested: Option<SaleUnit>) -> SaleUnit {
+        requested.unwrap_or(self.default_unit)
+    }
+
+    /// `quantity` of `unit` in loose units.
+    pub fn base_quantity(self, quantity: i64, unit: SaleUnit) -> AppResult<i64> {
+        quantity
+            .checked_mul(unit.size(self.pack_size))
+            .ok_or_else(|| AppError::validation("Requested quantity is too large"))
+    }
+}
+
+/// A sellable batch as seen by the allocator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchStock {
+    pub batch_id: i64,
+    pub batch_number: String,
+    pub expiry_date: String,
+    pub selling_price_paise: i64,
+    pub quantity: i64,
+}
+
+/// Loose units taken from one batch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchAllocation {
+    pub batch_id: i64,
+    pub batch_number: String,
+    pub expiry_date: String,
+    pub selling_price_paise: i64,
+    pub quantity: i64,
+}
+
+/// Split `requested` units across `batches`, earliest expiry first, taking
+/// whole multiples of `step` from each batch so a pack is never split
+/// across two.
+///
+/// `batches` must already be sorted by expiry and filtered to sellable
+/// stock. On a shortfall returns `Err(units_short)`.
+pub fn allocate(
+    batches: &[BatchStock],
+    requested: i64,
+    step: i64,
+) -> Result<Vec<BatchAllocation>, i64> {
+    let whole = |quantity: i64| quantity.max(0) / step * step;
+    let available: i64 = batches.iter().map(|b| whole(b.quantity)).sum();
+    if available < requested {
+        return Err(requested - available);
+    }
+
+    let mut remaining = requested;
+    let mut allocations = Vec::new();
+    for batch in batches {
+        if remaining == 0 {
+            break;
+        }
+        let take = whole(batch.quantity).min(remaining);
+        if take == 0 {
+            continue;
+        }
+        allocations.push(BatchAllocation {
+            batch_id: batch.batch_id,
+            batch_number: batch.batch_number.clone(),
+            expiry_date: batch.expiry_date.clone(),
+            selling_price_paise: batch.selling_price_paise,
+            quantity: take,
+        });
+        remaining -= take;
+    }
+    Ok(allocations)
+}
+
+/// Sellable batches of a medicine in FEFO order.
+///
+/// Sellable means available (not quarantined or blocked), in stock on the
+/// selling floor, and not past expiry (a batch expiring today still sells);
+/// the quantity given is what is on the floor. `branch_id` limits them to
+/// one branch; `None` takes every branch.
+pub fn sellable_batches(
+    conn: &Connection,
+    medicine_id: i64,
+    branch_id: Option<i64>,
+) -> AppResult<Vec<BatchStock>> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT b.id, b.batch_number, b.expiry_date, b.selling_price_paise,
+             b.quantity - COALESCE(
+                 (SELECT SUM(bl.quantity) FROM batch_locations bl WHERE bl.batch_id = b.id),
+                 0) AS on_floor
+         FROM batches b
+         WHERE b.medicine_id = ?1 AND on_floor > 0 AND {UNEXPIRED}
+           AND b.status = 'available' AND (?2 IS NULL OR b.branch_id = ?2)
+         ORDER BY b.expiry_date ASC, b.id ASC"
+    ))?;
+    let rows = stmt.query_map(params![medicine_id, branch_id], |row| {
+        Ok(BatchStock {
+            batch_id: row.get(0)?,
+            batch_number: row.get(1)?,
+            expiry_date: row.get(2)?,
+            selling_price_paise: row.get(3)?,
+            quantity: row.get(4)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+/// Pick batches to dispense `quantity` packs or loose units of a medicine,
+/// earliest expiry first. Allocations are in loose units.
+///
+/// Expired, quarantined and blocked batches are never picked, even if they
+/// hold stock. With a `branch_id`, only that branch's batches are picked.
+pub fn pick_batches_fefo(
+    conn: &Connection,
+    medicine_id: i64,
+    quantity: i64,
+    unit: Option<SaleUnit>,
+    branch_id: Option<i64>,
+) -> AppResult<Vec<BatchAllocation>> {
+    if quantity <= 0 {
+        return Err(AppError::validation(
+            "Requested quantity must be greater than 0",
+        ));
+    }
+    let packing = Packing::load(conn, medicine_id)?;
+    let unit = packing.unit(unit);
+    let base = packing.base_quantity(quantity, unit)?;
+    let batches = sellable_batches(conn, medicine_id, branch_id)?;
+    allocate(&batches, base, unit.size(packing.pack_size)).map_err(|short| {
+        AppError::validation(format!(
+            "Insufficient stock: requested {quantity}, {short} units short"
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{
+        batch_quantity, insert_batch, insert_medicine, migrated_db, paid_sale,
+    };
+    use crate::sales::{create_sale, PaymentMode};
+
+    fn batch(id: i64, expiry: &str, quantity: i64) -> BatchStock {
+        BatchStock {
+            batch_id: id,
+            batch_number: format!("B{id}"),
+            expiry_date: expiry.into(),
+            selling_price_paise: 1_000,
+            quantity,
+        }
+    }
+
+    #[test]
+    fn takes_from_earliest_expiry_first() {
+        let batches = [batch(1, "2030-01-01", 5), batch(2, "2030-06-01", 10)];
+        let plan = allocate(&batches, 8, 1).unwrap();
+        assert_eq!(plan.len(), 2);
+        assert_eq!((plan[0].batch_id, plan[0].quantity), (1, 5));
+        assert_eq!((plan[1].batch_id, plan[1].quantity), (2, 3));
+    }
+
+    #[test]
+    fn reports_units_short() {
+        let batches = [batch(1, "2030-01-01", 5)];
+        assert_eq!(allocate(&batches, 8, 1), Err(3));
+    }
+
+    #[test]
+    fn packs_are_not_split_across_batches() {
+        let batches = [batch(1, "2030-01-01", 15), batch(2, "2030-06-01", 20)];
+        let plan = allocate(&batches, 20, 10).unwrap();
+        assert_eq!((plan[0].batch_id, plan[0].quantity), (1, 10));
+        assert_eq!((plan[1].batch_id, plan[1].quantity), (2, 10));
+        assert_eq!(allocate(&batches, 40, 10), Err(10));
+    }
+
+    #[test]
+    fn loose_price_rounds_to_the_nearest_paisa() {
+        assert_eq!(SaleUnit::Loose.price(3_333, 10), 333);
+        assert_eq!(SaleUnit::Loose.price(3_335, 10), 334);
+        assert_eq!(SaleUnit::Pack.price(3_335, 10), 3_335);
+    }
+
+    #[test]
+    fn expired_stock_is_never_picked() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        insert_batch(&conn, para, "-1 day", 1_000, 50);
+        let fresh = insert_batch(&conn, para, "+90 days", 1_000, 4);
+
+        let plan = pick_batches_fefo(&conn, para, 4, None, None).unwrap();
+        assert_eq!((plan[0].batch_id, plan[0].quantity), (fresh, 4));
+
+        let err = pick_batches_fefo(&conn, para, 6, None, None).unwrap_err();
+        assert!(err.to_string().contains("2 units short"), "{err}");
+    }
+
+    #[test]
+    fn stock_expiring_today_is_picked_and_priced() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let today = insert_batch(&conn, para, "+0 days", 1_000, 3);
+        insert_batch(&conn, para, "+90 days", 1_000, 10);
+
+        let plan = pick_batches_fefo(&conn, para, 3, None, None).unwrap();
+        assert_eq!((plan[0].batch_id, plan[0].quantity), (today, 3));
+
+        let sale = paid_sale(&conn, None, PaymentMode::Cash, today, 3);
+        db.with_tx(|tx| create_sale(tx, &sale)).unwrap();
+        assert_eq!(batch_quantity(&conn, today), 0);
+    }
+}
@@ -0,0 +1,166 @@
+//! Human-readable renderings of amounts for printed documents.
+
+const ONES: [&str; 20] = [
+    "",
+    "One",
+    "Two",
+    "Three",
+    "Four",
+    "Five",
+    "Six",
+    "Seven",
+    "Eight",
+    "Nine",
+    "Ten",
+    "Eleven",
+    "Twelve",
+    "Thirteen",
+    "Fourteen",
+    "Fifteen",
+    "Sixteen",
+    "Seventeen",
+    "Eighteen",
+    "Nineteen",
+];
+
+const TENS: [&str; 10] = [
+    "", "", "Twenty", "Thirty", "Forty", "Fifty", "Sixty", "Seventy", "Eighty", "Ninety",
+];
+
+fn two_digit_words(n: u64) -> String {
+    let n = n as usize;
+    match (n / 10, n % 10) {
+        _ if n < 20 => ONES[n].to_owned(),
+        (t, 0) => TENS[t].to_owned(),
+        (t, o) => format!("{} {}", TENS[t], ONES[o]),
+    }
+}
+
+const CRORE: u64 = 10_000_000;
+const LAKH: u64 = 100_000;
+
+/// Words for a whole number of rupees in the Indian system, e.g. 1,25,000
+/// is "One Lakh Twenty Five Thousand".
+fn rupee_words(rupees: u64) -> String {
+    let mut parts = Vec::new();
+    let crores = rupees / CRORE;
+    if crores > 0 {
+        parts.push(format!("{} Crore", rupee_words(crores)));
+    }
+    for (value, unit) in [
+        (rupees % CRORE / LAKH, "Lakh"),
+        (rupees % LAKH / 1_000, "Thousand"),
+    ] {
+        if value > 0 {
+            parts.push(format!("{} {unit}", two_digit_words(value)));
+        }
+    }
+    let (hundreds, rest) = (rupees % 1_000 / 100, rupees % 100);
+    if hundreds > 0 {
+        parts.push(format!("{} Hundred", ONES[hundreds as usize]));
+    }
+    if rest > 0 {
+        parts.push(two_digit_words(rest));
+    }
+    parts.join(" ")
+}
+
+/// An amount in words as printed on an invoice, e.g. 123_450 paise is
+/// "One Thousand Two Hundred Thirty Four Rupees and Fifty Paise Only".
+///
+/// Matches `paiseToWords` in `src/lib/numberToWords.ts`; negative amounts
+/// (credit notes) are prefixed with "Minus".
+pub fn rupees_in_words(paise: i64) -> String {
+    if paise == 0 {
+        return "Zero Rupees Only".to_owned();
+    }
+    let amount = paise.unsigned_abs();
+    let (rupees, paise_part) = (amount / 100, amount % 100);
+    let mut words = if paise < 0 {
+        "Minus ".to_owned()
+    } else {
+        String::new()
+    };
+    if rupees > 0 {
+        words.push_str(&rupee_words(rupees));
+        words.push_str(if rupees == 1 { " Rupee" } else { " Rupees" });
+    }
+    if paise_part > 0 {
+        if rupees > 0 {
+            words.push_str(" and ");
+        }
+        words.push_str(&two_digit_words(paise_part));
+        words.push_str(" Paise");
+    }
+    words + " Only"
+}
+
+/// Paise as rupees with two decimals, e.g. 123_450 is "1234.50".
+pub fn rupees(paise: i64) -> String {
+    let sign = if paise < 0 { "-" } else { "" };
+    let amount = paise.unsigned_abs();
+    format!("{sign}{}.{:02}", amount / 100, amount % 100)
+}
+
+/// `YYYY-MM-DD` as `MM/YYYY`, the way expiry is printed on strips.
+pub fn expiry_month(date: &str) -> String {
+    match (date.get(0..4), date.get(5..7)) {
+        (Some(year), Some(month)) => format!("{month}/{year}"),
+        _ => date.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rupees_with_two_decimals() {
+        assert_eq!(rupees(123_450), "1234.50");
+        assert_eq!(rupees(5), "0.05");
+        assert_eq!(rupees(-40), "-0.40");
+    }
+
+    #[test]
+    fn rupees_and_paise() {
+        assert_eq!(rupees_in_words(0), "Zero Rupees Only");
+        assert_eq!(rupees_in_words(100), "One Rupee Only");
+        assert_eq!(rupees_in_words(5), "Five Paise Only");
+        assert_eq!(
+            rupees_in_words(123_450),
+            "One Thousand Two Hundred Thirty Four Rupees and Fifty Paise Only"
+        );
+    }
+
+    #[test]
+    fn lakh_boundary() {
+        assert_eq!(
+            rupees_in_words(99_999 * 100),
+            "Ninety Nine Thousand Nine Hundred Ninety Nine Rupees Only"
+        );
+        assert_eq!(rupees_in_words(100_000 * 100), "One Lakh Rupees Only");
+        assert_eq!(rupees_in_words(100_001 * 100), "One Lakh One Rupees Only");
+    }
+
+    #[test]
+    fn crore_boundary() {
+        assert_eq!(
+            rupees_in_words(9_999_999 * 100),
+            "Ninety Nine Lakh Ninety Nine Thousand Nine Hundred Ninety Nine Rupees Only"
+        );
+        assert_eq!(rupees_in_words(10_000_000 * 100), "One Crore Rupees Only");
+        assert_eq!(
+            rupees_in_words(1_250_000_000 * 100),
+            "One Hundred Twenty Five Crore Rupees Only"
+        );
+    }
+
+    #[test]
+    fn negative_amounts_are_prefixed() {
+        assert_eq!(
+            rupees_in_words(-1_050),
+            "Minus Ten Rupees and Fifty Paise Only"
+        );
+        assert!(rupees_in_words(i64::MIN).starts_with("Minus Nine Hundred"));
+    }
+}
@@ -0,0 +1,305 @@
+//! GST calculation — the backend twin of `src/lib/gst.ts`.
+//!
+//! All monetary values are integers in paise. MRP is GST-inclusive, so by
+//! default the taxable value is back-calculated; pharmacies that price
+//! before tax use [`calculate_line_exclusive`] instead. Rates are stored as REAL percentages
+//! (e.g. `5.0`, `12.0`) and converted to basis points so the arithmetic stays
+//! in integers; rounding matches `Math.round` in the frontend.
+
+use serde::Serialize;
+
+/// Convert a percentage rate to basis points (5% → 500).
+fn rate_bp(rate: f64) -> i64 {
+    (rate * 100.0).round() as i64
+}
+
+/// `round(n / d)` with halves rounded towards positive infinity, like `Math.round`.
+fn div_round(n: i64, d: i64) -> i64 {
+    (2 * n + d).div_euclid(2 * d)
+}
+
+/// Back-calculate the taxable amount from a GST-inclusive price.
+///
+/// `taxable = price * 100 / (100 + rate)`; 0% returns the price unchanged.
+pub fn taxable_from_inclusive(price_paise: i64, gst_rate: f64) -> i64 {
+    let bp = rate_bp(gst_rate);
+    if bp == 0 {
+        return price_paise;
+    }
+    div_round(price_paise * 10_000, 10_000 + bp)
+}
+
+/// GST on one line: either CGST + SGST (intra-state) or IGST (inter-state).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineTax {
+    pub cgst_rate: f64,
+    pub cgst_paise: i64,
+    pub sgst_rate: f64,
+    pub sgst_paise: i64,
+    pub igst_rate: f64,
+    pub igst_paise: i64,
+    pub total_gst_paise: i64,
+}
+
+/// Compute GST on a taxable amount.
+///
+/// The total is rounded once. Intra-state, CGST takes the floor half and
+/// SGST the rest, so `cgst + sgst` always equals the IGST an inter-state
+/// supply of the same value would carry.
+pub fn compute_line_tax(taxable_paise: i64, gst_rate: f64, interstate: bool) -> LineTax {
+    let bp = rate_bp(gst_rate);
+    let total = div_round(taxable_paise * bp, 10_000);
+    if interstate {
+        return LineTax {
+            cgst_rate: 0.0,
+            cgst_paise: 0,
+            sgst_rate: 0.0,
+            sgst_paise: 0,
+            igst_rate: gst_rate,
+            igst_paise: total,
+            total_gst_paise: total,
+        };
+    }
+    let cgst = total.div_euclid(2);
+    LineTax {
+        cgst_rate: gst_rate / 2.0,
+        cgst_paise: cgst,
+        sgst_rate: gst_rate / 2.0,
+        sgst_paise: total - cgst,
+        igst_rate: 0.0,
+        igst_paise: 0,
+        total_gst_paise: total,
+    }
+}
+
+/// A fully computed sale line.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineAmounts {
+    pub unit_price_paise: i64,
+    pub quantity: i64,
+    pub discount_paise: i64,
+    pub taxable_amount_paise: i64,
+    pub gst: LineTax,
+    pub total_paise: i64,
+}
+
+/// Compute a line: `unit * qty - discount` is GST-inclusive; tax is backed out.
+pub fn calculate_line(
+    unit_price_paise: i64,
+    quantity: i64,
+    gst_rate: f64,
+    discount_paise: i64,
+    interstate: bool,
+) -> LineAmounts {
+    let after_discount = unit_price_paise * quantity - discount_paise;
+    let taxable = taxable_from_inclusive(after_discount, gst_rate);
+    let gst = compute_line_tax(taxable, gst_rate, interstate);
+    LineAmounts {
+        unit_price_paise,
+        quantity,
+        discount_paise,
+        taxable_amount_paise: taxable,
+        gst,
+        total_paise: taxable + gst.total_gst_paise,
+    }
+}
+
+/// Compute a line whose price is before tax: `unit * qty - discount` is the
+/// taxable value and GST is added on top.
+pub fn calculate_line_exclusive(
+    unit_price_paise: i64,
+    quantity: i64,
+    gst_rate: f64,
+    discount_paise: i64,
+    interstate: bool,
+) -> LineAmounts {
+    let taxable = unit_price_paise * quantity - discount_paise;
+    let gst = compute_line_tax(taxable, gst_rate, interstate);
+    LineAmounts {
+        unit_price_paise,
+        quantity,
+        discount_paise,
+        taxable_amount_paise: taxable,
+        gst,
+        total_paise: taxable + gst.total_gst_paise,
+    }
+}
+
+/// Round a payable amount to the nearest rupee; 50 paise rounds up.
+pub fn round_to_rupee(paise: i64) -> i64 {
+    (paise + 50).div_euclid(100) * 100
+}
+
+/// Invoice-level totals aggregated from lines.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvoiceTotals {
+    pub subtotal_paise: i64,
+    pub discount_paise: i64,
+    pub total_cgst_paise: i64,
+    pub total_sgst_paise: i64,
+    pub total_igst_paise: i64,
+    pub total_gst_paise: i64,
+    /// Added to the sum of the lines to reach a whole rupee; may be negative.
+    pub round_off_paise: i64,
+    /// The payable amount, rounded to the rupee.
+    pub grand_total_paise: i64,
+}
+
+/// Sum the lines, then round only the payable amount; tax and subtotal
+/// figures are left exact.
+pub fn invoice_totals<'a>(lines: impl IntoIterator<Item = &'a LineAmounts>) -> InvoiceTotals {
+    let mut totals = lines
+        .into_iter()
+        .fold(InvoiceTotals::default(), |mut t, line| {
+            t.subtotal_paise += line.unit_price_paise * line.quantity;
+            t.discount_paise += line.discount_paise;
+            t.total_cgst_paise += line.gst.cgst_paise;
+            t.total_sgst_paise += line.gst.sgst_paise;
+            t.total_igst_paise += line.gst.igst_paise;
+            t.total_gst_paise += line.gst.total_gst_paise;
+            t.grand_total_paise += line.total_paise;
+            t
+        });
+    let rounded = round_to_rupee(totals.grand_total_paise);
+    totals.round_off_paise = rounded - totals.grand_total_paise;
+    totals.grand_total_paise = rounded;
+    totals
+}
+
+/// GST state and union territory codes, as used in GSTINs and place of supply.
+const STATES: &[(&str, &str)] = &[
+    ("01", "Jammu & Kashmir"),
+    ("02", "Himachal Pradesh"),
+    ("03", "Punjab"),
+    ("04", "Chandigarh"),
+    ("05", "Uttarakhand"),
+    ("06", "Haryana"),
+    ("07", "Delhi"),
+    ("08", "Rajasthan"),
+    ("09", "Uttar Pradesh"),
+    ("10", "Bihar"),
+    ("11", "Sikkim"),
+    ("12", "Arunachal Pradesh"),
+    ("13", "Nagaland"),
+    ("14", "Manipur"),
+    ("15", "Mizoram"),
+    ("16", "Tripura"),
+    ("17", "Meghalaya"),
+    ("18", "Assam"),
+    ("19", "West Bengal"),
+    ("20", "Jharkhand"),
+    ("21", "Odisha"),
+    ("22", "Chhattisgarh"),
+    ("23", "Madhya Pradesh"),
+    ("24", "Gujarat"),
+    ("25", "Daman & Diu"),
+    ("26", "Dadra & Nagar Haveli & Daman & Diu"),
+    ("27", "Maharashtra"),
+    ("28", "Andhra Pradesh (Before Division)"),
+    ("29", "Karnataka"),
+    ("30", "Goa"),
+    ("31", "Lakshadweep"),
+    ("32", "Kerala"),
+    ("33", "Tamil Nadu"),
+    ("34", "Puducherry"),
+    ("35", "Andaman & Nicobar Islands"),
+    ("36", "Telangana"),
+    ("37", "Andhra Pradesh"),
+    ("38", "Ladakh"),
+    ("97", "Other Territory"),
+];
+
+/// The name of a two-digit GST state code, e.g. `27` is Maharashtra.
+pub fn state_name(code: &str) -> Option<&'static str> {
+    STATES
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map(|(_, name)| *name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn back_calculates_taxable_like_the_frontend() {
+        // 10000 * 100 / 105 = 9523.81 → 9524
+        assert_eq!(taxable_from_inclusive(10_000, 5.0), 9_524);
+        assert_eq!(taxable_from_inclusive(11_200, 12.0), 10_000);
+        assert_eq!(taxable_from_inclusive(5_000, 0.0), 5_000);
+    }
+
+    #[test]
+    fn cgst_and_sgst_sum_to_the_total() {
+        let gst = compute_line_tax(9_524, 5.0, false);
+        assert_eq!(gst.total_gst_paise, 476);
+        assert_eq!(gst.cgst_rate, 2.5);
+        assert_eq!(gst.cgst_paise + gst.sgst_paise, gst.total_gst_paise);
+
+        // Odd total: 1 paise difference goes to SGST
+        let odd = compute_line_tax(1_000, 18.0, false);
+        assert_eq!(odd.total_gst_paise, 180);
+        let odd = compute_line_tax(1_010, 5.0, false);
+        assert_eq!((odd.cgst_paise, odd.sgst_paise), (25, 26));
+    }
+
+    #[test]
+    fn interstate_puts_the_full_rate_into_igst() {
+        let igst = compute_line_tax(9_524, 12.0, true);
+        assert_eq!(
+            (igst.igst_rate, igst.cgst_paise, igst.sgst_paise),
+            (12.0, 0, 0)
+        );
+        assert_eq!(igst.igst_paise, igst.total_gst_paise);
+    }
+
+    #[test]
+    fn split_halves_always_match_igst_to_the_paisa() {
+        for taxable in [1, 99, 1_010, 9_524, 12_345, 99_999] {
+            for rate in [0.0, 5.0, 12.0, 18.0, 28.0] {
+                let intra = compute_line_tax(taxable, rate, false);
+                let inter = compute_line_tax(taxable, rate, true);
+                assert_eq!(intra.cgst_paise + intra.sgst_paise, inter.igst_paise);
+            }
+        }
+    }
+
+    #[test]
+    fn inclusive_line_total_stays_within_a_paisa_of_price() {
+        let line = calculate_line(10_000, 3, 12.0, 500, false);
+        assert_eq!(line.taxable_amount_paise, 26_339);
+        assert!((line.total_paise - 29_500).abs() <= 1);
+    }
+
+    #[test]
+    fn totals_aggregate_every_line() {
+        let a = calculate_line(10_000, 1, 5.0, 0, false);
+        let b = calculate_line(11_800, 2, 18.0, 0, true);
+        let totals = invoice_totals([&a, &b]);
+        assert_eq!(totals.subtotal_paise, 33_600);
+        assert_eq!(
+            totals.total_gst_paise,
+            a.gst.total_gst_paise + b.gst.total_gst_paise
+        );
+        assert_eq!(totals.total_igst_paise, b.gst.igst_paise);
+        assert_eq!(totals.grand_total_paise, a.total_paise + b.total_paise);
+    }
+
+    #[test]
+    fn grand_total_rounds_to_the_nearest_rupee() {
+        assert_eq!(round_to_rupee(12_349), 12_300);
+        assert_eq!(round_to_rupee(12_350), 12_400);
+        assert_eq!(round_to_rupee(12_351), 12_400);
+
+        // ₹47.25 at 5%: the round-off is negative and tax is untouched.
+        let line = calculate_line(4_725, 1, 5.0, 0, false);
+        let totals = invoice_totals([&line]);
+        assert_eq!(totals.round_off_paise, -25);
+        assert_eq!(totals.grand_total_paise, 4_700);
+        assert_eq!(totals.total_gst_paise, line.gst.total_gst_paise);
+        assert_eq!(totals.subtotal_paise, 4_725);
+    }
+}
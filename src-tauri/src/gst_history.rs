@@ -0,0 +1,237 @@
+//! When the GST slab of a medicine changed, so each sale is taxed at the
+//! rate in force on its date.
+//!
+//! Each medicine has one open period (`effective_to` is NULL) and any
+//! number of closed ones; periods run from `effective_from` up to, but not
+//! including, `effective_to`.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::json;
+
+use crate::auth::{self, permission};
+use crate::db::{ChangeOp, Entity, Tx};
+use crate::error::{AppError, AppResult};
+use crate::sync;
+
+/// The GST rate of a medicine on `date` (`YYYY-MM-DD`).
+///
+/// Falls back to the medicine's current slab for a date before its history
+/// starts.
+pub fn rate_on(conn: &Connection, medicine_id: i64, date: &str) -> AppResult<f64> {
+    conn.query_row(
+        "SELECT g.rate
+         FROM medicines m
+         JOIN gst_slabs g ON g.id = COALESCE(
+             (SELECT h.gst_slab_id FROM medicine_gst_history h
+              WHERE h.medicine_id = m.id AND h.effective_from <= ?2
+                AND (h.effective_to IS NULL OR h.effective_to > ?2)),
+             m.gst_slab_id)
+         WHERE m.id = ?1",
+        params![medicine_id, date],
+        |row| row.get(0),
+    )
+    .optional()?
+    .ok_or_else(|| AppError::not_found(format!("Medicine {medicine_id}")))
+}
+
+/// Move a medicine to a new GST slab from `effective_from` onwards.
+///
+/// The open period is closed at `effective_from`, so sales dated earlier
+/// keep the old rate. A change on the day the open period starts corrects
+/// that period instead. The medicine's current slab is updated once the
+/// change is in force; a change dated in the future only applies to sales
+/// from that date.
+pub fn set_medicine_gst(
+    tx: &Tx,
+    medicine_id: i64,
+    gst_slab_id: i64,
+    effective_from: &str,
+    user_id: i64,
+) -> AppResult<()> {
+    auth::require_permission(tx, user_id, permission::MEDICINES_MANAGE)?;
+    let valid: bool = tx.query_row("SELECT date(?1) IS ?1", [effective_from], |row| row.get(0))?;
+    if !valid {
+        return Err(AppError::validation(
+            "The effective date must be in YYYY-MM-DD format",
+        ));
+    }
+    let slab_exists: bool = tx.query_row(
+        "SELECT EXISTS(SELECT 1 FROM gst_slabs WHERE id = ?1)",
+        params![gst_slab_id],
+        |row| row.get(0),
+    )?;
+    if !slab_exists {
+        return Err(AppError::not_found(format!("GST slab {gst_slab_id}")));
+    }
+    let current_slab: i64 = tx
+        .query_row(
+            "SELECT gst_slab_id FROM medicines WHERE id = ?1",
+            params![medicine_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Medicine {medicine_id}")))?;
+
+    let open: Option<(i64, i64, String)> = tx
+        .query_row(
+            "SELECT id, gst_slab_id, effective_from FROM medicine_gst_history
+             WHERE medicine_id = ?1 AND effective_to IS NULL",
+            params![medicine_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?;
+    match open {
+        Some((_, slab, _)) if slab == gst_slab_id => return Ok(()),
+        Some((id, _, from)) if from == effective_from => {
+            tx.execute(
+                "UPDATE medicine_gst_history SET gst_slab_id = ?1 WHERE id = ?2",
+                params![gst_slab_id, id],
+            )?;
+        }
+        Some((_, _, from)) if from.as_str() > effective_from => {
+            return Err(AppError::validation(format!(
+                "The current GST slab has applied since {from}; a change cannot start earlier"
+            )));
+        }
+        Some((id, _, _)) => {
+            tx.execute(
+                "UPDATE medicine_gst_history SET effective_to = ?1 WHERE id = ?2",
+                params![effective_from, id],
+            )?;
+            open_period(tx, medicine_id, gst_slab_id, effective_from)?;
+        }
+        None if current_slab == gst_slab_id => return Ok(()),
+        None => open_period(tx, medicine_id, gst_slab_id, effective_from)?,
+    }
+
+    tx.execute(
+        "UPDATE medicines SET gst_slab_id = ?1 WHERE id = ?2 AND ?3 <= date('now')",
+        params![gst_slab_id, medicine_id, effective_from],
+    )?;
+    tx.changed(Entity::Medicine, ChangeOp::Update, medicine_id);
+    sync::enqueue(
+        tx,
+        "set_medicine_gst",
+        &json!({
+            "medicineId": medicine_id,
+            "gstSlabId": gst_slab_id,
+            "effectiveFrom": effective_from,
+        }),
+    )?;
+    Ok(())
+}
+
+fn open_period(tx: &Tx, medicine_id: i64, gst_slab_id: i64, effective_from: &str) -> AppResult<()> {
+    tx.execute(
+        "INSERT INTO medicine_gst_history (medicine_id, gst_slab_id, effective_from)
+         VALUES (?1, ?2, ?3)",
+        params![medicine_id, gst_slab_id, effective_from],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{
+        insert_batch, insert_cashier, insert_medicine, migrated_db, paid_sale,
+    };
+    use crate::sales::{create_sale, PaymentMode};
+
+    const ADMIN: i64 = 1;
+
+    fn slab(conn: &Connection, rate: f64) -> i64 {
+        conn.query_row("SELECT id FROM gst_slabs WHERE rate = ?1", [rate], |row| {
+            row.get(0)
+        })
+        .unwrap()
+    }
+
+    fn day(conn: &Connection, offset: &str) -> String {
+        conn.query_row("SELECT date('now', ?1)", [offset], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn future_change_keeps_todays_sales_on_the_old_rate() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+90 days", 1_120, 10);
+        let tomorrow = day(&conn, "+1 day");
+
+        db.with_tx(|tx| set_medicine_gst(tx, para, slab(&conn, 5.0), &tomorrow, ADMIN))
+            .unwrap();
+
+        assert_eq!(rate_on(&conn, para, &day(&conn, "+0 days")).unwrap(), 12.0);
+        assert_eq!(rate_on(&conn, para, &tomorrow).unwrap(), 5.0);
+        let sale = paid_sale(&conn, None, PaymentMode::Cash, batch, 1);
+        let result = db.with_tx(|tx| create_sale(tx, &sale)).unwrap();
+        let rate: f64 = conn
+            .query_row(
+                "SELECT cgst_rate + sgst_rate + igst_rate FROM sale_items WHERE sale_id = ?1",
+                [result.sale_id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(rate, 12.0);
+
+        let err = db
+            .with_tx(|tx| {
+                set_medicine_gst(tx, para, slab(&conn, 18.0), &day(&conn, "+0 days"), ADMIN)
+            })
+            .unwrap_err();
+        assert!(err.to_string().contains("cannot start earlier"), "{err}");
+    }
+
+    #[test]
+    fn change_in_force_today_updates_the_current_slab() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let five = slab(&conn, 5.0);
+        let yesterday = day(&conn, "-1 day");
+        conn.execute(
+            "UPDATE medicine_gst_history SET effective_from = ?1 WHERE medicine_id = ?2",
+            params![yesterday, para],
+        )
+        .unwrap();
+
+        db.with_tx(|tx| set_medicine_gst(tx, para, five, &day(&conn, "+0 days"), ADMIN))
+            .unwrap();
+
+        let current: i64 = conn
+            .query_row(
+                "SELECT gst_slab_id FROM medicines WHERE id = ?1",
+                [para],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(current, five);
+        assert_eq!(rate_on(&conn, para, &yesterday).unwrap(), 12.0);
+        let periods: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM medicine_gst_history WHERE medicine_id = ?1",
+                [para],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(periods, 2);
+    }
+
+    #[test]
+    fn cashier_cannot_change_a_tax_rate() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let cashier = insert_cashier(&conn);
+
+        let err = db
+            .with_tx(|tx| {
+                set_medicine_gst(tx, para, slab(&conn, 5.0), &day(&conn, "+1 day"), cashier)
+            })
+            .unwrap_err();
+        assert!(matches!(err, AppError::Unauthorized(_)), "{err}");
+        assert_eq!(rate_on(&conn, para, &day(&conn, "+1 day")).unwrap(), 12.0);
+    }
+}
@@ -0,0 +1,753 @@
+//! GSTR-1, the monthly return of outward supplies.
+//!
+//! Sales for a month are grouped into the sections a pharmacy files: B2B
+//! invoices to registered customers, large inter-state B2C invoices (B2CL),
+//! the remaining B2C supplies summarised by state and rate (B2CS), the HSN
+//! summary and the documents issued. The report serialises as the portal's
+//! JSON, with amounts in rupees; [`export_gstr1_csv`] writes the sheets the
+//! offline tool imports instead.
+//!
+//! The HSN section is [`reports::hsn_tax_summary`] for the month. Refunded
+//! sales are left out of every value section, as there, and their invoice
+//! numbers are still counted as issued. Partial returns are not netted off;
+//! credit notes are filed separately.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+use crate::auth::{self, permission};
+use crate::error::{AppError, AppResult};
+use crate::export::{csv_field, ExportSummary};
+use crate::gst;
+use crate::reports;
+
+/// Inter-state B2C invoices above ₹1 lakh are reported one by one in B2CL.
+const B2CL_THRESHOLD_PAISE: i64 = 10_000_000;
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+#[derive(Debug, Serialize)]
+pub struct Gstr1 {
+    pub gstin: String,
+    /// Filing period, `MMYYYY`.
+    pub fp: String,
+    pub b2b: Vec<B2bParty>,
+    pub b2cl: Vec<B2clState>,
+    pub b2cs: Vec<B2csRow>,
+    pub hsn: HsnSection,
+    pub doc_issue: DocIssue,
+}
+
+#[derive(Debug, Serialize)]
+pub struct B2bParty {
+    pub ctin: String,
+    /// Shown in the offline tool's sheet; not part of the portal JSON.
+    #[serde(skip)]
+    pub name: String,
+    pub inv: Vec<B2bInvoice>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct B2bInvoice {
+    pub inum: String,
+    /// `dd-mm-yyyy`
+    pub idt: String,
+    pub val: f64,
+    pub pos: String,
+    pub rchrg: &'static str,
+    pub inv_typ: &'static str,
+    pub itms: Vec<Item>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct B2clState {
+    pub pos: String,
+    pub inv: Vec<B2clInvoice>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct B2clInvoice {
+    pub inum: String,
+    /// `dd-mm-yyyy`
+    pub idt: String,
+    pub val: f64,
+    pub itms: Vec<Item>,
+}
+
+/// One rate on an invoice.
+#[derive(Debug, Serialize)]
+pub struct Item {
+    pub num: u32,
+    pub itm_det: ItemDetail,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ItemDetail {
+    pub txval: f64,
+    pub rt: f64,
+    pub iamt: f64,
+    pub camt: f64,
+    pub samt: f64,
+    pub csamt: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct B2csRow {
+    /// `INTRA` or `INTER`.
+    pub sply_ty: &'static str,
+    pub pos: String,
+    pub typ: &'static str,
+    pub rt: f64,
+    pub txval: f64,
+    pub iamt: f64,
+    pub camt: f64,
+    pub samt: f64,
+    pub csamt: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HsnSection {
+    pub data: Vec<HsnRow>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HsnRow {
+    pub num: u32,
+    pub hsn_sc: String,
+    pub uqc: &'static str,
+    pub qty: i64,
+    pub rt: f64,
+    pub txval: f64,
+    pub iamt: f64,
+    pub camt: f64,
+    pub samt: f64,
+    pub csamt: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DocIssue {
+    pub doc_det: Vec<DocType>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DocType {
+    /// 1 for outward invoices, 5 for credit notes.
+    pub doc_num: u8,
+    pub docs: Vec<DocRange>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DocRange {
+    pub num: u32,
+    pub from: String,
+    pub to: String,
+    pub totnum: i64,
+    pub cancel: i64,
+    pub net_issue: i64,
+}
+
+fn rupees(paise: i64) -> f64 {
+    paise as f64 / 100.0
+}
+
+/// Taxable value and tax in paise, for one rate.
+#[derive(Debug, Clone, Copy, Default)]
+struct Amounts {
+    taxable: i64,
+    igst: i64,
+    cgst: i64,
+    sgst: i64,
+}
+
+impl Amounts {
+    fn add(&mut self, other: Amounts) {
+        self.taxable += other.taxable;
+        self.igst += other.igst;
+        self.cgst += other.cgst;
+        self.sgst += other.sgst;
+    }
+
+    fn detail(self, rate_bp: i64) -> ItemDetail {
+        ItemDetail {
+            txval: rupees(self.taxable),
+            rt: rate_bp as f64 / 100.0,
+            iamt: rupees(self.igst),
+            camt: rupees(self.cgst),
+            samt: rupees(self.sgst),
+            csamt: 0.0,
+        }
+    }
+}
+
+/// The first and last day of a `YYYY-MM` period, and the period as `MMYYYY`.
+pub(crate) fn period_bounds(
+    conn: &Connection,
+    period: &str,
+) -> AppResult<(String, String, String)> {
+    let valid = period.len() == 7
+        && period.as_bytes()[4] == b'-'
+        && period[..4].bytes().all(|c| c.is_ascii_digit())
+        && matches!(period[5..].parse::<u32>(), Ok(1..=12));
+    if !valid {
+        return Err(AppError::validation(format!(
+            "Return period must be YYYY-MM, got {period:?}"
+        )));
+    }
+    let (from, to): (String, String) = conn.query_row(
+        "SELECT ?1 || '-01', date(?1 || '-01', '+1 month', '-1 day')",
+        [period],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    Ok((from, to, format!("{}{}", &period[5..], &period[..4])))
+}
+
+/// `dd-mm-yyyy` as the offline tool's `dd-Mon-yyyy`.
+fn tool_date(idt: &str) -> String {
+    let month = idt
+        .get(3..5)
+        .and_then(|m| m.parse::<usize>().ok())
+        .and_then(|m| MONTHS.get(m.wrapping_sub(1)));
+    match month {
+        Some(month) => format!("{}-{month}-{}", &idt[..2], &idt[6..]),
+        None => idt.to_string(),
+    }
+}
+
+/// Place of supply as the offline tool spells it, e.g. `27-Maharashtra`.
+fn tool_place(code: &str) -> String {
+    match gst::state_name(code) {
+        Some(name) => format!("{code}-{name}"),
+        None => code.to_string(),
+    }
+}
+
+/// Items for an invoice's rates, numbered in rate order.
+fn items(rates: &BTreeMap<i64, Amounts>) -> Vec<Item> {
+    rates
+        .iter()
+        .enumerate()
+        .map(|(i, (&rate, amounts))| Item {
+            num: i as u32 + 1,
+            itm_det: amounts.detail(rate),
+        })
+        .collect()
+}
+
+struct SaleRates {
+    invoice_number: String,
+    date: String,
+    grand_total_paise: i64,
+    interstate: bool,
+    name: String,
+    gstin: String,
+    state_code: String,
+    rates: BTreeMap<i64, Amounts>,
+}
+
+/// Documents of one kind, first to last in the order they were issued.
+fn doc_range(conn: &Connection, sql: &str, from: &str, to: &str) -> AppResult<Vec<DocRange>> {
+    let mut stmt = conn.prepare(sql)?;
+    let numbers = stmt
+        .query_map([from, to], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    let (Some(first), Some(last)) = (numbers.first(), numbers.last()) else {
+        return Ok(Vec::new());
+    };
+    let count = numbers.len() as i64;
+    Ok(vec![DocRange {
+        num: 1,
+        from: first.clone(),
+        to: last.clone(),
+        totnum: count,
+        cancel: 0,
+        net_issue: count,
+    }])
+}
+
+/// Build GSTR-1 for a `YYYY-MM` period.
+///
+/// A sale is B2B when its customer has a GSTIN. Place of supply is the one
+/// recorded with the sale: the customer's state, taken from the GSTIN when no
+/// state is recorded, and the pharmacy's own state for walk-in sales.
+pub fn gstr1_report(conn: &Connection, period: &str) -> AppResult<Gstr1> {
+    let (from, to, fp) = period_bounds(conn, period)?;
+    let (gstin, home_state, composition): (String, String, bool) = conn.query_row(
+        "SELECT gstin, state_code, composition_scheme FROM pharmacy_settings WHERE id = 1",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    if composition {
+        return Err(AppError::validation(
+            "Composition dealers file CMP-08 and GSTR-4 instead of GSTR-1",
+        ));
+    }
+    let gstin = gstin.trim().to_string();
+    if gstin.is_empty() {
+        return Err(AppError::validation(
+            "Set the pharmacy's GSTIN in settings before preparing GSTR-1",
+        ));
+    }
+
+    let mut sales: Vec<SaleRates> = Vec::new();
+    {
+        let mut stmt = conn.prepare(
+            "SELECT s.id, s.invoice_number, strftime('%d-%m-%Y', s.sale_date),
+                 s.grand_total_paise, s.is_interstate, COALESCE(c.name, ''),
+                 TRIM(COALESCE(c.gstin, '')),
+                 COALESCE(s.place_of_supply, NULLIF(c.state_code, ''), substr(c.gstin, 1, 2), ''),
+                 CAST(ROUND((si.cgst_rate + si.sgst_rate + si.igst_rate) * 100) AS INTEGER)
+                     AS rate_bp,
+                 SUM(si.taxable_amount_paise), SUM(si.igst_amount_paise),
+                 SUM(si.cgst_amount_paise), SUM(si.sgst_amount_paise)
+             FROM sales s
+             JOIN sale_items si ON si.sale_id = s.id
+             LEFT JOIN customers c ON c.id = s.customer_id
+             WHERE date(s.sale_date) BETWEEN ?1 AND ?2 AND s.status <> 'refunded'
+             GROUP BY s.id, rate_bp
+             ORDER BY s.sale_date, s.id, rate_bp",
+        )?;
+        let mut rows = stmt.query(params![from, to])?;
+        let mut last_id = None;
+        while let Some(row) = rows.next()? {
+            let id: i64 = row.get(0)?;
+            if last_id != Some(id) {
+                last_id = Some(id);
+                let state_code: String = row.get(7)?;
+                sales.push(SaleRates {
+                    invoice_number: row.get(1)?,
+                    date: row.get(2)?,
+                    grand_total_paise: row.get(3)?,
+                    interstate: row.get(4)?,
+                    name: row.get(5)?,
+                    gstin: row.get(6)?,
+                    state_code: if state_code.is_empty() {
+                        home_state.clone()
+                    } else {
+                        state_code
+                    },
+                    rates: BTreeMap::new(),
+                });
+            }
+            let amounts = Amounts {
+                taxable: row.get(9)?,
+                igst: row.get(10)?,
+                cgst: row.get(11)?,
+                sgst: row.get(12)?,
+            };
+            if let Some(sale) = sales.last_mut() {
+                sale.rates.insert(row.get(8)?, amounts);
+            }
+        }
+    }
+
+    let mut b2b: BTreeMap<String, B2bParty> = BTreeMap::new();
+    let mut b2cl: BTreeMap<String, Vec<B2clInvoice>> = BTreeMap::new();
+    let mut b2cs: BTreeMap<(bool, String, i64), Amounts> = BTreeMap::new();
+    for sale in sales {
+        if !sale.gstin.is_empty() {
+            let party = b2b.entry(sale.gstin.clone()).or_insert_with(|| B2bParty {
+                ctin: sale.gstin.clone(),
+                name: sale.name.clone(),
+                inv: Vec::new(),
+            });
+            party.inv.push(B2bInvoice {
+                inum: sale.invoice_number,
+                idt: sale.date,
+                val: rupees(sale.grand_total_paise),
+                pos: sale.state_code,
+                rchrg: "N",
+                inv_typ: "R",
+                itms: items(&sale.rates),
+            });
+        } else if sale.interstate && sale.grand_total_paise > B2CL_THRESHOLD_PAISE {
+            b2cl.entry(sale.state_code).or_default().push(B2clInvoice {
+                inum: sale.invoice_number,
+                idt: sale.date,
+                val: rupees(sale.grand_total_paise),
+                itms: items(&sale.rates),
+            });
+        } else {
+            for (rate, amounts) in sale.rates {
+                b2cs.entry((sale.interstate, sale.state_code.clone(), rate))
+                    .or_default()
+                    .add(amounts);
+            }
+        }
+    }
+
+    let hsn = HsnSection {
+        data: reports::hsn_tax_summary(conn, &from, &to)?
+            .into_iter()
+            .enumerate()
+            .map(|(i, row)| {
+                let detail = Amounts {
+                    taxable: row.taxable_value_paise,
+                    igst: row.igst_paise,
+                    cgst: row.cgst_paise,
+                    sgst: row.sgst_paise,
+                }
+                .detail((row.gst_rate * 100.0).round() as i64);
+                HsnRow {
+                    num: i as u32 + 1,
+                    hsn_sc: row.hsn_code,
+                    uqc: "NOS",
+                    qty: row.total_quantity,
+                    rt: detail.rt,
+                    txval: detail.txval,
+                    iamt: detail.iamt,
+                    camt: detail.camt,
+                    samt: detail.samt,
+                    csamt: 0.0,
+                }
+            })
+            .collect(),
+    };
+
+    let invoices = doc_range(
+        conn,
+        "SELECT invoice_number FROM sales
+         WHERE date(sale_date) BETWEEN ?1 AND ?2 ORDER BY id",
+        &from,
+        &to,
+    )?;
+    let credit_notes = doc_range(
+        conn,
+        "SELECT credit_note_number FROM sale_returns
+         WHERE date(return_date) BETWEEN ?1 AND ?2 ORDER BY id",
+        &from,
+        &to,
+    )?;
+    let doc_det = [(1, invoices), (5, credit_notes)]
+        .into_iter()
+        .filter(|(_, docs)| !docs.is_empty())
+        .map(|(doc_num, docs)| DocType { doc_num, docs })
+        .collect();
+
+    Ok(Gstr1 {
+        gstin,
+        fp,
+        b2b: b2b.into_values().collect(),
+        b2cl: b2cl
+            .into_iter()
+            .map(|(pos, inv)| B2clState { pos, inv })
+            .collect(),
+        b2cs: b2cs
+            .into_iter()
+            .map(|((interstate, pos, rate), amounts)| {
+                let detail = amounts.detail(rate);
+                B2csRow {
+                    sply_ty: if interstate { "INTER" } else { "INTRA" },
+                    pos,
+                    typ: "OE",
+                    rt: detail.rt,
+                    txval: detail.txval,
+                    iamt: detail.iamt,
+                    camt: detail.camt,
+                    samt: detail.samt,
+                    csamt: 0.0,
+                }
+            })
+            .collect(),
+        hsn,
+        doc_issue: DocIssue { doc_det },
+    })
+}
+
+/// Write GSTR-1 for `period` to `dest` as the portal's JSON.
+///
+/// `rows_written` counts the invoices, summary rows and document ranges.
+pub fn export_gstr1_json(
+    conn: &Connection,
+    period: &str,
+    dest: &Path,
+    user_id: i64,
+) -> AppResult<ExportSummary> {
+    auth::require_permission(conn, user_id, permission::REPORTS_EXPORT)?;
+    let report = gstr1_report(conn, period)?;
+    let entries = report.b2b.iter().map(|p| p.inv.len()).sum::<usize>()
+        + report.b2cl.iter().map(|s| s.inv.len()).sum::<usize>()
+        + report.b2cs.len()
+        + report.hsn.data.len()
+        + report.doc_issue.doc_det.len();
+    let json = serde_json::to_vec_pretty(&report)
+        .map_err(|e| AppError::validation(format!("Cannot encode GSTR-1: {e}")))?;
+    std::fs::write(dest, json)?;
+    Ok(ExportSummary {
+        path: dest.to_string_lossy().into_owned(),
+        rows_written: entries as u64,
+    })
+}
+
+fn write_sheet(
+    dest_dir: &Path,
+    file_name: &str,
+    header: &str,
+    rows: Vec<Vec<String>>,
+) -> AppResult<ExportSummary> {
+    let path = dest_dir.join(file_name);
+    let mut out = BufWriter::new(File::create(&path)?);
+    writeln!(out, "{header}")?;
+    for row in &rows {
+        let fields: Vec<String> = row.iter().map(|f| csv_field(f)).collect();
+        writeln!(out, "{}", fields.join(","))?;
+    }
+    out.flush()?;
+    Ok(ExportSummary {
+        path: path.to_string_lossy().into_owned(),
+        rows_written: rows.len() as u64,
+    })
+}
+
+/// Write GSTR-1 for `period` into `dest_dir` as the offline tool's CSV
+/// sheets: `b2b.csv`, `b2cl.csv`, `b2cs.csv`, `hsn.csv` and `docs.csv`.
+///
+/// Invoices carry one row per rate, as the tool expects.
+pub fn export_gstr1_csv(
+    conn: &Connection,
+    period: &str,
+    dest_dir: &Path,
+    user_id: i64,
+) -> AppResult<Vec<ExportSummary>> {
+    auth::require_permission(conn, user_id, permission::REPORTS_EXPORT)?;
+    let report = gstr1_report(conn, period)?;
+    let money = |v: f64| format!("{v:.2}");
+
+    let mut b2b = Vec::new();
+    for party in &report.b2b {
+        for inv in &party.inv {
+            for item in &inv.itms {
+                b2b.push(vec![
+                    party.ctin.clone(),
+                    party.name.clone(),
+                    inv.inum.clone(),
+                    tool_date(&inv.idt),
+                    money(inv.val),
+                    tool_place(&inv.pos),
+                    inv.rchrg.to_string(),
+                    String::new(),
+                    "Regular B2B".to_string(),
+                    String::new(),
+                    item.itm_det.rt.to_string(),
+                    money(item.itm_det.txval),
+                    money(item.itm_det.csamt),
+                ]);
+            }
+        }
+    }
+
+    let mut b2cl = Vec::new();
+    for state in &report.b2cl {
+        for inv in &state.inv {
+            for item in &inv.itms {
+                b2cl.push(vec![
+                    inv.inum.clone(),
+                    tool_date(&inv.idt),
+                    money(inv.val),
+                    tool_place(&state.pos),
+                    String::new(),
+                    item.itm_det.rt.to_string(),
+                    money(item.itm_det.txval),
+                    money(item.itm_det.csamt),
+                    String::new(),
+                ]);
+            }
+        }
+    }
+
+    let b2cs = report
+        .b2cs
+        .iter()
+        .map(|row| {
+            vec![
+                row.typ.to_string(),
+                tool_place(&row.pos),
+                String::new(),
+                row.rt.to_string(),
+                money(row.txval),
+                money(row.csamt),
+                String::new(),
+            ]
+        })
+        .collect();
+
+    let hsn = report
+        .hsn
+        .data
+        .iter()
+        .map(|row| {
+            let total = rupees(
+                [row.txval, row.iamt, row.camt, row.samt, row.csamt]
+                    .iter()
+                    .map(|v| (v * 100.0).round() as i64)
+                    .sum(),
+            );
+            vec![
+                row.hsn_sc.clone(),
+                String::new(),
+                "NOS-NUMBERS".to_string(),
+                row.qty.to_string(),
+                money(total),
+                money(row.txval),
+                money(row.iamt),
+                money(row.camt),
+                money(row.samt),
+                money(row.csamt),
+                row.rt.to_string(),
+            ]
+        })
+        .collect();
+
+    let mut docs = Vec::new();
+    for doc_type in &report.doc_issue.doc_det {
+        let nature = match doc_type.doc_num {
+            1 => "Invoices for outward supply",
+            _ => "Credit Note",
+        };
+        for range in &doc_type.docs {
+            docs.push(vec![
+                nature.to_string(),
+                range.from.clone(),
+                range.to.clone(),
+                range.totnum.to_string(),
+                range.cancel.to_string(),
+            ]);
+        }
+    }
+
+    Ok(vec![
+        write_sheet(
+            dest_dir,
+            "b2b.csv",
+            "GSTIN/UIN of Recipient,Receiver Name,Invoice Number,Invoice date,Invoice Value,\
+             Place Of Supply,Reverse Charge,Applicable % of Tax Rate,Invoice Type,\
+             E-Commerce GSTIN,Rate,Taxable Value,Cess Amount",
+            b2b,
+        )?,
+        write_sheet(
+            dest_dir,
+            "b2cl.csv",
+            "Invoice Number,Invoice date,Invoice Value,Place Of Supply,\
+             Applicable % of Tax Rate,Rate,Taxable Value,Cess Amount,E-Commerce GSTIN",
+            b2cl,
+        )?,
+        write_sheet(
+            dest_dir,
+            "b2cs.csv",
+            "Type,Place Of Supply,Applicable % of Tax Rate,Rate,Taxable Value,Cess Amount,\
+             E-Commerce GSTIN",
+            b2cs,
+        )?,
+        write_sheet(
+            dest_dir,
+            "hsn.csv",
+            "HSN,Description,UQC,Total Quantity,Total Value,Taxable Value,\
+             Integrated Tax Amount,Central Tax Amount,State/UT Tax Amount,Cess Amount,Rate",
+            hsn,
+        )?,
+        write_sheet(
+            dest_dir,
+            "docs.csv",
+            "Nature of Document,Sr. No. From,Sr. No. To,Total Number,Cancelled",
+            docs,
+        )?,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{
+        insert_batch, insert_cashier, insert_medicine, migrated_db, paid_sale,
+    };
+    use crate::sales::{self, PaymentMode};
+
+    #[test]
+    fn sales_are_split_into_b2b_and_b2cs_sections() {
+        let (dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        conn.execute_batch(
+            "UPDATE pharmacy_settings SET gstin = '29ABCDE1234F1Z5', state_code = '29'
+             WHERE id = 1;
+             INSERT INTO customers (name, gstin) VALUES ('Care Clinic', '29AAACC1234D1Z2');
+             INSERT INTO customers (name, state_code) VALUES ('Traveller', '27');",
+        )
+        .unwrap();
+        let para = insert_medicine(&conn, "Paracetamol 500", 12.0);
+        let batch = insert_batch(&conn, para, "+1 year", 11_200, 20);
+        for customer in [Some(1), None, Some(2)] {
+            let sale = paid_sale(&conn, customer, PaymentMode::Cash, batch, 2);
+            db.with_tx(|tx| sales::create_sale(tx, &sale)).unwrap();
+        }
+        let period: String = conn
+            .query_row("SELECT strftime('%Y-%m', 'now')", [], |r| r.get(0))
+            .unwrap();
+
+        let report = gstr1_report(&conn, &period).unwrap();
+        assert_eq!(report.fp, format!("{}{}", &period[5..], &period[..4]));
+        assert_eq!(report.b2b.len(), 1);
+        let inv = &report.b2b[0].inv[0];
+        assert_eq!((inv.pos.as_str(), inv.val), ("29", 224.0));
+        let det = inv.itms[0].itm_det;
+        assert_eq!(
+            (det.rt, det.txval, det.camt, det.samt),
+            (12.0, 200.0, 12.0, 12.0)
+        );
+
+        let b2cs: Vec<_> = report
+            .b2cs
+            .iter()
+            .map(|r| (r.sply_ty, r.pos.as_str(), r.txval, r.iamt))
+            .collect();
+        assert_eq!(
+            b2cs,
+            [("INTRA", "29", 200.0, 0.0), ("INTER", "27", 200.0, 24.0)]
+        );
+        assert_eq!(report.hsn.data[0].qty, 6);
+        assert_eq!(report.doc_issue.doc_det[0].docs[0].totnum, 3);
+
+        let sheets = export_gstr1_csv(&conn, &period, dir.path(), 1).unwrap();
+        assert_eq!(sheets[2].rows_written, 2);
+        let b2cs_csv = std::fs::read_to_string(dir.path().join("b2cs.csv")).unwrap();
+        assert!(
+            b2cs_csv.contains("\nOE,27-Maharashtra,,12,200.00,0.00,\n"),
+            "{b2cs_csv}"
+        );
+        let b2b_csv = std::fs::read_to_string(dir.path().join("b2b.csv")).unwrap();
+        assert!(b2b_csv.contains(",Care Clinic,"), "{b2b_csv}");
+    }
+
+    #[test]
+    fn period_must_be_a_month() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        for bad in ["2024-13", "2024-1", "24-01-01"] {
+            assert!(period_bounds(&conn, bad).is_err(), "{bad}");
+        }
+        assert_eq!(tool_date("05-10-2026"), "05-Oct-2026");
+    }
+
+    #[test]
+    fn cashier_cannot_export_gstr1() {
+        let (dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let cashier = insert_cashier(&conn);
+        let period: String = conn
+            .query_row("SELECT strftime('%Y-%m', 'now')", [], |r| r.get(0))
+            .unwrap();
+
+        let dest = dir.path().join("gstr1.json");
+        let err = export_gstr1_json(&conn, &period, &dest, cashier).unwrap_err();
+        assert!(matches!(err, AppError::Unauthorized(_)), "{err}");
+        assert!(!dest.exists());
+        let err = export_gstr1_csv(&conn, &period, dir.path(), cashier).unwrap_err();
+        assert!(matches!(err, AppError::Unauthorized(_)), "{err}");
+        assert!(!dir.path().join("b2b.csv").exists());
+    }
+}
@@ -0,0 +1,341 @@
+//! GSTR-3B, the monthly summary return the tax is paid on.
+//!
+//! Outward supplies are net of the credit notes issued in the month, so a
+//! sale returned in the month it was made drops out entirely while one
+//! returned later reduces that later month. Input tax credit is worked out
+//! from the lines of purchases from registered suppliers: cost prices
+//! include GST, which is backed out at each medicine's rate on the purchase
+//! date, as debit notes do.
+
+use std::collections::BTreeMap;
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+use crate::error::{AppError, AppResult};
+use crate::gst;
+use crate::gst_history;
+use crate::gstr1;
+
+/// Taxable value and tax in paise. Cess is not levied on medicines.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaxValues {
+    pub taxable_paise: i64,
+    pub igst_paise: i64,
+    pub cgst_paise: i64,
+    pub sgst_paise: i64,
+}
+
+impl TaxValues {
+    fn add(&mut self, other: TaxValues) {
+        self.taxable_paise += other.taxable_paise;
+        self.igst_paise += other.igst_paise;
+        self.cgst_paise += other.cgst_paise;
+        self.sgst_paise += other.sgst_paise;
+    }
+
+    fn minus(mut self, other: TaxValues) -> TaxValues {
+        self.taxable_paise -= other.taxable_paise;
+        self.igst_paise -= other.igst_paise;
+        self.cgst_paise -= other.cgst_paise;
+        self.sgst_paise -= other.sgst_paise;
+        self
+    }
+}
+
+/// Inter-state supplies to unregistered buyers in one state (table 3.2).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateSupplies {
+    pub place_of_supply: String,
+    pub taxable_paise: i64,
+    pub igst_paise: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Gstr3bSummary {
+    /// `YYYY-MM`
+    pub month: String,
+    /// 3.1(a): outward taxable supplies.
+    pub outward_taxable: TaxValues,
+    /// 3.1(c): nil-rated and exempt supplies, by taxable value.
+    pub outward_nil_exempt_paise: i64,
+    /// 3.2: the part of 3.1(a) supplied inter-state to unregistered buyers.
+    pub interstate_unregistered: Vec<StateSupplies>,
+    /// 4(A)(5): credit on purchases from registered suppliers.
+    pub itc_available: TaxValues,
+    /// 4(B)(2): credit reversed on goods sent back to suppliers.
+    pub itc_reversed: TaxValues,
+    /// 4(C): available less reversed.
+    pub net_itc: TaxValues,
+    /// Purchases in the month recorded before their lines were kept, whose
+    /// credit cannot be worked out and is left out of 4(A)(5).
+    pub purchases_without_lines: i64,
+}
+
+/// Outward supplies for the month, sales less credit notes.
+fn outward(
+    conn: &Connection,
+    from: &str,
+    to: &str,
+    home_state: &str,
+) -> AppResult<(TaxValues, i64, Vec<StateSupplies>)> {
+    // Credit notes carry the sign-flipped amounts of the lines they reverse,
+    // and the rate and buyer of the original sale.
+    let mut stmt = conn.prepare(
+        "WITH lines AS (
+             SELECT si.sale_id, si.cgst_rate + si.sgst_rate + si.igst_rate AS rate,
+                 si.taxable_amount_paise AS taxable, si.igst_amount_paise AS igst,
+                 si.cgst_amount_paise AS cgst, si.sgst_amount_paise AS sgst
+             FROM sale_items si JOIN sales s ON s.id = si.sale_id
+             WHERE date(s.sale_date) BETWEEN ?1 AND ?2
+             UNION ALL
+             SELECT si.sale_id, si.cgst_rate + si.sgst_rate + si.igst_rate,
+                 -ri.taxable_amount_paise, -ri.igst_amount_paise,
+                 -ri.cgst_amount_paise, -ri.sgst_amount_paise
+             FROM sale_return_items ri
+             JOIN sale_returns r ON r.id = ri.return_id
+             JOIN sale_items si ON si.id = ri.sale_item_id
+             WHERE date(r.return_date) BETWEEN ?1 AND ?2
+         )
+         SELECT l.rate > 0 AS taxed, s.is_interstate,
+             TRIM(COALESCE(c.gstin, '')) <> '' AS registered,
+             COALESCE(s.place_of_supply, NULLIF(c.state_code, ''), substr(c.gstin, 1, 2), '')
+                 AS pos,
+             SUM(l.taxable), SUM(l.igst), SUM(l.cgst), SUM(l.sgst)
+         FROM lines l
+         JOIN sales s ON s.id = l.sale_id
+         LEFT JOIN customers c ON c.id = s.customer_id
+         GROUP BY taxed, s.is_interstate, registered, pos
+         ORDER BY pos",
+    )?;
+    let mut rows = stmt.query(params![from, to])?;
+
+    let mut taxable = TaxValues::default();
+    let mut nil_exempt = 0;
+    let mut by_state: BTreeMap<String, StateSupplies> = BTreeMap::new();
+    while let Some(row) = rows.next()? {
+        let (taxed, interstate, registered): (bool, bool, bool) =
+            (row.get(0)?, row.get(1)?, row.get(2)?);
+        let values = TaxValues {
+            taxable_paise: row.get(4)?,
+            igst_paise: row.get(5)?,
+            cgst_paise: row.get(6)?,
+            sgst_paise: row.get(7)?,
+        };
+        if !taxed {
+            nil_exempt += values.taxable_paise;
+            continue;
+        }
+        taxable.add(values);
+        if interstate && !registered {
+            let pos: String = row.get(3)?;
+            let pos = if pos.is_empty() {
+                home_state.to_string()
+            } else {
+                pos
+            };
+            let state = by_state
+                .entry(pos.clone())
+                .or_insert_with(|| StateSupplies {
+                    place_of_supply: pos,
+                    taxable_paise: 0,
+                    igst_paise: 0,
+                });
+            state.taxable_paise += values.taxable_paise;
+            state.igst_paise += values.igst_paise;
+        }
+    }
+    let interstate_unregistered = by_state
+        .into_values()
+        .filter(|s| s.taxable_paise != 0 || s.igst_paise != 0)
+        .collect();
+    Ok((taxable, nil_exempt, interstate_unregistered))
+}
+
+/// Credit on the month's purchases from suppliers with a GSTIN, and the
+/// number of purchases that have no lines to work it out from.
+fn input_credit(
+    conn: &Connection,
+    from: &str,
+    to: &str,
+    home_state: &str,
+) -> AppResult<(TaxValues, i64)> {
+    let mut stmt = conn.prepare(
+        "SELECT pi.medicine_id, pi.quantity, pi.cost_price_paise, p.purchase_date,
+             substr(TRIM(su.gst_in), 1, 2)
+         FROM purchase_items pi
+         JOIN purchases p ON p.id = pi.purchase_id
+         JOIN suppliers su ON su.id = p.supplier_id
+         WHERE date(p.purchase_date) BETWEEN ?1 AND ?2
+           AND TRIM(COALESCE(su.gst_in, '')) <> ''
+         ORDER BY pi.id",
+    )?;
+    let mut rows = stmt.query(params![from, to])?;
+    let mut credit = TaxValues::default();
+    while let Some(row) = rows.next()? {
+        let (medicine_id, quantity, cost_price_paise, date, supplier_state): (
+            i64,
+            i64,
+            i64,
+            String,
+            String,
+        ) = (
+            row.get(0)?,
+            row.get(1)?,
+            row.get(2)?,
+            row.get(3)?,
+            row.get(4)?,
+        );
+        let rate = gst_history::rate_on(conn, medicine_id, &date)?;
+        let interstate = !home_state.is_empty() && supplier_state != home_state;
+        let line = gst::calculate_line(cost_price_paise, quantity, rate, 0, interstate);
+        credit.add(TaxValues {
+            taxable_paise: line.taxable_amount_paise,
+            igst_paise: line.gst.igst_paise,
+            cgst_paise: line.gst.cgst_paise,
+            sgst_paise: line.gst.sgst_paise,
+        });
+    }
+
+    let without_lines = conn.query_row(
+        "SELECT COUNT(*) FROM purchases p
+         WHERE date(p.purchase_date) BETWEEN ?1 AND ?2
+           AND NOT EXISTS (SELECT 1 FROM purchase_items pi WHERE pi.purchase_id = p.id)",
+        params![from, to],
+        |row| row.get(0),
+    )?;
+    Ok((credit, without_lines))
+}
+
+/// Summarise a `YYYY-MM` month for GSTR-3B.
+pub fn gstr3b_summary(conn: &Connection, month: &str) -> AppResult<Gstr3bSummary> {
+    let (from, to, _) = gstr1::period_bounds(conn, month)?;
+    let (home_state, composition): (String, bool) = conn.query_row(
+        "SELECT state_code, composition_scheme FROM pharmacy_settings WHERE id = 1",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    if composition {
+        return Err(AppError::validation(
+            "Composition dealers file CMP-08 and GSTR-4 instead of GSTR-3B",
+        ));
+    }
+
+    let (outward_taxable, outward_nil_exempt_paise, interstate_unregistered) =
+        outward(conn, &from, &to, &home_state)?;
+    let (itc_available, purchases_without_lines) = input_credit(conn, &from, &to, &home_state)?;
+    let itc_reversed = conn.query_row(
+        "SELECT COALESCE(SUM(r.taxable_amount_paise), 0), COALESCE(SUM(r.total_igst_paise), 0),
+             COALESCE(SUM(r.total_cgst_paise), 0), COALESCE(SUM(r.total_sgst_paise), 0)
+         FROM purchase_returns r JOIN suppliers su ON su.id = r.supplier_id
+         WHERE date(r.return_date) BETWEEN ?1 AND ?2
+           AND TRIM(COALESCE(su.gst_in, '')) <> ''",
+        params![from, to],
+        |row| {
+            Ok(TaxValues {
+                taxable_paise: row.get(0)?,
+                igst_paise: row.get(1)?,
+                cgst_paise: row.get(2)?,
+                sgst_paise: row.get(3)?,
+            })
+        },
+    )?;
+
+    Ok(Gstr3bSummary {
+        month: month.to_string(),
+        outward_taxable,
+        outward_nil_exempt_paise,
+        interstate_unregistered,
+        itc_available,
+        itc_reversed,
+        net_itc: itc_available.minus(itc_reversed),
+        purchases_without_lines,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{insert_batch, insert_medicine, migrated_db, paid_sale};
+    use crate::returns::{self, ReturnItem};
+    use crate::sales::{self, PaymentMode};
+
+    #[test]
+    fn outward_supplies_are_net_of_credit_notes() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        conn.execute_batch(
+            "UPDATE pharmacy_settings SET state_code = '29' WHERE id = 1;
+             INSERT INTO customers (name, state_code) VALUES ('Traveller', '27');
+             INSERT INTO suppliers (name, gst_in) VALUES ('Pharma Distributors', '29AAAPD1234E1Z1');",
+        )
+        .unwrap();
+        let para = insert_medicine(&conn, "Paracetamol 500", 12.0);
+        let para_batch = insert_batch(&conn, para, "+1 year", 11_200, 20);
+        let cotton = insert_medicine(&conn, "Cotton Wool", 0.0);
+        let cotton_batch = insert_batch(&conn, cotton, "+1 year", 5_000, 20);
+        conn.execute(
+            "INSERT INTO purchases (supplier_id, invoice_number, total_paise)
+             VALUES (1, 'PD-1', 22400)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO purchase_items (purchase_id, batch_id, medicine_id, quantity,
+                 cost_price_paise, mrp_paise, selling_price_paise)
+             VALUES (1, ?1, ?2, 2, 11200, 11200, 11200)",
+            params![para_batch, para],
+        )
+        .unwrap();
+
+        for (customer, batch) in [
+            (None, para_batch),
+            (Some(1), para_batch),
+            (None, cotton_batch),
+        ] {
+            let sale = paid_sale(&conn, customer, PaymentMode::Cash, batch, 2);
+            db.with_tx(|tx| sales::create_sale(tx, &sale)).unwrap();
+        }
+        let first_line: i64 = conn
+            .query_row("SELECT MIN(id) FROM sale_items", [], |r| r.get(0))
+            .unwrap();
+        let item = ReturnItem {
+            sale_item_id: first_line,
+            quantity: 1,
+            non_saleable: false,
+        };
+        db.with_tx(|tx| returns::create_sale_return(tx, 1, &[item], PaymentMode::Cash, 1))
+            .unwrap();
+        let month: String = conn
+            .query_row("SELECT strftime('%Y-%m', 'now')", [], |r| r.get(0))
+            .unwrap();
+
+        let summary = gstr3b_summary(&conn, &month).unwrap();
+        assert_eq!(
+            summary.outward_taxable,
+            TaxValues {
+                taxable_paise: 30_000,
+                igst_paise: 2_400,
+                cgst_paise: 600,
+                sgst_paise: 600,
+            }
+        );
+        assert_eq!(summary.outward_nil_exempt_paise, 10_000);
+        assert_eq!(
+            summary.interstate_unregistered,
+            [StateSupplies {
+                place_of_supply: "27".into(),
+                taxable_paise: 20_000,
+                igst_paise: 2_400,
+            }]
+        );
+        assert_eq!(
+            (summary.net_itc.cgst_paise, summary.net_itc.sgst_paise),
+            (1_200, 1_200)
+        );
+        assert_eq!(summary.purchases_without_lines, 0);
+    }
+}
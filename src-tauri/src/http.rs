@@ -0,0 +1,125 @@
+//! HTTPS requests through the system `curl`.
+//!
+//! The app carries no TLS stack of its own, so transfers are handed to curl,
+//! which brings one. Options and credentials go to it as a config file on
+//! stdin, so they never show in the process list.
+
+use std::fmt::Write as _;
+use std::io::{ErrorKind, Write as _};
+use std::process::{Command, Stdio};
+
+use crate::error::{AppError, AppResult};
+
+/// A value quoted for a curl config file.
+///
+/// Control characters are refused rather than escaped: a line break would
+/// end the option early and let the rest of the value add options of its
+/// own, and curl would turn an escaped one back into a raw line break
+/// inside a header or URL.
+fn quoted(value: &str) -> AppResult<String> {
+    if value.chars().any(char::is_control) {
+        return Err(AppError::validation(
+            "Request values cannot contain line breaks or other control characters",
+        ));
+    }
+    Ok(format!(
+        "\"{}\"",
+        value.replace('\\', "\\\\").replace('"', "\\\"")
+    ))
+}
+
+/// Run curl with `options`, one `name = value` per line, and return what
+/// it wrote to stdout. `what` names the task in errors, e.g. "Upload".
+pub(crate) fn curl(
+    what: &str,
+    timeout_secs: u32,
+    options: &[(&str, String)],
+) -> AppResult<Vec<u8>> {
+    let mut config = format!("silent\nshow-error\nfail\nmax-time = {timeout_secs}\n");
+    for (name, value) in options {
+        let _ = writeln!(config, "{name} = {}", quoted(value)?);
+    }
+    let mut child = Command::new("curl")
+        .args(["--config", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| match err.kind() {
+            ErrorKind::NotFound => {
+                AppError::validation(format!("{what} needs curl, which was not found on this PC"))
+            }
+            _ => err.into(),
+        })?;
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(config.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(AppError::validation(format!(
+            "{what} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// A JSON POST: url, headers and body in, response body out. APIs take one
+/// of these so tests can stand in for the network.
+pub type Post<'a> = dyn FnMut(&str, &[(&str, String)], &str) -> AppResult<Vec<u8>> + 'a;
+
+/// A GET: url and headers in, response body out.
+pub type Get<'a> = dyn FnMut(&str, &[(&str, String)]) -> AppResult<Vec<u8>> + 'a;
+
+/// GET `url` with extra `headers`.
+pub(crate) fn get(
+    what: &str,
+    timeout_secs: u32,
+    url: &str,
+    headers: &[(&str, String)],
+) -> AppResult<Vec<u8>> {
+    let mut options = vec![("url", url.to_string())];
+    options.extend(
+        headers
+            .iter()
+            .map(|(name, value)| ("header", format!("{name}: {value}"))),
+    );
+    curl(what, timeout_secs, &options)
+}
+
+/// POST a JSON `body` to `url` with extra `headers`.
+pub(crate) fn post_json(
+    what: &str,
+    timeout_secs: u32,
+    url: &str,
+    headers: &[(&str, String)],
+    body: &str,
+) -> AppResult<Vec<u8>> {
+    let mut options = vec![
+        ("url", url.to_string()),
+        ("header", "Content-Type: application/json".to_string()),
+        ("data-binary", body.to_string()),
+    ];
+    options.extend(
+        headers
+            .iter()
+            .map(|(name, value)| ("header", format!("{name}: {value}"))),
+    );
+    curl(what, timeout_secs, &options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_are_quoted_and_line_breaks_refused() {
+        assert_eq!(quoted(r#"a "b" \c"#).unwrap(), r#""a \"b\" \\c""#);
+
+        let err = quoted("https://example.com\nupload-file = /etc/passwd").unwrap_err();
+        assert!(err.to_string().contains("control characters"), "{err}");
+        assert!(quoted("secret\r").is_err());
+    }
+}
@@ -0,0 +1,369 @@
+//! Bulk onboarding of medicines from CSV.
+
+use std::collections::HashSet;
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::auth::{self, permission};
+use crate::db::{ChangeOp, Entity, Tx};
+use crate::error::{AppError, AppResult};
+use crate::sync;
+
+/// Values accepted in `medicines.dosage_form`, as offered by the medicine form.
+const DOSAGE_FORMS: &[&str] = &[
+    "tablet",
+    "capsule",
+    "syrup",
+    "injection",
+    "cream",
+    "ointment",
+    "drops",
+    "inhaler",
+    "powder",
+    "gel",
+    "lotion",
+    "suspension",
+    "other",
+];
+
+const DEFAULT_DOSAGE_FORM: &str = "tablet";
+const DEFAULT_HSN_CODE: &str = "3004";
+const DEFAULT_REORDER_LEVEL: i64 = 20;
+
+/// A medicine row that passed validation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedMedicine {
+    /// Line in the file, counting the header as line 1.
+    pub row: usize,
+    /// Set once the row has been written; `None` in a dry run.
+    pub medicine_id: Option<i64>,
+    pub name: String,
+    pub generic_name: Option<String>,
+    pub brand_name: Option<String>,
+    pub manufacturer: Option<String>,
+    pub dosage_form: String,
+    pub strength: Option<String>,
+    pub category: Option<String>,
+    pub hsn_code: String,
+    pub gst_rate: f64,
+    pub reorder_level: i64,
+    #[serde(skip)]
+    gst_slab_id: i64,
+}
+
+/// A row left out because a medicine of that name already exists.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedRow {
+    pub row: usize,
+    pub name: String,
+}
+
+/// Every problem found on one row.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RowError {
+    pub row: usize,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSummary {
+    pub dry_run: bool,
+    pub imported: Vec<ImportedMedicine>,
+    pub duplicates: Vec<SkippedRow>,
+    pub errors: Vec<RowError>,
+}
+
+/// Split CSV text into records of fields.
+///
+/// Follows RFC 4180: fields may be quoted, quoted fields may contain commas,
+/// newlines and doubled quotes. Blank lines are dropped.
+fn parse_csv(text: &str) -> AppResult<Vec<Vec<String>>> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => quoted = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' => quoted = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' | '\r' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(AppError::validation("CSV has an unterminated quoted field"));
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    records.retain(|r| r.iter().any(|f| !f.trim().is_empty()));
+    Ok(records)
+}
+
+/// `"GST Rate (%)"` and `"gst_rate"` both become `gst_rate`.
+fn normalize_header(header: &str) -> String {
+    header
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// Looks up fields of one record by column name.
+struct Columns(Vec<String>);
+
+impl Columns {
+    fn get<'r>(&self, record: &'r [String], column: &str) -> Option<&'r str> {
+        let index = self.0.iter().position(|c| c == column)?;
+        record
+            .get(index)
+            .map(|f| f.trim())
+            .filter(|f| !f.is_empty())
+    }
+}
+
+/// Validate every row against the database without writing anything.
+///
+/// Names are matched case-insensitively against existing medicines and
+/// earlier rows of the same file.
+pub fn plan_medicine_import(
+    conn: &Connection,
+    csv: &str,
+    user_id: i64,
+) -> AppResult<ImportSummary> {
+    auth::require_permission(conn, user_id, permission::MEDICINES_IMPORT)?;
+    let mut records = parse_csv(csv)?.into_iter();
+    let header = records
+        .next()
+        .ok_or_else(|| AppError::validation("The CSV file is empty"))?;
+    let columns = Columns(header.iter().map(|h| normalize_header(h)).collect());
+    for required in ["name", "gst_rate"] {
+        if !columns.0.iter().any(|c| c == required) {
+            return Err(AppError::validation(format!(
+                "The CSV file has no '{required}' column"
+            )));
+        }
+    }
+
+    let mut slab_for_rate = conn.prepare("SELECT id FROM gst_slabs WHERE rate = ?1")?;
+    let mut existing = conn.prepare("SELECT 1 FROM medicines WHERE lower(name) = lower(?1)")?;
+    let mut seen = HashSet::new();
+    let mut summary = ImportSummary {
+        dry_run: true,
+        ..Default::default()
+    };
+
+    for (index, record) in records.enumerate() {
+        let row = index + 2;
+        let field = |column: &str| columns.get(&record, column);
+        let optional = |column: &str| field(column).map(str::to_owned);
+        let mut errors = Vec::new();
+
+        let name = field("name").unwrap_or_default().to_owned();
+        if name.is_empty() {
+            errors.push("Name is required".to_owned());
+        }
+
+        let dosage_form = field("dosage_form")
+            .unwrap_or(DEFAULT_DOSAGE_FORM)
+            .to_ascii_lowercase();
+        if !DOSAGE_FORMS.contains(&dosage_form.as_str()) {
+            errors.push(format!("Invalid dosage form: {dosage_form}"));
+        }
+
+        let hsn_code = field("hsn_code").unwrap_or(DEFAULT_HSN_CODE).to_owned();
+        if !(4..=8).contains(&hsn_code.len()) || !hsn_code.bytes().all(|b| b.is_ascii_digit()) {
+            errors.push("HSN code must be 4-8 digits".to_owned());
+        }
+
+        let mut gst = None;
+        match field("gst_rate") {
+            None => errors.push("GST rate is required".to_owned()),
+            Some(raw) => match raw.trim_end_matches('%').trim().parse::<f64>() {
+                Err(_) => errors.push(format!("Invalid GST rate: {raw}")),
+                Ok(rate) => {
+                    let slab: Option<i64> = slab_for_rate
+                        .query_row(params![rate], |r| r.get(0))
+                        .optional()?;
+                    match slab {
+                        Some(slab) => gst = Some((rate, slab)),
+                        None => errors.push(format!("No GST slab exists for {rate}%")),
+                    }
+                }
+            },
+        }
+
+        let reorder_level = match field("reorder_level") {
+            None => DEFAULT_REORDER_LEVEL,
+            Some(raw) => match raw.parse::<i64>() {
+                Ok(level) if level >= 0 => level,
+                _ => {
+                    errors.push("Reorder level must be a non-negative number".to_owned());
+                    DEFAULT_REORDER_LEVEL
+                }
+            },
+        };
+
+        let (gst_rate, gst_slab_id) = match gst {
+            Some(gst) if errors.is_empty() => gst,
+            _ => {
+                summary.errors.push(RowError { row, errors });
+                continue;
+            }
+        };
+        if existing.exists(params![name])? || !seen.insert(name.to_lowercase()) {
+            summary.duplicates.push(SkippedRow { row, name });
+            continue;
+        }
+
+        summary.imported.push(ImportedMedicine {
+            row,
+            medicine_id: None,
+            generic_name: optional("generic_name"),
+            brand_name: optional("brand_name"),
+            manufacturer: optional("manufacturer"),
+            strength: optional("strength"),
+            category: optional("category").map(|c| c.to_lowercase()),
+            name,
+            dosage_form,
+            hsn_code,
+            gst_rate,
+            reorder_level,
+            gst_slab_id,
+        });
+    }
+    Ok(summary)
+}
+
+/// Validate the file and insert every valid, non-duplicate row.
+///
+/// Bad rows are reported, not fatal: the good ones are still written.
+pub fn import_medicines(tx: &Tx, csv: &str, user_id: i64) -> AppResult<ImportSummary> {
+    let mut summary = plan_medicine_import(tx, csv, user_id)?;
+    summary.dry_run = false;
+
+    let mut insert = tx.prepare_cached(
+        "INSERT INTO medicines (name, generic_name, brand_name, manufacturer, dosage_form,
+             strength, category, hsn_code, gst_slab_id, reorder_level)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+    )?;
+    for medicine in &mut summary.imported {
+        insert.execute(params![
+            medicine.name,
+            medicine.generic_name,
+            medicine.brand_name,
+            medicine.manufacturer,
+            medicine.dosage_form,
+            medicine.strength,
+            medicine.category,
+            medicine.hsn_code,
+            medicine.gst_slab_id,
+            medicine.reorder_level,
+        ])?;
+        let id = tx.last_insert_rowid();
+        medicine.medicine_id = Some(id);
+        tx.changed(Entity::Medicine, ChangeOp::Insert, id);
+    }
+
+    if !summary.imported.is_empty() {
+        sync::enqueue(
+            tx,
+            "import_medicines_csv",
+            &json!({ "medicines": summary.imported }),
+        )?;
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{insert_medicine, migrated_db};
+
+    const ADMIN: i64 = 1;
+
+    const CSV: &str = "\
+name,generic_name,manufacturer,dosage_form,hsn_code,gst_rate,reorder_level
+Dolo 650,Paracetamol,Micro Labs,tablet,3004,12,30
+\"Cetirizine, 10mg\",Cetirizine,Cipla,tablet,3004,12,
+paracetamol 500,Paracetamol,GSK,tablet,3004,12,10
+Bad Rate,,,tablet,3004,7,10
+,,,pill,30,12,-1
+";
+
+    #[test]
+    fn quoted_fields_keep_commas_and_quotes() {
+        let records = parse_csv("a,\"b, \"\"c\"\"\"\r\n\r\nd,e").unwrap();
+        assert_eq!(records, [vec!["a", "b, \"c\""], vec!["d", "e"]]);
+    }
+
+    #[test]
+    fn dry_run_reports_without_writing() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        insert_medicine(&conn, "Paracetamol 500", 12.0);
+
+        let summary = plan_medicine_import(&conn, CSV, ADMIN).unwrap();
+
+        let names: Vec<_> = summary.imported.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, ["Dolo 650", "Cetirizine, 10mg"]);
+        assert_eq!(summary.imported[1].reorder_level, DEFAULT_REORDER_LEVEL);
+        assert_eq!(summary.duplicates[0].row, 4);
+        assert_eq!(summary.errors.len(), 2);
+        assert!(summary.errors[0].errors[0].contains("No GST slab"));
+        assert_eq!(summary.errors[1].errors.len(), 4);
+
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM medicines", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn import_writes_valid_rows_despite_bad_ones() {
+        let (_dir, db) = migrated_db();
+
+        let summary = db.with_tx(|tx| import_medicines(tx, CSV, ADMIN)).unwrap();
+
+        assert!(!summary.dry_run);
+        assert_eq!(summary.imported.len(), 3);
+        assert!(summary.imported.iter().all(|m| m.medicine_id.is_some()));
+        let conn = db.connect().unwrap();
+        let slab_rate: f64 = conn
+            .query_row(
+                "SELECT s.rate FROM medicines m JOIN gst_slabs s ON s.id = m.gst_slab_id
+                 WHERE m.name = 'Dolo 650'",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(slab_rate, 12.0);
+    }
+}
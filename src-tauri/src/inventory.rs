@@ -0,0 +1,987 @@
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::auth::{self, permission};
+use crate::db::{ChangeOp, Entity, Tx};
+use crate::error::{AppError, AppResult};
+use crate::locations;
+use crate::reports::validate_date_range;
+use crate::sync;
+
+/// SQL that is true for a batch past its `expiry_date`.
+///
+/// Stock is good through its expiry date: a batch expiring today may still
+/// be sold and is not expired, so cannot be disposed of, until tomorrow.
+/// Every expiry check uses this or [`UNEXPIRED`]; `expiry_date` is only a
+/// column of `batches`, so it needs no table alias.
+pub(crate) const EXPIRED: &str = "expiry_date < date('now')";
+
+/// SQL that is true while a batch may still be sold; the negation of
+/// [`EXPIRED`].
+pub(crate) const UNEXPIRED: &str = "expiry_date >= date('now')";
+
+/// Sellability state of a batch.
+///
+/// Only `Available` stock is offered to allocation and counted as sellable.
+/// Quarantined and blocked stock still counts towards inventory value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BatchStatus {
+    Available,
+    /// On QC hold; may be released back to `Available`.
+    Quarantine,
+    /// Rejected; must be re-inspected via `Quarantine` before release.
+    Blocked,
+}
+
+impl BatchStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Available => "available",
+            Self::Quarantine => "quarantine",
+            Self::Blocked => "blocked",
+        }
+    }
+
+    pub fn parse(s: &str) -> AppResult<Self> {
+        match s {
+            "available" => Ok(Self::Available),
+            "quarantine" => Ok(Self::Quarantine),
+            "blocked" => Ok(Self::Blocked),
+            other => Err(AppError::validation(format!(
+                "Unknown batch status '{other}'"
+            ))),
+        }
+    }
+
+    /// Whether a batch may move from `self` to `next`.
+    pub fn can_transition_to(self, next: Self) -> bool {
+        use BatchStatus::*;
+        matches!(
+            (self, next),
+            (Available, Quarantine)
+                | (Available, Blocked)
+                | (Quarantine, Available)
+                | (Quarantine, Blocked)
+                | (Blocked, Quarantine)
+        )
+    }
+}
+
+/// A batch entered by hand, outside a supplier invoice.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchInput {
+    pub medicine_id: i64,
+    pub batch_number: String,
+    pub expiry_date: String,
+    pub cost_price_paise: i64,
+    pub mrp_paise: i64,
+    pub selling_price_paise: i64,
+    pub quantity: i64,
+    pub manufacturing_date: Option<String>,
+    pub barcode: Option<String>,
+}
+
+/// Add a batch with its opening stock as the first ledger entry.
+pub fn create_batch(tx: &Tx, batch: &BatchInput, user_id: i64) -> AppResult<i64> {
+    auth::require_permission(tx, user_id, permission::PURCHASES_RECEIVE)?;
+    let batch_number = batch.batch_number.trim();
+    if batch_number.is_empty() {
+        return Err(AppError::validation("Batch number is required"));
+    }
+    if batch.quantity <= 0 {
+        return Err(AppError::validation("Quantity must be greater than 0"));
+    }
+    if batch.selling_price_paise > batch.mrp_paise {
+        return Err(AppError::validation("Selling price cannot exceed MRP"));
+    }
+
+    tx.execute(
+        "INSERT INTO batches (medicine_id, batch_number, expiry_date, cost_price_paise,
+             mrp_paise, selling_price_paise, quantity, manufacturing_date, barcode)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            batch.medicine_id,
+            batch_number,
+            batch.expiry_date,
+            batch.cost_price_paise,
+            batch.mrp_paise,
+            batch.selling_price_paise,
+            batch.quantity,
+            batch.manufacturing_date,
+            batch.barcode
+        ],
+    )?;
+    let batch_id = tx.last_insert_rowid();
+    record_movement(tx, batch_id, batch.quantity, MovementReason::Purchase, None)?;
+    tx.changed(Entity::Batch, ChangeOp::Insert, batch_id);
+    sync::enqueue(
+        tx,
+        "create_batch",
+        &json!({ "batchId": batch_id, "medicineId": batch.medicine_id }),
+    )?;
+    Ok(batch_id)
+}
+
+/// Move a batch to a new status, recording the reason.
+pub fn set_batch_status(
+    tx: &Tx,
+    batch_id: i64,
+    status: BatchStatus,
+    reason: &str,
+    user_id: i64,
+) -> AppResult<()> {
+    auth::require_permission(tx, user_id, permission::INVENTORY_ADJUST)?;
+    let reason = reason.trim();
+    if reason.is_empty() {
+        return Err(AppError::validation(
+            "A reason is required to change batch status",
+        ));
+    }
+
+    let current: String = tx
+        .query_row(
+            "SELECT status FROM batches WHERE id = ?1",
+            params![batch_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Batch {batch_id}")))?;
+    let current = BatchStatus::parse(&current)?;
+
+    if !current.can_transition_to(status) {
+        return Err(AppError::validation(format!(
+            "Cannot change batch status from {} to {}",
+            current.as_str(),
+            status.as_str()
+        )));
+    }
+
+    tx.execute(
+        "UPDATE batches SET status = ?1 WHERE id = ?2",
+        params![status.as_str(), batch_id],
+    )?;
+    tx.execute(
+        "INSERT INTO batch_status_changes (batch_id, from_status, to_status, reason)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![batch_id, current.as_str(), status.as_str(), reason],
+    )?;
+    tx.changed(Entity::Batch, ChangeOp::Update, batch_id);
+    sync::enqueue(
+        tx,
+        "set_batch_status",
+        &json!({ "batchId": batch_id, "status": status, "reason": reason }),
+    )
+}
+
+/// Why a batch's quantity changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MovementReason {
+    Sale,
+    Return,
+    Adjustment,
+    Purchase,
+    Disposal,
+}
+
+impl MovementReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Sale => "sale",
+            Self::Return => "return",
+            Self::Adjustment => "adjustment",
+            Self::Purchase => "purchase",
+            Self::Disposal => "disposal",
+        }
+    }
+
+    pub fn parse(s: &str) -> AppResult<Self> {
+        match s {
+            "sale" => Ok(Self::Sale),
+            "return" => Ok(Self::Return),
+            "adjustment" => Ok(Self::Adjustment),
+            "purchase" => Ok(Self::Purchase),
+            "disposal" => Ok(Self::Disposal),
+            other => Err(AppError::validation(format!(
+                "Unknown stock movement reason '{other}'"
+            ))),
+        }
+    }
+}
+
+/// One entry in a batch's stock ledger.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StockMovement {
+    pub id: i64,
+    pub batch_id: i64,
+    pub medicine_id: i64,
+    pub change_qty: i64,
+    pub reason: MovementReason,
+    /// The sale (or other document) that caused the movement, if any.
+    pub reference_id: Option<i64>,
+    /// Why the stock was moved, for manual adjustments.
+    pub note: Option<String>,
+    /// Set on both halves of a transfer between branches.
+    pub from_branch_id: Option<i64>,
+    pub to_branch_id: Option<i64>,
+    pub created_at: String,
+}
+
+/// Append a quantity change to the stock ledger.
+///
+/// Call this in the same transaction as the `batches.quantity` update it
+/// describes, so the ledger for a batch always sums to its quantity.
+pub fn record_movement(
+    tx: &Tx,
+    batch_id: i64,
+    change: i64,
+    reason: MovementReason,
+    reference: Option<i64>,
+) -> AppResult<()> {
+    record_noted_movement(tx, batch_id, change, reason, reference, None, None)
+}
+
+/// [`record_movement`] with a note explaining it and, for a transfer
+/// between branches, the source and destination branch.
+pub(crate) fn record_noted_movement(
+    tx: &Tx,
+    batch_id: i64,
+    change: i64,
+    reason: MovementReason,
+    reference: Option<i64>,
+    note: Option<&str>,
+    branches: Option<(i64, i64)>,
+) -> AppResult<()> {
+    let (from_branch, to_branch) = branches.unzip();
+    let inserted = tx
+        .prepare_cached(
+            "INSERT INTO stock_movements (batch_id, medicine_id, change_qty, reason,
+                 reference_id, note, from_branch_id, to_branch_id)
+             SELECT id, medicine_id, ?2, ?3, ?4, ?5, ?6, ?7 FROM batches WHERE id = ?1",
+        )?
+        .execute(params![
+            batch_id,
+            change,
+            reason.as_str(),
+            reference,
+            note,
+            from_branch,
+            to_branch
+        ])?;
+    if inserted == 0 {
+        return Err(AppError::not_found(format!("Batch {batch_id}")));
+    }
+    Ok(())
+}
+
+const MOVEMENT_COLUMNS: &str = "id, batch_id, medicine_id, change_qty, reason, reference_id, note,
+     from_branch_id, to_branch_id, created_at";
+
+fn movement_from_row(row: &Row) -> AppResult<StockMovement> {
+    let reason: String = row.get(4)?;
+    Ok(StockMovement {
+        id: row.get(0)?,
+        batch_id: row.get(1)?,
+        medicine_id: row.get(2)?,
+        change_qty: row.get(3)?,
+        reason: MovementReason::parse(&reason)?,
+        reference_id: row.get(5)?,
+        note: row.get(6)?,
+        from_branch_id: row.get(7)?,
+        to_branch_id: row.get(8)?,
+        created_at: row.get(9)?,
+    })
+}
+
+/// Every recorded quantity change of a batch, oldest first.
+pub fn batch_movement_history(conn: &Connection, batch_id: i64) -> AppResult<Vec<StockMovement>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {MOVEMENT_COLUMNS} FROM stock_movements WHERE batch_id = ?1 ORDER BY id ASC"
+    ))?;
+    let mut rows = stmt.query(params![batch_id])?;
+
+    let mut movements = Vec::new();
+    while let Some(row) = rows.next()? {
+        movements.push(movement_from_row(row)?);
+    }
+    Ok(movements)
+}
+
+/// One batch written off by [`dispose_expired`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DisposedBatch {
+    pub batch_id: i64,
+    pub medicine_id: i64,
+    pub medicine_name: String,
+    pub batch_number: String,
+    pub expiry_date: String,
+    pub quantity: i64,
+    /// Quantity at the batch's cost price.
+    pub cost_value_paise: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DisposalSummary {
+    pub disposal_id: i64,
+    pub batches: Vec<DisposedBatch>,
+    pub total_cost_paise: i64,
+}
+
+/// Write off the remaining stock of `batch_ids` and enter it in the
+/// disposal register.
+///
+/// Each batch is zeroed with a `disposal` movement referencing the register
+/// entry. Batches that have not expired are refused unless
+/// `allow_unexpired` is set, so saleable stock is not written off by a
+/// stray click. The user needs `inventory.dispose`.
+pub fn dispose_expired(
+    tx: &Tx,
+    batch_ids: &[i64],
+    reason: &str,
+    allow_unexpired: bool,
+    user_id: i64,
+) -> AppResult<DisposalSummary> {
+    auth::require_permission(tx, user_id, permission::INVENTORY_DISPOSE)?;
+    let reason = reason.trim();
+    if reason.is_empty() {
+        return Err(AppError::validation(
+            "A reason is required to dispose of stock",
+        ));
+    }
+    if batch_ids.is_empty() {
+        return Err(AppError::validation("Select at least one batch to dispose"));
+    }
+
+    let mut batches = Vec::with_capacity(batch_ids.len());
+    for (i, &batch_id) in batch_ids.iter().enumerate() {
+        if batch_ids[..i].contains(&batch_id) {
+            return Err(AppError::validation(format!(
+                "Batch {batch_id} is listed more than once"
+            )));
+        }
+        let (batch, cost_price, expired) = tx
+            .query_row(
+                &format!(
+                    "SELECT b.id, b.medicine_id, m.name, b.batch_number, b.expiry_date,
+                         b.quantity, b.cost_price_paise, {EXPIRED}
+                     FROM batches b JOIN medicines m ON m.id = b.medicine_id
+                     WHERE b.id = ?1"
+                ),
+                params![batch_id],
+                |row| {
+                    let quantity: i64 = row.get(5)?;
+                    let cost_price: i64 = row.get(6)?;
+                    Ok((
+                        DisposedBatch {
+                            batch_id: row.get(0)?,
+                            medicine_id: row.get(1)?,
+                            medicine_name: row.get(2)?,
+                            batch_number: row.get(3)?,
+                            expiry_date: row.get(4)?,
+                            quantity,
+                            cost_value_paise: cost_price * quantity,
+                        },
+                        cost_price,
+                        row.get::<_, bool>(7)?,
+                    ))
+                },
+            )
+            .optional()?
+            .ok_or_else(|| AppError::not_found(format!("Batch {batch_id}")))?;
+        let label = format!("{} batch {}", batch.medicine_name, batch.batch_number);
+        if batch.quantity <= 0 {
+            return Err(AppError::validation(format!(
+                "{label} has no stock to dispose of"
+            )));
+        }
+        if !expired && !allow_unexpired {
+            return Err(AppError::validation(format!(
+                "{label} does not expire until {}; confirm the override to write it off",
+                batch.expiry_date
+            )));
+        }
+        batches.push((batch, cost_price));
+    }
+
+    let total_cost_paise: i64 = batches.iter().map(|(b, _)| b.cost_value_paise).sum();
+    tx.execute(
+        "INSERT INTO stock_disposals (reason, total_cost_paise) VALUES (?1, ?2)",
+        params![reason, total_cost_paise],
+    )?;
+    let disposal_id = tx.last_insert_rowid();
+
+    for (batch, cost_price) in &batches {
+        tx.execute(
+            "INSERT INTO stock_disposal_items (disposal_id, batch_id, quantity,
+                 cost_price_paise, cost_value_paise)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                disposal_id,
+                batch.batch_id,
+                batch.quantity,
+                cost_price,
+                batch.cost_value_paise
+            ],
+        )?;
+        // Expired stock is written off wherever it is kept.
+        tx.execute(
+            "DELETE FROM batch_locations WHERE batch_id = ?1",
+            params![batch.batch_id],
+        )?;
+        tx.execute(
+            "UPDATE batches SET quantity = 0 WHERE id = ?1",
+            params![batch.batch_id],
+        )?;
+        record_movement(
+            tx,
+            batch.batch_id,
+            -batch.quantity,
+            MovementReason::Disposal,
+            Some(disposal_id),
+        )?;
+        tx.changed(Entity::Batch, ChangeOp::Update, batch.batch_id);
+    }
+
+    sync::enqueue(
+        tx,
+        "dispose_expired",
+        &json!({ "batchIds": batch_ids, "reason": reason, "override": allow_unexpired }),
+    )?;
+    Ok(DisposalSummary {
+        disposal_id,
+        batches: batches.into_iter().map(|(b, _)| b).collect(),
+        total_cost_paise,
+    })
+}
+
+/// Why stock was written off or corrected by hand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AdjustmentReason {
+    Breakage,
+    Theft,
+    /// The shelf count differs from the books.
+    CountError,
+    Other,
+}
+
+impl AdjustmentReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Breakage => "breakage",
+            Self::Theft => "theft",
+            Self::CountError => "count_error",
+            Self::Other => "other",
+        }
+    }
+
+    pub fn parse(s: &str) -> AppResult<Self> {
+        match s {
+            "breakage" => Ok(Self::Breakage),
+            "theft" => Ok(Self::Theft),
+            "count_error" => Ok(Self::CountError),
+            "other" => Ok(Self::Other),
+            other => Err(AppError::validation(format!(
+                "Unknown adjustment reason '{other}'"
+            ))),
+        }
+    }
+
+    /// Breakage and theft only ever take stock away.
+    fn writes_off(self) -> bool {
+        matches!(self, Self::Breakage | Self::Theft)
+    }
+}
+
+/// One entry in the stock adjustment register.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StockAdjustment {
+    pub id: i64,
+    pub batch_id: i64,
+    pub medicine_id: i64,
+    pub reason: AdjustmentReason,
+    pub remarks: String,
+    pub previous_quantity: i64,
+    pub new_quantity: i64,
+    /// Negative when stock was written off.
+    pub change_qty: i64,
+    pub user_id: i64,
+    pub created_at: String,
+}
+
+/// Set a batch to its physically counted quantity.
+///
+/// The adjustment is entered in the register under a reason code with the
+/// user's remarks, and the difference is logged as an `adjustment`
+/// movement referencing it. A count that matches is still recorded, as a
+/// zero change, to record the check.
+pub fn adjust_stock(
+    tx: &Tx,
+    batch_id: i64,
+    new_quantity: i64,
+    reason: AdjustmentReason,
+    remarks: &str,
+    user_id: i64,
+) -> AppResult<StockAdjustment> {
+    auth::require_permission(tx, user_id, permission::INVENTORY_ADJUST)?;
+    let remarks = remarks.trim();
+    if remarks.is_empty() {
+        return Err(AppError::validation("Remarks are required to adjust stock"));
+    }
+    if new_quantity < 0 {
+        return Err(AppError::validation(
+            "The counted quantity cannot be negative",
+        ));
+    }
+    let (medicine_id, current): (i64, i64) = tx
+        .query_row(
+            "SELECT medicine_id, quantity FROM batches WHERE id = ?1",
+            params![batch_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Batch {batch_id}")))?;
+    if reason.writes_off() && new_quantity > current {
+        return Err(AppError::validation(format!(
+            "A {} write-off cannot add stock",
+            reason.as_str()
+        )));
+    }
+    // The count can only change what is on the selling floor; stock kept
+    // in a store is recounted once it has been moved back out.
+    let off_floor = locations::off_floor_quantity(tx, batch_id)?;
+    if new_quantity < off_floor {
+        return Err(AppError::validation(format!(
+            "{off_floor} units of batch {batch_id} are kept away from the selling floor; \
+             move them back before counting the batch below that"
+        )));
+    }
+
+    tx.execute(
+        "UPDATE batches SET quantity = ?1 WHERE id = ?2",
+        params![new_quantity, batch_id],
+    )?;
+    let adjustment = tx.query_row(
+        "INSERT INTO stock_adjustments (batch_id, medicine_id, reason_code, remarks,
+             previous_quantity, new_quantity, user_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         RETURNING id, created_at",
+        params![
+            batch_id,
+            medicine_id,
+            reason.as_str(),
+            remarks,
+            current,
+            new_quantity,
+            user_id
+        ],
+        |row| {
+            Ok(StockAdjustment {
+                id: row.get(0)?,
+                batch_id,
+                medicine_id,
+                reason,
+                remarks: remarks.to_owned(),
+                previous_quantity: current,
+                new_quantity,
+                change_qty: new_quantity - current,
+                user_id,
+                created_at: row.get(1)?,
+            })
+        },
+    )?;
+    record_noted_movement(
+        tx,
+        batch_id,
+        adjustment.change_qty,
+        MovementReason::Adjustment,
+        Some(adjustment.id),
+        Some(remarks),
+        None,
+    )?;
+    tx.changed(Entity::Batch, ChangeOp::Update, batch_id);
+    sync::enqueue(
+        tx,
+        "adjust_stock",
+        &json!({
+            "batchId": batch_id,
+            "newQuantity": new_quantity,
+            "reason": reason,
+            "remarks": remarks,
+            "userId": user_id,
+        }),
+    )?;
+    Ok(adjustment)
+}
+
+fn adjustment_from_row(row: &Row) -> AppResult<StockAdjustment> {
+    let reason: String = row.get(3)?;
+    let previous_quantity: i64 = row.get(5)?;
+    let new_quantity: i64 = row.get(6)?;
+    Ok(StockAdjustment {
+        id: row.get(0)?,
+        batch_id: row.get(1)?,
+        medicine_id: row.get(2)?,
+        reason: AdjustmentReason::parse(&reason)?,
+        remarks: row.get(4)?,
+        previous_quantity,
+        new_quantity,
+        change_qty: new_quantity - previous_quantity,
+        user_id: row.get(7)?,
+        created_at: row.get(8)?,
+    })
+}
+
+/// The adjustment register between two dates, inclusive, oldest first.
+pub fn list_stock_adjustments(
+    conn: &Connection,
+    from_date: &str,
+    to_date: &str,
+) -> AppResult<Vec<StockAdjustment>> {
+    validate_date_range(conn, from_date, to_date)?;
+    let mut stmt = conn.prepare(
+        "SELECT id, batch_id, medicine_id, reason_code, remarks, previous_quantity,
+             new_quantity, user_id, created_at
+         FROM stock_adjustments
+         WHERE date(created_at) BETWEEN ?1 AND ?2
+         ORDER BY id",
+    )?;
+    let mut rows = stmt.query(params![from_date, to_date])?;
+    let mut adjustments = Vec::new();
+    while let Some(row) = rows.next()? {
+        adjustments.push(adjustment_from_row(row)?);
+    }
+    Ok(adjustments)
+}
+
+/// Move stock booked against the wrong batch to the right one, or between
+/// branches.
+///
+/// Both sides are logged as `adjustment` movements carrying `note`, so
+/// they net to zero; when the batches are at different branches both also
+/// record the source and destination branch. Batches of different
+/// medicines are refused unless `allow_cross_medicine` is set, for
+/// repacking one product as another. The user needs `inventory.transfer`.
+pub fn transfer_stock(
+    tx: &Tx,
+    from_batch_id: i64,
+    to_batch_id: i64,
+    quantity: i64,
+    note: &str,
+    allow_cross_medicine: bool,
+    user_id: i64,
+) -> AppResult<()> {
+    auth::require_permission(tx, user_id, permission::INVENTORY_TRANSFER)?;
+    let note = note.trim();
+    if note.is_empty() {
+        return Err(AppError::validation("A note is required to transfer stock"));
+    }
+    if quantity <= 0 {
+        return Err(AppError::validation(
+            "Transfer quantity must be greater than 0",
+        ));
+    }
+    if from_batch_id == to_batch_id {
+        return Err(AppError::validation(
+            "Stock cannot be transferred to the same batch",
+        ));
+    }
+    let batch = |batch_id: i64| -> AppResult<(i64, Option<i64>)> {
+        tx.query_row(
+            "SELECT medicine_id, branch_id FROM batches WHERE id = ?1",
+            params![batch_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Batch {batch_id}")))
+    };
+    let (from_medicine, from_branch) = batch(from_batch_id)?;
+    let (to_medicine, to_branch) = batch(to_batch_id)?;
+    if from_medicine != to_medicine && !allow_cross_medicine {
+        return Err(AppError::validation(
+            "The batches are of different medicines; confirm the cross-medicine transfer",
+        ));
+    }
+
+    if !locations::take_from_floor(tx, from_batch_id, quantity)? {
+        return Err(AppError::validation(format!(
+            "Batch {from_batch_id} has fewer than {quantity} units on the selling floor"
+        )));
+    }
+    tx.execute(
+        "UPDATE batches SET quantity = quantity + ?1 WHERE id = ?2",
+        params![quantity, to_batch_id],
+    )?;
+    let branches = match (from_branch, to_branch) {
+        (Some(from), Some(to)) if from != to => Some((from, to)),
+        _ => None,
+    };
+    for (batch_id, change) in [(from_batch_id, -quantity), (to_batch_id, quantity)] {
+        record_noted_movement(
+            tx,
+            batch_id,
+            change,
+            MovementReason::Adjustment,
+            None,
+            Some(note),
+            branches,
+        )?;
+        tx.changed(Entity::Batch, ChangeOp::Update, batch_id);
+    }
+    sync::enqueue(
+        tx,
+        "transfer_stock",
+        &json!({
+            "fromBatchId": from_batch_id,
+            "toBatchId": to_batch_id,
+            "quantity": quantity,
+            "note": note,
+            "allowCrossMedicine": allow_cross_medicine,
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BatchStatus::*;
+    use super::*;
+    use crate::db::test_support::{
+        batch_quantity, insert_batch, insert_cashier, insert_medicine, migrated_db,
+    };
+
+    #[test]
+    fn blocked_stock_must_pass_through_quarantine() {
+        assert!(!Blocked.can_transition_to(Available));
+        assert!(Blocked.can_transition_to(Quarantine));
+        assert!(Quarantine.can_transition_to(Available));
+    }
+
+    #[test]
+    fn same_status_is_not_a_transition() {
+        for status in [Available, Quarantine, Blocked] {
+            assert!(!status.can_transition_to(status));
+        }
+    }
+
+    #[test]
+    fn disposal_zeroes_expired_stock_and_values_it_at_cost() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let first = insert_batch(&conn, para, "-10 days", 1_000, 4);
+        let second = insert_batch(&conn, para, "-1 days", 2_000, 3);
+
+        let summary = db
+            .with_tx(|tx| dispose_expired(tx, &[first, second], "Expired", false, 1))
+            .unwrap();
+
+        assert_eq!(summary.batches[0].cost_value_paise, 2_800);
+        assert_eq!(summary.batches[1].cost_value_paise, 4_200);
+        assert_eq!(summary.total_cost_paise, 7_000);
+        assert_eq!(batch_quantity(&conn, first), 0);
+        let last = batch_movement_history(&conn, second)
+            .unwrap()
+            .pop()
+            .unwrap();
+        assert_eq!(last.reason, MovementReason::Disposal);
+        assert_eq!(last.change_qty, -3);
+        assert_eq!(last.reference_id, Some(summary.disposal_id));
+    }
+
+    #[test]
+    fn unexpired_stock_needs_the_override() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let expired = insert_batch(&conn, para, "-1 days", 1_000, 4);
+        let fresh = insert_batch(&conn, para, "+90 days", 1_000, 5);
+
+        let err = db
+            .with_tx(|tx| dispose_expired(tx, &[expired, fresh], "Damaged", false, 1))
+            .unwrap_err();
+        assert!(err.to_string().contains("does not expire until"), "{err}");
+        assert_eq!(batch_quantity(&conn, expired), 4);
+
+        db.with_tx(|tx| dispose_expired(tx, &[expired, fresh], "Damaged", true, 1))
+            .unwrap();
+        assert_eq!(batch_quantity(&conn, fresh), 0);
+    }
+
+    #[test]
+    fn stock_expiring_today_sells_but_is_not_yet_disposable() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let today = insert_batch(&conn, para, "+0 days", 1_000, 3);
+
+        let sellable = crate::fefo::sellable_batches(&conn, para, None).unwrap();
+        assert_eq!(sellable.len(), 1);
+
+        let err = db
+            .with_tx(|tx| dispose_expired(tx, &[today], "Expired", false, 1))
+            .unwrap_err();
+        assert!(err.to_string().contains("does not expire until"), "{err}");
+        assert_eq!(batch_quantity(&conn, today), 3);
+    }
+
+    #[test]
+    fn transfer_moves_stock_with_a_noted_pair_of_adjustments() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let wrong = insert_batch(&conn, para, "+90 days", 1_000, 10);
+        let right = insert_batch(&conn, para, "+120 days", 1_000, 2);
+
+        db.with_tx(|tx| transfer_stock(tx, wrong, right, 4, "Booked to the wrong batch", false, 1))
+            .unwrap();
+
+        assert_eq!(batch_quantity(&conn, wrong), 6);
+        assert_eq!(batch_quantity(&conn, right), 6);
+        let out = batch_movement_history(&conn, wrong).unwrap().pop().unwrap();
+        let into = batch_movement_history(&conn, right).unwrap().pop().unwrap();
+        assert_eq!(out.change_qty + into.change_qty, 0);
+        assert_eq!(into.reason, MovementReason::Adjustment);
+        assert_eq!(into.note.as_deref(), Some("Booked to the wrong batch"));
+
+        let err = db
+            .with_tx(|tx| transfer_stock(tx, wrong, right, 7, "Too many", false, 1))
+            .unwrap_err();
+        assert!(err.to_string().contains("fewer than 7"), "{err}");
+    }
+
+    #[test]
+    fn cross_medicine_transfer_needs_the_flag() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let cetirizine = insert_medicine(&conn, "Cetirizine", 12.0);
+        let from = insert_batch(&conn, para, "+90 days", 1_000, 5);
+        let to = insert_batch(&conn, cetirizine, "+90 days", 1_000, 0);
+
+        let err = db
+            .with_tx(|tx| transfer_stock(tx, from, to, 5, "Repacked", false, 1))
+            .unwrap_err();
+        assert!(err.to_string().contains("different medicines"), "{err}");
+        assert_eq!(batch_quantity(&conn, from), 5);
+
+        db.with_tx(|tx| transfer_stock(tx, from, to, 5, "Repacked", true, 1))
+            .unwrap();
+        let into = batch_movement_history(&conn, to).unwrap().pop().unwrap();
+        assert_eq!((into.medicine_id, into.change_qty), (cetirizine, 5));
+    }
+
+    #[test]
+    fn adjustment_sets_the_counted_quantity_and_logs_the_signed_change() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+90 days", 1_000, 10);
+        let count = |quantity, reason, remarks: &'static str| {
+            db.with_tx(|tx| adjust_stock(tx, batch, quantity, reason, remarks, 1))
+        };
+
+        let adjustment = count(7, AdjustmentReason::CountError, "3 strips missing").unwrap();
+
+        assert_eq!(batch_quantity(&conn, batch), 7);
+        assert_eq!(
+            (adjustment.previous_quantity, adjustment.change_qty),
+            (10, -3)
+        );
+        let movement = batch_movement_history(&conn, batch).unwrap().pop().unwrap();
+        assert_eq!(movement.reason, MovementReason::Adjustment);
+        assert_eq!(movement.reference_id, Some(adjustment.id));
+        assert_eq!(movement.note.as_deref(), Some("3 strips missing"));
+        let ledger: i64 = batch_movement_history(&conn, batch)
+            .unwrap()
+            .iter()
+            .map(|m| m.change_qty)
+            .sum();
+        assert_eq!(ledger, 7);
+        let today: String = conn
+            .query_row("SELECT date('now')", [], |r| r.get(0))
+            .unwrap();
+        let register = list_stock_adjustments(&conn, &today, &today).unwrap();
+        assert_eq!(register, vec![adjustment]);
+
+        let err = count(9, AdjustmentReason::CountError, "  ").unwrap_err();
+        assert!(err.to_string().contains("Remarks are required"), "{err}");
+        let err = count(9, AdjustmentReason::Breakage, "Dropped a carton").unwrap_err();
+        assert!(err.to_string().contains("cannot add stock"), "{err}");
+        assert_eq!(batch_quantity(&conn, batch), 7);
+
+        conn.execute(
+            "INSERT INTO users (username, password_hash, full_name, role)
+             VALUES ('till', 'x', 'Till', 'cashier')",
+            [],
+        )
+        .unwrap();
+        let cashier = conn.last_insert_rowid();
+        let err = db
+            .with_tx(|tx| {
+                adjust_stock(tx, batch, 5, AdjustmentReason::Theft, "Shoplifted", cashier)
+            })
+            .unwrap_err();
+        assert!(matches!(err, AppError::Unauthorized(_)), "{err}");
+    }
+
+    #[test]
+    fn cashier_cannot_change_a_batch_status() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+1 year", 1_000, 10);
+        let cashier = insert_cashier(&conn);
+
+        let err = db
+            .with_tx(|tx| set_batch_status(tx, batch, Quarantine, "Recall notice", cashier))
+            .unwrap_err();
+        assert!(matches!(err, AppError::Unauthorized(_)), "{err}");
+
+        db.with_tx(|tx| set_batch_status(tx, batch, Quarantine, "Recall notice", 1))
+            .unwrap();
+        let status: String = conn
+            .query_row("SELECT status FROM batches WHERE id = ?1", [batch], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(status, "quarantine");
+    }
+
+    #[test]
+    fn a_batch_entered_by_hand_opens_its_ledger() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let cashier = insert_cashier(&conn);
+        let batch = BatchInput {
+            medicine_id: para,
+            batch_number: " PCM-9 ".into(),
+            expiry_date: "2099-12-31".into(),
+            cost_price_paise: 600,
+            mrp_paise: 1_200,
+            selling_price_paise: 1_000,
+            quantity: 40,
+            manufacturing_date: None,
+            barcode: None,
+        };
+
+        let err = db
+            .with_tx(|tx| create_batch(tx, &batch, cashier))
+            .unwrap_err();
+        assert!(matches!(err, AppError::Unauthorized(_)), "{err}");
+
+        let id = db.with_tx(|tx| create_batch(tx, &batch, 1)).unwrap();
+        assert_eq!(batch_quantity(&conn, id), 40);
+        let movements = batch_movement_history(&conn, id).unwrap();
+        assert_eq!(movements.len(), 1);
+        assert_eq!(movements[0].change_qty, 40);
+        assert_eq!(movements[0].reason, MovementReason::Purchase);
+    }
+}
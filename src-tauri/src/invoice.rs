@@ -0,0 +1,330 @@
+//! The complete printable invoice of a recorded sale, for reprinting.
+//!
+//! Everything is read back from what was stored when the sale was made, so
+//! a reprint matches the original even after prices, tax slabs or settings
+//! have changed. Only the pharmacy header comes from the current settings.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+
+use crate::einvoice::{self, IrnDetails};
+use crate::error::{AppError, AppResult};
+use crate::ewaybill::{self, EwayBill};
+use crate::format;
+use crate::gst::InvoiceTotals;
+use crate::sales::{self, Payment};
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvoiceHeader {
+    pub name: String,
+    pub address: String,
+    pub city: String,
+    pub pincode: String,
+    pub phone: String,
+    pub email: Option<String>,
+    pub gstin: String,
+    pub drug_license_no: String,
+    pub state_code: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvoiceCustomer {
+    pub id: i64,
+    pub name: String,
+    pub phone: Option<String>,
+    pub address: Option<String>,
+    pub gstin: Option<String>,
+    pub state_code: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvoiceLine {
+    pub medicine_name: String,
+    pub batch_number: String,
+    pub expiry_date: String,
+    pub hsn_code: String,
+    pub quantity: i64,
+    pub unit_price_paise: i64,
+    pub discount_paise: i64,
+    pub taxable_amount_paise: i64,
+    pub cgst_rate: f64,
+    pub cgst_paise: i64,
+    pub sgst_rate: f64,
+    pub sgst_paise: i64,
+    pub igst_rate: f64,
+    pub igst_paise: i64,
+    pub total_paise: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvoiceDocument {
+    pub sale_id: i64,
+    pub invoice_number: String,
+    pub sale_date: String,
+    pub status: String,
+    pub cashier: String,
+    pub pharmacy: InvoiceHeader,
+    /// `None` for walk-in sales.
+    pub customer: Option<InvoiceCustomer>,
+    pub is_interstate: bool,
+    /// Billed under the composition scheme, without tax.
+    pub bill_of_supply: bool,
+    /// State code the sale was taxed for.
+    pub place_of_supply: String,
+    pub items: Vec<InvoiceLine>,
+    pub totals: InvoiceTotals,
+    pub payments: Vec<Payment>,
+    /// The grand total in words, e.g. "One Hundred Rupees Only".
+    pub amount_in_words: String,
+    pub notes: Option<String>,
+    /// The IRN, once the sale is registered as an e-invoice.
+    pub einvoice: Option<IrnDetails>,
+    /// The e-way bill the goods travel on, unless it was cancelled.
+    pub eway_bill: Option<EwayBill>,
+}
+
+/// The declaration rule 5 of the invoice rules requires on a bill of supply.
+pub const COMPOSITION_DECLARATION: &str =
+    "Composition taxable person, not eligible to collect tax on supplies";
+
+impl InvoiceDocument {
+    /// `TAX INVOICE`, or `BILL OF SUPPLY` under the composition scheme.
+    pub fn title(&self) -> &'static str {
+        if self.bill_of_supply {
+            "BILL OF SUPPLY"
+        } else {
+            "TAX INVOICE"
+        }
+    }
+}
+
+/// Fold any CGST/SGST halves into IGST. Sales recorded before IGST was
+/// stored carry halves even when they were inter-state.
+fn as_igst(line: &mut InvoiceLine) {
+    line.igst_rate += line.cgst_rate + line.sgst_rate;
+    line.igst_paise += line.cgst_paise + line.sgst_paise;
+    line.cgst_rate = 0.0;
+    line.sgst_rate = 0.0;
+    line.cgst_paise = 0;
+    line.sgst_paise = 0;
+}
+
+struct SaleRow {
+    invoice_number: String,
+    sale_date: String,
+    status: String,
+    cashier: String,
+    customer_id: Option<i64>,
+    is_interstate: bool,
+    bill_of_supply: bool,
+    place_of_supply: Option<String>,
+    notes: Option<String>,
+    totals: InvoiceTotals,
+}
+
+/// The pharmacy's letterhead, from the current settings.
+pub fn pharmacy_header(conn: &Connection) -> AppResult<InvoiceHeader> {
+    Ok(conn.query_row(
+        "SELECT name, address, city, pincode, phone, email, gstin, drug_license_no, state_code
+         FROM pharmacy_settings WHERE id = 1",
+        [],
+        |row| {
+            Ok(InvoiceHeader {
+                name: row.get(0)?,
+                address: row.get(1)?,
+                city: row.get(2)?,
+                pincode: row.get(3)?,
+                phone: row.get(4)?,
+                email: row.get(5)?,
+                gstin: row.get(6)?,
+                drug_license_no: row.get(7)?,
+                state_code: row.get(8)?,
+            })
+        },
+    )?)
+}
+
+/// Assemble the invoice of any recorded sale, whatever its status.
+pub fn get_invoice(conn: &Connection, sale_id: i64) -> AppResult<InvoiceDocument> {
+    let sale = conn
+        .query_row(
+            "SELECT s.invoice_number, s.sale_date, s.status, COALESCE(u.full_name, ''),
+                 s.customer_id, s.is_interstate, s.notes, s.subtotal_paise, s.discount_paise,
+                 s.total_cgst_paise, s.total_sgst_paise, s.total_igst_paise,
+                 s.total_gst_paise, s.round_off_paise, s.grand_total_paise, s.place_of_supply,
+                 s.bill_of_supply
+             FROM sales s LEFT JOIN users u ON u.id = s.user_id
+             WHERE s.id = ?1",
+            params![sale_id],
+            |row| {
+                Ok(SaleRow {
+                    invoice_number: row.get(0)?,
+                    sale_date: row.get(1)?,
+                    status: row.get(2)?,
+                    cashier: row.get(3)?,
+                    customer_id: row.get(4)?,
+                    is_interstate: row.get(5)?,
+                    bill_of_supply: row.get(16)?,
+                    place_of_supply: row.get(15)?,
+                    notes: row.get(6)?,
+                    totals: InvoiceTotals {
+                        subtotal_paise: row.get(7)?,
+                        discount_paise: row.get(8)?,
+                        total_cgst_paise: row.get(9)?,
+                        total_sgst_paise: row.get(10)?,
+                        total_igst_paise: row.get(11)?,
+                        total_gst_paise: row.get(12)?,
+                        round_off_paise: row.get(13)?,
+                        grand_total_paise: row.get(14)?,
+                    },
+                })
+            },
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Sale {sale_id}")))?;
+
+    let pharmacy = pharmacy_header(conn)?;
+
+    let customer = match sale.customer_id {
+        Some(id) => conn
+            .query_row(
+                "SELECT name, phone, address, gstin, state_code FROM customers WHERE id = ?1",
+                params![id],
+                |row| {
+                    Ok(InvoiceCustomer {
+                        id,
+                        name: row.get(0)?,
+                        phone: row.get(1)?,
+                        address: row.get(2)?,
+                        gstin: row.get(3)?,
+                        state_code: row.get(4)?,
+                    })
+                },
+            )
+            .optional()?,
+        None => None,
+    };
+
+    let mut stmt = conn.prepare(
+        "SELECT m.name, b.batch_number, b.expiry_date, si.hsn_code, si.quantity / si.unit_size,
+             si.unit_price_paise, si.discount_paise, si.taxable_amount_paise,
+             si.cgst_rate, si.cgst_amount_paise, si.sgst_rate, si.sgst_amount_paise,
+             si.igst_rate, si.igst_amount_paise, si.total_paise
+         FROM sale_items si
+         JOIN medicines m ON m.id = si.medicine_id
+         JOIN batches b ON b.id = si.batch_id
+         WHERE si.sale_id = ?1
+         ORDER BY si.id",
+    )?;
+    let lines = stmt.query_map(params![sale_id], |row| {
+        Ok(InvoiceLine {
+            medicine_name: row.get(0)?,
+            batch_number: row.get(1)?,
+            expiry_date: row.get(2)?,
+            hsn_code: row.get(3)?,
+            quantity: row.get(4)?,
+            unit_price_paise: row.get(5)?,
+            discount_paise: row.get(6)?,
+            taxable_amount_paise: row.get(7)?,
+            cgst_rate: row.get(8)?,
+            cgst_paise: row.get(9)?,
+            sgst_rate: row.get(10)?,
+            sgst_paise: row.get(11)?,
+            igst_rate: row.get(12)?,
+            igst_paise: row.get(13)?,
+            total_paise: row.get(14)?,
+        })
+    })?;
+    let mut items = lines.collect::<Result<Vec<_>, _>>()?;
+
+    // Sales recorded without a place fall back to the customer's state.
+    let place_of_supply = sale
+        .place_of_supply
+        .or_else(|| customer.as_ref().and_then(|c| c.state_code.clone()))
+        .filter(|code| !code.is_empty())
+        .unwrap_or_else(|| pharmacy.state_code.clone());
+
+    let mut totals = sale.totals;
+    if sale.is_interstate {
+        items.iter_mut().for_each(as_igst);
+        totals.total_igst_paise += totals.total_cgst_paise + totals.total_sgst_paise;
+        totals.total_cgst_paise = 0;
+        totals.total_sgst_paise = 0;
+    }
+    Ok(InvoiceDocument {
+        sale_id,
+        invoice_number: sale.invoice_number,
+        sale_date: sale.sale_date,
+        status: sale.status,
+        cashier: sale.cashier,
+        pharmacy,
+        customer,
+        is_interstate: sale.is_interstate,
+        bill_of_supply: sale.bill_of_supply,
+        place_of_supply,
+        items,
+        amount_in_words: format::rupees_in_words(totals.grand_total_paise),
+        totals,
+        payments: sales::sale_payment_breakdown(conn, sale_id)?,
+        notes: sale.notes,
+        einvoice: einvoice::sale_irn(conn, sale_id)?,
+        eway_bill: ewaybill::active_eway_bill(conn, sale_id)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{insert_batch, insert_medicine, migrated_db, paid_sale};
+    use crate::sales::{create_sale, PaymentMode};
+
+    #[test]
+    fn invoice_reads_back_the_recorded_sale() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        conn.execute(
+            "UPDATE pharmacy_settings SET name = 'City Pharmacy', state_code = '27'",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO customers (name, state_code) VALUES ('Asha', '29')",
+            [],
+        )
+        .unwrap();
+        let customer = conn.last_insert_rowid();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 1_120, 10);
+        let sale = paid_sale(&conn, Some(customer), PaymentMode::Upi, batch, 3);
+        let sale_id = db.with_tx(|tx| create_sale(tx, &sale)).unwrap().sale_id;
+        conn.execute("UPDATE pharmacy_settings SET name = 'Renamed'", [])
+            .unwrap();
+        conn.execute("UPDATE batches SET selling_price_paise = 999", [])
+            .unwrap();
+
+        let doc = get_invoice(&conn, sale_id).unwrap();
+
+        assert_eq!(doc.pharmacy.name, "Renamed");
+        assert_eq!(doc.customer.as_ref().unwrap().name, "Asha");
+        assert!(doc.is_interstate);
+        let line = &doc.items[0];
+        assert_eq!((line.unit_price_paise, line.quantity), (1_120, 3));
+        assert_eq!((line.igst_rate, line.igst_paise), (12.0, 360));
+        assert_eq!(line.cgst_paise + line.sgst_paise, 0);
+        assert_eq!(doc.totals.grand_total_paise, 3_400);
+        assert_eq!(doc.totals.round_off_paise, 40);
+        assert_eq!(doc.amount_in_words, "Thirty Four Rupees Only");
+        assert_eq!(doc.payments, sale.payments);
+    }
+
+    #[test]
+    fn unknown_sale_is_not_found() {
+        let (_dir, db) = migrated_db();
+        let err = get_invoice(&db.connect().unwrap(), 42).unwrap_err();
+        assert!(matches!(err, AppError::NotFound(_)), "{err}");
+    }
+}
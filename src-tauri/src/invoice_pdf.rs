@@ -0,0 +1,578 @@
+//! The GST tax invoice of a sale as an A4 PDF.
+//!
+//! Tables are set in Courier so their columns line up; text is kept to
+//! ASCII, so amounts read "Rs.".
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use rusqlite::Connection;
+
+use crate::backup;
+use crate::error::AppResult;
+use crate::format::{expiry_month, rupees};
+use crate::invoice::{self, InvoiceDocument};
+use crate::pdf::{Canvas, Font};
+use crate::qr::{self, QrCode};
+
+const PAGE_WIDTH: f64 = 595.0;
+const PAGE_HEIGHT: f64 = 842.0;
+const MARGIN: f64 = 40.0;
+const TABLE_SIZE: f64 = 7.0;
+const TABLE_LEADING: f64 = 10.0;
+
+/// A4 pages laid out top-down from `y`.
+struct Pages {
+    pdf: Canvas,
+    y: f64,
+}
+
+impl Pages {
+    fn new() -> Self {
+        Self {
+            pdf: Canvas::new(PAGE_WIDTH, PAGE_HEIGHT),
+            y: PAGE_HEIGHT - MARGIN,
+        }
+    }
+
+    fn text(&mut self, x: f64, y: f64, font: Font, size: f64, text: &str) {
+        self.pdf.text(x, y, font, size, text);
+    }
+
+    fn mono_right(&mut self, right: f64, y: f64, font: Font, size: f64, text: &str) {
+        self.pdf.mono_right(right, y, font, size, text);
+    }
+
+    fn rule(&mut self, y: f64) {
+        self.pdf.line(MARGIN, y, PAGE_WIDTH - MARGIN, y);
+    }
+
+    /// Move down by `height`, starting a new page first if it would not fit
+    /// above the bottom margin. Returns whether a page was started.
+    fn advance(&mut self, height: f64) -> bool {
+        let new_page = self.y - height < MARGIN;
+        if new_page {
+            self.pdf.new_page();
+            self.y = PAGE_HEIGHT - MARGIN;
+        }
+        self.y -= height;
+        new_page
+    }
+
+    /// Draw `code` as a `side`-point square with its lower left at (x, y),
+    /// one rectangle per run of dark modules.
+    fn qr(&mut self, x: f64, y: f64, side: f64, code: &QrCode) {
+        let module = side / code.size as f64;
+        for row in 0..code.size {
+            let top = y + side - module * (row + 1) as f64;
+            let mut col = 0;
+            while col < code.size {
+                if !code.is_dark(col, row) {
+                    col += 1;
+                    continue;
+                }
+                let start = col;
+                while col < code.size && code.is_dark(col, row) {
+                    col += 1;
+                }
+                let run = (col - start) as f64;
+                self.pdf
+                    .fill_rect(x + module * start as f64, top, module * run, module);
+            }
+        }
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.pdf.finish()
+    }
+}
+
+/// One column of a monospaced table: its heading, width in characters and
+/// whether values are right-aligned.
+struct Column(&'static str, usize, bool);
+
+const ITEM_COLUMNS: [Column; 12] = [
+    Column("#", 3, true),
+    Column("Item", 24, false),
+    Column("HSN", 8, false),
+    Column("Batch", 10, false),
+    Column("Exp", 7, false),
+    Column("Qty", 5, true),
+    Column("Rate", 9, true),
+    Column("Disc", 8, true),
+    Column("Taxable", 10, true),
+    Column("GST%", 5, true),
+    Column("Tax", 9, true),
+    Column("Amount", 10, true),
+];
+
+/// A bill of supply shows no tax, so its items get the width instead.
+const SUPPLY_COLUMNS: [Column; 9] = [
+    Column("#", 3, true),
+    Column("Item", 51, false),
+    Column("HSN", 8, false),
+    Column("Batch", 10, false),
+    Column("Exp", 7, false),
+    Column("Qty", 5, true),
+    Column("Rate", 9, true),
+    Column("Disc", 8, true),
+    Column("Amount", 10, true),
+];
+
+const HSN_COLUMNS: [Column; 7] = [
+    Column("HSN", 10, false),
+    Column("GST%", 6, true),
+    Column("Taxable", 12, true),
+    Column("CGST", 11, true),
+    Column("SGST", 11, true),
+    Column("IGST", 11, true),
+    Column("Total Tax", 12, true),
+];
+
+/// Cells padded and truncated to their columns, one space apart.
+fn table_row(columns: &[Column], cells: &[String]) -> String {
+    columns
+        .iter()
+        .zip(cells)
+        .map(|(Column(_, width, right), cell)| {
+            let cell: String = cell.chars().take(*width).collect();
+            if *right {
+                format!("{cell:>width$}")
+            } else {
+                format!("{cell:<width$}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn table_heading(pages: &mut Pages, columns: &[Column]) {
+    let headings: Vec<String> = columns.iter().map(|c| c.0.to_string()).collect();
+    pages.advance(TABLE_LEADING);
+    let y = pages.y;
+    pages.text(
+        MARGIN,
+        y,
+        Font::MonoBold,
+        TABLE_SIZE,
+        &table_row(columns, &headings),
+    );
+    pages.rule(y - 3.0);
+    pages.advance(4.0);
+}
+
+/// A table line, repeating the heading at the top of a new page.
+fn table_line(pages: &mut Pages, columns: &[Column], cells: &[String]) {
+    if pages.advance(TABLE_LEADING) {
+        pages.y += TABLE_LEADING;
+        table_heading(pages, columns);
+        pages.advance(TABLE_LEADING);
+    }
+    let y = pages.y;
+    pages.text(
+        MARGIN,
+        y,
+        Font::Mono,
+        TABLE_SIZE,
+        &table_row(columns, cells),
+    );
+}
+
+/// Tax on one HSN code at one rate.
+#[derive(Default)]
+struct HsnTotals {
+    taxable: i64,
+    cgst: i64,
+    sgst: i64,
+    igst: i64,
+}
+
+/// Render the invoice document as PDF bytes.
+pub fn render_invoice_pdf(doc: &InvoiceDocument) -> Vec<u8> {
+    let mut pages = Pages::new();
+    let pharmacy = &doc.pharmacy;
+    let right = PAGE_WIDTH - MARGIN;
+
+    // Letterhead on the left, the invoice's identity on the right.
+    pages.advance(16.0);
+    let top = pages.y;
+    pages.text(MARGIN, top, Font::Bold, 16.0, &pharmacy.name);
+    let mut left_lines = vec![pharmacy.address.clone()];
+    let place = [pharmacy.city.as_str(), pharmacy.pincode.as_str()]
+        .iter()
+        .filter(|s| !s.is_empty())
+        .copied()
+        .collect::<Vec<_>>()
+        .join(" - ");
+    left_lines.push(place);
+    let contact = [Some(pharmacy.phone.as_str()), pharmacy.email.as_deref()]
+        .into_iter()
+        .flatten()
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("  |  ");
+    left_lines.push(contact);
+    left_lines.push(format!(
+        "GSTIN: {}    State code: {}",
+        pharmacy.gstin, pharmacy.state_code
+    ));
+    left_lines.push(format!("Drug licence no: {}", pharmacy.drug_license_no));
+    let mut invoice_lines = vec![
+        format!("Invoice no: {}", doc.invoice_number),
+        format!("Date: {}", doc.sale_date),
+    ];
+    if doc.status != "completed" {
+        invoice_lines.push(format!("Status: {}", doc.status.to_uppercase()));
+    }
+    if let Some(bill) = &doc.eway_bill {
+        invoice_lines.push(format!("E-way bill: {}", bill.ewb_no));
+        invoice_lines.push(format!("Valid until: {}", bill.valid_upto));
+    }
+    pages.mono_right(right, top, Font::MonoBold, 12.0, doc.title());
+    for (i, line) in invoice_lines.iter().enumerate() {
+        let y = top - 14.0 - 11.0 * i as f64;
+        pages.mono_right(right, y, Font::Mono, 8.0, line);
+    }
+    for line in left_lines.iter().filter(|l| !l.trim().is_empty()) {
+        pages.advance(11.0);
+        let y = pages.y;
+        pages.text(MARGIN, y, Font::Regular, 9.0, line);
+    }
+    pages.advance(8.0);
+    let y = pages.y;
+    pages.rule(y);
+
+    // Billed to.
+    pages.advance(14.0);
+    let y = pages.y;
+    pages.text(MARGIN, y, Font::Bold, 9.0, "Bill to");
+    let mut bill_to = Vec::new();
+    match &doc.customer {
+        Some(customer) => {
+            bill_to.push(customer.name.clone());
+            bill_to.extend(customer.address.clone());
+            bill_to.extend(customer.phone.as_ref().map(|p| format!("Phone: {p}")));
+            bill_to.extend(customer.gstin.as_ref().map(|g| format!("GSTIN: {g}")));
+            bill_to.push(format!("Place of supply: state {}", doc.place_of_supply));
+        }
+        None => bill_to.push("Walk-in customer".to_string()),
+    }
+    for line in &bill_to {
+        pages.advance(11.0);
+        let y = pages.y;
+        pages.text(MARGIN, y, Font::Regular, 9.0, line);
+    }
+    pages.advance(6.0);
+
+    // E-invoice reference, with the IRP's signed QR code to its right.
+    if let Some(einvoice) = &doc.einvoice {
+        let side = 110.0;
+        pages.advance(side + 4.0);
+        let bottom = pages.y;
+        let lines = [
+            format!("IRN: {}", einvoice.irn),
+            format!("Ack no: {}", einvoice.ack_no),
+            format!("Ack date: {}", einvoice.ack_date),
+        ];
+        for (i, line) in lines.iter().enumerate() {
+            let y = bottom + side - 11.0 * (i + 1) as f64;
+            pages.text(MARGIN, y, Font::Mono, 7.0, line);
+        }
+        if let Ok(code) = qr::encode(einvoice.signed_qr_code.as_bytes()) {
+            pages.qr(right - side, bottom, side, &code);
+        }
+        pages.advance(6.0);
+    }
+
+    // Items.
+    let columns: &[Column] = if doc.bill_of_supply {
+        &SUPPLY_COLUMNS
+    } else {
+        &ITEM_COLUMNS
+    };
+    table_heading(&mut pages, columns);
+    let mut by_hsn = BTreeMap::<(String, i64), HsnTotals>::new();
+    for (i, item) in doc.items.iter().enumerate() {
+        let rate = item.cgst_rate + item.sgst_rate + item.igst_rate;
+        let tax = item.cgst_paise + item.sgst_paise + item.igst_paise;
+        let mut cells = vec![
+            (i + 1).to_string(),
+            item.medicine_name.clone(),
+            item.hsn_code.clone(),
+            item.batch_number.clone(),
+            expiry_month(&item.expiry_date),
+            item.quantity.to_string(),
+            rupees(item.unit_price_paise),
+            rupees(item.discount_paise),
+        ];
+        if !doc.bill_of_supply {
+            cells.extend([
+                rupees(item.taxable_amount_paise),
+                format!("{rate}"),
+                rupees(tax),
+            ]);
+        }
+        cells.push(rupees(item.total_paise));
+        table_line(&mut pages, columns, &cells);
+        // Hundredths of a percent, so 2.5% and 12% sort and group exactly.
+        let totals = by_hsn
+            .entry((item.hsn_code.clone(), (rate * 100.0).round() as i64))
+            .or_default();
+        totals.taxable += item.taxable_amount_paise;
+        totals.cgst += item.cgst_paise;
+        totals.sgst += item.sgst_paise;
+        totals.igst += item.igst_paise;
+    }
+    pages.advance(5.0);
+    let y = pages.y;
+    pages.rule(y);
+
+    // Totals, right-aligned under the table.
+    let t = &doc.totals;
+    let mut totals = vec![("Subtotal", t.subtotal_paise)];
+    if t.discount_paise != 0 {
+        totals.push(("Discount", -t.discount_paise));
+    }
+    if !doc.bill_of_supply {
+        if doc.is_interstate {
+            totals.push(("IGST", t.total_igst_paise));
+        } else {
+            totals.push(("CGST", t.total_cgst_paise));
+            totals.push(("SGST", t.total_sgst_paise));
+        }
+    }
+    if t.round_off_paise != 0 {
+        totals.push(("Round off", t.round_off_paise));
+    }
+    for (label, paise) in totals {
+        pages.advance(11.0);
+        let y = pages.y;
+        pages.mono_right(
+            right,
+            y,
+            Font::Mono,
+            8.0,
+            &format!("{label:>12} {:>12}", rupees(paise)),
+        );
+    }
+    pages.advance(14.0);
+    let y = pages.y;
+    pages.mono_right(
+        right,
+        y,
+        Font::MonoBold,
+        10.0,
+        &format!("Grand total Rs. {}", rupees(t.grand_total_paise)),
+    );
+    pages.advance(12.0);
+    let y = pages.y;
+    pages.text(
+        MARGIN,
+        y,
+        Font::Regular,
+        9.0,
+        &format!("Amount in words: {}", doc.amount_in_words),
+    );
+    if !doc.payments.is_empty() {
+        let paid = doc
+            .payments
+            .iter()
+            .map(|p| format!("{} {}", p.mode.as_str(), rupees(p.amount_paise)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        pages.advance(11.0);
+        let y = pages.y;
+        pages.text(MARGIN, y, Font::Regular, 9.0, &format!("Paid: {paid}"));
+    }
+    pages.advance(10.0);
+
+    // HSN-wise tax summary, or why there is none.
+    pages.advance(12.0);
+    let y = pages.y;
+    if doc.bill_of_supply {
+        pages.text(MARGIN, y, Font::Bold, 9.0, invoice::COMPOSITION_DECLARATION);
+    } else {
+        pages.text(MARGIN, y, Font::Bold, 9.0, "HSN-wise tax summary");
+        table_heading(&mut pages, &HSN_COLUMNS);
+        for ((hsn, rate), totals) in &by_hsn {
+            table_line(
+                &mut pages,
+                &HSN_COLUMNS,
+                &[
+                    hsn.clone(),
+                    format!("{}", *rate as f64 / 100.0),
+                    rupees(totals.taxable),
+                    rupees(totals.cgst),
+                    rupees(totals.sgst),
+                    rupees(totals.igst),
+                    rupees(totals.cgst + totals.sgst + totals.igst),
+                ],
+            );
+        }
+    }
+
+    if let Some(notes) = &doc.notes {
+        pages.advance(16.0);
+        let y = pages.y;
+        pages.text(MARGIN, y, Font::Regular, 8.0, &format!("Notes: {notes}"));
+    }
+    pages.advance(36.0);
+    let y = pages.y;
+    pages.text(
+        MARGIN,
+        y,
+        Font::Regular,
+        7.0,
+        "This is a computer generated invoice.",
+    );
+    pages.mono_right(
+        right,
+        y + 14.0,
+        Font::MonoBold,
+        8.0,
+        &format!("For {}", pharmacy.name),
+    );
+    pages.mono_right(right, y, Font::Mono, 8.0, "Authorised signatory");
+    pages.finish()
+}
+
+/// Write the tax invoice of a recorded sale to `dest` as a PDF. Returns
+/// the file size in bytes.
+///
+/// `dest` must be a new file other than the live database at `live`.
+pub fn generate_invoice_pdf(
+    conn: &Connection,
+    live: &Path,
+    sale_id: i64,
+    dest: &Path,
+) -> AppResult<u64> {
+    let dest = backup::new_destination(live, dest)?;
+    let doc = invoice::get_invoice(conn, sale_id)?;
+    let pdf = render_invoice_pdf(&doc);
+    fs::File::create_new(&dest)?.write_all(&pdf)?;
+    Ok(pdf.len() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{insert_batch, insert_medicine, migrated_db, paid_sale};
+    use crate::sales::{create_sale, PaymentMode};
+
+    fn contains(haystack: &[u8], needle: &str) -> bool {
+        haystack
+            .windows(needle.len())
+            .any(|w| w == needle.as_bytes())
+    }
+
+    #[test]
+    fn invoice_pdf_lists_every_line_and_the_hsn_summary() {
+        let (dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        conn.execute(
+            "UPDATE pharmacy_settings SET name = 'City (Main) Pharmacy',
+                 gstin = '27ABCDE1234F1Z5', drug_license_no = 'MH-PZ-12345'",
+            [],
+        )
+        .unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 1_120, 100);
+        let sale = paid_sale(&conn, None, PaymentMode::Cash, batch, 3);
+        let sale_id = db.with_tx(|tx| create_sale(tx, &sale)).unwrap().sale_id;
+        let dest = dir.path().join("invoice.pdf");
+
+        let size = generate_invoice_pdf(&conn, db.path(), sale_id, &dest).unwrap();
+
+        let pdf = fs::read(&dest).unwrap();
+        assert_eq!(size, pdf.len() as u64);
+        assert!(pdf.starts_with(b"%PDF-1.4\n"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+        assert!(contains(&pdf, r"City \(Main\) Pharmacy"));
+        assert!(contains(&pdf, "GSTIN: 27ABCDE1234F1Z5"));
+        assert!(contains(&pdf, "Drug licence no: MH-PZ-12345"));
+        assert!(contains(&pdf, "HSN-wise tax summary"));
+        assert!(contains(&pdf, "Thirty Four Rupees Only"));
+    }
+
+    #[test]
+    fn invoice_pdf_never_overwrites_the_database_or_an_existing_file() {
+        let (dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 1_120, 100);
+        let sale = paid_sale(&conn, None, PaymentMode::Cash, batch, 1);
+        let sale_id = db.with_tx(|tx| create_sale(tx, &sale)).unwrap().sale_id;
+
+        let err = generate_invoice_pdf(&conn, db.path(), sale_id, db.path()).unwrap_err();
+        assert!(err.to_string().contains("live database"), "{err}");
+        let existing = dir.path().join("invoice.pdf");
+        fs::write(&existing, "keep me").unwrap();
+        let err = generate_invoice_pdf(&conn, db.path(), sale_id, &existing).unwrap_err();
+        assert!(err.to_string().contains("already exists"), "{err}");
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "keep me");
+    }
+
+    #[test]
+    fn e_invoices_carry_the_irn_and_its_signed_qr_code() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 1_120, 100);
+        let sale = paid_sale(&conn, None, PaymentMode::Cash, batch, 1);
+        let sale_id = db.with_tx(|tx| create_sale(tx, &sale)).unwrap().sale_id;
+        let plain = render_invoice_pdf(&invoice::get_invoice(&conn, sale_id).unwrap());
+        conn.execute(
+            "INSERT INTO sale_einvoices
+                 (sale_id, irn, ack_no, ack_date, signed_invoice, signed_qr_code)
+             VALUES (?1, 'f0a1b2', '112610000012345', '2026-10-14 12:00:00', 'inv.jwt', 'qr.jwt')",
+            [sale_id],
+        )
+        .unwrap();
+
+        let pdf = render_invoice_pdf(&invoice::get_invoice(&conn, sale_id).unwrap());
+
+        assert!(!contains(&plain, " re f"));
+        assert!(contains(&pdf, "IRN: f0a1b2"));
+        assert!(contains(&pdf, "Ack no: 112610000012345"));
+        assert!(contains(&pdf, " re f"));
+    }
+
+    #[test]
+    fn composition_sales_print_as_a_bill_of_supply() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        conn.execute("UPDATE pharmacy_settings SET composition_scheme = 1", [])
+            .unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 1_120, 100);
+        let sale = paid_sale(&conn, None, PaymentMode::Cash, batch, 1);
+        let sale_id = db.with_tx(|tx| create_sale(tx, &sale)).unwrap().sale_id;
+
+        let pdf = render_invoice_pdf(&invoice::get_invoice(&conn, sale_id).unwrap());
+
+        assert!(contains(&pdf, "BILL OF SUPPLY"));
+        assert!(contains(&pdf, invoice::COMPOSITION_DECLARATION));
+        assert!(!contains(&pdf, "TAX INVOICE"));
+        assert!(!contains(&pdf, "HSN-wise tax summary"));
+        assert!(!contains(&pdf, "CGST"));
+    }
+
+    #[test]
+    fn long_invoices_continue_on_further_pages() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 1_120, 100);
+        let sale = paid_sale(&conn, None, PaymentMode::Cash, batch, 1);
+        let sale_id = db.with_tx(|tx| create_sale(tx, &sale)).unwrap().sale_id;
+        let mut doc = invoice::get_invoice(&conn, sale_id).unwrap();
+        let line = doc.items[0].clone();
+        doc.items = vec![line; 120];
+
+        let pdf = render_invoice_pdf(&doc);
+
+        assert!(!contains(&pdf, "/Type /Pages /Count 1 "));
+    }
+}
@@ -0,0 +1,361 @@
+//! Barcode labels for batches, one label to a PDF page sized to the roll.
+//!
+//! Each label carries the medicine name, batch number, expiry, MRP and the
+//! batch's barcode, so loose stock and re-packed strips scan at the till. A
+//! batch without a barcode is given an in-store EAN-13 first.
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::auth::{self, permission};
+use crate::backup;
+use crate::barcode::{self, Symbology};
+use crate::db::{ChangeOp, Entity, Tx};
+use crate::error::{AppError, AppResult};
+use crate::format::{expiry_month, rupees};
+use crate::pdf::{self, Canvas, Font, PT_PER_MM};
+use crate::sync;
+
+/// Most labels one request may print.
+const MAX_COPIES: u32 = 500;
+
+/// Common label rolls, width by height in millimetres.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LabelSize {
+    #[serde(rename = "38x25")]
+    Mm38x25,
+    #[serde(rename = "50x25")]
+    Mm50x25,
+    #[serde(rename = "50x38")]
+    Mm50x38,
+}
+
+impl LabelSize {
+    fn millimetres(self) -> (f64, f64) {
+        match self {
+            Self::Mm38x25 => (38.0, 25.0),
+            Self::Mm50x25 => (50.0, 25.0),
+            Self::Mm50x38 => (50.0, 38.0),
+        }
+    }
+}
+
+/// Which batch to label, how many labels and how they look.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LabelRequest {
+    pub batch_id: i64,
+    pub copies: u32,
+    pub symbology: Symbology,
+    pub size: LabelSize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LabelSheet {
+    pub batch_id: i64,
+    /// What the labels encode.
+    pub barcode: String,
+    /// Whether `barcode` was assigned to the batch for these labels.
+    pub assigned: bool,
+    pub labels: u32,
+}
+
+struct LabelContent {
+    medicine_name: String,
+    batch_number: String,
+    expiry_date: String,
+    mrp_paise: i64,
+    barcode: String,
+}
+
+/// Courier text cut to fit `width` points.
+fn fitted(text: &str, size: f64, width: f64) -> String {
+    let fits = (width / pdf::mono_width("M", size)).floor() as usize;
+    text.chars().take(fits).collect()
+}
+
+fn render_labels(
+    content: &LabelContent,
+    modules: &[bool],
+    size: LabelSize,
+    copies: u32,
+) -> Vec<u8> {
+    let (width_mm, height_mm) = size.millimetres();
+    let (width, height) = (width_mm * PT_PER_MM, height_mm * PT_PER_MM);
+    let margin = 1.5 * PT_PER_MM;
+    let inner = width - 2.0 * margin;
+    // The bars fill the width left by quiet zones of ten modules a side.
+    let module = inner / (modules.len() + 20) as f64;
+    let text_size = (height / 10.0).min(7.0);
+    let line = text_size + 1.5;
+    let bars_top = height - margin - 2.0 * line;
+    let bars_bottom = margin + 2.0 * line;
+
+    let mut pdf = Canvas::new(width, height);
+    for copy in 0..copies {
+        if copy > 0 {
+            pdf.new_page();
+        }
+        let name = fitted(&content.medicine_name, text_size, inner);
+        pdf.text(
+            margin,
+            height - margin - text_size,
+            Font::MonoBold,
+            text_size,
+            &name,
+        );
+        let batch = fitted(
+            &format!(
+                "B:{} EXP:{}",
+                content.batch_number,
+                expiry_month(&content.expiry_date)
+            ),
+            text_size,
+            inner,
+        );
+        pdf.text(
+            margin,
+            height - margin - text_size - line,
+            Font::Mono,
+            text_size,
+            &batch,
+        );
+
+        let mut x = margin + 10.0 * module;
+        for &bar in modules {
+            if bar {
+                pdf.fill_rect(x, bars_bottom, module, bars_top - bars_bottom);
+            }
+            x += module;
+        }
+
+        let code = fitted(&content.barcode, text_size, inner);
+        let code_x = (width - pdf::mono_width(&code, text_size)) / 2.0;
+        pdf.text(code_x, margin + line, Font::Mono, text_size, &code);
+        let mrp = format!("MRP Rs. {}", rupees(content.mrp_paise));
+        let mrp_x = (width - pdf::mono_width(&mrp, text_size)) / 2.0;
+        pdf.text(mrp_x, margin, Font::MonoBold, text_size, &mrp);
+    }
+    pdf.finish()
+}
+
+/// Write `copies` labels for a batch to `dest` as a PDF, one per page.
+///
+/// The batch's own barcode is printed in `symbology`; EAN-13 needs a valid
+/// thirteen-digit code. A batch with no barcode is assigned an in-store
+/// EAN-13, so its labels scan at the till.
+///
+/// `dest` must be a new file other than the live database at `live`.
+pub fn generate_batch_labels(
+    tx: &Tx,
+    live: &Path,
+    request: LabelRequest,
+    dest: &Path,
+    user_id: i64,
+) -> AppResult<LabelSheet> {
+    let LabelRequest {
+        batch_id,
+        copies,
+        symbology,
+        size,
+    } = request;
+    auth::require_permission(tx, user_id, permission::MEDICINES_MANAGE)?;
+    let dest = backup::new_destination(live, dest)?;
+    if copies == 0 || copies > MAX_COPIES {
+        return Err(AppError::validation(format!(
+            "Print between 1 and {MAX_COPIES} labels at a time"
+        )));
+    }
+    let (mut content, existing) = tx
+        .query_row(
+            "SELECT m.name, b.batch_number, b.expiry_date, b.mrp_paise, b.barcode
+             FROM batches b JOIN medicines m ON m.id = b.medicine_id
+             WHERE b.id = ?1",
+            params![batch_id],
+            |row| {
+                let barcode: Option<String> = row.get(4)?;
+                Ok((
+                    LabelContent {
+                        medicine_name: row.get(0)?,
+                        batch_number: row.get(1)?,
+                        expiry_date: row.get(2)?,
+                        mrp_paise: row.get(3)?,
+                        barcode: String::new(),
+                    },
+                    barcode.filter(|code| !code.trim().is_empty()),
+                ))
+            },
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Batch {batch_id}")))?;
+
+    let assigned = existing.is_none();
+    content.barcode = match existing {
+        Some(code) => code.trim().to_string(),
+        None => {
+            let code = barcode::in_store_ean13(batch_id);
+            tx.execute(
+                "UPDATE batches SET barcode = ?1 WHERE id = ?2",
+                params![code, batch_id],
+            )?;
+            tx.changed(Entity::Batch, ChangeOp::Update, batch_id);
+            sync::enqueue(
+                tx,
+                "assign_batch_barcode",
+                &json!({ "batchId": batch_id, "barcode": code }),
+            )?;
+            code
+        }
+    };
+    let modules = barcode::encode(symbology, &content.barcode)?;
+    fs::File::create_new(&dest)?.write_all(&render_labels(&content, &modules, size, copies))?;
+    Ok(LabelSheet {
+        batch_id,
+        barcode: content.barcode,
+        assigned,
+        labels: copies,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{insert_batch, insert_cashier, insert_medicine, migrated_db};
+    use crate::medicines::lookup_by_barcode;
+
+    const ADMIN: i64 = 1;
+
+    fn request(batch_id: i64, copies: u32, symbology: Symbology, size: LabelSize) -> LabelRequest {
+        LabelRequest {
+            batch_id,
+            copies,
+            symbology,
+            size,
+        }
+    }
+
+    #[test]
+    fn unlabelled_batch_gets_an_in_store_code_that_scans() {
+        let (dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol 500mg", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 1_000, 10);
+        let dest = dir.path().join("labels.pdf");
+
+        let sheet = db
+            .with_tx(|tx| {
+                generate_batch_labels(
+                    tx,
+                    db.path(),
+                    request(batch, 3, Symbology::Ean13, LabelSize::Mm50x25),
+                    &dest,
+                    ADMIN,
+                )
+            })
+            .unwrap();
+
+        assert!(sheet.assigned);
+        assert_eq!(sheet.barcode, barcode::in_store_ean13(batch));
+        let scan = lookup_by_barcode(&conn, &sheet.barcode).unwrap().unwrap();
+        assert_eq!(scan.batch_id, batch);
+        let pdf = String::from_utf8_lossy(&std::fs::read(&dest).unwrap()).into_owned();
+        assert!(pdf.contains("/Type /Pages /Count 3 "));
+        assert!(pdf.contains("(Paracetamol 500mg) Tj"));
+        assert!(pdf.contains("(MRP Rs. 10.00) Tj"));
+
+        // The code sticks; a second run reuses it.
+        let dest = dir.path().join("more-labels.pdf");
+        let again = db
+            .with_tx(|tx| {
+                generate_batch_labels(
+                    tx,
+                    db.path(),
+                    request(batch, 1, Symbology::Code128, LabelSize::Mm38x25),
+                    &dest,
+                    ADMIN,
+                )
+            })
+            .unwrap();
+        assert_eq!(
+            (again.barcode.as_str(), again.assigned),
+            (sheet.barcode.as_str(), false)
+        );
+    }
+
+    #[test]
+    fn ean13_labels_need_an_ean13_barcode() {
+        let (dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 1_000, 10);
+        conn.execute(
+            "UPDATE batches SET barcode = 'LOOSE-PARA' WHERE id = ?1",
+            params![batch],
+        )
+        .unwrap();
+        let dest = dir.path().join("labels.pdf");
+
+        let err = db
+            .with_tx(|tx| {
+                generate_batch_labels(
+                    tx,
+                    db.path(),
+                    request(batch, 1, Symbology::Ean13, LabelSize::Mm50x25),
+                    &dest,
+                    ADMIN,
+                )
+            })
+            .unwrap_err();
+        assert!(err.to_string().contains("Code 128"), "{err}");
+        db.with_tx(|tx| {
+            generate_batch_labels(
+                tx,
+                db.path(),
+                request(batch, 1, Symbology::Code128, LabelSize::Mm50x25),
+                &dest,
+                ADMIN,
+            )
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn labels_need_permission_and_a_new_file() {
+        let (dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 1_000, 10);
+        let labels = request(batch, 1, Symbology::Code128, LabelSize::Mm50x25);
+        let cashier = insert_cashier(&conn);
+        let dest = dir.path().join("labels.pdf");
+
+        let err = db
+            .with_tx(|tx| generate_batch_labels(tx, db.path(), labels, &dest, cashier))
+            .unwrap_err();
+        assert!(matches!(err, AppError::Unauthorized(_)), "{err}");
+        let code: Option<String> = conn
+            .query_row(
+                "SELECT barcode FROM batches WHERE id = ?1",
+                [batch],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(code, None);
+
+        let err = db
+            .with_tx(|tx| generate_batch_labels(tx, db.path(), labels, db.path(), ADMIN))
+            .unwrap_err();
+        assert!(err.to_string().contains("live database"), "{err}");
+        std::fs::write(&dest, "keep me").unwrap();
+        let err = db
+            .with_tx(|tx| generate_batch_labels(tx, db.path(), labels, &dest, ADMIN))
+            .unwrap_err();
+        assert!(err.to_string().contains("already exists"), "{err}");
+        assert_eq!(std::fs::read_to_string(&dest).unwrap(), "keep me");
+    }
+}
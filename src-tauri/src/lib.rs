@@ -1,221 +1,306 @@
-use tauri_plugin_sql::{Migration, MigrationKind};
+mod alerts;
+mod audit;
+mod auth;
+mod backup;
+mod backup_archive;
+mod barcode;
+mod branches;
+mod cart;
+mod commands;
+mod customers;
+mod dashboard;
+mod daybook;
+mod db;
+mod demand;
+mod einvoice;
+mod encryption;
+mod error;
+mod ewaybill;
+mod expenses;
+mod export;
+mod fefo;
+mod format;
+mod gst;
+mod gst_history;
+mod gstr1;
+mod gstr3b;
+mod http;
+mod import;
+mod inventory;
+mod invoice;
+mod invoice_pdf;
+mod labels;
+mod locations;
+mod loyalty;
+mod maintenance;
+mod medicines;
+mod migrations;
+mod pdf;
+mod prescriptions;
+mod printing;
+mod purchase_orders;
+mod purchase_returns;
+mod purchases;
+mod qr;
+mod registration;
+mod remote_backup;
+mod reorder;
+mod reports;
+mod returns;
+mod sales;
+mod scheduler;
+mod settings;
+mod setup;
+mod shifts;
+mod stock_take;
+mod suppliers;
+mod sync;
+mod tally;
 
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    let migrations = vec![
-        Migration {
-            version: 1,
-            description: "create initial schema",
-            sql: r#"
-                PRAGMA journal_mode=WAL;
-                PRAGMA foreign_keys=ON;
-
-                CREATE TABLE IF NOT EXISTS users (
-                    id INTEGER PRIMARY KEY AUTOINCREMENT,
-                    username TEXT NOT NULL UNIQUE,
-                    password_hash TEXT NOT NULL,
-                    full_name TEXT NOT NULL,
-                    role TEXT NOT NULL CHECK(role IN ('admin', 'pharmacist', 'cashier')),
-                    is_active INTEGER NOT NULL DEFAULT 1,
-                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
-                    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
-                );
-
-                CREATE TABLE IF NOT EXISTS gst_slabs (
-                    id INTEGER PRIMARY KEY AUTOINCREMENT,
-                    rate REAL NOT NULL UNIQUE,
-                    description TEXT NOT NULL
-                );
-
-                CREATE TABLE IF NOT EXISTS medicines (
-                    id INTEGER PRIMARY KEY AUTOINCREMENT,
-                    name TEXT NOT NULL,
-                    generic_name TEXT,
-                    brand_name TEXT,
-                    manufacturer TEXT,
-                    dosage_form TEXT NOT NULL DEFAULT 'tablet',
-                    strength TEXT,
-                    category TEXT,
-                    hsn_code TEXT NOT NULL DEFAULT '3004',
-                    gst_slab_id INTEGER NOT NULL,
-                    reorder_level INTEGER NOT NULL DEFAULT 20,
-                    is_active INTEGER NOT NULL DEFAULT 1,
-                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
-                    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
-                    FOREIGN KEY (gst_slab_id) REFERENCES gst_slabs(id)
-                );
-
-                CREATE TABLE IF NOT EXISTS batches (
-                    id INTEGER PRIMARY KEY AUTOINCREMENT,
-                    medicine_id INTEGER NOT NULL,
-                    batch_number TEXT NOT NULL,
-                    expiry_date TEXT NOT NULL,
-                    cost_price_paise INTEGER NOT NULL,
-                    mrp_paise INTEGER NOT NULL,
-                    selling_price_paise INTEGER NOT NULL,
-                    quantity INTEGER NOT NULL DEFAULT 0,
-                    manufacturing_date TEXT,
-                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
-                    FOREIGN KEY (medicine_id) REFERENCES medicines(id),
-                    CHECK (selling_price_paise <= mrp_paise),
-                    CHECK (cost_price_paise >= 0),
-                    CHECK (mrp_paise > 0),
-                    CHECK (selling_price_paise > 0),
-                    CHECK (quantity >= 0)
-                );
-
-                CREATE TABLE IF NOT EXISTS customers (
-                    id INTEGER PRIMARY KEY AUTOINCREMENT,
-                    name TEXT NOT NULL,
-                    phone TEXT,
-                    email TEXT,
-                    address TEXT,
-                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
-                    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
-                );
-
-                CREATE TABLE IF NOT EXISTS suppliers (
-                    id INTEGER PRIMARY KEY AUTOINCREMENT,
-                    name TEXT NOT NULL,
-                    phone TEXT,
-                    email TEXT,
-                    address TEXT,
-                    gst_in TEXT,
-                    drug_license_no TEXT,
-                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
-                    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
-                );
-
-                CREATE TABLE IF NOT EXISTS supplier_payments (
-                    id INTEGER PRIMARY KEY AUTOINCREMENT,
-                    supplier_id INTEGER NOT NULL,
-                    amount_paise INTEGER NOT NULL,
-                    payment_date TEXT NOT NULL,
-                    payment_mode TEXT NOT NULL CHECK(payment_mode IN ('cash', 'card', 'upi', 'credit')),
-                    reference TEXT,
-                    notes TEXT,
-                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
-                    FOREIGN KEY (supplier_id) REFERENCES suppliers(id)
-                );
-
-                CREATE TABLE IF NOT EXISTS sales (
-                    id INTEGER PRIMARY KEY AUTOINCREMENT,
-                    invoice_number TEXT NOT NULL UNIQUE,
-                    customer_id INTEGER,
-                    user_id INTEGER NOT NULL,
-                    sale_date TEXT NOT NULL DEFAULT (datetime('now')),
-                    subtotal_paise INTEGER NOT NULL DEFAULT 0,
-                    discount_paise INTEGER NOT NULL DEFAULT 0,
-                    total_cgst_paise INTEGER NOT NULL DEFAULT 0,
-                    total_sgst_paise INTEGER NOT NULL DEFAULT 0,
-                    total_gst_paise INTEGER NOT NULL DEFAULT 0,
-                    grand_total_paise INTEGER NOT NULL DEFAULT 0,
-                    payment_mode TEXT NOT NULL DEFAULT 'cash' CHECK(payment_mode IN ('cash', 'card', 'upi', 'credit')),
-                    notes TEXT,
-                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
-                    FOREIGN KEY (customer_id) REFERENCES customers(id),
-                    FOREIGN KEY (user_id) REFERENCES users(id)
-                );
-
-                CREATE TABLE IF NOT EXISTS sale_items (
-                    id INTEGER PRIMARY KEY AUTOINCREMENT,
-                    sale_id INTEGER NOT NULL,
-                    batch_id INTEGER NOT NULL,
-                    medicine_id INTEGER NOT NULL,
-                    quantity INTEGER NOT NULL,
-                    unit_price_paise INTEGER NOT NULL,
-                    discount_paise INTEGER NOT NULL DEFAULT 0,
-                    taxable_amount_paise INTEGER NOT NULL,
-                    cgst_rate REAL NOT NULL DEFAULT 0,
-                    cgst_amount_paise INTEGER NOT NULL DEFAULT 0,
-                    sgst_rate REAL NOT NULL DEFAULT 0,
-                    sgst_amount_paise INTEGER NOT NULL DEFAULT 0,
-                    total_paise INTEGER NOT NULL,
-                    FOREIGN KEY (sale_id) REFERENCES sales(id),
-                    FOREIGN KEY (batch_id) REFERENCES batches(id),
-                    FOREIGN KEY (medicine_id) REFERENCES medicines(id)
-                );
-
-                CREATE TABLE IF NOT EXISTS prescriptions (
-                    id INTEGER PRIMARY KEY AUTOINCREMENT,
-                    customer_id INTEGER NOT NULL,
-                    sale_id INTEGER,
-                    doctor_name TEXT NOT NULL,
-                    rx_number TEXT,
-                    prescription_date TEXT NOT NULL,
-                    notes TEXT,
-                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
-                    FOREIGN KEY (customer_id) REFERENCES customers(id),
-                    FOREIGN KEY (sale_id) REFERENCES sales(id)
-                );
+use tauri::{Emitter, Manager};
+use tauri_plugin_notification::NotificationExt;
 
-                CREATE TABLE IF NOT EXISTS pharmacy_settings (
-                    id INTEGER PRIMARY KEY CHECK(id = 1),
-                    name TEXT NOT NULL DEFAULT 'My Pharmacy',
-                    address TEXT NOT NULL DEFAULT '',
-                    phone TEXT NOT NULL DEFAULT '',
-                    email TEXT,
-                    gstin TEXT NOT NULL DEFAULT '',
-                    drug_license_no TEXT NOT NULL DEFAULT '',
-                    state_code TEXT NOT NULL DEFAULT '',
-                    invoice_prefix TEXT NOT NULL DEFAULT 'INV',
-                    next_invoice_number INTEGER NOT NULL DEFAULT 1,
-                    low_stock_threshold INTEGER NOT NULL DEFAULT 20,
-                    near_expiry_days INTEGER NOT NULL DEFAULT 90,
-                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
-                    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
-                );
+use db::{Db, DATA_CHANGED_EVENT, DB_URL};
+use scheduler::Scheduler;
 
-                CREATE INDEX IF NOT EXISTS idx_medicines_name ON medicines(name);
-                CREATE INDEX IF NOT EXISTS idx_batches_medicine_id ON batches(medicine_id);
-                CREATE INDEX IF NOT EXISTS idx_batches_expiry_date ON batches(expiry_date);
-                CREATE INDEX IF NOT EXISTS idx_sales_invoice_number ON sales(invoice_number);
-                CREATE INDEX IF NOT EXISTS idx_sales_sale_date ON sales(sale_date);
-                CREATE INDEX IF NOT EXISTS idx_sale_items_sale_id ON sale_items(sale_id);
-                CREATE INDEX IF NOT EXISTS idx_customers_name ON customers(name);
-                CREATE INDEX IF NOT EXISTS idx_customers_phone ON customers(phone);
-                CREATE INDEX IF NOT EXISTS idx_suppliers_name ON suppliers(name);
-                CREATE INDEX IF NOT EXISTS idx_prescriptions_customer_id ON prescriptions(customer_id);
-            "#,
-            kind: MigrationKind::Up,
-        },
-        Migration {
-            version: 2,
-            description: "seed default data",
-            sql: r#"
-                INSERT OR IGNORE INTO gst_slabs (rate, description) VALUES (0, 'GST Exempt (0%)');
-                INSERT OR IGNORE INTO gst_slabs (rate, description) VALUES (5, 'GST 5% (Most medicines post Sep 2025)');
-                INSERT OR IGNORE INTO gst_slabs (rate, description) VALUES (12, 'GST 12%');
-                INSERT OR IGNORE INTO gst_slabs (rate, description) VALUES (18, 'GST 18%');
-
-                INSERT OR IGNORE INTO users (username, password_hash, full_name, role, is_active)
-                VALUES ('admin', '$2b$10$n5vbmtk602soZ1.G8CH/6u4F69goDCXD9c.SObHhhH36QdpnVtFkG', 'Administrator', 'admin', 1);
-
-                INSERT OR IGNORE INTO pharmacy_settings (id, name, address, phone, gstin, drug_license_no, state_code)
-                VALUES (1, 'My Pharmacy', '123 Main Street', '0000000000', '', '', '');
-            "#,
-            kind: MigrationKind::Up,
-        },
-        Migration {
-            version: 3,
-            description: "add hsn_code to sale_items",
-            sql: r#"
-                ALTER TABLE sale_items ADD COLUMN hsn_code TEXT NOT NULL DEFAULT '3004';
-            "#,
-            kind: MigrationKind::Up,
-        },
-    ];
+/// File name of the database inside the app config dir.
+const DB_FILE: &str = "pharmacare.db";
+/// How often the scheduler looks for due jobs; well under a minute, the
+/// finest step a schedule can take.
+const SCHEDULER_TICK: std::time::Duration = std::time::Duration::from_secs(20);
 
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        // The plugin's sqlx pool enables `foreign_keys` on every connection it
+        // opens (the sqlx SQLite default); `Db::connect` does the same for the
+        // backend's own connections, and `schema_health` reports it.
         .plugin(
             tauri_plugin_sql::Builder::default()
-                .add_migrations("sqlite:pharmacare.db", migrations)
+                .add_migrations(DB_URL, migrations::all())
                 .build(),
         )
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
-        .plugin(tauri_plugin_process::init())
+        .plugin(tauri_plugin_notification::init())
+        .setup(|app| {
+            // The SQL plugin resolves `DB_URL` against the app config dir;
+            // backend commands open the same file directly.
+            let dir = app.path().app_config_dir()?;
+            std::fs::create_dir_all(&dir)?;
+            let handle = app.handle().clone();
+            let db = Db::new(dir.join(DB_FILE)).on_change(move |change| {
+                if let Err(err) = handle.emit(DATA_CHANGED_EVENT, change) {
+                    eprintln!("failed to emit {DATA_CHANGED_EVENT}: {err}");
+                }
+            });
+            app.manage(db);
+
+            // Every periodic task is registered here; schedules and run
+            // history are kept in the database and edited in settings.
+            let handle = app.handle().clone();
+            let scheduler = Scheduler::new()
+                .job(
+                    "backup",
+                    "Take the daily backup, run maintenance after it and upload to remote storage",
+                    "* * * * *",
+                    |db| {
+                        db.with_conn(|conn| {
+                            let snapshot = backup::backup_if_due(conn, db.path())?;
+                            if snapshot.is_some() {
+                                maintenance::maintenance_if_due(conn, db.path())?;
+                            }
+                            remote_backup::upload_if_due(conn, db.path())?;
+                            Ok(snapshot.map(|info| format!("Backed up to {}", info.path)))
+                        })
+                    },
+                )
+                .job(
+                    "stock_alerts",
+                    "Raise low-stock and near-expiry alerts",
+                    "*/15 * * * *",
+                    move |db| {
+                        let raised = db.with_tx(alerts::check_alerts)?;
+                        if let Some(body) = alerts::notification_body(&raised) {
+                            if let Err(err) = handle
+                                .notification()
+                                .builder()
+                                .title("Stock alerts")
+                                .body(body)
+                                .show()
+                            {
+                                eprintln!("failed to show alert notification: {err}");
+                            }
+                        }
+                        Ok((!raised.is_empty()).then(|| format!("Raised {} alerts", raised.len())))
+                    },
+                );
+            app.manage(scheduler);
+
+            let handle = app.handle().clone();
+            std::thread::spawn(move || loop {
+                let db = handle.state::<Db>();
+                if let Err(err) = handle.state::<Scheduler>().tick(&db) {
+                    eprintln!("scheduled jobs failed: {err}");
+                }
+                std::thread::sleep(SCHEDULER_TICK);
+            });
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            commands::alerts::acknowledge_alert,
+            commands::alerts::check_alerts,
+            commands::alerts::list_alerts,
+            commands::audit::query_audit_log,
+            commands::auth::change_password,
+            commands::auth::check_permission,
+            commands::auth::create_user,
+            commands::auth::current_session,
+            commands::auth::login,
+            commands::auth::logout,
+            commands::auth::set_role_permissions,
+            commands::auth::unlock_user,
+            commands::auth::update_user,
+            commands::backup::backup_database,
+            commands::backup::backup_now,
+            commands::backup::backup_status,
+            commands::backup::export_backup,
+            commands::backup::list_backups,
+            commands::backup::remote_backup_secrets,
+            commands::backup::restore_backup,
+            commands::backup::restore_database,
+            commands::backup::set_remote_backup_secrets,
+            commands::cart::validate_cart,
+            commands::customers::create_customer,
+            commands::customers::customer_ledger,
+            commands::customers::customer_outstanding,
+            commands::customers::open_invoices,
+            commands::customers::receivables_aging,
+            commands::customers::record_customer_payment,
+            commands::customers::update_customer,
+            commands::dashboard::dashboard_metrics,
+            commands::einvoice::generate_einvoice_json,
+            commands::einvoice::generate_irn,
+            commands::encryption::change_db_key,
+            commands::encryption::database_status,
+            commands::encryption::encrypt_database,
+            commands::encryption::unlock_database,
+            commands::encryption::verify_db_key,
+            commands::ewaybill::cancel_eway_bill,
+            commands::ewaybill::generate_eway_bill,
+            commands::expenses::create_expense,
+            commands::expenses::create_expense_category,
+            commands::expenses::delete_expense,
+            commands::expenses::expense_report,
+            commands::expenses::list_expense_categories,
+            commands::expenses::list_expenses,
+            commands::expenses::set_expense_category_active,
+            commands::expenses::update_expense,
+            commands::export::export_day_book_csv,
+            commands::export::export_sales_csv,
+            commands::export::export_tally_xml,
+            commands::fefo::pick_batches_fefo,
+            commands::gst_history::set_medicine_gst,
+            commands::gstr1::export_gstr1_csv,
+            commands::gstr1::export_gstr1_json,
+            commands::gstr1::gstr1_report,
+            commands::gstr3b::gstr3b_summary,
+            commands::import::import_medicines_csv,
+            commands::inventory::adjust_stock,
+            commands::inventory::batch_movement_history,
+            commands::inventory::create_batch,
+            commands::inventory::dispose_expired,
+            commands::inventory::list_stock_adjustments,
+            commands::inventory::set_batch_status,
+            commands::inventory::transfer_stock,
+            commands::invoice::generate_invoice_pdf,
+            commands::invoice::get_invoice,
+            commands::labels::generate_batch_labels,
+            commands::locations::batch_locations,
+            commands::locations::create_location,
+            commands::locations::list_locations,
+            commands::locations::transfer_between_locations,
+            commands::loyalty::redeem_points,
+            commands::maintenance::db_maintenance,
+            commands::maintenance::optimize_database,
+            commands::maintenance::schema_health,
+            commands::medicines::create_medicine,
+            commands::medicines::deactivate_medicine,
+            commands::medicines::lookup_barcode,
+            commands::medicines::lookup_by_barcode,
+            commands::medicines::reactivate_medicine,
+            commands::medicines::search_medicines,
+            commands::medicines::update_medicine,
+            commands::prescriptions::create_prescription,
+            commands::prescriptions::link_prescription,
+            commands::printing::print_receipt,
+            commands::printing::print_shift_report,
+            commands::printing::printer_config,
+            commands::purchase_orders::approve_purchase_order,
+            commands::purchase_orders::close_purchase_order,
+            commands::purchase_orders::create_purchase_order,
+            commands::purchase_orders::get_purchase_order,
+            commands::purchase_orders::purchase_order_document,
+            commands::purchase_orders::send_purchase_order,
+            commands::purchase_returns::create_purchase_return,
+            commands::purchase_returns::get_debit_note,
+            commands::purchase_returns::list_debit_notes,
+            commands::purchase_returns::mark_debit_note_credited,
+            commands::purchases::receive_goods,
+            commands::purchases::receive_purchase,
+            commands::registration::lookup_gstin,
+            commands::registration::validate_registrations,
+            commands::reorder::apply_reorder_levels,
+            commands::reorder::draft_suggested_orders,
+            commands::reorder::suggest_orders,
+            commands::reorder::suggest_reorder_levels,
+            commands::reports::abc_analysis,
+            commands::reports::daily_sales_summary,
+            commands::reports::day_book,
+            commands::reports::dead_stock_report,
+            commands::reports::expiry_report,
+            commands::reports::hsn_tax_summary,
+            commands::reports::inventory_valuation,
+            commands::reports::margin_report,
+            commands::reports::movement_analytics,
+            commands::reports::profit_report,
+            commands::reports::profit_summary,
+            commands::reports::reorder_report,
+            commands::reports::sales_analytics,
+            commands::reports::stock_by_location,
+            commands::reports::stock_valuation,
+            commands::returns::create_sale_return,
+            commands::sales::amend_sale,
+            commands::sales::create_sale,
+            commands::sales::sale_payment_breakdown,
+            commands::scheduler::list_scheduled_jobs,
+            commands::scheduler::scheduled_job_runs,
+            commands::scheduler::set_scheduled_job_enabled,
+            commands::scheduler::set_scheduled_job_schedule,
+            commands::scheduler::trigger_scheduled_job,
+            commands::settings::update_settings,
+            commands::setup::complete_first_run,
+            commands::shifts::close_shift,
+            commands::shifts::current_shift,
+            commands::shifts::list_shifts,
+            commands::shifts::open_shift,
+            commands::shifts::shift_report,
+            commands::stock_take::finalize_stock_take,
+            commands::stock_take::get_stock_take,
+            commands::stock_take::record_scanned_count,
+            commands::stock_take::record_stock_count,
+            commands::stock_take::start_stock_take,
+            commands::stock_take::stock_take_variance_report,
+            commands::suppliers::create_supplier,
+            commands::suppliers::payables_aging,
+            commands::suppliers::record_supplier_payment,
+            commands::suppliers::supplier_ledger,
+            commands::suppliers::supplier_payables,
+            commands::suppliers::update_supplier,
+            commands::sync::flush_sync_queue,
+            commands::sync::pending_sync_count,
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -0,0 +1,382 @@
+//! Where a branch keeps its stock: the selling floor and any stores behind
+//! it.
+//!
+//! A batch's quantity is its total at its branch. `batch_locations` holds
+//! only what is kept away from the selling floor; the floor has the rest,
+//! and sales take from the floor alone.
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::auth::{self, permission};
+use crate::db::{ChangeOp, Entity, Tx};
+use crate::error::{AppError, AppResult};
+use crate::sync;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Location {
+    pub id: i64,
+    pub branch_id: i64,
+    pub name: String,
+    /// The shop floor sales are made from; one per branch.
+    pub is_selling: bool,
+    pub is_active: bool,
+}
+
+/// How much of a batch is kept at one location.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocationQuantity {
+    pub location_id: i64,
+    pub location_name: String,
+    pub is_selling: bool,
+    pub quantity: i64,
+}
+
+const LOCATION_COLUMNS: &str = "id, branch_id, name, is_selling, is_active";
+
+fn location_from_row(row: &Row) -> rusqlite::Result<Location> {
+    Ok(Location {
+        id: row.get(0)?,
+        branch_id: row.get(1)?,
+        name: row.get(2)?,
+        is_selling: row.get(3)?,
+        is_active: row.get(4)?,
+    })
+}
+
+/// The locations of one branch, or of all, selling floors first.
+pub fn list_locations(conn: &Connection, branch_id: Option<i64>) -> AppResult<Vec<Location>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {LOCATION_COLUMNS} FROM locations
+         WHERE ?1 IS NULL OR branch_id = ?1
+         ORDER BY branch_id, is_selling DESC, name"
+    ))?;
+    let rows = stmt.query_map(params![branch_id], location_from_row)?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+fn get_location(conn: &Connection, location_id: i64) -> AppResult<Location> {
+    conn.query_row(
+        &format!("SELECT {LOCATION_COLUMNS} FROM locations WHERE id = ?1"),
+        params![location_id],
+        location_from_row,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::not_found(format!("Location {location_id}")))
+}
+
+/// Add a storage location, such as a back store, to a branch.
+pub fn create_location(tx: &Tx, branch_id: i64, name: &str, user_id: i64) -> AppResult<Location> {
+    auth::require_permission(tx, user_id, permission::INVENTORY_ADJUST)?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::validation("A location name is required"));
+    }
+    let branch_exists: bool = tx.query_row(
+        "SELECT EXISTS (SELECT 1 FROM branches WHERE id = ?1)",
+        params![branch_id],
+        |row| row.get(0),
+    )?;
+    if !branch_exists {
+        return Err(AppError::not_found(format!("Branch {branch_id}")));
+    }
+    let duplicate: bool = tx.query_row(
+        "SELECT EXISTS (SELECT 1 FROM locations WHERE branch_id = ?1 AND name = ?2)",
+        params![branch_id, name],
+        |row| row.get(0),
+    )?;
+    if duplicate {
+        return Err(AppError::validation(format!(
+            "The branch already has a location called {name}"
+        )));
+    }
+    tx.execute(
+        "INSERT INTO locations (branch_id, name) VALUES (?1, ?2)",
+        params![branch_id, name],
+    )?;
+    let location_id = tx.last_insert_rowid();
+    tx.changed(Entity::Location, ChangeOp::Insert, location_id);
+    sync::enqueue(
+        tx,
+        "create_location",
+        &json!({ "locationId": location_id, "branchId": branch_id, "name": name }),
+    )?;
+    get_location(tx, location_id)
+}
+
+/// Units of a batch kept away from the selling floor; the floor has the rest.
+pub fn off_floor_quantity(conn: &Connection, batch_id: i64) -> AppResult<i64> {
+    Ok(conn.query_row(
+        "SELECT COALESCE(SUM(quantity), 0) FROM batch_locations WHERE batch_id = ?1",
+        params![batch_id],
+        |row| row.get(0),
+    )?)
+}
+
+/// Take units of a batch off its selling floor, as sales do. Returns
+/// `false`, changing nothing, when the floor holds fewer, so stock kept in
+/// a store is only taken once it has been moved out.
+pub fn take_from_floor(tx: &Tx, batch_id: i64, quantity: i64) -> AppResult<bool> {
+    let taken = tx.execute(
+        "UPDATE batches SET quantity = quantity - ?1
+         WHERE id = ?2 AND quantity - COALESCE(
+             (SELECT SUM(quantity) FROM batch_locations WHERE batch_id = ?2), 0) >= ?1",
+        params![quantity, batch_id],
+    )?;
+    Ok(taken > 0)
+}
+
+/// Where a batch is kept, selling floor first. Locations holding none of it
+/// are left out, except the floor.
+pub fn batch_locations(conn: &Connection, batch_id: i64) -> AppResult<Vec<LocationQuantity>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT l.id, l.name, l.is_selling,
+             CASE WHEN l.is_selling THEN b.quantity - COALESCE(
+                      (SELECT SUM(quantity) FROM batch_locations WHERE batch_id = b.id), 0)
+                  ELSE bl.quantity END
+         FROM batches b
+         JOIN locations l ON l.branch_id = COALESCE(b.branch_id, 1)
+         LEFT JOIN batch_locations bl ON bl.batch_id = b.id AND bl.location_id = l.id
+         WHERE b.id = ?1 AND (l.is_selling OR bl.quantity IS NOT NULL)
+         ORDER BY l.is_selling DESC, l.name",
+    )?;
+    let rows = stmt.query_map(params![batch_id], |row| {
+        Ok(LocationQuantity {
+            location_id: row.get(0)?,
+            location_name: row.get(1)?,
+            is_selling: row.get(2)?,
+            quantity: row.get(3)?,
+        })
+    })?;
+    let locations: Vec<_> = rows.collect::<Result<_, _>>()?;
+    if locations.is_empty() {
+        return Err(AppError::not_found(format!("Batch {batch_id}")));
+    }
+    Ok(locations)
+}
+
+/// Move units of a batch between two locations of its branch.
+///
+/// The batch's total is unchanged, so nothing is written to the stock
+/// ledger; the move is logged in `location_transfers` instead. Returns
+/// where the batch is kept afterwards.
+pub fn transfer_between_locations(
+    tx: &Tx,
+    batch_id: i64,
+    from_location_id: i64,
+    to_location_id: i64,
+    quantity: i64,
+    user_id: i64,
+) -> AppResult<Vec<LocationQuantity>> {
+    auth::require_permission(tx, user_id, permission::INVENTORY_ADJUST)?;
+    if quantity <= 0 {
+        return Err(AppError::validation(
+            "Transfer quantity must be greater than 0",
+        ));
+    }
+    if from_location_id == to_location_id {
+        return Err(AppError::validation(
+            "Stock cannot be moved to the location it is already in",
+        ));
+    }
+    let batch_branch: i64 = tx
+        .query_row(
+            "SELECT COALESCE(branch_id, 1) FROM batches WHERE id = ?1",
+            params![batch_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Batch {batch_id}")))?;
+    let from = get_location(tx, from_location_id)?;
+    let to = get_location(tx, to_location_id)?;
+    for location in [&from, &to] {
+        if location.branch_id != batch_branch {
+            return Err(AppError::validation(format!(
+                "{} is at another branch than the batch",
+                location.name
+            )));
+        }
+    }
+    if !to.is_active {
+        return Err(AppError::validation(format!("{} is inactive", to.name)));
+    }
+
+    let available = batch_locations(tx, batch_id)?
+        .into_iter()
+        .find(|l| l.location_id == from.id)
+        .map_or(0, |l| l.quantity);
+    if available < quantity {
+        return Err(AppError::validation(format!(
+            "{} holds only {available} units of batch {batch_id}",
+            from.name
+        )));
+    }
+
+    if !from.is_selling {
+        tx.execute(
+            "UPDATE batch_locations SET quantity = quantity - ?1
+             WHERE batch_id = ?2 AND location_id = ?3",
+            params![quantity, batch_id, from.id],
+        )?;
+        tx.execute(
+            "DELETE FROM batch_locations WHERE batch_id = ?1 AND location_id = ?2 AND quantity = 0",
+            params![batch_id, from.id],
+        )?;
+    }
+    if !to.is_selling {
+        tx.execute(
+            "INSERT INTO batch_locations (batch_id, location_id, quantity) VALUES (?1, ?2, ?3)
+             ON CONFLICT (batch_id, location_id) DO UPDATE SET quantity = quantity + excluded.quantity",
+            params![batch_id, to.id, quantity],
+        )?;
+    }
+    tx.execute(
+        "INSERT INTO location_transfers (batch_id, from_location_id, to_location_id, quantity,
+             user_id)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![batch_id, from.id, to.id, quantity, user_id],
+    )?;
+    tx.changed(Entity::Batch, ChangeOp::Update, batch_id);
+    sync::enqueue(
+        tx,
+        "transfer_between_locations",
+        &json!({
+            "batchId": batch_id,
+            "fromLocationId": from.id,
+            "toLocationId": to.id,
+            "quantity": quantity,
+        }),
+    )?;
+    batch_locations(tx, batch_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{insert_batch, insert_medicine, migrated_db, paid_sale};
+    use crate::fefo::pick_batches_fefo;
+    use crate::inventory::{adjust_stock, transfer_stock, AdjustmentReason};
+    use crate::purchase_returns::{
+        create_purchase_return, PurchaseReturnLine, PurchaseReturnReason,
+    };
+    use crate::reports::stock_by_location;
+    use crate::sales::{create_sale, PaymentMode};
+
+    const ADMIN: i64 = 1;
+
+    #[test]
+    fn stock_in_the_back_store_cannot_be_sold_until_it_is_moved_out() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 1_000, 10);
+        let floor = list_locations(&conn, Some(1)).unwrap()[0].id;
+        let store = db
+            .with_tx(|tx| create_location(tx, 1, "Back store", ADMIN))
+            .unwrap()
+            .id;
+
+        let split = db
+            .with_tx(|tx| transfer_between_locations(tx, batch, floor, store, 8, ADMIN))
+            .unwrap();
+        let quantities: Vec<_> = split.iter().map(|l| (l.is_selling, l.quantity)).collect();
+        assert_eq!(quantities, [(true, 2), (false, 8)]);
+        assert_eq!(
+            pick_batches_fefo(&conn, para, 2, None, None).unwrap()[0].quantity,
+            2
+        );
+
+        let sale = paid_sale(&conn, None, PaymentMode::Cash, batch, 3);
+        let err = db.with_tx(|tx| create_sale(tx, &sale)).unwrap_err();
+        assert!(err.to_string().contains("Insufficient stock"), "{err}");
+        let err = db
+            .with_tx(|tx| transfer_between_locations(tx, batch, store, floor, 9, ADMIN))
+            .unwrap_err();
+        assert!(err.to_string().contains("holds only 8"), "{err}");
+
+        db.with_tx(|tx| transfer_between_locations(tx, batch, store, floor, 1, ADMIN))
+            .unwrap();
+        db.with_tx(|tx| create_sale(tx, &sale)).unwrap();
+
+        let report = stock_by_location(&conn, Some(1)).unwrap();
+        let by_name: Vec<_> = report
+            .iter()
+            .map(|l| (l.location_name.as_str(), l.value.quantity))
+            .collect();
+        assert_eq!(by_name, [("Shop floor", 0), ("Back store", 7)]);
+    }
+
+    #[test]
+    fn stock_in_a_store_is_not_adjusted_transferred_or_returned_from_the_floor() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        conn.execute("INSERT INTO suppliers (name) VALUES ('Medline')", [])
+            .unwrap();
+        let supplier = conn.last_insert_rowid();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 1_000, 10);
+        let other = insert_batch(&conn, para, "+60 days", 1_000, 0);
+        let floor = list_locations(&conn, Some(1)).unwrap()[0].id;
+        let store = db
+            .with_tx(|tx| create_location(tx, 1, "Back store", ADMIN))
+            .unwrap()
+            .id;
+        db.with_tx(|tx| transfer_between_locations(tx, batch, floor, store, 8, ADMIN))
+            .unwrap();
+        let floor_quantity = || batch_locations(&conn, batch).unwrap()[0].quantity;
+
+        let err = db
+            .with_tx(|tx| transfer_stock(tx, batch, other, 3, "Wrong batch", false, ADMIN))
+            .unwrap_err();
+        assert!(err.to_string().contains("selling floor"), "{err}");
+        let line = PurchaseReturnLine {
+            batch_id: batch,
+            quantity: 3,
+        };
+        let err = db
+            .with_tx(|tx| {
+                create_purchase_return(
+                    tx,
+                    supplier,
+                    PurchaseReturnReason::Damaged,
+                    None,
+                    std::slice::from_ref(&line),
+                    ADMIN,
+                )
+            })
+            .unwrap_err();
+        assert!(err.to_string().contains("selling floor"), "{err}");
+        let err = db
+            .with_tx(|tx| {
+                adjust_stock(tx, batch, 7, AdjustmentReason::CountError, "Recount", ADMIN)
+            })
+            .unwrap_err();
+        assert!(err.to_string().contains("kept away"), "{err}");
+        assert_eq!(floor_quantity(), 2);
+
+        // What the floor holds can still be counted, moved and returned.
+        db.with_tx(|tx| transfer_stock(tx, batch, other, 1, "Wrong batch", false, ADMIN))
+            .unwrap();
+        db.with_tx(|tx| {
+            create_purchase_return(
+                tx,
+                supplier,
+                PurchaseReturnReason::Damaged,
+                None,
+                &[PurchaseReturnLine {
+                    batch_id: batch,
+                    quantity: 1,
+                }],
+                ADMIN,
+            )
+        })
+        .unwrap();
+        db.with_tx(|tx| adjust_stock(tx, batch, 8, AdjustmentReason::CountError, "Recount", ADMIN))
+            .unwrap();
+        assert_eq!(floor_quantity(), 0);
+        assert_eq!(off_floor_quantity(&conn, batch).unwrap(), 8);
+    }
+}
@@ -0,0 +1,449 @@
+//! Customer loyalty points: earned on a sale's grand total, redeemed
+//! against a sale as part payment.
+//!
+//! Every change to a balance is written to `loyalty_transactions`, so
+//! `customers.loyalty_points` always equals the sum of its rows there.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::auth::{self, permission};
+use crate::db::{ChangeOp, Entity, Tx};
+use crate::error::{AppError, AppResult};
+use crate::sales;
+use crate::sync;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RedeemResult {
+    pub customer_id: i64,
+    pub sale_id: i64,
+    pub points_redeemed: i64,
+    /// What the points paid of the sale.
+    pub discount_paise: i64,
+    /// Points earned on the sale that the redemption took back.
+    pub points_reversed: i64,
+    pub points_balance: i64,
+}
+
+/// How points are earned and what they are worth; zero turns either off.
+struct LoyaltyRates {
+    spend_per_point_paise: i64,
+    point_value_paise: i64,
+}
+
+fn rates(conn: &Connection) -> AppResult<LoyaltyRates> {
+    Ok(conn.query_row(
+        "SELECT loyalty_spend_per_point_paise, loyalty_point_value_paise
+         FROM pharmacy_settings WHERE id = 1",
+        [],
+        |row| {
+            Ok(LoyaltyRates {
+                spend_per_point_paise: row.get(0)?,
+                point_value_paise: row.get(1)?,
+            })
+        },
+    )?)
+}
+
+/// Points earned on `paid_paise` of spend.
+fn points_for(rates: &LoyaltyRates, paid_paise: i64) -> i64 {
+    if rates.spend_per_point_paise == 0 {
+        0
+    } else {
+        paid_paise.max(0) / rates.spend_per_point_paise
+    }
+}
+
+/// Move `points` on a customer's balance, logging the transaction.
+///
+/// Fails rather than let the balance go negative.
+fn post(
+    tx: &Tx,
+    customer_id: i64,
+    sale_id: i64,
+    kind: &str,
+    points: i64,
+    value_paise: i64,
+) -> AppResult<()> {
+    if points == 0 {
+        return Ok(());
+    }
+    let updated = tx.execute(
+        "UPDATE customers SET loyalty_points = loyalty_points + ?1
+         WHERE id = ?2 AND loyalty_points + ?1 >= 0",
+        params![points, customer_id],
+    )?;
+    if updated == 0 {
+        return Err(AppError::validation(
+            "The customer does not have enough loyalty points",
+        ));
+    }
+    tx.execute(
+        "INSERT INTO loyalty_transactions (customer_id, sale_id, kind, points, value_paise)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![customer_id, sale_id, kind, points, value_paise],
+    )?;
+    Ok(())
+}
+
+/// Credit the points a new sale earns its customer.
+pub fn accrue(tx: &Tx, sale_id: i64, customer_id: i64, grand_total_paise: i64) -> AppResult<()> {
+    let points = points_for(&rates(tx)?, grand_total_paise);
+    post(tx, customer_id, sale_id, "accrual", points, 0)
+}
+
+/// Net points a sale has earned, and the paise paid for it in points.
+fn sale_ledger(conn: &Connection, sale_id: i64) -> AppResult<(i64, i64)> {
+    Ok(conn.query_row(
+        "SELECT COALESCE(SUM(CASE WHEN kind <> 'redemption' THEN points END), 0),
+             COALESCE(SUM(CASE WHEN kind = 'redemption' THEN value_paise END), 0)
+         FROM loyalty_transactions WHERE sale_id = ?1",
+        params![sale_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?)
+}
+
+/// Bring the points a sale has earned back in line with its total after an
+/// amendment or a return changes it.
+///
+/// Points are recomputed on the grand total less refunds and redeemed
+/// spend, and the difference is posted as a reversal, which is returned.
+pub fn rebalance(tx: &Tx, sale_id: i64) -> AppResult<i64> {
+    let (customer_id, net_paise): (Option<i64>, i64) = tx.query_row(
+        "SELECT customer_id, grand_total_paise - COALESCE((
+             SELECT SUM(refund_total_paise) FROM sale_returns WHERE sale_id = sales.id), 0)
+         FROM sales WHERE id = ?1",
+        params![sale_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    let Some(customer_id) = customer_id else {
+        return Ok(0);
+    };
+    let (earned, redeemed_paise) = sale_ledger(tx, sale_id)?;
+    let change = points_for(&rates(tx)?, net_paise - redeemed_paise) - earned;
+    post(tx, customer_id, sale_id, "reversal", change, 0)?;
+    Ok(change)
+}
+
+/// Take `paise` off a sale's recorded payments, largest first, now that
+/// points cover it.
+fn reduce_payments(tx: &Tx, sale_id: i64, mut paise: i64) -> AppResult<()> {
+    let mut payments = sales::sale_payment_breakdown(tx, sale_id)?;
+    payments.sort_by_key(|p| std::cmp::Reverse(p.amount_paise));
+    for payment in payments {
+        if paise == 0 {
+            break;
+        }
+        let take = payment.amount_paise.min(paise);
+        paise -= take;
+        if take == payment.amount_paise {
+            tx.execute(
+                "DELETE FROM sale_payments WHERE sale_id = ?1 AND payment_mode = ?2",
+                params![sale_id, payment.mode.as_str()],
+            )?;
+        } else {
+            tx.execute(
+                "UPDATE sale_payments SET amount_paise = amount_paise - ?1
+                 WHERE sale_id = ?2 AND payment_mode = ?3",
+                params![take, sale_id, payment.mode.as_str()],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Pay part of a sale with the customer's points.
+///
+/// The points' value comes off the sale's payments, largest first, and the
+/// points the sale earned are recomputed on what is left, so redeemed
+/// spend never earns points of its own.
+pub fn redeem_points(
+    tx: &Tx,
+    customer_id: i64,
+    points: i64,
+    sale_id: i64,
+    user_id: i64,
+) -> AppResult<RedeemResult> {
+    auth::require_permission(tx, user_id, permission::LOYALTY_REDEEM)?;
+    if points <= 0 {
+        return Err(AppError::validation(
+            "Points to redeem must be greater than 0",
+        ));
+    }
+    let (sale_customer, status): (Option<i64>, String) = tx
+        .query_row(
+            "SELECT customer_id, status FROM sales WHERE id = ?1",
+            params![sale_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Sale {sale_id}")))?;
+    if sale_customer != Some(customer_id) {
+        return Err(AppError::validation(
+            "Points can only be redeemed on the customer's own sale",
+        ));
+    }
+    if status == "refunded" {
+        return Err(AppError::validation(
+            "Points cannot be redeemed on a refunded sale",
+        ));
+    }
+
+    let rates = rates(tx)?;
+    if rates.point_value_paise == 0 {
+        return Err(AppError::validation(
+            "Loyalty point redemption is turned off",
+        ));
+    }
+    let discount_paise = points
+        .checked_mul(rates.point_value_paise)
+        .ok_or_else(|| AppError::validation("Too many points to redeem"))?;
+    let paid_paise: i64 = sales::sale_payment_breakdown(tx, sale_id)?
+        .iter()
+        .map(|p| p.amount_paise)
+        .sum();
+    if discount_paise > paid_paise {
+        return Err(AppError::validation(format!(
+            "{points} points are worth {discount_paise} paise, more than the {paid_paise} paise left to pay"
+        )));
+    }
+
+    post(
+        tx,
+        customer_id,
+        sale_id,
+        "redemption",
+        -points,
+        discount_paise,
+    )?;
+    reduce_payments(tx, sale_id, discount_paise)?;
+
+    let points_reversed = -rebalance(tx, sale_id)?;
+
+    let payments = sales::sale_payment_breakdown(tx, sale_id)?;
+    tx.execute(
+        "UPDATE sales SET payment_mode = ?1 WHERE id = ?2",
+        params![sales::dominant_mode(&payments).as_str(), sale_id],
+    )?;
+
+    let points_balance = tx.query_row(
+        "SELECT loyalty_points FROM customers WHERE id = ?1",
+        params![customer_id],
+        |row| row.get(0),
+    )?;
+    tx.changed(Entity::Sale, ChangeOp::Update, sale_id);
+    sync::enqueue(
+        tx,
+        "redeem_points",
+        &json!({ "customerId": customer_id, "points": points, "saleId": sale_id }),
+    )?;
+    Ok(RedeemResult {
+        customer_id,
+        sale_id,
+        points_redeemed: points,
+        discount_paise,
+        points_reversed,
+        points_balance,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{
+        insert_batch, insert_cashier, insert_medicine, migrated_db, paid_sale,
+    };
+    use crate::db::Db;
+    use crate::returns::{create_sale_return, ReturnItem};
+    use crate::sales::{amend_sale, create_sale, Payment, PaymentMode, SaleLineInput};
+
+    const ADMIN: i64 = 1;
+
+    fn customer(db: &Db, name: &str) -> i64 {
+        let conn = db.connect().unwrap();
+        conn.execute("INSERT INTO customers (name) VALUES (?1)", [name])
+            .unwrap();
+        conn.last_insert_rowid()
+    }
+
+    /// A ₹300 sale to `customer_id`, 3 points at the default rate.
+    fn sell(db: &Db, customer_id: Option<i64>, batch_id: i64) -> i64 {
+        let sale = paid_sale(
+            &db.connect().unwrap(),
+            customer_id,
+            PaymentMode::Cash,
+            batch_id,
+            3,
+        );
+        db.with_tx(|tx| create_sale(tx, &sale)).unwrap().sale_id
+    }
+
+    fn balance(db: &Db, customer_id: i64) -> i64 {
+        db.connect()
+            .unwrap()
+            .query_row(
+                "SELECT loyalty_points FROM customers WHERE id = ?1",
+                [customer_id],
+                |row| row.get(0),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn sales_to_customers_accrue_points_on_the_grand_total() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 10_000, 10);
+        let asha = customer(&db, "Asha");
+
+        sell(&db, Some(asha), batch);
+        sell(&db, None, batch);
+
+        assert_eq!(balance(&db, asha), 3);
+        let logged: i64 = conn
+            .query_row("SELECT SUM(points) FROM loyalty_transactions", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(logged, 3);
+    }
+
+    #[test]
+    fn redeemed_spend_pays_the_sale_and_earns_nothing() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 10_000, 10);
+        let asha = customer(&db, "Asha");
+        sell(&db, Some(asha), batch);
+        let sale_id = sell(&db, Some(asha), batch);
+        assert_eq!(balance(&db, asha), 6);
+
+        let result = db
+            .with_tx(|tx| redeem_points(tx, asha, 5, sale_id, ADMIN))
+            .unwrap();
+
+        assert_eq!(result.discount_paise, 500);
+        // ₹295 is left to pay, which earns 2 of the sale's 3 points.
+        assert_eq!(result.points_reversed, 1);
+        assert_eq!(result.points_balance, 0);
+        assert_eq!(balance(&db, asha), 0);
+        assert_eq!(
+            sales::sale_payment_breakdown(&conn, sale_id).unwrap(),
+            [Payment {
+                mode: PaymentMode::Cash,
+                amount_paise: 29_500
+            }]
+        );
+    }
+
+    #[test]
+    fn points_never_go_negative() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 10_000, 10);
+        let asha = customer(&db, "Asha");
+        let sale_id = sell(&db, Some(asha), batch);
+
+        let err = db
+            .with_tx(|tx| redeem_points(tx, asha, 4, sale_id, ADMIN))
+            .unwrap_err();
+        assert!(err.to_string().contains("enough loyalty points"), "{err}");
+        // Redeeming all 3 would leave the sale's own reversal unpaid.
+        let err = db
+            .with_tx(|tx| redeem_points(tx, asha, 3, sale_id, ADMIN))
+            .unwrap_err();
+        assert!(err.to_string().contains("enough loyalty points"), "{err}");
+        assert_eq!(balance(&db, asha), 3);
+
+        let ravi = customer(&db, "Ravi");
+        let err = db
+            .with_tx(|tx| redeem_points(tx, ravi, 1, sale_id, ADMIN))
+            .unwrap_err();
+        assert!(err.to_string().contains("own sale"), "{err}");
+    }
+
+    fn reversals(db: &Db, sale_id: i64) -> i64 {
+        db.connect()
+            .unwrap()
+            .query_row(
+                "SELECT COALESCE(SUM(points), 0) FROM loyalty_transactions
+                 WHERE sale_id = ?1 AND kind = 'reversal'",
+                [sale_id],
+                |row| row.get(0),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn amending_a_sale_down_reverses_the_points_it_no_longer_earns() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 10_000, 10);
+        let asha = customer(&db, "Asha");
+        let sale_id = sell(&db, Some(asha), batch);
+
+        let line = SaleLineInput {
+            medicine_id: para,
+            quantity: 1,
+            unit: None,
+            discount_paise: 0,
+        };
+        db.with_tx(|tx| amend_sale(tx, sale_id, &[line], ADMIN))
+            .unwrap();
+
+        assert_eq!(reversals(&db, sale_id), -2);
+        assert_eq!(balance(&db, asha), 1);
+    }
+
+    #[test]
+    fn a_return_reverses_the_points_on_the_refunded_spend() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 10_000, 10);
+        let asha = customer(&db, "Asha");
+        let sale_id = sell(&db, Some(asha), batch);
+        let sale_item_id: i64 = conn
+            .query_row(
+                "SELECT id FROM sale_items WHERE sale_id = ?1",
+                [sale_id],
+                |row| row.get(0),
+            )
+            .unwrap();
+
+        let item = ReturnItem {
+            sale_item_id,
+            quantity: 2,
+            non_saleable: false,
+        };
+        db.with_tx(|tx| create_sale_return(tx, sale_id, &[item], PaymentMode::Cash, ADMIN))
+            .unwrap();
+
+        assert_eq!(reversals(&db, sale_id), -2);
+        assert_eq!(balance(&db, asha), 1);
+    }
+
+    #[test]
+    fn cashier_cannot_redeem_points() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 10_000, 10);
+        let asha = customer(&db, "Asha");
+        sell(&db, Some(asha), batch);
+        let sale_id = sell(&db, Some(asha), batch);
+        let cashier = insert_cashier(&conn);
+
+        let err = db
+            .with_tx(|tx| redeem_points(tx, asha, 5, sale_id, cashier))
+            .unwrap_err();
+        assert!(matches!(err, AppError::Unauthorized(_)), "{err}");
+        assert_eq!(balance(&db, asha), 6);
+    }
+}
@@ -0,0 +1,328 @@
+//! Housekeeping on the live database file.
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use rusqlite::{params, Connection, ErrorCode};
+use serde::Serialize;
+
+use crate::auth::{self, permission};
+use crate::error::{AppError, AppResult};
+use crate::migrations;
+
+const LOG_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceReport {
+    /// Database plus WAL file, in bytes.
+    pub size_before_bytes: u64,
+    pub size_after_bytes: u64,
+    pub reclaimed_bytes: u64,
+}
+
+/// Size of the database file and its WAL, if any.
+fn on_disk_size(live: &Path) -> AppResult<u64> {
+    let mut wal = live.as_os_str().to_owned();
+    wal.push("-wal");
+    let wal_size = match fs::metadata(&wal) {
+        Ok(meta) => meta.len(),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => 0,
+        Err(err) => return Err(err.into()),
+    };
+    Ok(fs::metadata(live)?.len() + wal_size)
+}
+
+fn in_use() -> AppError {
+    AppError::validation("The database is in use; finish the current transaction and try again")
+}
+
+/// Turn a lock error from SQLite into [`in_use`].
+fn unless_busy(err: rusqlite::Error) -> AppError {
+    match err.sqlite_error_code() {
+        Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked) => in_use(),
+        _ => err.into(),
+    }
+}
+
+/// Fold the WAL back into the database, then rebuild and re-analyze it.
+///
+/// The connection stops waiting on locks for the duration, so a
+/// transaction in progress on another connection fails the call straight
+/// away instead of stalling it.
+pub fn optimize_database(
+    conn: &Connection,
+    live: &Path,
+    user_id: i64,
+) -> AppResult<MaintenanceReport> {
+    auth::require_permission(conn, user_id, permission::DATABASE_MAINTAIN)?;
+    optimize(conn, live)
+}
+
+fn optimize(conn: &Connection, live: &Path) -> AppResult<MaintenanceReport> {
+    let size_before_bytes = on_disk_size(live)?;
+    conn.busy_timeout(Duration::ZERO)?;
+
+    let busy: bool = conn
+        .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| row.get(0))
+        .map_err(unless_busy)?;
+    if busy {
+        return Err(in_use());
+    }
+    conn.execute_batch("VACUUM; ANALYZE;")
+        .map_err(unless_busy)?;
+    // VACUUM itself goes through the WAL; truncate it again.
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))
+        .map_err(unless_busy)?;
+
+    let size_after_bytes = on_disk_size(live)?;
+    Ok(MaintenanceReport {
+        size_before_bytes,
+        size_after_bytes,
+        reclaimed_bytes: size_before_bytes.saturating_sub(size_after_bytes),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceRun {
+    pub integrity_ok: bool,
+    /// What `integrity_check` reported, one problem per entry.
+    pub problems: Vec<String>,
+    /// `None` when the integrity check failed: rebuilding a damaged file
+    /// can lose what is left of it, so it is left for a restore.
+    pub report: Option<MaintenanceReport>,
+}
+
+fn integrity_problems(conn: &Connection) -> AppResult<Vec<String>> {
+    let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+    let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+    let rows = rows.collect::<Result<Vec<_>, _>>()?;
+    Ok(if rows == ["ok"] { Vec::new() } else { rows })
+}
+
+/// Run `integrity_check` and, if the file is intact, [`optimize_database`].
+///
+/// Every run is logged in `maintenance_runs`, including one whose VACUUM
+/// could not go ahead; that error is then returned.
+pub fn db_maintenance(conn: &Connection, live: &Path, user_id: i64) -> AppResult<MaintenanceRun> {
+    auth::require_permission(conn, user_id, permission::DATABASE_MAINTAIN)?;
+    run_maintenance(conn, live)
+}
+
+fn run_maintenance(conn: &Connection, live: &Path) -> AppResult<MaintenanceRun> {
+    let size_before_bytes = on_disk_size(live)?;
+    let problems = integrity_problems(conn)?;
+    let outcome = problems
+        .is_empty()
+        .then(|| optimize(conn, live))
+        .transpose();
+    // optimize stops waiting on locks; wait again to log the run.
+    conn.busy_timeout(LOG_BUSY_TIMEOUT)?;
+    let size_after_bytes = match &outcome {
+        Ok(Some(report)) => report.size_after_bytes,
+        _ => size_before_bytes,
+    };
+    conn.execute(
+        "INSERT INTO maintenance_runs
+             (integrity_ok, problems, size_before_bytes, size_after_bytes, error)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            problems.is_empty(),
+            problems.join("\n"),
+            size_before_bytes as i64,
+            size_after_bytes as i64,
+            outcome.as_ref().err().map(ToString::to_string),
+        ],
+    )?;
+    Ok(MaintenanceRun {
+        integrity_ok: problems.is_empty(),
+        problems,
+        report: outcome?,
+    })
+}
+
+/// Run [`db_maintenance`] if the configured number of days has passed
+/// since the last run. Called after the nightly backup, when the shop is
+/// closed and a fresh snapshot exists.
+pub fn maintenance_if_due(conn: &Connection, live: &Path) -> AppResult<Option<MaintenanceRun>> {
+    let due: bool = conn.query_row(
+        "SELECT s.maintenance_interval_days > 0
+            AND NOT EXISTS (
+                SELECT 1 FROM maintenance_runs
+                WHERE ran_at > datetime('now', '-' || s.maintenance_interval_days || ' days'))
+         FROM pharmacy_settings s WHERE s.id = 1",
+        [],
+        |row| row.get(0),
+    )?;
+    if !due {
+        return Ok(None);
+    }
+    run_maintenance(conn, live).map(Some)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaHealth {
+    pub healthy: bool,
+    /// `None` if the database has never been migrated.
+    pub applied_version: Option<i64>,
+    pub expected_version: i64,
+    pub missing_tables: Vec<String>,
+    pub foreign_keys: bool,
+    /// One line per problem found, empty when healthy.
+    pub problems: Vec<String>,
+}
+
+/// Check the database against the schema this build expects.
+pub fn schema_health(conn: &Connection) -> AppResult<SchemaHealth> {
+    let applied_version = migrations::applied_version(conn)?;
+    let expected_version = migrations::latest_version();
+    let mut problems = Vec::new();
+    match applied_version {
+        None => problems.push("The database has no migration history".to_owned()),
+        Some(v) if v < expected_version => problems.push(format!(
+            "The schema is at version {v} but this build expects {expected_version}"
+        )),
+        Some(v) if v > expected_version => problems.push(format!(
+            "The schema is at version {v}, newer than this build supports ({expected_version})"
+        )),
+        Some(_) => {}
+    }
+
+    let mut stmt =
+        conn.prepare("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1")?;
+    let mut missing_tables = Vec::new();
+    for table in migrations::expected_tables() {
+        if !stmt.exists([&table])? {
+            problems.push(format!("Table {table} is missing"));
+            missing_tables.push(table);
+        }
+    }
+
+    let foreign_keys: bool = conn.query_row("PRAGMA foreign_keys", [], |row| row.get(0))?;
+    if !foreign_keys {
+        problems.push("Foreign key enforcement is off".to_owned());
+    }
+
+    Ok(SchemaHealth {
+        healthy: problems.is_empty(),
+        applied_version,
+        expected_version,
+        missing_tables,
+        foreign_keys,
+        problems,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{insert_cashier, insert_medicine, mark_migrated, migrated_db};
+
+    const ADMIN: i64 = 1;
+
+    #[test]
+    fn deleted_rows_are_reclaimed() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        for i in 0..500 {
+            insert_medicine(&conn, &format!("Medicine {i:0>200}"), 12.0);
+        }
+        conn.execute("DELETE FROM medicines", []).unwrap();
+
+        let report = optimize_database(&conn, db.path(), ADMIN).unwrap();
+
+        assert!(report.reclaimed_bytes > 0, "{report:?}");
+        assert_eq!(
+            report.size_after_bytes,
+            report.size_before_bytes - report.reclaimed_bytes
+        );
+    }
+
+    #[test]
+    fn open_transaction_is_reported_instead_of_waited_on() {
+        let (_dir, db) = migrated_db();
+        let writer = db.connect().unwrap();
+        writer.execute_batch("BEGIN IMMEDIATE").unwrap();
+
+        let err = optimize_database(&db.connect().unwrap(), db.path(), ADMIN).unwrap_err();
+
+        assert!(err.to_string().contains("in use"), "{err}");
+    }
+
+    #[test]
+    fn scheduled_maintenance_runs_once_per_interval() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+
+        let run = maintenance_if_due(&conn, db.path()).unwrap().unwrap();
+        assert!(run.integrity_ok && run.problems.is_empty());
+        assert!(run.report.is_some());
+        assert!(maintenance_if_due(&conn, db.path()).unwrap().is_none());
+
+        conn.execute(
+            "UPDATE maintenance_runs SET ran_at = datetime('now', '-8 days')",
+            [],
+        )
+        .unwrap();
+        assert!(maintenance_if_due(&conn, db.path()).unwrap().is_some());
+        conn.execute(
+            "UPDATE pharmacy_settings SET maintenance_interval_days = 0 WHERE id = 1",
+            [],
+        )
+        .unwrap();
+        conn.execute("DELETE FROM maintenance_runs", []).unwrap();
+        assert!(maintenance_if_due(&conn, db.path()).unwrap().is_none());
+    }
+
+    #[test]
+    fn cashier_cannot_run_maintenance() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let cashier = insert_cashier(&conn);
+
+        let err = db_maintenance(&conn, db.path(), cashier).unwrap_err();
+        assert!(matches!(err, AppError::Unauthorized(_)), "{err}");
+        let err = optimize_database(&conn, db.path(), cashier).unwrap_err();
+        assert!(matches!(err, AppError::Unauthorized(_)), "{err}");
+        let runs: i64 = conn
+            .query_row("SELECT COUNT(*) FROM maintenance_runs", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(runs, 0);
+    }
+
+    #[test]
+    fn fully_migrated_database_is_healthy() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        mark_migrated(&conn, migrations::latest_version());
+
+        let health = schema_health(&conn).unwrap();
+
+        assert!(health.healthy, "{health:?}");
+        assert_eq!(health.applied_version, Some(health.expected_version));
+        assert!(health.foreign_keys);
+        // Created without `IF NOT EXISTS` by a migration that has shipped.
+        assert!(migrations::expected_tables().contains(&"remote_backup_status".to_owned()));
+    }
+
+    #[test]
+    fn missing_pieces_are_listed_as_problems() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        conn.execute_batch("DROP TABLE stock_disposal_items; PRAGMA foreign_keys = OFF;")
+            .unwrap();
+
+        let health = schema_health(&conn).unwrap();
+
+        assert!(!health.healthy);
+        assert_eq!(health.applied_version, None);
+        assert_eq!(health.missing_tables, ["stock_disposal_items"]);
+        assert!(!health.foreign_keys);
+        assert_eq!(health.problems.len(), 3, "{:?}", health.problems);
+    }
+}
@@ -0,0 +1,674 @@
+//! Medicine lookups for the billing screen: typeahead and barcode scans.
+//! Deactivated medicines are left out of both.
+//!
+//! Adding and editing medicines, and taking them off sale, also go through
+//! here.
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::auth::{self, permission};
+use crate::barcode::{self, Gs1Data};
+use crate::db::{ChangeOp, Entity, Tx};
+use crate::error::{AppError, AppResult};
+use crate::fefo;
+use crate::inventory::{BatchStatus, EXPIRED, UNEXPIRED};
+use crate::sync;
+
+/// Upper bound on typeahead results, whatever the caller asks for.
+const MAX_SEARCH_RESULTS: i64 = 50;
+
+/// A medicine matching a search, best match first.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MedicineHit {
+    pub medicine_id: i64,
+    pub name: String,
+    /// Sellable units: available, unexpired batches.
+    pub total_stock: i64,
+}
+
+/// Turn free text into an FTS5 query where every word is a prefix term.
+///
+/// Words are quoted so that FTS syntax (`AND`, `-`, `*`, `"`) typed by a
+/// cashier is searched for rather than interpreted. Returns `None` when
+/// nothing searchable is left.
+fn fts_query(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{word}\"*"))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// Active medicines whose name, generic name or brand name has a word
+/// starting with each word of `query`, ranked by relevance.
+pub fn search_medicines(conn: &Connection, query: &str, limit: i64) -> AppResult<Vec<MedicineHit>> {
+    if limit < 1 {
+        return Err(AppError::validation("Search limit must be at least 1"));
+    }
+    let Some(fts) = fts_query(query) else {
+        return Ok(Vec::new());
+    };
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT m.id, m.name,
+             (SELECT COALESCE(SUM(b.quantity), 0) FROM batches b
+              WHERE b.medicine_id = m.id AND b.quantity > 0
+                AND {UNEXPIRED} AND b.status = 'available')
+         FROM medicines_fts
+         JOIN medicines m ON m.id = medicines_fts.rowid
+         WHERE medicines_fts MATCH ?1 AND m.is_active = 1
+         ORDER BY medicines_fts.rank, m.name
+         LIMIT ?2"
+    ))?;
+    let rows = stmt.query_map(params![fts, limit.min(MAX_SEARCH_RESULTS)], |row| {
+        Ok(MedicineHit {
+            medicine_id: row.get(0)?,
+            name: row.get(1)?,
+            total_stock: row.get(2)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+/// The batch behind a scanned barcode.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanResult {
+    pub medicine_id: i64,
+    pub medicine_name: String,
+    pub batch_id: i64,
+    pub batch_number: String,
+    pub expiry_date: String,
+    pub selling_price_paise: i64,
+    pub quantity: i64,
+    pub status: BatchStatus,
+    /// Returned anyway so the till can warn rather than fail silently.
+    pub expired: bool,
+}
+
+fn scan_select() -> String {
+    format!(
+        "SELECT m.id, m.name, b.id, b.batch_number, b.expiry_date,
+             b.selling_price_paise, b.quantity, b.status, {EXPIRED}
+         FROM batches b
+         JOIN medicines m ON m.id = b.medicine_id"
+    )
+}
+
+fn scan_from_row(row: &Row) -> AppResult<ScanResult> {
+    let status: String = row.get(7)?;
+    Ok(ScanResult {
+        medicine_id: row.get(0)?,
+        medicine_name: row.get(1)?,
+        batch_id: row.get(2)?,
+        batch_number: row.get(3)?,
+        expiry_date: row.get(4)?,
+        selling_price_paise: row.get(5)?,
+        quantity: row.get(6)?,
+        status: BatchStatus::parse(&status)?,
+        expired: row.get(8)?,
+    })
+}
+
+/// Find the batch with `code` as its barcode; unknown codes, and codes of
+/// deactivated medicines, give `None`.
+pub fn lookup_by_barcode(conn: &Connection, code: &str) -> AppResult<Option<ScanResult>> {
+    let code = code.trim();
+    if code.is_empty() {
+        return Ok(None);
+    }
+    let mut stmt = conn.prepare_cached(&format!(
+        "{} WHERE b.barcode = ?1 AND m.is_active = 1",
+        scan_select()
+    ))?;
+    let mut rows = stmt.query(params![code])?;
+    rows.next()?.map(scan_from_row).transpose()
+}
+
+/// How a scanned code was matched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BarcodeSource {
+    /// A barcode of one batch, such as an in-store label.
+    BatchBarcode,
+    /// A product barcode (EAN-13 or GTIN) of the medicine.
+    Gtin,
+    /// A GS1 DataMatrix with the GTIN and, usually, batch and expiry.
+    Gs1,
+}
+
+/// The medicine, and where possible the batch, behind a scanned code.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BarcodeMatch {
+    pub source: BarcodeSource,
+    pub medicine_id: i64,
+    pub medicine_name: String,
+    /// The scanned batch; for a product barcode, the next batch to sell by
+    /// FEFO. `None` when no such batch is in stock.
+    pub batch: Option<ScanResult>,
+    /// What a GS1 code said, so the till can show a batch it cannot find.
+    pub gs1: Option<Gs1Data>,
+}
+
+/// Resolve any code the till scans: a batch barcode first, then a GS1
+/// element string, then a product GTIN. Unknown codes and deactivated
+/// medicines give `None`.
+///
+/// A GS1 code names its batch, which is matched by batch number at the
+/// branch selling, then anywhere. A bare product code does not, so the
+/// batch FEFO would sell at `branch_id` is given.
+pub fn lookup_barcode(
+    conn: &Connection,
+    code: &str,
+    branch_id: Option<i64>,
+) -> AppResult<Option<BarcodeMatch>> {
+    if let Some(scan) = lookup_by_barcode(conn, code)? {
+        return Ok(Some(BarcodeMatch {
+            source: BarcodeSource::BatchBarcode,
+            medicine_id: scan.medicine_id,
+            medicine_name: scan.medicine_name.clone(),
+            batch: Some(scan),
+            gs1: None,
+        }));
+    }
+    let gs1 = barcode::parse_gs1(code);
+    let Some(gtin) = gs1
+        .as_ref()
+        .map(|data| data.gtin.clone())
+        .or_else(|| barcode::normalize_gtin(code))
+    else {
+        return Ok(None);
+    };
+    let Some((medicine_id, medicine_name)) = conn
+        .query_row(
+            "SELECT id, name FROM medicines
+             WHERE substr('00000000000000' || gtin, -14) = ?1 AND gtin IS NOT NULL
+               AND is_active = 1",
+            params![gtin],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?
+    else {
+        return Ok(None);
+    };
+
+    let batch = match gs1.as_ref().and_then(|data| data.batch_number.as_deref()) {
+        Some(batch_number) => {
+            let mut stmt = conn.prepare_cached(&format!(
+                "{}
+                 WHERE b.medicine_id = ?1 AND b.batch_number = ?2
+                 ORDER BY COALESCE(b.branch_id, 1) IS ?3 DESC, b.quantity > 0 DESC, b.id
+                 LIMIT 1",
+                scan_select()
+            ))?;
+            let mut rows = stmt.query(params![medicine_id, batch_number, branch_id])?;
+            rows.next()?.map(scan_from_row).transpose()?
+        }
+        None => match fefo::sellable_batches(conn, medicine_id, branch_id)?.first() {
+            Some(next) => {
+                let mut stmt =
+                    conn.prepare_cached(&format!("{} WHERE b.id = ?1", scan_select()))?;
+                let mut rows = stmt.query(params![next.batch_id])?;
+                rows.next()?.map(scan_from_row).transpose()?
+            }
+            None => None,
+        },
+    };
+    Ok(Some(BarcodeMatch {
+        source: if gs1.is_some() {
+            BarcodeSource::Gs1
+        } else {
+            BarcodeSource::Gtin
+        },
+        medicine_id,
+        medicine_name,
+        batch,
+        gs1,
+    }))
+}
+
+/// A medicine as entered in the catalogue form.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MedicineInput {
+    pub name: String,
+    #[serde(default)]
+    pub generic_name: Option<String>,
+    #[serde(default)]
+    pub brand_name: Option<String>,
+    #[serde(default)]
+    pub manufacturer: Option<String>,
+    pub dosage_form: String,
+    #[serde(default)]
+    pub strength: Option<String>,
+    #[serde(default)]
+    pub category: Option<String>,
+    /// `H`, `H1` or `X`; `None` for over-the-counter medicines.
+    #[serde(default)]
+    pub schedule: Option<String>,
+    #[serde(default = "one")]
+    pub pack_size: i64,
+    #[serde(default)]
+    pub sale_unit: Option<String>,
+    pub hsn_code: String,
+    #[serde(default)]
+    pub gtin: Option<String>,
+    /// Only read when the medicine is added; later changes go through
+    /// [`gst_history::set_medicine_gst`](crate::gst_history::set_medicine_gst)
+    /// so past sales keep their rate.
+    pub gst_slab_id: i64,
+    pub reorder_level: i64,
+}
+
+fn one() -> i64 {
+    1
+}
+
+fn check_medicine(medicine: &MedicineInput) -> AppResult<&str> {
+    let name = medicine.name.trim();
+    if name.is_empty() {
+        return Err(AppError::validation("The medicine name is required"));
+    }
+    if medicine.hsn_code.trim().is_empty() {
+        return Err(AppError::validation("The HSN code is required"));
+    }
+    Ok(name)
+}
+
+/// Add a medicine to the catalogue and return its id.
+pub fn create_medicine(tx: &Tx, medicine: &MedicineInput, user_id: i64) -> AppResult<i64> {
+    auth::require_permission(tx, user_id, permission::MEDICINES_MANAGE)?;
+    let name = check_medicine(medicine)?;
+    tx.execute(
+        "INSERT INTO medicines (name, generic_name, brand_name, manufacturer, dosage_form,
+             strength, category, schedule, pack_size, sale_unit, hsn_code, gst_slab_id,
+             reorder_level, gtin)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+        params![
+            name,
+            medicine.generic_name,
+            medicine.brand_name,
+            medicine.manufacturer,
+            medicine.dosage_form,
+            medicine.strength,
+            medicine.category,
+            medicine.schedule,
+            medicine.pack_size,
+            medicine.sale_unit,
+            medicine.hsn_code.trim(),
+            medicine.gst_slab_id,
+            medicine.reorder_level,
+            medicine.gtin,
+        ],
+    )?;
+    let medicine_id = tx.last_insert_rowid();
+    tx.changed(Entity::Medicine, ChangeOp::Insert, medicine_id);
+    sync::enqueue(
+        tx,
+        "create_medicine",
+        &json!({ "medicineId": medicine_id, "medicine": medicine }),
+    )?;
+    Ok(medicine_id)
+}
+
+/// Replace a medicine's catalogue details. Its GST slab and whether it is
+/// on sale are left alone.
+pub fn update_medicine(
+    tx: &Tx,
+    medicine_id: i64,
+    medicine: &MedicineInput,
+    user_id: i64,
+) -> AppResult<()> {
+    auth::require_permission(tx, user_id, permission::MEDICINES_MANAGE)?;
+    let name = check_medicine(medicine)?;
+    let updated = tx.execute(
+        "UPDATE medicines SET name = ?1, generic_name = ?2, brand_name = ?3, manufacturer = ?4,
+             dosage_form = ?5, strength = ?6, category = ?7, schedule = ?8, pack_size = ?9,
+             sale_unit = ?10, hsn_code = ?11, reorder_level = ?12, gtin = ?13
+         WHERE id = ?14",
+        params![
+            name,
+            medicine.generic_name,
+            medicine.brand_name,
+            medicine.manufacturer,
+            medicine.dosage_form,
+            medicine.strength,
+            medicine.category,
+            medicine.schedule,
+            medicine.pack_size,
+            medicine.sale_unit,
+            medicine.hsn_code.trim(),
+            medicine.reorder_level,
+            medicine.gtin,
+            medicine_id,
+        ],
+    )?;
+    if updated == 0 {
+        return Err(AppError::not_found(format!("Medicine {medicine_id}")));
+    }
+    tx.changed(Entity::Medicine, ChangeOp::Update, medicine_id);
+    sync::enqueue(
+        tx,
+        "update_medicine",
+        &json!({ "medicineId": medicine_id, "medicine": medicine }),
+    )?;
+    Ok(())
+}
+
+/// Take a medicine off sale. Its batches and past sales are kept.
+///
+/// Refuses while unexpired batches still hold stock, naming the units left,
+/// unless `force` is set.
+pub fn deactivate_medicine(tx: &Tx, medicine_id: i64, force: bool, user_id: i64) -> AppResult<()> {
+    auth::require_permission(tx, user_id, permission::MEDICINES_MANAGE)?;
+    let (name, remaining): (String, i64) = tx
+        .query_row(
+            &format!(
+                "SELECT m.name,
+                     (SELECT COALESCE(SUM(b.quantity), 0) FROM batches b
+                      WHERE b.medicine_id = m.id AND b.quantity > 0 AND {UNEXPIRED})
+                 FROM medicines m WHERE m.id = ?1"
+            ),
+            params![medicine_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Medicine {medicine_id}")))?;
+    if remaining > 0 && !force {
+        return Err(AppError::validation(format!(
+            "{name} still has {remaining} units in unexpired stock; \
+             sell or dispose of them first, or force the deactivation"
+        )));
+    }
+    set_active(tx, medicine_id, false)
+}
+
+/// Put a deactivated medicine back on sale.
+pub fn reactivate_medicine(tx: &Tx, medicine_id: i64, user_id: i64) -> AppResult<()> {
+    auth::require_permission(tx, user_id, permission::MEDICINES_MANAGE)?;
+    set_active(tx, medicine_id, true)
+}
+
+fn set_active(tx: &Tx, medicine_id: i64, active: bool) -> AppResult<()> {
+    let updated = tx.execute(
+        "UPDATE medicines SET is_active = ?1 WHERE id = ?2",
+        params![active, medicine_id],
+    )?;
+    if updated == 0 {
+        return Err(AppError::not_found(format!("Medicine {medicine_id}")));
+    }
+    tx.changed(Entity::Medicine, ChangeOp::Update, medicine_id);
+    sync::enqueue(
+        tx,
+        if active {
+            "reactivate_medicine"
+        } else {
+            "deactivate_medicine"
+        },
+        &json!({ "medicineId": medicine_id }),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{
+        insert_batch, insert_cashier, insert_medicine, migrated_db, paid_sale,
+    };
+    use crate::invoice;
+    use crate::sales::{create_sale, PaymentMode};
+
+    const ADMIN: i64 = 1;
+
+    #[test]
+    fn matches_generic_and_brand_name_prefixes_with_stock() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let crocin = insert_medicine(&conn, "Crocin 500", 12.0);
+        let dolo = insert_medicine(&conn, "Dolo 650", 12.0);
+        insert_medicine(&conn, "Cetirizine 10", 12.0);
+        conn.execute(
+            "UPDATE medicines SET generic_name = 'Paracetamol' WHERE id IN (?1, ?2)",
+            params![crocin, dolo],
+        )
+        .unwrap();
+        conn.execute(
+            "UPDATE medicines SET brand_name = 'Micro Labs' WHERE id = ?1",
+            [dolo],
+        )
+        .unwrap();
+        insert_batch(&conn, dolo, "+90 days", 3_000, 7);
+        insert_batch(&conn, dolo, "-1 day", 3_000, 4);
+
+        let hits = search_medicines(&conn, "para", 10).unwrap();
+        let mut ids: Vec<i64> = hits.iter().map(|h| h.medicine_id).collect();
+        ids.sort();
+        assert_eq!(ids, vec![crocin, dolo]);
+
+        let hits = search_medicines(&conn, "micro para", 10).unwrap();
+        assert_eq!(
+            hits,
+            vec![MedicineHit {
+                medicine_id: dolo,
+                name: "Dolo 650".into(),
+                total_stock: 7,
+            }]
+        );
+    }
+
+    #[test]
+    fn inactive_and_renamed_medicines_follow_the_table() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let old = insert_medicine(&conn, "Azithral", 12.0);
+        let renamed = insert_medicine(&conn, "Azee", 12.0);
+        conn.execute("UPDATE medicines SET is_active = 0 WHERE id = ?1", [old])
+            .unwrap();
+        conn.execute(
+            "UPDATE medicines SET name = 'Zithromax' WHERE id = ?1",
+            [renamed],
+        )
+        .unwrap();
+
+        assert!(search_medicines(&conn, "az", 10).unwrap().is_empty());
+        let hits = search_medicines(&conn, "zith", 10).unwrap();
+        assert_eq!(hits[0].medicine_id, renamed);
+    }
+
+    #[test]
+    fn blank_or_punctuation_only_query_returns_nothing() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        insert_medicine(&conn, "Crocin", 12.0);
+
+        assert!(search_medicines(&conn, "", 10).unwrap().is_empty());
+        assert!(search_medicines(&conn, "  \"*- ", 10).unwrap().is_empty());
+        assert_eq!(search_medicines(&conn, "\"croc", 10).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn barcode_scan_finds_the_batch_and_flags_expiry() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Crocin 500", 12.0);
+        let fresh = insert_batch(&conn, para, "+90 days", 3_000, 7);
+        let stale = insert_batch(&conn, para, "-1 day", 2_800, 4);
+        for (batch, code) in [(fresh, "8901234560012"), (stale, "8901234560029")] {
+            conn.execute(
+                "UPDATE batches SET barcode = ?1 WHERE id = ?2",
+                params![code, batch],
+            )
+            .unwrap();
+        }
+
+        let hit = lookup_by_barcode(&conn, " 8901234560012 ")
+            .unwrap()
+            .unwrap();
+        assert_eq!((hit.batch_id, hit.medicine_id), (fresh, para));
+        assert_eq!((hit.selling_price_paise, hit.quantity), (3_000, 7));
+        assert_eq!(hit.status, BatchStatus::Available);
+        assert!(!hit.expired);
+
+        let hit = lookup_by_barcode(&conn, "8901234560029").unwrap().unwrap();
+        assert_eq!(hit.batch_id, stale);
+        assert!(hit.expired);
+
+        assert_eq!(lookup_by_barcode(&conn, "0000000000000").unwrap(), None);
+    }
+
+    #[test]
+    fn product_and_gs1_codes_resolve_to_the_medicine_and_batch() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Crocin 500", 12.0);
+        let later = insert_batch(&conn, para, "+90 days", 3_000, 7);
+        let sooner = insert_batch(&conn, para, "+30 days", 3_000, 2);
+        conn.execute(
+            "UPDATE medicines SET gtin = '8901234567890' WHERE id = ?1",
+            params![para],
+        )
+        .unwrap();
+        conn.execute(
+            "UPDATE batches SET batch_number = 'AB-1234' WHERE id = ?1",
+            params![later],
+        )
+        .unwrap();
+
+        let ean = lookup_barcode(&conn, "8901234567890", None)
+            .unwrap()
+            .unwrap();
+        assert_eq!((ean.source, ean.medicine_id), (BarcodeSource::Gtin, para));
+        assert_eq!(ean.batch.unwrap().batch_id, sooner);
+
+        let code = "]d2010890123456789017270600\u{1d}10AB-1234";
+        let gs1 = lookup_barcode(&conn, code, Some(1)).unwrap().unwrap();
+        assert_eq!(gs1.source, BarcodeSource::Gs1);
+        assert_eq!(gs1.batch.unwrap().batch_id, later);
+        assert_eq!(gs1.gs1.unwrap().expiry_date.as_deref(), Some("2027-06-30"));
+
+        let unknown_batch = lookup_barcode(&conn, "(01)08901234567890(10)ZZ9", None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(unknown_batch.batch, None);
+        assert_eq!(lookup_barcode(&conn, "4006381333931", None).unwrap(), None);
+    }
+
+    #[test]
+    fn deactivated_medicine_leaves_lookups_but_keeps_its_sales() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let crocin = insert_medicine(&conn, "Crocin 500", 12.0);
+        let batch = insert_batch(&conn, crocin, "+90 days", 3_000, 7);
+        conn.execute(
+            "UPDATE batches SET barcode = '8901234560012' WHERE id = ?1",
+            [batch],
+        )
+        .unwrap();
+        let sale = paid_sale(&conn, None, PaymentMode::Cash, batch, 2);
+        let sale_id = db.with_tx(|tx| create_sale(tx, &sale)).unwrap().sale_id;
+
+        let err = db
+            .with_tx(|tx| deactivate_medicine(tx, crocin, false, ADMIN))
+            .unwrap_err();
+        assert!(err.to_string().contains("5 units"), "{err}");
+        assert_eq!(search_medicines(&conn, "croc", 10).unwrap().len(), 1);
+
+        db.with_tx(|tx| deactivate_medicine(tx, crocin, true, ADMIN))
+            .unwrap();
+
+        assert!(search_medicines(&conn, "croc", 10).unwrap().is_empty());
+        assert_eq!(lookup_by_barcode(&conn, "8901234560012").unwrap(), None);
+        let doc = invoice::get_invoice(&conn, sale_id).unwrap();
+        assert_eq!(doc.items[0].medicine_name, "Crocin 500");
+
+        db.with_tx(|tx| reactivate_medicine(tx, crocin, ADMIN))
+            .unwrap();
+        assert_eq!(search_medicines(&conn, "croc", 10).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn cashier_cannot_deactivate_or_reactivate_a_medicine() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let crocin = insert_medicine(&conn, "Crocin 500", 12.0);
+        let cashier = insert_cashier(&conn);
+
+        let err = db
+            .with_tx(|tx| deactivate_medicine(tx, crocin, true, cashier))
+            .unwrap_err();
+        assert!(matches!(err, AppError::Unauthorized(_)), "{err}");
+
+        db.with_tx(|tx| deactivate_medicine(tx, crocin, true, ADMIN))
+            .unwrap();
+        let err = db
+            .with_tx(|tx| reactivate_medicine(tx, crocin, cashier))
+            .unwrap_err();
+        assert!(matches!(err, AppError::Unauthorized(_)), "{err}");
+    }
+
+    #[test]
+    fn medicines_are_added_and_edited_with_a_permission() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let slab = |rate: f64| -> i64 {
+            conn.query_row("SELECT id FROM gst_slabs WHERE rate = ?1", [rate], |row| {
+                row.get(0)
+            })
+            .unwrap()
+        };
+        let mut input = MedicineInput {
+            name: " Crocin 500 ".into(),
+            generic_name: Some("Paracetamol".into()),
+            brand_name: None,
+            manufacturer: None,
+            dosage_form: "tablet".into(),
+            strength: Some("500mg".into()),
+            category: None,
+            schedule: None,
+            pack_size: 15,
+            sale_unit: Some("pack".into()),
+            hsn_code: "3004".into(),
+            gtin: None,
+            gst_slab_id: slab(12.0),
+            reorder_level: 20,
+        };
+        let cashier = insert_cashier(&conn);
+
+        let err = db
+            .with_tx(|tx| create_medicine(tx, &input, cashier))
+            .unwrap_err();
+        assert!(matches!(err, AppError::Unauthorized(_)), "{err}");
+        let crocin = db.with_tx(|tx| create_medicine(tx, &input, ADMIN)).unwrap();
+        assert_eq!(
+            search_medicines(&conn, "parac", 10).unwrap()[0].name,
+            "Crocin 500"
+        );
+
+        input.name = "Crocin Advance".into();
+        input.schedule = Some("H".into());
+        input.gst_slab_id = slab(5.0);
+        db.with_tx(|tx| update_medicine(tx, crocin, &input, ADMIN))
+            .unwrap();
+        let (name, schedule, gst_slab_id): (String, Option<String>, i64) = conn
+            .query_row(
+                "SELECT name, schedule, gst_slab_id FROM medicines WHERE id = ?1",
+                [crocin],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            (name.as_str(), schedule.as_deref(), gst_slab_id),
+            ("Crocin Advance", Some("H"), slab(12.0))
+        );
+        let err = db
+            .with_tx(|tx| update_medicine(tx, 999, &input, ADMIN))
+            .unwrap_err();
+        assert!(matches!(err, AppError::NotFound(_)), "{err}");
+    }
+}
@@ -0,0 +1,2602 @@
+use std::future::Future;
+use std::pin::Pin;
+
+use rusqlite::{Connection, OptionalExtension};
+use sqlx::error::BoxDynError;
+use sqlx::migrate::{Migration as SqlxMigration, MigrationSource, MigrationType};
+use tauri_plugin_sql::{Migration, MigrationKind};
+
+use crate::error::AppResult;
+
+/// Journal table in which the SQL plugin records applied migrations.
+const JOURNAL_TABLE: &str = "_sqlx_migrations";
+
+/// Highest migration version this build ships.
+pub fn latest_version() -> i64 {
+    all().iter().map(|m| m.version).max().unwrap_or(0)
+}
+
+/// Every table the migrations create, in creation order.
+pub fn expected_tables() -> Vec<String> {
+    let mut tables = Vec::new();
+    for migration in all() {
+        for marker in ["CREATE TABLE ", "CREATE VIRTUAL TABLE "] {
+            for (at, _) in migration.sql.match_indices(marker) {
+                // Shipped migrations cannot be edited, so some lack the guard.
+                let rest = &migration.sql[at + marker.len()..];
+                let rest = rest.strip_prefix("IF NOT EXISTS ").unwrap_or(rest);
+                let end = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                tables.push(rest[..end].to_owned());
+            }
+        }
+    }
+    tables
+}
+
+/// Latest version the SQL plugin recorded as applied to `conn`'s database,
+/// or `None` if it has never migrated it.
+pub fn applied_version(conn: &Connection) -> AppResult<Option<i64>> {
+    let has_journal: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+        [JOURNAL_TABLE],
+        |row| row.get(0),
+    )?;
+    if !has_journal {
+        return Ok(None);
+    }
+    let version: Option<i64> = conn
+        .query_row(
+            &format!("SELECT MAX(version) FROM {JOURNAL_TABLE} WHERE success = 1"),
+            [],
+            |row| row.get(0),
+        )
+        .optional()?
+        .flatten();
+    Ok(Some(version.unwrap_or(0)))
+}
+
+type Resolved = Pin<Box<dyn Future<Output = Result<Vec<SqlxMigration>, BoxDynError>> + Send>>;
+
+/// [`all`] as a sqlx migration source, for pools the backend opens itself.
+///
+/// Resolved the way the SQL plugin resolves them, so the checksums each
+/// records are accepted by the other.
+#[derive(Debug)]
+pub struct Source;
+
+impl MigrationSource<'static> for Source {
+    fn resolve(self) -> Resolved {
+        Box::pin(async {
+            Ok(all()
+                .into_iter()
+                .filter(|m| matches!(m.kind, MigrationKind::Up))
+                .map(|m| {
+                    SqlxMigration::new(
+                        m.version,
+                        m.description.into(),
+                        MigrationType::ReversibleUp,
+                        m.sql.into(),
+                        false,
+                    )
+                })
+                .collect())
+        })
+    }
+}
+
+/// Schema migrations applied by the SQL plugin on first `Database.load`, or
+/// by [`crate::db::Db::open_pool`] for an encrypted database.
+///
+/// Applied migrations are checksummed, so never edit an existing entry —
+/// append a new version instead.
+pub fn all() -> Vec<Migration> {
+    vec![
+        Migration {
+            version: 1,
+            description: "create initial schema",
+            sql: r#"
+                PRAGMA journal_mode=WAL;
+                PRAGMA foreign_keys=ON;
+
+                CREATE TABLE IF NOT EXISTS users (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    username TEXT NOT NULL UNIQUE,
+                    password_hash TEXT NOT NULL,
+                    full_name TEXT NOT NULL,
+                    role TEXT NOT NULL CHECK(role IN ('admin', 'pharmacist', 'cashier')),
+                    is_active INTEGER NOT NULL DEFAULT 1,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+                );
+
+                CREATE TABLE IF NOT EXISTS gst_slabs (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    rate REAL NOT NULL UNIQUE,
+                    description TEXT NOT NULL
+                );
+
+                CREATE TABLE IF NOT EXISTS medicines (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    name TEXT NOT NULL,
+                    generic_name TEXT,
+                    brand_name TEXT,
+                    manufacturer TEXT,
+                    dosage_form TEXT NOT NULL DEFAULT 'tablet',
+                    strength TEXT,
+                    category TEXT,
+                    hsn_code TEXT NOT NULL DEFAULT '3004',
+                    gst_slab_id INTEGER NOT NULL,
+                    reorder_level INTEGER NOT NULL DEFAULT 20,
+                    is_active INTEGER NOT NULL DEFAULT 1,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    FOREIGN KEY (gst_slab_id) REFERENCES gst_slabs(id)
+                );
+
+                CREATE TABLE IF NOT EXISTS batches (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    medicine_id INTEGER NOT NULL,
+                    batch_number TEXT NOT NULL,
+                    expiry_date TEXT NOT NULL,
+                    cost_price_paise INTEGER NOT NULL,
+                    mrp_paise INTEGER NOT NULL,
+                    selling_price_paise INTEGER NOT NULL,
+                    quantity INTEGER NOT NULL DEFAULT 0,
+                    manufacturing_date TEXT,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    FOREIGN KEY (medicine_id) REFERENCES medicines(id),
+                    CHECK (selling_price_paise <= mrp_paise),
+                    CHECK (cost_price_paise >= 0),
+                    CHECK (mrp_paise > 0),
+                    CHECK (selling_price_paise > 0),
+                    CHECK (quantity >= 0)
+                );
+
+                CREATE TABLE IF NOT EXISTS customers (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    name TEXT NOT NULL,
+                    phone TEXT,
+                    email TEXT,
+                    address TEXT,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+                );
+
+                CREATE TABLE IF NOT EXISTS suppliers (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    name TEXT NOT NULL,
+                    phone TEXT,
+                    email TEXT,
+                    address TEXT,
+                    gst_in TEXT,
+                    drug_license_no TEXT,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+                );
+
+                CREATE TABLE IF NOT EXISTS supplier_payments (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    supplier_id INTEGER NOT NULL,
+                    amount_paise INTEGER NOT NULL,
+                    payment_date TEXT NOT NULL,
+                    payment_mode TEXT NOT NULL CHECK(payment_mode IN ('cash', 'card', 'upi', 'credit')),
+                    reference TEXT,
+                    notes TEXT,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    FOREIGN KEY (supplier_id) REFERENCES suppliers(id)
+                );
+
+                CREATE TABLE IF NOT EXISTS sales (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    invoice_number TEXT NOT NULL UNIQUE,
+                    customer_id INTEGER,
+                    user_id INTEGER NOT NULL,
+                    sale_date TEXT NOT NULL DEFAULT (datetime('now')),
+                    subtotal_paise INTEGER NOT NULL DEFAULT 0,
+                    discount_paise INTEGER NOT NULL DEFAULT 0,
+                    total_cgst_paise INTEGER NOT NULL DEFAULT 0,
+                    total_sgst_paise INTEGER NOT NULL DEFAULT 0,
+                    total_gst_paise INTEGER NOT NULL DEFAULT 0,
+                    grand_total_paise INTEGER NOT NULL DEFAULT 0,
+                    payment_mode TEXT NOT NULL DEFAULT 'cash' CHECK(payment_mode IN ('cash', 'card', 'upi', 'credit')),
+                    notes TEXT,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    FOREIGN KEY (customer_id) REFERENCES customers(id),
+                    FOREIGN KEY (user_id) REFERENCES users(id)
+                );
+
+                CREATE TABLE IF NOT EXISTS sale_items (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    sale_id INTEGER NOT NULL,
+                    batch_id INTEGER NOT NULL,
+                    medicine_id INTEGER NOT NULL,
+                    quantity INTEGER NOT NULL,
+                    unit_price_paise INTEGER NOT NULL,
+                    discount_paise INTEGER NOT NULL DEFAULT 0,
+                    taxable_amount_paise INTEGER NOT NULL,
+                    cgst_rate REAL NOT NULL DEFAULT 0,
+                    cgst_amount_paise INTEGER NOT NULL DEFAULT 0,
+                    sgst_rate REAL NOT NULL DEFAULT 0,
+                    sgst_amount_paise INTEGER NOT NULL DEFAULT 0,
+                    total_paise INTEGER NOT NULL,
+                    FOREIGN KEY (sale_id) REFERENCES sales(id),
+                    FOREIGN KEY (batch_id) REFERENCES batches(id),
+                    FOREIGN KEY (medicine_id) REFERENCES medicines(id)
+                );
+
+                CREATE TABLE IF NOT EXISTS prescriptions (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    customer_id INTEGER NOT NULL,
+                    sale_id INTEGER,
+                    doctor_name TEXT NOT NULL,
+                    rx_number TEXT,
+                    prescription_date TEXT NOT NULL,
+                    notes TEXT,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    FOREIGN KEY (customer_id) REFERENCES customers(id),
+                    FOREIGN KEY (sale_id) REFERENCES sales(id)
+                );
+
+                CREATE TABLE IF NOT EXISTS pharmacy_settings (
+                    id INTEGER PRIMARY KEY CHECK(id = 1),
+                    name TEXT NOT NULL DEFAULT 'My Pharmacy',
+                    address TEXT NOT NULL DEFAULT '',
+                    phone TEXT NOT NULL DEFAULT '',
+                    email TEXT,
+                    gstin TEXT NOT NULL DEFAULT '',
+                    drug_license_no TEXT NOT NULL DEFAULT '',
+                    state_code TEXT NOT NULL DEFAULT '',
+                    invoice_prefix TEXT NOT NULL DEFAULT 'INV',
+                    next_invoice_number INTEGER NOT NULL DEFAULT 1,
+                    low_stock_threshold INTEGER NOT NULL DEFAULT 20,
+                    near_expiry_days INTEGER NOT NULL DEFAULT 90,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+                );
+
+                CREATE INDEX IF NOT EXISTS idx_medicines_name ON medicines(name);
+                CREATE INDEX IF NOT EXISTS idx_batches_medicine_id ON batches(medicine_id);
+                CREATE INDEX IF NOT EXISTS idx_batches_expiry_date ON batches(expiry_date);
+                CREATE INDEX IF NOT EXISTS idx_sales_invoice_number ON sales(invoice_number);
+                CREATE INDEX IF NOT EXISTS idx_sales_sale_date ON sales(sale_date);
+                CREATE INDEX IF NOT EXISTS idx_sale_items_sale_id ON sale_items(sale_id);
+                CREATE INDEX IF NOT EXISTS idx_customers_name ON customers(name);
+                CREATE INDEX IF NOT EXISTS idx_customers_phone ON customers(phone);
+                CREATE INDEX IF NOT EXISTS idx_suppliers_name ON suppliers(name);
+                CREATE INDEX IF NOT EXISTS idx_prescriptions_customer_id ON prescriptions(customer_id);
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 2,
+            description: "seed default data",
+            sql: r#"
+                INSERT OR IGNORE INTO gst_slabs (rate, description) VALUES (0, 'GST Exempt (0%)');
+                INSERT OR IGNORE INTO gst_slabs (rate, description) VALUES (5, 'GST 5% (Most medicines post Sep 2025)');
+                INSERT OR IGNORE INTO gst_slabs (rate, description) VALUES (12, 'GST 12%');
+                INSERT OR IGNORE INTO gst_slabs (rate, description) VALUES (18, 'GST 18%');
+
+                INSERT OR IGNORE INTO users (username, password_hash, full_name, role, is_active)
+                VALUES ('admin', '$2b$10$n5vbmtk602soZ1.G8CH/6u4F69goDCXD9c.SObHhhH36QdpnVtFkG', 'Administrator', 'admin', 1);
+
+                INSERT OR IGNORE INTO pharmacy_settings (id, name, address, phone, gstin, drug_license_no, state_code)
+                VALUES (1, 'My Pharmacy', '123 Main Street', '0000000000', '', '', '');
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 3,
+            description: "add hsn_code to sale_items",
+            sql: r#"
+                ALTER TABLE sale_items ADD COLUMN hsn_code TEXT NOT NULL DEFAULT '3004';
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 4,
+            description: "add batch status for quarantine holds",
+            sql: r#"
+                ALTER TABLE batches ADD COLUMN status TEXT NOT NULL DEFAULT 'available'
+                    CHECK(status IN ('available', 'quarantine', 'blocked'));
+
+                CREATE TABLE IF NOT EXISTS batch_status_changes (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    batch_id INTEGER NOT NULL,
+                    from_status TEXT NOT NULL,
+                    to_status TEXT NOT NULL,
+                    reason TEXT NOT NULL,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    FOREIGN KEY (batch_id) REFERENCES batches(id)
+                );
+
+                CREATE INDEX IF NOT EXISTS idx_batches_status ON batches(status);
+                CREATE INDEX IF NOT EXISTS idx_batch_status_changes_batch_id ON batch_status_changes(batch_id);
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 5,
+            description: "add gstin and state_code to customers",
+            sql: r#"
+                ALTER TABLE customers ADD COLUMN gstin TEXT;
+                ALTER TABLE customers ADD COLUMN state_code TEXT;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 6,
+            description: "add sale status and amendment history",
+            sql: r#"
+                ALTER TABLE sales ADD COLUMN status TEXT NOT NULL DEFAULT 'completed'
+                    CHECK(status IN ('completed', 'amended', 'refunded'));
+
+                CREATE TABLE IF NOT EXISTS sale_amendments (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    sale_id INTEGER NOT NULL,
+                    user_id INTEGER NOT NULL,
+                    previous_items TEXT NOT NULL,
+                    previous_grand_total_paise INTEGER NOT NULL,
+                    new_grand_total_paise INTEGER NOT NULL,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    FOREIGN KEY (sale_id) REFERENCES sales(id),
+                    FOREIGN KEY (user_id) REFERENCES users(id)
+                );
+
+                CREATE INDEX IF NOT EXISTS idx_sale_amendments_sale_id ON sale_amendments(sale_id);
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 7,
+            description: "add e-invoice location fields",
+            sql: r#"
+                ALTER TABLE pharmacy_settings ADD COLUMN city TEXT NOT NULL DEFAULT '';
+                ALTER TABLE pharmacy_settings ADD COLUMN pincode TEXT NOT NULL DEFAULT '';
+                ALTER TABLE pharmacy_settings ADD COLUMN einvoice_enabled INTEGER NOT NULL DEFAULT 0;
+                ALTER TABLE customers ADD COLUMN city TEXT;
+                ALTER TABLE customers ADD COLUMN pincode TEXT;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 8,
+            description: "add reorder lead time to settings",
+            sql: r#"
+                ALTER TABLE pharmacy_settings ADD COLUMN reorder_lead_time_days INTEGER NOT NULL DEFAULT 7;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 9,
+            description: "add sync queue",
+            sql: r#"
+                CREATE TABLE IF NOT EXISTS sync_queue (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    operation TEXT NOT NULL,
+                    payload TEXT NOT NULL,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    synced_at TEXT
+                );
+
+                CREATE INDEX IF NOT EXISTS idx_sync_queue_synced_at ON sync_queue(synced_at);
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 10,
+            description: "add igst columns and interstate flag",
+            sql: r#"
+                ALTER TABLE sale_items ADD COLUMN igst_rate REAL NOT NULL DEFAULT 0;
+                ALTER TABLE sale_items ADD COLUMN igst_amount_paise INTEGER NOT NULL DEFAULT 0;
+                ALTER TABLE sales ADD COLUMN total_igst_paise INTEGER NOT NULL DEFAULT 0;
+                ALTER TABLE sales ADD COLUMN is_interstate INTEGER NOT NULL DEFAULT 0;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 11,
+            description: "add stock movements ledger",
+            sql: r#"
+                CREATE TABLE IF NOT EXISTS stock_movements (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    batch_id INTEGER NOT NULL REFERENCES batches(id),
+                    medicine_id INTEGER NOT NULL REFERENCES medicines(id),
+                    change_qty INTEGER NOT NULL,
+                    reason TEXT NOT NULL
+                        CHECK(reason IN ('sale', 'return', 'adjustment', 'purchase', 'disposal')),
+                    reference_id INTEGER,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+                );
+
+                CREATE INDEX IF NOT EXISTS idx_stock_movements_batch ON stock_movements(batch_id);
+
+                -- Opening balance so every batch's ledger sums to its quantity.
+                INSERT INTO stock_movements (batch_id, medicine_id, change_qty, reason)
+                SELECT id, medicine_id, quantity, 'adjustment' FROM batches WHERE quantity <> 0;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 12,
+            description: "add sale returns",
+            sql: r#"
+                CREATE TABLE IF NOT EXISTS sale_returns (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    sale_id INTEGER NOT NULL,
+                    user_id INTEGER NOT NULL,
+                    return_date TEXT NOT NULL DEFAULT (datetime('now')),
+                    taxable_amount_paise INTEGER NOT NULL DEFAULT 0,
+                    total_cgst_paise INTEGER NOT NULL DEFAULT 0,
+                    total_sgst_paise INTEGER NOT NULL DEFAULT 0,
+                    total_igst_paise INTEGER NOT NULL DEFAULT 0,
+                    total_gst_paise INTEGER NOT NULL DEFAULT 0,
+                    refund_total_paise INTEGER NOT NULL DEFAULT 0,
+                    refund_mode TEXT NOT NULL DEFAULT 'cash' CHECK(refund_mode IN ('cash', 'card', 'upi', 'credit')),
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    FOREIGN KEY (sale_id) REFERENCES sales(id),
+                    FOREIGN KEY (user_id) REFERENCES users(id)
+                );
+
+                CREATE TABLE IF NOT EXISTS sale_return_items (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    return_id INTEGER NOT NULL,
+                    sale_item_id INTEGER NOT NULL,
+                    batch_id INTEGER NOT NULL,
+                    medicine_id INTEGER NOT NULL,
+                    quantity INTEGER NOT NULL CHECK(quantity > 0),
+                    taxable_amount_paise INTEGER NOT NULL,
+                    cgst_amount_paise INTEGER NOT NULL DEFAULT 0,
+                    sgst_amount_paise INTEGER NOT NULL DEFAULT 0,
+                    igst_amount_paise INTEGER NOT NULL DEFAULT 0,
+                    total_paise INTEGER NOT NULL,
+                    batch_expired INTEGER NOT NULL DEFAULT 0,
+                    FOREIGN KEY (return_id) REFERENCES sale_returns(id),
+                    FOREIGN KEY (sale_item_id) REFERENCES sale_items(id),
+                    FOREIGN KEY (batch_id) REFERENCES batches(id),
+                    FOREIGN KEY (medicine_id) REFERENCES medicines(id)
+                );
+
+                CREATE INDEX IF NOT EXISTS idx_sale_returns_sale ON sale_returns(sale_id);
+                CREATE INDEX IF NOT EXISTS idx_sale_return_items_sale_item ON sale_return_items(sale_item_id);
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 13,
+            description: "add purchases",
+            sql: r#"
+                CREATE TABLE IF NOT EXISTS purchases (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    supplier_id INTEGER NOT NULL,
+                    invoice_number TEXT NOT NULL,
+                    total_paise INTEGER NOT NULL CHECK (total_paise >= 0),
+                    purchase_date TEXT NOT NULL DEFAULT (date('now')),
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    FOREIGN KEY (supplier_id) REFERENCES suppliers(id),
+                    UNIQUE (supplier_id, invoice_number)
+                );
+
+                CREATE INDEX IF NOT EXISTS idx_purchases_supplier_date ON purchases(supplier_id, purchase_date);
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 14,
+            description: "add sales round off",
+            sql: r#"
+                ALTER TABLE sales ADD COLUMN round_off_paise INTEGER NOT NULL DEFAULT 0;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 15,
+            description: "add medicines full-text index",
+            sql: r#"
+                CREATE VIRTUAL TABLE IF NOT EXISTS medicines_fts USING fts5(
+                    name, generic_name, brand_name,
+                    content = 'medicines', content_rowid = 'id',
+                    tokenize = 'unicode61 remove_diacritics 2'
+                );
+
+                CREATE TRIGGER IF NOT EXISTS medicines_fts_insert AFTER INSERT ON medicines BEGIN
+                    INSERT INTO medicines_fts (rowid, name, generic_name, brand_name)
+                    VALUES (new.id, new.name, new.generic_name, new.brand_name);
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS medicines_fts_delete AFTER DELETE ON medicines BEGIN
+                    INSERT INTO medicines_fts (medicines_fts, rowid, name, generic_name, brand_name)
+                    VALUES ('delete', old.id, old.name, old.generic_name, old.brand_name);
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS medicines_fts_update AFTER UPDATE ON medicines BEGIN
+                    INSERT INTO medicines_fts (medicines_fts, rowid, name, generic_name, brand_name)
+                    VALUES ('delete', old.id, old.name, old.generic_name, old.brand_name);
+                    INSERT INTO medicines_fts (rowid, name, generic_name, brand_name)
+                    VALUES (new.id, new.name, new.generic_name, new.brand_name);
+                END;
+
+                INSERT INTO medicines_fts (medicines_fts) VALUES ('rebuild');
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 16,
+            description: "add batch barcodes",
+            sql: r#"
+                ALTER TABLE batches ADD COLUMN barcode TEXT;
+
+                CREATE UNIQUE INDEX IF NOT EXISTS idx_batches_barcode ON batches(barcode);
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 17,
+            description: "maintain updated_at on update",
+            sql: r#"
+                -- Each trigger fires only when a data column changed and the
+                -- statement did not set updated_at itself; its own UPDATE
+                -- changes neither, so it cannot recurse. Columns added to
+                -- these tables later must be added to the WHEN clause.
+                -- The invoice counter in pharmacy_settings is not a settings
+                -- edit and is left out.
+
+                -- The touch UPDATE would otherwise re-run the full-text trigger
+                -- with already-updated values and corrupt the index.
+                DROP TRIGGER IF EXISTS medicines_fts_update;
+                CREATE TRIGGER medicines_fts_update
+                AFTER UPDATE OF name, generic_name, brand_name ON medicines BEGIN
+                    INSERT INTO medicines_fts (medicines_fts, rowid, name, generic_name, brand_name)
+                    VALUES ('delete', old.id, old.name, old.generic_name, old.brand_name);
+                    INSERT INTO medicines_fts (rowid, name, generic_name, brand_name)
+                    VALUES (new.id, new.name, new.generic_name, new.brand_name);
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS users_touch_updated_at AFTER UPDATE ON users
+                FOR EACH ROW WHEN new.updated_at IS old.updated_at
+                    AND (old.username IS NOT new.username
+                         OR old.password_hash IS NOT new.password_hash
+                         OR old.full_name IS NOT new.full_name
+                         OR old.role IS NOT new.role
+                         OR old.is_active IS NOT new.is_active)
+                BEGIN
+                    UPDATE users SET updated_at = datetime('now') WHERE id = new.id;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS medicines_touch_updated_at AFTER UPDATE ON medicines
+                FOR EACH ROW WHEN new.updated_at IS old.updated_at
+                    AND (old.name IS NOT new.name
+                         OR old.generic_name IS NOT new.generic_name
+                         OR old.brand_name IS NOT new.brand_name
+                         OR old.manufacturer IS NOT new.manufacturer
+                         OR old.dosage_form IS NOT new.dosage_form
+                         OR old.strength IS NOT new.strength
+                         OR old.category IS NOT new.category
+                         OR old.hsn_code IS NOT new.hsn_code
+                         OR old.gst_slab_id IS NOT new.gst_slab_id
+                         OR old.reorder_level IS NOT new.reorder_level
+                         OR old.is_active IS NOT new.is_active)
+                BEGIN
+                    UPDATE medicines SET updated_at = datetime('now') WHERE id = new.id;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS customers_touch_updated_at AFTER UPDATE ON customers
+                FOR EACH ROW WHEN new.updated_at IS old.updated_at
+                    AND (old.name IS NOT new.name
+                         OR old.phone IS NOT new.phone
+                         OR old.email IS NOT new.email
+                         OR old.address IS NOT new.address
+                         OR old.gstin IS NOT new.gstin
+                         OR old.state_code IS NOT new.state_code
+                         OR old.city IS NOT new.city
+                         OR old.pincode IS NOT new.pincode)
+                BEGIN
+                    UPDATE customers SET updated_at = datetime('now') WHERE id = new.id;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS suppliers_touch_updated_at AFTER UPDATE ON suppliers
+                FOR EACH ROW WHEN new.updated_at IS old.updated_at
+                    AND (old.name IS NOT new.name
+                         OR old.phone IS NOT new.phone
+                         OR old.email IS NOT new.email
+                         OR old.address IS NOT new.address
+                         OR old.gst_in IS NOT new.gst_in
+                         OR old.drug_license_no IS NOT new.drug_license_no)
+                BEGIN
+                    UPDATE suppliers SET updated_at = datetime('now') WHERE id = new.id;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS pharmacy_settings_touch_updated_at AFTER UPDATE ON pharmacy_settings
+                FOR EACH ROW WHEN new.updated_at IS old.updated_at
+                    AND (old.name IS NOT new.name
+                         OR old.address IS NOT new.address
+                         OR old.phone IS NOT new.phone
+                         OR old.email IS NOT new.email
+                         OR old.gstin IS NOT new.gstin
+                         OR old.drug_license_no IS NOT new.drug_license_no
+                         OR old.state_code IS NOT new.state_code
+                         OR old.city IS NOT new.city
+                         OR old.pincode IS NOT new.pincode
+                         OR old.invoice_prefix IS NOT new.invoice_prefix
+                         OR old.low_stock_threshold IS NOT new.low_stock_threshold
+                         OR old.near_expiry_days IS NOT new.near_expiry_days
+                         OR old.einvoice_enabled IS NOT new.einvoice_enabled
+                         OR old.reorder_lead_time_days IS NOT new.reorder_lead_time_days)
+                BEGIN
+                    UPDATE pharmacy_settings SET updated_at = datetime('now') WHERE id = new.id;
+                END;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 18,
+            description: "add customer payments",
+            sql: r#"
+                CREATE TABLE IF NOT EXISTS customer_payments (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    customer_id INTEGER NOT NULL,
+                    amount_paise INTEGER NOT NULL CHECK(amount_paise > 0),
+                    payment_date TEXT NOT NULL,
+                    payment_mode TEXT NOT NULL CHECK(payment_mode IN ('cash', 'card', 'upi', 'credit')),
+                    reference TEXT,
+                    notes TEXT,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    FOREIGN KEY (customer_id) REFERENCES customers(id)
+                );
+
+                CREATE INDEX IF NOT EXISTS idx_customer_payments_customer_id ON customer_payments(customer_id);
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 19,
+            description: "add split-tender sale payments",
+            sql: r#"
+                CREATE TABLE IF NOT EXISTS sale_payments (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    sale_id INTEGER NOT NULL REFERENCES sales(id),
+                    payment_mode TEXT NOT NULL CHECK(payment_mode IN ('cash', 'card', 'upi', 'credit')),
+                    amount_paise INTEGER NOT NULL CHECK(amount_paise > 0),
+                    UNIQUE (sale_id, payment_mode)
+                );
+
+                CREATE INDEX IF NOT EXISTS idx_sale_payments_sale_id ON sale_payments(sale_id);
+
+                INSERT INTO sale_payments (sale_id, payment_mode, amount_paise)
+                SELECT id, payment_mode, grand_total_paise FROM sales WHERE grand_total_paise > 0;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 20,
+            description: "add drug schedule to medicines",
+            sql: r#"
+                ALTER TABLE medicines ADD COLUMN schedule TEXT CHECK(schedule IN ('H', 'H1', 'X'));
+
+                DROP TRIGGER IF EXISTS medicines_touch_updated_at;
+                CREATE TRIGGER medicines_touch_updated_at AFTER UPDATE ON medicines
+                FOR EACH ROW WHEN new.updated_at IS old.updated_at
+                    AND (old.name IS NOT new.name
+                         OR old.generic_name IS NOT new.generic_name
+                         OR old.brand_name IS NOT new.brand_name
+                         OR old.manufacturer IS NOT new.manufacturer
+                         OR old.dosage_form IS NOT new.dosage_form
+                         OR old.strength IS NOT new.strength
+                         OR old.category IS NOT new.category
+                         OR old.hsn_code IS NOT new.hsn_code
+                         OR old.gst_slab_id IS NOT new.gst_slab_id
+                         OR old.reorder_level IS NOT new.reorder_level
+                         OR old.is_active IS NOT new.is_active
+                         OR old.schedule IS NOT new.schedule)
+                BEGIN
+                    UPDATE medicines SET updated_at = datetime('now') WHERE id = new.id;
+                END;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 21,
+            description: "add stock disposal register",
+            sql: r#"
+                CREATE TABLE IF NOT EXISTS stock_disposals (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    reason TEXT NOT NULL,
+                    total_cost_paise INTEGER NOT NULL,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+                );
+
+                CREATE TABLE IF NOT EXISTS stock_disposal_items (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    disposal_id INTEGER NOT NULL REFERENCES stock_disposals(id),
+                    batch_id INTEGER NOT NULL REFERENCES batches(id),
+                    quantity INTEGER NOT NULL CHECK(quantity > 0),
+                    cost_price_paise INTEGER NOT NULL,
+                    cost_value_paise INTEGER NOT NULL
+                );
+
+                CREATE INDEX IF NOT EXISTS idx_stock_disposal_items_disposal_id ON stock_disposal_items(disposal_id);
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 22,
+            description: "add tax-inclusive pricing setting",
+            sql: r#"
+                ALTER TABLE pharmacy_settings ADD COLUMN prices_include_gst INTEGER NOT NULL DEFAULT 1;
+
+                DROP TRIGGER IF EXISTS pharmacy_settings_touch_updated_at;
+                CREATE TRIGGER pharmacy_settings_touch_updated_at AFTER UPDATE ON pharmacy_settings
+                FOR EACH ROW WHEN new.updated_at IS old.updated_at
+                    AND (old.name IS NOT new.name
+                         OR old.address IS NOT new.address
+                         OR old.phone IS NOT new.phone
+                         OR old.email IS NOT new.email
+                         OR old.gstin IS NOT new.gstin
+                         OR old.drug_license_no IS NOT new.drug_license_no
+                         OR old.state_code IS NOT new.state_code
+                         OR old.city IS NOT new.city
+                         OR old.pincode IS NOT new.pincode
+                         OR old.invoice_prefix IS NOT new.invoice_prefix
+                         OR old.low_stock_threshold IS NOT new.low_stock_threshold
+                         OR old.near_expiry_days IS NOT new.near_expiry_days
+                         OR old.einvoice_enabled IS NOT new.einvoice_enabled
+                         OR old.reorder_lead_time_days IS NOT new.reorder_lead_time_days
+                         OR old.prices_include_gst IS NOT new.prices_include_gst)
+                BEGIN
+                    UPDATE pharmacy_settings SET updated_at = datetime('now') WHERE id = new.id;
+                END;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 23,
+            description: "add pack size and sale unit to medicines",
+            sql: r#"
+                ALTER TABLE medicines ADD COLUMN pack_size INTEGER NOT NULL DEFAULT 1
+                    CHECK(pack_size > 0);
+                ALTER TABLE medicines ADD COLUMN sale_unit TEXT
+                    CHECK(sale_unit IN ('pack', 'loose'));
+                ALTER TABLE sale_items ADD COLUMN unit_size INTEGER NOT NULL DEFAULT 1
+                    CHECK(unit_size > 0);
+
+                DROP TRIGGER IF EXISTS medicines_touch_updated_at;
+                CREATE TRIGGER medicines_touch_updated_at AFTER UPDATE ON medicines
+                FOR EACH ROW WHEN new.updated_at IS old.updated_at
+                    AND (old.name IS NOT new.name
+                         OR old.generic_name IS NOT new.generic_name
+                         OR old.brand_name IS NOT new.brand_name
+                         OR old.manufacturer IS NOT new.manufacturer
+                         OR old.dosage_form IS NOT new.dosage_form
+                         OR old.strength IS NOT new.strength
+                         OR old.category IS NOT new.category
+                         OR old.hsn_code IS NOT new.hsn_code
+                         OR old.gst_slab_id IS NOT new.gst_slab_id
+                         OR old.reorder_level IS NOT new.reorder_level
+                         OR old.is_active IS NOT new.is_active
+                         OR old.schedule IS NOT new.schedule
+                         OR old.pack_size IS NOT new.pack_size
+                         OR old.sale_unit IS NOT new.sale_unit)
+                BEGIN
+                    UPDATE medicines SET updated_at = datetime('now') WHERE id = new.id;
+                END;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 24,
+            description: "add customer loyalty points",
+            sql: r#"
+                ALTER TABLE customers ADD COLUMN loyalty_points INTEGER NOT NULL DEFAULT 0
+                    CHECK(loyalty_points >= 0);
+                ALTER TABLE pharmacy_settings ADD COLUMN loyalty_spend_per_point_paise INTEGER NOT NULL DEFAULT 10000
+                    CHECK(loyalty_spend_per_point_paise >= 0);
+                ALTER TABLE pharmacy_settings ADD COLUMN loyalty_point_value_paise INTEGER NOT NULL DEFAULT 100
+                    CHECK(loyalty_point_value_paise >= 0);
+
+                CREATE TABLE IF NOT EXISTS loyalty_transactions (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    customer_id INTEGER NOT NULL,
+                    sale_id INTEGER,
+                    kind TEXT NOT NULL CHECK(kind IN ('accrual', 'redemption', 'reversal')),
+                    points INTEGER NOT NULL CHECK(points <> 0),
+                    value_paise INTEGER NOT NULL DEFAULT 0,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    FOREIGN KEY (customer_id) REFERENCES customers(id),
+                    FOREIGN KEY (sale_id) REFERENCES sales(id)
+                );
+                CREATE INDEX IF NOT EXISTS idx_loyalty_transactions_customer ON loyalty_transactions(customer_id);
+                CREATE INDEX IF NOT EXISTS idx_loyalty_transactions_sale ON loyalty_transactions(sale_id);
+
+                DROP TRIGGER IF EXISTS pharmacy_settings_touch_updated_at;
+                CREATE TRIGGER pharmacy_settings_touch_updated_at AFTER UPDATE ON pharmacy_settings
+                FOR EACH ROW WHEN new.updated_at IS old.updated_at
+                    AND (old.name IS NOT new.name
+                         OR old.address IS NOT new.address
+                         OR old.phone IS NOT new.phone
+                         OR old.email IS NOT new.email
+                         OR old.gstin IS NOT new.gstin
+                         OR old.drug_license_no IS NOT new.drug_license_no
+                         OR old.state_code IS NOT new.state_code
+                         OR old.city IS NOT new.city
+                         OR old.pincode IS NOT new.pincode
+                         OR old.invoice_prefix IS NOT new.invoice_prefix
+                         OR old.low_stock_threshold IS NOT new.low_stock_threshold
+                         OR old.near_expiry_days IS NOT new.near_expiry_days
+                         OR old.einvoice_enabled IS NOT new.einvoice_enabled
+                         OR old.reorder_lead_time_days IS NOT new.reorder_lead_time_days
+                         OR old.prices_include_gst IS NOT new.prices_include_gst
+                         OR old.loyalty_spend_per_point_paise IS NOT new.loyalty_spend_per_point_paise
+                         OR old.loyalty_point_value_paise IS NOT new.loyalty_point_value_paise)
+                BEGIN
+                    UPDATE pharmacy_settings SET updated_at = datetime('now') WHERE id = new.id;
+                END;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 25,
+            description: "add invoice number format and financial-year reset",
+            sql: r#"
+                ALTER TABLE pharmacy_settings ADD COLUMN invoice_number_format TEXT NOT NULL DEFAULT '{prefix}-{seq:6}';
+                ALTER TABLE pharmacy_settings ADD COLUMN fy_reset INTEGER NOT NULL DEFAULT 0;
+                ALTER TABLE pharmacy_settings ADD COLUMN invoice_sequence_fy TEXT;
+
+                DROP TRIGGER IF EXISTS pharmacy_settings_touch_updated_at;
+                CREATE TRIGGER pharmacy_settings_touch_updated_at AFTER UPDATE ON pharmacy_settings
+                FOR EACH ROW WHEN new.updated_at IS old.updated_at
+                    AND (old.name IS NOT new.name
+                         OR old.address IS NOT new.address
+                         OR old.phone IS NOT new.phone
+                         OR old.email IS NOT new.email
+                         OR old.gstin IS NOT new.gstin
+                         OR old.drug_license_no IS NOT new.drug_license_no
+                         OR old.state_code IS NOT new.state_code
+                         OR old.city IS NOT new.city
+                         OR old.pincode IS NOT new.pincode
+                         OR old.invoice_prefix IS NOT new.invoice_prefix
+                         OR old.low_stock_threshold IS NOT new.low_stock_threshold
+                         OR old.near_expiry_days IS NOT new.near_expiry_days
+                         OR old.einvoice_enabled IS NOT new.einvoice_enabled
+                         OR old.reorder_lead_time_days IS NOT new.reorder_lead_time_days
+                         OR old.prices_include_gst IS NOT new.prices_include_gst
+                         OR old.loyalty_spend_per_point_paise IS NOT new.loyalty_spend_per_point_paise
+                         OR old.loyalty_point_value_paise IS NOT new.loyalty_point_value_paise
+                         OR old.invoice_number_format IS NOT new.invoice_number_format
+                         OR old.fy_reset IS NOT new.fy_reset)
+                BEGIN
+                    UPDATE pharmacy_settings SET updated_at = datetime('now') WHERE id = new.id;
+                END;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 26,
+            description: "add a note to stock movements",
+            sql: r#"
+                ALTER TABLE stock_movements ADD COLUMN note TEXT;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 27,
+            description: "record each medicine's GST slab history",
+            sql: r#"
+                CREATE TABLE IF NOT EXISTS medicine_gst_history (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    medicine_id INTEGER NOT NULL,
+                    gst_slab_id INTEGER NOT NULL,
+                    effective_from TEXT NOT NULL,
+                    effective_to TEXT,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    FOREIGN KEY (medicine_id) REFERENCES medicines(id) ON DELETE CASCADE,
+                    FOREIGN KEY (gst_slab_id) REFERENCES gst_slabs(id),
+                    CHECK (effective_to IS NULL OR effective_to >= effective_from)
+                );
+                CREATE INDEX IF NOT EXISTS idx_medicine_gst_history_medicine
+                    ON medicine_gst_history(medicine_id, effective_from);
+                CREATE UNIQUE INDEX IF NOT EXISTS idx_medicine_gst_history_open
+                    ON medicine_gst_history(medicine_id) WHERE effective_to IS NULL;
+
+                INSERT INTO medicine_gst_history (medicine_id, gst_slab_id, effective_from)
+                SELECT id, gst_slab_id, date(created_at) FROM medicines;
+
+                CREATE TRIGGER IF NOT EXISTS medicines_open_gst_history AFTER INSERT ON medicines
+                FOR EACH ROW
+                BEGIN
+                    INSERT INTO medicine_gst_history (medicine_id, gst_slab_id, effective_from)
+                    VALUES (new.id, new.gst_slab_id, date('now'));
+                END;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 28,
+            description: "track prescription refills",
+            sql: r#"
+                ALTER TABLE prescriptions ADD COLUMN refills_allowed INTEGER NOT NULL DEFAULT 0
+                    CHECK(refills_allowed >= 0);
+                ALTER TABLE prescriptions ADD COLUMN refills_used INTEGER NOT NULL DEFAULT 0
+                    CHECK(refills_used >= 0);
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 29,
+            description: "add branches and scope stock and sales to them",
+            sql: r#"
+                CREATE TABLE IF NOT EXISTS branches (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    name TEXT NOT NULL UNIQUE,
+                    is_active INTEGER NOT NULL DEFAULT 1,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+                );
+                INSERT OR IGNORE INTO branches (id, name) VALUES (1, 'Main');
+
+                ALTER TABLE batches ADD COLUMN branch_id INTEGER REFERENCES branches(id);
+                ALTER TABLE sales ADD COLUMN branch_id INTEGER REFERENCES branches(id);
+                ALTER TABLE users ADD COLUMN branch_id INTEGER REFERENCES branches(id);
+                ALTER TABLE stock_movements ADD COLUMN from_branch_id INTEGER REFERENCES branches(id);
+                ALTER TABLE stock_movements ADD COLUMN to_branch_id INTEGER REFERENCES branches(id);
+                UPDATE batches SET branch_id = 1;
+                UPDATE sales SET branch_id = 1;
+                UPDATE users SET branch_id = 1;
+
+                CREATE INDEX IF NOT EXISTS idx_batches_branch_medicine ON batches(branch_id, medicine_id);
+                CREATE INDEX IF NOT EXISTS idx_sales_branch_sale_date ON sales(branch_id, sale_date);
+
+                -- Rows written without a branch, as the frontend does, belong to Main.
+                CREATE TRIGGER IF NOT EXISTS batches_default_branch AFTER INSERT ON batches
+                FOR EACH ROW WHEN new.branch_id IS NULL
+                BEGIN
+                    UPDATE batches SET branch_id = 1 WHERE id = new.id;
+                END;
+                CREATE TRIGGER IF NOT EXISTS sales_default_branch AFTER INSERT ON sales
+                FOR EACH ROW WHEN new.branch_id IS NULL
+                BEGIN
+                    UPDATE sales SET branch_id = 1 WHERE id = new.id;
+                END;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 30,
+            description: "add idempotency keys for sales",
+            sql: r#"
+                ALTER TABLE pharmacy_settings ADD COLUMN idempotency_window_hours INTEGER NOT NULL DEFAULT 24
+                    CHECK(idempotency_window_hours > 0);
+
+                CREATE TABLE IF NOT EXISTS sale_idempotency (
+                    key TEXT PRIMARY KEY,
+                    sale_id INTEGER NOT NULL REFERENCES sales(id) ON DELETE CASCADE,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+                );
+                CREATE INDEX IF NOT EXISTS idx_sale_idempotency_created_at ON sale_idempotency(created_at);
+
+                DROP TRIGGER IF EXISTS pharmacy_settings_touch_updated_at;
+                CREATE TRIGGER pharmacy_settings_touch_updated_at AFTER UPDATE ON pharmacy_settings
+                FOR EACH ROW WHEN new.updated_at IS old.updated_at
+                    AND (old.name IS NOT new.name
+                         OR old.address IS NOT new.address
+                         OR old.phone IS NOT new.phone
+                         OR old.email IS NOT new.email
+                         OR old.gstin IS NOT new.gstin
+                         OR old.drug_license_no IS NOT new.drug_license_no
+                         OR old.state_code IS NOT new.state_code
+                         OR old.city IS NOT new.city
+                         OR old.pincode IS NOT new.pincode
+                         OR old.invoice_prefix IS NOT new.invoice_prefix
+                         OR old.low_stock_threshold IS NOT new.low_stock_threshold
+                         OR old.near_expiry_days IS NOT new.near_expiry_days
+                         OR old.einvoice_enabled IS NOT new.einvoice_enabled
+                         OR old.reorder_lead_time_days IS NOT new.reorder_lead_time_days
+                         OR old.prices_include_gst IS NOT new.prices_include_gst
+                         OR old.loyalty_spend_per_point_paise IS NOT new.loyalty_spend_per_point_paise
+                         OR old.loyalty_point_value_paise IS NOT new.loyalty_point_value_paise
+                         OR old.invoice_number_format IS NOT new.invoice_number_format
+                         OR old.fy_reset IS NOT new.fy_reset
+                         OR old.idempotency_window_hours IS NOT new.idempotency_window_hours)
+                BEGIN
+                    UPDATE pharmacy_settings SET updated_at = datetime('now') WHERE id = new.id;
+                END;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 31,
+            description: "add login sessions",
+            sql: r#"
+                CREATE TABLE IF NOT EXISTS sessions (
+                    token_hash TEXT PRIMARY KEY,
+                    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    expires_at TEXT NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS idx_sessions_user ON sessions(user_id);
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 32,
+            description: "add a role permission matrix",
+            sql: r#"
+                CREATE TABLE IF NOT EXISTS permissions (
+                    key TEXT PRIMARY KEY,
+                    description TEXT NOT NULL
+                );
+                CREATE TABLE IF NOT EXISTS role_permissions (
+                    role TEXT NOT NULL CHECK(role IN ('admin', 'pharmacist', 'cashier')),
+                    permission TEXT NOT NULL REFERENCES permissions(key) ON DELETE CASCADE,
+                    PRIMARY KEY (role, permission)
+                );
+
+                INSERT OR IGNORE INTO permissions (key, description) VALUES
+                    ('backup.restore', 'Restore the database from a backup'),
+                    ('dashboard.financials', 'See revenue and profit on the dashboard'),
+                    ('dashboard.stock', 'See expiry and prescription alerts on the dashboard'),
+                    ('inventory.reorder', 'Apply suggested reorder levels'),
+                    ('medicines.import', 'Import medicines from CSV'),
+                    ('purchases.receive', 'Receive purchases into stock'),
+                    ('sales.amend', 'Amend a recorded sale'),
+                    ('sales.refund', 'Take back sold items and issue a credit note'),
+                    ('users.permissions', 'Change what each role may do');
+
+                -- Each role starts with exactly the access it had before.
+                INSERT OR IGNORE INTO role_permissions (role, permission)
+                SELECT 'admin', key FROM permissions;
+                INSERT OR IGNORE INTO role_permissions (role, permission) VALUES
+                    ('pharmacist', 'dashboard.stock'),
+                    ('pharmacist', 'inventory.reorder'),
+                    ('pharmacist', 'medicines.import'),
+                    ('pharmacist', 'purchases.receive'),
+                    ('pharmacist', 'sales.amend'),
+                    ('pharmacist', 'sales.refund'),
+                    ('cashier', 'sales.refund');
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 33,
+            description: "audit logins and lock accounts after repeated failures",
+            sql: r#"
+                CREATE TABLE IF NOT EXISTS login_audit (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    username TEXT NOT NULL,
+                    user_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+                    success INTEGER NOT NULL,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+                );
+                CREATE INDEX IF NOT EXISTS idx_login_audit_user_created_at ON login_audit(user_id, created_at);
+
+                ALTER TABLE users ADD COLUMN locked_until TEXT;
+                ALTER TABLE pharmacy_settings ADD COLUMN lockout_max_attempts INTEGER NOT NULL DEFAULT 5
+                    CHECK(lockout_max_attempts > 0);
+                ALTER TABLE pharmacy_settings ADD COLUMN lockout_window_minutes INTEGER NOT NULL DEFAULT 15
+                    CHECK(lockout_window_minutes > 0);
+                ALTER TABLE pharmacy_settings ADD COLUMN lockout_minutes INTEGER NOT NULL DEFAULT 30
+                    CHECK(lockout_minutes > 0);
+
+                INSERT OR IGNORE INTO permissions (key, description)
+                VALUES ('users.unlock', 'Unlock an account locked after failed logins');
+                INSERT OR IGNORE INTO role_permissions (role, permission) VALUES ('admin', 'users.unlock');
+
+                DROP TRIGGER IF EXISTS pharmacy_settings_touch_updated_at;
+                CREATE TRIGGER pharmacy_settings_touch_updated_at AFTER UPDATE ON pharmacy_settings
+                FOR EACH ROW WHEN new.updated_at IS old.updated_at
+                    AND (old.name IS NOT new.name
+                         OR old.address IS NOT new.address
+                         OR old.phone IS NOT new.phone
+                         OR old.email IS NOT new.email
+                         OR old.gstin IS NOT new.gstin
+                         OR old.drug_license_no IS NOT new.drug_license_no
+                         OR old.state_code IS NOT new.state_code
+                         OR old.city IS NOT new.city
+                         OR old.pincode IS NOT new.pincode
+                         OR old.invoice_prefix IS NOT new.invoice_prefix
+                         OR old.low_stock_threshold IS NOT new.low_stock_threshold
+                         OR old.near_expiry_days IS NOT new.near_expiry_days
+                         OR old.einvoice_enabled IS NOT new.einvoice_enabled
+                         OR old.reorder_lead_time_days IS NOT new.reorder_lead_time_days
+                         OR old.prices_include_gst IS NOT new.prices_include_gst
+                         OR old.loyalty_spend_per_point_paise IS NOT new.loyalty_spend_per_point_paise
+                         OR old.loyalty_point_value_paise IS NOT new.loyalty_point_value_paise
+                         OR old.invoice_number_format IS NOT new.invoice_number_format
+                         OR old.fy_reset IS NOT new.fy_reset
+                         OR old.idempotency_window_hours IS NOT new.idempotency_window_hours
+                         OR old.lockout_max_attempts IS NOT new.lockout_max_attempts
+                         OR old.lockout_window_minutes IS NOT new.lockout_window_minutes
+                         OR old.lockout_minutes IS NOT new.lockout_minutes)
+                BEGIN
+                    UPDATE pharmacy_settings SET updated_at = datetime('now') WHERE id = new.id;
+                END;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 34,
+            description: "add an audit trail of data changes",
+            sql: r#"
+                CREATE TABLE IF NOT EXISTS audit_log (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    table_name TEXT NOT NULL,
+                    row_id INTEGER NOT NULL,
+                    action TEXT NOT NULL CHECK(action IN ('insert', 'update', 'delete')),
+                    user_id INTEGER,
+                    old_values TEXT,
+                    new_values TEXT,
+                    changed_at TEXT NOT NULL DEFAULT (datetime('now'))
+                );
+                CREATE INDEX IF NOT EXISTS idx_audit_log_table_changed_at ON audit_log(table_name, changed_at);
+                CREATE INDEX IF NOT EXISTS idx_audit_log_user_changed_at ON audit_log(user_id, changed_at);
+
+                INSERT OR IGNORE INTO permissions (key, description)
+                VALUES ('audit.view', 'Read the audit trail of data changes');
+                INSERT OR IGNORE INTO role_permissions (role, permission) VALUES ('admin', 'audit.view');
+
+                -- Only sales name the user behind a change; writes made through the SQL
+                -- plugin cannot say who made them. Password hashes are never copied, and
+                -- updates that only touch updated_at are not logged.
+                CREATE TRIGGER IF NOT EXISTS medicines_audit_insert AFTER INSERT ON medicines
+                FOR EACH ROW
+                BEGIN
+                    INSERT INTO audit_log (table_name, row_id, action, user_id, new_values)
+                    VALUES ('medicines', new.id, 'insert', NULL, json_object(
+                            'id', new.id, 'name', new.name, 'generic_name', new.generic_name,
+                            'brand_name', new.brand_name, 'manufacturer', new.manufacturer,
+                            'dosage_form', new.dosage_form, 'strength', new.strength,
+                            'category', new.category, 'hsn_code', new.hsn_code,
+                            'gst_slab_id', new.gst_slab_id, 'reorder_level', new.reorder_level,
+                            'is_active', new.is_active, 'schedule', new.schedule,
+                            'pack_size', new.pack_size, 'sale_unit', new.sale_unit));
+                END;
+                CREATE TRIGGER IF NOT EXISTS medicines_audit_update AFTER UPDATE ON medicines
+                FOR EACH ROW WHEN (old.name IS NOT new.name
+                         OR old.generic_name IS NOT new.generic_name
+                         OR old.brand_name IS NOT new.brand_name
+                         OR old.manufacturer IS NOT new.manufacturer
+                         OR old.dosage_form IS NOT new.dosage_form
+                         OR old.strength IS NOT new.strength
+                         OR old.category IS NOT new.category
+                         OR old.hsn_code IS NOT new.hsn_code
+                         OR old.gst_slab_id IS NOT new.gst_slab_id
+                         OR old.reorder_level IS NOT new.reorder_level
+                         OR old.is_active IS NOT new.is_active
+                         OR old.schedule IS NOT new.schedule
+                         OR old.pack_size IS NOT new.pack_size
+                         OR old.sale_unit IS NOT new.sale_unit)
+                BEGIN
+                    INSERT INTO audit_log (table_name, row_id, action, user_id, old_values, new_values)
+                    VALUES ('medicines', new.id, 'update', NULL,
+                        json_object(
+                            'id', old.id, 'name', old.name, 'generic_name', old.generic_name,
+                            'brand_name', old.brand_name, 'manufacturer', old.manufacturer,
+                            'dosage_form', old.dosage_form, 'strength', old.strength,
+                            'category', old.category, 'hsn_code', old.hsn_code,
+                            'gst_slab_id', old.gst_slab_id, 'reorder_level', old.reorder_level,
+                            'is_active', old.is_active, 'schedule', old.schedule,
+                            'pack_size', old.pack_size, 'sale_unit', old.sale_unit),
+                        json_object(
+                            'id', new.id, 'name', new.name, 'generic_name', new.generic_name,
+                            'brand_name', new.brand_name, 'manufacturer', new.manufacturer,
+                            'dosage_form', new.dosage_form, 'strength', new.strength,
+                            'category', new.category, 'hsn_code', new.hsn_code,
+                            'gst_slab_id', new.gst_slab_id, 'reorder_level', new.reorder_level,
+                            'is_active', new.is_active, 'schedule', new.schedule,
+                            'pack_size', new.pack_size, 'sale_unit', new.sale_unit));
+                END;
+                CREATE TRIGGER IF NOT EXISTS medicines_audit_delete AFTER DELETE ON medicines
+                FOR EACH ROW
+                BEGIN
+                    INSERT INTO audit_log (table_name, row_id, action, user_id, old_values)
+                    VALUES ('medicines', old.id, 'delete', NULL, json_object(
+                            'id', old.id, 'name', old.name, 'generic_name', old.generic_name,
+                            'brand_name', old.brand_name, 'manufacturer', old.manufacturer,
+                            'dosage_form', old.dosage_form, 'strength', old.strength,
+                            'category', old.category, 'hsn_code', old.hsn_code,
+                            'gst_slab_id', old.gst_slab_id, 'reorder_level', old.reorder_level,
+                            'is_active', old.is_active, 'schedule', old.schedule,
+                            'pack_size', old.pack_size, 'sale_unit', old.sale_unit));
+                END;
+                CREATE TRIGGER IF NOT EXISTS batches_audit_insert AFTER INSERT ON batches
+                FOR EACH ROW
+                BEGIN
+                    INSERT INTO audit_log (table_name, row_id, action, user_id, new_values)
+                    VALUES ('batches', new.id, 'insert', NULL, json_object(
+                            'id', new.id, 'medicine_id', new.medicine_id,
+                            'batch_number', new.batch_number, 'expiry_date', new.expiry_date,
+                            'cost_price_paise', new.cost_price_paise, 'mrp_paise', new.mrp_paise,
+                            'selling_price_paise', new.selling_price_paise, 'quantity', new.quantity,
+                            'manufacturing_date', new.manufacturing_date, 'status', new.status,
+                            'barcode', new.barcode, 'branch_id', new.branch_id));
+                END;
+                CREATE TRIGGER IF NOT EXISTS batches_audit_update AFTER UPDATE ON batches
+                FOR EACH ROW WHEN (old.medicine_id IS NOT new.medicine_id
+                         OR old.batch_number IS NOT new.batch_number
+                         OR old.expiry_date IS NOT new.expiry_date
+                         OR old.cost_price_paise IS NOT new.cost_price_paise
+                         OR old.mrp_paise IS NOT new.mrp_paise
+                         OR old.selling_price_paise IS NOT new.selling_price_paise
+                         OR old.quantity IS NOT new.quantity
+                         OR old.manufacturing_date IS NOT new.manufacturing_date
+                         OR old.status IS NOT new.status
+                         OR old.barcode IS NOT new.barcode
+                         OR old.branch_id IS NOT new.branch_id)
+                BEGIN
+                    INSERT INTO audit_log (table_name, row_id, action, user_id, old_values, new_values)
+                    VALUES ('batches', new.id, 'update', NULL,
+                        json_object(
+                            'id', old.id, 'medicine_id', old.medicine_id,
+                            'batch_number', old.batch_number, 'expiry_date', old.expiry_date,
+                            'cost_price_paise', old.cost_price_paise, 'mrp_paise', old.mrp_paise,
+                            'selling_price_paise', old.selling_price_paise, 'quantity', old.quantity,
+                            'manufacturing_date', old.manufacturing_date, 'status', old.status,
+                            'barcode', old.barcode, 'branch_id', old.branch_id),
+                        json_object(
+                            'id', new.id, 'medicine_id', new.medicine_id,
+                            'batch_number', new.batch_number, 'expiry_date', new.expiry_date,
+                            'cost_price_paise', new.cost_price_paise, 'mrp_paise', new.mrp_paise,
+                            'selling_price_paise', new.selling_price_paise, 'quantity', new.quantity,
+                            'manufacturing_date', new.manufacturing_date, 'status', new.status,
+                            'barcode', new.barcode, 'branch_id', new.branch_id));
+                END;
+                CREATE TRIGGER IF NOT EXISTS batches_audit_delete AFTER DELETE ON batches
+                FOR EACH ROW
+                BEGIN
+                    INSERT INTO audit_log (table_name, row_id, action, user_id, old_values)
+                    VALUES ('batches', old.id, 'delete', NULL, json_object(
+                            'id', old.id, 'medicine_id', old.medicine_id,
+                            'batch_number', old.batch_number, 'expiry_date', old.expiry_date,
+                            'cost_price_paise', old.cost_price_paise, 'mrp_paise', old.mrp_paise,
+                            'selling_price_paise', old.selling_price_paise, 'quantity', old.quantity,
+                            'manufacturing_date', old.manufacturing_date, 'status', old.status,
+                            'barcode', old.barcode, 'branch_id', old.branch_id));
+                END;
+                CREATE TRIGGER IF NOT EXISTS sales_audit_insert AFTER INSERT ON sales
+                FOR EACH ROW
+                BEGIN
+                    INSERT INTO audit_log (table_name, row_id, action, user_id, new_values)
+                    VALUES ('sales', new.id, 'insert', new.user_id, json_object(
+                            'id', new.id, 'invoice_number', new.invoice_number,
+                            'customer_id', new.customer_id, 'user_id', new.user_id,
+                            'sale_date', new.sale_date, 'subtotal_paise', new.subtotal_paise,
+                            'discount_paise', new.discount_paise,
+                            'total_cgst_paise', new.total_cgst_paise,
+                            'total_sgst_paise', new.total_sgst_paise,
+                            'total_igst_paise', new.total_igst_paise,
+                            'total_gst_paise', new.total_gst_paise,
+                            'round_off_paise', new.round_off_paise,
+                            'grand_total_paise', new.grand_total_paise,
+                            'payment_mode', new.payment_mode, 'notes', new.notes,
+                            'status', new.status, 'is_interstate', new.is_interstate,
+                            'branch_id', new.branch_id));
+                END;
+                CREATE TRIGGER IF NOT EXISTS sales_audit_update AFTER UPDATE ON sales
+                FOR EACH ROW WHEN (old.invoice_number IS NOT new.invoice_number
+                         OR old.customer_id IS NOT new.customer_id
+                         OR old.user_id IS NOT new.user_id
+                         OR old.sale_date IS NOT new.sale_date
+                         OR old.subtotal_paise IS NOT new.subtotal_paise
+                         OR old.discount_paise IS NOT new.discount_paise
+                         OR old.total_cgst_paise IS NOT new.total_cgst_paise
+                         OR old.total_sgst_paise IS NOT new.total_sgst_paise
+                         OR old.total_igst_paise IS NOT new.total_igst_paise
+                         OR old.total_gst_paise IS NOT new.total_gst_paise
+                         OR old.round_off_paise IS NOT new.round_off_paise
+                         OR old.grand_total_paise IS NOT new.grand_total_paise
+                         OR old.payment_mode IS NOT new.payment_mode
+                         OR old.notes IS NOT new.notes
+                         OR old.status IS NOT new.status
+                         OR old.is_interstate IS NOT new.is_interstate
+                         OR old.branch_id IS NOT new.branch_id)
+                BEGIN
+                    INSERT INTO audit_log (table_name, row_id, action, user_id, old_values, new_values)
+                    VALUES ('sales', new.id, 'update', new.user_id,
+                        json_object(
+                            'id', old.id, 'invoice_number', old.invoice_number,
+                            'customer_id', old.customer_id, 'user_id', old.user_id,
+                            'sale_date', old.sale_date, 'subtotal_paise', old.subtotal_paise,
+                            'discount_paise', old.discount_paise,
+                            'total_cgst_paise', old.total_cgst_paise,
+                            'total_sgst_paise', old.total_sgst_paise,
+                            'total_igst_paise', old.total_igst_paise,
+                            'total_gst_paise', old.total_gst_paise,
+                            'round_off_paise', old.round_off_paise,
+                            'grand_total_paise', old.grand_total_paise,
+                            'payment_mode', old.payment_mode, 'notes', old.notes,
+                            'status', old.status, 'is_interstate', old.is_interstate,
+                            'branch_id', old.branch_id),
+                        json_object(
+                            'id', new.id, 'invoice_number', new.invoice_number,
+                            'customer_id', new.customer_id, 'user_id', new.user_id,
+                            'sale_date', new.sale_date, 'subtotal_paise', new.subtotal_paise,
+                            'discount_paise', new.discount_paise,
+                            'total_cgst_paise', new.total_cgst_paise,
+                            'total_sgst_paise', new.total_sgst_paise,
+                            'total_igst_paise', new.total_igst_paise,
+                            'total_gst_paise', new.total_gst_paise,
+                            'round_off_paise', new.round_off_paise,
+                            'grand_total_paise', new.grand_total_paise,
+                            'payment_mode', new.payment_mode, 'notes', new.notes,
+                            'status', new.status, 'is_interstate', new.is_interstate,
+                            'branch_id', new.branch_id));
+                END;
+                CREATE TRIGGER IF NOT EXISTS sales_audit_delete AFTER DELETE ON sales
+                FOR EACH ROW
+                BEGIN
+                    INSERT INTO audit_log (table_name, row_id, action, user_id, old_values)
+                    VALUES ('sales', old.id, 'delete', old.user_id, json_object(
+                            'id', old.id, 'invoice_number', old.invoice_number,
+                            'customer_id', old.customer_id, 'user_id', old.user_id,
+                            'sale_date', old.sale_date, 'subtotal_paise', old.subtotal_paise,
+                            'discount_paise', old.discount_paise,
+                            'total_cgst_paise', old.total_cgst_paise,
+                            'total_sgst_paise', old.total_sgst_paise,
+                            'total_igst_paise', old.total_igst_paise,
+                            'total_gst_paise', old.total_gst_paise,
+                            'round_off_paise', old.round_off_paise,
+                            'grand_total_paise', old.grand_total_paise,
+                            'payment_mode', old.payment_mode, 'notes', old.notes,
+                            'status', old.status, 'is_interstate', old.is_interstate,
+                            'branch_id', old.branch_id));
+                END;
+                CREATE TRIGGER IF NOT EXISTS users_audit_insert AFTER INSERT ON users
+                FOR EACH ROW
+                BEGIN
+                    INSERT INTO audit_log (table_name, row_id, action, user_id, new_values)
+                    VALUES ('users', new.id, 'insert', NULL, json_object(
+                            'id', new.id, 'username', new.username, 'full_name', new.full_name,
+                            'role', new.role, 'is_active', new.is_active, 'branch_id', new.branch_id,
+                            'locked_until', new.locked_until));
+                END;
+                CREATE TRIGGER IF NOT EXISTS users_audit_update AFTER UPDATE ON users
+                FOR EACH ROW WHEN (old.username IS NOT new.username
+                         OR old.full_name IS NOT new.full_name
+                         OR old.role IS NOT new.role
+                         OR old.is_active IS NOT new.is_active
+                         OR old.branch_id IS NOT new.branch_id
+                         OR old.locked_until IS NOT new.locked_until
+                         OR old.password_hash IS NOT new.password_hash)
+                BEGIN
+                    INSERT INTO audit_log (table_name, row_id, action, user_id, old_values, new_values)
+                    VALUES ('users', new.id, 'update', NULL,
+                        json_object(
+                            'id', old.id, 'username', old.username, 'full_name', old.full_name,
+                            'role', old.role, 'is_active', old.is_active, 'branch_id', old.branch_id,
+                            'locked_until', old.locked_until),
+                        json_object(
+                            'id', new.id, 'username', new.username, 'full_name', new.full_name,
+                            'role', new.role, 'is_active', new.is_active, 'branch_id', new.branch_id,
+                            'locked_until', new.locked_until));
+                END;
+                CREATE TRIGGER IF NOT EXISTS users_audit_delete AFTER DELETE ON users
+                FOR EACH ROW
+                BEGIN
+                    INSERT INTO audit_log (table_name, row_id, action, user_id, old_values)
+                    VALUES ('users', old.id, 'delete', NULL, json_object(
+                            'id', old.id, 'username', old.username, 'full_name', old.full_name,
+                            'role', old.role, 'is_active', old.is_active, 'branch_id', old.branch_id,
+                            'locked_until', old.locked_until));
+                END;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 35,
+            description: "add purchase orders",
+            sql: r#"
+                CREATE TABLE IF NOT EXISTS purchase_orders (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    po_number TEXT NOT NULL UNIQUE,
+                    supplier_id INTEGER NOT NULL REFERENCES suppliers(id),
+                    status TEXT NOT NULL DEFAULT 'draft'
+                        CHECK(status IN ('draft', 'approved', 'sent', 'partially_received', 'closed')),
+                    notes TEXT,
+                    created_by INTEGER REFERENCES users(id),
+                    approved_by INTEGER REFERENCES users(id),
+                    approved_at TEXT,
+                    sent_at TEXT,
+                    closed_at TEXT,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+                );
+                CREATE INDEX IF NOT EXISTS idx_purchase_orders_supplier_status ON purchase_orders(supplier_id, status);
+
+                CREATE TABLE IF NOT EXISTS purchase_order_items (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    purchase_order_id INTEGER NOT NULL REFERENCES purchase_orders(id) ON DELETE CASCADE,
+                    medicine_id INTEGER NOT NULL REFERENCES medicines(id),
+                    quantity INTEGER NOT NULL CHECK(quantity > 0),
+                    received_quantity INTEGER NOT NULL DEFAULT 0 CHECK(received_quantity >= 0),
+                    expected_cost_paise INTEGER NOT NULL DEFAULT 0 CHECK(expected_cost_paise >= 0),
+                    UNIQUE (purchase_order_id, medicine_id)
+                );
+
+                ALTER TABLE purchases ADD COLUMN purchase_order_id INTEGER REFERENCES purchase_orders(id);
+
+                INSERT OR IGNORE INTO permissions (key, description) VALUES
+                    ('purchases.order', 'Raise, send and close purchase orders'),
+                    ('purchases.approve', 'Approve purchase orders');
+                INSERT OR IGNORE INTO role_permissions (role, permission) VALUES
+                    ('admin', 'purchases.order'),
+                    ('admin', 'purchases.approve'),
+                    ('pharmacist', 'purchases.order');
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 36,
+            description: "keep the lines of received purchases",
+            sql: r#"
+                CREATE TABLE IF NOT EXISTS purchase_items (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    purchase_id INTEGER NOT NULL REFERENCES purchases(id) ON DELETE CASCADE,
+                    batch_id INTEGER NOT NULL REFERENCES batches(id),
+                    medicine_id INTEGER NOT NULL REFERENCES medicines(id),
+                    quantity INTEGER NOT NULL CHECK(quantity > 0),
+                    cost_price_paise INTEGER NOT NULL CHECK(cost_price_paise >= 0),
+                    mrp_paise INTEGER NOT NULL CHECK(mrp_paise > 0),
+                    selling_price_paise INTEGER NOT NULL CHECK(selling_price_paise > 0),
+                    purchase_order_item_id INTEGER REFERENCES purchase_order_items(id)
+                );
+                CREATE INDEX IF NOT EXISTS idx_purchase_items_purchase ON purchase_items(purchase_id);
+                CREATE INDEX IF NOT EXISTS idx_purchase_items_batch ON purchase_items(batch_id);
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 37,
+            description: "number credit notes and record returns left off the shelf",
+            sql: r#"
+                -- Credit notes take their own series, in the invoice number format
+                -- with a prefix of their own. Returns made before there was a
+                -- series are numbered in the order they were taken.
+                ALTER TABLE pharmacy_settings ADD COLUMN credit_note_prefix TEXT NOT NULL DEFAULT 'CN';
+                ALTER TABLE pharmacy_settings ADD COLUMN next_credit_note_number INTEGER NOT NULL DEFAULT 1;
+                ALTER TABLE pharmacy_settings ADD COLUMN credit_note_sequence_fy TEXT;
+
+                ALTER TABLE sale_returns ADD COLUMN credit_note_number TEXT;
+                UPDATE sale_returns SET credit_note_number = 'CN-' || printf('%06d', id);
+                UPDATE pharmacy_settings
+                SET next_credit_note_number = (SELECT COALESCE(MAX(id), 0) + 1 FROM sale_returns);
+                CREATE UNIQUE INDEX IF NOT EXISTS idx_sale_returns_credit_note
+                    ON sale_returns(credit_note_number);
+
+                -- Units that came back damaged or unfit are credited but not put
+                -- back into their batch.
+                ALTER TABLE sale_return_items ADD COLUMN restocked INTEGER NOT NULL DEFAULT 1;
+
+                DROP TRIGGER IF EXISTS pharmacy_settings_touch_updated_at;
+                CREATE TRIGGER pharmacy_settings_touch_updated_at AFTER UPDATE ON pharmacy_settings
+                FOR EACH ROW WHEN new.updated_at IS old.updated_at
+                    AND (old.name IS NOT new.name
+                         OR old.address IS NOT new.address
+                         OR old.phone IS NOT new.phone
+                         OR old.email IS NOT new.email
+                         OR old.gstin IS NOT new.gstin
+                         OR old.drug_license_no IS NOT new.drug_license_no
+                         OR old.state_code IS NOT new.state_code
+                         OR old.city IS NOT new.city
+                         OR old.pincode IS NOT new.pincode
+                         OR old.invoice_prefix IS NOT new.invoice_prefix
+                         OR old.credit_note_prefix IS NOT new.credit_note_prefix
+                         OR old.low_stock_threshold IS NOT new.low_stock_threshold
+                         OR old.near_expiry_days IS NOT new.near_expiry_days
+                         OR old.einvoice_enabled IS NOT new.einvoice_enabled
+                         OR old.reorder_lead_time_days IS NOT new.reorder_lead_time_days
+                         OR old.prices_include_gst IS NOT new.prices_include_gst
+                         OR old.loyalty_spend_per_point_paise IS NOT new.loyalty_spend_per_point_paise
+                         OR old.loyalty_point_value_paise IS NOT new.loyalty_point_value_paise
+                         OR old.invoice_number_format IS NOT new.invoice_number_format
+                         OR old.fy_reset IS NOT new.fy_reset
+                         OR old.idempotency_window_hours IS NOT new.idempotency_window_hours
+                         OR old.lockout_max_attempts IS NOT new.lockout_max_attempts
+                         OR old.lockout_window_minutes IS NOT new.lockout_window_minutes
+                         OR old.lockout_minutes IS NOT new.lockout_minutes)
+                BEGIN
+                    UPDATE pharmacy_settings SET updated_at = datetime('now') WHERE id = new.id;
+                END;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 38,
+            description: "add returns to suppliers as debit notes",
+            sql: r#"
+                ALTER TABLE pharmacy_settings ADD COLUMN debit_note_prefix TEXT NOT NULL DEFAULT 'DN';
+                ALTER TABLE pharmacy_settings ADD COLUMN next_debit_note_number INTEGER NOT NULL DEFAULT 1;
+                ALTER TABLE pharmacy_settings ADD COLUMN debit_note_sequence_fy TEXT;
+
+                CREATE TABLE IF NOT EXISTS purchase_returns (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    debit_note_number TEXT NOT NULL UNIQUE,
+                    supplier_id INTEGER NOT NULL REFERENCES suppliers(id),
+                    reason TEXT NOT NULL
+                        CHECK(reason IN ('near_expiry', 'expired', 'damaged', 'other')),
+                    notes TEXT,
+                    taxable_amount_paise INTEGER NOT NULL DEFAULT 0,
+                    total_cgst_paise INTEGER NOT NULL DEFAULT 0,
+                    total_sgst_paise INTEGER NOT NULL DEFAULT 0,
+                    total_igst_paise INTEGER NOT NULL DEFAULT 0,
+                    total_gst_paise INTEGER NOT NULL DEFAULT 0,
+                    total_paise INTEGER NOT NULL DEFAULT 0,
+                    -- Pending until the supplier issues a credit note against it.
+                    status TEXT NOT NULL DEFAULT 'pending' CHECK(status IN ('pending', 'credited')),
+                    credit_reference TEXT,
+                    credited_at TEXT,
+                    user_id INTEGER NOT NULL REFERENCES users(id),
+                    return_date TEXT NOT NULL DEFAULT (date('now')),
+                    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+                );
+
+                CREATE TABLE IF NOT EXISTS purchase_return_items (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    return_id INTEGER NOT NULL REFERENCES purchase_returns(id) ON DELETE CASCADE,
+                    batch_id INTEGER NOT NULL REFERENCES batches(id),
+                    medicine_id INTEGER NOT NULL REFERENCES medicines(id),
+                    quantity INTEGER NOT NULL CHECK(quantity > 0),
+                    cost_price_paise INTEGER NOT NULL,
+                    gst_rate REAL NOT NULL,
+                    taxable_amount_paise INTEGER NOT NULL,
+                    cgst_amount_paise INTEGER NOT NULL DEFAULT 0,
+                    sgst_amount_paise INTEGER NOT NULL DEFAULT 0,
+                    igst_amount_paise INTEGER NOT NULL DEFAULT 0,
+                    total_paise INTEGER NOT NULL
+                );
+
+                CREATE INDEX IF NOT EXISTS idx_purchase_returns_supplier
+                    ON purchase_returns(supplier_id, status);
+                CREATE INDEX IF NOT EXISTS idx_purchase_return_items_return
+                    ON purchase_return_items(return_id);
+
+                DROP TRIGGER IF EXISTS pharmacy_settings_touch_updated_at;
+                CREATE TRIGGER pharmacy_settings_touch_updated_at AFTER UPDATE ON pharmacy_settings
+                FOR EACH ROW WHEN new.updated_at IS old.updated_at
+                    AND (old.name IS NOT new.name
+                         OR old.address IS NOT new.address
+                         OR old.phone IS NOT new.phone
+                         OR old.email IS NOT new.email
+                         OR old.gstin IS NOT new.gstin
+                         OR old.drug_license_no IS NOT new.drug_license_no
+                         OR old.state_code IS NOT new.state_code
+                         OR old.city IS NOT new.city
+                         OR old.pincode IS NOT new.pincode
+                         OR old.invoice_prefix IS NOT new.invoice_prefix
+                         OR old.credit_note_prefix IS NOT new.credit_note_prefix
+                         OR old.debit_note_prefix IS NOT new.debit_note_prefix
+                         OR old.low_stock_threshold IS NOT new.low_stock_threshold
+                         OR old.near_expiry_days IS NOT new.near_expiry_days
+                         OR old.einvoice_enabled IS NOT new.einvoice_enabled
+                         OR old.reorder_lead_time_days IS NOT new.reorder_lead_time_days
+                         OR old.prices_include_gst IS NOT new.prices_include_gst
+                         OR old.loyalty_spend_per_point_paise IS NOT new.loyalty_spend_per_point_paise
+                         OR old.loyalty_point_value_paise IS NOT new.loyalty_point_value_paise
+                         OR old.invoice_number_format IS NOT new.invoice_number_format
+                         OR old.fy_reset IS NOT new.fy_reset
+                         OR old.idempotency_window_hours IS NOT new.idempotency_window_hours
+                         OR old.lockout_max_attempts IS NOT new.lockout_max_attempts
+                         OR old.lockout_window_minutes IS NOT new.lockout_window_minutes
+                         OR old.lockout_minutes IS NOT new.lockout_minutes)
+                BEGIN
+                    UPDATE pharmacy_settings SET updated_at = datetime('now') WHERE id = new.id;
+                END;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 39,
+            description: "add stock adjustments with reason codes",
+            sql: r#"
+                CREATE TABLE IF NOT EXISTS stock_adjustments (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    batch_id INTEGER NOT NULL REFERENCES batches(id),
+                    medicine_id INTEGER NOT NULL REFERENCES medicines(id),
+                    reason_code TEXT NOT NULL
+                        CHECK(reason_code IN ('breakage', 'theft', 'count_error', 'other')),
+                    remarks TEXT NOT NULL,
+                    previous_quantity INTEGER NOT NULL,
+                    new_quantity INTEGER NOT NULL CHECK(new_quantity >= 0),
+                    user_id INTEGER NOT NULL REFERENCES users(id),
+                    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+                );
+
+                CREATE INDEX IF NOT EXISTS idx_stock_adjustments_batch ON stock_adjustments(batch_id);
+                CREATE INDEX IF NOT EXISTS idx_stock_adjustments_created ON stock_adjustments(created_at);
+
+                INSERT OR IGNORE INTO permissions (key, description)
+                VALUES ('inventory.adjust', 'Write off or correct batch quantities');
+                INSERT OR IGNORE INTO role_permissions (role, permission) VALUES
+                    ('admin', 'inventory.adjust'),
+                    ('pharmacist', 'inventory.adjust');
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 40,
+            description: "add stock takes",
+            sql: r#"
+                CREATE TABLE IF NOT EXISTS stock_takes (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    -- NULL counts every branch.
+                    branch_id INTEGER REFERENCES branches(id),
+                    status TEXT NOT NULL DEFAULT 'open' CHECK(status IN ('open', 'finalized')),
+                    notes TEXT,
+                    started_by INTEGER NOT NULL REFERENCES users(id),
+                    started_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    finalized_by INTEGER REFERENCES users(id),
+                    finalized_at TEXT
+                );
+
+                -- expected_quantity is the batch as it stood when the take began;
+                -- sales made during the count are allowed for when it is posted.
+                CREATE TABLE IF NOT EXISTS stock_take_lines (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    stock_take_id INTEGER NOT NULL REFERENCES stock_takes(id) ON DELETE CASCADE,
+                    batch_id INTEGER NOT NULL REFERENCES batches(id),
+                    expected_quantity INTEGER NOT NULL,
+                    counted_quantity INTEGER CHECK(counted_quantity >= 0),
+                    counted_by INTEGER REFERENCES users(id),
+                    counted_at TEXT,
+                    adjustment_id INTEGER REFERENCES stock_adjustments(id),
+                    UNIQUE (stock_take_id, batch_id)
+                );
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 41,
+            description: "add stock locations within a branch",
+            sql: r#"
+                -- Each branch has one selling location, the shop floor, created with
+                -- the branch. Other locations, such as a back store, hold stock that
+                -- cannot be sold until it is moved to the floor.
+                CREATE TABLE IF NOT EXISTS locations (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    branch_id INTEGER NOT NULL REFERENCES branches(id),
+                    name TEXT NOT NULL,
+                    is_selling INTEGER NOT NULL DEFAULT 0,
+                    is_active INTEGER NOT NULL DEFAULT 1,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    UNIQUE (branch_id, name)
+                );
+                CREATE UNIQUE INDEX IF NOT EXISTS idx_locations_selling
+                    ON locations(branch_id) WHERE is_selling = 1;
+
+                INSERT INTO locations (branch_id, name, is_selling)
+                SELECT id, 'Shop floor', 1 FROM branches;
+
+                CREATE TRIGGER IF NOT EXISTS branches_add_selling_location AFTER INSERT ON branches
+                FOR EACH ROW
+                BEGIN
+                    INSERT INTO locations (branch_id, name, is_selling) VALUES (new.id, 'Shop floor', 1);
+                END;
+
+                -- Stock away from the selling floor. What is on the floor is the
+                -- batch quantity less these rows, so everything that books stock in
+                -- or sells it keeps working on batches.quantity alone.
+                CREATE TABLE IF NOT EXISTS batch_locations (
+                    batch_id INTEGER NOT NULL REFERENCES batches(id) ON DELETE CASCADE,
+                    location_id INTEGER NOT NULL REFERENCES locations(id),
+                    quantity INTEGER NOT NULL CHECK(quantity > 0),
+                    PRIMARY KEY (batch_id, location_id)
+                );
+                CREATE INDEX IF NOT EXISTS idx_batch_locations_location ON batch_locations(location_id);
+
+                CREATE TRIGGER IF NOT EXISTS batches_keep_located_stock
+                BEFORE UPDATE OF quantity ON batches
+                FOR EACH ROW WHEN new.quantity <
+                    (SELECT COALESCE(SUM(quantity), 0) FROM batch_locations WHERE batch_id = new.id)
+                BEGIN
+                    SELECT RAISE(ABORT, 'Stock held away from the selling floor must be moved back before it is taken out');
+                END;
+
+                CREATE TABLE IF NOT EXISTS location_transfers (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    batch_id INTEGER NOT NULL REFERENCES batches(id),
+                    from_location_id INTEGER NOT NULL REFERENCES locations(id),
+                    to_location_id INTEGER NOT NULL REFERENCES locations(id),
+                    quantity INTEGER NOT NULL CHECK(quantity > 0),
+                    user_id INTEGER NOT NULL REFERENCES users(id),
+                    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+                );
+                CREATE INDEX IF NOT EXISTS idx_location_transfers_batch ON location_transfers(batch_id);
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 42,
+            description: "add thermal receipt printer settings",
+            sql: r#"
+                -- 'usb' and 'serial' printers are written to as a device file
+                -- (/dev/usb/lp0, /dev/ttyUSB0, COM3); 'network' ones at
+                -- host:port, usually port 9100.
+                ALTER TABLE pharmacy_settings ADD COLUMN printer_interface TEXT NOT NULL DEFAULT 'none'
+                    CHECK(printer_interface IN ('none', 'usb', 'serial', 'network'));
+                ALTER TABLE pharmacy_settings ADD COLUMN printer_address TEXT NOT NULL DEFAULT '';
+                -- Characters per line: 32 on 58 mm paper, 48 on 80 mm.
+                ALTER TABLE pharmacy_settings ADD COLUMN printer_line_width INTEGER NOT NULL DEFAULT 48
+                    CHECK(printer_line_width BETWEEN 24 AND 64);
+
+                DROP TRIGGER IF EXISTS pharmacy_settings_touch_updated_at;
+                CREATE TRIGGER pharmacy_settings_touch_updated_at AFTER UPDATE ON pharmacy_settings
+                FOR EACH ROW WHEN new.updated_at IS old.updated_at
+                    AND (old.name IS NOT new.name
+                         OR old.address IS NOT new.address
+                         OR old.phone IS NOT new.phone
+                         OR old.email IS NOT new.email
+                         OR old.gstin IS NOT new.gstin
+                         OR old.drug_license_no IS NOT new.drug_license_no
+                         OR old.state_code IS NOT new.state_code
+                         OR old.city IS NOT new.city
+                         OR old.pincode IS NOT new.pincode
+                         OR old.invoice_prefix IS NOT new.invoice_prefix
+                         OR old.credit_note_prefix IS NOT new.credit_note_prefix
+                         OR old.debit_note_prefix IS NOT new.debit_note_prefix
+                         OR old.low_stock_threshold IS NOT new.low_stock_threshold
+                         OR old.near_expiry_days IS NOT new.near_expiry_days
+                         OR old.einvoice_enabled IS NOT new.einvoice_enabled
+                         OR old.reorder_lead_time_days IS NOT new.reorder_lead_time_days
+                         OR old.prices_include_gst IS NOT new.prices_include_gst
+                         OR old.loyalty_spend_per_point_paise IS NOT new.loyalty_spend_per_point_paise
+                         OR old.loyalty_point_value_paise IS NOT new.loyalty_point_value_paise
+                         OR old.invoice_number_format IS NOT new.invoice_number_format
+                         OR old.fy_reset IS NOT new.fy_reset
+                         OR old.idempotency_window_hours IS NOT new.idempotency_window_hours
+                         OR old.lockout_max_attempts IS NOT new.lockout_max_attempts
+                         OR old.lockout_window_minutes IS NOT new.lockout_window_minutes
+                         OR old.lockout_minutes IS NOT new.lockout_minutes
+                         OR old.printer_interface IS NOT new.printer_interface
+                         OR old.printer_address IS NOT new.printer_address
+                         OR old.printer_line_width IS NOT new.printer_line_width)
+                BEGIN
+                    UPDATE pharmacy_settings SET updated_at = datetime('now') WHERE id = new.id;
+                END;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 43,
+            description: "add medicine GTINs for product barcode scans",
+            sql: r#"
+                -- The pack's EAN-13 or GTIN as printed. Codes are compared
+                -- zero-padded to the 14 digits of a GTIN-14, so an EAN-13 and
+                -- the GTIN-14 in a GS1 DataMatrix of the same pack match.
+                ALTER TABLE medicines ADD COLUMN gtin TEXT
+                    CHECK(gtin IS NULL OR (length(gtin) BETWEEN 8 AND 14 AND gtin NOT GLOB '*[^0-9]*'));
+                CREATE UNIQUE INDEX IF NOT EXISTS idx_medicines_gtin
+                    ON medicines(substr('00000000000000' || gtin, -14)) WHERE gtin IS NOT NULL;
+
+                DROP TRIGGER IF EXISTS medicines_touch_updated_at;
+                CREATE TRIGGER medicines_touch_updated_at AFTER UPDATE ON medicines
+                FOR EACH ROW WHEN new.updated_at IS old.updated_at
+                    AND (old.name IS NOT new.name
+                         OR old.generic_name IS NOT new.generic_name
+                         OR old.brand_name IS NOT new.brand_name
+                         OR old.manufacturer IS NOT new.manufacturer
+                         OR old.dosage_form IS NOT new.dosage_form
+                         OR old.strength IS NOT new.strength
+                         OR old.category IS NOT new.category
+                         OR old.hsn_code IS NOT new.hsn_code
+                         OR old.gst_slab_id IS NOT new.gst_slab_id
+                         OR old.reorder_level IS NOT new.reorder_level
+                         OR old.is_active IS NOT new.is_active
+                         OR old.schedule IS NOT new.schedule
+                         OR old.pack_size IS NOT new.pack_size
+                         OR old.sale_unit IS NOT new.sale_unit
+                         OR old.gtin IS NOT new.gtin)
+                BEGIN
+                    UPDATE medicines SET updated_at = datetime('now') WHERE id = new.id;
+                END;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 44,
+            description: "add scheduled local backup settings",
+            sql: r#"
+                -- A snapshot is taken once a day at auto_backup_time, local
+                -- time, or at the next launch when the app was closed then.
+                ALTER TABLE pharmacy_settings ADD COLUMN auto_backup_enabled INTEGER NOT NULL DEFAULT 1
+                    CHECK(auto_backup_enabled IN (0, 1));
+                ALTER TABLE pharmacy_settings ADD COLUMN auto_backup_time TEXT NOT NULL DEFAULT '21:00'
+                    CHECK(auto_backup_time GLOB '[0-2][0-9]:[0-5][0-9]' AND auto_backup_time < '24:00');
+                -- Older snapshots beyond this many are deleted.
+                ALTER TABLE pharmacy_settings ADD COLUMN auto_backup_keep INTEGER NOT NULL DEFAULT 14
+                    CHECK(auto_backup_keep BETWEEN 1 AND 365);
+                -- NULL keeps snapshots in a `backups` folder beside the database.
+                ALTER TABLE pharmacy_settings ADD COLUMN auto_backup_dir TEXT;
+
+                DROP TRIGGER IF EXISTS pharmacy_settings_touch_updated_at;
+                CREATE TRIGGER pharmacy_settings_touch_updated_at AFTER UPDATE ON pharmacy_settings
+                FOR EACH ROW WHEN new.updated_at IS old.updated_at
+                    AND (old.name IS NOT new.name
+                         OR old.address IS NOT new.address
+                         OR old.phone IS NOT new.phone
+                         OR old.email IS NOT new.email
+                         OR old.gstin IS NOT new.gstin
+                         OR old.drug_license_no IS NOT new.drug_license_no
+                         OR old.state_code IS NOT new.state_code
+                         OR old.city IS NOT new.city
+                         OR old.pincode IS NOT new.pincode
+                         OR old.invoice_prefix IS NOT new.invoice_prefix
+                         OR old.credit_note_prefix IS NOT new.credit_note_prefix
+                         OR old.debit_note_prefix IS NOT new.debit_note_prefix
+                         OR old.low_stock_threshold IS NOT new.low_stock_threshold
+                         OR old.near_expiry_days IS NOT new.near_expiry_days
+                         OR old.einvoice_enabled IS NOT new.einvoice_enabled
+                         OR old.reorder_lead_time_days IS NOT new.reorder_lead_time_days
+                         OR old.prices_include_gst IS NOT new.prices_include_gst
+                         OR old.loyalty_spend_per_point_paise IS NOT new.loyalty_spend_per_point_paise
+                         OR old.loyalty_point_value_paise IS NOT new.loyalty_point_value_paise
+                         OR old.invoice_number_format IS NOT new.invoice_number_format
+                         OR old.fy_reset IS NOT new.fy_reset
+                         OR old.idempotency_window_hours IS NOT new.idempotency_window_hours
+                         OR old.lockout_max_attempts IS NOT new.lockout_max_attempts
+                         OR old.lockout_window_minutes IS NOT new.lockout_window_minutes
+                         OR old.lockout_minutes IS NOT new.lockout_minutes
+                         OR old.printer_interface IS NOT new.printer_interface
+                         OR old.printer_address IS NOT new.printer_address
+                         OR old.printer_line_width IS NOT new.printer_line_width
+                         OR old.auto_backup_enabled IS NOT new.auto_backup_enabled
+                         OR old.auto_backup_time IS NOT new.auto_backup_time
+                         OR old.auto_backup_keep IS NOT new.auto_backup_keep
+                         OR old.auto_backup_dir IS NOT new.auto_backup_dir)
+                BEGIN
+                    UPDATE pharmacy_settings SET updated_at = datetime('now') WHERE id = new.id;
+                END;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 45,
+            description: "add remote backup target and upload status",
+            sql: r#"
+                -- Each new scheduled snapshot is uploaded as an encrypted
+                -- archive. Settings by target:
+                --   s3:     url = bucket URL (path style), username = access
+                --           key id, region
+                --   gdrive: url = folder id (optional), username = OAuth
+                --           client id
+                --   webdav: url = collection URL, username
+                -- Secrets, refresh tokens and the archive passphrase are kept
+                -- outside the database.
+                ALTER TABLE pharmacy_settings ADD COLUMN remote_backup_target TEXT NOT NULL DEFAULT 'none'
+                    CHECK(remote_backup_target IN ('none', 's3', 'gdrive', 'webdav'));
+                ALTER TABLE pharmacy_settings ADD COLUMN remote_backup_url TEXT NOT NULL DEFAULT '';
+                ALTER TABLE pharmacy_settings ADD COLUMN remote_backup_region TEXT NOT NULL DEFAULT '';
+                ALTER TABLE pharmacy_settings ADD COLUMN remote_backup_username TEXT NOT NULL DEFAULT '';
+
+                DROP TRIGGER IF EXISTS pharmacy_settings_touch_updated_at;
+                CREATE TRIGGER pharmacy_settings_touch_updated_at AFTER UPDATE ON pharmacy_settings
+                FOR EACH ROW WHEN new.updated_at IS old.updated_at
+                    AND (old.name IS NOT new.name
+                         OR old.address IS NOT new.address
+                         OR old.phone IS NOT new.phone
+                         OR old.email IS NOT new.email
+                         OR old.gstin IS NOT new.gstin
+                         OR old.drug_license_no IS NOT new.drug_license_no
+                         OR old.state_code IS NOT new.state_code
+                         OR old.city IS NOT new.city
+                         OR old.pincode IS NOT new.pincode
+                         OR old.invoice_prefix IS NOT new.invoice_prefix
+                         OR old.credit_note_prefix IS NOT new.credit_note_prefix
+                         OR old.debit_note_prefix IS NOT new.debit_note_prefix
+                         OR old.low_stock_threshold IS NOT new.low_stock_threshold
+                         OR old.near_expiry_days IS NOT new.near_expiry_days
+                         OR old.einvoice_enabled IS NOT new.einvoice_enabled
+                         OR old.reorder_lead_time_days IS NOT new.reorder_lead_time_days
+                         OR old.prices_include_gst IS NOT new.prices_include_gst
+                         OR old.loyalty_spend_per_point_paise IS NOT new.loyalty_spend_per_point_paise
+                         OR old.loyalty_point_value_paise IS NOT new.loyalty_point_value_paise
+                         OR old.invoice_number_format IS NOT new.invoice_number_format
+                         OR old.fy_reset IS NOT new.fy_reset
+                         OR old.idempotency_window_hours IS NOT new.idempotency_window_hours
+                         OR old.lockout_max_attempts IS NOT new.lockout_max_attempts
+                         OR old.lockout_window_minutes IS NOT new.lockout_window_minutes
+                         OR old.lockout_minutes IS NOT new.lockout_minutes
+                         OR old.printer_interface IS NOT new.printer_interface
+                         OR old.printer_address IS NOT new.printer_address
+                         OR old.printer_line_width IS NOT new.printer_line_width
+                         OR old.auto_backup_enabled IS NOT new.auto_backup_enabled
+                         OR old.auto_backup_time IS NOT new.auto_backup_time
+                         OR old.auto_backup_keep IS NOT new.auto_backup_keep
+                         OR old.auto_backup_dir IS NOT new.auto_backup_dir
+                         OR old.remote_backup_target IS NOT new.remote_backup_target
+                         OR old.remote_backup_url IS NOT new.remote_backup_url
+                         OR old.remote_backup_region IS NOT new.remote_backup_region
+                         OR old.remote_backup_username IS NOT new.remote_backup_username)
+                BEGIN
+                    UPDATE pharmacy_settings SET updated_at = datetime('now') WHERE id = new.id;
+                END;
+
+                -- Outcome of uploads on this machine; never synced.
+                CREATE TABLE remote_backup_status (
+                    id INTEGER PRIMARY KEY CHECK(id = 1),
+                    last_attempt_at TEXT,
+                    last_success_at TEXT,
+                    -- taken_at of the last snapshot uploaded
+                    last_uploaded TEXT,
+                    last_error TEXT,
+                    -- Consecutive failures; each doubles the wait before a retry.
+                    failures INTEGER NOT NULL DEFAULT 0
+                );
+                INSERT INTO remote_backup_status (id) VALUES (1);
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 46,
+            description: "restrict setting the database passphrase to admins",
+            sql: r#"
+                INSERT OR IGNORE INTO permissions (key, description)
+                VALUES ('database.encrypt', 'Encrypt the database or change its passphrase');
+                INSERT OR IGNORE INTO role_permissions (role, permission) VALUES ('admin', 'database.encrypt');
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 47,
+            description: "schedule database maintenance and log its runs",
+            sql: r#"
+                -- Days between scheduled runs, which follow the nightly backup;
+                -- 0 turns them off.
+                ALTER TABLE pharmacy_settings ADD COLUMN maintenance_interval_days INTEGER NOT NULL DEFAULT 7
+                    CHECK(maintenance_interval_days BETWEEN 0 AND 365);
+
+                DROP TRIGGER IF EXISTS pharmacy_settings_touch_updated_at;
+                CREATE TRIGGER pharmacy_settings_touch_updated_at AFTER UPDATE ON pharmacy_settings
+                FOR EACH ROW WHEN new.updated_at IS old.updated_at
+                    AND (old.name IS NOT new.name
+                         OR old.address IS NOT new.address
+                         OR old.phone IS NOT new.phone
+                         OR old.email IS NOT new.email
+                         OR old.gstin IS NOT new.gstin
+                         OR old.drug_license_no IS NOT new.drug_license_no
+                         OR old.state_code IS NOT new.state_code
+                         OR old.city IS NOT new.city
+                         OR old.pincode IS NOT new.pincode
+                         OR old.invoice_prefix IS NOT new.invoice_prefix
+                         OR old.credit_note_prefix IS NOT new.credit_note_prefix
+                         OR old.debit_note_prefix IS NOT new.debit_note_prefix
+                         OR old.low_stock_threshold IS NOT new.low_stock_threshold
+                         OR old.near_expiry_days IS NOT new.near_expiry_days
+                         OR old.einvoice_enabled IS NOT new.einvoice_enabled
+                         OR old.reorder_lead_time_days IS NOT new.reorder_lead_time_days
+                         OR old.prices_include_gst IS NOT new.prices_include_gst
+                         OR old.loyalty_spend_per_point_paise IS NOT new.loyalty_spend_per_point_paise
+                         OR old.loyalty_point_value_paise IS NOT new.loyalty_point_value_paise
+                         OR old.invoice_number_format IS NOT new.invoice_number_format
+                         OR old.fy_reset IS NOT new.fy_reset
+                         OR old.idempotency_window_hours IS NOT new.idempotency_window_hours
+                         OR old.lockout_max_attempts IS NOT new.lockout_max_attempts
+                         OR old.lockout_window_minutes IS NOT new.lockout_window_minutes
+                         OR old.lockout_minutes IS NOT new.lockout_minutes
+                         OR old.printer_interface IS NOT new.printer_interface
+                         OR old.printer_address IS NOT new.printer_address
+                         OR old.printer_line_width IS NOT new.printer_line_width
+                         OR old.auto_backup_enabled IS NOT new.auto_backup_enabled
+                         OR old.auto_backup_time IS NOT new.auto_backup_time
+                         OR old.auto_backup_keep IS NOT new.auto_backup_keep
+                         OR old.auto_backup_dir IS NOT new.auto_backup_dir
+                         OR old.remote_backup_target IS NOT new.remote_backup_target
+                         OR old.remote_backup_url IS NOT new.remote_backup_url
+                         OR old.remote_backup_region IS NOT new.remote_backup_region
+                         OR old.remote_backup_username IS NOT new.remote_backup_username
+                         OR old.maintenance_interval_days IS NOT new.maintenance_interval_days)
+                BEGIN
+                    UPDATE pharmacy_settings SET updated_at = datetime('now') WHERE id = new.id;
+                END;
+
+                CREATE TABLE IF NOT EXISTS maintenance_runs (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    ran_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    integrity_ok INTEGER NOT NULL CHECK(integrity_ok IN (0, 1)),
+                    -- integrity_check output, one problem per line
+                    problems TEXT NOT NULL DEFAULT '',
+                    size_before_bytes INTEGER NOT NULL,
+                    size_after_bytes INTEGER NOT NULL,
+                    -- Why VACUUM did not run, if it did not.
+                    error TEXT
+                );
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 48,
+            description: "add e-invoicing API credentials and store IRNs against sales",
+            sql: r#"
+                -- GSP credentials for generating IRNs. api_url is the GSP's
+                -- base URL; username and password are the e-invoice API user
+                -- created on the IRP portal.
+                ALTER TABLE pharmacy_settings ADD COLUMN einvoice_api_url TEXT NOT NULL DEFAULT '';
+                ALTER TABLE pharmacy_settings ADD COLUMN einvoice_client_id TEXT NOT NULL DEFAULT '';
+                ALTER TABLE pharmacy_settings ADD COLUMN einvoice_client_secret TEXT NOT NULL DEFAULT '';
+                ALTER TABLE pharmacy_settings ADD COLUMN einvoice_username TEXT NOT NULL DEFAULT '';
+                ALTER TABLE pharmacy_settings ADD COLUMN einvoice_password TEXT NOT NULL DEFAULT '';
+
+                DROP TRIGGER IF EXISTS pharmacy_settings_touch_updated_at;
+                CREATE TRIGGER pharmacy_settings_touch_updated_at AFTER UPDATE ON pharmacy_settings
+                FOR EACH ROW WHEN new.updated_at IS old.updated_at
+                    AND (old.name IS NOT new.name
+                         OR old.address IS NOT new.address
+                         OR old.phone IS NOT new.phone
+                         OR old.email IS NOT new.email
+                         OR old.gstin IS NOT new.gstin
+                         OR old.drug_license_no IS NOT new.drug_license_no
+                         OR old.state_code IS NOT new.state_code
+                         OR old.city IS NOT new.city
+                         OR old.pincode IS NOT new.pincode
+                         OR old.invoice_prefix IS NOT new.invoice_prefix
+                         OR old.credit_note_prefix IS NOT new.credit_note_prefix
+                         OR old.debit_note_prefix IS NOT new.debit_note_prefix
+                         OR old.low_stock_threshold IS NOT new.low_stock_threshold
+                         OR old.near_expiry_days IS NOT new.near_expiry_days
+                         OR old.einvoice_enabled IS NOT new.einvoice_enabled
+                         OR old.reorder_lead_time_days IS NOT new.reorder_lead_time_days
+                         OR old.prices_include_gst IS NOT new.prices_include_gst
+                         OR old.loyalty_spend_per_point_paise IS NOT new.loyalty_spend_per_point_paise
+                         OR old.loyalty_point_value_paise IS NOT new.loyalty_point_value_paise
+                         OR old.invoice_number_format IS NOT new.invoice_number_format
+                         OR old.fy_reset IS NOT new.fy_reset
+                         OR old.idempotency_window_hours IS NOT new.idempotency_window_hours
+                         OR old.lockout_max_attempts IS NOT new.lockout_max_attempts
+                         OR old.lockout_window_minutes IS NOT new.lockout_window_minutes
+                         OR old.lockout_minutes IS NOT new.lockout_minutes
+                         OR old.printer_interface IS NOT new.printer_interface
+                         OR old.printer_address IS NOT new.printer_address
+                         OR old.printer_line_width IS NOT new.printer_line_width
+                         OR old.auto_backup_enabled IS NOT new.auto_backup_enabled
+                         OR old.auto_backup_time IS NOT new.auto_backup_time
+                         OR old.auto_backup_keep IS NOT new.auto_backup_keep
+                         OR old.auto_backup_dir IS NOT new.auto_backup_dir
+                         OR old.remote_backup_target IS NOT new.remote_backup_target
+                         OR old.remote_backup_url IS NOT new.remote_backup_url
+                         OR old.remote_backup_region IS NOT new.remote_backup_region
+                         OR old.remote_backup_username IS NOT new.remote_backup_username
+                         OR old.maintenance_interval_days IS NOT new.maintenance_interval_days
+                         OR old.einvoice_api_url IS NOT new.einvoice_api_url
+                         OR old.einvoice_client_id IS NOT new.einvoice_client_id
+                         OR old.einvoice_client_secret IS NOT new.einvoice_client_secret
+                         OR old.einvoice_username IS NOT new.einvoice_username
+                         OR old.einvoice_password IS NOT new.einvoice_password)
+                BEGIN
+                    UPDATE pharmacy_settings SET updated_at = datetime('now') WHERE id = new.id;
+                END;
+
+                -- The IRP's response for a registered invoice. A sale has at
+                -- most one IRN.
+                CREATE TABLE IF NOT EXISTS sale_einvoices (
+                    sale_id INTEGER PRIMARY KEY REFERENCES sales(id),
+                    irn TEXT NOT NULL UNIQUE,
+                    ack_no TEXT NOT NULL,
+                    ack_date TEXT NOT NULL,
+                    signed_invoice TEXT NOT NULL,
+                    signed_qr_code TEXT NOT NULL,
+                    user_id INTEGER REFERENCES users(id),
+                    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+                );
+
+                INSERT OR IGNORE INTO permissions (key, description)
+                VALUES ('sales.einvoice', 'Register sales with the e-invoice portal');
+                INSERT OR IGNORE INTO role_permissions (role, permission) VALUES ('admin', 'sales.einvoice');
+                INSERT OR IGNORE INTO role_permissions (role, permission) VALUES ('pharmacist', 'sales.einvoice');
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 49,
+            description: "add e-way bill login and threshold, and store e-way bills against sales",
+            sql: r#"
+                -- The e-way bill system has its own API user; requests go
+                -- through the e-invoicing GSP URL and client credentials.
+                ALTER TABLE pharmacy_settings ADD COLUMN ewaybill_username TEXT NOT NULL DEFAULT '';
+                ALTER TABLE pharmacy_settings ADD COLUMN ewaybill_password TEXT NOT NULL DEFAULT '';
+                -- Consignment value above which a dispatch needs an e-way bill.
+                ALTER TABLE pharmacy_settings ADD COLUMN ewaybill_threshold_paise INTEGER NOT NULL DEFAULT 5000000
+                    CHECK(ewaybill_threshold_paise >= 0);
+
+                DROP TRIGGER IF EXISTS pharmacy_settings_touch_updated_at;
+                CREATE TRIGGER pharmacy_settings_touch_updated_at AFTER UPDATE ON pharmacy_settings
+                FOR EACH ROW WHEN new.updated_at IS old.updated_at
+                    AND (old.name IS NOT new.name
+                         OR old.address IS NOT new.address
+                         OR old.phone IS NOT new.phone
+                         OR old.email IS NOT new.email
+                         OR old.gstin IS NOT new.gstin
+                         OR old.drug_license_no IS NOT new.drug_license_no
+                         OR old.state_code IS NOT new.state_code
+                         OR old.city IS NOT new.city
+                         OR old.pincode IS NOT new.pincode
+                         OR old.invoice_prefix IS NOT new.invoice_prefix
+                         OR old.credit_note_prefix IS NOT new.credit_note_prefix
+                         OR old.debit_note_prefix IS NOT new.debit_note_prefix
+                         OR old.low_stock_threshold IS NOT new.low_stock_threshold
+                         OR old.near_expiry_days IS NOT new.near_expiry_days
+                         OR old.einvoice_enabled IS NOT new.einvoice_enabled
+                         OR old.reorder_lead_time_days IS NOT new.reorder_lead_time_days
+                         OR old.prices_include_gst IS NOT new.prices_include_gst
+                         OR old.loyalty_spend_per_point_paise IS NOT new.loyalty_spend_per_point_paise
+                         OR old.loyalty_point_value_paise IS NOT new.loyalty_point_value_paise
+                         OR old.invoice_number_format IS NOT new.invoice_number_format
+                         OR old.fy_reset IS NOT new.fy_reset
+                         OR old.idempotency_window_hours IS NOT new.idempotency_window_hours
+                         OR old.lockout_max_attempts IS NOT new.lockout_max_attempts
+                         OR old.lockout_window_minutes IS NOT new.lockout_window_minutes
+                         OR old.lockout_minutes IS NOT new.lockout_minutes
+                         OR old.printer_interface IS NOT new.printer_interface
+                         OR old.printer_address IS NOT new.printer_address
+                         OR old.printer_line_width IS NOT new.printer_line_width
+                         OR old.auto_backup_enabled IS NOT new.auto_backup_enabled
+                         OR old.auto_backup_time IS NOT new.auto_backup_time
+                         OR old.auto_backup_keep IS NOT new.auto_backup_keep
+                         OR old.auto_backup_dir IS NOT new.auto_backup_dir
+                         OR old.remote_backup_target IS NOT new.remote_backup_target
+                         OR old.remote_backup_url IS NOT new.remote_backup_url
+                         OR old.remote_backup_region IS NOT new.remote_backup_region
+                         OR old.remote_backup_username IS NOT new.remote_backup_username
+                         OR old.maintenance_interval_days IS NOT new.maintenance_interval_days
+                         OR old.einvoice_api_url IS NOT new.einvoice_api_url
+                         OR old.einvoice_client_id IS NOT new.einvoice_client_id
+                         OR old.einvoice_client_secret IS NOT new.einvoice_client_secret
+                         OR old.einvoice_username IS NOT new.einvoice_username
+                         OR old.einvoice_password IS NOT new.einvoice_password
+                         OR old.ewaybill_username IS NOT new.ewaybill_username
+                         OR old.ewaybill_password IS NOT new.ewaybill_password
+                         OR old.ewaybill_threshold_paise IS NOT new.ewaybill_threshold_paise)
+                BEGIN
+                    UPDATE pharmacy_settings SET updated_at = datetime('now') WHERE id = new.id;
+                END;
+
+                CREATE TABLE IF NOT EXISTS sale_eway_bills (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    sale_id INTEGER NOT NULL REFERENCES sales(id),
+                    ewb_no TEXT NOT NULL UNIQUE,
+                    ewb_date TEXT NOT NULL,
+                    valid_upto TEXT NOT NULL,
+                    transport_mode TEXT NOT NULL CHECK(transport_mode IN ('road', 'rail', 'air', 'ship')),
+                    distance_km INTEGER NOT NULL,
+                    vehicle_no TEXT,
+                    transporter_id TEXT,
+                    user_id INTEGER REFERENCES users(id),
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    -- Set when the bill is cancelled on the portal; a new one
+                    -- may then be generated for the sale.
+                    cancelled_at TEXT,
+                    cancel_reason TEXT,
+                    cancel_remark TEXT
+                );
+                CREATE UNIQUE INDEX IF NOT EXISTS idx_sale_eway_bills_active
+                    ON sale_eway_bills(sale_id) WHERE cancelled_at IS NULL;
+
+                INSERT OR IGNORE INTO permissions (key, description)
+                VALUES ('sales.ewaybill', 'Generate and cancel e-way bills for dispatches');
+                INSERT OR IGNORE INTO role_permissions (role, permission) VALUES ('admin', 'sales.ewaybill');
+                INSERT OR IGNORE INTO role_permissions (role, permission) VALUES ('pharmacist', 'sales.ewaybill');
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 50,
+            description: "record the place of supply on sales",
+            sql: r#"
+                -- The state code the sale was taxed for. Customers can move
+                -- state later, so GSTR-1 and e-invoices read this instead.
+                ALTER TABLE sales ADD COLUMN place_of_supply TEXT;
+                UPDATE sales SET place_of_supply = COALESCE(
+                    (SELECT COALESCE(NULLIF(c.state_code, ''), NULLIF(substr(c.gstin, 1, 2), ''))
+                     FROM customers c WHERE c.id = sales.customer_id),
+                    (SELECT NULLIF(state_code, '') FROM pharmacy_settings WHERE id = 1));
+
+                DROP TRIGGER IF EXISTS sales_audit_insert;
+                DROP TRIGGER IF EXISTS sales_audit_update;
+                DROP TRIGGER IF EXISTS sales_audit_delete;
+                CREATE TRIGGER sales_audit_insert AFTER INSERT ON sales
+                FOR EACH ROW
+                BEGIN
+                    INSERT INTO audit_log (table_name, row_id, action, user_id, new_values)
+                    VALUES ('sales', new.id, 'insert', new.user_id, json_object(
+                            'id', new.id, 'invoice_number', new.invoice_number,
+                            'customer_id', new.customer_id, 'user_id', new.user_id,
+                            'sale_date', new.sale_date, 'subtotal_paise', new.subtotal_paise,
+                            'discount_paise', new.discount_paise,
+                            'total_cgst_paise', new.total_cgst_paise,
+                            'total_sgst_paise', new.total_sgst_paise,
+                            'total_igst_paise', new.total_igst_paise,
+                            'total_gst_paise', new.total_gst_paise,
+                            'round_off_paise', new.round_off_paise,
+                            'grand_total_paise', new.grand_total_paise,
+                            'payment_mode', new.payment_mode, 'notes', new.notes,
+                            'status', new.status, 'is_interstate', new.is_interstate,
+                            'place_of_supply', new.place_of_supply,
+                            'branch_id', new.branch_id));
+                END;
+                CREATE TRIGGER sales_audit_update AFTER UPDATE ON sales
+                FOR EACH ROW WHEN (old.invoice_number IS NOT new.invoice_number
+                         OR old.customer_id IS NOT new.customer_id
+                         OR old.user_id IS NOT new.user_id
+                         OR old.sale_date IS NOT new.sale_date
+                         OR old.subtotal_paise IS NOT new.subtotal_paise
+                         OR old.discount_paise IS NOT new.discount_paise
+                         OR old.total_cgst_paise IS NOT new.total_cgst_paise
+                         OR old.total_sgst_paise IS NOT new.total_sgst_paise
+                         OR old.total_igst_paise IS NOT new.total_igst_paise
+                         OR old.total_gst_paise IS NOT new.total_gst_paise
+                         OR old.round_off_paise IS NOT new.round_off_paise
+                         OR old.grand_total_paise IS NOT new.grand_total_paise
+                         OR old.payment_mode IS NOT new.payment_mode
+                         OR old.notes IS NOT new.notes
+                         OR old.status IS NOT new.status
+                         OR old.is_interstate IS NOT new.is_interstate
+                         OR old.place_of_supply IS NOT new.place_of_supply
+                         OR old.branch_id IS NOT new.branch_id)
+                BEGIN
+                    INSERT INTO audit_log (table_name, row_id, action, user_id, old_values, new_values)
+                    VALUES ('sales', new.id, 'update', new.user_id,
+                        json_object(
+                            'id', old.id, 'invoice_number', old.invoice_number,
+                            'customer_id', old.customer_id, 'user_id', old.user_id,
+                            'sale_date', old.sale_date, 'subtotal_paise', old.subtotal_paise,
+                            'discount_paise', old.discount_paise,
+                            'total_cgst_paise', old.total_cgst_paise,
+                            'total_sgst_paise', old.total_sgst_paise,
+                            'total_igst_paise', old.total_igst_paise,
+                            'total_gst_paise', old.total_gst_paise,
+                            'round_off_paise', old.round_off_paise,
+                            'grand_total_paise', old.grand_total_paise,
+                            'payment_mode', old.payment_mode, 'notes', old.notes,
+                            'status', old.status, 'is_interstate', old.is_interstate,
+                            'place_of_supply', old.place_of_supply,
+                            'branch_id', old.branch_id),
+                        json_object(
+                            'id', new.id, 'invoice_number', new.invoice_number,
+                            'customer_id', new.customer_id, 'user_id', new.user_id,
+                            'sale_date', new.sale_date, 'subtotal_paise', new.subtotal_paise,
+                            'discount_paise', new.discount_paise,
+                            'total_cgst_paise', new.total_cgst_paise,
+                            'total_sgst_paise', new.total_sgst_paise,
+                            'total_igst_paise', new.total_igst_paise,
+                            'total_gst_paise', new.total_gst_paise,
+                            'round_off_paise', new.round_off_paise,
+                            'grand_total_paise', new.grand_total_paise,
+                            'payment_mode', new.payment_mode, 'notes', new.notes,
+                            'status', new.status, 'is_interstate', new.is_interstate,
+                            'place_of_supply', new.place_of_supply,
+                            'branch_id', new.branch_id));
+                END;
+                CREATE TRIGGER sales_audit_delete AFTER DELETE ON sales
+                FOR EACH ROW
+                BEGIN
+                    INSERT INTO audit_log (table_name, row_id, action, user_id, old_values)
+                    VALUES ('sales', old.id, 'delete', old.user_id, json_object(
+                            'id', old.id, 'invoice_number', old.invoice_number,
+                            'customer_id', old.customer_id, 'user_id', old.user_id,
+                            'sale_date', old.sale_date, 'subtotal_paise', old.subtotal_paise,
+                            'discount_paise', old.discount_paise,
+                            'total_cgst_paise', old.total_cgst_paise,
+                            'total_sgst_paise', old.total_sgst_paise,
+                            'total_igst_paise', old.total_igst_paise,
+                            'total_gst_paise', old.total_gst_paise,
+                            'round_off_paise', old.round_off_paise,
+                            'grand_total_paise', old.grand_total_paise,
+                            'payment_mode', old.payment_mode, 'notes', old.notes,
+                            'status', old.status, 'is_interstate', old.is_interstate,
+                            'place_of_supply', old.place_of_supply,
+                            'branch_id', old.branch_id));
+                END;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 51,
+            description: "add composition scheme billing",
+            sql: r#"
+                -- Composition dealers issue bills of supply: no tax is
+                -- charged to the buyer, though purchases still carry GST.
+                ALTER TABLE pharmacy_settings ADD COLUMN composition_scheme INTEGER NOT NULL DEFAULT 0
+                    CHECK(composition_scheme IN (0, 1));
+                -- Whether the sale was billed under the scheme, so reprints
+                -- keep their title after the setting changes.
+                ALTER TABLE sales ADD COLUMN bill_of_supply INTEGER NOT NULL DEFAULT 0;
+
+                DROP TRIGGER IF EXISTS pharmacy_settings_touch_updated_at;
+                CREATE TRIGGER pharmacy_settings_touch_updated_at AFTER UPDATE ON pharmacy_settings
+                FOR EACH ROW WHEN new.updated_at IS old.updated_at
+                    AND (old.name IS NOT new.name
+                         OR old.address IS NOT new.address
+                         OR old.phone IS NOT new.phone
+                         OR old.email IS NOT new.email
+                         OR old.gstin IS NOT new.gstin
+                         OR old.drug_license_no IS NOT new.drug_license_no
+                         OR old.state_code IS NOT new.state_code
+                         OR old.city IS NOT new.city
+                         OR old.pincode IS NOT new.pincode
+                         OR old.invoice_prefix IS NOT new.invoice_prefix
+                         OR old.credit_note_prefix IS NOT new.credit_note_prefix
+                         OR old.debit_note_prefix IS NOT new.debit_note_prefix
+                         OR old.low_stock_threshold IS NOT new.low_stock_threshold
+                         OR old.near_expiry_days IS NOT new.near_expiry_days
+                         OR old.einvoice_enabled IS NOT new.einvoice_enabled
+                         OR old.reorder_lead_time_days IS NOT new.reorder_lead_time_days
+                         OR old.prices_include_gst IS NOT new.prices_include_gst
+                         OR old.loyalty_spend_per_point_paise IS NOT new.loyalty_spend_per_point_paise
+                         OR old.loyalty_point_value_paise IS NOT new.loyalty_point_value_paise
+                         OR old.invoice_number_format IS NOT new.invoice_number_format
+                         OR old.fy_reset IS NOT new.fy_reset
+                         OR old.idempotency_window_hours IS NOT new.idempotency_window_hours
+                         OR old.lockout_max_attempts IS NOT new.lockout_max_attempts
+                         OR old.lockout_window_minutes IS NOT new.lockout_window_minutes
+                         OR old.lockout_minutes IS NOT new.lockout_minutes
+                         OR old.printer_interface IS NOT new.printer_interface
+                         OR old.printer_address IS NOT new.printer_address
+                         OR old.printer_line_width IS NOT new.printer_line_width
+                         OR old.auto_backup_enabled IS NOT new.auto_backup_enabled
+                         OR old.auto_backup_time IS NOT new.auto_backup_time
+                         OR old.auto_backup_keep IS NOT new.auto_backup_keep
+                         OR old.auto_backup_dir IS NOT new.auto_backup_dir
+                         OR old.remote_backup_target IS NOT new.remote_backup_target
+                         OR old.remote_backup_url IS NOT new.remote_backup_url
+                         OR old.remote_backup_region IS NOT new.remote_backup_region
+                         OR old.remote_backup_username IS NOT new.remote_backup_username
+                         OR old.maintenance_interval_days IS NOT new.maintenance_interval_days
+                         OR old.einvoice_api_url IS NOT new.einvoice_api_url
+                         OR old.einvoice_client_id IS NOT new.einvoice_client_id
+                         OR old.einvoice_client_secret IS NOT new.einvoice_client_secret
+                         OR old.einvoice_username IS NOT new.einvoice_username
+                         OR old.einvoice_password IS NOT new.einvoice_password
+                         OR old.ewaybill_username IS NOT new.ewaybill_username
+                         OR old.ewaybill_password IS NOT new.ewaybill_password
+                         OR old.ewaybill_threshold_paise IS NOT new.ewaybill_threshold_paise
+                         OR old.composition_scheme IS NOT new.composition_scheme)
+                BEGIN
+                    UPDATE pharmacy_settings SET updated_at = datetime('now') WHERE id = new.id;
+                END;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 52,
+            description: "add customer credit limits and settle receipts against invoices",
+            sql: r#"
+                -- NULL leaves the customer's credit unlimited.
+                ALTER TABLE customers ADD COLUMN credit_limit_paise INTEGER
+                    CHECK(credit_limit_paise IS NULL OR credit_limit_paise >= 0);
+                ALTER TABLE customer_payments ADD COLUMN user_id INTEGER REFERENCES users(id);
+
+                -- The part of a receipt settling one credit invoice. Whatever
+                -- is not allocated settles the oldest invoices first.
+                CREATE TABLE IF NOT EXISTS customer_payment_allocations (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    payment_id INTEGER NOT NULL REFERENCES customer_payments(id) ON DELETE CASCADE,
+                    sale_id INTEGER NOT NULL REFERENCES sales(id),
+                    amount_paise INTEGER NOT NULL CHECK(amount_paise > 0),
+                    UNIQUE(payment_id, sale_id)
+                );
+                CREATE INDEX IF NOT EXISTS idx_customer_payment_allocations_sale
+                    ON customer_payment_allocations(sale_id);
+
+                DROP TRIGGER IF EXISTS customers_touch_updated_at;
+                CREATE TRIGGER customers_touch_updated_at AFTER UPDATE ON customers
+                FOR EACH ROW WHEN new.updated_at IS old.updated_at
+                    AND (old.name IS NOT new.name
+                         OR old.phone IS NOT new.phone
+                         OR old.email IS NOT new.email
+                         OR old.address IS NOT new.address
+                         OR old.gstin IS NOT new.gstin
+                         OR old.state_code IS NOT new.state_code
+                         OR old.city IS NOT new.city
+                         OR old.pincode IS NOT new.pincode
+                         OR old.credit_limit_paise IS NOT new.credit_limit_paise)
+                BEGIN
+                    UPDATE customers SET updated_at = datetime('now') WHERE id = new.id;
+                END;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 53,
+            description: "settle supplier payments against purchase invoices",
+            sql: r#"
+                ALTER TABLE supplier_payments ADD COLUMN user_id INTEGER REFERENCES users(id);
+
+                -- The part of a payment settling one purchase invoice. Whatever
+                -- is not allocated, and credited debit notes, settle the oldest
+                -- invoices first.
+                CREATE TABLE IF NOT EXISTS supplier_payment_allocations (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    payment_id INTEGER NOT NULL REFERENCES supplier_payments(id) ON DELETE CASCADE,
+                    purchase_id INTEGER NOT NULL REFERENCES purchases(id),
+                    amount_paise INTEGER NOT NULL CHECK(amount_paise > 0),
+                    UNIQUE(payment_id, purchase_id)
+                );
+                CREATE INDEX IF NOT EXISTS idx_supplier_payment_allocations_purchase
+                    ON supplier_payment_allocations(purchase_id);
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 54,
+            description: "record expenses paid by the pharmacy",
+            sql: r#"
+                CREATE TABLE IF NOT EXISTS expense_categories (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+                    -- Retired categories keep their expenses but take no new ones.
+                    is_active INTEGER NOT NULL DEFAULT 1 CHECK(is_active IN (0, 1)),
+                    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+                );
+
+                INSERT OR IGNORE INTO expense_categories (name) VALUES
+                    ('Rent'),
+                    ('Electricity'),
+                    ('Salaries'),
+                    ('Telephone & Internet'),
+                    ('Repairs & Maintenance'),
+                    ('Transport'),
+                    ('Stationery'),
+                    ('Miscellaneous');
+
+                CREATE TABLE IF NOT EXISTS expenses (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    category_id INTEGER NOT NULL REFERENCES expense_categories(id),
+                    amount_paise INTEGER NOT NULL CHECK(amount_paise > 0),
+                    expense_date TEXT NOT NULL,
+                    payment_mode TEXT NOT NULL CHECK(payment_mode IN ('cash', 'card', 'upi')),
+                    paid_to TEXT,
+                    reference TEXT,
+                    notes TEXT,
+                    user_id INTEGER NOT NULL REFERENCES users(id),
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+                );
+                CREATE INDEX IF NOT EXISTS idx_expenses_date ON expenses(expense_date);
+
+                INSERT OR IGNORE INTO permissions (key, description)
+                VALUES ('expenses.manage', 'Record, correct and delete expenses');
+                INSERT OR IGNORE INTO role_permissions (role, permission) VALUES ('admin', 'expenses.manage');
+                INSERT OR IGNORE INTO role_permissions (role, permission) VALUES ('pharmacist', 'expenses.manage');
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 55,
+            description: "open and close cash drawer shifts",
+            sql: r#"
+                CREATE TABLE IF NOT EXISTS shifts (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    user_id INTEGER NOT NULL REFERENCES users(id),
+                    opened_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    opening_float_paise INTEGER NOT NULL CHECK(opening_float_paise >= 0),
+                    closed_at TEXT,
+                    closed_by INTEGER REFERENCES users(id),
+                    -- What the drawer should hold at close, float included for cash.
+                    expected_cash_paise INTEGER,
+                    expected_card_paise INTEGER,
+                    expected_upi_paise INTEGER,
+                    counted_cash_paise INTEGER,
+                    counted_card_paise INTEGER,
+                    counted_upi_paise INTEGER,
+                    notes TEXT,
+                    CHECK((closed_at IS NULL) = (counted_cash_paise IS NULL))
+                );
+                -- A cashier works one drawer at a time.
+                CREATE UNIQUE INDEX IF NOT EXISTS idx_shifts_open_per_user
+                    ON shifts(user_id) WHERE closed_at IS NULL;
+
+                ALTER TABLE sales ADD COLUMN shift_id INTEGER REFERENCES shifts(id);
+                CREATE INDEX IF NOT EXISTS idx_sales_shift ON sales(shift_id);
+
+                INSERT OR IGNORE INTO permissions (key, description)
+                VALUES ('shifts.manage', 'Close and review the shifts of other cashiers');
+                INSERT OR IGNORE INTO role_permissions (role, permission) VALUES ('admin', 'shifts.manage');
+                INSERT OR IGNORE INTO role_permissions (role, permission) VALUES ('pharmacist', 'shifts.manage');
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 56,
+            description: "low stock and near-expiry alerts",
+            sql: r#"
+                CREATE TABLE IF NOT EXISTS alerts (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    kind TEXT NOT NULL CHECK(kind IN ('low_stock', 'near_expiry')),
+                    medicine_id INTEGER NOT NULL REFERENCES medicines(id),
+                    -- Set for near-expiry alerts only.
+                    batch_id INTEGER REFERENCES batches(id),
+                    message TEXT NOT NULL,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    acknowledged_at TEXT,
+                    acknowledged_by INTEGER REFERENCES users(id),
+                    -- When the condition cleared; the next check may raise it again.
+                    resolved_at TEXT,
+                    CHECK((kind = 'near_expiry') = (batch_id IS NOT NULL))
+                );
+                -- One unresolved alert per condition, so each check raises only new ones.
+                CREATE UNIQUE INDEX IF NOT EXISTS idx_alerts_unresolved
+                    ON alerts(kind, medicine_id, IFNULL(batch_id, 0)) WHERE resolved_at IS NULL;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 57,
+            description: "scheduled jobs and their run history",
+            sql: r#"
+                CREATE TABLE IF NOT EXISTS scheduled_jobs (
+                    key TEXT PRIMARY KEY,
+                    description TEXT NOT NULL,
+                    -- Five-field cron expression, local time.
+                    schedule TEXT NOT NULL,
+                    enabled INTEGER NOT NULL DEFAULT 1,
+                    last_run_at TEXT,
+                    -- UTC; NULL when the schedule never matches or the job is disabled.
+                    next_run_at TEXT,
+                    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+                );
+
+                CREATE TABLE IF NOT EXISTS scheduled_job_runs (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    job_key TEXT NOT NULL REFERENCES scheduled_jobs(key) ON DELETE CASCADE,
+                    source TEXT NOT NULL CHECK(source IN ('schedule', 'manual')),
+                    -- Who ran it by hand; NULL for scheduled runs.
+                    triggered_by INTEGER REFERENCES users(id),
+                    started_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    finished_at TEXT,
+                    status TEXT NOT NULL DEFAULT 'running'
+                        CHECK(status IN ('running', 'succeeded', 'failed')),
+                    -- What the job reported, or the error it failed with.
+                    message TEXT
+                );
+                CREATE INDEX IF NOT EXISTS idx_scheduled_job_runs_job
+                    ON scheduled_job_runs(job_key, id);
+
+                INSERT OR IGNORE INTO permissions (key, description)
+                VALUES ('scheduler.manage', 'Change, pause and run scheduled jobs');
+                INSERT OR IGNORE INTO role_permissions (role, permission) VALUES ('admin', 'scheduler.manage');
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 58,
+            description: "permissions to dispose of and transfer stock",
+            sql: r#"
+                INSERT OR IGNORE INTO permissions (key, description) VALUES
+                    ('inventory.dispose', 'Write off expired stock to the disposal register'),
+                    ('inventory.transfer', 'Move stock between batches or branches');
+                INSERT OR IGNORE INTO role_permissions (role, permission) VALUES
+                    ('admin', 'inventory.dispose'),
+                    ('pharmacist', 'inventory.dispose'),
+                    ('admin', 'inventory.transfer'),
+                    ('pharmacist', 'inventory.transfer');
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 59,
+            description: "permissions for backups, maintenance, exports, settings, users and catalogue changes",
+            sql: r#"
+                INSERT OR IGNORE INTO permissions (key, description) VALUES
+                    ('backup.create', 'Back up or export the database'),
+                    ('database.maintain', 'Check and rebuild the database file'),
+                    ('loyalty.redeem', 'Redeem loyalty points against a sale'),
+                    ('medicines.manage', 'Add and edit medicines, change tax rates and deactivate or reactivate them'),
+                    ('prescriptions.link', 'Link prescriptions to sales'),
+                    ('reports.export', 'Export sales, the day book, GSTR-1 and Tally files'),
+                    ('settings.manage', 'Change the pharmacy profile and settings'),
+                    ('sync.flush', 'Send queued changes to the sync server'),
+                    ('users.manage', 'Add users and change their name, role, password or status');
+                INSERT OR IGNORE INTO role_permissions (role, permission) VALUES
+                    ('admin', 'backup.create'),
+                    ('admin', 'database.maintain'),
+                    ('admin', 'loyalty.redeem'),
+                    ('pharmacist', 'loyalty.redeem'),
+                    ('admin', 'medicines.manage'),
+                    ('pharmacist', 'medicines.manage'),
+                    ('admin', 'prescriptions.link'),
+                    ('pharmacist', 'prescriptions.link'),
+                    ('admin', 'reports.export'),
+                    ('admin', 'settings.manage'),
+                    ('admin', 'sync.flush'),
+                    ('admin', 'users.manage');
+            "#,
+            kind: MigrationKind::Up,
+        },
+    ]
+}
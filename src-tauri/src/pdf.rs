@@ -0,0 +1,191 @@
+//! A minimal PDF writer for printed documents.
+//!
+//! Only what invoices and labels need: text in the standard Helvetica and
+//! Courier fonts, which need no embedding, ruled lines and filled
+//! rectangles. Courier's fixed advance lets columns line up without font
+//! metrics. Text is kept to ASCII.
+
+use std::fmt::Write as _;
+
+/// Points per millimetre.
+pub const PT_PER_MM: f64 = 72.0 / 25.4;
+/// Courier advances 600/1000 of the type size per character.
+const COURIER_ADVANCE: f64 = 0.6;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Font {
+    Regular,
+    Bold,
+    Mono,
+    MonoBold,
+}
+
+impl Font {
+    fn resource(self) -> &'static str {
+        match self {
+            Self::Regular => "F1",
+            Self::Bold => "F2",
+            Self::Mono => "F3",
+            Self::MonoBold => "F4",
+        }
+    }
+}
+
+const FONT_NAMES: [&str; 4] = ["Helvetica", "Helvetica-Bold", "Courier", "Courier-Bold"];
+
+/// Escape text for a PDF string literal, replacing what WinAnsi lacks.
+fn pdf_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '(' | ')' => {
+                out.push('\\');
+                out.push(c);
+            }
+            ' '..='~' => out.push(c),
+            _ => out.push('?'),
+        }
+    }
+    out
+}
+
+/// Width of `text` set in Courier at `size`.
+pub fn mono_width(text: &str, size: f64) -> f64 {
+    text.chars().count() as f64 * size * COURIER_ADVANCE
+}
+
+/// Pages of one size, drawn in points from the bottom-left corner.
+pub struct Canvas {
+    width: f64,
+    height: f64,
+    done: Vec<String>,
+    current: String,
+}
+
+impl Canvas {
+    pub fn new(width: f64, height: f64) -> Self {
+        Self {
+            width,
+            height,
+            done: Vec::new(),
+            current: String::new(),
+        }
+    }
+
+    pub fn text(&mut self, x: f64, y: f64, font: Font, size: f64, text: &str) {
+        let _ = writeln!(
+            self.current,
+            "BT /{} {size} Tf {x:.2} {y:.2} Td ({}) Tj ET",
+            font.resource(),
+            pdf_string(text)
+        );
+    }
+
+    /// A line of Courier text ending at `right`.
+    pub fn mono_right(&mut self, right: f64, y: f64, font: Font, size: f64, text: &str) {
+        self.text(right - mono_width(text, size), y, font, size, text);
+    }
+
+    pub fn line(&mut self, x1: f64, y1: f64, x2: f64, y2: f64) {
+        let _ = writeln!(self.current, "0.5 w {x1:.2} {y1:.2} m {x2:.2} {y2:.2} l S");
+    }
+
+    pub fn fill_rect(&mut self, x: f64, y: f64, width: f64, height: f64) {
+        let _ = writeln!(self.current, "{x:.3} {y:.3} {width:.3} {height:.3} re f");
+    }
+
+    pub fn new_page(&mut self) {
+        self.done.push(std::mem::take(&mut self.current));
+    }
+
+    /// The finished document.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.done.push(self.current);
+        let pages = self.done;
+        // Objects: 1 catalog, 2 page tree, 3-6 fonts, then a page and its
+        // content stream for each page.
+        let first_page = 3 + FONT_NAMES.len();
+        let mut objects = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            format!(
+                "<< /Type /Pages /Count {} /Kids [{}] >>",
+                pages.len(),
+                (0..pages.len())
+                    .map(|i| format!("{} 0 R", first_page + 2 * i))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+        ];
+        for name in FONT_NAMES {
+            objects.push(format!(
+                "<< /Type /Font /Subtype /Type1 /BaseFont /{name} /Encoding /WinAnsiEncoding >>"
+            ));
+        }
+        let fonts = (0..FONT_NAMES.len())
+            .map(|i| format!("/F{} {} 0 R", i + 1, 3 + i))
+            .collect::<Vec<_>>()
+            .join(" ");
+        for (i, content) in pages.iter().enumerate() {
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] \
+                 /Resources << /Font << {fonts} >> >> /Contents {} 0 R >>",
+                self.width,
+                self.height,
+                first_page + 2 * i + 1
+            ));
+            objects.push(format!(
+                "<< /Length {} >>\nstream\n{content}endstream",
+                content.len()
+            ));
+        }
+
+        let mut out = b"%PDF-1.4\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend_from_slice(format!("{} 0 obj\n{object}\nendobj\n", i + 1).as_bytes());
+        }
+        let xref = out.len();
+        let mut trailer = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(trailer, "{offset:010} 00000 n ");
+        }
+        let _ = write!(
+            trailer,
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            objects.len() + 1
+        );
+        out.extend_from_slice(trailer.as_bytes());
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xref_offsets_point_at_their_objects() {
+        let mut canvas = Canvas::new(100.0, 50.0);
+        canvas.text(5.0, 5.0, Font::Regular, 8.0, "Rs. (10)");
+        canvas.new_page();
+        canvas.fill_rect(1.0, 1.0, 2.0, 20.0);
+        let pdf = canvas.finish();
+
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.starts_with("%PDF-1.4\n") && text.ends_with("%%EOF\n"));
+        assert!(text.contains(r"(Rs. \(10\)) Tj"));
+        assert!(text.contains("/Type /Pages /Count 2 "));
+        let xref = text.rfind("\nxref\n").unwrap() + 1;
+        let offsets: Vec<usize> = text[xref..]
+            .lines()
+            .skip(3)
+            .take_while(|l| l.ends_with(" n "))
+            .map(|l| l[..10].parse().unwrap())
+            .collect();
+        assert_eq!(offsets.len(), 10);
+        for (i, offset) in offsets.iter().enumerate() {
+            assert!(text[*offset..].starts_with(&format!("{} 0 obj", i + 1)));
+        }
+    }
+}
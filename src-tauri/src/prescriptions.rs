@@ -0,0 +1,231 @@
+//! Linking prescriptions to the sales dispensed against them.
+//!
+//! The first sale against a prescription is stored as its `sale_id`; each
+//! later sale counts as a refill, up to the number the doctor authorized.
+
+use rusqlite::{params, OptionalExtension};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::auth::{self, permission};
+use crate::db::{ChangeOp, Entity, Tx};
+use crate::error::{AppError, AppResult};
+use crate::sync;
+
+/// A prescription as entered at the counter; it is linked to sales later.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewPrescription {
+    pub customer_id: i64,
+    pub doctor_name: String,
+    pub rx_number: Option<String>,
+    /// `YYYY-MM-DD`.
+    pub prescription_date: String,
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub refills_allowed: i64,
+}
+
+/// Record a prescription, returning its id.
+pub fn create_prescription(
+    tx: &Tx,
+    prescription: &NewPrescription,
+    user_id: i64,
+) -> AppResult<i64> {
+    auth::active_role(tx, user_id)?;
+    let doctor_name = prescription.doctor_name.trim();
+    if doctor_name.is_empty() {
+        return Err(AppError::validation("Doctor name is required"));
+    }
+    if prescription.refills_allowed < 0 {
+        return Err(AppError::validation("Refills allowed cannot be negative"));
+    }
+    tx.execute(
+        "INSERT INTO prescriptions (customer_id, doctor_name, rx_number, prescription_date,
+             notes, refills_allowed)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            prescription.customer_id,
+            doctor_name,
+            prescription.rx_number,
+            prescription.prescription_date,
+            prescription.notes,
+            prescription.refills_allowed
+        ],
+    )?;
+    let prescription_id = tx.last_insert_rowid();
+    tx.changed(Entity::Prescription, ChangeOp::Insert, prescription_id);
+    sync::enqueue(
+        tx,
+        "create_prescription",
+        &json!({ "prescriptionId": prescription_id, "customerId": prescription.customer_id }),
+    )?;
+    Ok(prescription_id)
+}
+
+/// Record that `sale_id` was dispensed against `prescription_id`.
+///
+/// Selling against a prescription that is already linked to another sale
+/// uses up one refill, and fails once none are left.
+pub fn use_for_sale(tx: &Tx, prescription_id: i64, sale_id: i64) -> AppResult<()> {
+    let (linked_sale, allowed): (Option<i64>, i64) = tx
+        .query_row(
+            "SELECT sale_id, refills_allowed FROM prescriptions WHERE id = ?1",
+            params![prescription_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Prescription {prescription_id}")))?;
+    match linked_sale {
+        None => {
+            tx.execute(
+                "UPDATE prescriptions SET sale_id = ?1 WHERE id = ?2",
+                params![sale_id, prescription_id],
+            )?;
+        }
+        Some(linked) if linked == sale_id => {}
+        Some(_) => {
+            let refilled = tx.execute(
+                "UPDATE prescriptions SET refills_used = refills_used + 1
+                 WHERE id = ?1 AND refills_used < refills_allowed",
+                params![prescription_id],
+            )?;
+            if refilled == 0 {
+                return Err(AppError::validation(format!(
+                    "Prescription {prescription_id} has no refills left ({allowed} allowed)"
+                )));
+            }
+        }
+    }
+    tx.changed(Entity::Prescription, ChangeOp::Update, prescription_id);
+    Ok(())
+}
+
+/// Link a prescription to a sale made for the same customer.
+pub fn link_prescription(
+    tx: &Tx,
+    prescription_id: i64,
+    sale_id: i64,
+    user_id: i64,
+) -> AppResult<()> {
+    auth::require_permission(tx, user_id, permission::PRESCRIPTIONS_LINK)?;
+    let prescribed_for: i64 = tx
+        .query_row(
+            "SELECT customer_id FROM prescriptions WHERE id = ?1",
+            params![prescription_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Prescription {prescription_id}")))?;
+    let sold_to: Option<i64> = tx
+        .query_row(
+            "SELECT customer_id FROM sales WHERE id = ?1",
+            params![sale_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Sale {sale_id}")))?;
+    if sold_to != Some(prescribed_for) {
+        return Err(AppError::validation(
+            "The prescription was written for a different customer",
+        ));
+    }
+
+    use_for_sale(tx, prescription_id, sale_id)?;
+    sync::enqueue(
+        tx,
+        "link_prescription",
+        &json!({ "prescriptionId": prescription_id, "saleId": sale_id }),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use super::*;
+    use crate::db::test_support::{
+        insert_batch, insert_cashier, insert_medicine, migrated_db, paid_sale,
+    };
+    use crate::sales::{create_sale, PaymentMode};
+
+    const ADMIN: i64 = 1;
+
+    fn customer(conn: &Connection, name: &str) -> i64 {
+        conn.execute("INSERT INTO customers (name) VALUES (?1)", [name])
+            .unwrap();
+        conn.last_insert_rowid()
+    }
+
+    #[test]
+    fn refills_are_counted_until_the_authorized_number_is_used() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let asha = customer(&conn, "Asha");
+        let ravi = customer(&conn, "Ravi");
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+90 days", 1_000, 10);
+        conn.execute(
+            "INSERT INTO prescriptions (customer_id, doctor_name, prescription_date, refills_allowed)
+             VALUES (?1, 'Dr. Rao', date('now'), 1)",
+            [asha],
+        )
+        .unwrap();
+        let rx = conn.last_insert_rowid();
+        let mut sales = Vec::new();
+        for buyer in [asha, asha, asha, ravi] {
+            let sale = paid_sale(&conn, Some(buyer), PaymentMode::Cash, batch, 1);
+            sales.push(db.with_tx(|tx| create_sale(tx, &sale)).unwrap().sale_id);
+        }
+
+        let err = db
+            .with_tx(|tx| link_prescription(tx, rx, sales[3], ADMIN))
+            .unwrap_err();
+        assert!(err.to_string().contains("different customer"), "{err}");
+
+        db.with_tx(|tx| link_prescription(tx, rx, sales[0], ADMIN))
+            .unwrap();
+        db.with_tx(|tx| link_prescription(tx, rx, sales[0], ADMIN))
+            .unwrap();
+        db.with_tx(|tx| link_prescription(tx, rx, sales[1], ADMIN))
+            .unwrap();
+        let err = db
+            .with_tx(|tx| link_prescription(tx, rx, sales[2], ADMIN))
+            .unwrap_err();
+        assert!(err.to_string().contains("no refills left"), "{err}");
+
+        let (linked, used): (i64, i64) = conn
+            .query_row(
+                "SELECT sale_id, refills_used FROM prescriptions WHERE id = ?1",
+                [rx],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((linked, used), (sales[0], 1));
+    }
+
+    #[test]
+    fn cashier_cannot_link_a_prescription() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let asha = customer(&conn, "Asha");
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+90 days", 1_000, 10);
+        conn.execute(
+            "INSERT INTO prescriptions (customer_id, doctor_name, prescription_date)
+             VALUES (?1, 'Dr. Rao', date('now'))",
+            [asha],
+        )
+        .unwrap();
+        let rx = conn.last_insert_rowid();
+        let sale = paid_sale(&conn, Some(asha), PaymentMode::Cash, batch, 1);
+        let sale_id = db.with_tx(|tx| create_sale(tx, &sale)).unwrap().sale_id;
+        let cashier = insert_cashier(&conn);
+
+        let err = db
+            .with_tx(|tx| link_prescription(tx, rx, sale_id, cashier))
+            .unwrap_err();
+        assert!(matches!(err, AppError::Unauthorized(_)), "{err}");
+    }
+}
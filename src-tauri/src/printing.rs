@@ -0,0 +1,538 @@
+//! Receipts for thermal printers at the counter, as ESC/POS bytes.
+//!
+//! The receipt is rendered from the stored invoice, so a reprint matches
+//! the original sale. USB and serial printers are written to as a device
+//! file; the port is expected to be set up by the OS. Network printers take
+//! the bytes on a raw TCP socket.
+
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use rusqlite::Connection;
+use serde::Serialize;
+
+use crate::error::{AppError, AppResult};
+use crate::format::rupees;
+use crate::invoice::{self, InvoiceDocument};
+use crate::reports::PaymentModeTotals;
+use crate::shifts::{self, DrawerTotals, ZReport};
+
+const ESC: u8 = 0x1b;
+const GS: u8 = 0x1d;
+
+/// How long to wait for a network printer to accept the connection.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PrinterInterface {
+    None,
+    Usb,
+    Serial,
+    Network,
+}
+
+impl PrinterInterface {
+    pub fn parse(s: &str) -> AppResult<Self> {
+        match s {
+            "none" => Ok(Self::None),
+            "usb" => Ok(Self::Usb),
+            "serial" => Ok(Self::Serial),
+            "network" => Ok(Self::Network),
+            other => Err(AppError::validation(format!(
+                "Unknown printer interface: {other}"
+            ))),
+        }
+    }
+}
+
+/// The receipt printer, from the pharmacy settings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrinterConfig {
+    pub interface: PrinterInterface,
+    /// A device path for USB and serial printers, `host:port` on a network.
+    pub address: String,
+    /// Characters per line.
+    pub line_width: usize,
+}
+
+pub fn printer_config(conn: &Connection) -> AppResult<PrinterConfig> {
+    let (interface, address, line_width): (String, String, i64) = conn.query_row(
+        "SELECT printer_interface, printer_address, printer_line_width
+         FROM pharmacy_settings WHERE id = 1",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    Ok(PrinterConfig {
+        interface: PrinterInterface::parse(&interface)?,
+        address: address.trim().to_string(),
+        line_width: usize::try_from(line_width)
+            .map_err(|_| AppError::validation("Printer line width must be positive"))?,
+    })
+}
+
+/// Builds the byte stream; text is kept to ASCII, which every code page
+/// prints the same.
+struct Receipt {
+    bytes: Vec<u8>,
+    width: usize,
+}
+
+impl Receipt {
+    fn new(width: usize) -> Self {
+        let mut receipt = Self {
+            bytes: Vec::new(),
+            width,
+        };
+        receipt.command(&[ESC, b'@']);
+        receipt
+    }
+
+    fn command(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    fn text(&mut self, text: &str) {
+        self.bytes.extend(
+            text.chars()
+                .map(|c| if c.is_ascii() { c as u8 } else { b'?' }),
+        );
+    }
+
+    fn line(&mut self, text: &str) {
+        self.text(text);
+        self.bytes.push(b'\n');
+    }
+
+    /// `text` wrapped at the line width, breaking between words.
+    fn wrapped(&mut self, text: &str) {
+        let mut current = String::new();
+        for word in text.split_whitespace() {
+            if !current.is_empty() && current.len() + 1 + word.len() > self.width {
+                self.line(&current);
+                current.clear();
+            }
+            if !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(word);
+        }
+        if !current.is_empty() {
+            self.line(&current);
+        }
+    }
+
+    /// `left` and `right` at either end of one line.
+    fn columns(&mut self, left: &str, right: &str) {
+        let gap = self.width.saturating_sub(left.len() + right.len()).max(1);
+        self.line(&format!("{left}{}{right}", " ".repeat(gap)));
+    }
+
+    fn rule(&mut self) {
+        self.line(&"-".repeat(self.width));
+    }
+
+    fn align_center(&mut self, center: bool) {
+        self.command(&[ESC, b'a', u8::from(center)]);
+    }
+
+    fn bold(&mut self, on: bool) {
+        self.command(&[ESC, b'E', u8::from(on)]);
+    }
+
+    /// A QR code the printer draws itself, model 2 with error correction M.
+    fn qr(&mut self, data: &str) {
+        self.command(&[GS, b'(', b'k', 4, 0, 49, 65, 50, 0]);
+        self.command(&[GS, b'(', b'k', 3, 0, 49, 67, 6]);
+        self.command(&[GS, b'(', b'k', 3, 0, 49, 69, 49]);
+        let len = data.len() + 3;
+        self.command(&[
+            GS,
+            b'(',
+            b'k',
+            (len % 256) as u8,
+            (len / 256) as u8,
+            49,
+            80,
+            48,
+        ]);
+        self.text(data);
+        self.command(&[GS, b'(', b'k', 3, 0, 49, 81, 48]);
+    }
+
+    /// Feed past the tear bar and cut, leaving a hinge.
+    fn cut(mut self) -> Vec<u8> {
+        self.command(&[GS, b'V', 66, 3]);
+        self.bytes
+    }
+}
+
+/// Tax on the invoice per GST rate, for the breakup table.
+#[derive(Default)]
+struct RateTotals {
+    taxable: i64,
+    cgst: i64,
+    sgst: i64,
+    igst: i64,
+}
+
+/// What the receipt's QR code carries: the IRP's signed QR for an
+/// e-invoice, otherwise who issued it, which invoice, when and for how much.
+fn qr_payload(doc: &InvoiceDocument) -> String {
+    if let Some(einvoice) = &doc.einvoice {
+        return einvoice.signed_qr_code.clone();
+    }
+    format!(
+        "GSTIN:{};INV:{};DT:{};AMT:{}",
+        doc.pharmacy.gstin,
+        doc.invoice_number,
+        doc.sale_date,
+        rupees(doc.totals.grand_total_paise)
+    )
+}
+
+/// The receipt of an invoice as ESC/POS bytes for a printer with
+/// `line_width` characters per line.
+pub fn render_receipt(doc: &InvoiceDocument, line_width: usize) -> Vec<u8> {
+    let mut r = Receipt::new(line_width);
+    let pharmacy = &doc.pharmacy;
+
+    r.align_center(true);
+    r.bold(true);
+    r.wrapped(&pharmacy.name);
+    r.bold(false);
+    r.wrapped(&pharmacy.address);
+    let place = [pharmacy.city.as_str(), pharmacy.pincode.as_str()]
+        .iter()
+        .filter(|s| !s.is_empty())
+        .copied()
+        .collect::<Vec<_>>()
+        .join(" - ");
+    if !place.is_empty() {
+        r.wrapped(&place);
+    }
+    if !pharmacy.phone.is_empty() {
+        r.line(&format!("Ph: {}", pharmacy.phone));
+    }
+    if !pharmacy.gstin.is_empty() {
+        r.line(&format!("GSTIN: {}", pharmacy.gstin));
+    }
+    if !pharmacy.drug_license_no.is_empty() {
+        r.wrapped(&format!("DL No: {}", pharmacy.drug_license_no));
+    }
+    r.bold(true);
+    r.line(doc.title());
+    r.bold(false);
+    r.align_center(false);
+    r.rule();
+
+    r.line(&format!("Bill: {}", doc.invoice_number));
+    r.line(&format!("Date: {}", doc.sale_date));
+    if !doc.cashier.is_empty() {
+        r.line(&format!("Cashier: {}", doc.cashier));
+    }
+    if let Some(customer) = &doc.customer {
+        r.wrapped(&format!("Customer: {}", customer.name));
+        if let Some(gstin) = &customer.gstin {
+            r.line(&format!("GSTIN: {gstin}"));
+        }
+    }
+    r.rule();
+
+    // Keyed by hundredths of a percent so the rates sort numerically.
+    let mut by_rate = BTreeMap::<i64, RateTotals>::new();
+    for item in &doc.items {
+        r.wrapped(&item.medicine_name);
+        r.wrapped(&format!(
+            "Batch {} Exp {}",
+            item.batch_number, item.expiry_date
+        ));
+        r.columns(
+            &format!("  {} x {}", item.quantity, rupees(item.unit_price_paise)),
+            &rupees(item.total_paise),
+        );
+        let rate = item.cgst_rate + item.sgst_rate + item.igst_rate;
+        let totals = by_rate.entry((rate * 100.0).round() as i64).or_default();
+        totals.taxable += item.taxable_amount_paise;
+        totals.cgst += item.cgst_paise;
+        totals.sgst += item.sgst_paise;
+        totals.igst += item.igst_paise;
+    }
+    r.rule();
+
+    let t = &doc.totals;
+    r.columns("Subtotal", &rupees(t.subtotal_paise));
+    if t.discount_paise != 0 {
+        r.columns("Discount", &format!("-{}", rupees(t.discount_paise)));
+    }
+    if !doc.bill_of_supply {
+        if doc.is_interstate {
+            r.columns("IGST", &rupees(t.total_igst_paise));
+        } else {
+            r.columns("CGST", &rupees(t.total_cgst_paise));
+            r.columns("SGST", &rupees(t.total_sgst_paise));
+        }
+    }
+    if t.round_off_paise != 0 {
+        r.columns("Round off", &rupees(t.round_off_paise));
+    }
+    r.bold(true);
+    r.columns("TOTAL Rs.", &rupees(t.grand_total_paise));
+    r.bold(false);
+    for payment in &doc.payments {
+        r.columns(
+            &format!("  Paid by {}", payment.mode.as_str()),
+            &rupees(payment.amount_paise),
+        );
+    }
+    r.wrapped(&doc.amount_in_words);
+    r.rule();
+
+    if doc.bill_of_supply {
+        r.wrapped(invoice::COMPOSITION_DECLARATION);
+    } else {
+        r.columns(
+            "GST    Taxable",
+            if doc.is_interstate {
+                "IGST"
+            } else {
+                "CGST/SGST"
+            },
+        );
+        for (rate, totals) in &by_rate {
+            let tax = if doc.is_interstate {
+                rupees(totals.igst)
+            } else {
+                format!("{}/{}", rupees(totals.cgst), rupees(totals.sgst))
+            };
+            let percent = format!("{}%", *rate as f64 / 100.0);
+            r.columns(&format!("{percent:<6} {}", rupees(totals.taxable)), &tax);
+        }
+    }
+    r.rule();
+
+    if let Some(einvoice) = &doc.einvoice {
+        r.wrapped(&format!("IRN: {}", einvoice.irn));
+        r.wrapped(&format!(
+            "Ack no {} of {}",
+            einvoice.ack_no, einvoice.ack_date
+        ));
+        r.rule();
+    }
+
+    r.align_center(true);
+    r.qr(&qr_payload(doc));
+    r.line("");
+    if let Some(notes) = &doc.notes {
+        r.wrapped(notes);
+    }
+    r.line("Thank you! Get well soon.");
+    r.line("");
+    r.cut()
+}
+
+/// The Z-report of a shift as ESC/POS bytes, or the X-report of one still
+/// open.
+pub fn render_z_report(report: &ZReport, pharmacy_name: &str, line_width: usize) -> Vec<u8> {
+    let mut r = Receipt::new(line_width);
+    let shift = &report.shift;
+
+    r.align_center(true);
+    r.bold(true);
+    r.wrapped(pharmacy_name);
+    r.line(if shift.closed_at.is_some() {
+        "Z-REPORT"
+    } else {
+        "X-REPORT"
+    });
+    r.bold(false);
+    r.align_center(false);
+    r.rule();
+
+    r.line(&format!("Shift: {}", shift.id));
+    r.wrapped(&format!("Cashier: {}", shift.cashier));
+    r.line(&format!("Opened: {}", shift.opened_at));
+    if let Some(closed_at) = &shift.closed_at {
+        r.line(&format!("Closed: {closed_at}"));
+    }
+    r.rule();
+
+    r.columns(
+        &format!("Sales ({})", report.sale_count),
+        &rupees(report.sales_total_paise),
+    );
+    let modes = |r: &mut Receipt, title: &str, totals: &PaymentModeTotals| {
+        let rows = [
+            ("Cash", totals.cash_paise),
+            ("Card", totals.card_paise),
+            ("UPI", totals.upi_paise),
+            ("Credit", totals.credit_paise),
+        ];
+        if rows.iter().all(|(_, paise)| *paise == 0) {
+            return;
+        }
+        r.line(title);
+        for (label, paise) in rows.iter().filter(|(_, paise)| *paise != 0) {
+            r.columns(&format!("  {label}"), &rupees(*paise));
+        }
+    };
+    modes(&mut r, "Taken", &report.sales);
+    modes(&mut r, "Received on account", &report.receipts);
+    modes(&mut r, "Refunded", &report.refunds);
+    modes(&mut r, "Paid out", &report.paid_out);
+    r.rule();
+
+    r.columns("Opening float", &rupees(shift.opening_float_paise));
+    let drawer = |r: &mut Receipt, title: &str, totals: &DrawerTotals| {
+        r.bold(true);
+        r.line(title);
+        r.bold(false);
+        r.columns("  Cash", &rupees(totals.cash_paise));
+        r.columns("  Card", &rupees(totals.card_paise));
+        r.columns("  UPI", &rupees(totals.upi_paise));
+    };
+    drawer(&mut r, "Expected", &report.expected);
+    if let (Some(counted), Some(variance)) = (&report.counted, &report.variance) {
+        drawer(&mut r, "Counted", counted);
+        drawer(&mut r, "Variance", variance);
+    }
+    if let Some(notes) = &shift.notes {
+        r.rule();
+        r.wrapped(notes);
+    }
+    r.line("");
+    r.cut()
+}
+
+/// Print the Z-report of a shift on the configured printer.
+pub fn print_shift_report(conn: &Connection, shift_id: i64) -> AppResult<()> {
+    let config = printer_config(conn)?;
+    let report = shifts::shift_report(conn, shift_id)?;
+    let pharmacy_name: String = conn.query_row(
+        "SELECT name FROM pharmacy_settings WHERE id = 1",
+        [],
+        |row| row.get(0),
+    )?;
+    send_to_printer(
+        &config,
+        &render_z_report(&report, &pharmacy_name, config.line_width),
+    )
+}
+
+/// Send raw bytes to the configured printer.
+pub fn send_to_printer(config: &PrinterConfig, bytes: &[u8]) -> AppResult<()> {
+    if config.interface != PrinterInterface::None && config.address.is_empty() {
+        return Err(AppError::validation("The receipt printer has no address"));
+    }
+    match config.interface {
+        PrinterInterface::None => {
+            return Err(AppError::validation("No receipt printer is configured"));
+        }
+        PrinterInterface::Usb | PrinterInterface::Serial => {
+            let mut device = OpenOptions::new().write(true).open(&config.address)?;
+            device.write_all(bytes)?;
+            device.flush()?;
+        }
+        PrinterInterface::Network => {
+            let addr = config.address.to_socket_addrs()?.next().ok_or_else(|| {
+                AppError::validation(format!("Cannot resolve printer {}", config.address))
+            })?;
+            let mut stream = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)?;
+            stream.write_all(bytes)?;
+            stream.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Print the receipt of a recorded sale on the configured printer.
+pub fn print_receipt(conn: &Connection, sale_id: i64) -> AppResult<()> {
+    let config = printer_config(conn)?;
+    let doc = invoice::get_invoice(conn, sale_id)?;
+    send_to_printer(&config, &render_receipt(&doc, config.line_width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{insert_batch, insert_medicine, migrated_db, paid_sale};
+    use crate::sales::{create_sale, PaymentMode};
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|w| w == needle)
+    }
+
+    #[test]
+    fn receipt_is_written_to_a_device_printer() {
+        let (dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let device = dir.path().join("lp0");
+        std::fs::write(&device, b"").unwrap();
+        conn.execute(
+            "UPDATE pharmacy_settings SET name = 'City Pharmacy', gstin = '27ABCDE1234F1Z5',
+                 printer_interface = 'usb', printer_address = ?1, printer_line_width = 32",
+            [device.to_str().unwrap()],
+        )
+        .unwrap();
+        let para = insert_medicine(&conn, "Paracetamol 500mg", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 1_120, 10);
+        let sale = paid_sale(&conn, None, PaymentMode::Cash, batch, 3);
+        let sale_id = db.with_tx(|tx| create_sale(tx, &sale)).unwrap().sale_id;
+
+        print_receipt(&conn, sale_id).unwrap();
+
+        let bytes = std::fs::read(&device).unwrap();
+        assert!(bytes.starts_with(&[ESC, b'@']));
+        assert!(bytes.ends_with(&[GS, b'V', 66, 3]));
+        assert!(contains(&bytes, b"City Pharmacy"));
+        assert!(contains(&bytes, b"Paracetamol 500mg"));
+        assert!(contains(&bytes, b"GSTIN:27ABCDE1234F1Z5;INV:"));
+        let text = String::from_utf8_lossy(&bytes);
+        let plain = text.lines().filter(|l| !l.contains(['\x1b', '\x1d']));
+        assert!(plain.clone().count() > 10);
+        assert!(plain.clone().all(|l| l.len() <= 32), "{text}");
+    }
+
+    #[test]
+    fn z_report_shows_the_variance_and_fits_the_line() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+90 days", 5_000, 10);
+        let shift = db
+            .with_tx(|tx| shifts::open_shift(tx, 1, 1_000, None))
+            .unwrap();
+        let sale = paid_sale(&conn, None, PaymentMode::Cash, batch, 1);
+        db.with_tx(|tx| create_sale(tx, &sale)).unwrap();
+        let counted = DrawerTotals {
+            cash_paise: 6_200,
+            ..Default::default()
+        };
+        let report = db
+            .with_tx(|tx| shifts::close_shift(tx, shift.id, counted, None, 1))
+            .unwrap();
+
+        let bytes = render_z_report(&report, "City Pharmacy", 32);
+
+        let text = String::from_utf8_lossy(&bytes);
+        assert!(text.contains("Z-REPORT"), "{text}");
+        assert!(text.contains("Variance"), "{text}");
+        assert_eq!(report.variance.unwrap().cash_paise, 200);
+        let plain = text.lines().filter(|l| !l.contains(['\x1b', '\x1d']));
+        assert!(plain.clone().all(|l| l.len() <= 32), "{text}");
+    }
+
+    #[test]
+    fn printing_needs_a_configured_printer() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let config = printer_config(&conn).unwrap();
+        assert_eq!(config.interface, PrinterInterface::None);
+        let err = send_to_printer(&config, b"x").unwrap_err();
+        assert!(err.to_string().contains("No receipt printer"), "{err}");
+    }
+}
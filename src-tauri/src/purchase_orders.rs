@@ -0,0 +1,557 @@
+//! Purchase orders raised with suppliers, from draft to closed.
+//!
+//! An order is drafted, approved, then sent to the supplier as a printed or
+//! emailed document. Goods received against a sent order count towards its
+//! lines; the order closes itself once everything has arrived, or can be
+//! closed early when the supplier will not send the rest.
+
+use std::collections::HashSet;
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::auth::{self, permission};
+use crate::db::{ChangeOp, Entity, Tx};
+use crate::error::{AppError, AppResult};
+use crate::invoice::{self, InvoiceHeader};
+use crate::purchases::{self, PurchaseLine, PurchaseResult};
+use crate::sync;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PoStatus {
+    Draft,
+    Approved,
+    Sent,
+    PartiallyReceived,
+    Closed,
+}
+
+impl PoStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Draft => "draft",
+            Self::Approved => "approved",
+            Self::Sent => "sent",
+            Self::PartiallyReceived => "partially_received",
+            Self::Closed => "closed",
+        }
+    }
+
+    pub fn parse(s: &str) -> AppResult<Self> {
+        match s {
+            "draft" => Ok(Self::Draft),
+            "approved" => Ok(Self::Approved),
+            "sent" => Ok(Self::Sent),
+            "partially_received" => Ok(Self::PartiallyReceived),
+            "closed" => Ok(Self::Closed),
+            other => Err(AppError::validation(format!(
+                "Unknown purchase order status '{other}'"
+            ))),
+        }
+    }
+}
+
+/// One medicine asked for on a new order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PurchaseOrderLine {
+    pub medicine_id: i64,
+    pub quantity: i64,
+    /// The cost the supplier quoted, if any.
+    #[serde(default)]
+    pub expected_cost_paise: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PurchaseOrderItem {
+    pub medicine_id: i64,
+    pub medicine_name: String,
+    pub quantity: i64,
+    pub received_quantity: i64,
+    pub expected_cost_paise: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PurchaseOrder {
+    pub id: i64,
+    pub po_number: String,
+    pub supplier_id: i64,
+    pub supplier_name: String,
+    pub status: PoStatus,
+    pub notes: Option<String>,
+    pub created_at: String,
+    pub approved_at: Option<String>,
+    pub sent_at: Option<String>,
+    pub closed_at: Option<String>,
+    pub items: Vec<PurchaseOrderItem>,
+    /// Sum of quantity × expected cost over the lines.
+    pub expected_total_paise: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SupplierContact {
+    pub name: String,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub address: Option<String>,
+    pub gstin: Option<String>,
+}
+
+/// Everything needed to print an order or email it to the supplier.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PurchaseOrderDocument {
+    pub pharmacy: InvoiceHeader,
+    pub supplier: SupplierContact,
+    pub order: PurchaseOrder,
+}
+
+fn order_from_row(row: &Row) -> AppResult<PurchaseOrder> {
+    let status: String = row.get(4)?;
+    Ok(PurchaseOrder {
+        id: row.get(0)?,
+        po_number: row.get(1)?,
+        supplier_id: row.get(2)?,
+        supplier_name: row.get(3)?,
+        status: PoStatus::parse(&status)?,
+        notes: row.get(5)?,
+        created_at: row.get(6)?,
+        approved_at: row.get(7)?,
+        sent_at: row.get(8)?,
+        closed_at: row.get(9)?,
+        items: Vec::new(),
+        expected_total_paise: 0,
+    })
+}
+
+/// Load an order with its lines.
+pub fn get_purchase_order(conn: &Connection, order_id: i64) -> AppResult<PurchaseOrder> {
+    let mut stmt = conn.prepare(
+        "SELECT po.id, po.po_number, po.supplier_id, s.name, po.status, po.notes,
+             po.created_at, po.approved_at, po.sent_at, po.closed_at
+         FROM purchase_orders po JOIN suppliers s ON s.id = po.supplier_id
+         WHERE po.id = ?1",
+    )?;
+    let mut rows = stmt.query(params![order_id])?;
+    let mut order = rows
+        .next()?
+        .map(order_from_row)
+        .transpose()?
+        .ok_or_else(|| AppError::not_found(format!("Purchase order {order_id}")))?;
+
+    let mut stmt = conn.prepare(
+        "SELECT i.medicine_id, m.name, i.quantity, i.received_quantity, i.expected_cost_paise
+         FROM purchase_order_items i JOIN medicines m ON m.id = i.medicine_id
+         WHERE i.purchase_order_id = ?1
+         ORDER BY i.id",
+    )?;
+    order.items = stmt
+        .query_map(params![order_id], |row| {
+            Ok(PurchaseOrderItem {
+                medicine_id: row.get(0)?,
+                medicine_name: row.get(1)?,
+                quantity: row.get(2)?,
+                received_quantity: row.get(3)?,
+                expected_cost_paise: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    order.expected_total_paise = order
+        .items
+        .iter()
+        .map(|i| i.quantity * i.expected_cost_paise)
+        .sum();
+    Ok(order)
+}
+
+/// The printable order: the pharmacy's letterhead, the supplier's contact
+/// details and the lines.
+pub fn purchase_order_document(
+    conn: &Connection,
+    order_id: i64,
+) -> AppResult<PurchaseOrderDocument> {
+    let order = get_purchase_order(conn, order_id)?;
+    let supplier = conn.query_row(
+        "SELECT name, phone, email, address, gst_in FROM suppliers WHERE id = ?1",
+        params![order.supplier_id],
+        |row| {
+            Ok(SupplierContact {
+                name: row.get(0)?,
+                phone: row.get(1)?,
+                email: row.get(2)?,
+                address: row.get(3)?,
+                gstin: row.get(4)?,
+            })
+        },
+    )?;
+    Ok(PurchaseOrderDocument {
+        pharmacy: invoice::pharmacy_header(conn)?,
+        supplier,
+        order,
+    })
+}
+
+/// Draft a new order for `supplier_id`.
+pub fn create_purchase_order(
+    tx: &Tx,
+    supplier_id: i64,
+    lines: &[PurchaseOrderLine],
+    notes: Option<&str>,
+    user_id: i64,
+) -> AppResult<PurchaseOrder> {
+    auth::require_permission(tx, user_id, permission::PURCHASES_ORDER)?;
+    if lines.is_empty() {
+        return Err(AppError::validation(
+            "A purchase order must have at least one line",
+        ));
+    }
+    let supplier_exists: bool = tx.query_row(
+        "SELECT EXISTS (SELECT 1 FROM suppliers WHERE id = ?1)",
+        params![supplier_id],
+        |row| row.get(0),
+    )?;
+    if !supplier_exists {
+        return Err(AppError::not_found(format!("Supplier {supplier_id}")));
+    }
+    let mut seen = HashSet::new();
+    for (index, line) in lines.iter().enumerate() {
+        if line.quantity <= 0 {
+            return Err(AppError::validation(format!(
+                "Line {}: quantity must be greater than 0",
+                index + 1
+            )));
+        }
+        if line.expected_cost_paise < 0 {
+            return Err(AppError::validation(format!(
+                "Line {}: cost cannot be negative",
+                index + 1
+            )));
+        }
+        let name: String = tx
+            .query_row(
+                "SELECT name FROM medicines WHERE id = ?1",
+                params![line.medicine_id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| AppError::not_found(format!("Medicine {}", line.medicine_id)))?;
+        if !seen.insert(line.medicine_id) {
+            return Err(AppError::validation(format!(
+                "{name} is listed more than once"
+            )));
+        }
+    }
+
+    let next: i64 = tx.query_row(
+        "SELECT COALESCE(MAX(id), 0) + 1 FROM purchase_orders",
+        [],
+        |row| row.get(0),
+    )?;
+    let notes = notes.map(str::trim).filter(|n| !n.is_empty());
+    tx.execute(
+        "INSERT INTO purchase_orders (po_number, supplier_id, notes, created_by)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![format!("PO-{next:06}"), supplier_id, notes, user_id],
+    )?;
+    let order_id = tx.last_insert_rowid();
+    let mut insert = tx.prepare_cached(
+        "INSERT INTO purchase_order_items
+             (purchase_order_id, medicine_id, quantity, expected_cost_paise)
+         VALUES (?1, ?2, ?3, ?4)",
+    )?;
+    for line in lines {
+        insert.execute(params![
+            order_id,
+            line.medicine_id,
+            line.quantity,
+            line.expected_cost_paise
+        ])?;
+    }
+
+    tx.changed(Entity::PurchaseOrder, ChangeOp::Insert, order_id);
+    sync::enqueue(
+        tx,
+        "create_purchase_order",
+        &json!({
+            "purchaseOrderId": order_id,
+            "supplierId": supplier_id,
+            "lines": lines,
+            "notes": notes,
+        }),
+    )?;
+    get_purchase_order(tx, order_id)
+}
+
+fn current_status(tx: &Tx, order_id: i64) -> AppResult<PoStatus> {
+    let status: String = tx
+        .query_row(
+            "SELECT status FROM purchase_orders WHERE id = ?1",
+            params![order_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Purchase order {order_id}")))?;
+    PoStatus::parse(&status)
+}
+
+/// Move an order from one of `from` to `to`, stamping `stamp_column`.
+fn transition(
+    tx: &Tx,
+    order_id: i64,
+    from: &[PoStatus],
+    to: PoStatus,
+    stamp_column: &str,
+) -> AppResult<()> {
+    let status = current_status(tx, order_id)?;
+    if !from.contains(&status) {
+        return Err(AppError::validation(format!(
+            "A {} purchase order cannot be marked {}",
+            status.as_str().replace('_', " "),
+            to.as_str().replace('_', " ")
+        )));
+    }
+    tx.execute(
+        &format!(
+            "UPDATE purchase_orders SET status = ?1, {stamp_column} = datetime('now') WHERE id = ?2"
+        ),
+        params![to.as_str(), order_id],
+    )?;
+    tx.changed(Entity::PurchaseOrder, ChangeOp::Update, order_id);
+    sync::enqueue(
+        tx,
+        "set_purchase_order_status",
+        &json!({ "purchaseOrderId": order_id, "status": to }),
+    )?;
+    Ok(())
+}
+
+/// Approve a draft so it can be sent.
+pub fn approve_purchase_order(tx: &Tx, order_id: i64, user_id: i64) -> AppResult<PurchaseOrder> {
+    auth::require_permission(tx, user_id, permission::PURCHASES_APPROVE)?;
+    transition(
+        tx,
+        order_id,
+        &[PoStatus::Draft],
+        PoStatus::Approved,
+        "approved_at",
+    )?;
+    tx.execute(
+        "UPDATE purchase_orders SET approved_by = ?1 WHERE id = ?2",
+        params![user_id, order_id],
+    )?;
+    get_purchase_order(tx, order_id)
+}
+
+/// Record that an approved order has gone to the supplier, and return the
+/// document that was printed or emailed.
+pub fn send_purchase_order(
+    tx: &Tx,
+    order_id: i64,
+    user_id: i64,
+) -> AppResult<PurchaseOrderDocument> {
+    auth::require_permission(tx, user_id, permission::PURCHASES_ORDER)?;
+    transition(
+        tx,
+        order_id,
+        &[PoStatus::Approved],
+        PoStatus::Sent,
+        "sent_at",
+    )?;
+    purchase_order_document(tx, order_id)
+}
+
+/// Close an order, whatever is still outstanding on it. A draft or approved
+/// order that is closed was never sent.
+pub fn close_purchase_order(tx: &Tx, order_id: i64, user_id: i64) -> AppResult<PurchaseOrder> {
+    auth::require_permission(tx, user_id, permission::PURCHASES_ORDER)?;
+    transition(
+        tx,
+        order_id,
+        &[
+            PoStatus::Draft,
+            PoStatus::Approved,
+            PoStatus::Sent,
+            PoStatus::PartiallyReceived,
+        ],
+        PoStatus::Closed,
+        "closed_at",
+    )?;
+    get_purchase_order(tx, order_id)
+}
+
+/// Receive a supplier invoice against a sent order.
+///
+/// Every line must be for a medicine on the order and may not take it past
+/// the quantity ordered. The order becomes partially received, or closes
+/// once every line has arrived in full.
+pub fn receive_purchase_order(
+    tx: &Tx,
+    order_id: i64,
+    invoice_number: &str,
+    lines: &[PurchaseLine],
+    user_id: i64,
+) -> AppResult<PurchaseResult> {
+    let status = current_status(tx, order_id)?;
+    if !matches!(status, PoStatus::Sent | PoStatus::PartiallyReceived) {
+        return Err(AppError::validation(format!(
+            "Goods can only be received against a sent purchase order, not a {} one",
+            status.as_str().replace('_', " ")
+        )));
+    }
+    let supplier_id: i64 = tx.query_row(
+        "SELECT supplier_id FROM purchase_orders WHERE id = ?1",
+        params![order_id],
+        |row| row.get(0),
+    )?;
+    let result = purchases::receive_purchase(tx, supplier_id, invoice_number, lines, user_id)?;
+    tx.execute(
+        "UPDATE purchases SET purchase_order_id = ?1 WHERE id = ?2",
+        params![order_id, result.purchase_id],
+    )?;
+    tx.execute(
+        "UPDATE purchase_items SET purchase_order_item_id = (
+             SELECT i.id FROM purchase_order_items i
+             WHERE i.purchase_order_id = ?1 AND i.medicine_id = purchase_items.medicine_id)
+         WHERE purchase_id = ?2",
+        params![order_id, result.purchase_id],
+    )?;
+
+    for line in lines {
+        let outstanding: Option<(String, i64)> = tx
+            .query_row(
+                "SELECT m.name, i.quantity - i.received_quantity
+                 FROM purchase_order_items i JOIN medicines m ON m.id = i.medicine_id
+                 WHERE i.purchase_order_id = ?1 AND i.medicine_id = ?2",
+                params![order_id, line.medicine_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        match outstanding {
+            None => {
+                return Err(AppError::validation(format!(
+                    "Medicine {} is not on this purchase order",
+                    line.medicine_id
+                )));
+            }
+            Some((name, left)) if line.quantity > left => {
+                return Err(AppError::validation(format!(
+                    "Only {left} more of {name} were ordered"
+                )));
+            }
+            Some(_) => {
+                tx.execute(
+                    "UPDATE purchase_order_items SET received_quantity = received_quantity + ?1
+                     WHERE purchase_order_id = ?2 AND medicine_id = ?3",
+                    params![line.quantity, order_id, line.medicine_id],
+                )?;
+            }
+        }
+    }
+
+    let complete: bool = tx.query_row(
+        "SELECT NOT EXISTS (SELECT 1 FROM purchase_order_items
+                            WHERE purchase_order_id = ?1 AND received_quantity < quantity)",
+        params![order_id],
+        |row| row.get(0),
+    )?;
+    if complete {
+        transition(
+            tx,
+            order_id,
+            &[PoStatus::Sent, PoStatus::PartiallyReceived],
+            PoStatus::Closed,
+            "closed_at",
+        )?;
+    } else if status == PoStatus::Sent {
+        tx.execute(
+            "UPDATE purchase_orders SET status = ?1 WHERE id = ?2",
+            params![PoStatus::PartiallyReceived.as_str(), order_id],
+        )?;
+        tx.changed(Entity::PurchaseOrder, ChangeOp::Update, order_id);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{insert_medicine, migrated_db};
+
+    const ADMIN: i64 = 1;
+
+    fn received(medicine_id: i64, batch_number: &str, quantity: i64) -> PurchaseLine {
+        PurchaseLine {
+            medicine_id,
+            batch_number: batch_number.into(),
+            expiry_date: "2099-12-31".into(),
+            cost_price_paise: 700,
+            mrp_paise: 1_000,
+            selling_price_paise: 950,
+            quantity,
+        }
+    }
+
+    #[test]
+    fn order_moves_from_draft_to_closed_as_goods_arrive() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        conn.execute("INSERT INTO suppliers (name) VALUES ('Medline')", [])
+            .unwrap();
+        let supplier = conn.last_insert_rowid();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let ordered = [PurchaseOrderLine {
+            medicine_id: para,
+            quantity: 10,
+            expected_cost_paise: 700,
+        }];
+
+        let order = db
+            .with_tx(|tx| create_purchase_order(tx, supplier, &ordered, None, ADMIN))
+            .unwrap();
+        assert_eq!(order.po_number, "PO-000001");
+        assert_eq!(order.expected_total_paise, 7_000);
+
+        let early = db
+            .with_tx(|tx| {
+                receive_purchase_order(tx, order.id, "INV-1", &[received(para, "PC01", 4)], ADMIN)
+            })
+            .unwrap_err();
+        assert!(early.to_string().contains("sent purchase order"), "{early}");
+
+        db.with_tx(|tx| approve_purchase_order(tx, order.id, ADMIN))
+            .unwrap();
+        let doc = db
+            .with_tx(|tx| send_purchase_order(tx, order.id, ADMIN))
+            .unwrap();
+        assert_eq!(doc.supplier.name, "Medline");
+        assert_eq!(doc.order.status, PoStatus::Sent);
+
+        db.with_tx(|tx| {
+            receive_purchase_order(tx, order.id, "INV-1", &[received(para, "PC01", 4)], ADMIN)
+        })
+        .unwrap();
+        assert_eq!(
+            get_purchase_order(&conn, order.id).unwrap().status,
+            PoStatus::PartiallyReceived
+        );
+        let over = db
+            .with_tx(|tx| {
+                receive_purchase_order(tx, order.id, "INV-2", &[received(para, "PC02", 7)], ADMIN)
+            })
+            .unwrap_err();
+        assert!(over.to_string().contains("Only 6 more"), "{over}");
+
+        db.with_tx(|tx| {
+            receive_purchase_order(tx, order.id, "INV-2", &[received(para, "PC02", 6)], ADMIN)
+        })
+        .unwrap();
+        let done = get_purchase_order(&conn, order.id).unwrap();
+        assert_eq!(done.status, PoStatus::Closed);
+        assert_eq!(done.items[0].received_quantity, 10);
+    }
+}
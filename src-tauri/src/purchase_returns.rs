@@ -0,0 +1,518 @@
+//! Stock sent back to suppliers, each return issued as a debit note.
+//!
+//! Near-expiry, expired or damaged units are taken out of their batch and
+//! the debit note claims back their cost with its GST. The note stays
+//! pending until the supplier credits us for it; from then it counts
+//! against what we owe them in the supplier ledger.
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::auth::{self, permission};
+use crate::db::{ChangeOp, Entity, Tx};
+use crate::error::{AppError, AppResult};
+use crate::gst;
+use crate::gst_history;
+use crate::inventory::{self, MovementReason};
+use crate::locations;
+use crate::sales;
+use crate::sync;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PurchaseReturnReason {
+    NearExpiry,
+    Expired,
+    Damaged,
+    Other,
+}
+
+impl PurchaseReturnReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::NearExpiry => "near_expiry",
+            Self::Expired => "expired",
+            Self::Damaged => "damaged",
+            Self::Other => "other",
+        }
+    }
+
+    pub fn parse(s: &str) -> AppResult<Self> {
+        match s {
+            "near_expiry" => Ok(Self::NearExpiry),
+            "expired" => Ok(Self::Expired),
+            "damaged" => Ok(Self::Damaged),
+            "other" => Ok(Self::Other),
+            other => Err(AppError::validation(format!(
+                "Unknown purchase return reason '{other}'"
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DebitNoteStatus {
+    /// Sent with the goods; the supplier has not credited us yet.
+    Pending,
+    Credited,
+}
+
+impl DebitNoteStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Credited => "credited",
+        }
+    }
+
+    pub fn parse(s: &str) -> AppResult<Self> {
+        match s {
+            "pending" => Ok(Self::Pending),
+            "credited" => Ok(Self::Credited),
+            other => Err(AppError::validation(format!(
+                "Unknown debit note status '{other}'"
+            ))),
+        }
+    }
+}
+
+/// Units of one batch being sent back.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PurchaseReturnLine {
+    pub batch_id: i64,
+    pub quantity: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DebitNoteItem {
+    pub batch_id: i64,
+    pub medicine_id: i64,
+    pub medicine_name: String,
+    pub batch_number: String,
+    pub expiry_date: String,
+    pub quantity: i64,
+    pub cost_price_paise: i64,
+    pub gst_rate: f64,
+    pub taxable_amount_paise: i64,
+    pub cgst_amount_paise: i64,
+    pub sgst_amount_paise: i64,
+    pub igst_amount_paise: i64,
+    pub total_paise: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DebitNote {
+    pub id: i64,
+    pub debit_note_number: String,
+    pub supplier_id: i64,
+    pub supplier_name: String,
+    pub supplier_gstin: Option<String>,
+    pub reason: PurchaseReturnReason,
+    pub notes: Option<String>,
+    pub status: DebitNoteStatus,
+    /// The supplier's credit note number, once credited.
+    pub credit_reference: Option<String>,
+    pub credited_at: Option<String>,
+    pub return_date: String,
+    pub taxable_amount_paise: i64,
+    pub total_cgst_paise: i64,
+    pub total_sgst_paise: i64,
+    pub total_igst_paise: i64,
+    pub total_gst_paise: i64,
+    pub total_paise: i64,
+    pub items: Vec<DebitNoteItem>,
+}
+
+const DEBIT_NOTE_COLUMNS: &str = "r.id, r.debit_note_number, r.supplier_id, s.name, s.gst_in,
+     r.reason, r.notes, r.status, r.credit_reference, r.credited_at, r.return_date,
+     r.taxable_amount_paise, r.total_cgst_paise, r.total_sgst_paise, r.total_igst_paise,
+     r.total_gst_paise, r.total_paise";
+
+fn debit_note_from_row(row: &Row) -> AppResult<DebitNote> {
+    let reason: String = row.get(5)?;
+    let status: String = row.get(7)?;
+    Ok(DebitNote {
+        id: row.get(0)?,
+        debit_note_number: row.get(1)?,
+        supplier_id: row.get(2)?,
+        supplier_name: row.get(3)?,
+        supplier_gstin: row.get(4)?,
+        reason: PurchaseReturnReason::parse(&reason)?,
+        notes: row.get(6)?,
+        status: DebitNoteStatus::parse(&status)?,
+        credit_reference: row.get(8)?,
+        credited_at: row.get(9)?,
+        return_date: row.get(10)?,
+        taxable_amount_paise: row.get(11)?,
+        total_cgst_paise: row.get(12)?,
+        total_sgst_paise: row.get(13)?,
+        total_igst_paise: row.get(14)?,
+        total_gst_paise: row.get(15)?,
+        total_paise: row.get(16)?,
+        items: Vec::new(),
+    })
+}
+
+fn load_items(conn: &Connection, note: &mut DebitNote) -> AppResult<()> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT i.batch_id, i.medicine_id, m.name, b.batch_number, b.expiry_date, i.quantity,
+             i.cost_price_paise, i.gst_rate, i.taxable_amount_paise, i.cgst_amount_paise,
+             i.sgst_amount_paise, i.igst_amount_paise, i.total_paise
+         FROM purchase_return_items i
+         JOIN medicines m ON m.id = i.medicine_id
+         JOIN batches b ON b.id = i.batch_id
+         WHERE i.return_id = ?1
+         ORDER BY i.id",
+    )?;
+    let items = stmt.query_map(params![note.id], |row| {
+        Ok(DebitNoteItem {
+            batch_id: row.get(0)?,
+            medicine_id: row.get(1)?,
+            medicine_name: row.get(2)?,
+            batch_number: row.get(3)?,
+            expiry_date: row.get(4)?,
+            quantity: row.get(5)?,
+            cost_price_paise: row.get(6)?,
+            gst_rate: row.get(7)?,
+            taxable_amount_paise: row.get(8)?,
+            cgst_amount_paise: row.get(9)?,
+            sgst_amount_paise: row.get(10)?,
+            igst_amount_paise: row.get(11)?,
+            total_paise: row.get(12)?,
+        })
+    })?;
+    note.items = items.collect::<Result<_, _>>()?;
+    Ok(())
+}
+
+/// Load a debit note with its lines.
+pub fn get_debit_note(conn: &Connection, return_id: i64) -> AppResult<DebitNote> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {DEBIT_NOTE_COLUMNS}
+         FROM purchase_returns r JOIN suppliers s ON s.id = r.supplier_id
+         WHERE r.id = ?1"
+    ))?;
+    let mut rows = stmt.query(params![return_id])?;
+    let mut note = rows
+        .next()?
+        .map(debit_note_from_row)
+        .transpose()?
+        .ok_or_else(|| AppError::not_found(format!("Debit note {return_id}")))?;
+    load_items(conn, &mut note)?;
+    Ok(note)
+}
+
+/// Debit notes, newest first, optionally for one supplier or in one status.
+pub fn list_debit_notes(
+    conn: &Connection,
+    supplier_id: Option<i64>,
+    status: Option<DebitNoteStatus>,
+) -> AppResult<Vec<DebitNote>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {DEBIT_NOTE_COLUMNS}
+         FROM purchase_returns r JOIN suppliers s ON s.id = r.supplier_id
+         WHERE (?1 IS NULL OR r.supplier_id = ?1) AND (?2 IS NULL OR r.status = ?2)
+         ORDER BY r.id DESC"
+    ))?;
+    let mut rows = stmt.query(params![supplier_id, status.map(DebitNoteStatus::as_str)])?;
+    let mut notes = Vec::new();
+    while let Some(row) = rows.next()? {
+        let mut note = debit_note_from_row(row)?;
+        load_items(conn, &mut note)?;
+        notes.push(note);
+    }
+    Ok(notes)
+}
+
+/// Whether goods go back across a state border: the supplier's GSTIN
+/// prefix against the pharmacy's state code. A supplier without a GSTIN
+/// is treated as intra-state, as walk-in customers are on sales.
+fn return_is_interstate(tx: &Tx, supplier_id: i64) -> AppResult<Option<bool>> {
+    Ok(tx
+        .query_row(
+            "SELECT COALESCE(substr(s.gst_in, 1, 2), ''), p.state_code
+             FROM suppliers s, pharmacy_settings p
+             WHERE s.id = ?1 AND p.id = 1",
+            params![supplier_id],
+            |row| {
+                let supplier: String = row.get(0)?;
+                let pharmacy: String = row.get(1)?;
+                Ok(!supplier.is_empty() && !pharmacy.is_empty() && supplier != pharmacy)
+            },
+        )
+        .optional()?)
+}
+
+/// Send units of specific batches back to `supplier_id` and raise a debit
+/// note for them.
+///
+/// Each line is valued at its batch's cost price, which, like the purchase
+/// it came in on, includes GST; the tax is backed out at the medicine's
+/// current rate and reversed as CGST + SGST or IGST.
+pub fn create_purchase_return(
+    tx: &Tx,
+    supplier_id: i64,
+    reason: PurchaseReturnReason,
+    notes: Option<&str>,
+    lines: &[PurchaseReturnLine],
+    user_id: i64,
+) -> AppResult<DebitNote> {
+    auth::require_permission(tx, user_id, permission::PURCHASES_RECEIVE)?;
+    if lines.is_empty() {
+        return Err(AppError::validation(
+            "A return to a supplier must have at least one line",
+        ));
+    }
+    let interstate = return_is_interstate(tx, supplier_id)?
+        .ok_or_else(|| AppError::not_found(format!("Supplier {supplier_id}")))?;
+    let notes = notes.map(str::trim).filter(|n| !n.is_empty());
+
+    let debit_note_number = sales::allocate_debit_note_number(tx)?;
+    tx.execute(
+        "INSERT INTO purchase_returns (debit_note_number, supplier_id, reason, notes, user_id)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            debit_note_number,
+            supplier_id,
+            reason.as_str(),
+            notes,
+            user_id
+        ],
+    )?;
+    let return_id = tx.last_insert_rowid();
+    let today: String = tx.query_row("SELECT date('now')", [], |row| row.get(0))?;
+    let movement_note = format!("Returned to supplier on debit note {debit_note_number}");
+
+    let mut amounts = Vec::with_capacity(lines.len());
+    for line in lines {
+        if line.quantity <= 0 {
+            return Err(AppError::validation(
+                "Returned quantity must be greater than 0",
+            ));
+        }
+        let (medicine_id, batch_number, cost_price_paise): (i64, String, i64) = tx
+            .query_row(
+                "SELECT medicine_id, batch_number, cost_price_paise FROM batches WHERE id = ?1",
+                params![line.batch_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?
+            .ok_or_else(|| AppError::not_found(format!("Batch {}", line.batch_id)))?;
+        if !locations::take_from_floor(tx, line.batch_id, line.quantity)? {
+            return Err(AppError::validation(format!(
+                "Batch {batch_number} has fewer than {} units on the selling floor to return",
+                line.quantity
+            )));
+        }
+        inventory::record_noted_movement(
+            tx,
+            line.batch_id,
+            -line.quantity,
+            MovementReason::Adjustment,
+            Some(return_id),
+            Some(&movement_note),
+            None,
+        )?;
+        tx.changed(Entity::Batch, ChangeOp::Update, line.batch_id);
+
+        let gst_rate = gst_history::rate_on(tx, medicine_id, &today)?;
+        let amount = gst::calculate_line(cost_price_paise, line.quantity, gst_rate, 0, interstate);
+        tx.execute(
+            "INSERT INTO purchase_return_items (return_id, batch_id, medicine_id, quantity,
+                 cost_price_paise, gst_rate, taxable_amount_paise, cgst_amount_paise,
+                 sgst_amount_paise, igst_amount_paise, total_paise)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                return_id,
+                line.batch_id,
+                medicine_id,
+                line.quantity,
+                cost_price_paise,
+                gst_rate,
+                amount.taxable_amount_paise,
+                amount.gst.cgst_paise,
+                amount.gst.sgst_paise,
+                amount.gst.igst_paise,
+                amount.total_paise,
+            ],
+        )?;
+        amounts.push(amount);
+    }
+
+    let sum = |f: fn(&gst::LineAmounts) -> i64| amounts.iter().map(f).sum::<i64>();
+    tx.execute(
+        "UPDATE purchase_returns SET taxable_amount_paise = ?1, total_cgst_paise = ?2,
+             total_sgst_paise = ?3, total_igst_paise = ?4, total_gst_paise = ?5,
+             total_paise = ?6
+         WHERE id = ?7",
+        params![
+            sum(|a| a.taxable_amount_paise),
+            sum(|a| a.gst.cgst_paise),
+            sum(|a| a.gst.sgst_paise),
+            sum(|a| a.gst.igst_paise),
+            sum(|a| a.gst.total_gst_paise),
+            sum(|a| a.total_paise),
+            return_id,
+        ],
+    )?;
+    tx.changed(Entity::DebitNote, ChangeOp::Insert, return_id);
+    sync::enqueue(
+        tx,
+        "create_purchase_return",
+        &json!({
+            "supplierId": supplier_id,
+            "reason": reason,
+            "notes": notes,
+            "lines": lines,
+            "userId": user_id,
+        }),
+    )?;
+    get_debit_note(tx, return_id)
+}
+
+/// Record that the supplier has credited us for a debit note, under their
+/// own credit note number.
+pub fn mark_debit_note_credited(
+    tx: &Tx,
+    return_id: i64,
+    credit_reference: &str,
+    user_id: i64,
+) -> AppResult<DebitNote> {
+    auth::require_permission(tx, user_id, permission::PURCHASES_RECEIVE)?;
+    let credit_reference = credit_reference.trim();
+    if credit_reference.is_empty() {
+        return Err(AppError::validation(
+            "The supplier's credit note number is required",
+        ));
+    }
+    let (number, status): (String, String) = tx
+        .query_row(
+            "SELECT debit_note_number, status FROM purchase_returns WHERE id = ?1",
+            params![return_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Debit note {return_id}")))?;
+    if DebitNoteStatus::parse(&status)? == DebitNoteStatus::Credited {
+        return Err(AppError::validation(format!(
+            "Debit note {number} has already been credited"
+        )));
+    }
+    tx.execute(
+        "UPDATE purchase_returns SET status = 'credited', credit_reference = ?1,
+             credited_at = datetime('now')
+         WHERE id = ?2",
+        params![credit_reference, return_id],
+    )?;
+    tx.changed(Entity::DebitNote, ChangeOp::Update, return_id);
+    sync::enqueue(
+        tx,
+        "mark_debit_note_credited",
+        &json!({ "returnId": return_id, "creditReference": credit_reference }),
+    )?;
+    get_debit_note(tx, return_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{batch_quantity, insert_batch, insert_medicine, migrated_db};
+    use crate::suppliers::{supplier_ledger, LedgerEntryKind};
+
+    const ADMIN: i64 = 1;
+
+    #[test]
+    fn returned_stock_is_debited_until_the_supplier_credits_it() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        conn.execute(
+            "INSERT INTO suppliers (name, gst_in) VALUES ('Medline', '27AAAAA0000A1Z5')",
+            [],
+        )
+        .unwrap();
+        let supplier = conn.last_insert_rowid();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 1_120, 10);
+        conn.execute(
+            "UPDATE batches SET cost_price_paise = 560 WHERE id = ?1",
+            [batch],
+        )
+        .unwrap();
+        let line = PurchaseReturnLine {
+            batch_id: batch,
+            quantity: 4,
+        };
+
+        let note = db
+            .with_tx(|tx| {
+                create_purchase_return(
+                    tx,
+                    supplier,
+                    PurchaseReturnReason::NearExpiry,
+                    None,
+                    std::slice::from_ref(&line),
+                    ADMIN,
+                )
+            })
+            .unwrap();
+        assert_eq!(note.debit_note_number, "DN-000001");
+        assert_eq!(note.status, DebitNoteStatus::Pending);
+        assert_eq!(
+            (note.taxable_amount_paise, note.total_gst_paise),
+            (2_000, 240)
+        );
+        assert_eq!(note.total_cgst_paise + note.total_sgst_paise, 240);
+        assert_eq!(batch_quantity(&conn, batch), 6);
+        let ledger: i64 = inventory::batch_movement_history(&conn, batch)
+            .unwrap()
+            .iter()
+            .map(|m| m.change_qty)
+            .sum();
+        assert_eq!(ledger, 6);
+
+        let too_many = PurchaseReturnLine {
+            quantity: 7,
+            ..line
+        };
+        let err = db
+            .with_tx(|tx| {
+                create_purchase_return(
+                    tx,
+                    supplier,
+                    PurchaseReturnReason::Damaged,
+                    None,
+                    &[too_many],
+                    ADMIN,
+                )
+            })
+            .unwrap_err();
+        assert!(err.to_string().contains("fewer than 7"), "{err}");
+
+        let today: String = conn
+            .query_row("SELECT date('now')", [], |r| r.get(0))
+            .unwrap();
+        let before = supplier_ledger(&conn, supplier, &today, &today).unwrap();
+        assert!(before.entries.is_empty());
+
+        let credited = db
+            .with_tx(|tx| mark_debit_note_credited(tx, note.id, "MCN-88", ADMIN))
+            .unwrap();
+        assert_eq!(credited.status, DebitNoteStatus::Credited);
+        let err = db
+            .with_tx(|tx| mark_debit_note_credited(tx, note.id, "MCN-88", ADMIN))
+            .unwrap_err();
+        assert!(err.to_string().contains("already been credited"), "{err}");
+
+        let after = supplier_ledger(&conn, supplier, &today, &today).unwrap();
+        assert_eq!(after.entries[0].kind, LedgerEntryKind::DebitNote);
+        assert_eq!(after.closing_balance_paise, -note.total_paise);
+        let pending =
+            list_debit_notes(&conn, Some(supplier), Some(DebitNoteStatus::Pending)).unwrap();
+        assert!(pending.is_empty());
+    }
+}
@@ -0,0 +1,465 @@
+//! Goods received from suppliers.
+
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::auth::{self, permission};
+use crate::db::{ChangeOp, Entity, Tx};
+use crate::error::{AppError, AppResult};
+use crate::inventory::{self, MovementReason, EXPIRED};
+use crate::purchase_orders;
+use crate::sync;
+
+/// One batch line of a supplier invoice.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PurchaseLine {
+    pub medicine_id: i64,
+    pub batch_number: String,
+    pub expiry_date: String,
+    pub cost_price_paise: i64,
+    pub mrp_paise: i64,
+    pub selling_price_paise: i64,
+    pub quantity: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceivedBatch {
+    pub batch_id: i64,
+    pub medicine_id: i64,
+    pub batch_number: String,
+    pub quantity: i64,
+    /// The batch already existed and the quantity was added to it.
+    pub merged: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PurchaseResult {
+    pub purchase_id: i64,
+    pub total_paise: i64,
+    pub batches: Vec<ReceivedBatch>,
+}
+
+/// Check a line against the `batches` CHECK constraints before SQLite does,
+/// so the operator sees which line is wrong.
+fn validate_line(tx: &Tx, index: usize, line: &PurchaseLine) -> AppResult<()> {
+    let label = format!("Line {} (batch {})", index + 1, line.batch_number.trim());
+    let fail = |msg: &str| Err(AppError::validation(format!("{label}: {msg}")));
+
+    if line.batch_number.trim().is_empty() {
+        return Err(AppError::validation(format!(
+            "Line {}: batch number is required",
+            index + 1
+        )));
+    }
+    if line.quantity <= 0 {
+        return fail("quantity must be greater than 0");
+    }
+    if line.mrp_paise <= 0 || line.selling_price_paise <= 0 {
+        return fail("MRP and selling price must be greater than 0");
+    }
+    if line.cost_price_paise < 0 {
+        return fail("cost price cannot be negative");
+    }
+    if line.selling_price_paise > line.mrp_paise {
+        return fail("selling price cannot exceed MRP");
+    }
+
+    let (valid_date, expired): (bool, bool) = tx.query_row(
+        &format!(
+            "SELECT date(expiry_date) IS expiry_date, {EXPIRED} FROM (SELECT ?1 AS expiry_date)"
+        ),
+        params![line.expiry_date],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    if !valid_date {
+        return fail("expiry date must be in YYYY-MM-DD format");
+    }
+    if expired {
+        return fail("batch has already expired");
+    }
+    Ok(())
+}
+
+/// Add one line to stock: merge into the medicine's batch with the same
+/// number, or create it.
+///
+/// A merged batch takes the prices from the latest invoice; its expiry must
+/// match.
+fn receive_line(tx: &Tx, purchase_id: i64, line: &PurchaseLine) -> AppResult<ReceivedBatch> {
+    let batch_number = line.batch_number.trim();
+    let existing: Option<(i64, String)> = tx
+        .query_row(
+            "SELECT id, expiry_date FROM batches WHERE medicine_id = ?1 AND batch_number = ?2",
+            params![line.medicine_id, batch_number],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+
+    let (batch_id, merged) = match existing {
+        Some((_, expiry)) if expiry != line.expiry_date => {
+            return Err(AppError::validation(format!(
+                "Batch {batch_number} is already in stock with expiry {expiry}, not {}",
+                line.expiry_date
+            )));
+        }
+        Some((id, _)) => {
+            tx.execute(
+                "UPDATE batches SET quantity = quantity + ?1, cost_price_paise = ?2,
+                     mrp_paise = ?3, selling_price_paise = ?4
+                 WHERE id = ?5",
+                params![
+                    line.quantity,
+                    line.cost_price_paise,
+                    line.mrp_paise,
+                    line.selling_price_paise,
+                    id
+                ],
+            )?;
+            tx.changed(Entity::Batch, ChangeOp::Update, id);
+            (id, true)
+        }
+        None => {
+            tx.execute(
+                "INSERT INTO batches (medicine_id, batch_number, expiry_date, cost_price_paise,
+                     mrp_paise, selling_price_paise, quantity)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    line.medicine_id,
+                    batch_number,
+                    line.expiry_date,
+                    line.cost_price_paise,
+                    line.mrp_paise,
+                    line.selling_price_paise,
+                    line.quantity
+                ],
+            )?;
+            let id = tx.last_insert_rowid();
+            tx.changed(Entity::Batch, ChangeOp::Insert, id);
+            (id, false)
+        }
+    };
+
+    inventory::record_movement(
+        tx,
+        batch_id,
+        line.quantity,
+        MovementReason::Purchase,
+        Some(purchase_id),
+    )?;
+    tx.execute(
+        "INSERT INTO purchase_items (purchase_id, batch_id, medicine_id, quantity,
+             cost_price_paise, mrp_paise, selling_price_paise)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            purchase_id,
+            batch_id,
+            line.medicine_id,
+            line.quantity,
+            line.cost_price_paise,
+            line.mrp_paise,
+            line.selling_price_paise
+        ],
+    )?;
+    Ok(ReceivedBatch {
+        batch_id,
+        medicine_id: line.medicine_id,
+        batch_number: batch_number.to_owned(),
+        quantity: line.quantity,
+        merged,
+    })
+}
+
+/// Record a supplier invoice and put its batches into stock.
+///
+/// Lines are validated up front; any failure rolls back the whole receipt.
+pub fn receive_purchase(
+    tx: &Tx,
+    supplier_id: i64,
+    invoice_number: &str,
+    lines: &[PurchaseLine],
+    user_id: i64,
+) -> AppResult<PurchaseResult> {
+    auth::require_permission(tx, user_id, permission::PURCHASES_RECEIVE)?;
+    let invoice_number = invoice_number.trim();
+    if invoice_number.is_empty() {
+        return Err(AppError::validation(
+            "The supplier invoice number is required",
+        ));
+    }
+    if lines.is_empty() {
+        return Err(AppError::validation(
+            "A purchase must have at least one line",
+        ));
+    }
+    let supplier_exists: bool = tx.query_row(
+        "SELECT EXISTS (SELECT 1 FROM suppliers WHERE id = ?1)",
+        params![supplier_id],
+        |row| row.get(0),
+    )?;
+    if !supplier_exists {
+        return Err(AppError::not_found(format!("Supplier {supplier_id}")));
+    }
+    let duplicate: bool = tx.query_row(
+        "SELECT EXISTS (SELECT 1 FROM purchases WHERE supplier_id = ?1 AND invoice_number = ?2)",
+        params![supplier_id, invoice_number],
+        |row| row.get(0),
+    )?;
+    if duplicate {
+        return Err(AppError::validation(format!(
+            "Invoice {invoice_number} from this supplier has already been received"
+        )));
+    }
+
+    for (index, line) in lines.iter().enumerate() {
+        validate_line(tx, index, line)?;
+        let medicine_exists: bool = tx.query_row(
+            "SELECT EXISTS (SELECT 1 FROM medicines WHERE id = ?1)",
+            params![line.medicine_id],
+            |row| row.get(0),
+        )?;
+        if !medicine_exists {
+            return Err(AppError::not_found(format!(
+                "Medicine {}",
+                line.medicine_id
+            )));
+        }
+    }
+
+    let total_paise: i64 = lines.iter().map(|l| l.cost_price_paise * l.quantity).sum();
+    tx.execute(
+        "INSERT INTO purchases (supplier_id, invoice_number, total_paise) VALUES (?1, ?2, ?3)",
+        params![supplier_id, invoice_number, total_paise],
+    )?;
+    let purchase_id = tx.last_insert_rowid();
+
+    let batches = lines
+        .iter()
+        .map(|line| receive_line(tx, purchase_id, line))
+        .collect::<AppResult<Vec<_>>>()?;
+
+    tx.changed(Entity::Purchase, ChangeOp::Insert, purchase_id);
+    sync::enqueue(
+        tx,
+        "receive_purchase",
+        &json!({
+            "supplierId": supplier_id,
+            "invoiceNumber": invoice_number,
+            "lines": lines,
+        }),
+    )?;
+    Ok(PurchaseResult {
+        purchase_id,
+        total_paise,
+        batches,
+    })
+}
+
+/// Book a goods receipt (GRN) for a supplier invoice, against the purchase
+/// order it fulfils if there is one.
+///
+/// Without an order this is [`receive_purchase`]. With one, the order must
+/// be from the same supplier and the received lines are linked back to its
+/// lines; see [`purchase_orders::receive_purchase_order`].
+pub fn receive_goods(
+    tx: &Tx,
+    supplier_id: i64,
+    invoice_number: &str,
+    purchase_order_id: Option<i64>,
+    lines: &[PurchaseLine],
+    user_id: i64,
+) -> AppResult<PurchaseResult> {
+    let Some(order_id) = purchase_order_id else {
+        return receive_purchase(tx, supplier_id, invoice_number, lines, user_id);
+    };
+    let order_supplier: i64 = tx
+        .query_row(
+            "SELECT supplier_id FROM purchase_orders WHERE id = ?1",
+            params![order_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Purchase order {order_id}")))?;
+    if order_supplier != supplier_id {
+        return Err(AppError::validation(
+            "The purchase order was raised with a different supplier",
+        ));
+    }
+    purchase_orders::receive_purchase_order(tx, order_id, invoice_number, lines, user_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{batch_quantity, insert_medicine, migrated_db};
+    use crate::error::AppError;
+
+    const ADMIN: i64 = 1;
+
+    fn line(medicine_id: i64, batch_number: &str, quantity: i64) -> PurchaseLine {
+        PurchaseLine {
+            medicine_id,
+            batch_number: batch_number.into(),
+            expiry_date: "2099-12-31".into(),
+            cost_price_paise: 700,
+            mrp_paise: 1_000,
+            selling_price_paise: 950,
+            quantity,
+        }
+    }
+
+    fn supplier(db: &crate::db::Db) -> i64 {
+        let conn = db.connect().unwrap();
+        conn.execute("INSERT INTO suppliers (name) VALUES ('Medline')", [])
+            .unwrap();
+        conn.last_insert_rowid()
+    }
+
+    #[test]
+    fn receiving_the_same_batch_merges_quantity() {
+        let (_dir, db) = migrated_db();
+        let supplier = supplier(&db);
+        let para = insert_medicine(&db.connect().unwrap(), "Paracetamol", 12.0);
+
+        let first = db
+            .with_tx(|tx| receive_purchase(tx, supplier, "INV-1", &[line(para, "PC01", 10)], ADMIN))
+            .unwrap();
+        let second = db
+            .with_tx(|tx| receive_purchase(tx, supplier, "INV-2", &[line(para, "PC01", 5)], ADMIN))
+            .unwrap();
+
+        let batch = first.batches[0].batch_id;
+        assert_eq!(second.batches[0].batch_id, batch);
+        assert!(second.batches[0].merged);
+        let conn = db.connect().unwrap();
+        assert_eq!(batch_quantity(&conn, batch), 15);
+        let history = inventory::batch_movement_history(&conn, batch).unwrap();
+        let ledger: i64 = history.iter().map(|m| m.change_qty).sum();
+        assert_eq!(ledger, 15);
+        assert_eq!(history[1].reference_id, Some(second.purchase_id));
+        assert_eq!(first.total_paise, 7_000);
+    }
+
+    #[test]
+    fn price_above_mrp_is_reported_and_nothing_is_written() {
+        let (_dir, db) = migrated_db();
+        let supplier = supplier(&db);
+        let para = insert_medicine(&db.connect().unwrap(), "Paracetamol", 12.0);
+        let mut bad = line(para, "PC02", 3);
+        bad.selling_price_paise = 1_200;
+
+        let err = db
+            .with_tx(|tx| {
+                receive_purchase(tx, supplier, "INV-1", &[line(para, "PC01", 1), bad], ADMIN)
+            })
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Line 2 (batch PC02): selling price cannot exceed MRP"
+        );
+        let purchases: i64 = db
+            .connect()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM purchases", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(purchases, 0);
+    }
+
+    #[test]
+    fn cashier_cannot_receive_a_purchase() {
+        let (_dir, db) = migrated_db();
+        let supplier = supplier(&db);
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        conn.execute(
+            "INSERT INTO users (username, password_hash, full_name, role)
+             VALUES ('till1', 'x', 'Till 1', 'cashier')",
+            [],
+        )
+        .unwrap();
+        let cashier = conn.last_insert_rowid();
+
+        let err = db
+            .with_tx(|tx| {
+                receive_purchase(tx, supplier, "INV-1", &[line(para, "PC01", 1)], cashier)
+            })
+            .unwrap_err();
+
+        assert!(matches!(err, AppError::Unauthorized(_)), "{err}");
+        let batches: i64 = conn
+            .query_row("SELECT COUNT(*) FROM batches", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(batches, 0);
+    }
+
+    #[test]
+    fn goods_received_against_an_order_are_linked_to_its_lines() {
+        use crate::purchase_orders::{
+            approve_purchase_order, create_purchase_order, send_purchase_order, PurchaseOrderLine,
+        };
+
+        let (_dir, db) = migrated_db();
+        let supplier = supplier(&db);
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let order = [PurchaseOrderLine {
+            medicine_id: para,
+            quantity: 10,
+            expected_cost_paise: 700,
+        }];
+        let order_id = db
+            .with_tx(|tx| {
+                let order = create_purchase_order(tx, supplier, &order, None, ADMIN)?;
+                approve_purchase_order(tx, order.id, ADMIN)?;
+                send_purchase_order(tx, order.id, ADMIN)?;
+                Ok(order.id)
+            })
+            .unwrap();
+        conn.execute("INSERT INTO suppliers (name) VALUES ('Other')", [])
+            .unwrap();
+        let other = conn.last_insert_rowid();
+
+        let err = db
+            .with_tx(|tx| {
+                receive_goods(
+                    tx,
+                    other,
+                    "INV-1",
+                    Some(order_id),
+                    &[line(para, "PC01", 4)],
+                    ADMIN,
+                )
+            })
+            .unwrap_err();
+        assert!(err.to_string().contains("different supplier"), "{err}");
+
+        let grn = db
+            .with_tx(|tx| {
+                receive_goods(
+                    tx,
+                    supplier,
+                    "INV-1",
+                    Some(order_id),
+                    &[line(para, "PC01", 4)],
+                    ADMIN,
+                )
+            })
+            .unwrap();
+        let (batch_id, quantity, linked): (i64, i64, Option<i64>) = conn
+            .query_row(
+                "SELECT pi.batch_id, pi.quantity, poi.purchase_order_id
+                 FROM purchase_items pi
+                 LEFT JOIN purchase_order_items poi ON poi.id = pi.purchase_order_item_id
+                 WHERE pi.purchase_id = ?1",
+                [grn.purchase_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(batch_id, grn.batches[0].batch_id);
+        assert_eq!((quantity, linked), (4, Some(order_id)));
+    }
+}
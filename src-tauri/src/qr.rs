@@ -0,0 +1,457 @@
+//! QR codes for printed documents, byte mode at error correction level M.
+//!
+//! The smallest version 1–40 that holds the data is used, with the mask
+//! that scores lowest on the standard's penalty rules. The code is returned
+//! as a square of modules, `true` for dark, without the quiet zone.
+
+use crate::error::{AppError, AppResult};
+
+/// Error correction codewords per block, by version, at level M.
+const ECC_PER_BLOCK: [usize; 41] = [
+    0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26, 30, 22, 22, 24, 24, 28, 28, 26, 26, 26, 26, 28, 28,
+    28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28,
+];
+/// Error correction blocks, by version, at level M.
+const BLOCKS: [usize; 41] = [
+    0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5, 5, 8, 9, 9, 10, 10, 11, 13, 14, 16, 17, 17, 18, 20, 21, 23,
+    25, 26, 28, 29, 31, 33, 35, 37, 38, 40, 43, 45, 47, 49,
+];
+/// Level M's two format bits.
+const FORMAT_LEVEL_M: u32 = 0b00;
+
+#[derive(Debug, Clone)]
+pub struct QrCode {
+    pub size: usize,
+    modules: Vec<bool>,
+    function: Vec<bool>,
+}
+
+impl QrCode {
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.size + x]
+    }
+
+    fn set(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y * self.size + x] = dark;
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.set(x, y, dark);
+        self.function[y * self.size + x] = true;
+    }
+}
+
+/// Modules left for data and error correction once the function patterns
+/// are drawn.
+fn raw_data_modules(version: usize) -> usize {
+    let mut modules = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let align = version / 7 + 2;
+        modules -= (25 * align - 10) * align - 55;
+        if version >= 7 {
+            modules -= 36;
+        }
+    }
+    modules
+}
+
+fn data_codewords(version: usize) -> usize {
+    raw_data_modules(version) / 8 - ECC_PER_BLOCK[version] * BLOCKS[version]
+}
+
+fn alignment_positions(version: usize, size: usize) -> Vec<usize> {
+    if version == 1 {
+        return Vec::new();
+    }
+    let count = version / 7 + 2;
+    let step = (version * 8 + count * 3 + 5) / (count * 4 - 4) * 2;
+    let mut positions: Vec<usize> = (0..count - 1).map(|i| size - 7 - i * step).collect();
+    positions.push(6);
+    positions.reverse();
+    positions
+}
+
+/// Multiply in GF(2^8) modulo x^8 + x^4 + x^3 + x^2 + 1.
+fn gf_mul(x: u8, y: u8) -> u8 {
+    let mut z: u32 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11D);
+        z ^= ((u32::from(y) >> i) & 1) * u32::from(x);
+    }
+    z as u8
+}
+
+/// The generator polynomial of `degree`, highest coefficient first and the
+/// leading 1 left out.
+fn rs_divisor(degree: usize) -> Vec<u8> {
+    let mut divisor = vec![0u8; degree];
+    divisor[degree - 1] = 1;
+    let mut root = 1u8;
+    for _ in 0..degree {
+        for j in 0..degree {
+            divisor[j] = gf_mul(divisor[j], root);
+            if j + 1 < degree {
+                divisor[j] ^= divisor[j + 1];
+            }
+        }
+        root = gf_mul(root, 0x02);
+    }
+    divisor
+}
+
+fn rs_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0u8; divisor.len()];
+    for &byte in data {
+        let factor = byte ^ result.remove(0);
+        result.push(0);
+        for (r, &coef) in result.iter_mut().zip(divisor) {
+            *r ^= gf_mul(coef, factor);
+        }
+    }
+    result
+}
+
+/// Split the data into blocks, add each block's error correction and
+/// interleave the lot.
+fn add_ecc_and_interleave(data: &[u8], version: usize) -> Vec<u8> {
+    let blocks = BLOCKS[version];
+    let ecc_len = ECC_PER_BLOCK[version];
+    let raw_codewords = raw_data_modules(version) / 8;
+    let short_blocks = blocks - raw_codewords % blocks;
+    let short_len = raw_codewords / blocks;
+    let divisor = rs_divisor(ecc_len);
+
+    let mut filled = Vec::with_capacity(blocks);
+    let mut k = 0;
+    for i in 0..blocks {
+        let len = short_len - ecc_len + usize::from(i >= short_blocks);
+        let mut block = data[k..k + len].to_vec();
+        k += len;
+        let ecc = rs_remainder(&block, &divisor);
+        if i < short_blocks {
+            block.push(0);
+        }
+        block.extend(ecc);
+        filled.push(block);
+    }
+
+    let mut result = Vec::with_capacity(raw_codewords);
+    for i in 0..=short_len {
+        for (j, block) in filled.iter().enumerate() {
+            // Skip the padding byte of the short blocks.
+            if i != short_len - ecc_len || j >= short_blocks {
+                result.push(block[i]);
+            }
+        }
+    }
+    result
+}
+
+/// The 15 format bits for level M with `mask`, BCH-protected and masked.
+fn format_bits(mask: u32) -> u32 {
+    let data = (FORMAT_LEVEL_M << 3) | mask;
+    let mut rem = data;
+    for _ in 0..10 {
+        rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+    }
+    ((data << 10) | rem) ^ 0x5412
+}
+
+/// The 18 version bits, from version 7 up.
+fn version_bits(version: usize) -> u32 {
+    let version = version as u32;
+    let mut rem = version;
+    for _ in 0..12 {
+        rem = (rem << 1) ^ ((rem >> 11) * 0x1F25);
+    }
+    (version << 12) | rem
+}
+
+fn bit(value: u32, i: usize) -> bool {
+    (value >> i) & 1 != 0
+}
+
+fn draw_format(qr: &mut QrCode, mask: u32) {
+    let bits = format_bits(mask);
+    let size = qr.size;
+    for i in 0..6 {
+        qr.set_function(8, i, bit(bits, i));
+    }
+    qr.set_function(8, 7, bit(bits, 6));
+    qr.set_function(8, 8, bit(bits, 7));
+    qr.set_function(7, 8, bit(bits, 8));
+    for i in 9..15 {
+        qr.set_function(14 - i, 8, bit(bits, i));
+    }
+    for i in 0..8 {
+        qr.set_function(size - 1 - i, 8, bit(bits, i));
+    }
+    for i in 8..15 {
+        qr.set_function(8, size - 15 + i, bit(bits, i));
+    }
+    qr.set_function(8, size - 8, true);
+}
+
+fn draw_function_patterns(qr: &mut QrCode, version: usize) {
+    let size = qr.size;
+    for i in 0..size {
+        qr.set_function(6, i, i % 2 == 0);
+        qr.set_function(i, 6, i % 2 == 0);
+    }
+    for (cx, cy) in [(3, 3), (size - 4, 3), (3, size - 4)] {
+        for dy in -4i32..=4 {
+            for dx in -4i32..=4 {
+                let (x, y) = (cx as i32 + dx, cy as i32 + dy);
+                if (0..size as i32).contains(&x) && (0..size as i32).contains(&y) {
+                    let dist = dx.abs().max(dy.abs());
+                    qr.set_function(x as usize, y as usize, dist != 2 && dist != 4);
+                }
+            }
+        }
+    }
+    let positions = alignment_positions(version, size);
+    let last = positions.len().saturating_sub(1);
+    for (i, &cy) in positions.iter().enumerate() {
+        for (j, &cx) in positions.iter().enumerate() {
+            // The corners taken by finder patterns get none.
+            if (i == 0 && (j == 0 || j == last)) || (i == last && j == 0) {
+                continue;
+            }
+            for dy in -2i32..=2 {
+                for dx in -2i32..=2 {
+                    let dark = dx.abs().max(dy.abs()) != 1;
+                    qr.set_function((cx as i32 + dx) as usize, (cy as i32 + dy) as usize, dark);
+                }
+            }
+        }
+    }
+    // Reserve the format areas; the real bits go in once the mask is chosen.
+    draw_format(qr, 0);
+    if version >= 7 {
+        let bits = version_bits(version);
+        for i in 0..18 {
+            let (a, b) = (size - 11 + i % 3, i / 3);
+            qr.set_function(a, b, bit(bits, i));
+            qr.set_function(b, a, bit(bits, i));
+        }
+    }
+}
+
+/// Place the codewords in the zigzag of two-module columns from the
+/// bottom-right corner.
+fn draw_codewords(qr: &mut QrCode, codewords: &[u8]) {
+    let size = qr.size;
+    let total_bits = codewords.len() * 8;
+    let mut i = 0;
+    let mut right = size - 1;
+    loop {
+        if right == 6 {
+            right = 5;
+        }
+        for vert in 0..size {
+            for j in 0..2 {
+                let x = right - j;
+                let upward = (right + 1) & 2 == 0;
+                let y = if upward { size - 1 - vert } else { vert };
+                if !qr.function[y * size + x] && i < total_bits {
+                    qr.set(x, y, (codewords[i >> 3] >> (7 - (i & 7))) & 1 != 0);
+                    i += 1;
+                }
+            }
+        }
+        if right < 2 {
+            break;
+        }
+        right -= 2;
+    }
+}
+
+fn mask_applies(mask: u32, x: usize, y: usize) -> bool {
+    match mask {
+        0 => (x + y).is_multiple_of(2),
+        1 => y.is_multiple_of(2),
+        2 => x.is_multiple_of(3),
+        3 => (x + y).is_multiple_of(3),
+        4 => (x / 3 + y / 2).is_multiple_of(2),
+        5 => x * y % 2 + x * y % 3 == 0,
+        6 => (x * y % 2 + x * y % 3).is_multiple_of(2),
+        _ => ((x + y) % 2 + x * y % 3).is_multiple_of(2),
+    }
+}
+
+/// Flip the data modules under `mask`; applying it twice undoes it.
+fn apply_mask(qr: &mut QrCode, mask: u32) {
+    let size = qr.size;
+    for y in 0..size {
+        for x in 0..size {
+            let i = y * size + x;
+            if !qr.function[i] && mask_applies(mask, x, y) {
+                qr.modules[i] = !qr.modules[i];
+            }
+        }
+    }
+}
+
+/// Score a finished symbol on the four penalty rules; lower is better.
+fn penalty(qr: &QrCode) -> usize {
+    let size = qr.size;
+    let mut score = 0;
+    let lines = |horizontal: bool| {
+        (0..size).map(move |a| {
+            (0..size)
+                .map(|b| {
+                    if horizontal {
+                        qr.is_dark(b, a)
+                    } else {
+                        qr.is_dark(a, b)
+                    }
+                })
+                .collect::<Vec<bool>>()
+        })
+    };
+    const FINDER: [bool; 11] = [
+        true, false, true, true, true, false, true, false, false, false, false,
+    ];
+    for horizontal in [true, false] {
+        for line in lines(horizontal) {
+            // Runs of five or more of one colour.
+            let mut run = 1;
+            for i in 1..=size {
+                if i < size && line[i] == line[i - 1] {
+                    run += 1;
+                } else {
+                    if run >= 5 {
+                        score += run - 2;
+                    }
+                    run = 1;
+                }
+            }
+            // Finder-like 1:1:3:1:1 with four light modules on one side.
+            for window in line.windows(11) {
+                let reversed = window.iter().rev().copied().eq(FINDER);
+                if window == FINDER || reversed {
+                    score += 40;
+                }
+            }
+        }
+    }
+    // Blocks of 2x2 in one colour.
+    for y in 0..size - 1 {
+        for x in 0..size - 1 {
+            let c = qr.is_dark(x, y);
+            if c == qr.is_dark(x + 1, y)
+                && c == qr.is_dark(x, y + 1)
+                && c == qr.is_dark(x + 1, y + 1)
+            {
+                score += 3;
+            }
+        }
+    }
+    // Balance of dark and light.
+    let total = size * size;
+    let dark = qr.modules.iter().filter(|&&m| m).count();
+    let k = (dark * 20).abs_diff(total * 10).div_ceil(total) - 1;
+    score + k * 10
+}
+
+/// The symbol for `data` with its codewords placed, before masking.
+fn unmasked(data: &[u8]) -> AppResult<QrCode> {
+    let header_bits = |version: usize| 4 + if version < 10 { 8 } else { 16 };
+    let version = (1..=40)
+        .find(|&v| header_bits(v) + data.len() * 8 <= data_codewords(v) * 8)
+        .ok_or_else(|| {
+            AppError::validation(format!("{} bytes is too long for a QR code", data.len()))
+        })?;
+
+    // Mode, length and data, then terminator and padding to capacity.
+    let capacity = data_codewords(version) * 8;
+    let mut bits: Vec<bool> = Vec::with_capacity(capacity);
+    let push = |bits: &mut Vec<bool>, value: u32, len: usize| {
+        for i in (0..len).rev() {
+            bits.push(bit(value, i));
+        }
+    };
+    push(&mut bits, 0b0100, 4);
+    push(&mut bits, data.len() as u32, header_bits(version) - 4);
+    for &byte in data {
+        push(&mut bits, u32::from(byte), 8);
+    }
+    let terminator = (capacity - bits.len()).min(4);
+    push(&mut bits, 0, terminator);
+    let to_byte = (8 - bits.len() % 8) % 8;
+    push(&mut bits, 0, to_byte);
+    let mut codewords: Vec<u8> = bits
+        .chunks(8)
+        .map(|c| c.iter().fold(0u8, |acc, &b| (acc << 1) | u8::from(b)))
+        .collect();
+    for pad in [0xEC, 0x11].into_iter().cycle() {
+        if codewords.len() >= data_codewords(version) {
+            break;
+        }
+        codewords.push(pad);
+    }
+
+    let size = version * 4 + 17;
+    let mut qr = QrCode {
+        size,
+        modules: vec![false; size * size],
+        function: vec![false; size * size],
+    };
+    draw_function_patterns(&mut qr, version);
+    draw_codewords(&mut qr, &add_ecc_and_interleave(&codewords, version));
+    Ok(qr)
+}
+
+/// Encode `data` as a QR code.
+pub fn encode(data: &[u8]) -> AppResult<QrCode> {
+    let mut qr = unmasked(data)?;
+    let mut best = (usize::MAX, 0);
+    for mask in 0..8 {
+        apply_mask(&mut qr, mask);
+        draw_format(&mut qr, mask);
+        let score = penalty(&qr);
+        if score < best.0 {
+            best = (score, mask);
+        }
+        apply_mask(&mut qr, mask);
+    }
+    apply_mask(&mut qr, best.1);
+    draw_format(&mut qr, best.1);
+    Ok(qr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_correction_and_format_bits_match_the_standard() {
+        // "HELLO WORLD" at 1-M, from the worked example of the standard.
+        let data = [
+            32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17,
+        ];
+        assert_eq!(
+            rs_remainder(&data, &rs_divisor(10)),
+            [196, 35, 39, 119, 235, 215, 231, 226, 93, 23]
+        );
+        assert_eq!(format_bits(0), 0b101010000010010);
+        assert_eq!(version_bits(7), 0b000111110010010100);
+        assert_eq!(
+            alignment_positions(32, 32 * 4 + 17),
+            [6, 34, 60, 86, 112, 138]
+        );
+        assert_eq!(data_codewords(40), 2334);
+    }
+
+    #[test]
+    fn symbol_grows_with_the_data_and_keeps_its_finders() {
+        assert_eq!(encode(b"hello").unwrap().size, 21);
+        let qr = encode(&[b'x'; 1000]).unwrap();
+        assert_eq!(qr.size, 26 * 4 + 17);
+        for (x, y) in [(0, 0), (qr.size - 7, 0), (0, qr.size - 7)] {
+            assert!(qr.is_dark(x, y) && qr.is_dark(x + 6, y + 6) && qr.is_dark(x + 3, y + 3));
+            assert!(!qr.is_dark(x + 1, y + 1));
+        }
+        assert!(encode(&[0; 2400]).is_err());
+    }
+}
@@ -0,0 +1,318 @@
+//! Checks on GSTINs and drug licence numbers before a supplier, customer
+//! or the pharmacy's own settings are saved.
+//!
+//! A GSTIN must have the standard shape, a known state code and the right
+//! check character in its last place. Drug licence numbers follow each
+//! state's own scheme, so only their shape is checked. The legal name
+//! behind a GSTIN can be looked up through the e-invoicing GSP, whose
+//! credentials are in settings.
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+use crate::einvoice::{self, text};
+use crate::error::{AppError, AppResult};
+use crate::gst;
+use crate::http;
+
+const GSTIN_PATH: &str = "/eivital/v1.04/Master/gstin";
+
+const LOOKUP_TIMEOUT_SECS: u32 = 30;
+
+/// Base-36 digits, in the order the GSTIN check character uses them.
+const CHECK_ALPHABET: &[u8; 36] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// The registration numbers of a record about to be saved.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Registrations {
+    pub gstin: Option<String>,
+    pub state_code: Option<String>,
+    pub drug_license_no: Option<String>,
+    /// The pharmacy's own licence is mandatory; a party's is not.
+    #[serde(default)]
+    pub drug_license_required: bool,
+}
+
+/// A problem with one field, keyed by its camelCase name in the form.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldProblem {
+    pub field: &'static str,
+    pub message: String,
+}
+
+/// The check character for the first 14 characters of a GSTIN.
+fn gstin_check_char(body: &[u8]) -> u8 {
+    let sum: usize = body
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let value = CHECK_ALPHABET.iter().position(|a| a == c).unwrap_or(0);
+            let product = value * if i.is_multiple_of(2) { 1 } else { 2 };
+            product / 36 + product % 36
+        })
+        .sum();
+    CHECK_ALPHABET[(36 - sum % 36) % 36]
+}
+
+/// Why `gstin` is not a valid GSTIN, if it is not.
+pub fn gstin_problem(gstin: &str) -> Option<String> {
+    let b = gstin.as_bytes();
+    let shape = b.len() == 15
+        && b[..2].iter().all(u8::is_ascii_digit)
+        && b[2..7].iter().all(u8::is_ascii_uppercase)
+        && b[7..11].iter().all(u8::is_ascii_digit)
+        && b[11].is_ascii_uppercase()
+        && (b[12].is_ascii_uppercase() || (b'1'..=b'9').contains(&b[12]))
+        && b[13] == b'Z'
+        && (b[14].is_ascii_uppercase() || b[14].is_ascii_digit());
+    if !shape {
+        return Some("GSTIN must be 15 characters, e.g. 29ABCDE1234F1Z5".to_string());
+    }
+    if gst::state_name(&gstin[..2]).is_none() {
+        return Some(format!(
+            "GSTIN state code {} is not a GST state",
+            &gstin[..2]
+        ));
+    }
+    let expected = gstin_check_char(&b[..14]);
+    if b[14] != expected {
+        return Some(format!(
+            "GSTIN check character should be {}; the number may be mistyped",
+            expected as char
+        ));
+    }
+    None
+}
+
+/// Why `number` does not look like a drug licence number, if it does not:
+/// 5 to 40 letters, digits, spaces and `/ - . ,`, with at least one digit.
+pub fn drug_license_problem(number: &str) -> Option<String> {
+    let len = number.chars().count();
+    if !(5..=40).contains(&len) {
+        return Some("Drug licence number must be 5 to 40 characters".to_string());
+    }
+    if !number
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || " /-.,".contains(c))
+    {
+        return Some(
+            "Drug licence number may only have letters, digits, spaces and / - . ,".to_string(),
+        );
+    }
+    if !number.chars().any(|c| c.is_ascii_digit()) {
+        return Some("Drug licence number must contain its serial number".to_string());
+    }
+    None
+}
+
+fn trimmed(value: &Option<String>) -> &str {
+    value.as_deref().map(str::trim).unwrap_or_default()
+}
+
+/// Every problem with the registration numbers of a record; empty when it
+/// may be saved.
+pub fn validate_registrations(r: &Registrations) -> Vec<FieldProblem> {
+    let mut problems = Vec::new();
+    let gstin = trimmed(&r.gstin).to_ascii_uppercase();
+    let state_code = trimmed(&r.state_code);
+    if !gstin.is_empty() {
+        if let Some(message) = gstin_problem(&gstin) {
+            problems.push(FieldProblem {
+                field: "gstin",
+                message,
+            });
+        }
+    }
+    if !state_code.is_empty() {
+        if gst::state_name(state_code).is_none() {
+            problems.push(FieldProblem {
+                field: "stateCode",
+                message: format!("{state_code} is not a GST state code"),
+            });
+        } else if gstin.len() >= 2 && gstin.is_char_boundary(2) && gstin[..2] != *state_code {
+            problems.push(FieldProblem {
+                field: "stateCode",
+                message: format!("The GSTIN is registered in state {}", &gstin[..2]),
+            });
+        }
+    }
+    let licence = trimmed(&r.drug_license_no);
+    if licence.is_empty() {
+        if r.drug_license_required {
+            problems.push(FieldProblem {
+                field: "drugLicenseNo",
+                message: "Drug licence number is required".to_string(),
+            });
+        }
+    } else if let Some(message) = drug_license_problem(licence) {
+        problems.push(FieldProblem {
+            field: "drugLicenseNo",
+            message,
+        });
+    }
+    problems
+}
+
+/// [`validate_registrations`] as an error naming the first problem, for
+/// commands that save the record.
+pub fn check_registrations(r: &Registrations) -> AppResult<()> {
+    match validate_registrations(r).into_iter().next() {
+        Some(problem) => Err(AppError::validation(problem.message)),
+        None => Ok(()),
+    }
+}
+
+/// A taxpayer as registered on the GST network.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GstinDetails {
+    pub gstin: String,
+    pub legal_name: String,
+    pub trade_name: String,
+    /// `ACT` while the registration is active.
+    pub status: String,
+    pub state_code: String,
+    pub address: String,
+    pub pincode: String,
+}
+
+/// Look a GSTIN up through the e-invoicing GSP.
+pub fn lookup_gstin(
+    conn: &Connection,
+    gstin: &str,
+    post: &mut http::Post<'_>,
+    get: &mut http::Get<'_>,
+) -> AppResult<GstinDetails> {
+    let gstin = gstin.trim().to_ascii_uppercase();
+    if let Some(problem) = gstin_problem(&gstin) {
+        return Err(AppError::validation(problem));
+    }
+    let (url, headers) = einvoice::login(conn, post)?;
+    let what = "GSTIN lookup";
+    let data = einvoice::api_data(what, &get(&format!("{url}{GSTIN_PATH}/{gstin}"), &headers)?)?;
+    let legal_name = text(&data["LegalName"]);
+    if legal_name.is_empty() {
+        return Err(AppError::validation(format!(
+            "{what}: no taxpayer found for {gstin}"
+        )));
+    }
+    let address = ["AddrBno", "AddrBnm", "AddrFlno", "AddrSt", "AddrLoc"]
+        .iter()
+        .map(|key| text(&data[*key]))
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(", ");
+    Ok(GstinDetails {
+        legal_name,
+        trade_name: text(&data["TradeName"]),
+        status: text(&data["Status"]),
+        state_code: format!("{:0>2}", text(&data["StateCode"])),
+        address,
+        pincode: text(&data["AddrPncd"]),
+        gstin,
+    })
+}
+
+/// GET through the system curl.
+pub fn curl_get(url: &str, headers: &[(&str, String)]) -> AppResult<Vec<u8>> {
+    http::get("GSTIN lookup", LOOKUP_TIMEOUT_SECS, url, headers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::migrated_db;
+
+    #[test]
+    fn gstins_are_checked_for_state_and_check_character() {
+        assert_eq!(gstin_problem("27AAPFU0939F1ZV"), None);
+        let err = gstin_problem("27AAPFU0939F1ZX").unwrap();
+        assert!(err.contains("should be V"), "{err}");
+        assert!(gstin_problem("99AAPFU0939F1ZV")
+            .unwrap()
+            .contains("state code 99"));
+        assert!(gstin_problem("27AAPFU0939F1Z")
+            .unwrap()
+            .contains("15 characters"));
+
+        let problems = validate_registrations(&Registrations {
+            gstin: Some(" 27aapfu0939f1zv ".into()),
+            state_code: Some("29".into()),
+            drug_license_no: Some("MH-PZ-12345".into()),
+            drug_license_required: true,
+        });
+        assert_eq!(
+            problems,
+            vec![FieldProblem {
+                field: "stateCode",
+                message: "The GSTIN is registered in state 27".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn drug_licences_are_checked_for_shape() {
+        for valid in [
+            "MH-PZ-12345",
+            "20B/123/2020",
+            "KA BG1 20B 123456",
+            "RLF20KA2023000123",
+        ] {
+            assert_eq!(drug_license_problem(valid), None, "{valid}");
+        }
+        assert!(drug_license_problem("DL-1").is_some());
+        assert!(drug_license_problem("FORM-TWENTY").is_some());
+        assert!(drug_license_problem("KA#123456").is_some());
+
+        let problems = validate_registrations(&Registrations {
+            drug_license_required: true,
+            ..Registrations::default()
+        });
+        assert_eq!(problems[0].field, "drugLicenseNo");
+        assert!(validate_registrations(&Registrations::default()).is_empty());
+    }
+
+    #[test]
+    fn gstin_lookup_reads_the_taxpayer_from_the_gsp() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        conn.execute(
+            "UPDATE pharmacy_settings SET einvoice_api_url = 'https://gsp.example',
+                 einvoice_client_id = 'cid', einvoice_client_secret = 'cs',
+                 einvoice_username = 'user', einvoice_password = 'pw'",
+            [],
+        )
+        .unwrap();
+        let mut post = |_: &str, _: &[(&str, String)], _: &str| {
+            Ok(br#"{"Status":1,"Data":{"AuthToken":"tok"}}"#.to_vec())
+        };
+        let mut urls = Vec::new();
+        let mut get = |url: &str, headers: &[(&str, String)]| {
+            assert!(headers.contains(&("AuthToken", "tok".to_string())));
+            urls.push(url.to_string());
+            Ok(
+                br#"{"Status":1,"Data":{"Gstin":"27AAPFU0939F1ZV","LegalName":"Unique Pharma LLP",
+                "TradeName":"Unique Pharma","AddrBno":"12","AddrSt":"Link Road","AddrLoc":"Andheri",
+                "StateCode":27,"AddrPncd":400053,"Status":"ACT"}}"#
+                    .to_vec(),
+            )
+        };
+
+        let found = lookup_gstin(&conn, "27aapfu0939f1zv", &mut post, &mut get).unwrap();
+        let err = lookup_gstin(&conn, "27AAPFU0939F1ZX", &mut post, &mut get).unwrap_err();
+        assert!(err.to_string().contains("check character"), "{err}");
+
+        assert_eq!(
+            urls,
+            ["https://gsp.example/eivital/v1.04/Master/gstin/27AAPFU0939F1ZV"]
+        );
+        assert_eq!(found.legal_name, "Unique Pharma LLP");
+        assert_eq!(found.address, "12, Link Road, Andheri");
+        assert_eq!(
+            (found.state_code.as_str(), found.pincode.as_str()),
+            ("27", "400053")
+        );
+    }
+}
@@ -0,0 +1,619 @@
+//! Uploading scheduled snapshots to an off-site target.
+//!
+//! Each new snapshot in the backups folder is packed into an encrypted
+//! archive and uploaded to an S3-compatible bucket, a Google Drive folder
+//! or a WebDAV collection, through the system `curl`.
+//!
+//! A failed upload is retried on later checks, waiting twice as long after
+//! each consecutive failure.
+//!
+//! The target's secret, refresh token and archive passphrase are kept in a
+//! file beside the database rather than in `pharmacy_settings`, which the
+//! frontend reads through the SQL plugin.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use hmac::{Hmac, Mac};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::auth::{self, permission};
+use crate::backup::{self, BackupFile};
+use crate::backup_archive;
+use crate::error::{AppError, AppResult};
+use crate::http;
+
+/// Wait after the first failure, in minutes; doubled for each further one.
+const FIRST_RETRY_MINUTES: i64 = 5;
+const MAX_RETRY_MINUTES: i64 = 6 * 60;
+/// Longest a single transfer may take, in seconds.
+const TRANSFER_TIMEOUT_SECS: u32 = 15 * 60;
+const S3_SIGNED_HEADERS: &str = "host;x-amz-content-sha256;x-amz-date";
+const GOOGLE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const DRIVE_UPLOAD_URL: &str =
+    "https://www.googleapis.com/upload/drive/v3/files?uploadType=multipart";
+const SECRETS_FILE: &str = "remote-backup-secrets.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RemoteTarget {
+    None,
+    S3,
+    Gdrive,
+    Webdav,
+}
+
+impl RemoteTarget {
+    fn parse(value: &str) -> AppResult<Self> {
+        match value {
+            "none" => Ok(Self::None),
+            "s3" => Ok(Self::S3),
+            "gdrive" => Ok(Self::Gdrive),
+            "webdav" => Ok(Self::Webdav),
+            other => Err(AppError::validation(format!(
+                "Unknown remote backup target: {other}"
+            ))),
+        }
+    }
+}
+
+struct RemoteConfig {
+    target: RemoteTarget,
+    url: String,
+    region: String,
+    username: String,
+    secret: String,
+    refresh_token: String,
+    passphrase: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RemoteSecrets {
+    secret: String,
+    refresh_token: String,
+    passphrase: String,
+}
+
+/// Which secrets have been saved; the values themselves never leave the
+/// backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteSecretsSet {
+    pub secret: bool,
+    pub refresh_token: bool,
+    pub passphrase: bool,
+}
+
+fn secrets_path(live: &Path) -> PathBuf {
+    live.with_file_name(SECRETS_FILE)
+}
+
+fn write_secrets(live: &Path, secrets: &RemoteSecrets) -> AppResult<()> {
+    let path = secrets_path(live);
+    let json = serde_json::to_vec(secrets).map_err(AppError::validation)?;
+    fs::write(&path, json)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(())
+}
+
+/// The saved secrets; empty until some are saved.
+fn read_secrets(live: &Path) -> AppResult<RemoteSecrets> {
+    match fs::read(secrets_path(live)) {
+        Ok(json) => serde_json::from_slice(&json).map_err(|_| {
+            AppError::validation(format!(
+                "{SECRETS_FILE} is unreadable; save the secrets again"
+            ))
+        }),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(RemoteSecrets::default()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn remote_config(conn: &Connection, live: &Path) -> AppResult<RemoteConfig> {
+    let (target, url, region, username): (String, String, String, String) = conn.query_row(
+        "SELECT remote_backup_target, remote_backup_url, remote_backup_region,
+                remote_backup_username
+         FROM pharmacy_settings WHERE id = 1",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    )?;
+    let secrets = read_secrets(live)?;
+    Ok(RemoteConfig {
+        target: RemoteTarget::parse(&target)?,
+        url: url.trim().trim_end_matches('/').to_string(),
+        region: region.trim().to_string(),
+        username: username.trim().to_string(),
+        secret: secrets.secret,
+        refresh_token: secrets.refresh_token.trim().to_string(),
+        passphrase: secrets.passphrase,
+    })
+}
+
+fn secrets_set(secrets: &RemoteSecrets) -> RemoteSecretsSet {
+    RemoteSecretsSet {
+        secret: !secrets.secret.is_empty(),
+        refresh_token: !secrets.refresh_token.is_empty(),
+        passphrase: !secrets.passphrase.is_empty(),
+    }
+}
+
+/// Which of the remote target's secrets are saved.
+pub fn remote_secrets_set(
+    conn: &Connection,
+    live: &Path,
+    user_id: i64,
+) -> AppResult<RemoteSecretsSet> {
+    auth::require_permission(conn, user_id, permission::BACKUP_CREATE)?;
+    Ok(secrets_set(&read_secrets(live)?))
+}
+
+/// Save the remote target's secrets; `None` keeps the one already saved.
+pub fn set_remote_secrets(
+    conn: &Connection,
+    live: &Path,
+    secret: Option<String>,
+    refresh_token: Option<String>,
+    passphrase: Option<String>,
+    user_id: i64,
+) -> AppResult<RemoteSecretsSet> {
+    auth::require_permission(conn, user_id, permission::BACKUP_CREATE)?;
+    let mut secrets = read_secrets(live)?;
+    for (value, kept) in [
+        (secret, &mut secrets.secret),
+        (refresh_token, &mut secrets.refresh_token),
+        (passphrase, &mut secrets.passphrase),
+    ] {
+        if let Some(value) = value {
+            *kept = value;
+        }
+    }
+    write_secrets(live, &secrets)?;
+    Ok(secrets_set(&secrets))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupStatus {
+    /// Newest snapshot in the backups folder.
+    pub latest_local: Option<BackupFile>,
+    pub remote_target: RemoteTarget,
+    pub last_attempt_at: Option<String>,
+    pub last_success_at: Option<String>,
+    /// When the snapshot last uploaded was taken.
+    pub last_uploaded: Option<String>,
+    /// Why the latest attempt failed; cleared by a success.
+    pub last_error: Option<String>,
+    pub failures: i64,
+}
+
+/// Where local and remote backups stand.
+pub fn backup_status(conn: &Connection, live: &Path, user_id: i64) -> AppResult<BackupStatus> {
+    auth::require_permission(conn, user_id, permission::BACKUP_CREATE)?;
+    let target = remote_config(conn, live)?.target;
+    let latest_local = backup::list_backups(conn, live)?.into_iter().next();
+    Ok(conn.query_row(
+        "SELECT last_attempt_at, last_success_at, last_uploaded, last_error, failures
+         FROM remote_backup_status WHERE id = 1",
+        [],
+        |row| {
+            Ok(BackupStatus {
+                latest_local,
+                remote_target: target,
+                last_attempt_at: row.get(0)?,
+                last_success_at: row.get(1)?,
+                last_uploaded: row.get(2)?,
+                last_error: row.get(3)?,
+                failures: row.get(4)?,
+            })
+        },
+    )?)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut out, byte| {
+        let _ = write!(out, "{byte:02x}");
+        out
+    })
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    Hmac::<Sha256>::new_from_slice(key)
+        .expect("HMAC accepts keys of any length")
+        .chain_update(data)
+        .finalize()
+        .into_bytes()
+        .into()
+}
+
+/// The AWS Signature Version 4 key for one day, region and service.
+fn signing_key(secret: &str, date: &str, region: &str, service: &str) -> [u8; 32] {
+    let day = hmac_sha256(format!("AWS4{secret}").as_bytes(), date.as_bytes());
+    let region = hmac_sha256(&day, region.as_bytes());
+    let service = hmac_sha256(&region, service.as_bytes());
+    hmac_sha256(&service, b"aws4_request")
+}
+
+/// Split `https://host/path` into its host and path.
+fn split_url(url: &str) -> AppResult<(&str, &str)> {
+    let rest = url
+        .strip_prefix("https://")
+        .ok_or_else(|| AppError::validation("The backup URL must start with https://"))?;
+    Ok(match rest.find('/') {
+        Some(at) => (&rest[..at], &rest[at..]),
+        None => (rest, ""),
+    })
+}
+
+/// `path` as SigV4 expects it in a canonical request: every byte but the
+/// unreserved characters and `/` percent-encoded.
+fn uri_encode_path(path: &str) -> String {
+    path.bytes().fold(String::new(), |mut out, byte| {
+        if byte.is_ascii_alphanumeric() || b"-_.~/".contains(&byte) {
+            out.push(char::from(byte));
+        } else {
+            let _ = write!(out, "%{byte:02X}");
+        }
+        out
+    })
+}
+
+/// The `Authorization` header for a path-style S3 `PUT` to the encoded
+/// `path` of a payload whose SHA-256 is `payload_hash`, signed at
+/// `amz_date` (`YYYYMMDDTHHMMSSZ`, UTC).
+fn s3_authorization(
+    config: &RemoteConfig,
+    host: &str,
+    path: &str,
+    payload_hash: &str,
+    amz_date: &str,
+) -> String {
+    let date = &amz_date[..8];
+    let canonical = format!(
+        "PUT\n{path}\n\nhost:{host}\nx-amz-content-sha256:{payload_hash}\nx-amz-date:{amz_date}\n\n\
+         {S3_SIGNED_HEADERS}\n{payload_hash}"
+    );
+    let scope = format!("{date}/{}/s3/aws4_request", config.region);
+    let to_sign = format!(
+        "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+        hex(&Sha256::digest(canonical.as_bytes()))
+    );
+    let key = signing_key(&config.secret, date, &config.region, "s3");
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={S3_SIGNED_HEADERS}, Signature={}",
+        config.username,
+        hex(&hmac_sha256(&key, to_sign.as_bytes()))
+    )
+}
+
+fn upload_s3(config: &RemoteConfig, archive: &Path, name: &str, amz_date: &str) -> AppResult<()> {
+    let (host, bucket_path) = split_url(&config.url)?;
+    if config.region.is_empty() || config.username.is_empty() {
+        return Err(AppError::validation(
+            "S3 backups need a region and an access key",
+        ));
+    }
+    let payload_hash = hex(&Sha256::digest(fs::read(archive)?));
+    // The request must go to the same encoded path that was signed.
+    let path = uri_encode_path(&format!("{bucket_path}/{name}"));
+    let authorization = s3_authorization(config, host, &path, &payload_hash, amz_date);
+    http::curl(
+        "Upload",
+        TRANSFER_TIMEOUT_SECS,
+        &[
+            ("url", format!("https://{host}{path}")),
+            ("upload-file", archive.to_string_lossy().into_owned()),
+            ("header", format!("x-amz-content-sha256: {payload_hash}")),
+            ("header", format!("x-amz-date: {amz_date}")),
+            ("header", format!("Authorization: {authorization}")),
+        ],
+    )?;
+    Ok(())
+}
+
+fn upload_webdav(config: &RemoteConfig, archive: &Path, name: &str) -> AppResult<()> {
+    split_url(&config.url)?;
+    http::curl(
+        "Upload",
+        TRANSFER_TIMEOUT_SECS,
+        &[
+            ("url", format!("{}/{name}", config.url)),
+            ("upload-file", archive.to_string_lossy().into_owned()),
+            ("user", format!("{}:{}", config.username, config.secret)),
+        ],
+    )?;
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct GoogleToken {
+    access_token: String,
+}
+
+fn upload_gdrive(config: &RemoteConfig, archive: &Path, name: &str) -> AppResult<()> {
+    if config.username.is_empty() || config.refresh_token.is_empty() {
+        return Err(AppError::validation(
+            "Google Drive backups need an OAuth client id and refresh token",
+        ));
+    }
+    let token = http::curl(
+        "Upload",
+        TRANSFER_TIMEOUT_SECS,
+        &[
+            ("url", GOOGLE_TOKEN_URL.to_string()),
+            ("data-urlencode", format!("client_id={}", config.username)),
+            ("data-urlencode", format!("client_secret={}", config.secret)),
+            (
+                "data-urlencode",
+                format!("refresh_token={}", config.refresh_token),
+            ),
+            ("data-urlencode", "grant_type=refresh_token".to_string()),
+        ],
+    )?;
+    let token: GoogleToken = serde_json::from_slice(&token)
+        .map_err(|_| AppError::validation("Google did not return an access token"))?;
+
+    let mut metadata = serde_json::json!({ "name": name });
+    if !config.url.is_empty() {
+        metadata["parents"] = serde_json::json!([config.url]);
+    }
+    let metadata_path = archive.with_extension("json");
+    fs::write(&metadata_path, metadata.to_string())?;
+    let result = http::curl(
+        "Upload",
+        TRANSFER_TIMEOUT_SECS,
+        &[
+            ("url", DRIVE_UPLOAD_URL.to_string()),
+            (
+                "header",
+                format!("Authorization: Bearer {}", token.access_token),
+            ),
+            (
+                "form",
+                format!(
+                    "metadata=<{};type=application/json",
+                    metadata_path.display()
+                ),
+            ),
+            (
+                "form",
+                format!("file=@{};type=application/octet-stream", archive.display()),
+            ),
+        ],
+    );
+    let _ = fs::remove_file(&metadata_path);
+    result.map(drop)
+}
+
+fn upload(config: &RemoteConfig, archive: &Path, name: &str, utc_now: &str) -> AppResult<()> {
+    match config.target {
+        RemoteTarget::None => Ok(()),
+        RemoteTarget::S3 => upload_s3(config, archive, name, utc_now),
+        RemoteTarget::Gdrive => upload_gdrive(config, archive, name),
+        RemoteTarget::Webdav => upload_webdav(config, archive, name),
+    }
+}
+
+type Uploader<'a> = &'a dyn Fn(&RemoteConfig, &Path, &str) -> AppResult<()>;
+
+fn upload_if_due_with(
+    conn: &Connection,
+    live: &Path,
+    uploader: Uploader,
+) -> AppResult<Option<bool>> {
+    let config = remote_config(conn, live)?;
+    if config.target == RemoteTarget::None {
+        return Ok(None);
+    }
+    let Some(latest) = backup::list_backups(conn, live)?.into_iter().next() else {
+        return Ok(None);
+    };
+    let due: bool = conn.query_row(
+        "SELECT (last_uploaded IS NULL OR last_uploaded < ?1)
+            AND (failures = 0 OR last_attempt_at IS NULL
+                 OR datetime(last_attempt_at,
+                             '+' || min(?2 << (failures - 1), ?3) || ' minutes')
+                    <= datetime('now'))
+         FROM remote_backup_status WHERE id = 1",
+        params![latest.taken_at, FIRST_RETRY_MINUTES, MAX_RETRY_MINUTES],
+        |row| row.get(0),
+    )?;
+    if !due {
+        return Ok(None);
+    }
+
+    let staged: PathBuf = live.with_extension("db.upload");
+    let name = latest.file_name.replace(".db", ".pcbak");
+    let result = fs::read(&latest.path)
+        .map_err(AppError::from)
+        .and_then(|database| backup_archive::archive_database(&database, &config.passphrase))
+        .and_then(|archive| Ok(fs::write(&staged, archive)?))
+        .and_then(|()| uploader(&config, &staged, &name));
+    let _ = fs::remove_file(&staged);
+
+    let uploaded = result.is_ok();
+    match result {
+        Ok(()) => conn.execute(
+            "UPDATE remote_backup_status
+             SET last_attempt_at = datetime('now'), last_success_at = datetime('now'),
+                 last_uploaded = ?1, last_error = NULL, failures = 0
+             WHERE id = 1",
+            params![latest.taken_at],
+        )?,
+        Err(err) => conn.execute(
+            "UPDATE remote_backup_status
+             SET last_attempt_at = datetime('now'), last_error = ?1, failures = failures + 1
+             WHERE id = 1",
+            params![err.to_string()],
+        )?,
+    };
+    Ok(Some(uploaded))
+}
+
+/// Upload the newest snapshot if it has not been uploaded yet and no
+/// retry wait is running. Upload failures are recorded in the status
+/// rather than returned. Called periodically while the app runs.
+pub fn upload_if_due(conn: &Connection, live: &Path) -> AppResult<()> {
+    let utc_now: String =
+        conn.query_row("SELECT strftime('%Y%m%dT%H%M%SZ', 'now')", [], |row| {
+            row.get(0)
+        })?;
+    upload_if_due_with(conn, live, &|config, archive, name| {
+        upload(config, archive, name, &utc_now)
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::db::test_support::{insert_cashier, migrated_db};
+
+    const ADMIN: i64 = 1;
+
+    #[test]
+    fn signing_key_matches_the_aws_example() {
+        let key = signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20120215",
+            "us-east-1",
+            "iam",
+        );
+        assert_eq!(
+            hex(&key),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+    }
+
+    #[test]
+    fn s3_paths_are_signed_uri_encoded() {
+        assert_eq!(
+            uri_encode_path("/shop backups/pharmacare+2024~1.pcbak"),
+            "/shop%20backups/pharmacare%2B2024~1.pcbak"
+        );
+    }
+
+    #[test]
+    fn backup_urls_must_use_https() {
+        assert_eq!(
+            split_url("https://s3.example.com/backups").unwrap(),
+            ("s3.example.com", "/backups")
+        );
+        let err = split_url("http://s3.example.com/backups").unwrap_err();
+        assert!(err.to_string().contains("https://"), "{err}");
+    }
+
+    #[test]
+    fn secrets_are_kept_out_of_the_database_and_need_a_permission() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+
+        set_remote_secrets(&conn, db.path(), Some("s3cret".into()), None, None, ADMIN).unwrap();
+        let set = set_remote_secrets(&conn, db.path(), None, Some("refresh".into()), None, ADMIN)
+            .unwrap();
+        assert_eq!(
+            (set.secret, set.refresh_token, set.passphrase),
+            (true, true, false)
+        );
+        assert_eq!(remote_secrets_set(&conn, db.path(), ADMIN).unwrap(), set);
+        let config = remote_config(&conn, db.path()).unwrap();
+        assert_eq!(
+            (config.secret.as_str(), config.refresh_token.as_str()),
+            ("s3cret", "refresh")
+        );
+        let saved = fs::read_to_string(secrets_path(db.path())).unwrap();
+        assert!(saved.contains("s3cret"));
+
+        let cashier = insert_cashier(&conn);
+        let err = set_remote_secrets(&conn, db.path(), Some("x".into()), None, None, cashier)
+            .unwrap_err();
+        assert!(matches!(err, AppError::Unauthorized(_)), "{err}");
+        let err = remote_secrets_set(&conn, db.path(), cashier).unwrap_err();
+        assert!(matches!(err, AppError::Unauthorized(_)), "{err}");
+        let err = backup_status(&conn, db.path(), cashier).unwrap_err();
+        assert!(matches!(err, AppError::Unauthorized(_)), "{err}");
+    }
+
+    #[test]
+    fn failed_upload_waits_before_retrying_and_success_clears_it() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        conn.execute(
+            "UPDATE pharmacy_settings
+             SET remote_backup_target = 'webdav', remote_backup_url = 'https://dav.example/backups'
+             WHERE id = 1",
+            [],
+        )
+        .unwrap();
+        set_remote_secrets(
+            &conn,
+            db.path(),
+            None,
+            None,
+            Some("correct horse".into()),
+            ADMIN,
+        )
+        .unwrap();
+        backup::backup_now(&conn, db.path(), 1).unwrap();
+        let calls = Cell::new(0);
+        let failing = |_: &RemoteConfig, _: &Path, _: &str| -> AppResult<()> {
+            calls.set(calls.get() + 1);
+            Err(AppError::validation("Upload failed: connection refused"))
+        };
+
+        assert_eq!(
+            upload_if_due_with(&conn, db.path(), &failing).unwrap(),
+            Some(false)
+        );
+        // Still inside the retry wait.
+        assert_eq!(
+            upload_if_due_with(&conn, db.path(), &failing).unwrap(),
+            None
+        );
+        assert_eq!(calls.get(), 1);
+        let status = backup_status(&conn, db.path(), ADMIN).unwrap();
+        assert_eq!(status.failures, 1);
+        assert!(status.last_error.unwrap().contains("connection refused"));
+
+        conn.execute(
+            "UPDATE remote_backup_status SET last_attempt_at = datetime('now', '-10 minutes')",
+            [],
+        )
+        .unwrap();
+        let uploaded = Cell::new(None);
+        let working = |config: &RemoteConfig, archive: &Path, name: &str| -> AppResult<()> {
+            assert_eq!(config.target, RemoteTarget::Webdav);
+            assert!(fs::read(archive).unwrap().starts_with(b"PHARMBAK"));
+            uploaded.set(Some(name.to_string()));
+            Ok(())
+        };
+        assert_eq!(
+            upload_if_due_with(&conn, db.path(), &working).unwrap(),
+            Some(true)
+        );
+        let name = uploaded.take().unwrap();
+        assert!(name.starts_with("pharmacare-") && name.ends_with(".pcbak"));
+
+        let status = backup_status(&conn, db.path(), ADMIN).unwrap();
+        assert_eq!((status.failures, status.last_error), (0, None));
+        assert_eq!(
+            status.last_uploaded,
+            status.latest_local.map(|f| f.taken_at)
+        );
+        assert!(status.last_success_at.is_some());
+        // Nothing new to upload.
+        assert_eq!(
+            upload_if_due_with(&conn, db.path(), &working).unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,385 @@
+use std::collections::{BTreeMap, HashMap};
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::auth::{self, permission};
+use crate::db::{ChangeOp, Entity, Tx};
+use crate::demand;
+use crate::error::{AppError, AppResult};
+use crate::inventory::UNEXPIRED;
+use crate::purchase_orders::{self, PurchaseOrder, PurchaseOrderLine};
+use crate::sync;
+
+/// z-score for a 95% cycle service level.
+const SAFETY_FACTOR: f64 = 1.65;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReorderSuggestion {
+    pub medicine_id: i64,
+    pub medicine_name: String,
+    pub current_level: i64,
+    pub suggested_level: i64,
+    pub mean_daily_demand: f64,
+    pub safety_stock: i64,
+}
+
+/// Suggest reorder levels from recent sales.
+///
+/// `level = mean daily demand × lead time + safety stock`, where safety stock
+/// is `z × σ(daily demand) × √lead time` and the lead time comes from
+/// settings. Only active medicines with sales in the window are included, so
+/// new or seasonal lines keep their manual levels.
+pub fn suggest_reorder_levels(
+    conn: &Connection,
+    lookback_days: i64,
+) -> AppResult<Vec<ReorderSuggestion>> {
+    let lead_time: i64 = conn.query_row(
+        "SELECT reorder_lead_time_days FROM pharmacy_settings WHERE id = 1",
+        [],
+        |row| row.get(0),
+    )?;
+    let lead_time = lead_time.max(1) as f64;
+
+    let mut medicine = conn.prepare_cached(
+        "SELECT name, reorder_level FROM medicines WHERE id = ?1 AND is_active = 1",
+    )?;
+    let mut suggestions = Vec::new();
+    for d in demand::daily_demand(conn, lookback_days)? {
+        let Some((name, current)) = medicine
+            .query_row(params![d.medicine_id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .optional()?
+        else {
+            continue;
+        };
+        let safety = (SAFETY_FACTOR * d.std_dev_daily * lead_time.sqrt()).ceil();
+        suggestions.push(ReorderSuggestion {
+            medicine_id: d.medicine_id,
+            medicine_name: name,
+            current_level: current,
+            suggested_level: (d.mean_daily * lead_time + safety).ceil() as i64,
+            mean_daily_demand: d.mean_daily,
+            safety_stock: safety as i64,
+        });
+    }
+    Ok(suggestions)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReorderLevelUpdate {
+    pub medicine_id: i64,
+    pub reorder_level: i64,
+}
+
+/// Bulk-update reorder levels, typically with accepted suggestions.
+///
+/// Returns the number of medicines updated.
+pub fn apply_reorder_levels(
+    tx: &Tx,
+    updates: &[ReorderLevelUpdate],
+    user_id: i64,
+) -> AppResult<usize> {
+    auth::require_permission(tx, user_id, permission::INVENTORY_REORDER)?;
+
+    let mut stmt = tx.prepare_cached("UPDATE medicines SET reorder_level = ?1 WHERE id = ?2")?;
+    for update in updates {
+        if update.reorder_level < 0 {
+            return Err(AppError::validation("Reorder level cannot be negative"));
+        }
+        if stmt.execute(params![update.reorder_level, update.medicine_id])? == 0 {
+            return Err(AppError::not_found(format!(
+                "Medicine {}",
+                update.medicine_id
+            )));
+        }
+        tx.changed(Entity::Medicine, ChangeOp::Update, update.medicine_id);
+    }
+    sync::enqueue(
+        tx,
+        "apply_reorder_levels",
+        &json!({ "updates": updates, "userId": user_id }),
+    )?;
+    Ok(updates.len())
+}
+
+/// How much of one medicine to order.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderSuggestion {
+    pub medicine_id: i64,
+    pub medicine_name: String,
+    /// Sellable loose units across batches.
+    pub current_stock: i64,
+    pub reorder_level: i64,
+    pub mean_daily_demand: f64,
+    /// Loose units, rounded up to whole packs.
+    pub suggested_quantity: i64,
+    /// Cost on the last purchase of the medicine, 0 if never bought.
+    pub last_cost_paise: i64,
+}
+
+/// Suggestions for the supplier each medicine was last bought from.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SupplierOrder {
+    /// `None` for medicines never bought from anyone.
+    pub supplier_id: Option<i64>,
+    pub supplier_name: Option<String>,
+    pub items: Vec<OrderSuggestion>,
+}
+
+/// What to order, by supplier, for active medicines running low.
+///
+/// A medicine needs ordering when its sellable stock has fallen to its
+/// reorder level, or below what it is expected to sell over the lead time
+/// at its rate over the last `lookback_days` days. Enough is suggested to
+/// bring it back to the reorder level plus that lead-time demand. Each is
+/// grouped under the supplier of its latest purchase; those never
+/// purchased come last, under no supplier. Stock is counted as in
+/// [`crate::reports::reorder_report`].
+pub fn suggest_orders(
+    conn: &Connection,
+    lookback_days: i64,
+    branch_id: Option<i64>,
+) -> AppResult<Vec<SupplierOrder>> {
+    let lead_time: i64 = conn.query_row(
+        "SELECT reorder_lead_time_days FROM pharmacy_settings WHERE id = 1",
+        [],
+        |row| row.get(0),
+    )?;
+    let lead_time = lead_time.max(1) as f64;
+    let demand: HashMap<i64, f64> = demand::daily_demand(conn, lookback_days)?
+        .into_iter()
+        .map(|d| (d.medicine_id, d.mean_daily))
+        .collect();
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT m.id, m.name, m.pack_size,
+             CASE WHEN m.reorder_level > 0 THEN m.reorder_level
+                  ELSE (SELECT low_stock_threshold FROM pharmacy_settings WHERE id = 1)
+             END,
+             COALESCE(SUM(b.quantity), 0),
+             last.supplier_id, sup.name, COALESCE(last.cost_price_paise, 0)
+         FROM medicines m
+         LEFT JOIN batches b ON m.id = b.medicine_id
+             AND {UNEXPIRED} AND b.quantity > 0 AND b.status = 'available'
+             AND (?1 IS NULL OR b.branch_id = ?1)
+         LEFT JOIN (
+             SELECT pi.medicine_id, p.supplier_id, pi.cost_price_paise,
+                 ROW_NUMBER() OVER (PARTITION BY pi.medicine_id ORDER BY pi.id DESC) AS recency
+             FROM purchase_items pi
+             JOIN purchases p ON p.id = pi.purchase_id
+         ) last ON last.medicine_id = m.id AND last.recency = 1
+         LEFT JOIN suppliers sup ON sup.id = last.supplier_id
+         WHERE m.is_active = 1
+         GROUP BY m.id
+         ORDER BY sup.name IS NULL, sup.name, m.name"
+    ))?;
+    let mut rows = stmt.query([branch_id])?;
+
+    let mut orders: Vec<SupplierOrder> = Vec::new();
+    while let Some(row) = rows.next()? {
+        let medicine_id: i64 = row.get(0)?;
+        let (pack_size, level, stock): (i64, i64, i64) = (row.get(2)?, row.get(3)?, row.get(4)?);
+        let mean_daily = demand.get(&medicine_id).copied().unwrap_or(0.0);
+        let lead_demand = (mean_daily * lead_time).ceil() as i64;
+        if stock > level && stock >= lead_demand {
+            continue;
+        }
+        let pack_size = pack_size.max(1);
+        let wanted = (level + lead_demand - stock).max(1);
+        let suggestion = OrderSuggestion {
+            medicine_id,
+            medicine_name: row.get(1)?,
+            current_stock: stock,
+            reorder_level: level,
+            mean_daily_demand: mean_daily,
+            suggested_quantity: (wanted + pack_size - 1) / pack_size * pack_size,
+            last_cost_paise: row.get(7)?,
+        };
+        let supplier_id: Option<i64> = row.get(5)?;
+        match orders.last_mut() {
+            Some(order) if order.supplier_id == supplier_id => order.items.push(suggestion),
+            _ => orders.push(SupplierOrder {
+                supplier_id,
+                supplier_name: row.get(6)?,
+                items: vec![suggestion],
+            }),
+        }
+    }
+    Ok(orders)
+}
+
+/// Draft a purchase order for each supplier with suggestions, or only for
+/// `supplier_id` when given.
+///
+/// Lines carry the suggested quantities and the last purchase cost.
+/// Medicines with no supplier on record are left for a manual order.
+pub fn draft_suggested_orders(
+    tx: &Tx,
+    lookback_days: i64,
+    branch_id: Option<i64>,
+    supplier_id: Option<i64>,
+    user_id: i64,
+) -> AppResult<Vec<PurchaseOrder>> {
+    let mut drafts = BTreeMap::new();
+    for order in suggest_orders(tx, lookback_days, branch_id)? {
+        let Some(id) = order.supplier_id else {
+            continue;
+        };
+        if supplier_id.is_some_and(|wanted| wanted != id) {
+            continue;
+        }
+        let lines: Vec<PurchaseOrderLine> = order
+            .items
+            .iter()
+            .map(|item| PurchaseOrderLine {
+                medicine_id: item.medicine_id,
+                quantity: item.suggested_quantity,
+                expected_cost_paise: item.last_cost_paise,
+            })
+            .collect();
+        drafts.insert(id, lines);
+    }
+    if drafts.is_empty() {
+        return Err(AppError::validation(
+            "Nothing needs ordering from a known supplier",
+        ));
+    }
+    drafts
+        .into_iter()
+        .map(|(id, lines)| {
+            purchase_orders::create_purchase_order(
+                tx,
+                id,
+                &lines,
+                Some("Drafted from reorder suggestions"),
+                user_id,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{insert_batch, insert_medicine, migrated_db};
+    use crate::purchases::{self, PurchaseLine};
+
+    #[test]
+    fn steady_demand_needs_no_safety_stock() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        conn.execute_batch(&format!(
+            "UPDATE pharmacy_settings SET reorder_lead_time_days = 5;
+             INSERT INTO batches (medicine_id, batch_number, expiry_date, cost_price_paise,
+                 mrp_paise, selling_price_paise, quantity)
+             VALUES ({para}, 'B1', '2099-01-01', 100, 100, 100, 0);
+             INSERT INTO sales (invoice_number, user_id, sale_date)
+             VALUES ('A', 1, datetime('now')), ('B', 1, datetime('now', '-1 day'));
+             INSERT INTO sale_items (sale_id, batch_id, medicine_id, quantity,
+                 unit_price_paise, taxable_amount_paise, total_paise)
+             VALUES (1, 1, {para}, 3, 100, 90, 100), (2, 1, {para}, 3, 100, 90, 100);"
+        ))
+        .unwrap();
+
+        let suggestions = suggest_reorder_levels(&conn, 2).unwrap();
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].current_level, 20);
+        assert_eq!(suggestions[0].safety_stock, 0);
+        assert_eq!(suggestions[0].suggested_level, 15);
+    }
+
+    #[test]
+    fn apply_updates_levels_and_rejects_cashiers() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        conn.execute(
+            "INSERT INTO users (username, password_hash, full_name, role)
+             VALUES ('till', 'x', 'Till', 'cashier')",
+            [],
+        )
+        .unwrap();
+        let cashier = conn.last_insert_rowid();
+        let updates = [ReorderLevelUpdate {
+            medicine_id: para,
+            reorder_level: 42,
+        }];
+
+        assert!(db
+            .with_tx(|tx| apply_reorder_levels(tx, &updates, cashier))
+            .is_err());
+        assert_eq!(
+            db.with_tx(|tx| apply_reorder_levels(tx, &updates, 1))
+                .unwrap(),
+            1
+        );
+        let level: i64 = conn
+            .query_row(
+                "SELECT reorder_level FROM medicines WHERE id = ?1",
+                [para],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(level, 42);
+    }
+
+    #[test]
+    fn suggestions_group_by_last_supplier_and_draft_orders() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let cough = insert_medicine(&conn, "Cough Syrup", 12.0);
+        let plenty = insert_medicine(&conn, "Vitamin C", 12.0);
+        conn.execute_batch(&format!(
+            "UPDATE pharmacy_settings SET reorder_lead_time_days = 5;
+             UPDATE medicines SET reorder_level = 10;
+             UPDATE medicines SET pack_size = 10 WHERE id = {para};
+             INSERT INTO suppliers (name) VALUES ('Medline');"
+        ))
+        .unwrap();
+        let supplier = conn.last_insert_rowid();
+        insert_batch(&conn, plenty, "+90 days", 100, 50);
+        db.with_tx(|tx| {
+            purchases::receive_purchase(
+                tx,
+                supplier,
+                "INV-1",
+                &[PurchaseLine {
+                    medicine_id: para,
+                    batch_number: "P1".into(),
+                    expiry_date: "2099-01-01".into(),
+                    cost_price_paise: 450,
+                    mrp_paise: 900,
+                    selling_price_paise: 900,
+                    quantity: 4,
+                }],
+                1,
+            )
+        })
+        .unwrap();
+
+        let orders = suggest_orders(&conn, 30, None).unwrap();
+
+        assert_eq!(orders.len(), 2);
+        assert_eq!(orders[0].supplier_id, Some(supplier));
+        // 10 - 4 = 6 units, rounded up to a strip of 10.
+        let item = &orders[0].items[0];
+        assert_eq!((item.medicine_id, item.suggested_quantity), (para, 10));
+        assert_eq!(item.last_cost_paise, 450);
+        assert_eq!(orders[1].supplier_id, None);
+        assert_eq!(orders[1].items[0].medicine_id, cough);
+
+        let drafts = db
+            .with_tx(|tx| draft_suggested_orders(tx, 30, None, None, 1))
+            .unwrap();
+        assert_eq!(drafts.len(), 1);
+        assert_eq!(drafts[0].supplier_id, supplier);
+        assert_eq!(drafts[0].items[0].quantity, 10);
+    }
+}
@@ -0,0 +1,2098 @@
+//! Stock reports computed in the backend.
+
+use std::collections::{BTreeMap, HashMap};
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult};
+use crate::expenses::{self, CategoryExpense};
+use crate::inventory::{EXPIRED, UNEXPIRED};
+use crate::locations;
+
+/// An in-stock batch with the cost tied up in it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpiryBatch {
+    pub batch_id: i64,
+    pub medicine_id: i64,
+    pub medicine_name: String,
+    pub batch_number: String,
+    pub expiry_date: String,
+    pub quantity: i64,
+    /// `quantity * cost_price_paise`.
+    pub value_paise: i64,
+}
+
+/// In-stock batches split by how close they are to expiry, soonest first.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpiryReport {
+    /// The `pharmacy_settings.near_expiry_days` window used.
+    pub near_expiry_days: i64,
+    /// Past expiry, so no longer sellable.
+    pub expired: Vec<ExpiryBatch>,
+    pub near_expiry: Vec<ExpiryBatch>,
+    pub fine: Vec<ExpiryBatch>,
+}
+
+/// Bucket every batch that still holds stock by expiry.
+///
+/// Expired as in [`EXPIRED`]: a batch expiring today is still sold, so it
+/// is near expiry rather than expired. `branch_id` limits the report to one
+/// branch; `None` covers all of them.
+pub fn expiry_report(conn: &Connection, branch_id: Option<i64>) -> AppResult<ExpiryReport> {
+    let near_expiry_days: i64 = conn.query_row(
+        "SELECT near_expiry_days FROM pharmacy_settings WHERE id = 1",
+        [],
+        |row| row.get(0),
+    )?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT b.id, b.medicine_id, m.name, b.batch_number, b.expiry_date, b.quantity,
+             b.quantity * b.cost_price_paise,
+             {EXPIRED},
+             julianday(b.expiry_date) - julianday('now') <= ?1
+         FROM batches b
+         JOIN medicines m ON m.id = b.medicine_id
+         WHERE b.quantity > 0 AND (?2 IS NULL OR b.branch_id = ?2)
+         ORDER BY b.expiry_date ASC, b.id ASC"
+    ))?;
+    let mut rows = stmt.query(params![near_expiry_days, branch_id])?;
+
+    let mut report = ExpiryReport {
+        near_expiry_days,
+        ..Default::default()
+    };
+    while let Some(row) = rows.next()? {
+        let batch = ExpiryBatch {
+            batch_id: row.get(0)?,
+            medicine_id: row.get(1)?,
+            medicine_name: row.get(2)?,
+            batch_number: row.get(3)?,
+            expiry_date: row.get(4)?,
+            quantity: row.get(5)?,
+            value_paise: row.get(6)?,
+        };
+        let (expired, near): (bool, bool) = (row.get(7)?, row.get(8)?);
+        if expired {
+            report.expired.push(batch);
+        } else if near {
+            report.near_expiry.push(batch);
+        } else {
+            report.fine.push(batch);
+        }
+    }
+    Ok(report)
+}
+
+/// An active medicine at or below its reorder level.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReorderItem {
+    pub medicine_id: i64,
+    pub medicine_name: String,
+    /// The medicine's own level, or `low_stock_threshold` when it has none.
+    pub reorder_level: i64,
+    pub current_stock: i64,
+    /// `reorder_level - current_stock`.
+    pub shortfall: i64,
+}
+
+/// Active medicines whose sellable stock has fallen to their reorder level,
+/// largest shortfall first. Stock is counted at `branch_id`, or across all
+/// branches when it is `None`.
+///
+/// [`crate::reorder::suggest_orders`] adds order quantities and suppliers.
+pub fn reorder_report(conn: &Connection, branch_id: Option<i64>) -> AppResult<Vec<ReorderItem>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, name, level, stock, level - stock AS shortfall
+         FROM (
+             SELECT m.id, m.name,
+                 CASE WHEN m.reorder_level > 0 THEN m.reorder_level
+                      ELSE (SELECT low_stock_threshold FROM pharmacy_settings WHERE id = 1)
+                 END AS level,
+                 COALESCE(SUM(b.quantity), 0) AS stock
+             FROM medicines m
+             LEFT JOIN batches b ON m.id = b.medicine_id
+                 AND {UNEXPIRED} AND b.quantity > 0 AND b.status = 'available'
+                 AND (?1 IS NULL OR b.branch_id = ?1)
+             WHERE m.is_active = 1
+             GROUP BY m.id
+         )
+         WHERE stock <= level
+         ORDER BY shortfall DESC, name ASC"
+    ))?;
+    let rows = stmt.query_map([branch_id], |row| {
+        Ok(ReorderItem {
+            medicine_id: row.get(0)?,
+            medicine_name: row.get(1)?,
+            reorder_level: row.get(2)?,
+            current_stock: row.get(3)?,
+            shortfall: row.get(4)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+/// Units on hand and what they are worth.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StockValue {
+    pub quantity: i64,
+    /// `quantity * cost_price_paise`.
+    pub cost_value_paise: i64,
+    /// `quantity * mrp_paise`.
+    pub mrp_value_paise: i64,
+}
+
+fn overflow() -> AppError {
+    AppError::validation("Inventory value is too large to total")
+}
+
+impl StockValue {
+    /// Add one batch, failing instead of wrapping on overflow.
+    fn add_batch(&mut self, quantity: i64, cost_paise: i64, mrp_paise: i64) -> AppResult<()> {
+        let cost = quantity.checked_mul(cost_paise).ok_or_else(overflow)?;
+        let mrp = quantity.checked_mul(mrp_paise).ok_or_else(overflow)?;
+        self.add(&StockValue {
+            quantity,
+            cost_value_paise: cost,
+            mrp_value_paise: mrp,
+        })
+    }
+
+    fn add(&mut self, other: &StockValue) -> AppResult<()> {
+        self.quantity = self
+            .quantity
+            .checked_add(other.quantity)
+            .ok_or_else(overflow)?;
+        self.cost_value_paise = self
+            .cost_value_paise
+            .checked_add(other.cost_value_paise)
+            .ok_or_else(overflow)?;
+        self.mrp_value_paise = self
+            .mrp_value_paise
+            .checked_add(other.mrp_value_paise)
+            .ok_or_else(overflow)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryValuation {
+    /// The medicine category, or "Uncategorized".
+    pub category: String,
+    #[serde(flatten)]
+    pub value: StockValue,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValuationReport {
+    /// Unexpired stock, by category name.
+    pub categories: Vec<CategoryValuation>,
+    /// Sum of `categories`.
+    pub total: StockValue,
+    /// Expired stock, kept out of `total`.
+    pub dead_stock: StockValue,
+}
+
+/// Value of the stock on hand at cost and at MRP.
+///
+/// Quarantined and blocked batches are included; expired batches go to
+/// dead stock, as in [`expiry_report`]. `branch_id` values
+/// one branch only.
+pub fn inventory_valuation(
+    conn: &Connection,
+    branch_id: Option<i64>,
+) -> AppResult<ValuationReport> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT COALESCE(NULLIF(TRIM(m.category), ''), 'Uncategorized'),
+             b.quantity, b.cost_price_paise, b.mrp_paise, {EXPIRED}
+         FROM batches b
+         JOIN medicines m ON m.id = b.medicine_id
+         WHERE b.quantity > 0 AND (?1 IS NULL OR b.branch_id = ?1)"
+    ))?;
+    let mut rows = stmt.query([branch_id])?;
+
+    let mut report = ValuationReport::default();
+    let mut categories = BTreeMap::<String, StockValue>::new();
+    while let Some(row) = rows.next()? {
+        let (quantity, cost, mrp): (i64, i64, i64) = (row.get(1)?, row.get(2)?, row.get(3)?);
+        if row.get::<_, bool>(4)? {
+            report.dead_stock.add_batch(quantity, cost, mrp)?;
+        } else {
+            categories
+                .entry(row.get(0)?)
+                .or_default()
+                .add_batch(quantity, cost, mrp)?;
+        }
+    }
+
+    for (category, value) in categories {
+        report.total.add(&value)?;
+        report
+            .categories
+            .push(CategoryValuation { category, value });
+    }
+    Ok(report)
+}
+
+/// How [`stock_valuation`] prices stock at cost.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CostMethod {
+    /// Each batch at its own cost price.
+    #[default]
+    Batch,
+    /// Every batch of a medicine at the average cost of all units bought.
+    WeightedAverage,
+}
+
+/// Loose units on hand at cost, MRP and selling price.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Valuation {
+    pub quantity: i64,
+    pub cost_value_paise: i64,
+    pub mrp_value_paise: i64,
+    pub selling_value_paise: i64,
+}
+
+impl Valuation {
+    fn add(&mut self, other: &Valuation) -> AppResult<()> {
+        let sum = |a: i64, b: i64| a.checked_add(b).ok_or_else(overflow);
+        self.quantity = sum(self.quantity, other.quantity)?;
+        self.cost_value_paise = sum(self.cost_value_paise, other.cost_value_paise)?;
+        self.mrp_value_paise = sum(self.mrp_value_paise, other.mrp_value_paise)?;
+        self.selling_value_paise = sum(self.selling_value_paise, other.selling_value_paise)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupValuation {
+    /// The category or manufacturer; blanks are grouped as "Uncategorized"
+    /// and "Unknown".
+    pub name: String,
+    #[serde(flatten)]
+    pub value: Valuation,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StockValuation {
+    pub method: CostMethod,
+    /// Unexpired stock, by category name.
+    pub categories: Vec<GroupValuation>,
+    /// Unexpired stock, by manufacturer name.
+    pub manufacturers: Vec<GroupValuation>,
+    pub total: Valuation,
+    /// Expired stock, kept out of `total`.
+    pub expired: Valuation,
+}
+
+/// `quantity` loose units at `numerator / denominator` paise a pack of
+/// `pack_size`, rounded to the paisa.
+fn units_value(quantity: i64, numerator: i64, denominator: i64, pack_size: i64) -> AppResult<i64> {
+    let divisor = i128::from(denominator) * i128::from(pack_size.max(1));
+    let value = (i128::from(quantity) * i128::from(numerator) + divisor / 2) / divisor;
+    i64::try_from(value).map_err(|_| overflow())
+}
+
+/// Value of the stock on hand at cost, MRP and selling price, by category
+/// and by manufacturer.
+///
+/// Prices are per pack and quantities loose units, so each batch is valued
+/// at its share of a pack. With [`CostMethod::WeightedAverage`] a
+/// medicine's cost is the average over every purchase of it, weighted by
+/// units received; medicines with no purchases on record keep their batch
+/// costs. Expired stock and `branch_id` are treated as in
+/// [`inventory_valuation`].
+pub fn stock_valuation(
+    conn: &Connection,
+    branch_id: Option<i64>,
+    method: CostMethod,
+) -> AppResult<StockValuation> {
+    // (units received, their cost in paise a pack) per medicine.
+    let mut averages = HashMap::<i64, (i64, i64)>::new();
+    if method == CostMethod::WeightedAverage {
+        let mut stmt = conn.prepare(
+            "SELECT sm.medicine_id, SUM(sm.change_qty), SUM(sm.change_qty * b.cost_price_paise)
+             FROM stock_movements sm
+             JOIN batches b ON b.id = sm.batch_id
+             WHERE sm.reason = 'purchase' AND sm.change_qty > 0
+             GROUP BY sm.medicine_id",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))?;
+        averages = rows.collect::<Result<_, _>>()?;
+    }
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT m.id, COALESCE(NULLIF(TRIM(m.category), ''), 'Uncategorized'),
+             COALESCE(NULLIF(TRIM(m.manufacturer), ''), 'Unknown'), m.pack_size,
+             b.quantity, b.cost_price_paise, b.mrp_paise, b.selling_price_paise,
+             {EXPIRED}
+         FROM batches b
+         JOIN medicines m ON m.id = b.medicine_id
+         WHERE b.quantity > 0 AND (?1 IS NULL OR b.branch_id = ?1)"
+    ))?;
+    let mut rows = stmt.query([branch_id])?;
+
+    let mut expired = Valuation::default();
+    let mut categories = BTreeMap::<String, Valuation>::new();
+    let mut manufacturers = BTreeMap::<String, Valuation>::new();
+    while let Some(row) = rows.next()? {
+        let (pack_size, quantity): (i64, i64) = (row.get(3)?, row.get(4)?);
+        let (cost, cost_units) = match averages.get(&row.get::<_, i64>(0)?) {
+            Some(&(units, total)) if units > 0 => (total, units),
+            _ => (row.get(5)?, 1),
+        };
+        let value = Valuation {
+            quantity,
+            cost_value_paise: units_value(quantity, cost, cost_units, pack_size)?,
+            mrp_value_paise: units_value(quantity, row.get(6)?, 1, pack_size)?,
+            selling_value_paise: units_value(quantity, row.get(7)?, 1, pack_size)?,
+        };
+        if row.get::<_, bool>(8)? {
+            expired.add(&value)?;
+        } else {
+            categories.entry(row.get(1)?).or_default().add(&value)?;
+            manufacturers.entry(row.get(2)?).or_default().add(&value)?;
+        }
+    }
+
+    let mut total = Valuation::default();
+    for value in categories.values() {
+        total.add(value)?;
+    }
+    let groups = |map: BTreeMap<String, Valuation>| {
+        map.into_iter()
+            .map(|(name, value)| GroupValuation { name, value })
+            .collect()
+    };
+    Ok(StockValuation {
+        method,
+        categories: groups(categories),
+        manufacturers: groups(manufacturers),
+        total,
+        expired,
+    })
+}
+
+/// Stock kept at one location.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocationStock {
+    pub location_id: i64,
+    pub branch_id: i64,
+    pub location_name: String,
+    pub is_selling: bool,
+    /// Batches with stock at the location.
+    pub batch_count: i64,
+    #[serde(flatten)]
+    pub value: StockValue,
+}
+
+/// Stock on hand per location, selling floor first within each branch.
+///
+/// Every location is listed, empty ones included. The floor holds what a
+/// batch has beyond the units kept elsewhere, as in
+/// [`crate::locations::batch_locations`].
+pub fn stock_by_location(
+    conn: &Connection,
+    branch_id: Option<i64>,
+) -> AppResult<Vec<LocationStock>> {
+    let mut report: Vec<LocationStock> = locations::list_locations(conn, branch_id)?
+        .into_iter()
+        .map(|location| LocationStock {
+            location_id: location.id,
+            branch_id: location.branch_id,
+            location_name: location.name,
+            is_selling: location.is_selling,
+            batch_count: 0,
+            value: StockValue::default(),
+        })
+        .collect();
+    let mut stmt = conn.prepare(
+        "SELECT l.id,
+             CASE WHEN l.is_selling THEN b.quantity - COALESCE(
+                      (SELECT SUM(quantity) FROM batch_locations WHERE batch_id = b.id), 0)
+                  ELSE bl.quantity END AS held,
+             b.cost_price_paise, b.mrp_paise
+         FROM batches b
+         JOIN locations l ON l.branch_id = COALESCE(b.branch_id, 1)
+         LEFT JOIN batch_locations bl ON bl.batch_id = b.id AND bl.location_id = l.id
+         WHERE b.quantity > 0 AND (?1 IS NULL OR l.branch_id = ?1) AND held > 0",
+    )?;
+    let mut rows = stmt.query([branch_id])?;
+    while let Some(row) = rows.next()? {
+        let location_id: i64 = row.get(0)?;
+        let Some(entry) = report.iter_mut().find(|l| l.location_id == location_id) else {
+            continue;
+        };
+        entry.batch_count += 1;
+        entry
+            .value
+            .add_batch(row.get(1)?, row.get(2)?, row.get(3)?)?;
+    }
+    Ok(report)
+}
+
+/// Tax collected on one HSN code at one GST rate, in paise.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HsnSummary {
+    pub hsn_code: String,
+    pub gst_rate: f64,
+    pub total_quantity: i64,
+    pub taxable_value_paise: i64,
+    pub cgst_paise: i64,
+    pub sgst_paise: i64,
+    pub igst_paise: i64,
+    pub total_tax_paise: i64,
+}
+
+/// Fail unless both dates are `YYYY-MM-DD` and `from` is not after `to`.
+pub fn validate_date_range(conn: &Connection, from: &str, to: &str) -> AppResult<()> {
+    let (from_ok, to_ok): (bool, bool) =
+        conn.query_row("SELECT date(?1) IS ?1, date(?2) IS ?2", [from, to], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?;
+    if !from_ok || !to_ok {
+        return Err(AppError::validation("Dates must be in YYYY-MM-DD format"));
+    }
+    if from > to {
+        return Err(AppError::validation(
+            "The start date must not be after the end date",
+        ));
+    }
+    Ok(())
+}
+
+/// HSN-wise tax summary for sales dated `from_date` to `to_date`, inclusive.
+///
+/// Lines are grouped by the HSN code recorded on the sale and their
+/// combined GST rate. Refunded sales are excluded; partial returns are not
+/// netted off.
+pub fn hsn_tax_summary(
+    conn: &Connection,
+    from_date: &str,
+    to_date: &str,
+) -> AppResult<Vec<HsnSummary>> {
+    validate_date_range(conn, from_date, to_date)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT COALESCE(NULLIF(si.hsn_code, ''), m.hsn_code) AS hsn,
+             si.cgst_rate + si.sgst_rate + si.igst_rate AS rate,
+             SUM(si.quantity), SUM(si.taxable_amount_paise),
+             SUM(si.cgst_amount_paise), SUM(si.sgst_amount_paise), SUM(si.igst_amount_paise)
+         FROM sale_items si
+         JOIN sales s ON s.id = si.sale_id
+         JOIN medicines m ON m.id = si.medicine_id
+         WHERE date(s.sale_date) BETWEEN ?1 AND ?2 AND s.status <> 'refunded'
+         GROUP BY hsn, rate
+         ORDER BY hsn ASC, rate ASC",
+    )?;
+    let rows = stmt.query_map([from_date, to_date], |row| {
+        let (cgst, sgst, igst): (i64, i64, i64) = (row.get(4)?, row.get(5)?, row.get(6)?);
+        Ok(HsnSummary {
+            hsn_code: row.get(0)?,
+            gst_rate: row.get(1)?,
+            total_quantity: row.get(2)?,
+            taxable_value_paise: row.get(3)?,
+            cgst_paise: cgst,
+            sgst_paise: sgst,
+            igst_paise: igst,
+            total_tax_paise: cgst + sgst + igst,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+/// Grand totals split by how they were paid (or refunded).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentModeTotals {
+    pub cash_paise: i64,
+    pub card_paise: i64,
+    pub upi_paise: i64,
+    pub credit_paise: i64,
+}
+
+impl PaymentModeTotals {
+    pub(crate) fn add(&mut self, mode: &str, paise: i64) {
+        match mode {
+            "cash" => self.cash_paise += paise,
+            "card" => self.card_paise += paise,
+            "upi" => self.upi_paise += paise,
+            _ => self.credit_paise += paise,
+        }
+    }
+
+    /// Everything except credit, which is not collected at the till.
+    fn collected(&self) -> i64 {
+        self.cash_paise + self.card_paise + self.upi_paise
+    }
+}
+
+/// End-of-day (Z-report) totals for one date.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DailySummary {
+    pub date: String,
+    pub invoice_count: i64,
+    /// Line value before discount.
+    pub gross_sales_paise: i64,
+    pub discount_paise: i64,
+    pub total_gst_paise: i64,
+    pub grand_total_paise: i64,
+    pub by_payment_mode: PaymentModeTotals,
+    pub return_count: i64,
+    /// Refunds processed on the date, as a negative amount.
+    pub returns_paise: i64,
+    pub returns_by_refund_mode: PaymentModeTotals,
+    /// Cash, card and UPI taken minus the same refunded.
+    pub net_collection_paise: i64,
+}
+
+/// Totals of every sale dated `date` in local time, and of returns
+/// processed that day.
+///
+/// With a `branch_id` only that branch's sales, and returns against them,
+/// are counted; without one every branch is. A day without sales gives
+/// zeros.
+pub fn daily_sales_summary(
+    conn: &Connection,
+    date: &str,
+    branch_id: Option<i64>,
+) -> AppResult<DailySummary> {
+    validate_date_range(conn, date, date)?;
+    let mut summary = DailySummary {
+        date: date.to_owned(),
+        ..Default::default()
+    };
+
+    (
+        summary.invoice_count,
+        summary.gross_sales_paise,
+        summary.discount_paise,
+        summary.total_gst_paise,
+        summary.grand_total_paise,
+    ) = conn.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(subtotal_paise), 0), COALESCE(SUM(discount_paise), 0),
+             COALESCE(SUM(total_gst_paise), 0), COALESCE(SUM(grand_total_paise), 0)
+         FROM sales
+         WHERE date(sale_date, 'localtime') = ?1 AND (?2 IS NULL OR branch_id = ?2)",
+        params![date, branch_id],
+        |row| {
+            Ok((
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                row.get(3)?,
+                row.get(4)?,
+            ))
+        },
+    )?;
+
+    // Split-tender sales count towards each mode they were paid in.
+    let mut stmt = conn.prepare(
+        "SELECT sp.payment_mode, SUM(sp.amount_paise)
+         FROM sale_payments sp
+         JOIN sales s ON s.id = sp.sale_id
+         WHERE date(s.sale_date, 'localtime') = ?1 AND (?2 IS NULL OR s.branch_id = ?2)
+         GROUP BY sp.payment_mode",
+    )?;
+    let mut rows = stmt.query(params![date, branch_id])?;
+    while let Some(row) = rows.next()? {
+        let mode: String = row.get(0)?;
+        summary.by_payment_mode.add(&mode, row.get(1)?);
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT r.refund_mode, COUNT(*), SUM(r.refund_total_paise)
+         FROM sale_returns r
+         JOIN sales s ON s.id = r.sale_id
+         WHERE date(r.return_date, 'localtime') = ?1 AND (?2 IS NULL OR s.branch_id = ?2)
+         GROUP BY r.refund_mode",
+    )?;
+    let mut rows = stmt.query(params![date, branch_id])?;
+    while let Some(row) = rows.next()? {
+        let mode: String = row.get(0)?;
+        let refunded: i64 = row.get(2)?;
+        summary.return_count += row.get::<_, i64>(1)?;
+        summary.returns_paise -= refunded;
+        summary.returns_by_refund_mode.add(&mode, refunded);
+    }
+
+    summary.net_collection_paise =
+        summary.by_payment_mode.collected() - summary.returns_by_refund_mode.collected();
+    Ok(summary)
+}
+
+/// What one medicine earned over a date range, net of returns.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfitRow {
+    pub medicine_id: i64,
+    pub name: String,
+    /// Loose units sold less units returned.
+    pub quantity_sold: i64,
+    /// Taxable value, excluding GST.
+    pub revenue_paise: i64,
+    /// Cost of goods sold at the cost price of the batches sold from.
+    pub cost_paise: i64,
+    pub gross_profit_paise: i64,
+    /// Gross profit as a percentage of revenue; `None` when there was no
+    /// revenue to divide by.
+    pub margin_percent: Option<f64>,
+}
+
+/// `part` as a percentage of `whole`, to two places; `None` when `whole`
+/// is zero.
+fn percent_of(part_paise: i64, whole_paise: i64) -> Option<f64> {
+    (whole_paise != 0).then(|| (part_paise as f64 * 10_000.0 / whole_paise as f64).round() / 100.0)
+}
+
+/// Revenue, cost and margin per medicine for sales dated `from_date` to
+/// `to_date`, inclusive, most profitable first.
+///
+/// Returns against those sales are taken off, whenever they were made, so
+/// a fully refunded sale contributes nothing.
+pub fn profit_report(
+    conn: &Connection,
+    from_date: &str,
+    to_date: &str,
+) -> AppResult<Vec<ProfitRow>> {
+    validate_date_range(conn, from_date, to_date)?;
+
+    // Batch prices are per pack while quantities are loose units, so the
+    // cost is summed unit by unit and divided by the pack size once.
+    let mut stmt = conn.prepare(
+        "SELECT m.id, m.name, m.pack_size, SUM(t.quantity), SUM(t.revenue), SUM(t.cost)
+         FROM (
+             SELECT si.medicine_id, si.quantity, si.taxable_amount_paise AS revenue,
+                 si.quantity * b.cost_price_paise AS cost
+             FROM sale_items si
+             JOIN sales s ON s.id = si.sale_id
+             JOIN batches b ON b.id = si.batch_id
+             WHERE date(s.sale_date) BETWEEN ?1 AND ?2
+             UNION ALL
+             SELECT ri.medicine_id, -ri.quantity, -ri.taxable_amount_paise,
+                 -ri.quantity * b.cost_price_paise
+             FROM sale_return_items ri
+             JOIN sale_items si ON si.id = ri.sale_item_id
+             JOIN sales s ON s.id = si.sale_id
+             JOIN batches b ON b.id = ri.batch_id
+             WHERE date(s.sale_date) BETWEEN ?1 AND ?2
+         ) t
+         JOIN medicines m ON m.id = t.medicine_id
+         GROUP BY m.id
+         HAVING SUM(t.quantity) <> 0 OR SUM(t.revenue) <> 0",
+    )?;
+    let rows = stmt.query_map([from_date, to_date], |row| {
+        let pack_size: i64 = row.get(2)?;
+        let revenue_paise: i64 = row.get(4)?;
+        let unit_cost: i64 = row.get(5)?;
+        let cost_paise = (unit_cost + pack_size / 2).div_euclid(pack_size);
+        let gross_profit_paise = revenue_paise - cost_paise;
+        Ok(ProfitRow {
+            medicine_id: row.get(0)?,
+            name: row.get(1)?,
+            quantity_sold: row.get(3)?,
+            revenue_paise,
+            cost_paise,
+            gross_profit_paise,
+            margin_percent: percent_of(gross_profit_paise, revenue_paise),
+        })
+    })?;
+    let mut report = rows.collect::<Result<Vec<_>, _>>()?;
+    report.sort_by(|a, b| {
+        b.gross_profit_paise
+            .cmp(&a.gross_profit_paise)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    Ok(report)
+}
+
+/// Profit over a date range once expenses are paid.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfitSummary {
+    pub from_date: String,
+    pub to_date: String,
+    /// Taxable value of sales, net of returns.
+    pub revenue_paise: i64,
+    pub cost_paise: i64,
+    pub gross_profit_paise: i64,
+    pub expenses: Vec<CategoryExpense>,
+    pub expenses_paise: i64,
+    /// Gross profit less expenses dated in the range.
+    pub net_profit_paise: i64,
+}
+
+/// The totals of [`profit_report`] for `from_date` to `to_date`, less the
+/// expenses of the same dates.
+pub fn profit_summary(
+    conn: &Connection,
+    from_date: &str,
+    to_date: &str,
+) -> AppResult<ProfitSummary> {
+    let rows = profit_report(conn, from_date, to_date)?;
+    let expenses = expenses::expense_report(conn, from_date, to_date)?;
+    let revenue_paise = rows.iter().map(|r| r.revenue_paise).sum();
+    let cost_paise = rows.iter().map(|r| r.cost_paise).sum();
+    let gross_profit_paise = rows.iter().map(|r| r.gross_profit_paise).sum::<i64>();
+    Ok(ProfitSummary {
+        from_date: from_date.to_owned(),
+        to_date: to_date.to_owned(),
+        revenue_paise,
+        cost_paise,
+        gross_profit_paise,
+        net_profit_paise: gross_profit_paise - expenses.total_paise,
+        expenses_paise: expenses.total_paise,
+        expenses: expenses.categories,
+    })
+}
+
+/// Revenue against the cost of the batches sold from, net of returns.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Margin {
+    /// Taxable value, excluding GST.
+    pub revenue_paise: i64,
+    pub cost_paise: i64,
+    pub gross_profit_paise: i64,
+    pub margin_percent: Option<f64>,
+}
+
+impl Margin {
+    fn add(&mut self, revenue_paise: i64, cost_paise: i64) {
+        self.revenue_paise += revenue_paise;
+        self.cost_paise += cost_paise;
+        self.gross_profit_paise = self.revenue_paise - self.cost_paise;
+        self.margin_percent = percent_of(self.gross_profit_paise, self.revenue_paise);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvoiceMargin {
+    pub sale_id: i64,
+    pub invoice_number: String,
+    pub sale_date: String,
+    pub customer: Option<String>,
+    #[serde(flatten)]
+    pub margin: Margin,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchMargin {
+    pub batch_id: i64,
+    pub batch_number: String,
+    pub medicine_id: i64,
+    pub name: String,
+    /// Loose units sold less units returned.
+    pub quantity_sold: i64,
+    #[serde(flatten)]
+    pub margin: Margin,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyMargin {
+    /// `YYYY-MM-DD`.
+    pub date: String,
+    pub invoice_count: i64,
+    #[serde(flatten)]
+    pub margin: Margin,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarginReport {
+    pub from_date: String,
+    pub to_date: String,
+    pub total: Margin,
+    /// In the order they were made.
+    pub invoices: Vec<InvoiceMargin>,
+    /// Most profitable first.
+    pub medicines: Vec<ProfitRow>,
+    /// Most profitable first.
+    pub batches: Vec<BatchMargin>,
+    pub days: Vec<DailyMargin>,
+}
+
+/// Gross profit and margin per invoice, medicine, batch and day for sales
+/// dated `from_date` to `to_date`, inclusive.
+///
+/// Every figure is built from the same lines: each batch sold on each
+/// invoice, costed at that batch's cost price, less whatever of it came
+/// back. The sections therefore add up to the same total, though a
+/// medicine's cost can differ by a paisa from [`profit_report`], which
+/// rounds once per medicine rather than per line.
+pub fn margin_report(conn: &Connection, from_date: &str, to_date: &str) -> AppResult<MarginReport> {
+    validate_date_range(conn, from_date, to_date)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT s.id, s.invoice_number, date(s.sale_date), c.name, b.id, b.batch_number,
+             m.id, m.name, m.pack_size, SUM(t.quantity), SUM(t.revenue),
+             SUM(t.quantity) * b.cost_price_paise
+         FROM (
+             SELECT si.sale_id, si.batch_id, si.medicine_id, si.quantity,
+                 si.taxable_amount_paise AS revenue
+             FROM sale_items si
+             JOIN sales s ON s.id = si.sale_id
+             WHERE date(s.sale_date) BETWEEN ?1 AND ?2
+             UNION ALL
+             SELECT si.sale_id, ri.batch_id, ri.medicine_id, -ri.quantity,
+                 -ri.taxable_amount_paise
+             FROM sale_return_items ri
+             JOIN sale_items si ON si.id = ri.sale_item_id
+             JOIN sales s ON s.id = si.sale_id
+             WHERE date(s.sale_date) BETWEEN ?1 AND ?2
+         ) t
+         JOIN sales s ON s.id = t.sale_id
+         JOIN batches b ON b.id = t.batch_id
+         JOIN medicines m ON m.id = t.medicine_id
+         LEFT JOIN customers c ON c.id = s.customer_id
+         GROUP BY s.id, b.id
+         HAVING SUM(t.quantity) <> 0 OR SUM(t.revenue) <> 0
+         ORDER BY s.sale_date, s.id, b.id",
+    )?;
+    let mut rows = stmt.query(params![from_date, to_date])?;
+
+    let mut total = Margin::default();
+    let mut invoices: Vec<InvoiceMargin> = Vec::new();
+    let mut medicines = BTreeMap::<i64, ProfitRow>::new();
+    let mut batches = BTreeMap::<i64, BatchMargin>::new();
+    let mut days = BTreeMap::<String, DailyMargin>::new();
+    while let Some(row) = rows.next()? {
+        let sale_id: i64 = row.get(0)?;
+        let sale_date: String = row.get(2)?;
+        let (batch_id, medicine_id, pack_size): (i64, i64, i64) =
+            (row.get(4)?, row.get(6)?, row.get(8)?);
+        let (quantity, revenue, unit_cost): (i64, i64, i64) =
+            (row.get(9)?, row.get(10)?, row.get(11)?);
+        // Batch prices are per pack while quantities are loose units.
+        let cost = (unit_cost + pack_size / 2).div_euclid(pack_size);
+        total.add(revenue, cost);
+
+        if invoices.last().map(|i| i.sale_id) != Some(sale_id) {
+            invoices.push(InvoiceMargin {
+                sale_id,
+                invoice_number: row.get(1)?,
+                sale_date: sale_date.clone(),
+                customer: row.get(3)?,
+                margin: Margin::default(),
+            });
+            let day = days.entry(sale_date.clone()).or_insert(DailyMargin {
+                date: sale_date.clone(),
+                invoice_count: 0,
+                margin: Margin::default(),
+            });
+            day.invoice_count += 1;
+        }
+        if let Some(invoice) = invoices.last_mut() {
+            invoice.margin.add(revenue, cost);
+        }
+        if let Some(day) = days.get_mut(&sale_date) {
+            day.margin.add(revenue, cost);
+        }
+
+        let name: String = row.get(7)?;
+        let batch = batches.entry(batch_id).or_insert(BatchMargin {
+            batch_id,
+            batch_number: row.get(5)?,
+            medicine_id,
+            name: name.clone(),
+            quantity_sold: 0,
+            margin: Margin::default(),
+        });
+        batch.quantity_sold += quantity;
+        batch.margin.add(revenue, cost);
+
+        let medicine = medicines.entry(medicine_id).or_insert(ProfitRow {
+            medicine_id,
+            name,
+            quantity_sold: 0,
+            revenue_paise: 0,
+            cost_paise: 0,
+            gross_profit_paise: 0,
+            margin_percent: None,
+        });
+        medicine.quantity_sold += quantity;
+        medicine.revenue_paise += revenue;
+        medicine.cost_paise += cost;
+        medicine.gross_profit_paise = medicine.revenue_paise - medicine.cost_paise;
+        medicine.margin_percent = percent_of(medicine.gross_profit_paise, medicine.revenue_paise);
+    }
+
+    let mut medicines: Vec<_> = medicines.into_values().collect();
+    medicines.sort_by(|a, b| {
+        b.gross_profit_paise
+            .cmp(&a.gross_profit_paise)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    let mut batches: Vec<_> = batches.into_values().collect();
+    batches.sort_by(|a, b| {
+        b.margin
+            .gross_profit_paise
+            .cmp(&a.margin.gross_profit_paise)
+            .then_with(|| a.name.cmp(&b.name))
+            .then_with(|| a.batch_number.cmp(&b.batch_number))
+    });
+    Ok(MarginReport {
+        from_date: from_date.to_owned(),
+        to_date: to_date.to_owned(),
+        total,
+        invoices,
+        medicines,
+        batches,
+        days: days.into_values().collect(),
+    })
+}
+
+/// How fast one medicine sold over a date range.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MovementRow {
+    pub medicine_id: i64,
+    pub name: String,
+    /// Loose units sold in the range, less units returned.
+    pub quantity_sold: i64,
+    /// Loose units in stock across all batches.
+    pub stock_on_hand: i64,
+    /// Days since the medicine last sold, whenever that was; `None` if it
+    /// never has.
+    pub days_since_last_sale: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MovementAnalytics {
+    /// Best sellers first; only medicines that sold in the range.
+    pub top_sellers: Vec<MovementRow>,
+    /// Slowest first, unsold ones included; medicines with neither stock
+    /// nor sales are left out.
+    pub slow_movers: Vec<MovementRow>,
+}
+
+/// The `limit` fastest and slowest moving medicines for sales dated
+/// `from_date` to `to_date`, inclusive.
+///
+/// Among equally slow movers the one unsold the longest comes first, so
+/// stock that has never sold leads the list.
+pub fn movement_analytics(
+    conn: &Connection,
+    from_date: &str,
+    to_date: &str,
+    limit: usize,
+) -> AppResult<MovementAnalytics> {
+    validate_date_range(conn, from_date, to_date)?;
+    if limit == 0 {
+        return Err(AppError::validation("The limit must be at least 1"));
+    }
+
+    let mut stmt = conn.prepare(
+        "WITH sold AS (
+             SELECT medicine_id, SUM(quantity) AS quantity
+             FROM (
+                 SELECT si.medicine_id, si.quantity
+                 FROM sale_items si
+                 JOIN sales s ON s.id = si.sale_id
+                 WHERE date(s.sale_date) BETWEEN ?1 AND ?2
+                 UNION ALL
+                 SELECT ri.medicine_id, -ri.quantity
+                 FROM sale_return_items ri
+                 JOIN sale_items si ON si.id = ri.sale_item_id
+                 JOIN sales s ON s.id = si.sale_id
+                 WHERE date(s.sale_date) BETWEEN ?1 AND ?2
+             )
+             GROUP BY medicine_id
+         ),
+         stock AS (
+             SELECT medicine_id, SUM(quantity) AS quantity FROM batches GROUP BY medicine_id
+         ),
+         last_sale AS (
+             SELECT si.medicine_id, MAX(s.sale_date) AS sold_at
+             FROM sale_items si
+             JOIN sales s ON s.id = si.sale_id
+             GROUP BY si.medicine_id
+         )
+         SELECT m.id, m.name, COALESCE(sold.quantity, 0), COALESCE(stock.quantity, 0),
+             CAST(julianday(date('now')) - julianday(date(last_sale.sold_at)) AS INTEGER)
+         FROM medicines m
+         LEFT JOIN sold ON sold.medicine_id = m.id
+         LEFT JOIN stock ON stock.medicine_id = m.id
+         LEFT JOIN last_sale ON last_sale.medicine_id = m.id
+         WHERE COALESCE(sold.quantity, 0) > 0 OR COALESCE(stock.quantity, 0) > 0",
+    )?;
+    let rows = stmt.query_map([from_date, to_date], |row| {
+        Ok(MovementRow {
+            medicine_id: row.get(0)?,
+            name: row.get(1)?,
+            quantity_sold: row.get(2)?,
+            stock_on_hand: row.get(3)?,
+            days_since_last_sale: row.get(4)?,
+        })
+    })?;
+    let mut rows = rows.collect::<Result<Vec<_>, _>>()?;
+
+    rows.sort_by(|a, b| {
+        a.quantity_sold
+            .cmp(&b.quantity_sold)
+            .then_with(|| {
+                let idle = |r: &MovementRow| r.days_since_last_sale.unwrap_or(i64::MAX);
+                idle(b).cmp(&idle(a))
+            })
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    let slow_movers = rows.iter().take(limit).cloned().collect();
+    rows.retain(|r| r.quantity_sold > 0);
+    rows.sort_by(|a, b| {
+        b.quantity_sold
+            .cmp(&a.quantity_sold)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    rows.truncate(limit);
+    Ok(MovementAnalytics {
+        top_sellers: rows,
+        slow_movers,
+    })
+}
+
+/// A batch with stock that has not sold for a while.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdleBatch {
+    pub batch_id: i64,
+    pub medicine_id: i64,
+    pub medicine_name: String,
+    pub batch_number: String,
+    pub expiry_date: String,
+    /// Negative once the batch has expired.
+    pub days_to_expiry: i64,
+    /// Loose units on hand.
+    pub quantity: i64,
+    /// What the units on hand cost.
+    pub value_paise: i64,
+    /// `None` if the batch has never sold.
+    pub last_sold_on: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeadStockReport {
+    pub idle_days: i64,
+    /// Soonest to expire first.
+    pub batches: Vec<IdleBatch>,
+    /// Sum of `batches`' values.
+    pub value_paise: i64,
+}
+
+/// Batches with stock and no sales in the last `idle_days` days.
+///
+/// Batches received within the window are left out, as they have not had
+/// the time to sell. Expired batches are kept in so the stock can still
+/// go back to the supplier. `branch_id` behaves as in [`expiry_report`].
+pub fn dead_stock_report(
+    conn: &Connection,
+    idle_days: i64,
+    branch_id: Option<i64>,
+) -> AppResult<DeadStockReport> {
+    if !(1..=3650).contains(&idle_days) {
+        return Err(AppError::validation(
+            "The idle period must be between 1 and 3650 days",
+        ));
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT b.id, b.medicine_id, m.name, b.batch_number, b.expiry_date,
+             CAST(julianday(b.expiry_date) - julianday(date('now')) AS INTEGER),
+             b.quantity, b.cost_price_paise, m.pack_size,
+             (SELECT date(MAX(s.sale_date))
+              FROM sale_items si
+              JOIN sales s ON s.id = si.sale_id
+              WHERE si.batch_id = b.id) AS last_sold_on
+         FROM batches b
+         JOIN medicines m ON m.id = b.medicine_id
+         WHERE b.quantity > 0 AND (?2 IS NULL OR b.branch_id = ?2)
+             AND date(b.created_at) <= date('now', printf('-%d days', ?1))
+             AND (last_sold_on IS NULL OR last_sold_on <= date('now', printf('-%d days', ?1)))
+         ORDER BY b.expiry_date ASC, b.id ASC",
+    )?;
+    let mut rows = stmt.query(params![idle_days, branch_id])?;
+
+    let mut report = DeadStockReport {
+        idle_days,
+        batches: Vec::new(),
+        value_paise: 0,
+    };
+    while let Some(row) = rows.next()? {
+        let quantity: i64 = row.get(6)?;
+        let value_paise = units_value(quantity, row.get(7)?, 1, row.get(8)?)?;
+        report.value_paise = report
+            .value_paise
+            .checked_add(value_paise)
+            .ok_or_else(overflow)?;
+        report.batches.push(IdleBatch {
+            batch_id: row.get(0)?,
+            medicine_id: row.get(1)?,
+            medicine_name: row.get(2)?,
+            batch_number: row.get(3)?,
+            expiry_date: row.get(4)?,
+            days_to_expiry: row.get(5)?,
+            quantity,
+            value_paise,
+            last_sold_on: row.get(9)?,
+        });
+    }
+    Ok(report)
+}
+
+/// ABC class of a medicine by its share of sales value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum AbcClass {
+    /// The few medicines making up the first 80% of sales.
+    A,
+    /// The next 15%.
+    B,
+    /// The long tail.
+    C,
+}
+
+impl AbcClass {
+    /// Class of a medicine when `before_paise` of `total_paise` has already
+    /// gone to the medicines ranked above it.
+    fn for_share(before_paise: i64, total_paise: i64) -> Self {
+        let before = i128::from(before_paise) * 100;
+        let total = i128::from(total_paise);
+        if before < total * 80 {
+            Self::A
+        } else if before < total * 95 {
+            Self::B
+        } else {
+            Self::C
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbcRow {
+    pub medicine_id: i64,
+    pub name: String,
+    pub quantity_sold: i64,
+    /// Taxable value, net of returns.
+    pub sales_value_paise: i64,
+    pub share_percent: f64,
+    /// Share of this medicine and all those ranked above it.
+    pub cumulative_percent: f64,
+    pub class: AbcClass,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbcClassSummary {
+    pub class: AbcClass,
+    pub medicine_count: i64,
+    pub sales_value_paise: i64,
+    pub share_percent: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbcReport {
+    pub from_date: String,
+    pub to_date: String,
+    pub total_sales_paise: i64,
+    /// Highest sales value first.
+    pub medicines: Vec<AbcRow>,
+    /// A, B and C, in that order.
+    pub classes: Vec<AbcClassSummary>,
+}
+
+/// Classify the medicines sold from `from_date` to `to_date`, inclusive,
+/// by their contribution to sales value.
+///
+/// Ranked by net taxable sales, a medicine is A while the ones above it
+/// make up less than 80% of the total, B while they make up less than 95%,
+/// and C after that, so the medicine that crosses a boundary stays in the
+/// higher class. Medicines whose returns cancelled their sales are left
+/// out.
+pub fn abc_analysis(conn: &Connection, from_date: &str, to_date: &str) -> AppResult<AbcReport> {
+    validate_date_range(conn, from_date, to_date)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT m.id, m.name, SUM(t.quantity), SUM(t.revenue) AS revenue
+         FROM (
+             SELECT si.medicine_id, si.quantity, si.taxable_amount_paise AS revenue
+             FROM sale_items si
+             JOIN sales s ON s.id = si.sale_id
+             WHERE date(s.sale_date) BETWEEN ?1 AND ?2
+             UNION ALL
+             SELECT ri.medicine_id, -ri.quantity, -ri.taxable_amount_paise
+             FROM sale_return_items ri
+             JOIN sale_items si ON si.id = ri.sale_item_id
+             JOIN sales s ON s.id = si.sale_id
+             WHERE date(s.sale_date) BETWEEN ?1 AND ?2
+         ) t
+         JOIN medicines m ON m.id = t.medicine_id
+         GROUP BY m.id
+         HAVING revenue > 0
+         ORDER BY revenue DESC, m.name ASC",
+    )?;
+    let rows = stmt.query_map([from_date, to_date], |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+    })?;
+    let rows = rows.collect::<Result<Vec<(i64, String, i64, i64)>, _>>()?;
+
+    let total_sales_paise = rows
+        .iter()
+        .try_fold(0_i64, |sum, row| sum.checked_add(row.3))
+        .ok_or_else(|| AppError::validation("Sales value is too large to total"))?;
+    let percent = |paise: i64| percent_of(paise, total_sales_paise).unwrap_or(0.0);
+
+    let mut classes: Vec<AbcClassSummary> = [AbcClass::A, AbcClass::B, AbcClass::C]
+        .into_iter()
+        .map(|class| AbcClassSummary {
+            class,
+            medicine_count: 0,
+            sales_value_paise: 0,
+            share_percent: 0.0,
+        })
+        .collect();
+    let mut medicines = Vec::with_capacity(rows.len());
+    let mut cumulative_paise = 0;
+    for (medicine_id, name, quantity_sold, sales_value_paise) in rows {
+        let class = AbcClass::for_share(cumulative_paise, total_sales_paise);
+        cumulative_paise += sales_value_paise;
+        let summary = &mut classes[class as usize];
+        summary.medicine_count += 1;
+        summary.sales_value_paise += sales_value_paise;
+        medicines.push(AbcRow {
+            medicine_id,
+            name,
+            quantity_sold,
+            sales_value_paise,
+            share_percent: percent(sales_value_paise),
+            cumulative_percent: percent(cumulative_paise),
+            class,
+        });
+    }
+    for summary in &mut classes {
+        summary.share_percent = percent(summary.sales_value_paise);
+    }
+    Ok(AbcReport {
+        from_date: from_date.to_owned(),
+        to_date: to_date.to_owned(),
+        total_sales_paise,
+        medicines,
+        classes,
+    })
+}
+
+/// Bills made in one hour of the day or one day of the week.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SalesBucket {
+    /// Hour `0..=23`, or weekday `0..=6` from Sunday.
+    pub bucket: i64,
+    pub invoice_count: i64,
+    pub total_paise: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TopSeller {
+    pub medicine_id: i64,
+    pub name: String,
+    /// Loose units sold less units returned.
+    pub quantity_sold: i64,
+    /// Line totals with GST, less returns.
+    pub sales_value_paise: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SalesAnalytics {
+    pub from_date: String,
+    pub to_date: String,
+    pub invoice_count: i64,
+    pub total_paise: i64,
+    /// `total_paise` over `invoice_count`, rounded; zero without sales.
+    pub average_bill_paise: i64,
+    /// Highest sales value first.
+    pub top_sellers: Vec<TopSeller>,
+    /// All 24 hours, midnight first.
+    pub by_hour: Vec<SalesBucket>,
+    /// All 7 days, Sunday first.
+    pub by_weekday: Vec<SalesBucket>,
+    /// Split-tender sales count towards each mode they were paid in.
+    pub payment_mix: PaymentModeTotals,
+}
+
+/// Bill counts and values grouped by `bucket`, an SQL expression on `s`
+/// giving the bucket number.
+fn sales_buckets(
+    conn: &Connection,
+    bucket: &str,
+    count: i64,
+    from_date: &str,
+    to_date: &str,
+) -> AppResult<Vec<SalesBucket>> {
+    let mut buckets: Vec<SalesBucket> = (0..count)
+        .map(|bucket| SalesBucket {
+            bucket,
+            ..Default::default()
+        })
+        .collect();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT CAST({bucket} AS INTEGER) AS bucket, COUNT(*), SUM(s.grand_total_paise)
+         FROM sales s
+         WHERE date(s.sale_date, 'localtime') BETWEEN ?1 AND ?2
+         GROUP BY bucket"
+    ))?;
+    let mut rows = stmt.query([from_date, to_date])?;
+    while let Some(row) = rows.next()? {
+        let bucket: i64 = row.get(0)?;
+        if let Some(entry) = usize::try_from(bucket)
+            .ok()
+            .and_then(|i| buckets.get_mut(i))
+        {
+            entry.invoice_count = row.get(1)?;
+            entry.total_paise = row.get(2)?;
+        }
+    }
+    Ok(buckets)
+}
+
+/// Top sellers, trading pattern and payment mix for sales dated
+/// `from_date` to `to_date`, inclusive.
+///
+/// Dates, hours and weekdays are all read in the machine's local time, as
+/// the till sees them, so a sale just after local midnight falls on the
+/// day it was rung up.
+pub fn sales_analytics(
+    conn: &Connection,
+    from_date: &str,
+    to_date: &str,
+    limit: usize,
+) -> AppResult<SalesAnalytics> {
+    validate_date_range(conn, from_date, to_date)?;
+    if limit == 0 {
+        return Err(AppError::validation("The limit must be at least 1"));
+    }
+
+    let (invoice_count, total_paise): (i64, i64) = conn.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(grand_total_paise), 0)
+         FROM sales
+         WHERE date(sale_date, 'localtime') BETWEEN ?1 AND ?2",
+        [from_date, to_date],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    let average_bill_paise = if invoice_count == 0 {
+        0
+    } else {
+        (total_paise + invoice_count / 2).div_euclid(invoice_count)
+    };
+
+    let mut stmt = conn.prepare(
+        "SELECT m.id, m.name, SUM(t.quantity), SUM(t.value) AS value
+         FROM (
+             SELECT si.medicine_id, si.quantity, si.total_paise AS value
+             FROM sale_items si
+             JOIN sales s ON s.id = si.sale_id
+             WHERE date(s.sale_date, 'localtime') BETWEEN ?1 AND ?2
+             UNION ALL
+             SELECT ri.medicine_id, -ri.quantity, -ri.total_paise
+             FROM sale_return_items ri
+             JOIN sale_items si ON si.id = ri.sale_item_id
+             JOIN sales s ON s.id = si.sale_id
+             WHERE date(s.sale_date, 'localtime') BETWEEN ?1 AND ?2
+         ) t
+         JOIN medicines m ON m.id = t.medicine_id
+         GROUP BY m.id
+         HAVING value > 0
+         ORDER BY value DESC, m.name ASC
+         LIMIT ?3",
+    )?;
+    let rows = stmt.query_map(
+        params![from_date, to_date, i64::try_from(limit).unwrap_or(i64::MAX)],
+        |row| {
+            Ok(TopSeller {
+                medicine_id: row.get(0)?,
+                name: row.get(1)?,
+                quantity_sold: row.get(2)?,
+                sales_value_paise: row.get(3)?,
+            })
+        },
+    )?;
+    let top_sellers = rows.collect::<Result<_, _>>()?;
+
+    let mut payment_mix = PaymentModeTotals::default();
+    let mut stmt = conn.prepare(
+        "SELECT sp.payment_mode, SUM(sp.amount_paise)
+         FROM sale_payments sp
+         JOIN sales s ON s.id = sp.sale_id
+         WHERE date(s.sale_date, 'localtime') BETWEEN ?1 AND ?2
+         GROUP BY sp.payment_mode",
+    )?;
+    let mut rows = stmt.query([from_date, to_date])?;
+    while let Some(row) = rows.next()? {
+        let mode: String = row.get(0)?;
+        payment_mix.add(&mode, row.get(1)?);
+    }
+
+    Ok(SalesAnalytics {
+        from_date: from_date.to_owned(),
+        to_date: to_date.to_owned(),
+        invoice_count,
+        total_paise,
+        average_bill_paise,
+        top_sellers,
+        by_hour: sales_buckets(
+            conn,
+            "strftime('%H', s.sale_date, 'localtime')",
+            24,
+            from_date,
+            to_date,
+        )?,
+        by_weekday: sales_buckets(
+            conn,
+            "strftime('%w', s.sale_date, 'localtime')",
+            7,
+            from_date,
+            to_date,
+        )?,
+        payment_mix,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{insert_batch, insert_medicine, migrated_db, paid_sale};
+    use crate::returns;
+    use crate::sales::{self, PaymentMode};
+
+    #[test]
+    fn expiry_buckets_follow_the_configured_window() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        conn.execute(
+            "UPDATE pharmacy_settings SET near_expiry_days = 30 WHERE id = 1",
+            [],
+        )
+        .unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let expired = insert_batch(&conn, para, "-2 days", 1_000, 4);
+        let near = insert_batch(&conn, para, "+20 days", 1_000, 3);
+        let fine = insert_batch(&conn, para, "+60 days", 1_000, 2);
+        insert_batch(&conn, para, "-10 days", 1_000, 0);
+
+        let report = expiry_report(&conn, None).unwrap();
+
+        let ids = |b: &[ExpiryBatch]| b.iter().map(|b| b.batch_id).collect::<Vec<_>>();
+        assert_eq!(ids(&report.expired), [expired]);
+        assert_eq!(ids(&report.near_expiry), [near]);
+        assert_eq!(ids(&report.fine), [fine]);
+        assert_eq!(report.expired[0].value_paise, 4 * 700);
+    }
+
+    #[test]
+    fn reorder_report_lists_worst_shortfall_first() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let cetirizine = insert_medicine(&conn, "Cetirizine", 12.0);
+        let stocked = insert_medicine(&conn, "Amoxicillin", 12.0);
+        let retired = insert_medicine(&conn, "Retired", 12.0);
+        conn.execute(
+            "UPDATE medicines SET reorder_level = 10 WHERE id IN (?1, ?2, ?3, ?4)",
+            [para, cetirizine, stocked, retired],
+        )
+        .unwrap();
+        conn.execute(
+            "UPDATE medicines SET is_active = 0 WHERE id = ?1",
+            [retired],
+        )
+        .unwrap();
+        insert_batch(&conn, para, "+90 days", 1_000, 10);
+        insert_batch(&conn, cetirizine, "+90 days", 1_000, 2);
+        insert_batch(&conn, cetirizine, "-1 day", 1_000, 50);
+        insert_batch(&conn, stocked, "+90 days", 1_000, 11);
+
+        let report = reorder_report(&conn, None).unwrap();
+
+        let rows: Vec<_> = report
+            .iter()
+            .map(|r| (r.medicine_id, r.current_stock, r.shortfall))
+            .collect();
+        assert_eq!(rows, [(cetirizine, 2, 8), (para, 10, 0)]);
+    }
+
+    fn sell(db: &crate::db::Db, batch_id: i64, quantity: i64) -> i64 {
+        let sale = paid_sale(
+            &db.connect().unwrap(),
+            None,
+            PaymentMode::Cash,
+            batch_id,
+            quantity,
+        );
+        db.with_tx(|tx| sales::create_sale(tx, &sale))
+            .unwrap()
+            .sale_id
+    }
+
+    #[test]
+    fn hsn_summary_groups_by_code_and_rate_within_the_range() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let cough = insert_medicine(&conn, "Cough Syrup", 18.0);
+        let para_batch = insert_batch(&conn, para, "+90 days", 1_120, 10);
+        let cough_batch = insert_batch(&conn, cough, "+90 days", 1_180, 10);
+        sell(&db, para_batch, 2);
+        sell(&db, para_batch, 1);
+        sell(&db, cough_batch, 1);
+        let old = sell(&db, cough_batch, 5);
+        conn.execute(
+            "UPDATE sales SET sale_date = datetime('now', '-40 days') WHERE id = ?1",
+            [old],
+        )
+        .unwrap();
+
+        let today: String = conn
+            .query_row("SELECT date('now')", [], |r| r.get(0))
+            .unwrap();
+        let summary = hsn_tax_summary(&conn, &today, &today).unwrap();
+
+        let rows: Vec<_> = summary
+            .iter()
+            .map(|h| {
+                (
+                    h.gst_rate,
+                    h.total_quantity,
+                    h.taxable_value_paise,
+                    h.total_tax_paise,
+                )
+            })
+            .collect();
+        assert_eq!(rows, [(12.0, 3, 3_000, 360), (18.0, 1, 1_000, 180)]);
+        assert!(hsn_tax_summary(&conn, "2026-02-01", "2026-01-01").is_err());
+        assert!(hsn_tax_summary(&conn, "01/01/2026", &today).is_err());
+    }
+
+    #[test]
+    fn daily_summary_nets_returns_against_collection() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+90 days", 1_120, 10);
+        let sale_id = sell(&db, batch, 2);
+        let credit = sell(&db, batch, 1);
+        conn.execute(
+            "UPDATE sale_payments SET payment_mode = 'credit' WHERE sale_id = ?1",
+            [credit],
+        )
+        .unwrap();
+        let item: i64 = conn
+            .query_row(
+                "SELECT id FROM sale_items WHERE sale_id = ?1",
+                [sale_id],
+                |r| r.get(0),
+            )
+            .unwrap();
+        db.with_tx(|tx| {
+            crate::returns::create_sale_return(
+                tx,
+                sale_id,
+                &[crate::returns::ReturnItem {
+                    sale_item_id: item,
+                    quantity: 1,
+                    non_saleable: false,
+                }],
+                PaymentMode::Cash,
+                1,
+            )
+        })
+        .unwrap();
+
+        let today: String = conn
+            .query_row("SELECT date('now', 'localtime')", [], |r| r.get(0))
+            .unwrap();
+        let summary = daily_sales_summary(&conn, &today, None).unwrap();
+
+        assert_eq!(summary.invoice_count, 2);
+        // Invoices are rounded to the rupee; the refund is the exact line value.
+        assert_eq!(summary.grand_total_paise, 3_300);
+        assert_eq!(summary.by_payment_mode.cash_paise, 2_200);
+        assert_eq!(summary.by_payment_mode.credit_paise, 1_100);
+        assert_eq!(summary.returns_paise, -1_120);
+        assert_eq!(summary.net_collection_paise, 1_080);
+
+        let empty = daily_sales_summary(&conn, "2020-01-01", None).unwrap();
+        assert_eq!((empty.invoice_count, empty.grand_total_paise), (0, 0));
+    }
+
+    #[test]
+    fn daily_summary_counts_a_sale_just_after_midnight_on_the_local_day() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+90 days", 1_120, 10);
+        let sale_id = sell(&db, batch, 2);
+        let item: i64 = conn
+            .query_row(
+                "SELECT id FROM sale_items WHERE sale_id = ?1",
+                [sale_id],
+                |r| r.get(0),
+            )
+            .unwrap();
+        db.with_tx(|tx| {
+            crate::returns::create_sale_return(
+                tx,
+                sale_id,
+                &[crate::returns::ReturnItem {
+                    sale_item_id: item,
+                    quantity: 1,
+                    non_saleable: false,
+                }],
+                PaymentMode::Cash,
+                1,
+            )
+        })
+        .unwrap();
+        let just_after_midnight =
+            "datetime('now', 'localtime', 'start of day', '+5 minutes', 'utc')";
+        conn.execute(
+            &format!("UPDATE sales SET sale_date = {just_after_midnight}"),
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            &format!("UPDATE sale_returns SET return_date = {just_after_midnight}"),
+            [],
+        )
+        .unwrap();
+        let today: String = conn
+            .query_row("SELECT date('now', 'localtime')", [], |r| r.get(0))
+            .unwrap();
+
+        let summary = daily_sales_summary(&conn, &today, None).unwrap();
+
+        assert_eq!(summary.invoice_count, 1);
+        assert_eq!(summary.by_payment_mode.cash_paise, 2_200);
+        assert_eq!(summary.return_count, 1);
+    }
+
+    #[test]
+    fn valuation_groups_by_category_and_separates_dead_stock() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let cough = insert_medicine(&conn, "Benadryl", 12.0);
+        conn.execute(
+            "UPDATE medicines SET category = 'Analgesic' WHERE id = ?1",
+            [para],
+        )
+        .unwrap();
+        insert_batch(&conn, para, "+90 days", 1_000, 4);
+        insert_batch(&conn, para, "-5 days", 1_000, 2);
+        insert_batch(&conn, cough, "+90 days", 2_000, 1);
+        insert_batch(&conn, cough, "+90 days", 2_000, 0);
+
+        let report = inventory_valuation(&conn, None).unwrap();
+
+        let by_name = |name: &str| {
+            report
+                .categories
+                .iter()
+                .find(|c| c.category == name)
+                .map(|c| c.value)
+        };
+        assert_eq!(
+            by_name("Analgesic"),
+            Some(StockValue {
+                quantity: 4,
+                cost_value_paise: 2_800,
+                mrp_value_paise: 4_000,
+            })
+        );
+        assert_eq!(by_name("Uncategorized").map(|v| v.quantity), Some(1));
+        assert_eq!(report.total.cost_value_paise, 2_800 + 1_400);
+        assert_eq!(report.dead_stock.mrp_value_paise, 2_000);
+    }
+
+    #[test]
+    fn stock_valuation_prices_packs_and_averages_purchase_costs() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let cough = insert_medicine(&conn, "Benadryl", 12.0);
+        conn.execute(
+            "UPDATE medicines SET category = 'Analgesic', manufacturer = 'Cipla', pack_size = 10
+             WHERE id = ?1",
+            [para],
+        )
+        .unwrap();
+        let first = insert_batch(&conn, para, "+90 days", 1_000, 10);
+        let second = insert_batch(&conn, para, "+180 days", 1_000, 30);
+        insert_batch(&conn, cough, "-5 days", 2_000, 1);
+        conn.execute("UPDATE batches SET quantity = 5 WHERE id = ?1", [first])
+            .unwrap();
+        conn.execute(
+            "UPDATE batches SET cost_price_paise = 900 WHERE id = ?1",
+            [second],
+        )
+        .unwrap();
+
+        let by_batch = stock_valuation(&conn, None, CostMethod::Batch).unwrap();
+        // 5 units at 700 a strip of 10 and 30 at 900.
+        assert_eq!(
+            by_batch.total,
+            Valuation {
+                quantity: 35,
+                cost_value_paise: 350 + 2_700,
+                mrp_value_paise: 3_500,
+                selling_value_paise: 3_500,
+            }
+        );
+        assert_eq!(by_batch.categories[0].name, "Analgesic");
+        assert_eq!(by_batch.manufacturers[0].name, "Cipla");
+        assert_eq!(by_batch.expired.cost_value_paise, 1_400);
+
+        // 40 units bought for 850 a strip on average.
+        let averaged = stock_valuation(&conn, None, CostMethod::WeightedAverage).unwrap();
+        assert_eq!(averaged.total.cost_value_paise, 2_975);
+        assert_eq!(averaged.manufacturers[0].value.cost_value_paise, 2_975);
+    }
+
+    #[test]
+    fn valuation_overflow_is_an_error() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+90 days", 1_000, 1);
+        conn.execute(
+            "UPDATE batches SET quantity = ?1 WHERE id = ?2",
+            [i64::MAX / 100, batch],
+        )
+        .unwrap();
+
+        let err = inventory_valuation(&conn, None).unwrap_err();
+        assert!(err.to_string().contains("too large"), "{err}");
+    }
+
+    #[test]
+    fn dead_stock_lists_batches_idle_for_the_window() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let selling = insert_batch(&conn, para, "+300 days", 1_000, 10);
+        let stale = insert_batch(&conn, para, "+200 days", 1_000, 10);
+        let unsold = insert_batch(&conn, para, "+30 days", 1_000, 4);
+        let fresh = insert_batch(&conn, para, "+30 days", 1_000, 4);
+        conn.execute(
+            "UPDATE batches SET created_at = datetime('now', '-200 days') WHERE id <> ?1",
+            [fresh],
+        )
+        .unwrap();
+        sell(&db, selling, 1);
+        let old_sale = sell(&db, stale, 1);
+        conn.execute(
+            "UPDATE sales SET sale_date = datetime('now', '-100 days') WHERE id = ?1",
+            [old_sale],
+        )
+        .unwrap();
+
+        let report = dead_stock_report(&conn, 90, None).unwrap();
+
+        let ids: Vec<_> = report.batches.iter().map(|b| b.batch_id).collect();
+        assert_eq!(ids, [unsold, stale]);
+        assert_eq!(report.batches[0].days_to_expiry, 30);
+        assert_eq!(report.batches[0].last_sold_on, None);
+        assert!(report.batches[1].last_sold_on.is_some());
+        // 4 + 9 units at 700.
+        assert_eq!(report.value_paise, 13 * 700);
+        assert!(dead_stock_report(&conn, 120, None)
+            .unwrap()
+            .batches
+            .iter()
+            .all(|b| b.batch_id == unsold));
+        assert!(dead_stock_report(&conn, 0, None).is_err());
+    }
+
+    #[test]
+    fn abc_analysis_keeps_the_boundary_medicine_in_the_higher_class() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        // Taxable values of 7000, 2000, 700 and 300 out of 10000.
+        for (name, units) in [("Amoxicillin", 7), ("Benadryl", 2), ("Cetirizine", 1)] {
+            let medicine = insert_medicine(&conn, name, 12.0);
+            let batch = insert_batch(&conn, medicine, "+90 days", 1_120, 10);
+            sell(&db, batch, units);
+        }
+        let antacid = insert_medicine(&conn, "Digene", 12.0);
+        let batch = insert_batch(&conn, antacid, "+90 days", 1_120, 10);
+        sell(&db, batch, 1);
+        conn.execute_batch(
+            "UPDATE sale_items SET taxable_amount_paise = 700
+                 WHERE medicine_id = (SELECT id FROM medicines WHERE name = 'Cetirizine');
+             UPDATE sale_items SET taxable_amount_paise = 300
+                 WHERE medicine_id = (SELECT id FROM medicines WHERE name = 'Digene');",
+        )
+        .unwrap();
+        let today: String = conn
+            .query_row("SELECT date('now')", [], |r| r.get(0))
+            .unwrap();
+
+        let report = abc_analysis(&conn, &today, &today).unwrap();
+
+        let rows: Vec<_> = report
+            .medicines
+            .iter()
+            .map(|r| (r.name.as_str(), r.class, r.cumulative_percent))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("Amoxicillin", AbcClass::A, 70.0),
+                ("Benadryl", AbcClass::A, 90.0),
+                ("Cetirizine", AbcClass::B, 97.0),
+                ("Digene", AbcClass::C, 100.0),
+            ]
+        );
+        assert_eq!(report.total_sales_paise, 10_000);
+        assert_eq!(report.classes[0].medicine_count, 2);
+        assert_eq!(report.classes[0].share_percent, 90.0);
+    }
+
+    #[test]
+    fn sales_analytics_buckets_bills_and_ranks_sellers() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let cough = insert_medicine(&conn, "Cough Syrup", 12.0);
+        let para_batch = insert_batch(&conn, para, "+90 days", 5_000, 10);
+        let cough_batch = insert_batch(&conn, cough, "+90 days", 5_000, 10);
+        sell(&db, para_batch, 2);
+        sell(&db, cough_batch, 1);
+        conn.execute(
+            "UPDATE sales
+             SET sale_date = datetime('now', 'localtime', 'start of day', '+10 hours', 'utc')",
+            [],
+        )
+        .unwrap();
+        let today: String = conn
+            .query_row("SELECT date('now', 'localtime')", [], |r| r.get(0))
+            .unwrap();
+        let weekday: usize = conn
+            .query_row(
+                "SELECT CAST(strftime('%w', sale_date, 'localtime') AS INTEGER) FROM sales",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+
+        let report = sales_analytics(&conn, &today, &today, 1).unwrap();
+
+        assert_eq!((report.invoice_count, report.total_paise), (2, 15_000));
+        assert_eq!(report.average_bill_paise, 7_500);
+        let sellers: Vec<_> = report
+            .top_sellers
+            .iter()
+            .map(|s| (s.medicine_id, s.quantity_sold, s.sales_value_paise))
+            .collect();
+        assert_eq!(sellers, [(para, 2, 10_000)]);
+        assert_eq!(report.by_hour.len(), 24);
+        assert_eq!(report.by_hour[10].invoice_count, 2);
+        assert_eq!(report.by_weekday[weekday].total_paise, 15_000);
+        assert_eq!(report.payment_mix.cash_paise, 15_000);
+    }
+
+    #[test]
+    fn profit_report_nets_returns_and_ranks_by_gross_profit() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let cough = insert_medicine(&conn, "Cough Syrup", 18.0);
+        let para_batch = insert_batch(&conn, para, "+90 days", 1_120, 10);
+        let sample = insert_batch(&conn, cough, "+90 days", 1_180, 10);
+        conn.execute(
+            "UPDATE batches SET cost_price_paise = 0 WHERE id = ?1",
+            [sample],
+        )
+        .unwrap();
+        let sale_id = sell(&db, para_batch, 3);
+        sell(&db, sample, 1);
+        let sale_item_id: i64 = conn
+            .query_row(
+                "SELECT id FROM sale_items WHERE sale_id = ?1",
+                [sale_id],
+                |r| r.get(0),
+            )
+            .unwrap();
+        db.with_tx(|tx| {
+            returns::create_sale_return(
+                tx,
+                sale_id,
+                &[returns::ReturnItem {
+                    sale_item_id,
+                    quantity: 1,
+                    non_saleable: false,
+                }],
+                PaymentMode::Cash,
+                1,
+            )
+        })
+        .unwrap();
+
+        let today: String = conn
+            .query_row("SELECT date('now')", [], |r| r.get(0))
+            .unwrap();
+        let report = profit_report(&conn, &today, &today).unwrap();
+
+        let rows: Vec<_> = report
+            .iter()
+            .map(|r| {
+                (
+                    r.medicine_id,
+                    r.quantity_sold,
+                    r.revenue_paise,
+                    r.cost_paise,
+                    r.gross_profit_paise,
+                    r.margin_percent,
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                (cough, 1, 1_000, 0, 1_000, Some(100.0)),
+                (para, 2, 2_000, 1_568, 432, Some(21.6)),
+            ]
+        );
+
+        conn.execute(
+            "INSERT INTO expenses (category_id, amount_paise, expense_date, payment_mode, user_id)
+             SELECT id, 500, date('now'), 'cash', 1 FROM expense_categories WHERE name = 'Rent'",
+            [],
+        )
+        .unwrap();
+        let summary = profit_summary(&conn, &today, &today).unwrap();
+        assert_eq!(summary.gross_profit_paise, 1_432);
+        assert_eq!(summary.expenses[0].category, "Rent");
+        assert_eq!(summary.net_profit_paise, 932);
+    }
+
+    #[test]
+    fn margin_report_agrees_by_invoice_batch_and_day() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let early = insert_batch(&conn, para, "+30 days", 1_120, 10);
+        let late = insert_batch(&conn, para, "+300 days", 1_120, 10);
+        conn.execute(
+            "UPDATE batches SET cost_price_paise = 560 WHERE id = ?1",
+            [late],
+        )
+        .unwrap();
+        let first = sell(&db, early, 2);
+        let second = sell(&db, late, 1);
+        let sale_item_id: i64 = conn
+            .query_row(
+                "SELECT id FROM sale_items WHERE sale_id = ?1",
+                [first],
+                |r| r.get(0),
+            )
+            .unwrap();
+        db.with_tx(|tx| {
+            returns::create_sale_return(
+                tx,
+                first,
+                &[returns::ReturnItem {
+                    sale_item_id,
+                    quantity: 1,
+                    non_saleable: false,
+                }],
+                PaymentMode::Cash,
+                1,
+            )
+        })
+        .unwrap();
+        conn.execute(
+            "UPDATE sales SET sale_date = datetime('now', '-1 day') WHERE id = ?1",
+            [second],
+        )
+        .unwrap();
+        let today: String = conn
+            .query_row("SELECT date('now')", [], |r| r.get(0))
+            .unwrap();
+        let yesterday: String = conn
+            .query_row("SELECT date('now', '-1 day')", [], |r| r.get(0))
+            .unwrap();
+
+        let report = margin_report(&conn, &yesterday, &today).unwrap();
+
+        // 2 x 1000 taxable at 784 cost with one returned, and 1 x 1000 at 560.
+        assert_eq!(report.total.revenue_paise, 2_000);
+        assert_eq!(report.total.cost_paise, 1_344);
+        assert_eq!(report.total.margin_percent, Some(32.8));
+        assert_eq!(report.invoices[0].sale_id, second);
+        assert_eq!(report.invoices[0].margin.gross_profit_paise, 440);
+        let days: Vec<_> = report
+            .days
+            .iter()
+            .map(|d| {
+                (
+                    d.date.as_str(),
+                    d.invoice_count,
+                    d.margin.gross_profit_paise,
+                )
+            })
+            .collect();
+        assert_eq!(
+            days,
+            [(yesterday.as_str(), 1, 440), (today.as_str(), 1, 216)]
+        );
+        assert_eq!(report.batches[0].batch_id, late);
+        assert_eq!(report.batches[1].quantity_sold, 1);
+        assert_eq!(report.medicines[0].gross_profit_paise, 656);
+    }
+
+    #[test]
+    fn movement_analytics_ranks_sellers_and_surfaces_idle_stock() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let cough = insert_medicine(&conn, "Cough Syrup", 18.0);
+        let balm = insert_medicine(&conn, "Pain Balm", 18.0);
+        let gone = insert_medicine(&conn, "Discontinued", 12.0);
+        let para_batch = insert_batch(&conn, para, "+90 days", 1_120, 10);
+        let cough_batch = insert_batch(&conn, cough, "+90 days", 1_180, 10);
+        insert_batch(&conn, balm, "+90 days", 500, 10);
+        insert_batch(&conn, gone, "+90 days", 500, 0);
+        sell(&db, para_batch, 5);
+        sell(&db, cough_batch, 1);
+
+        let today: String = conn
+            .query_row("SELECT date('now')", [], |r| r.get(0))
+            .unwrap();
+        let analytics = movement_analytics(&conn, &today, &today, 2).unwrap();
+
+        let ids = |rows: &[MovementRow]| rows.iter().map(|r| r.medicine_id).collect::<Vec<_>>();
+        assert_eq!(ids(&analytics.top_sellers), [para, cough]);
+        assert_eq!(ids(&analytics.slow_movers), [balm, cough]);
+        assert_eq!(analytics.slow_movers[0].days_since_last_sale, None);
+        assert_eq!(analytics.slow_movers[0].stock_on_hand, 10);
+        assert_eq!(analytics.slow_movers[1].days_since_last_sale, Some(0));
+    }
+}
@@ -0,0 +1,411 @@
+//! Customer returns against a finalized sale, each issued as a credit note.
+
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::auth::{self, permission};
+use crate::db::{ChangeOp, Entity, Tx};
+use crate::error::{AppError, AppResult};
+use crate::inventory::{self, MovementReason, EXPIRED};
+use crate::loyalty;
+use crate::sales::{self, PaymentMode};
+use crate::sync;
+
+/// Units of one sold line being brought back.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReturnItem {
+    pub sale_item_id: i64,
+    pub quantity: i64,
+    /// The units came back damaged or otherwise unfit to resell: they are
+    /// credited but not put back into their batch.
+    #[serde(default)]
+    pub non_saleable: bool,
+}
+
+/// A returned line with the share of the original line it reverses.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReturnLine {
+    pub sale_item_id: i64,
+    pub batch_id: i64,
+    pub quantity: i64,
+    pub taxable_amount_paise: i64,
+    pub cgst_amount_paise: i64,
+    pub sgst_amount_paise: i64,
+    pub igst_amount_paise: i64,
+    pub total_paise: i64,
+    /// The units went back into their batch.
+    pub restocked: bool,
+    /// The batch has expired: its stock is restored but should be
+    /// quarantined rather than resold.
+    pub batch_expired: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReturnResult {
+    pub return_id: i64,
+    pub credit_note_number: String,
+    pub sale_id: i64,
+    pub total_gst_paise: i64,
+    pub refund_total_paise: i64,
+    pub lines: Vec<ReturnLine>,
+}
+
+/// The sold line a return is made against, with what has come back so far.
+struct SoldLine {
+    sale_id: i64,
+    batch_id: i64,
+    medicine_name: String,
+    quantity: i64,
+    returned: i64,
+    taxable_amount_paise: i64,
+    cgst_amount_paise: i64,
+    sgst_amount_paise: i64,
+    igst_amount_paise: i64,
+    batch_expired: bool,
+}
+
+/// The part of `amount` owed for units `returned..returned + quantity` of a
+/// line of `sold` units.
+///
+/// Each return takes the difference of two rounded cumulative shares, so
+/// once every unit is back the refunds add up to the line exactly.
+fn prorate(amount: i64, sold: i64, returned: i64, quantity: i64) -> i64 {
+    let share = |units: i64| (2 * amount * units + sold) / (2 * sold);
+    share(returned + quantity) - share(returned)
+}
+
+fn sold_line(tx: &Tx, sale_item_id: i64) -> AppResult<SoldLine> {
+    tx.query_row(
+        &format!(
+            "SELECT si.sale_id, si.batch_id, m.name, si.quantity,
+                 COALESCE((SELECT SUM(ri.quantity) FROM sale_return_items ri
+                           WHERE ri.sale_item_id = si.id), 0),
+                 si.taxable_amount_paise, si.cgst_amount_paise, si.sgst_amount_paise,
+                 si.igst_amount_paise, {EXPIRED}
+             FROM sale_items si
+             JOIN medicines m ON m.id = si.medicine_id
+             JOIN batches b ON b.id = si.batch_id
+             WHERE si.id = ?1"
+        ),
+        params![sale_item_id],
+        |row| {
+            Ok(SoldLine {
+                sale_id: row.get(0)?,
+                batch_id: row.get(1)?,
+                medicine_name: row.get(2)?,
+                quantity: row.get(3)?,
+                returned: row.get(4)?,
+                taxable_amount_paise: row.get(5)?,
+                cgst_amount_paise: row.get(6)?,
+                sgst_amount_paise: row.get(7)?,
+                igst_amount_paise: row.get(8)?,
+                batch_expired: row.get(9)?,
+            })
+        },
+    )
+    .optional()?
+    .ok_or_else(|| AppError::not_found(format!("Sale item {sale_item_id}")))
+}
+
+/// Take back sold units against their invoice and issue a credit note.
+///
+/// Units are restored to their original batches unless marked non-saleable.
+/// Each line's taxable value and GST are reversed in proportion to the
+/// units returned. A sale is marked refunded once every unit is back.
+pub fn create_sale_return(
+    tx: &Tx,
+    sale_id: i64,
+    items: &[ReturnItem],
+    refund_mode: PaymentMode,
+    user_id: i64,
+) -> AppResult<ReturnResult> {
+    auth::require_permission(tx, user_id, permission::SALES_REFUND)?;
+    if items.is_empty() {
+        return Err(AppError::validation("A return must have at least one item"));
+    }
+    let invoice_number: String = tx
+        .query_row(
+            "SELECT invoice_number FROM sales WHERE id = ?1",
+            params![sale_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Sale {sale_id}")))?;
+
+    let credit_note_number = sales::allocate_credit_note_number(tx)?;
+    tx.execute(
+        "INSERT INTO sale_returns (credit_note_number, sale_id, user_id, refund_mode)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![credit_note_number, sale_id, user_id, refund_mode.as_str()],
+    )?;
+    let return_id = tx.last_insert_rowid();
+
+    let mut insert = tx.prepare_cached(
+        "INSERT INTO sale_return_items (return_id, sale_item_id, batch_id, medicine_id,
+             quantity, taxable_amount_paise, cgst_amount_paise, sgst_amount_paise,
+             igst_amount_paise, total_paise, restocked, batch_expired)
+         SELECT ?1, id, batch_id, medicine_id, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10
+         FROM sale_items WHERE id = ?2",
+    )?;
+    let mut restore =
+        tx.prepare_cached("UPDATE batches SET quantity = quantity + ?1 WHERE id = ?2")?;
+
+    let mut lines = Vec::with_capacity(items.len());
+    for item in items {
+        if item.quantity <= 0 {
+            return Err(AppError::validation(
+                "Returned quantity must be greater than 0",
+            ));
+        }
+        let sold = sold_line(tx, item.sale_item_id)?;
+        if sold.sale_id != sale_id {
+            return Err(AppError::validation(format!(
+                "Sale item {} is not part of sale {invoice_number}",
+                item.sale_item_id
+            )));
+        }
+        let remaining = sold.quantity - sold.returned;
+        if item.quantity > remaining {
+            return Err(AppError::validation(format!(
+                "Cannot return {} of {}: only {remaining} left to return",
+                item.quantity, sold.medicine_name
+            )));
+        }
+
+        let share = |amount| prorate(amount, sold.quantity, sold.returned, item.quantity);
+        let taxable = share(sold.taxable_amount_paise);
+        let cgst = share(sold.cgst_amount_paise);
+        let sgst = share(sold.sgst_amount_paise);
+        let igst = share(sold.igst_amount_paise);
+        let line = ReturnLine {
+            sale_item_id: item.sale_item_id,
+            batch_id: sold.batch_id,
+            quantity: item.quantity,
+            taxable_amount_paise: taxable,
+            cgst_amount_paise: cgst,
+            sgst_amount_paise: sgst,
+            igst_amount_paise: igst,
+            total_paise: taxable + cgst + sgst + igst,
+            restocked: !item.non_saleable,
+            batch_expired: sold.batch_expired,
+        };
+
+        insert.execute(params![
+            return_id,
+            line.sale_item_id,
+            line.quantity,
+            line.taxable_amount_paise,
+            line.cgst_amount_paise,
+            line.sgst_amount_paise,
+            line.igst_amount_paise,
+            line.total_paise,
+            line.restocked,
+            line.batch_expired,
+        ])?;
+        if line.restocked {
+            restore.execute(params![line.quantity, line.batch_id])?;
+            inventory::record_movement(
+                tx,
+                line.batch_id,
+                line.quantity,
+                MovementReason::Return,
+                Some(return_id),
+            )?;
+            tx.changed(Entity::Batch, ChangeOp::Update, line.batch_id);
+        }
+        lines.push(line);
+    }
+
+    let sum = |f: fn(&ReturnLine) -> i64| lines.iter().map(f).sum::<i64>();
+    let total_cgst = sum(|l| l.cgst_amount_paise);
+    let total_sgst = sum(|l| l.sgst_amount_paise);
+    let total_igst = sum(|l| l.igst_amount_paise);
+    let total_gst = total_cgst + total_sgst + total_igst;
+    let refund_total = sum(|l| l.total_paise);
+    tx.execute(
+        "UPDATE sale_returns SET taxable_amount_paise = ?1, total_cgst_paise = ?2,
+             total_sgst_paise = ?3, total_igst_paise = ?4, total_gst_paise = ?5,
+             refund_total_paise = ?6
+         WHERE id = ?7",
+        params![
+            sum(|l| l.taxable_amount_paise),
+            total_cgst,
+            total_sgst,
+            total_igst,
+            total_gst,
+            refund_total,
+            return_id,
+        ],
+    )?;
+    loyalty::rebalance(tx, sale_id)?;
+
+    let fully_returned = tx.execute(
+        "UPDATE sales SET status = 'refunded'
+         WHERE id = ?1 AND NOT EXISTS (
+             SELECT 1 FROM sale_items si
+             WHERE si.sale_id = sales.id AND si.quantity > (
+                 SELECT COALESCE(SUM(ri.quantity), 0) FROM sale_return_items ri
+                 WHERE ri.sale_item_id = si.id
+             )
+         )",
+        params![sale_id],
+    )?;
+    if fully_returned > 0 {
+        tx.changed(Entity::Sale, ChangeOp::Update, sale_id);
+    }
+    sync::enqueue(
+        tx,
+        "create_sale_return",
+        &json!({
+            "saleId": sale_id,
+            "items": items,
+            "refundMode": refund_mode,
+            "userId": user_id,
+        }),
+    )?;
+
+    Ok(ReturnResult {
+        return_id,
+        credit_note_number,
+        sale_id,
+        total_gst_paise: total_gst,
+        refund_total_paise: refund_total,
+        lines,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{
+        batch_quantity, insert_batch, insert_medicine, migrated_db, paid_sale,
+    };
+    const ADMIN: i64 = 1;
+
+    fn sell(tx: &Tx, batch_id: i64, quantity: i64) -> AppResult<i64> {
+        let sale = paid_sale(tx, None, PaymentMode::Cash, batch_id, quantity);
+        let sale_id = sales::create_sale(tx, &sale)?.sale_id;
+        Ok(tx.query_row(
+            "SELECT id FROM sale_items WHERE sale_id = ?1",
+            params![sale_id],
+            |row| row.get(0),
+        )?)
+    }
+
+    fn ret(sale_item_id: i64, quantity: i64) -> ReturnItem {
+        ReturnItem {
+            sale_item_id,
+            quantity,
+            non_saleable: false,
+        }
+    }
+
+    #[test]
+    fn prorated_shares_add_up_to_the_line() {
+        let parts = [
+            prorate(100, 3, 0, 1),
+            prorate(100, 3, 1, 1),
+            prorate(100, 3, 2, 1),
+        ];
+        assert_eq!(parts, [33, 34, 33]);
+        assert_eq!(prorate(100, 3, 0, 3), 100);
+    }
+
+    #[test]
+    fn partial_returns_restore_stock_until_the_sale_is_refunded() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 1_120, 5);
+        let item = db.with_tx(|tx| sell(tx, batch, 3)).unwrap();
+        let sale_id: i64 = conn
+            .query_row(
+                "SELECT sale_id FROM sale_items WHERE id = ?1",
+                [item],
+                |r| r.get(0),
+            )
+            .unwrap();
+
+        let first = db
+            .with_tx(|tx| {
+                create_sale_return(tx, sale_id, &[ret(item, 2)], PaymentMode::Cash, ADMIN)
+            })
+            .unwrap();
+        assert_eq!(first.refund_total_paise, 2_240);
+        assert_eq!(first.total_gst_paise, 240);
+        assert_eq!(batch_quantity(&conn, batch), 4);
+
+        let err = db
+            .with_tx(|tx| {
+                create_sale_return(tx, sale_id, &[ret(item, 2)], PaymentMode::Cash, ADMIN)
+            })
+            .unwrap_err();
+        assert!(err.to_string().contains("only 1 left"), "{err}");
+
+        db.with_tx(|tx| create_sale_return(tx, sale_id, &[ret(item, 1)], PaymentMode::Cash, ADMIN))
+            .unwrap();
+        let status: String = conn
+            .query_row("SELECT status FROM sales WHERE id = ?1", [sale_id], |r| {
+                r.get(0)
+            })
+            .unwrap();
+        assert_eq!(status, "refunded");
+
+        let history = inventory::batch_movement_history(&conn, batch).unwrap();
+        let ledger: i64 = history.iter().map(|m| m.change_qty).sum();
+        assert_eq!(ledger, batch_quantity(&conn, batch));
+        assert_eq!(history.last().unwrap().reason, MovementReason::Return);
+    }
+
+    #[test]
+    fn expired_batch_is_restored_but_flagged() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 1_120, 5);
+        let item = db.with_tx(|tx| sell(tx, batch, 2)).unwrap();
+        conn.execute(
+            "UPDATE batches SET expiry_date = date('now', '-1 day') WHERE id = ?1",
+            [batch],
+        )
+        .unwrap();
+
+        let result = db
+            .with_tx(|tx| create_sale_return(tx, 1, &[ret(item, 2)], PaymentMode::Upi, ADMIN))
+            .unwrap();
+
+        assert!(result.lines[0].batch_expired);
+        assert_eq!(batch_quantity(&conn, batch), 5);
+    }
+
+    #[test]
+    fn credit_notes_are_numbered_apart_from_invoices() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 1_120, 5);
+        let item = db.with_tx(|tx| sell(tx, batch, 3)).unwrap();
+        db.with_tx(|tx| sell(tx, batch, 1)).unwrap();
+
+        let damaged = ReturnItem {
+            non_saleable: true,
+            ..ret(item, 1)
+        };
+        let first = db
+            .with_tx(|tx| create_sale_return(tx, 1, &[damaged], PaymentMode::Cash, ADMIN))
+            .unwrap();
+        let second = db
+            .with_tx(|tx| create_sale_return(tx, 1, &[ret(item, 1)], PaymentMode::Cash, ADMIN))
+            .unwrap();
+
+        assert_eq!(first.credit_note_number, "CN-000001");
+        assert_eq!(second.credit_note_number, "CN-000002");
+        assert!(!first.lines[0].restocked);
+        assert_eq!(first.refund_total_paise, 1_120);
+        assert_eq!(batch_quantity(&conn, batch), 2);
+    }
+}
@@ -0,0 +1,1738 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::auth::{self, permission};
+use crate::branches;
+use crate::customers;
+use crate::db::{ChangeOp, Entity, Tx};
+use crate::error::{AppError, AppResult};
+use crate::ewaybill;
+use crate::fefo::{self, SaleUnit};
+use crate::gst::{self, InvoiceTotals, LineAmounts};
+use crate::gst_history;
+use crate::inventory::{self, MovementReason, EXPIRED};
+use crate::invoice::{self, InvoiceDocument};
+use crate::loyalty;
+use crate::prescriptions;
+use crate::shifts;
+use crate::sync;
+
+/// A cart line as entered at the till; batches are picked by FEFO.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SaleLineInput {
+    pub medicine_id: i64,
+    pub quantity: i64,
+    /// Defaults to the medicine's `sale_unit`, else packs.
+    #[serde(default)]
+    pub unit: Option<SaleUnit>,
+    #[serde(default)]
+    pub discount_paise: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PaymentMode {
+    Cash,
+    Card,
+    Upi,
+    Credit,
+}
+
+impl PaymentMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Cash => "cash",
+            Self::Card => "card",
+            Self::Upi => "upi",
+            Self::Credit => "credit",
+        }
+    }
+
+    pub fn parse(s: &str) -> AppResult<Self> {
+        match s {
+            "cash" => Ok(Self::Cash),
+            "card" => Ok(Self::Card),
+            "upi" => Ok(Self::Upi),
+            "credit" => Ok(Self::Credit),
+            other => Err(AppError::validation(format!(
+                "Unknown payment mode '{other}'"
+            ))),
+        }
+    }
+}
+
+/// The part of a bill settled in one mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Payment {
+    pub mode: PaymentMode,
+    pub amount_paise: i64,
+}
+
+/// The mode that settled the largest share, first listed on a tie.
+///
+/// Stored in `sales.payment_mode` for reports written before split tender.
+pub fn dominant_mode(payments: &[Payment]) -> PaymentMode {
+    payments
+        .iter()
+        .rev()
+        .max_by_key(|p| p.amount_paise)
+        .map_or(PaymentMode::Cash, |p| p.mode)
+}
+
+/// Fail unless `payments` settle exactly `grand_total_paise`, one entry per
+/// mode.
+fn validate_payments(payments: &[Payment], grand_total_paise: i64) -> AppResult<()> {
+    for (i, payment) in payments.iter().enumerate() {
+        if payment.amount_paise <= 0 {
+            return Err(AppError::validation(format!(
+                "The {} payment must be greater than 0",
+                payment.mode.as_str()
+            )));
+        }
+        if payments[..i].iter().any(|p| p.mode == payment.mode) {
+            return Err(AppError::validation(format!(
+                "The {} payment is listed more than once",
+                payment.mode.as_str()
+            )));
+        }
+    }
+    let paid: i64 = payments.iter().map(|p| p.amount_paise).sum();
+    if paid != grand_total_paise {
+        let verb = if paid < grand_total_paise {
+            "short of"
+        } else {
+            "more than"
+        };
+        return Err(AppError::validation(format!(
+            "Payments of {paid} paise are {verb} the bill of {grand_total_paise} paise"
+        )));
+    }
+    Ok(())
+}
+
+/// A cart line sold from a batch the cashier has already picked.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewSaleItem {
+    pub batch_id: i64,
+    pub quantity: i64,
+    /// Defaults to the medicine's `sale_unit`, else packs.
+    #[serde(default)]
+    pub unit: Option<SaleUnit>,
+    #[serde(default)]
+    pub discount_paise: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewSale {
+    pub customer_id: Option<i64>,
+    /// Set from the session by the command, never taken from the client.
+    #[serde(default, skip_deserializing)]
+    pub user_id: i64,
+    /// How the bill is settled; must add up to the grand total.
+    pub payments: Vec<Payment>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// Required when the cart holds a scheduled medicine.
+    #[serde(default)]
+    pub prescription_id: Option<i64>,
+    /// Where the sale is made; defaults to the user's branch, else Main.
+    #[serde(default)]
+    pub branch_id: Option<i64>,
+    /// Chosen by the client once per checkout; a retry carrying the same key
+    /// returns the sale the first attempt recorded instead of a second one.
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+    pub items: Vec<NewSaleItem>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SaleResult {
+    pub sale_id: i64,
+    pub invoice_number: String,
+    pub totals: InvoiceTotals,
+    /// The finished invoice, ready to print.
+    pub invoice: InvoiceDocument,
+}
+
+/// The result of a recorded sale, with its invoice read back as stored.
+fn sale_result(conn: &Connection, sale_id: i64) -> AppResult<SaleResult> {
+    let invoice = invoice::get_invoice(conn, sale_id)?;
+    Ok(SaleResult {
+        sale_id,
+        invoice_number: invoice.invoice_number.clone(),
+        totals: invoice.totals,
+        invoice,
+    })
+}
+
+/// A priced `sale_items` row, ready to insert.
+#[derive(Debug, Clone)]
+struct PricedItem {
+    pub batch_id: i64,
+    pub medicine_id: i64,
+    pub medicine_name: String,
+    pub hsn_code: String,
+    /// Drug schedule (`H`, `H1` or `X`); `None` for over-the-counter.
+    pub schedule: Option<String>,
+    /// Loose units in each unit sold; `amounts` count units sold.
+    pub unit_size: i64,
+    pub amounts: LineAmounts,
+}
+
+struct MedicineTax {
+    name: String,
+    hsn_code: String,
+    gst_rate: f64,
+    is_active: bool,
+    schedule: Option<String>,
+}
+
+/// A medicine's tax details, at the GST rate in force on `sale_date`.
+fn medicine_tax(conn: &Connection, medicine_id: i64, sale_date: &str) -> AppResult<MedicineTax> {
+    let (name, hsn_code, is_active, schedule) = conn
+        .query_row(
+            "SELECT name, hsn_code, is_active, schedule FROM medicines WHERE id = ?1",
+            params![medicine_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Medicine {medicine_id}")))?;
+    Ok(MedicineTax {
+        name,
+        hsn_code,
+        gst_rate: gst_history::rate_on(conn, medicine_id, sale_date)?,
+        is_active,
+        schedule,
+    })
+}
+
+/// Allocate batches by FEFO and compute GST for each cart line.
+///
+/// A line that spans several batches becomes several items, each holding
+/// whole units; its discount is split in proportion to quantity, with the
+/// remainder on the last item.
+fn price_lines(
+    conn: &Connection,
+    lines: &[SaleLineInput],
+    pricing: Pricing,
+    sale_date: &str,
+    branch_id: i64,
+) -> AppResult<Vec<PricedItem>> {
+    let mut items = Vec::new();
+    for line in lines {
+        let med = medicine_tax(conn, line.medicine_id, sale_date)?;
+        if !med.is_active {
+            return Err(AppError::validation(format!("{} is inactive", med.name)));
+        }
+        if line.quantity <= 0 {
+            return Err(AppError::validation(format!(
+                "Quantity for {} must be greater than 0",
+                med.name
+            )));
+        }
+        if line.discount_paise < 0 {
+            return Err(AppError::validation(format!(
+                "Discount for {} cannot be negative",
+                med.name
+            )));
+        }
+
+        let packing = fefo::Packing::load(conn, line.medicine_id)?;
+        let unit = packing.unit(line.unit);
+        let unit_size = unit.size(packing.pack_size);
+        let base = packing.base_quantity(line.quantity, unit)?;
+        let batches = fefo::sellable_batches(conn, line.medicine_id, Some(branch_id))?;
+        let plan = fefo::allocate(&batches, base, unit_size).map_err(|short| {
+            AppError::validation(format!(
+                "Insufficient stock for {}: requested {}, {short} units short",
+                med.name, line.quantity
+            ))
+        })?;
+
+        let mut discount_left = line.discount_paise;
+        for (i, part) in plan.iter().enumerate() {
+            let sold = part.quantity / unit_size;
+            let price = unit.price(part.selling_price_paise, packing.pack_size);
+            let discount = if i + 1 == plan.len() {
+                discount_left
+            } else {
+                line.discount_paise * sold / line.quantity
+            };
+            discount_left -= discount;
+            if discount > price * sold {
+                return Err(AppError::validation(format!(
+                    "Discount for {} exceeds the line value",
+                    med.name
+                )));
+            }
+
+            items.push(PricedItem {
+                batch_id: part.batch_id,
+                medicine_id: line.medicine_id,
+                medicine_name: med.name.clone(),
+                hsn_code: med.hsn_code.clone(),
+                schedule: med.schedule.clone(),
+                unit_size,
+                amounts: pricing.line(price, sold, med.gst_rate, discount),
+            });
+        }
+    }
+    Ok(items)
+}
+
+/// Insert items for a sale and deduct their quantities, in loose units,
+/// from stock.
+///
+/// Stock is sold off the selling floor. The deduction is guarded so the
+/// floor can never go negative, even if it changed after allocation.
+fn insert_items(tx: &Tx, sale_id: i64, items: &[PricedItem]) -> AppResult<()> {
+    let mut insert = tx.prepare_cached(
+        "INSERT INTO sale_items (sale_id, batch_id, medicine_id, quantity, unit_price_paise,
+             discount_paise, taxable_amount_paise, cgst_rate, cgst_amount_paise,
+             sgst_rate, sgst_amount_paise, igst_rate, igst_amount_paise, total_paise, hsn_code,
+             unit_size)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+    )?;
+    let mut deduct = tx.prepare_cached(
+        "UPDATE batches SET quantity = quantity - ?1
+         WHERE id = ?2 AND quantity - COALESCE(
+             (SELECT SUM(quantity) FROM batch_locations WHERE batch_id = ?2), 0) >= ?1",
+    )?;
+
+    for item in items {
+        let a = &item.amounts;
+        let quantity = a.quantity * item.unit_size;
+        insert.execute(params![
+            sale_id,
+            item.batch_id,
+            item.medicine_id,
+            quantity,
+            a.unit_price_paise,
+            a.discount_paise,
+            a.taxable_amount_paise,
+            a.gst.cgst_rate,
+            a.gst.cgst_paise,
+            a.gst.sgst_rate,
+            a.gst.sgst_paise,
+            a.gst.igst_rate,
+            a.gst.igst_paise,
+            a.total_paise,
+            item.hsn_code,
+            item.unit_size,
+        ])?;
+        if deduct.execute(params![quantity, item.batch_id])? == 0 {
+            return Err(AppError::validation(format!(
+                "Insufficient stock for {}: batch {} has fewer than {quantity} units on the selling floor",
+                item.medicine_name, item.batch_id
+            )));
+        }
+        inventory::record_movement(
+            tx,
+            item.batch_id,
+            -quantity,
+            MovementReason::Sale,
+            Some(sale_id),
+        )?;
+        tx.changed(Entity::Batch, ChangeOp::Update, item.batch_id);
+    }
+    Ok(())
+}
+
+/// Price a line against its batch: the stored selling price, never one sent
+/// by the client, divided down to a loose unit when sold loose. GST is
+/// charged at the medicine's rate on `sale_date`, and the batch must be
+/// stocked at `branch_id`.
+fn price_batch_item(
+    conn: &Connection,
+    item: &NewSaleItem,
+    pricing: Pricing,
+    sale_date: &str,
+    branch_id: i64,
+) -> AppResult<PricedItem> {
+    let (medicine_id, price, mrp, status, expired, batch_branch) = conn
+        .query_row(
+            &format!(
+                "SELECT medicine_id, selling_price_paise, mrp_paise, status, {EXPIRED},
+                     branch_id
+                 FROM batches WHERE id = ?1"
+            ),
+            params![item.batch_id],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, bool>(4)?,
+                    row.get::<_, Option<i64>>(5)?,
+                ))
+            },
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Batch {}", item.batch_id)))?;
+    let MedicineTax {
+        name,
+        hsn_code,
+        gst_rate,
+        is_active,
+        schedule,
+    } = medicine_tax(conn, medicine_id, sale_date)?;
+
+    if !is_active {
+        return Err(AppError::validation(format!("{name} is inactive")));
+    }
+    if status != "available" {
+        return Err(AppError::validation(format!(
+            "{name}: batch is on {status} hold"
+        )));
+    }
+    if batch_branch != Some(branch_id) {
+        return Err(AppError::validation(format!(
+            "{name}: batch is stocked at another branch"
+        )));
+    }
+    if expired {
+        return Err(AppError::validation(format!("{name}: batch has expired")));
+    }
+    if price > mrp {
+        return Err(AppError::validation(format!(
+            "{name}: selling price exceeds MRP"
+        )));
+    }
+    if item.quantity <= 0 {
+        return Err(AppError::validation(format!(
+            "Quantity for {name} must be greater than 0"
+        )));
+    }
+    let packing = fefo::Packing::load(conn, medicine_id)?;
+    let unit = packing.unit(item.unit);
+    packing.base_quantity(item.quantity, unit)?;
+    let price = unit.price(price, packing.pack_size);
+    if item.discount_paise < 0 || item.discount_paise > price * item.quantity {
+        return Err(AppError::validation(format!(
+            "Discount for {name} must be between 0 and the line value"
+        )));
+    }
+
+    Ok(PricedItem {
+        batch_id: item.batch_id,
+        medicine_id,
+        medicine_name: name,
+        hsn_code,
+        schedule,
+        unit_size: unit.size(packing.pack_size),
+        amounts: pricing.line(price, item.quantity, gst_rate, item.discount_paise),
+    })
+}
+
+/// Check that a cart with scheduled medicines is covered by a prescription
+/// written for the sale's customer.
+///
+/// H1 and X drugs must also be traceable to the prescriber, so the
+/// prescription needs a doctor name and an Rx number.
+fn check_prescription(
+    conn: &Connection,
+    items: &[PricedItem],
+    prescription_id: Option<i64>,
+    customer_id: Option<i64>,
+) -> AppResult<()> {
+    let mut scheduled: Vec<&str> = Vec::new();
+    let mut traceable: Vec<&str> = Vec::new();
+    for item in items {
+        let Some(schedule) = item.schedule.as_deref() else {
+            continue;
+        };
+        let name = item.medicine_name.as_str();
+        if !scheduled.contains(&name) {
+            scheduled.push(name);
+            if schedule != "H" {
+                traceable.push(name);
+            }
+        }
+    }
+    if scheduled.is_empty() {
+        return Ok(());
+    }
+    let Some(prescription_id) = prescription_id else {
+        return Err(AppError::validation(format!(
+            "A prescription is required for: {}",
+            scheduled.join(", ")
+        )));
+    };
+
+    let (prescribed_for, doctor_name, rx_number): (i64, String, Option<String>) = conn
+        .query_row(
+            "SELECT customer_id, doctor_name, rx_number FROM prescriptions WHERE id = ?1",
+            params![prescription_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Prescription {prescription_id}")))?;
+    if customer_id != Some(prescribed_for) {
+        return Err(AppError::validation(
+            "The prescription was written for a different customer",
+        ));
+    }
+    let blank = |s: Option<&str>| s.is_none_or(|s| s.trim().is_empty());
+    if !traceable.is_empty() && (blank(Some(&doctor_name)) || blank(rx_number.as_deref())) {
+        return Err(AppError::validation(format!(
+            "Schedule H1 and X medicines need the doctor's name and Rx number: {}",
+            traceable.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+/// How lines of a sale are taxed.
+#[derive(Debug, Clone, Copy)]
+struct Pricing {
+    interstate: bool,
+    /// Selling prices already include GST (the MRP convention).
+    prices_include_gst: bool,
+    /// Billed under the composition scheme: a bill of supply charging no tax.
+    composition: bool,
+}
+
+impl Pricing {
+    /// Tax basis for a sale to `customer_id` under the pharmacy's settings.
+    fn for_customer(conn: &Connection, customer_id: Option<i64>) -> AppResult<Self> {
+        let (prices_include_gst, composition) = conn.query_row(
+            "SELECT prices_include_gst, composition_scheme FROM pharmacy_settings WHERE id = 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let interstate = supply_is_interstate(conn, customer_id)?;
+        if composition && interstate {
+            return Err(AppError::validation(
+                "Composition dealers cannot make inter-state supplies",
+            ));
+        }
+        Ok(Self {
+            interstate,
+            prices_include_gst,
+            composition,
+        })
+    }
+
+    fn line(
+        self,
+        unit_price_paise: i64,
+        quantity: i64,
+        gst_rate: f64,
+        discount: i64,
+    ) -> LineAmounts {
+        let calculate = if self.prices_include_gst {
+            gst::calculate_line
+        } else {
+            gst::calculate_line_exclusive
+        };
+        let gst_rate = if self.composition { 0.0 } else { gst_rate };
+        calculate(
+            unit_price_paise,
+            quantity,
+            gst_rate,
+            discount,
+            self.interstate,
+        )
+    }
+}
+
+/// Where a sale to `customer_id` is supplied: the customer's state code (or
+/// their GSTIN prefix), else the pharmacy's own. `None` while neither is
+/// configured.
+fn place_of_supply(conn: &Connection, customer_id: Option<i64>) -> AppResult<Option<String>> {
+    Ok(conn.query_row(
+        "SELECT COALESCE(
+             (SELECT COALESCE(NULLIF(c.state_code, ''), NULLIF(substr(c.gstin, 1, 2), ''))
+              FROM customers c WHERE c.id = ?1),
+             NULLIF(state_code, ''))
+         FROM pharmacy_settings WHERE id = 1",
+        params![customer_id],
+        |row| row.get(0),
+    )?)
+}
+
+/// Whether a sale to `customer_id` is an inter-state supply.
+///
+/// Compares the place of supply with the pharmacy's state code. Walk-in
+/// customers and unconfigured state codes are treated as intra-state,
+/// matching `isInterstateSupply` in the frontend.
+fn supply_is_interstate(conn: &Connection, customer_id: Option<i64>) -> AppResult<bool> {
+    if customer_id.is_none() {
+        return Ok(false);
+    }
+    let pharmacy: String = conn.query_row(
+        "SELECT state_code FROM pharmacy_settings WHERE id = 1",
+        [],
+        |row| row.get(0),
+    )?;
+    let place = place_of_supply(conn, customer_id)?;
+    Ok(!pharmacy.is_empty() && place.is_some_and(|place| place != pharmacy))
+}
+
+/// Digits `{seq}` is padded to when the format gives no width.
+const DEFAULT_SEQ_WIDTH: usize = 4;
+
+/// The Indian financial year (April to March) containing a `YYYY-MM-DD`
+/// date, e.g. `2024-25` for 2025-03-31.
+fn financial_year(date: &str) -> AppResult<String> {
+    let invalid = || AppError::validation(format!("Invalid date '{date}'"));
+    let year: i32 = date
+        .get(..4)
+        .and_then(|y| y.parse().ok())
+        .ok_or_else(invalid)?;
+    let month: u32 = date
+        .get(5..7)
+        .and_then(|m| m.parse().ok())
+        .ok_or_else(invalid)?;
+    let start = if month >= 4 { year } else { year - 1 };
+    Ok(format!("{start}-{:02}", (start + 1) % 100))
+}
+
+/// Fill in an invoice number template: `{prefix}`, `{fy}` and `{seq}`,
+/// which takes an optional width as `{seq:6}`.
+fn expand_invoice_number(format: &str, prefix: &str, fy: &str, seq: i64) -> AppResult<String> {
+    let mut out = String::new();
+    let mut rest = format;
+    let mut has_seq = false;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let close = rest[open..].find('}').ok_or_else(|| {
+            AppError::validation(format!(
+                "Unclosed placeholder in invoice number format '{format}'"
+            ))
+        })?;
+        let placeholder = &rest[open + 1..open + close];
+        match placeholder.split_once(':').unwrap_or((placeholder, "")) {
+            ("prefix", "") => out.push_str(prefix),
+            ("fy", "") => out.push_str(fy),
+            ("seq", width) => {
+                let width = if width.is_empty() {
+                    DEFAULT_SEQ_WIDTH
+                } else {
+                    width.parse().map_err(|_| {
+                        AppError::validation(format!("Invalid {{seq}} width in '{format}'"))
+                    })?
+                };
+                out.push_str(&format!("{seq:0width$}"));
+                has_seq = true;
+            }
+            _ => {
+                return Err(AppError::validation(format!(
+                    "Unknown placeholder {{{placeholder}}} in invoice number format '{format}'"
+                )))
+            }
+        }
+        rest = &rest[open + close + 1..];
+    }
+    out.push_str(rest);
+    if !has_seq {
+        return Err(AppError::validation(
+            "The invoice number format must include {seq}",
+        ));
+    }
+    Ok(out)
+}
+
+/// A numbered document series kept in `pharmacy_settings`. Every series
+/// shares the invoice number format and financial-year reset.
+#[derive(Debug, Clone, Copy)]
+enum NumberSeries {
+    Invoice,
+    CreditNote,
+    DebitNote,
+}
+
+impl NumberSeries {
+    /// The prefix, next-number and financial-year columns of the series.
+    fn columns(self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            Self::Invoice => (
+                "invoice_prefix",
+                "next_invoice_number",
+                "invoice_sequence_fy",
+            ),
+            Self::CreditNote => (
+                "credit_note_prefix",
+                "next_credit_note_number",
+                "credit_note_sequence_fy",
+            ),
+            Self::DebitNote => (
+                "debit_note_prefix",
+                "next_debit_note_number",
+                "debit_note_sequence_fy",
+            ),
+        }
+    }
+}
+
+/// Take the next invoice number, e.g. `INV-000042` or `INV/2024-25/0042`.
+///
+/// The counter is read and bumped in a single statement inside the caller's
+/// write transaction, so two terminals can never be handed the same number.
+/// With `fy_reset` on, the first invoice of a financial year restarts the
+/// counter at 1, and the format must then carry `{fy}` to stay unique.
+/// The year is taken from the local date, so a sale just after midnight
+/// falls in the day and year printed on its invoice.
+fn allocate_invoice_number(tx: &Tx) -> AppResult<String> {
+    let today: String = tx.query_row("SELECT date('now', 'localtime')", [], |row| row.get(0))?;
+    allocate_invoice_number_on(tx, &today)
+}
+
+fn allocate_invoice_number_on(tx: &Tx, today: &str) -> AppResult<String> {
+    allocate_number_on(tx, NumberSeries::Invoice, today)
+}
+
+/// Take the next credit note number, e.g. `CN-000007`, from a series kept
+/// apart from invoices in the same way.
+pub(crate) fn allocate_credit_note_number(tx: &Tx) -> AppResult<String> {
+    let today: String = tx.query_row("SELECT date('now', 'localtime')", [], |row| row.get(0))?;
+    allocate_number_on(tx, NumberSeries::CreditNote, &today)
+}
+
+/// Take the next debit note number for goods sent back to a supplier,
+/// e.g. `DN-000003`.
+pub(crate) fn allocate_debit_note_number(tx: &Tx) -> AppResult<String> {
+    let today: String = tx.query_row("SELECT date('now', 'localtime')", [], |row| row.get(0))?;
+    allocate_number_on(tx, NumberSeries::DebitNote, &today)
+}
+
+fn allocate_number_on(tx: &Tx, series: NumberSeries, today: &str) -> AppResult<String> {
+    let fy = financial_year(today)?;
+    let (prefix_col, next_col, fy_col) = series.columns();
+    let (prefix, format, fy_reset, number): (String, String, bool, i64) = tx.query_row(
+        &format!(
+            "UPDATE pharmacy_settings SET
+                 {next_col} = CASE WHEN fy_reset AND {fy_col} IS NOT ?1
+                     THEN 2 ELSE {next_col} + 1 END,
+                 {fy_col} = ?1
+             WHERE id = 1
+             RETURNING {prefix_col}, invoice_number_format, fy_reset, {next_col} - 1"
+        ),
+        params![fy],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    )?;
+    if fy_reset && !format.contains("{fy}") {
+        return Err(AppError::validation(
+            "The invoice number format must include {fy} when numbering restarts each financial year",
+        ));
+    }
+    expand_invoice_number(&format, &prefix, &fy, number)
+}
+
+/// The sale already recorded under an idempotency key, if the key is still
+/// within the window set in `pharmacy_settings`. Expired keys are dropped
+/// first, so a key may be reused once its window has passed.
+fn replayed_sale(tx: &Tx, key: &str) -> AppResult<Option<SaleResult>> {
+    tx.execute(
+        "DELETE FROM sale_idempotency
+         WHERE created_at < datetime('now', '-' || (
+             SELECT idempotency_window_hours FROM pharmacy_settings WHERE id = 1) || ' hours')",
+        [],
+    )?;
+    let sale_id: Option<i64> = tx
+        .query_row(
+            "SELECT sale_id FROM sale_idempotency WHERE key = ?1",
+            params![key],
+            |row| row.get(0),
+        )
+        .optional()?;
+    sale_id.map(|id| sale_result(tx, id)).transpose()
+}
+
+/// Record a sale and deduct its stock in one transaction, returning the
+/// finished invoice.
+///
+/// Prices and GST are recomputed from the batches. If any batch lacks the
+/// stock, nothing is written and the error names the medicine.
+///
+/// A sale carrying an idempotency key that already produced a sale returns
+/// that sale unchanged. The write transaction is taken up front, so two
+/// concurrent calls with one key are serialized and only the first records
+/// anything.
+pub fn create_sale(tx: &Tx, sale: &NewSale) -> AppResult<SaleResult> {
+    auth::active_role(tx, sale.user_id)?;
+    let idempotency_key = sale
+        .idempotency_key
+        .as_deref()
+        .map(str::trim)
+        .filter(|k| !k.is_empty());
+    if let Some(key) = idempotency_key {
+        if let Some(replayed) = replayed_sale(tx, key)? {
+            return Ok(replayed);
+        }
+    }
+    if sale.items.is_empty() {
+        return Err(AppError::validation("A sale must have at least one item"));
+    }
+    if let Some(customer_id) = sale.customer_id {
+        let exists: bool = tx.query_row(
+            "SELECT EXISTS(SELECT 1 FROM customers WHERE id = ?1)",
+            params![customer_id],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            return Err(AppError::not_found(format!("Customer {customer_id}")));
+        }
+    }
+
+    let pricing = Pricing::for_customer(tx, sale.customer_id)?;
+    let today: String = tx.query_row("SELECT date('now', 'localtime')", [], |row| row.get(0))?;
+    let branch_id = branches::sale_branch(tx, sale.branch_id, sale.user_id)?;
+    let items = sale
+        .items
+        .iter()
+        .map(|item| price_batch_item(tx, item, pricing, &today, branch_id))
+        .collect::<AppResult<Vec<_>>>()?;
+    let totals = gst::invoice_totals(items.iter().map(|i| &i.amounts));
+    validate_payments(&sale.payments, totals.grand_total_paise)?;
+    let on_credit = sale
+        .payments
+        .iter()
+        .filter(|p| p.mode == PaymentMode::Credit)
+        .map(|p| p.amount_paise)
+        .sum();
+    customers::check_credit_limit(tx, sale.customer_id, on_credit)?;
+    check_prescription(tx, &items, sale.prescription_id, sale.customer_id)?;
+
+    let invoice_number = allocate_invoice_number(tx)?;
+    let notes = sale
+        .notes
+        .as_deref()
+        .map(str::trim)
+        .filter(|n| !n.is_empty());
+    tx.execute(
+        "INSERT INTO sales (invoice_number, customer_id, user_id, subtotal_paise, discount_paise,
+             total_cgst_paise, total_sgst_paise, total_igst_paise, total_gst_paise,
+             round_off_paise, grand_total_paise, payment_mode, notes, is_interstate,
+             place_of_supply, bill_of_supply, branch_id, shift_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17,
+             ?18)",
+        params![
+            invoice_number,
+            sale.customer_id,
+            sale.user_id,
+            totals.subtotal_paise,
+            totals.discount_paise,
+            totals.total_cgst_paise,
+            totals.total_sgst_paise,
+            totals.total_igst_paise,
+            totals.total_gst_paise,
+            totals.round_off_paise,
+            totals.grand_total_paise,
+            dominant_mode(&sale.payments).as_str(),
+            notes,
+            pricing.interstate,
+            place_of_supply(tx, sale.customer_id)?,
+            pricing.composition,
+            branch_id,
+            shifts::open_shift_id(tx, sale.user_id)?,
+        ],
+    )?;
+    let sale_id = tx.last_insert_rowid();
+    if let Some(key) = idempotency_key {
+        tx.execute(
+            "INSERT INTO sale_idempotency (key, sale_id) VALUES (?1, ?2)",
+            params![key, sale_id],
+        )?;
+    }
+    insert_items(tx, sale_id, &items)?;
+    let mut insert_payment = tx.prepare_cached(
+        "INSERT INTO sale_payments (sale_id, payment_mode, amount_paise) VALUES (?1, ?2, ?3)",
+    )?;
+    for payment in &sale.payments {
+        insert_payment.execute(params![
+            sale_id,
+            payment.mode.as_str(),
+            payment.amount_paise
+        ])?;
+    }
+    if let Some(prescription_id) = sale.prescription_id {
+        prescriptions::use_for_sale(tx, prescription_id, sale_id)?;
+    }
+    if let Some(customer_id) = sale.customer_id {
+        loyalty::accrue(tx, sale_id, customer_id, totals.grand_total_paise)?;
+    }
+    tx.changed(Entity::Sale, ChangeOp::Insert, sale_id);
+    sync::enqueue(tx, "create_sale", sale)?;
+
+    sale_result(tx, sale_id)
+}
+
+/// Put a sale's item quantities back into their batches, as ledger
+/// adjustments, and remove the items.
+fn reverse_items(tx: &Tx, sale_id: i64) -> AppResult<()> {
+    let returned = {
+        let mut stmt = tx.prepare(
+            "SELECT batch_id, SUM(quantity) FROM sale_items
+             WHERE sale_id = ?1
+             GROUP BY batch_id",
+        )?;
+        let rows = stmt.query_map(params![sale_id], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+        })?;
+        rows.collect::<Result<Vec<_>, _>>()?
+    };
+
+    let mut restore =
+        tx.prepare_cached("UPDATE batches SET quantity = quantity + ?1 WHERE id = ?2")?;
+    for &(batch_id, quantity) in &returned {
+        restore.execute(params![quantity, batch_id])?;
+        inventory::record_movement(
+            tx,
+            batch_id,
+            quantity,
+            MovementReason::Adjustment,
+            Some(sale_id),
+        )?;
+        tx.changed(Entity::Batch, ChangeOp::Update, batch_id);
+    }
+    tx.execute(
+        "DELETE FROM sale_items WHERE sale_id = ?1",
+        params![sale_id],
+    )?;
+    Ok(())
+}
+
+/// How a sale was settled, in the order the payments were taken.
+pub fn sale_payment_breakdown(conn: &Connection, sale_id: i64) -> AppResult<Vec<Payment>> {
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sales WHERE id = ?1)",
+        params![sale_id],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        return Err(AppError::not_found(format!("Sale {sale_id}")));
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT payment_mode, amount_paise FROM sale_payments WHERE sale_id = ?1 ORDER BY id",
+    )?;
+    let mut rows = stmt.query(params![sale_id])?;
+    let mut payments = Vec::new();
+    while let Some(row) = rows.next()? {
+        let mode: String = row.get(0)?;
+        payments.push(Payment {
+            mode: PaymentMode::parse(&mode)?,
+            amount_paise: row.get(1)?,
+        });
+    }
+    Ok(payments)
+}
+
+/// Settle an amendment's change in total in the mode that paid most of the
+/// sale: extra is charged to it, a reduction refunded from it.
+fn settle_difference(
+    tx: &Tx,
+    sale_id: i64,
+    mode: PaymentMode,
+    difference_paise: i64,
+) -> AppResult<Vec<Payment>> {
+    let current = sale_payment_breakdown(tx, sale_id)?
+        .iter()
+        .find(|p| p.mode == mode)
+        .map_or(0, |p| p.amount_paise);
+    let settled = current + difference_paise;
+    if settled < 0 {
+        return Err(AppError::validation(format!(
+            "The amended total cannot be refunded from the {} payment; record a return instead",
+            mode.as_str()
+        )));
+    }
+
+    if settled == 0 {
+        tx.execute(
+            "DELETE FROM sale_payments WHERE sale_id = ?1 AND payment_mode = ?2",
+            params![sale_id, mode.as_str()],
+        )?;
+    } else {
+        tx.execute(
+            "INSERT INTO sale_payments (sale_id, payment_mode, amount_paise) VALUES (?1, ?2, ?3)
+             ON CONFLICT (sale_id, payment_mode) DO UPDATE SET amount_paise = excluded.amount_paise",
+            params![sale_id, mode.as_str(), settled],
+        )?;
+    }
+    sale_payment_breakdown(tx, sale_id)
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AmendResult {
+    pub sale_id: i64,
+    pub invoice_number: String,
+    pub amendment_id: i64,
+    pub totals: InvoiceTotals,
+}
+
+/// Replace the lines of a finalized sale, keeping its invoice number.
+///
+/// The original deductions are reversed before the new lines are allocated,
+/// so the amended cart may reuse the same stock. The replaced lines and
+/// totals are kept in `sale_amendments`.
+pub fn amend_sale(
+    tx: &Tx,
+    sale_id: i64,
+    new_items: &[SaleLineInput],
+    user_id: i64,
+) -> AppResult<AmendResult> {
+    auth::require_permission(tx, user_id, permission::SALES_AMEND)?;
+    if new_items.is_empty() {
+        return Err(AppError::validation(
+            "An amended sale must have at least one item",
+        ));
+    }
+
+    let (invoice_number, status, previous_total, customer_id, settled_in, sale_date, branch_id): (
+        String,
+        String,
+        i64,
+        Option<i64>,
+        String,
+        String,
+        Option<i64>,
+    ) = tx
+        .query_row(
+            "SELECT invoice_number, status, grand_total_paise, customer_id, payment_mode,
+                 date(sale_date, 'localtime'), branch_id
+             FROM sales WHERE id = ?1",
+            params![sale_id],
+            |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                    row.get(6)?,
+                ))
+            },
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Sale {sale_id}")))?;
+    if status == "refunded" {
+        return Err(AppError::validation(format!(
+            "Sale {invoice_number} has been refunded and cannot be amended"
+        )));
+    }
+    let has_returns: bool = tx.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sale_returns WHERE sale_id = ?1)",
+        params![sale_id],
+        |row| row.get(0),
+    )?;
+    if has_returns {
+        return Err(AppError::validation(format!(
+            "Sale {invoice_number} has returns against it and cannot be amended"
+        )));
+    }
+    let irn: Option<String> = tx
+        .query_row(
+            "SELECT irn FROM sale_einvoices WHERE sale_id = ?1",
+            params![sale_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if let Some(irn) = irn {
+        return Err(AppError::validation(format!(
+            "Sale {invoice_number} is registered as e-invoice {irn} and cannot be amended"
+        )));
+    }
+    if let Some(bill) = ewaybill::active_eway_bill(tx, sale_id)? {
+        return Err(AppError::validation(format!(
+            "Sale {invoice_number} travels on e-way bill {}; cancel it before amending the sale",
+            bill.ewb_no
+        )));
+    }
+
+    let previous_items: String = tx.query_row(
+        "SELECT json_group_array(json_object(
+             'batch_id', batch_id, 'medicine_id', medicine_id, 'quantity', quantity,
+             'unit_price_paise', unit_price_paise, 'discount_paise', discount_paise,
+             'taxable_amount_paise', taxable_amount_paise,
+             'cgst_amount_paise', cgst_amount_paise, 'sgst_amount_paise', sgst_amount_paise,
+             'igst_amount_paise', igst_amount_paise, 'total_paise', total_paise,
+             'unit_size', unit_size))
+         FROM sale_items WHERE sale_id = ?1",
+        params![sale_id],
+        |row| row.get(0),
+    )?;
+
+    reverse_items(tx, sale_id)?;
+    let pricing = Pricing::for_customer(tx, customer_id)?;
+    let branch_id = branch_id.unwrap_or(branches::MAIN_BRANCH_ID);
+    let items = price_lines(tx, new_items, pricing, &sale_date, branch_id)?;
+    let prescription_id: Option<i64> = tx
+        .query_row(
+            "SELECT id FROM prescriptions WHERE sale_id = ?1 ORDER BY id DESC LIMIT 1",
+            params![sale_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    check_prescription(tx, &items, prescription_id, customer_id)?;
+    let totals = gst::invoice_totals(items.iter().map(|i| &i.amounts));
+    insert_items(tx, sale_id, &items)?;
+    let settled_in = PaymentMode::parse(&settled_in)?;
+    let difference_paise = totals.grand_total_paise - previous_total;
+    if settled_in == PaymentMode::Credit && difference_paise > 0 {
+        customers::check_credit_limit(tx, customer_id, difference_paise)?;
+    }
+    let payments = settle_difference(tx, sale_id, settled_in, difference_paise)?;
+
+    tx.execute(
+        "UPDATE sales SET subtotal_paise = ?1, discount_paise = ?2, total_cgst_paise = ?3,
+             total_sgst_paise = ?4, total_igst_paise = ?5, total_gst_paise = ?6,
+             round_off_paise = ?7, grand_total_paise = ?8, is_interstate = ?9,
+             place_of_supply = ?10, bill_of_supply = ?11, payment_mode = ?12,
+             status = 'amended'
+         WHERE id = ?13",
+        params![
+            totals.subtotal_paise,
+            totals.discount_paise,
+            totals.total_cgst_paise,
+            totals.total_sgst_paise,
+            totals.total_igst_paise,
+            totals.total_gst_paise,
+            totals.round_off_paise,
+            totals.grand_total_paise,
+            pricing.interstate,
+            place_of_supply(tx, customer_id)?,
+            pricing.composition,
+            dominant_mode(&payments).as_str(),
+            sale_id,
+        ],
+    )?;
+    tx.execute(
+        "INSERT INTO sale_amendments (sale_id, user_id, previous_items,
+             previous_grand_total_paise, new_grand_total_paise)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            sale_id,
+            user_id,
+            previous_items,
+            previous_total,
+            totals.grand_total_paise
+        ],
+    )?;
+    let amendment_id = tx.last_insert_rowid();
+    loyalty::rebalance(tx, sale_id)?;
+    tx.changed(Entity::Sale, ChangeOp::Update, sale_id);
+    sync::enqueue(
+        tx,
+        "amend_sale",
+        &json!({ "saleId": sale_id, "newItems": new_items, "userId": user_id }),
+    )?;
+
+    Ok(AmendResult {
+        sale_id,
+        invoice_number,
+        amendment_id,
+        totals,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{
+        batch_quantity, insert_batch, insert_medicine, migrated_db, paid_sale,
+    };
+
+    const ADMIN: i64 = 1;
+
+    fn line(medicine_id: i64, quantity: i64) -> SaleLineInput {
+        SaleLineInput {
+            medicine_id,
+            quantity,
+            unit: None,
+            discount_paise: 0,
+        }
+    }
+
+    /// Record a sale directly, the way the frontend does today.
+    fn seed_sale(tx: &Tx, lines: &[SaleLineInput]) -> i64 {
+        tx.execute(
+            "INSERT INTO sales (invoice_number, user_id) VALUES ('INV-000001', ?1)",
+            params![ADMIN],
+        )
+        .unwrap();
+        let sale_id = tx.last_insert_rowid();
+        let today: String = tx
+            .query_row("SELECT date('now', 'localtime')", [], |row| row.get(0))
+            .unwrap();
+        let pricing = Pricing::for_customer(tx, None).unwrap();
+        let items = price_lines(tx, lines, pricing, &today, branches::MAIN_BRANCH_ID).unwrap();
+        insert_items(tx, sale_id, &items).unwrap();
+        sale_id
+    }
+
+    #[test]
+    fn amend_restores_original_stock_and_reallocates() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let early = insert_batch(&conn, para, "+30 days", 1_120, 5);
+        let late = insert_batch(&conn, para, "+300 days", 1_120, 10);
+
+        let sale_id = db
+            .with_tx(|tx| Ok(seed_sale(tx, &[line(para, 8)])))
+            .unwrap();
+        assert_eq!(batch_quantity(&conn, early), 0);
+        assert_eq!(batch_quantity(&conn, late), 7);
+
+        let result = db
+            .with_tx(|tx| amend_sale(tx, sale_id, &[line(para, 3)], ADMIN))
+            .unwrap();
+
+        assert_eq!(result.invoice_number, "INV-000001");
+        assert_eq!(batch_quantity(&conn, early), 2);
+        assert_eq!(batch_quantity(&conn, late), 10);
+        assert_eq!(result.totals.subtotal_paise, 3_360);
+
+        let (status, amendments): (String, i64) = conn
+            .query_row(
+                "SELECT status, (SELECT COUNT(*) FROM sale_amendments WHERE sale_id = s.id)
+                 FROM sales s WHERE id = ?1",
+                params![sale_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((status.as_str(), amendments), ("amended", 1));
+    }
+
+    #[test]
+    fn financial_year_runs_april_to_march() {
+        assert_eq!(financial_year("2025-03-31").unwrap(), "2024-25");
+        assert_eq!(financial_year("2025-04-01").unwrap(), "2025-26");
+        assert_eq!(financial_year("2099-12-31").unwrap(), "2099-00");
+    }
+
+    #[test]
+    fn invoice_format_expands_placeholders() {
+        let expand = |f: &str| expand_invoice_number(f, "INV", "2024-25", 42);
+        assert_eq!(expand("{prefix}-{seq:6}").unwrap(), "INV-000042");
+        assert_eq!(expand("{prefix}/{fy}/{seq}").unwrap(), "INV/2024-25/0042");
+        assert!(expand("{prefix}/{fy}").is_err());
+        assert!(expand("{prefix}/{year}/{seq}").is_err());
+        assert!(expand("{prefix}/{seq").is_err());
+    }
+
+    #[test]
+    fn fy_reset_restarts_the_sequence_on_april_first() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        conn.execute(
+            "UPDATE pharmacy_settings SET invoice_number_format = '{prefix}/{fy}/{seq}',
+                 fy_reset = 1, next_invoice_number = 42, invoice_sequence_fy = '2024-25'",
+            [],
+        )
+        .unwrap();
+        let take = |today: &str| {
+            db.with_tx(|tx| allocate_invoice_number_on(tx, today))
+                .unwrap()
+        };
+
+        assert_eq!(take("2025-03-31"), "INV/2024-25/0042");
+        assert_eq!(take("2025-04-01"), "INV/2025-26/0001");
+        assert_eq!(take("2025-04-01"), "INV/2025-26/0002");
+
+        conn.execute(
+            "UPDATE pharmacy_settings SET invoice_number_format = '{prefix}-{seq}'",
+            [],
+        )
+        .unwrap();
+        let err = db
+            .with_tx(|tx| allocate_invoice_number_on(tx, "2025-04-02"))
+            .unwrap_err();
+        assert!(err.to_string().contains("{fy}"), "{err}");
+    }
+
+    #[test]
+    fn without_fy_reset_the_sequence_carries_over() {
+        let (_dir, db) = migrated_db();
+        let take = |today: &str| {
+            db.with_tx(|tx| allocate_invoice_number_on(tx, today))
+                .unwrap()
+        };
+
+        assert_eq!(take("2025-03-31"), "INV-000001");
+        assert_eq!(take("2025-04-01"), "INV-000002");
+    }
+
+    #[test]
+    fn create_sale_prices_from_the_batch_and_deducts_stock() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 1_120, 5);
+
+        let sale = paid_sale(&conn, None, PaymentMode::Cash, batch, 2);
+        let result = db.with_tx(|tx| create_sale(tx, &sale)).unwrap();
+
+        assert_eq!(result.invoice_number, "INV-000001");
+        // ₹22.40 of lines is payable as ₹22; the tax figures stay exact.
+        assert_eq!(result.totals.grand_total_paise, 2_200);
+        assert_eq!(result.totals.round_off_paise, -40);
+        assert_eq!(result.totals.total_gst_paise, 240);
+        assert_eq!(batch_quantity(&conn, batch), 3);
+    }
+
+    #[test]
+    fn create_sale_returns_the_finished_invoice() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 1_120, 5);
+
+        let sale = paid_sale(&conn, None, PaymentMode::Cash, batch, 2);
+        let result = db.with_tx(|tx| create_sale(tx, &sale)).unwrap();
+
+        let invoice = &result.invoice;
+        assert_eq!(invoice.sale_id, result.sale_id);
+        assert_eq!(invoice.invoice_number, "INV-000001");
+        assert_eq!(invoice.totals, result.totals);
+        assert_eq!(invoice.items.len(), 1);
+        assert_eq!(invoice.items[0].medicine_name, "Paracetamol");
+        assert_eq!(invoice.items[0].quantity, 2);
+        assert_eq!(invoice.payments[0].amount_paise, 2_200);
+        assert_eq!(invoice.amount_in_words, "Twenty Two Rupees Only");
+        assert_eq!(
+            invoice::get_invoice(&conn, result.sale_id).unwrap().items,
+            invoice.items
+        );
+    }
+
+    #[test]
+    fn sales_to_another_state_are_taxed_as_igst_at_their_place_of_supply() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        conn.execute_batch(
+            "UPDATE pharmacy_settings SET state_code = '29' WHERE id = 1;
+             INSERT INTO customers (name, gstin) VALUES ('Mumbai Clinic', '27AAPFU0939F1ZV');",
+        )
+        .unwrap();
+        let clinic = conn.last_insert_rowid();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 1_120, 5);
+
+        let away = paid_sale(&conn, Some(clinic), PaymentMode::Cash, batch, 1);
+        let away = db.with_tx(|tx| create_sale(tx, &away)).unwrap();
+        let home = paid_sale(&conn, None, PaymentMode::Cash, batch, 1);
+        let home = db.with_tx(|tx| create_sale(tx, &home)).unwrap();
+
+        assert_eq!(away.totals.total_igst_paise, away.totals.total_gst_paise);
+        assert_eq!(away.totals.total_cgst_paise, 0);
+        assert_eq!(home.totals.total_igst_paise, 0);
+        assert_eq!(
+            home.totals.total_cgst_paise + home.totals.total_sgst_paise,
+            home.totals.total_gst_paise
+        );
+        let recorded = |sale_id: i64| -> (bool, Option<String>) {
+            conn.query_row(
+                "SELECT is_interstate, place_of_supply FROM sales WHERE id = ?1",
+                params![sale_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap()
+        };
+        assert_eq!(recorded(away.sale_id), (true, Some("27".into())));
+        assert_eq!(recorded(home.sale_id), (false, Some("29".into())));
+    }
+
+    #[test]
+    fn composition_dealers_bill_without_tax_and_only_within_the_state() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        conn.execute_batch(
+            "UPDATE pharmacy_settings SET state_code = '29', composition_scheme = 1 WHERE id = 1;
+             INSERT INTO customers (name, state_code) VALUES ('Mumbai Clinic', '27');",
+        )
+        .unwrap();
+        let clinic = conn.last_insert_rowid();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 1_120, 5);
+
+        let sale = paid_sale(&conn, None, PaymentMode::Cash, batch, 2);
+        let result = db.with_tx(|tx| create_sale(tx, &sale)).unwrap();
+        // The buyer pays the same MRP; none of it is shown as tax.
+        assert_eq!(result.totals.total_gst_paise, 0);
+        assert_eq!(result.totals.grand_total_paise, 2_200);
+        let bill_of_supply: bool = conn
+            .query_row(
+                "SELECT bill_of_supply FROM sales WHERE id = ?1",
+                params![result.sale_id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(bill_of_supply);
+
+        let away = paid_sale(&conn, Some(clinic), PaymentMode::Cash, batch, 1);
+        let err = db.with_tx(|tx| create_sale(tx, &away)).unwrap_err();
+        assert!(err.to_string().contains("inter-state"), "{err}");
+    }
+
+    #[test]
+    fn retried_sale_with_the_same_key_is_recorded_once() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 1_120, 10);
+        let mut sale = paid_sale(&conn, None, PaymentMode::Cash, batch, 2);
+        sale.idempotency_key = Some("checkout-1".into());
+
+        let results = std::thread::scope(|scope| {
+            let attempts: Vec<_> = (0..2)
+                .map(|_| scope.spawn(|| db.with_tx(|tx| create_sale(tx, &sale)).unwrap()))
+                .collect();
+            attempts
+                .into_iter()
+                .map(|attempt| attempt.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        assert_eq!(results[0].sale_id, results[1].sale_id);
+        assert_eq!(results[0].invoice_number, results[1].invoice_number);
+        assert_eq!(results[0].invoice.items, results[1].invoice.items);
+        let sales: i64 = conn
+            .query_row("SELECT COUNT(*) FROM sales", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(sales, 1);
+        assert_eq!(batch_quantity(&conn, batch), 8);
+
+        // Once the window has passed the key starts a new sale.
+        conn.execute(
+            "UPDATE sale_idempotency SET created_at = datetime('now', '-25 hours')",
+            [],
+        )
+        .unwrap();
+        let again = db.with_tx(|tx| create_sale(tx, &sale)).unwrap();
+        assert_ne!(again.sale_id, results[0].sale_id);
+    }
+
+    #[test]
+    fn loose_units_and_packs_deduct_whole_loose_units() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        conn.execute(
+            "UPDATE medicines SET pack_size = 10 WHERE id = ?1",
+            params![para],
+        )
+        .unwrap();
+        let batch = insert_batch(&conn, para, "+30 days", 3_330, 20);
+        let sold = |sale_id: i64| -> (i64, i64, i64) {
+            conn.query_row(
+                "SELECT quantity, unit_size, unit_price_paise FROM sale_items WHERE sale_id = ?1",
+                params![sale_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap()
+        };
+
+        let mut loose = paid_sale(&conn, None, PaymentMode::Cash, batch, 3);
+        loose.items[0].unit = Some(SaleUnit::Loose);
+        loose.payments[0].amount_paise = 1_000;
+        let result = db.with_tx(|tx| create_sale(tx, &loose)).unwrap();
+        assert_eq!(result.totals.subtotal_paise, 999);
+        assert_eq!(sold(result.sale_id), (3, 1, 333));
+        assert_eq!(batch_quantity(&conn, batch), 17);
+
+        let strip = paid_sale(&conn, None, PaymentMode::Cash, batch, 1);
+        let result = db.with_tx(|tx| create_sale(tx, &strip)).unwrap();
+        assert_eq!(result.totals.subtotal_paise, 3_330);
+        assert_eq!(sold(result.sale_id), (10, 10, 3_330));
+        assert_eq!(batch_quantity(&conn, batch), 7);
+
+        let err = db.with_tx(|tx| create_sale(tx, &strip)).unwrap_err();
+        assert!(err.to_string().contains("fewer than 10 units"), "{err}");
+    }
+
+    #[test]
+    fn stock_ledger_reconciles_with_batch_quantity() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let early = insert_batch(&conn, para, "+30 days", 1_120, 5);
+        let late = insert_batch(&conn, para, "+300 days", 1_120, 10);
+
+        let sale = paid_sale(&conn, None, PaymentMode::Cash, early, 4);
+        let sale_id = db.with_tx(|tx| create_sale(tx, &sale)).unwrap().sale_id;
+        db.with_tx(|tx| amend_sale(tx, sale_id, &[line(para, 7)], ADMIN))
+            .unwrap();
+
+        for batch in [early, late] {
+            let history = inventory::batch_movement_history(&conn, batch).unwrap();
+            let ledger: i64 = history.iter().map(|m| m.change_qty).sum();
+            assert_eq!(ledger, batch_quantity(&conn, batch), "batch {batch}");
+        }
+        let sold = inventory::batch_movement_history(&conn, early).unwrap();
+        assert_eq!(sold[1].reason, MovementReason::Sale);
+        assert_eq!(
+            (sold[1].change_qty, sold[1].reference_id),
+            (-4, Some(sale_id))
+        );
+    }
+
+    #[test]
+    fn oversold_sale_rolls_back_entirely() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 1_120, 5);
+        let mut sale = paid_sale(&conn, None, PaymentMode::Cash, batch, 3);
+        sale.items.push(sale.items[0].clone());
+        sale.payments[0].amount_paise = gst::round_to_rupee(6 * 1_120);
+
+        let err = db.with_tx(|tx| create_sale(tx, &sale)).unwrap_err();
+
+        assert!(err.to_string().contains("Paracetamol"), "{err}");
+        assert_eq!(batch_quantity(&conn, batch), 5);
+        let (sales, next): (i64, i64) = conn
+            .query_row(
+                "SELECT (SELECT COUNT(*) FROM sales), next_invoice_number
+                 FROM pharmacy_settings WHERE id = 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((sales, next), (0, 1));
+    }
+
+    #[test]
+    fn concurrent_sales_get_distinct_invoice_numbers() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 1_120, 100);
+        let sale = paid_sale(&conn, None, PaymentMode::Cash, batch, 1);
+
+        let mut numbers: Vec<String> = std::thread::scope(|s| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| s.spawn(|| db.with_tx(|tx| create_sale(tx, &sale))))
+                .collect();
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap().unwrap().invoice_number)
+                .collect()
+        });
+
+        numbers.sort();
+        numbers.dedup();
+        assert_eq!(numbers.len(), 8);
+        assert_eq!(batch_quantity(&conn, batch), 92);
+    }
+
+    #[test]
+    fn refunded_sale_cannot_be_amended() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 1_120, 5);
+        let sale_id = db
+            .with_tx(|tx| Ok(seed_sale(tx, &[line(para, 2)])))
+            .unwrap();
+        conn.execute(
+            "UPDATE sales SET status = 'refunded' WHERE id = ?1",
+            params![sale_id],
+        )
+        .unwrap();
+
+        let err = db
+            .with_tx(|tx| amend_sale(tx, sale_id, &[line(para, 1)], ADMIN))
+            .unwrap_err();
+
+        assert!(err.to_string().contains("refunded"));
+        assert_eq!(batch_quantity(&conn, batch), 3);
+    }
+
+    #[test]
+    fn split_tender_must_settle_the_bill_exactly() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 5_000, 10);
+        let mut sale = paid_sale(&conn, None, PaymentMode::Cash, batch, 3);
+        let pay = |mode, amount_paise| Payment { mode, amount_paise };
+
+        sale.payments = vec![pay(PaymentMode::Cash, 5_000), pay(PaymentMode::Upi, 9_999)];
+        let err = db.with_tx(|tx| create_sale(tx, &sale)).unwrap_err();
+        assert!(err.to_string().contains("short of the bill"), "{err}");
+        sale.payments[1].amount_paise = 10_001;
+        let err = db.with_tx(|tx| create_sale(tx, &sale)).unwrap_err();
+        assert!(err.to_string().contains("more than the bill"), "{err}");
+
+        sale.payments[1].amount_paise = 10_000;
+        let sale_id = db.with_tx(|tx| create_sale(tx, &sale)).unwrap().sale_id;
+
+        assert_eq!(
+            sale_payment_breakdown(&conn, sale_id).unwrap(),
+            sale.payments
+        );
+        let mode: String = conn
+            .query_row(
+                "SELECT payment_mode FROM sales WHERE id = ?1",
+                [sale_id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(mode, "upi");
+        assert_eq!(batch_quantity(&conn, batch), 7);
+    }
+
+    #[test]
+    fn inclusive_pricing_totals_the_mrp_and_exclusive_adds_gst_on_top() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 5.0);
+        let batch = insert_batch(&conn, para, "+30 days", 10_500, 10);
+        let sale = paid_sale(&conn, None, PaymentMode::Cash, batch, 1);
+
+        let inclusive = db.with_tx(|tx| create_sale(tx, &sale)).unwrap().totals;
+        assert_eq!(inclusive.grand_total_paise, 10_500);
+        assert_eq!(inclusive.total_gst_paise, 500);
+
+        conn.execute("UPDATE pharmacy_settings SET prices_include_gst = 0", [])
+            .unwrap();
+        let mut sale = sale;
+        sale.payments[0].amount_paise = 11_000;
+        let exclusive = db.with_tx(|tx| create_sale(tx, &sale)).unwrap().totals;
+        assert_eq!(exclusive.subtotal_paise, 10_500);
+        assert_eq!(exclusive.total_gst_paise, 525);
+        // 10500 + 525 = 11025, rounded to the rupee
+        assert_eq!(exclusive.grand_total_paise, 11_000);
+
+        let lines: Vec<(i64, i64, i64)> = conn
+            .prepare("SELECT taxable_amount_paise, cgst_amount_paise + sgst_amount_paise, total_paise FROM sale_items ORDER BY id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(lines, [(10_000, 500, 10_500), (10_500, 525, 11_025)]);
+    }
+
+    #[test]
+    fn over_the_counter_cart_sells_without_a_prescription() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 1_000, 10);
+        let sale = paid_sale(&conn, None, PaymentMode::Cash, batch, 2);
+
+        db.with_tx(|tx| create_sale(tx, &sale)).unwrap();
+
+        assert_eq!(batch_quantity(&conn, batch), 8);
+    }
+
+    #[test]
+    fn scheduled_medicine_needs_a_prescription_for_the_customer() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let amox = insert_medicine(&conn, "Amoxicillin", 12.0);
+        let alpra = insert_medicine(&conn, "Alprazolam", 12.0);
+        conn.execute("UPDATE medicines SET schedule = 'H' WHERE id = ?1", [amox])
+            .unwrap();
+        conn.execute(
+            "UPDATE medicines SET schedule = 'H1' WHERE id = ?1",
+            [alpra],
+        )
+        .unwrap();
+        let amox_batch = insert_batch(&conn, amox, "+30 days", 1_000, 10);
+        let alpra_batch = insert_batch(&conn, alpra, "+30 days", 1_000, 10);
+        conn.execute("INSERT INTO customers (name) VALUES ('Asha')", [])
+            .unwrap();
+        let customer = conn.last_insert_rowid();
+        let mut sale = paid_sale(&conn, Some(customer), PaymentMode::Cash, amox_batch, 1);
+        sale.items.push(NewSaleItem {
+            batch_id: alpra_batch,
+            quantity: 1,
+            unit: None,
+            discount_paise: 0,
+        });
+        sale.payments[0].amount_paise = 2_000;
+
+        let err = db.with_tx(|tx| create_sale(tx, &sale)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "A prescription is required for: Amoxicillin, Alprazolam"
+        );
+
+        conn.execute(
+            "INSERT INTO prescriptions (customer_id, doctor_name, prescription_date)
+             VALUES (?1, 'Dr. Rao', date('now'))",
+            [customer],
+        )
+        .unwrap();
+        let prescription = conn.last_insert_rowid();
+        sale.prescription_id = Some(prescription);
+        let err = db.with_tx(|tx| create_sale(tx, &sale)).unwrap_err();
+        assert!(err.to_string().contains("Rx number: Alprazolam"), "{err}");
+
+        conn.execute(
+            "UPDATE prescriptions SET rx_number = 'RX-17' WHERE id = ?1",
+            [prescription],
+        )
+        .unwrap();
+        let sale_id = db.with_tx(|tx| create_sale(tx, &sale)).unwrap().sale_id;
+
+        let linked: Option<i64> = conn
+            .query_row(
+                "SELECT sale_id FROM prescriptions WHERE id = ?1",
+                [prescription],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(linked, Some(sale_id));
+    }
+
+    #[test]
+    fn amendment_settles_the_difference_in_the_dominant_mode() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+30 days", 5_000, 10);
+        let pay = |mode, amount_paise| Payment { mode, amount_paise };
+        let split = |payments: Vec<Payment>| {
+            let mut sale = paid_sale(&conn, None, PaymentMode::Cash, batch, 3);
+            sale.payments = payments;
+            db.with_tx(|tx| create_sale(tx, &sale)).unwrap().sale_id
+        };
+        let amounts = |sale_id| {
+            sale_payment_breakdown(&conn, sale_id)
+                .unwrap()
+                .iter()
+                .map(|p| (p.mode, p.amount_paise))
+                .collect::<Vec<_>>()
+        };
+
+        let sale_id = split(vec![
+            pay(PaymentMode::Card, 5_000),
+            pay(PaymentMode::Cash, 10_000),
+        ]);
+        db.with_tx(|tx| amend_sale(tx, sale_id, &[line(para, 2)], ADMIN))
+            .unwrap();
+        assert_eq!(
+            amounts(sale_id),
+            [(PaymentMode::Card, 5_000), (PaymentMode::Cash, 5_000)]
+        );
+        // Card now leads on the tie, so the next reduction comes off it.
+        db.with_tx(|tx| amend_sale(tx, sale_id, &[line(para, 1)], ADMIN))
+            .unwrap();
+        assert_eq!(amounts(sale_id), [(PaymentMode::Cash, 5_000)]);
+
+        let sale_id = split(vec![
+            pay(PaymentMode::Cash, 6_000),
+            pay(PaymentMode::Card, 5_000),
+            pay(PaymentMode::Upi, 4_000),
+        ]);
+        let err = db
+            .with_tx(|tx| amend_sale(tx, sale_id, &[line(para, 1)], ADMIN))
+            .unwrap_err();
+        assert!(err.to_string().contains("record a return"), "{err}");
+    }
+}
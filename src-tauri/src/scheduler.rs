@@ -0,0 +1,731 @@
+//! Periodic background jobs on cron-like schedules.
+//!
+//! Jobs are registered in code with a key, a default schedule and the
+//! function that runs them; the `scheduled_jobs` table keeps each job's
+//! current schedule, whether it is enabled and when it next runs, so
+//! changes made in settings survive restarts. Every run, scheduled or
+//! manual, is recorded in `scheduled_job_runs`.
+//!
+//! Schedules are five-field cron expressions (minute, hour, day of month,
+//! month, day of week) read in local time. A run missed while the app was
+//! closed happens once at the next start, not once per missed slot.
+
+use std::collections::HashSet;
+use std::sync::{Mutex, PoisonError};
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+
+use crate::auth::{self, permission};
+use crate::db::{Db, Tx};
+use crate::error::{AppError, AppResult};
+
+/// Runs kept per job; older ones are pruned as new ones are recorded.
+const RUN_HISTORY_LIMIT: i64 = 200;
+
+const MINUTES_PER_DAY: i64 = 24 * 60;
+
+/// How far ahead to look for a matching minute: long enough for a
+/// schedule pinned to 29 February.
+const SEARCH_DAYS: i64 = 8 * 366;
+
+/// A parsed cron expression; each field is a bit set of allowed values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Schedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Whether the day-of-month and day-of-week fields were restricted.
+    /// When both are, a day matching either one matches, as in cron.
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl Schedule {
+    /// Parse `minute hour day-of-month month day-of-week`.
+    ///
+    /// Each field takes `*`, a value, a range `a-b`, a step `*/n` or `a-b/n`,
+    /// or a comma-separated list of those. Day of week runs from 0 (Sunday)
+    /// to 6, with 7 also meaning Sunday. `@hourly`, `@daily`, `@weekly` and
+    /// `@monthly` stand for their usual expressions.
+    pub fn parse(expr: &str) -> AppResult<Self> {
+        let expanded = match expr.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(AppError::validation(format!(
+                "Schedule '{expr}' must have five fields: minute hour day month weekday"
+            )));
+        };
+        let field = |text: &str, name: &str, lo: u32, hi: u32| {
+            parse_field(text, lo, hi).ok_or_else(|| {
+                AppError::validation(format!(
+                    "Schedule '{expr}' has an invalid {name} field '{text}'"
+                ))
+            })
+        };
+
+        let mut weekdays = field(weekday, "weekday", 0, 7)?;
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            minutes: field(minute, "minute", 0, 59)?,
+            hours: field(hour, "hour", 0, 23)?,
+            days: field(day, "day", 1, 31)?,
+            months: field(month, "month", 1, 12)?,
+            weekdays,
+            days_restricted: day != "*",
+            weekdays_restricted: weekday != "*",
+        })
+    }
+
+    fn matches_day(&self, month: u32, day: u32, weekday: u32) -> bool {
+        if self.months & (1 << month) == 0 {
+            return false;
+        }
+        let by_day = self.days & (1 << day) != 0;
+        let by_weekday = self.weekdays & (1 << weekday) != 0;
+        match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => by_day || by_weekday,
+            _ => by_day && by_weekday,
+        }
+    }
+
+    /// The first matching minute strictly after `after`, both counted in
+    /// minutes since 1970-01-01 00:00 local time. `None` if the schedule
+    /// can never match, such as on 31 February.
+    pub fn next_after(&self, after: i64) -> Option<i64> {
+        let start = after + 1;
+        let first_day = start.div_euclid(MINUTES_PER_DAY);
+        for day in first_day..first_day + SEARCH_DAYS {
+            let (_, month, dom) = civil_from_days(day);
+            // 1970-01-01 was a Thursday.
+            let weekday = (day + 4).rem_euclid(7) as u32;
+            if !self.matches_day(month, dom, weekday) {
+                continue;
+            }
+            let from = if day == first_day {
+                start.rem_euclid(MINUTES_PER_DAY)
+            } else {
+                0
+            };
+            for minute_of_day in from..MINUTES_PER_DAY {
+                let (hour, minute) = (minute_of_day / 60, minute_of_day % 60);
+                if self.hours & (1 << hour) != 0 && self.minutes & (1 << minute) != 0 {
+                    return Some(day * MINUTES_PER_DAY + minute_of_day);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Bit set of the values one cron field allows, or `None` if it is invalid.
+fn parse_field(text: &str, lo: u32, hi: u32) -> Option<u64> {
+    let mut bits = 0u64;
+    for part in text.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|&s| s > 0)?),
+            None => (part, 1),
+        };
+        let (from, to) = if range == "*" {
+            (lo, hi)
+        } else if let Some((a, b)) = range.split_once('-') {
+            (a.parse().ok()?, b.parse().ok()?)
+        } else {
+            let value: u32 = range.parse().ok()?;
+            // `5/10` means every tenth value from 5, as in cron.
+            (value, if part.contains('/') { hi } else { value })
+        };
+        if from < lo || to > hi || from > to {
+            return None;
+        }
+        for value in (from..=to).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Some(bits)
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The date `days` after 1970-01-01, as (year, month, day).
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Minutes since the epoch of a `YYYY-MM-DD HH:MM` timestamp.
+fn parse_minute(text: &str) -> AppResult<i64> {
+    let invalid = || AppError::validation(format!("Invalid timestamp '{text}'"));
+    let number = |range: std::ops::Range<usize>| -> AppResult<i64> {
+        text.get(range)
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(invalid)
+    };
+    let days = days_from_civil(number(0..4)?, number(5..7)? as u32, number(8..10)? as u32);
+    Ok(days * MINUTES_PER_DAY + number(11..13)? * 60 + number(14..16)?)
+}
+
+fn format_minute(minute: i64) -> String {
+    let (year, month, day) = civil_from_days(minute.div_euclid(MINUTES_PER_DAY));
+    let minute_of_day = minute.rem_euclid(MINUTES_PER_DAY);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:00",
+        minute_of_day / 60,
+        minute_of_day % 60
+    )
+}
+
+/// When `schedule` next fires after the current minute, as a UTC
+/// `datetime()` string.
+fn next_run_at(conn: &Connection, schedule: &Schedule) -> AppResult<Option<String>> {
+    let now: String = conn.query_row(
+        "SELECT strftime('%Y-%m-%d %H:%M', 'now', 'localtime')",
+        [],
+        |row| row.get(0),
+    )?;
+    match schedule.next_after(parse_minute(&now)?) {
+        Some(next) => Ok(Some(conn.query_row(
+            "SELECT datetime(?1, 'utc')",
+            params![format_minute(next)],
+            |row| row.get(0),
+        )?)),
+        None => Ok(None),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunSource {
+    Schedule,
+    Manual,
+}
+
+impl RunSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Schedule => "schedule",
+            Self::Manual => "manual",
+        }
+    }
+
+    pub fn parse(s: &str) -> AppResult<Self> {
+        match s {
+            "schedule" => Ok(Self::Schedule),
+            "manual" => Ok(Self::Manual),
+            other => Err(AppError::validation(format!(
+                "Unknown run source '{other}'"
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl RunStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Running => "running",
+            Self::Succeeded => "succeeded",
+            Self::Failed => "failed",
+        }
+    }
+
+    pub fn parse(s: &str) -> AppResult<Self> {
+        match s {
+            "running" => Ok(Self::Running),
+            "succeeded" => Ok(Self::Succeeded),
+            "failed" => Ok(Self::Failed),
+            other => Err(AppError::validation(format!(
+                "Unknown run status '{other}'"
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobRun {
+    pub id: i64,
+    pub job_key: String,
+    pub source: RunSource,
+    /// Who ran it by hand; `None` for scheduled runs.
+    pub triggered_by: Option<i64>,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+    pub status: RunStatus,
+    /// What the job reported, or the error it failed with.
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledJob {
+    pub key: String,
+    pub description: String,
+    pub schedule: String,
+    pub enabled: bool,
+    pub last_run_at: Option<String>,
+    /// UTC; `None` while disabled or when the schedule never matches.
+    pub next_run_at: Option<String>,
+    pub last_run: Option<JobRun>,
+}
+
+const RUN_COLUMNS: &str =
+    "id, job_key, source, triggered_by, started_at, finished_at, status, message";
+
+fn run_from_row(row: &Row) -> AppResult<JobRun> {
+    let source: String = row.get(2)?;
+    let status: String = row.get(6)?;
+    Ok(JobRun {
+        id: row.get(0)?,
+        job_key: row.get(1)?,
+        source: RunSource::parse(&source)?,
+        triggered_by: row.get(3)?,
+        started_at: row.get(4)?,
+        finished_at: row.get(5)?,
+        status: RunStatus::parse(&status)?,
+        message: row.get(7)?,
+    })
+}
+
+fn get_run(conn: &Connection, run_id: i64) -> AppResult<JobRun> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {RUN_COLUMNS} FROM scheduled_job_runs WHERE id = ?1"
+    ))?;
+    let mut rows = stmt.query(params![run_id])?;
+    match rows.next()? {
+        Some(row) => run_from_row(row),
+        None => Err(AppError::not_found(format!("Job run {run_id}"))),
+    }
+}
+
+/// Recent runs of `job_key`, newest first.
+pub fn job_runs(conn: &Connection, job_key: &str, limit: i64) -> AppResult<Vec<JobRun>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {RUN_COLUMNS} FROM scheduled_job_runs
+         WHERE job_key = ?1
+         ORDER BY id DESC
+         LIMIT ?2"
+    ))?;
+    let mut rows = stmt.query(params![job_key, limit])?;
+    let mut runs = Vec::new();
+    while let Some(row) = rows.next()? {
+        runs.push(run_from_row(row)?);
+    }
+    Ok(runs)
+}
+
+fn get_job(conn: &Connection, key: &str) -> AppResult<ScheduledJob> {
+    let job = conn
+        .query_row(
+            "SELECT key, description, schedule, enabled, last_run_at, next_run_at
+             FROM scheduled_jobs WHERE key = ?1",
+            params![key],
+            |row| {
+                Ok(ScheduledJob {
+                    key: row.get(0)?,
+                    description: row.get(1)?,
+                    schedule: row.get(2)?,
+                    enabled: row.get(3)?,
+                    last_run_at: row.get(4)?,
+                    next_run_at: row.get(5)?,
+                    last_run: None,
+                })
+            },
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Scheduled job '{key}'")))?;
+    let last_run = job_runs(conn, key, 1)?.pop();
+    Ok(ScheduledJob { last_run, ..job })
+}
+
+/// Every job on record, by key.
+pub fn list_jobs(conn: &Connection) -> AppResult<Vec<ScheduledJob>> {
+    let keys: Vec<String> = {
+        let mut stmt = conn.prepare("SELECT key FROM scheduled_jobs ORDER BY key")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.collect::<Result<_, _>>()?
+    };
+    keys.iter().map(|key| get_job(conn, key)).collect()
+}
+
+/// Pause or resume a job. Resuming schedules it from now, so runs missed
+/// while it was paused are not made up.
+pub fn set_job_enabled(tx: &Tx, key: &str, enabled: bool, user_id: i64) -> AppResult<ScheduledJob> {
+    auth::require_permission(tx, user_id, permission::SCHEDULER_MANAGE)?;
+    let job = get_job(tx, key)?;
+    let next = if enabled {
+        next_run_at(tx, &Schedule::parse(&job.schedule)?)?
+    } else {
+        None
+    };
+    tx.execute(
+        "UPDATE scheduled_jobs
+         SET enabled = ?2, next_run_at = ?3, updated_at = datetime('now')
+         WHERE key = ?1",
+        params![key, enabled, next],
+    )?;
+    get_job(tx, key)
+}
+
+/// Change when a job runs.
+pub fn set_job_schedule(
+    tx: &Tx,
+    key: &str,
+    schedule: &str,
+    user_id: i64,
+) -> AppResult<ScheduledJob> {
+    auth::require_permission(tx, user_id, permission::SCHEDULER_MANAGE)?;
+    let parsed = Schedule::parse(schedule)?;
+    let job = get_job(tx, key)?;
+    let next = if job.enabled {
+        next_run_at(tx, &parsed)?
+    } else {
+        None
+    };
+    tx.execute(
+        "UPDATE scheduled_jobs
+         SET schedule = ?2, next_run_at = ?3, updated_at = datetime('now')
+         WHERE key = ?1",
+        params![key, schedule.trim(), next],
+    )?;
+    get_job(tx, key)
+}
+
+/// What a job reports on success, kept in its run history.
+pub type JobOutcome = AppResult<Option<String>>;
+
+type JobFn = Box<dyn Fn(&Db) -> JobOutcome + Send + Sync>;
+
+struct Job {
+    key: &'static str,
+    description: &'static str,
+    schedule: &'static str,
+    run: JobFn,
+}
+
+/// The registered jobs, managed as Tauri state.
+///
+/// Jobs open their own connections on the [`Db`] they are given, so a
+/// long job does not hold a transaction while it works.
+#[derive(Default)]
+pub struct Scheduler {
+    jobs: Vec<Job>,
+    /// Keys of jobs running right now, so a manual run cannot overlap a
+    /// scheduled one.
+    running: Mutex<HashSet<&'static str>>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a job. `schedule` is only the default: once the job is on
+    /// record, the schedule stored for it wins.
+    pub fn job(
+        mut self,
+        key: &'static str,
+        description: &'static str,
+        schedule: &'static str,
+        run: impl Fn(&Db) -> JobOutcome + Send + Sync + 'static,
+    ) -> Self {
+        debug_assert!(
+            Schedule::parse(schedule).is_ok(),
+            "invalid schedule for {key}"
+        );
+        self.jobs.push(Job {
+            key,
+            description,
+            schedule,
+            run: Box::new(run),
+        });
+        self
+    }
+
+    /// Record any registered job not yet in `scheduled_jobs`, first due at
+    /// the next matching minute, and refresh the descriptions of the rest.
+    pub fn register(&self, conn: &Connection) -> AppResult<()> {
+        for job in &self.jobs {
+            let next = next_run_at(conn, &Schedule::parse(job.schedule)?)?;
+            conn.execute(
+                "INSERT INTO scheduled_jobs (key, description, schedule, next_run_at)
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(key) DO UPDATE SET description = excluded.description",
+                params![job.key, job.description, job.schedule, next],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Run every enabled job whose next run has come, one after another.
+    ///
+    /// The next run is moved on before the job starts, so a job that keeps
+    /// failing is retried on its schedule rather than on every tick.
+    pub fn tick(&self, db: &Db) -> AppResult<()> {
+        let due = db.with_conn(|conn| {
+            self.register(conn)?;
+            let mut stmt = conn.prepare(
+                "SELECT key, schedule FROM scheduled_jobs
+                 WHERE enabled = 1 AND next_run_at <= datetime('now')
+                 ORDER BY next_run_at, key",
+            )?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            let due: Vec<(String, String)> = rows.collect::<Result<_, _>>()?;
+            for (key, schedule) in &due {
+                let next = next_run_at(conn, &Schedule::parse(schedule)?)?;
+                conn.execute(
+                    "UPDATE scheduled_jobs SET next_run_at = ?2 WHERE key = ?1",
+                    params![key, next],
+                )?;
+            }
+            Ok(due)
+        })?;
+
+        for (key, _) in due {
+            if let Some(job) = self.jobs.iter().find(|job| job.key == key) {
+                if let Err(err) = self.run_job(db, job, RunSource::Schedule, None) {
+                    eprintln!("scheduled job {key} could not run: {err}");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Run a job now on behalf of `user_id`, whatever its schedule.
+    pub fn trigger(&self, db: &Db, key: &str, user_id: i64) -> AppResult<JobRun> {
+        db.with_conn(|conn| {
+            auth::require_permission(conn, user_id, permission::SCHEDULER_MANAGE)?;
+            self.register(conn)
+        })?;
+        let job = self
+            .jobs
+            .iter()
+            .find(|job| job.key == key)
+            .ok_or_else(|| AppError::not_found(format!("Scheduled job '{key}'")))?;
+        self.run_job(db, job, RunSource::Manual, Some(user_id))
+    }
+
+    fn run_job(
+        &self,
+        db: &Db,
+        job: &Job,
+        source: RunSource,
+        triggered_by: Option<i64>,
+    ) -> AppResult<JobRun> {
+        if !self
+            .running
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(job.key)
+        {
+            return Err(AppError::validation(format!(
+                "Job '{}' is already running",
+                job.key
+            )));
+        }
+
+        let outcome = db
+            .with_conn(|conn| {
+                conn.execute(
+                    "INSERT INTO scheduled_job_runs (job_key, source, triggered_by)
+                     VALUES (?1, ?2, ?3)",
+                    params![job.key, source.as_str(), triggered_by],
+                )?;
+                Ok(conn.last_insert_rowid())
+            })
+            .and_then(|run_id| {
+                let (status, message) = match (job.run)(db) {
+                    Ok(message) => (RunStatus::Succeeded, message),
+                    Err(err) => {
+                        eprintln!("scheduled job {} failed: {err}", job.key);
+                        (RunStatus::Failed, Some(err.to_string()))
+                    }
+                };
+                db.with_conn(|conn| finish_run(conn, job.key, run_id, status, message))
+            });
+
+        self.running
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(job.key);
+        outcome
+    }
+}
+
+fn finish_run(
+    conn: &Connection,
+    key: &str,
+    run_id: i64,
+    status: RunStatus,
+    message: Option<String>,
+) -> AppResult<JobRun> {
+    conn.execute(
+        "UPDATE scheduled_job_runs
+         SET finished_at = datetime('now'), status = ?2, message = ?3
+         WHERE id = ?1",
+        params![run_id, status.as_str(), message],
+    )?;
+    conn.execute(
+        "UPDATE scheduled_jobs SET last_run_at = datetime('now') WHERE key = ?1",
+        params![key],
+    )?;
+    conn.execute(
+        "DELETE FROM scheduled_job_runs
+         WHERE job_key = ?1 AND id <= (
+             SELECT id FROM scheduled_job_runs WHERE job_key = ?1
+             ORDER BY id DESC LIMIT 1 OFFSET ?2
+         )",
+        params![key, RUN_HISTORY_LIMIT],
+    )?;
+    get_run(conn, run_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::db::test_support::migrated_db;
+
+    fn next(expr: &str, after: &str) -> String {
+        let schedule = Schedule::parse(expr).unwrap();
+        format_minute(schedule.next_after(parse_minute(after).unwrap()).unwrap())
+    }
+
+    #[test]
+    fn schedules_find_the_next_matching_minute() {
+        assert_eq!(next("* * * * *", "2026-10-14 09:59"), "2026-10-14 10:00:00");
+        assert_eq!(
+            next("*/15 * * * *", "2026-10-14 09:46"),
+            "2026-10-14 10:00:00"
+        );
+        assert_eq!(
+            next("30 2 * * *", "2026-10-14 02:30"),
+            "2026-10-15 02:30:00"
+        );
+        assert_eq!(
+            next("0 9 * * 1-5", "2026-10-16 10:00"),
+            "2026-10-19 09:00:00"
+        );
+        assert_eq!(next("@monthly", "2026-12-31 23:59"), "2027-01-01 00:00:00");
+        assert_eq!(
+            next("0 0 29 2 *", "2026-03-01 00:00"),
+            "2028-02-29 00:00:00"
+        );
+        // Day of month and day of week together match either.
+        assert_eq!(next("0 0 1 * 0", "2026-10-14 00:00"), "2026-10-18 00:00:00");
+        assert_eq!(next("0 0 * * 7", "2026-10-14 00:00"), "2026-10-18 00:00:00");
+        assert!(Schedule::parse("0 0 31 2 *")
+            .unwrap()
+            .next_after(0)
+            .is_none());
+
+        for bad in [
+            "",
+            "* * * *",
+            "60 * * * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "a * * * *",
+        ] {
+            assert!(Schedule::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn due_jobs_run_once_and_keep_their_history() {
+        let (_dir, db) = migrated_db();
+        let count = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&count);
+        let scheduler = Scheduler::new()
+            .job("count", "Count runs", "0 3 * * *", move |_| {
+                let n = counter.fetch_add(1, Ordering::SeqCst) + 1;
+                Ok(Some(format!("run {n}")))
+            })
+            .job("fail", "Always fails", "@hourly", |_| {
+                Err(AppError::validation("nothing to do"))
+            });
+
+        // Registration schedules the first run in the future.
+        scheduler.tick(&db).unwrap();
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+        let jobs = db.with_conn(list_jobs).unwrap();
+        assert_eq!(jobs.len(), 2);
+        assert!(jobs.iter().all(|j| j.enabled && j.next_run_at.is_some()));
+
+        // One missed slot makes up a single run.
+        db.with_conn(|conn| {
+            conn.execute(
+                "UPDATE scheduled_jobs SET next_run_at = datetime('now', '-3 days')",
+                [],
+            )?;
+            Ok(())
+        })
+        .unwrap();
+        scheduler.tick(&db).unwrap();
+        scheduler.tick(&db).unwrap();
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        let failed = db.with_conn(|conn| job_runs(conn, "fail", 10)).unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].status, RunStatus::Failed);
+        assert_eq!(failed[0].message.as_deref(), Some("nothing to do"));
+
+        let run = scheduler.trigger(&db, "count", 1).unwrap();
+        assert_eq!(run.source, RunSource::Manual);
+        assert_eq!(run.status, RunStatus::Succeeded);
+        assert_eq!(run.message.as_deref(), Some("run 2"));
+        let job = db.with_conn(|conn| get_job(conn, "count")).unwrap();
+        assert_eq!(job.last_run, Some(run));
+
+        let paused = db
+            .with_tx(|tx| set_job_enabled(tx, "count", false, 1))
+            .unwrap();
+        assert!(!paused.enabled && paused.next_run_at.is_none());
+        assert!(db
+            .with_tx(|tx| set_job_schedule(tx, "count", "every day", 1))
+            .is_err());
+        let moved = db
+            .with_tx(|tx| set_job_schedule(tx, "count", "@daily", 1))
+            .unwrap();
+        assert_eq!(moved.schedule, "@daily");
+        assert!(moved.next_run_at.is_none());
+    }
+}
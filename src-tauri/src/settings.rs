@@ -0,0 +1,176 @@
+//! Changes to the pharmacy profile and settings from the settings page.
+
+use rusqlite::params_from_iter;
+use rusqlite::types::Value as SqlValue;
+use serde_json::{Map, Value};
+
+use crate::auth::{self, permission};
+use crate::db::Tx;
+use crate::error::{AppError, AppResult};
+
+/// Settings the settings page may change, by their camelCase name, with
+/// the `pharmacy_settings` column each is stored in.
+///
+/// Off-site backup secrets are not here: they are kept out of the
+/// database altogether.
+const FIELDS: &[(&str, &str)] = &[
+    ("name", "name"),
+    ("address", "address"),
+    ("phone", "phone"),
+    ("email", "email"),
+    ("gstin", "gstin"),
+    ("drugLicenseNo", "drug_license_no"),
+    ("stateCode", "state_code"),
+    ("city", "city"),
+    ("pincode", "pincode"),
+    ("einvoiceEnabled", "einvoice_enabled"),
+    ("invoicePrefix", "invoice_prefix"),
+    ("invoiceNumberFormat", "invoice_number_format"),
+    ("fyReset", "fy_reset"),
+    ("creditNotePrefix", "credit_note_prefix"),
+    ("debitNotePrefix", "debit_note_prefix"),
+    ("lowStockThreshold", "low_stock_threshold"),
+    ("nearExpiryDays", "near_expiry_days"),
+    ("reorderLeadTimeDays", "reorder_lead_time_days"),
+    ("pricesIncludeGst", "prices_include_gst"),
+    ("compositionScheme", "composition_scheme"),
+    ("loyaltySpendPerPointPaise", "loyalty_spend_per_point_paise"),
+    ("loyaltyPointValuePaise", "loyalty_point_value_paise"),
+    ("idempotencyWindowHours", "idempotency_window_hours"),
+    ("lockoutMaxAttempts", "lockout_max_attempts"),
+    ("lockoutWindowMinutes", "lockout_window_minutes"),
+    ("lockoutMinutes", "lockout_minutes"),
+    ("printerInterface", "printer_interface"),
+    ("printerAddress", "printer_address"),
+    ("printerLineWidth", "printer_line_width"),
+    ("autoBackupEnabled", "auto_backup_enabled"),
+    ("autoBackupTime", "auto_backup_time"),
+    ("autoBackupKeep", "auto_backup_keep"),
+    ("autoBackupDir", "auto_backup_dir"),
+    ("remoteBackupTarget", "remote_backup_target"),
+    ("remoteBackupUrl", "remote_backup_url"),
+    ("remoteBackupRegion", "remote_backup_region"),
+    ("remoteBackupUsername", "remote_backup_username"),
+    ("maintenanceIntervalDays", "maintenance_interval_days"),
+    ("einvoiceApiUrl", "einvoice_api_url"),
+    ("einvoiceClientId", "einvoice_client_id"),
+    ("einvoiceClientSecret", "einvoice_client_secret"),
+    ("einvoiceUsername", "einvoice_username"),
+    ("einvoicePassword", "einvoice_password"),
+    ("ewaybillUsername", "ewaybill_username"),
+    ("ewaybillPassword", "ewaybill_password"),
+    ("ewaybillThresholdPaise", "ewaybill_threshold_paise"),
+];
+
+fn column(key: &str) -> AppResult<&'static str> {
+    FIELDS
+        .iter()
+        .find(|(name, _)| *name == key)
+        .map(|(_, column)| *column)
+        .ok_or_else(|| AppError::validation(format!("{key} is not a setting")))
+}
+
+fn sql_value(key: &str, value: &Value) -> AppResult<SqlValue> {
+    Ok(match value {
+        Value::Null => SqlValue::Null,
+        Value::Bool(b) => SqlValue::Integer(i64::from(*b)),
+        Value::Number(n) => match (n.as_i64(), n.as_f64()) {
+            (Some(i), _) => SqlValue::Integer(i),
+            (None, Some(f)) => SqlValue::Real(f),
+            _ => return Err(AppError::validation(format!("{key} is out of range"))),
+        },
+        Value::String(s) => SqlValue::Text(s.clone()),
+        _ => {
+            return Err(AppError::validation(format!(
+                "{key} must be a single value"
+            )))
+        }
+    })
+}
+
+/// Change the settings named in `changes`, leaving the rest as they are.
+///
+/// Keys are the camelCase names the settings page uses; an unknown key
+/// fails the whole change. The table's CHECK constraints still apply.
+pub fn update_settings(tx: &Tx, changes: &Map<String, Value>, user_id: i64) -> AppResult<()> {
+    auth::require_permission(tx, user_id, permission::SETTINGS_MANAGE)?;
+    let mut assignments = Vec::with_capacity(changes.len());
+    let mut values = Vec::with_capacity(changes.len());
+    for (key, value) in changes {
+        assignments.push(format!("{} = ?{}", column(key)?, values.len() + 1));
+        values.push(sql_value(key, value)?);
+    }
+    if assignments.is_empty() {
+        return Ok(());
+    }
+    tx.execute(
+        &format!(
+            "UPDATE pharmacy_settings SET {} WHERE id = 1",
+            assignments.join(", ")
+        ),
+        params_from_iter(values),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    use crate::db::test_support::{insert_cashier, migrated_db};
+
+    const ADMIN: i64 = 1;
+
+    fn changes(value: Value) -> Map<String, Value> {
+        value.as_object().cloned().unwrap()
+    }
+
+    #[test]
+    fn only_known_settings_change_and_only_with_the_permission() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let cashier = insert_cashier(&conn);
+
+        let denied = db.with_tx(|tx| {
+            update_settings(tx, &changes(json!({ "name": "Night Chemist" })), cashier)
+        });
+        assert!(matches!(denied, Err(AppError::Unauthorized(_))));
+
+        let unknown = db.with_tx(|tx| {
+            update_settings(
+                tx,
+                &changes(json!({ "remoteBackupSecret": "s3cret" })),
+                ADMIN,
+            )
+        });
+        assert!(matches!(unknown, Err(AppError::Validation(_))));
+
+        db.with_tx(|tx| {
+            update_settings(
+                tx,
+                &changes(json!({
+                    "name": "Night Chemist",
+                    "pricesIncludeGst": false,
+                    "nearExpiryDays": 60,
+                    "email": null
+                })),
+                ADMIN,
+            )
+        })
+        .unwrap();
+
+        let (name, inclusive, days, email): (String, bool, i64, Option<String>) = conn
+            .query_row(
+                "SELECT name, prices_include_gst, near_expiry_days, email
+                 FROM pharmacy_settings WHERE id = 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .unwrap();
+        assert_eq!(name, "Night Chemist");
+        assert!(!inclusive);
+        assert_eq!(days, 60);
+        assert_eq!(email, None);
+    }
+}
@@ -0,0 +1,145 @@
+//! First-run setup: replace the seeded admin password and record the
+//! pharmacy profile.
+
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::{self, DEFAULT_ADMIN_HASH};
+use crate::db::Tx;
+use crate::error::{AppError, AppResult};
+
+/// The pharmacy profile entered during setup.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PharmacySettingsInput {
+    pub name: String,
+    pub address: String,
+    pub phone: String,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub gstin: String,
+    #[serde(default)]
+    pub drug_license_no: String,
+    #[serde(default)]
+    pub state_code: String,
+    #[serde(default)]
+    pub city: String,
+    #[serde(default)]
+    pub pincode: String,
+}
+
+/// Set the admin password and pharmacy profile, once.
+///
+/// Only runs while the admin still has [`DEFAULT_ADMIN_HASH`]; after
+/// that it refuses, so it cannot be used to reset the admin account.
+pub fn complete_first_run(
+    tx: &Tx,
+    new_admin_password: &str,
+    pharmacy: &PharmacySettingsInput,
+) -> AppResult<()> {
+    let admin_id: i64 = tx
+        .query_row(
+            "SELECT id FROM users WHERE role = 'admin' AND password_hash = ?1",
+            params![DEFAULT_ADMIN_HASH],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::validation("First-run setup has already been completed"))?;
+
+    auth::check_password_strength(new_admin_password)?;
+    let name = pharmacy.name.trim();
+    if name.is_empty() {
+        return Err(AppError::validation("The pharmacy name is required"));
+    }
+
+    let hash = auth::hash_password(new_admin_password)?;
+    tx.execute(
+        "UPDATE users SET password_hash = ?1 WHERE id = ?2",
+        params![hash, admin_id],
+    )?;
+    tx.execute(
+        "UPDATE pharmacy_settings SET name = ?1, address = ?2, phone = ?3, email = ?4,
+             gstin = ?5, drug_license_no = ?6, state_code = ?7, city = ?8, pincode = ?9
+         WHERE id = 1",
+        params![
+            name,
+            pharmacy.address.trim(),
+            pharmacy.phone.trim(),
+            pharmacy
+                .email
+                .as_deref()
+                .map(str::trim)
+                .filter(|e| !e.is_empty()),
+            pharmacy.gstin.trim().to_uppercase(),
+            pharmacy.drug_license_no.trim(),
+            pharmacy.state_code.trim(),
+            pharmacy.city.trim(),
+            pharmacy.pincode.trim(),
+        ],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::migrated_db;
+
+    fn profile() -> PharmacySettingsInput {
+        PharmacySettingsInput {
+            name: "City Pharmacy".into(),
+            address: "12 MG Road".into(),
+            phone: "9800000000".into(),
+            email: None,
+            gstin: "27aapfu0939f1zv".into(),
+            drug_license_no: "MH-123".into(),
+            state_code: "27".into(),
+            city: "Pune".into(),
+            pincode: "411001".into(),
+        }
+    }
+
+    #[test]
+    fn setup_replaces_the_default_hash_and_runs_only_once() {
+        let (_dir, db) = migrated_db();
+
+        db.with_tx(|tx| complete_first_run(tx, "str0ng-pass", &profile()))
+            .unwrap();
+
+        let conn = db.connect().unwrap();
+        let (hash, name, gstin): (String, String, String) = conn
+            .query_row(
+                "SELECT u.password_hash, p.name, p.gstin
+                 FROM users u, pharmacy_settings p WHERE u.username = 'admin'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert!(bcrypt::verify("str0ng-pass", &hash).unwrap());
+        assert_eq!(
+            (name.as_str(), gstin.as_str()),
+            ("City Pharmacy", "27AAPFU0939F1ZV")
+        );
+
+        let err = db
+            .with_tx(|tx| complete_first_run(tx, "another-pass", &profile()))
+            .unwrap_err();
+        assert!(err.to_string().contains("already been completed"), "{err}");
+    }
+
+    #[test]
+    fn weak_passwords_are_rejected() {
+        let (_dir, db) = migrated_db();
+
+        let err = db
+            .with_tx(|tx| complete_first_run(tx, "short", &profile()))
+            .unwrap_err();
+        assert!(err.to_string().contains("at least 8"), "{err}");
+
+        let err = db
+            .with_tx(|tx| complete_first_run(tx, "admin123", &profile()))
+            .unwrap_err();
+        assert!(err.to_string().contains("default password"), "{err}");
+    }
+}
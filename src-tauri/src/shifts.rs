@@ -0,0 +1,423 @@
+//! Cash drawer shifts: a cashier opens one with a counted float, every sale
+//! they ring up is tagged with it, and closing it compares what the drawer
+//! should hold with what was counted.
+//!
+//! Sales are tagged with their shift. Refunds, customer receipts, expenses
+//! and supplier payments are not, so they are taken from the drawer of the
+//! cashier who recorded them while the shift was open.
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::auth::{self, permission};
+use crate::db::{ChangeOp, Entity, Tx};
+use crate::error::{AppError, AppResult};
+use crate::reports::PaymentModeTotals;
+use crate::sync;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Shift {
+    pub id: i64,
+    pub user_id: i64,
+    pub cashier: String,
+    pub opened_at: String,
+    pub opening_float_paise: i64,
+    /// `None` while the shift is open.
+    pub closed_at: Option<String>,
+    pub closed_by: Option<i64>,
+    pub notes: Option<String>,
+}
+
+/// What the drawer holds, or should hold, by mode; credit never reaches it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DrawerTotals {
+    pub cash_paise: i64,
+    pub card_paise: i64,
+    pub upi_paise: i64,
+}
+
+impl DrawerTotals {
+    fn plus(self, other: Self) -> Self {
+        Self {
+            cash_paise: self.cash_paise + other.cash_paise,
+            card_paise: self.card_paise + other.card_paise,
+            upi_paise: self.upi_paise + other.upi_paise,
+        }
+    }
+
+    fn less(self, other: Self) -> Self {
+        Self {
+            cash_paise: self.cash_paise - other.cash_paise,
+            card_paise: self.card_paise - other.card_paise,
+            upi_paise: self.upi_paise - other.upi_paise,
+        }
+    }
+}
+
+impl From<PaymentModeTotals> for DrawerTotals {
+    fn from(totals: PaymentModeTotals) -> Self {
+        Self {
+            cash_paise: totals.cash_paise,
+            card_paise: totals.card_paise,
+            upi_paise: totals.upi_paise,
+        }
+    }
+}
+
+/// The takings of a shift. For an open shift this is the X-report: the
+/// expected totals so far, with nothing counted.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ZReport {
+    pub shift: Shift,
+    pub sale_count: i64,
+    pub sales_total_paise: i64,
+    /// Sale payments, split tenders counted towards each mode.
+    pub sales: PaymentModeTotals,
+    /// Customer payments against their credit.
+    pub receipts: PaymentModeTotals,
+    pub refunds: PaymentModeTotals,
+    /// Expenses and supplier payments.
+    pub paid_out: PaymentModeTotals,
+    /// The float plus cash taken less cash paid out; card and UPI as taken
+    /// less refunded or paid out.
+    pub expected: DrawerTotals,
+    pub counted: Option<DrawerTotals>,
+    /// Counted less expected: a shortfall is negative.
+    pub variance: Option<DrawerTotals>,
+}
+
+const SHIFT_COLUMNS: &str = "s.id, s.user_id, u.full_name, s.opened_at,
+    s.opening_float_paise, s.closed_at, s.closed_by, s.notes";
+
+fn shift_from_row(row: &Row) -> rusqlite::Result<Shift> {
+    Ok(Shift {
+        id: row.get(0)?,
+        user_id: row.get(1)?,
+        cashier: row.get(2)?,
+        opened_at: row.get(3)?,
+        opening_float_paise: row.get(4)?,
+        closed_at: row.get(5)?,
+        closed_by: row.get(6)?,
+        notes: row.get(7)?,
+    })
+}
+
+fn get_shift(conn: &Connection, shift_id: i64) -> AppResult<Shift> {
+    conn.query_row(
+        &format!(
+            "SELECT {SHIFT_COLUMNS} FROM shifts s JOIN users u ON u.id = s.user_id
+             WHERE s.id = ?1"
+        ),
+        params![shift_id],
+        shift_from_row,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::not_found(format!("Shift {shift_id}")))
+}
+
+/// The shift `user_id` has open, if any.
+pub fn current_shift(conn: &Connection, user_id: i64) -> AppResult<Option<Shift>> {
+    Ok(conn
+        .query_row(
+            &format!(
+                "SELECT {SHIFT_COLUMNS} FROM shifts s JOIN users u ON u.id = s.user_id
+                 WHERE s.user_id = ?1 AND s.closed_at IS NULL"
+            ),
+            params![user_id],
+            shift_from_row,
+        )
+        .optional()?)
+}
+
+/// The id of the shift a sale rung up by `user_id` belongs to.
+pub(crate) fn open_shift_id(conn: &Connection, user_id: i64) -> AppResult<Option<i64>> {
+    Ok(conn
+        .query_row(
+            "SELECT id FROM shifts WHERE user_id = ?1 AND closed_at IS NULL",
+            params![user_id],
+            |row| row.get(0),
+        )
+        .optional()?)
+}
+
+/// Start a shift for `user_id` with `opening_float_paise` counted into the
+/// drawer.
+pub fn open_shift(
+    tx: &Tx,
+    user_id: i64,
+    opening_float_paise: i64,
+    notes: Option<&str>,
+) -> AppResult<Shift> {
+    auth::active_role(tx, user_id)?;
+    if opening_float_paise < 0 {
+        return Err(AppError::validation("The opening float cannot be negative"));
+    }
+    if let Some(open) = current_shift(tx, user_id)? {
+        return Err(AppError::validation(format!(
+            "Shift {} opened at {} is still open; close it first",
+            open.id, open.opened_at
+        )));
+    }
+    let notes = notes.map(str::trim).filter(|n| !n.is_empty());
+    tx.execute(
+        "INSERT INTO shifts (user_id, opening_float_paise, notes) VALUES (?1, ?2, ?3)",
+        params![user_id, opening_float_paise, notes],
+    )?;
+    let shift = get_shift(tx, tx.last_insert_rowid())?;
+    tx.changed(Entity::Shift, ChangeOp::Insert, shift.id);
+    sync::enqueue(
+        tx,
+        "open_shift",
+        &json!({ "shiftId": shift.id, "userId": user_id, "openingFloatPaise": opening_float_paise }),
+    )?;
+    Ok(shift)
+}
+
+/// Everything that went through the drawer of `shift` until `until`.
+fn takings(conn: &Connection, shift: &Shift, until: &str) -> AppResult<ZReport> {
+    let (sale_count, sales_total_paise) = conn.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(grand_total_paise), 0) FROM sales WHERE shift_id = ?1",
+        params![shift.id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+
+    let mut sales = PaymentModeTotals::default();
+    let mut stmt = conn.prepare(
+        "SELECT sp.payment_mode, SUM(sp.amount_paise)
+         FROM sale_payments sp
+         JOIN sales s ON s.id = sp.sale_id
+         WHERE s.shift_id = ?1
+         GROUP BY sp.payment_mode",
+    )?;
+    let mut rows = stmt.query(params![shift.id])?;
+    while let Some(row) = rows.next()? {
+        sales.add(&row.get::<_, String>(0)?, row.get(1)?);
+    }
+
+    // The rest by who recorded it while the shift was open.
+    let by_mode = |sql: &str| -> AppResult<PaymentModeTotals> {
+        let mut totals = PaymentModeTotals::default();
+        let mut stmt = conn.prepare(sql)?;
+        let mut rows = stmt.query(params![shift.user_id, shift.opened_at, until])?;
+        while let Some(row) = rows.next()? {
+            totals.add(&row.get::<_, String>(0)?, row.get(1)?);
+        }
+        Ok(totals)
+    };
+    let receipts = by_mode(
+        "SELECT payment_mode, SUM(amount_paise) FROM customer_payments
+         WHERE user_id = ?1 AND created_at BETWEEN ?2 AND ?3
+         GROUP BY payment_mode",
+    )?;
+    let refunds = by_mode(
+        "SELECT refund_mode, SUM(refund_total_paise) FROM sale_returns
+         WHERE user_id = ?1 AND created_at BETWEEN ?2 AND ?3
+         GROUP BY refund_mode",
+    )?;
+    let paid_out = by_mode(
+        "SELECT mode, SUM(amount) FROM (
+             SELECT payment_mode AS mode, amount_paise AS amount FROM expenses
+             WHERE user_id = ?1 AND created_at BETWEEN ?2 AND ?3
+             UNION ALL
+             SELECT payment_mode, amount_paise FROM supplier_payments
+             WHERE user_id = ?1 AND created_at BETWEEN ?2 AND ?3
+         )
+         GROUP BY mode",
+    )?;
+
+    let mut expected = DrawerTotals::from(sales)
+        .plus(receipts.into())
+        .less(refunds.into())
+        .less(paid_out.into());
+    expected.cash_paise += shift.opening_float_paise;
+
+    Ok(ZReport {
+        shift: shift.clone(),
+        sale_count,
+        sales_total_paise,
+        sales,
+        receipts,
+        refunds,
+        paid_out,
+        expected,
+        counted: None,
+        variance: None,
+    })
+}
+
+/// The Z-report of a closed shift as it was closed, or the running totals
+/// of an open one.
+pub fn shift_report(conn: &Connection, shift_id: i64) -> AppResult<ZReport> {
+    let shift = get_shift(conn, shift_id)?;
+    let Some(closed_at) = shift.closed_at.clone() else {
+        let now: String = conn.query_row("SELECT datetime('now')", [], |row| row.get(0))?;
+        return takings(conn, &shift, &now);
+    };
+    let mut report = takings(conn, &shift, &closed_at)?;
+    let (expected, counted) = conn.query_row(
+        "SELECT expected_cash_paise, expected_card_paise, expected_upi_paise,
+             counted_cash_paise, counted_card_paise, counted_upi_paise
+         FROM shifts WHERE id = ?1",
+        params![shift_id],
+        |row| {
+            Ok((
+                DrawerTotals {
+                    cash_paise: row.get(0)?,
+                    card_paise: row.get(1)?,
+                    upi_paise: row.get(2)?,
+                },
+                DrawerTotals {
+                    cash_paise: row.get(3)?,
+                    card_paise: row.get(4)?,
+                    upi_paise: row.get(5)?,
+                },
+            ))
+        },
+    )?;
+    // Sales amended after the close do not move what was expected then.
+    report.expected = expected;
+    report.counted = Some(counted);
+    report.variance = Some(counted.less(expected));
+    Ok(report)
+}
+
+/// Close `shift_id` with what was `counted` in the drawer, recording what
+/// was expected alongside. Cashiers close their own shifts; closing anyone
+/// else's needs [`permission::SHIFTS_MANAGE`].
+pub fn close_shift(
+    tx: &Tx,
+    shift_id: i64,
+    counted: DrawerTotals,
+    notes: Option<&str>,
+    user_id: i64,
+) -> AppResult<ZReport> {
+    let shift = get_shift(tx, shift_id)?;
+    if shift.user_id == user_id {
+        auth::active_role(tx, user_id)?;
+    } else {
+        auth::require_permission(tx, user_id, permission::SHIFTS_MANAGE)?;
+    }
+    if shift.closed_at.is_some() {
+        return Err(AppError::validation(format!(
+            "Shift {shift_id} is already closed"
+        )));
+    }
+    if counted.cash_paise < 0 || counted.card_paise < 0 || counted.upi_paise < 0 {
+        return Err(AppError::validation("Counted totals cannot be negative"));
+    }
+
+    let closed_at: String = tx.query_row("SELECT datetime('now')", [], |row| row.get(0))?;
+    let expected = takings(tx, &shift, &closed_at)?.expected;
+    let notes = notes
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .map(str::to_owned)
+        .or(shift.notes);
+    tx.execute(
+        "UPDATE shifts SET closed_at = ?1, closed_by = ?2,
+             expected_cash_paise = ?3, expected_card_paise = ?4, expected_upi_paise = ?5,
+             counted_cash_paise = ?6, counted_card_paise = ?7, counted_upi_paise = ?8,
+             notes = ?9
+         WHERE id = ?10",
+        params![
+            closed_at,
+            user_id,
+            expected.cash_paise,
+            expected.card_paise,
+            expected.upi_paise,
+            counted.cash_paise,
+            counted.card_paise,
+            counted.upi_paise,
+            notes,
+            shift_id
+        ],
+    )?;
+    tx.changed(Entity::Shift, ChangeOp::Update, shift_id);
+    sync::enqueue(
+        tx,
+        "close_shift",
+        &json!({ "shiftId": shift_id, "counted": counted, "closedBy": user_id }),
+    )?;
+    shift_report(tx, shift_id)
+}
+
+/// Shifts opened from `from_date` to `to_date`, inclusive, latest first.
+pub fn list_shifts(conn: &Connection, from_date: &str, to_date: &str) -> AppResult<Vec<Shift>> {
+    crate::reports::validate_date_range(conn, from_date, to_date)?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {SHIFT_COLUMNS} FROM shifts s JOIN users u ON u.id = s.user_id
+         WHERE date(s.opened_at) BETWEEN ?1 AND ?2
+         ORDER BY s.opened_at DESC, s.id DESC"
+    ))?;
+    let rows = stmt.query_map(params![from_date, to_date], shift_from_row)?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{insert_batch, insert_medicine, migrated_db, paid_sale};
+    use crate::sales::{create_sale, PaymentMode};
+
+    const ADMIN: i64 = 1;
+
+    #[test]
+    fn closing_a_shift_records_the_variance_of_each_mode() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+90 days", 5_000, 10);
+        // Rung up before the shift, so not in its drawer.
+        let sale = paid_sale(&conn, None, PaymentMode::Cash, batch, 1);
+        db.with_tx(|tx| create_sale(tx, &sale)).unwrap();
+
+        let shift = db.with_tx(|tx| open_shift(tx, ADMIN, 2_000, None)).unwrap();
+        let err = db.with_tx(|tx| open_shift(tx, ADMIN, 0, None)).unwrap_err();
+        assert!(err.to_string().contains("still open"), "{err}");
+        for mode in [PaymentMode::Cash, PaymentMode::Upi] {
+            let sale = paid_sale(&conn, None, mode, batch, 1);
+            db.with_tx(|tx| create_sale(tx, &sale)).unwrap();
+        }
+        conn.execute(
+            "INSERT INTO expenses (category_id, amount_paise, expense_date, payment_mode, user_id)
+             VALUES (1, 500, date('now'), 'cash', ?1)",
+            params![ADMIN],
+        )
+        .unwrap();
+
+        let running = shift_report(&conn, shift.id).unwrap();
+        assert_eq!(running.sale_count, 2);
+        assert_eq!(running.counted, None);
+
+        let counted = DrawerTotals {
+            cash_paise: 6_400,
+            card_paise: 0,
+            upi_paise: 5_000,
+        };
+        let report = db
+            .with_tx(|tx| close_shift(tx, shift.id, counted, Some("Short by a note"), ADMIN))
+            .unwrap();
+
+        assert_eq!(report.sales_total_paise, 10_000);
+        assert_eq!(report.paid_out.cash_paise, 500);
+        assert_eq!(
+            report.expected,
+            DrawerTotals {
+                cash_paise: 6_500,
+                card_paise: 0,
+                upi_paise: 5_000,
+            }
+        );
+        assert_eq!(report.variance.unwrap().cash_paise, -100);
+        assert_eq!(report.shift.notes.as_deref(), Some("Short by a note"));
+        assert_eq!(current_shift(&conn, ADMIN).unwrap(), None);
+        let err = db
+            .with_tx(|tx| close_shift(tx, shift.id, counted, None, ADMIN))
+            .unwrap_err();
+        assert!(err.to_string().contains("already closed"), "{err}");
+    }
+}
@@ -0,0 +1,448 @@
+//! Physical stock takes: snapshot the books, count the shelves, post the
+//! difference.
+//!
+//! Starting a take records what every batch in stock should hold. Counts
+//! are entered per batch, usually by scanning its barcode, and may be
+//! corrected until the take is finalized. Finalizing posts each variance as
+//! a `count_error` stock adjustment; batches nobody counted are left as
+//! they are.
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::auth::{self, permission};
+use crate::db::{ChangeOp, Entity, Tx};
+use crate::error::{AppError, AppResult};
+use crate::inventory::{self, AdjustmentReason};
+use crate::medicines;
+use crate::sync;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StockTakeStatus {
+    Open,
+    Finalized,
+}
+
+impl StockTakeStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Finalized => "finalized",
+        }
+    }
+
+    pub fn parse(s: &str) -> AppResult<Self> {
+        match s {
+            "open" => Ok(Self::Open),
+            "finalized" => Ok(Self::Finalized),
+            other => Err(AppError::validation(format!(
+                "Unknown stock take status '{other}'"
+            ))),
+        }
+    }
+}
+
+/// One batch on a stock take sheet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StockTakeLine {
+    pub batch_id: i64,
+    pub medicine_id: i64,
+    pub medicine_name: String,
+    pub batch_number: String,
+    pub expected_quantity: i64,
+    /// `None` until the batch has been counted.
+    pub counted_quantity: Option<i64>,
+    pub counted_at: Option<String>,
+    /// The adjustment that posted this line's variance, once finalized.
+    pub adjustment_id: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StockTake {
+    pub id: i64,
+    pub branch_id: Option<i64>,
+    pub status: StockTakeStatus,
+    pub notes: Option<String>,
+    pub started_by: i64,
+    pub started_at: String,
+    pub finalized_at: Option<String>,
+    pub lines: Vec<StockTakeLine>,
+}
+
+/// A counted batch whose count differs from the books.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VarianceLine {
+    pub batch_id: i64,
+    pub medicine_name: String,
+    pub batch_number: String,
+    pub expected_quantity: i64,
+    pub counted_quantity: i64,
+    /// Counted less expected: negative for a shortage.
+    pub variance: i64,
+    /// The variance at the batch's cost price.
+    pub variance_value_paise: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VarianceReport {
+    pub stock_take_id: i64,
+    pub counted_batches: usize,
+    /// Batches on the sheet that were never counted.
+    pub uncounted_batches: usize,
+    pub lines: Vec<VarianceLine>,
+    /// Cost of the units found missing, as a positive amount.
+    pub shortage_value_paise: i64,
+    /// Cost of the units found over the books.
+    pub excess_value_paise: i64,
+}
+
+fn line_from_row(row: &Row) -> rusqlite::Result<StockTakeLine> {
+    Ok(StockTakeLine {
+        batch_id: row.get(0)?,
+        medicine_id: row.get(1)?,
+        medicine_name: row.get(2)?,
+        batch_number: row.get(3)?,
+        expected_quantity: row.get(4)?,
+        counted_quantity: row.get(5)?,
+        counted_at: row.get(6)?,
+        adjustment_id: row.get(7)?,
+    })
+}
+
+/// The sheet of a stock take, or just the line of `batch_id`.
+fn stock_take_lines(
+    conn: &Connection,
+    stock_take_id: i64,
+    batch_id: Option<i64>,
+) -> AppResult<Vec<StockTakeLine>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT l.batch_id, b.medicine_id, m.name, b.batch_number, l.expected_quantity,
+             l.counted_quantity, l.counted_at, l.adjustment_id
+         FROM stock_take_lines l
+         JOIN batches b ON b.id = l.batch_id
+         JOIN medicines m ON m.id = b.medicine_id
+         WHERE l.stock_take_id = ?1 AND (?2 IS NULL OR l.batch_id = ?2)
+         ORDER BY m.name, b.expiry_date, b.id",
+    )?;
+    let lines = stmt.query_map(params![stock_take_id, batch_id], line_from_row)?;
+    Ok(lines.collect::<Result<_, _>>()?)
+}
+
+fn take_from_row(row: &Row) -> AppResult<StockTake> {
+    let status: String = row.get(2)?;
+    Ok(StockTake {
+        id: row.get(0)?,
+        branch_id: row.get(1)?,
+        status: StockTakeStatus::parse(&status)?,
+        notes: row.get(3)?,
+        started_by: row.get(4)?,
+        started_at: row.get(5)?,
+        finalized_at: row.get(6)?,
+        lines: Vec::new(),
+    })
+}
+
+/// Load a stock take with its sheet.
+pub fn get_stock_take(conn: &Connection, stock_take_id: i64) -> AppResult<StockTake> {
+    let mut stmt = conn.prepare(
+        "SELECT id, branch_id, status, notes, started_by, started_at, finalized_at
+         FROM stock_takes WHERE id = ?1",
+    )?;
+    let mut rows = stmt.query(params![stock_take_id])?;
+    let mut take = rows
+        .next()?
+        .map(take_from_row)
+        .transpose()?
+        .ok_or_else(|| AppError::not_found(format!("Stock take {stock_take_id}")))?;
+    take.lines = stock_take_lines(conn, stock_take_id, None)?;
+    Ok(take)
+}
+
+/// Open a stock take of every batch in stock, at one branch or all of them.
+///
+/// Only one take may be open for the same branch at a time.
+pub fn start_stock_take(
+    tx: &Tx,
+    branch_id: Option<i64>,
+    notes: Option<&str>,
+    user_id: i64,
+) -> AppResult<StockTake> {
+    auth::require_permission(tx, user_id, permission::INVENTORY_ADJUST)?;
+    let already_open: Option<i64> = tx
+        .query_row(
+            "SELECT id FROM stock_takes
+             WHERE status = ?2 AND (branch_id IS ?1 OR branch_id IS NULL OR ?1 IS NULL)",
+            params![branch_id, StockTakeStatus::Open.as_str()],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if let Some(open) = already_open {
+        return Err(AppError::validation(format!(
+            "Stock take {open} is still open; finalize it first"
+        )));
+    }
+    let notes = notes.map(str::trim).filter(|n| !n.is_empty());
+    tx.execute(
+        "INSERT INTO stock_takes (branch_id, notes, started_by) VALUES (?1, ?2, ?3)",
+        params![branch_id, notes, user_id],
+    )?;
+    let stock_take_id = tx.last_insert_rowid();
+    tx.execute(
+        "INSERT INTO stock_take_lines (stock_take_id, batch_id, expected_quantity)
+         SELECT ?1, id, quantity FROM batches
+         WHERE quantity > 0 AND (?2 IS NULL OR COALESCE(branch_id, 1) = ?2)",
+        params![stock_take_id, branch_id],
+    )?;
+    tx.changed(Entity::StockTake, ChangeOp::Insert, stock_take_id);
+    sync::enqueue(
+        tx,
+        "start_stock_take",
+        &json!({ "stockTakeId": stock_take_id, "branchId": branch_id, "notes": notes }),
+    )?;
+    get_stock_take(tx, stock_take_id)
+}
+
+/// The branch an open stock take counts; fails once it is finalized.
+fn require_open(tx: &Tx, stock_take_id: i64) -> AppResult<Option<i64>> {
+    let (status, branch_id): (String, Option<i64>) = tx
+        .query_row(
+            "SELECT status, branch_id FROM stock_takes WHERE id = ?1",
+            params![stock_take_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Stock take {stock_take_id}")))?;
+    if StockTakeStatus::parse(&status)? != StockTakeStatus::Open {
+        return Err(AppError::validation(format!(
+            "Stock take {stock_take_id} has been finalized"
+        )));
+    }
+    Ok(branch_id)
+}
+
+/// Enter the counted quantity of a batch, replacing any earlier count.
+///
+/// A batch missing from the sheet, because it was out of stock on the
+/// books when the take began, is added with what the books hold now.
+pub fn record_count(
+    tx: &Tx,
+    stock_take_id: i64,
+    batch_id: i64,
+    counted_quantity: i64,
+    user_id: i64,
+) -> AppResult<StockTakeLine> {
+    auth::require_permission(tx, user_id, permission::INVENTORY_ADJUST)?;
+    let branch_id = require_open(tx, stock_take_id)?;
+    if counted_quantity < 0 {
+        return Err(AppError::validation(
+            "The counted quantity cannot be negative",
+        ));
+    }
+    let batch_branch: i64 = tx
+        .query_row(
+            "SELECT COALESCE(branch_id, 1) FROM batches WHERE id = ?1",
+            params![batch_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Batch {batch_id}")))?;
+    if branch_id.is_some_and(|branch| branch != batch_branch) {
+        return Err(AppError::validation(format!(
+            "Batch {batch_id} is stocked at another branch"
+        )));
+    }
+
+    tx.execute(
+        "INSERT INTO stock_take_lines (stock_take_id, batch_id, expected_quantity,
+             counted_quantity, counted_by, counted_at)
+         SELECT ?1, id, quantity, ?3, ?4, datetime('now') FROM batches WHERE id = ?2
+         ON CONFLICT (stock_take_id, batch_id) DO UPDATE SET
+             counted_quantity = excluded.counted_quantity,
+             counted_by = excluded.counted_by,
+             counted_at = excluded.counted_at",
+        params![stock_take_id, batch_id, counted_quantity, user_id],
+    )?;
+    tx.changed(Entity::StockTake, ChangeOp::Update, stock_take_id);
+    sync::enqueue(
+        tx,
+        "record_stock_count",
+        &json!({
+            "stockTakeId": stock_take_id,
+            "batchId": batch_id,
+            "countedQuantity": counted_quantity,
+        }),
+    )?;
+    stock_take_lines(tx, stock_take_id, Some(batch_id))?
+        .pop()
+        .ok_or_else(|| AppError::not_found(format!("Batch {batch_id}")))
+}
+
+/// [`record_count`] for the batch behind a scanned barcode.
+pub fn record_scanned_count(
+    tx: &Tx,
+    stock_take_id: i64,
+    barcode: &str,
+    counted_quantity: i64,
+    user_id: i64,
+) -> AppResult<StockTakeLine> {
+    let scan = medicines::lookup_by_barcode(tx, barcode)?
+        .ok_or_else(|| AppError::not_found(format!("Barcode {}", barcode.trim())))?;
+    record_count(tx, stock_take_id, scan.batch_id, counted_quantity, user_id)
+}
+
+/// The counted lines that differ from the books, with their cost.
+pub fn variance_report(conn: &Connection, stock_take_id: i64) -> AppResult<VarianceReport> {
+    let take = get_stock_take(conn, stock_take_id)?;
+    let mut cost = conn.prepare_cached("SELECT cost_price_paise FROM batches WHERE id = ?1")?;
+    let mut report = VarianceReport {
+        stock_take_id,
+        counted_batches: 0,
+        uncounted_batches: 0,
+        lines: Vec::new(),
+        shortage_value_paise: 0,
+        excess_value_paise: 0,
+    };
+    for line in take.lines {
+        let Some(counted) = line.counted_quantity else {
+            report.uncounted_batches += 1;
+            continue;
+        };
+        report.counted_batches += 1;
+        let variance = counted - line.expected_quantity;
+        if variance == 0 {
+            continue;
+        }
+        let unit_cost: i64 = cost.query_row(params![line.batch_id], |row| row.get(0))?;
+        let value = variance * unit_cost;
+        if value < 0 {
+            report.shortage_value_paise -= value;
+        } else {
+            report.excess_value_paise += value;
+        }
+        report.lines.push(VarianceLine {
+            batch_id: line.batch_id,
+            medicine_name: line.medicine_name,
+            batch_number: line.batch_number,
+            expected_quantity: line.expected_quantity,
+            counted_quantity: counted,
+            variance,
+            variance_value_paise: value,
+        });
+    }
+    Ok(report)
+}
+
+/// Close a stock take and post every variance as a stock adjustment.
+///
+/// Each variance is applied to the batch as it stands now rather than set
+/// outright, so stock sold or received while the count was under way is
+/// not undone.
+pub fn finalize_stock_take(tx: &Tx, stock_take_id: i64, user_id: i64) -> AppResult<VarianceReport> {
+    auth::require_permission(tx, user_id, permission::INVENTORY_ADJUST)?;
+    require_open(tx, stock_take_id)?;
+    let report = variance_report(tx, stock_take_id)?;
+
+    let mut current = tx.prepare_cached("SELECT quantity FROM batches WHERE id = ?1")?;
+    let mut link = tx.prepare_cached(
+        "UPDATE stock_take_lines SET adjustment_id = ?1 WHERE stock_take_id = ?2 AND batch_id = ?3",
+    )?;
+    for line in &report.lines {
+        let on_hand: i64 = current.query_row(params![line.batch_id], |row| row.get(0))?;
+        let remarks = format!(
+            "Stock take {stock_take_id}: counted {}, books {}",
+            line.counted_quantity, line.expected_quantity
+        );
+        let adjustment = inventory::adjust_stock(
+            tx,
+            line.batch_id,
+            (on_hand + line.variance).max(0),
+            AdjustmentReason::CountError,
+            &remarks,
+            user_id,
+        )?;
+        link.execute(params![adjustment.id, stock_take_id, line.batch_id])?;
+    }
+    tx.execute(
+        "UPDATE stock_takes SET status = ?1, finalized_by = ?2, finalized_at = datetime('now')
+         WHERE id = ?3",
+        params![StockTakeStatus::Finalized.as_str(), user_id, stock_take_id],
+    )?;
+    tx.changed(Entity::StockTake, ChangeOp::Update, stock_take_id);
+    sync::enqueue(
+        tx,
+        "finalize_stock_take",
+        &json!({ "stockTakeId": stock_take_id }),
+    )?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{
+        batch_quantity, insert_batch, insert_medicine, migrated_db, paid_sale,
+    };
+    use crate::sales::{create_sale, PaymentMode};
+
+    const ADMIN: i64 = 1;
+
+    #[test]
+    fn finalizing_posts_variances_on_top_of_sales_made_during_the_count() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let short = insert_batch(&conn, para, "+30 days", 1_000, 10);
+        let exact = insert_batch(&conn, para, "+60 days", 1_000, 5);
+        let skipped = insert_batch(&conn, para, "+90 days", 1_000, 4);
+        conn.execute(
+            "UPDATE batches SET barcode = '8901234560012', cost_price_paise = 600 WHERE id = ?1",
+            [short],
+        )
+        .unwrap();
+
+        let take = db
+            .with_tx(|tx| start_stock_take(tx, None, Some("Year end"), ADMIN))
+            .unwrap();
+        assert_eq!(take.lines.len(), 3);
+        let err = db
+            .with_tx(|tx| start_stock_take(tx, Some(1), None, ADMIN))
+            .unwrap_err();
+        assert!(err.to_string().contains("still open"), "{err}");
+
+        db.with_tx(|tx| record_scanned_count(tx, take.id, "8901234560012", 9, ADMIN))
+            .unwrap();
+        let line = db
+            .with_tx(|tx| record_scanned_count(tx, take.id, " 8901234560012 ", 8, ADMIN))
+            .unwrap();
+        assert_eq!(line.counted_quantity, Some(8));
+        db.with_tx(|tx| record_count(tx, take.id, exact, 5, ADMIN))
+            .unwrap();
+        let sale = paid_sale(&conn, None, PaymentMode::Cash, short, 1);
+        db.with_tx(|tx| create_sale(tx, &sale)).unwrap();
+
+        let report = db
+            .with_tx(|tx| finalize_stock_take(tx, take.id, ADMIN))
+            .unwrap();
+
+        assert_eq!((report.counted_batches, report.uncounted_batches), (2, 1));
+        assert_eq!(report.lines.len(), 1);
+        assert_eq!(report.lines[0].variance, -2);
+        assert_eq!(report.shortage_value_paise, 1_200);
+        assert_eq!(batch_quantity(&conn, short), 7);
+        assert_eq!(batch_quantity(&conn, exact), 5);
+        assert_eq!(batch_quantity(&conn, skipped), 4);
+        let take = get_stock_take(&conn, take.id).unwrap();
+        assert_eq!(take.status, StockTakeStatus::Finalized);
+        assert!(take.lines.iter().any(|l| l.adjustment_id.is_some()));
+        let err = db
+            .with_tx(|tx| record_count(tx, take.id, exact, 4, ADMIN))
+            .unwrap_err();
+        assert!(err.to_string().contains("has been finalized"), "{err}");
+    }
+}
@@ -0,0 +1,620 @@
+//! Supplier accounts: what we owe for purchases against what we have paid
+//! and the returns they have credited us for.
+//!
+//! A payment may be allocated to particular purchase invoices; whatever is
+//! left unallocated, and every credited debit note, settles the oldest
+//! invoices first.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::auth::{self, permission};
+use crate::db::{ChangeOp, Entity, Tx};
+use crate::error::{AppError, AppResult};
+use crate::format::rupees;
+use crate::registration::{self, Registrations};
+use crate::reports::validate_date_range;
+use crate::sales::PaymentMode;
+use crate::sync;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerEntryKind {
+    Purchase,
+    Payment,
+    /// Goods returned on a debit note the supplier has credited.
+    DebitNote,
+}
+
+/// One purchase (debit), or payment or credited return (credit), with the
+/// balance after it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LedgerEntry {
+    pub date: String,
+    pub kind: LedgerEntryKind,
+    /// Supplier invoice number, the payment reference if one was given, or
+    /// the debit note number.
+    pub reference: Option<String>,
+    pub debit_paise: i64,
+    pub credit_paise: i64,
+    /// Outstanding amount owed to the supplier after this entry.
+    pub balance_paise: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SupplierLedger {
+    pub supplier_id: i64,
+    pub supplier_name: String,
+    pub from_date: String,
+    pub to_date: String,
+    /// Outstanding before `from_date`.
+    pub opening_balance_paise: i64,
+    pub entries: Vec<LedgerEntry>,
+    pub closing_balance_paise: i64,
+}
+
+/// A supplier's details as entered on the supplier form.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SupplierInput {
+    pub name: String,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub address: Option<String>,
+    pub gst_in: Option<String>,
+    pub drug_license_no: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewSupplierPayment {
+    pub supplier_id: i64,
+    pub amount_paise: i64,
+    pub payment_mode: PaymentMode,
+    /// `YYYY-MM-DD`; today when not given.
+    #[serde(default)]
+    pub payment_date: Option<String>,
+    #[serde(default)]
+    pub reference: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// Purchase invoices the payment settles; when empty it settles the
+    /// oldest open invoices first.
+    #[serde(default)]
+    pub allocations: Vec<Allocation>,
+    /// Set from the session by the command, never taken from the client.
+    #[serde(default, skip_deserializing)]
+    pub user_id: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Allocation {
+    pub purchase_id: i64,
+    pub amount_paise: i64,
+}
+
+/// A purchase invoice and what is still owed on it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenPurchase {
+    pub purchase_id: i64,
+    pub invoice_number: String,
+    /// `YYYY-MM-DD`.
+    pub purchase_date: String,
+    /// Days since the purchase.
+    pub age_days: i64,
+    pub total_paise: i64,
+    pub due_paise: i64,
+}
+
+/// What we owe one supplier, invoice by invoice and by age.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SupplierPayables {
+    pub supplier_id: i64,
+    pub supplier_name: String,
+    /// Invoices with something still due, oldest first.
+    pub invoices: Vec<OpenPurchase>,
+    /// Up to 30 days old.
+    pub current_paise: i64,
+    pub days_31_to_60_paise: i64,
+    pub over_60_days_paise: i64,
+    /// Paid or credited beyond every invoice.
+    pub advance_paise: i64,
+    /// Owed less any advance.
+    pub outstanding_paise: i64,
+}
+
+fn check_supplier(tx: &Tx, supplier: &SupplierInput, user_id: i64) -> AppResult<()> {
+    auth::require_permission(tx, user_id, permission::PURCHASES_ORDER)?;
+    if supplier.name.trim().is_empty() {
+        return Err(AppError::validation("Supplier name is required"));
+    }
+    registration::check_registrations(&Registrations {
+        gstin: supplier.gst_in.clone(),
+        drug_license_no: supplier.drug_license_no.clone(),
+        ..Registrations::default()
+    })
+}
+
+/// Add a supplier, returning its id.
+pub fn create_supplier(tx: &Tx, supplier: &SupplierInput, user_id: i64) -> AppResult<i64> {
+    check_supplier(tx, supplier, user_id)?;
+    tx.execute(
+        "INSERT INTO suppliers (name, phone, email, address, gst_in, drug_license_no)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            supplier.name.trim(),
+            supplier.phone,
+            supplier.email,
+            supplier.address,
+            supplier.gst_in,
+            supplier.drug_license_no
+        ],
+    )?;
+    let supplier_id = tx.last_insert_rowid();
+    tx.changed(Entity::Supplier, ChangeOp::Insert, supplier_id);
+    sync::enqueue(
+        tx,
+        "create_supplier",
+        &json!({ "supplierId": supplier_id, "name": supplier.name.trim() }),
+    )?;
+    Ok(supplier_id)
+}
+
+/// Replace a supplier's details.
+pub fn update_supplier(
+    tx: &Tx,
+    supplier_id: i64,
+    supplier: &SupplierInput,
+    user_id: i64,
+) -> AppResult<()> {
+    check_supplier(tx, supplier, user_id)?;
+    let updated = tx.execute(
+        "UPDATE suppliers SET name = ?1, phone = ?2, email = ?3, address = ?4, gst_in = ?5,
+             drug_license_no = ?6
+         WHERE id = ?7",
+        params![
+            supplier.name.trim(),
+            supplier.phone,
+            supplier.email,
+            supplier.address,
+            supplier.gst_in,
+            supplier.drug_license_no,
+            supplier_id
+        ],
+    )?;
+    if updated == 0 {
+        return Err(AppError::not_found(format!("Supplier {supplier_id}")));
+    }
+    tx.changed(Entity::Supplier, ChangeOp::Update, supplier_id);
+    sync::enqueue(
+        tx,
+        "update_supplier",
+        &json!({ "supplierId": supplier_id, "name": supplier.name.trim() }),
+    )
+}
+
+/// Purchases, payments and credited returns for a supplier between two
+/// dates, inclusive.
+///
+/// Entries run in date order; on a shared date purchases come before
+/// payments and returns, so the running balance does not dip below what is
+/// owed. A return counts from the day it was credited.
+pub fn supplier_ledger(
+    conn: &Connection,
+    supplier_id: i64,
+    from_date: &str,
+    to_date: &str,
+) -> AppResult<SupplierLedger> {
+    validate_date_range(conn, from_date, to_date)?;
+    let supplier_name = supplier_name(conn, supplier_id)?;
+
+    let opening_balance_paise: i64 = conn.query_row(
+        "SELECT
+             (SELECT COALESCE(SUM(total_paise), 0) FROM purchases
+              WHERE supplier_id = ?1 AND date(purchase_date) < ?2)
+           - (SELECT COALESCE(SUM(amount_paise), 0) FROM supplier_payments
+              WHERE supplier_id = ?1 AND date(payment_date) < ?2)
+           - (SELECT COALESCE(SUM(total_paise), 0) FROM purchase_returns
+              WHERE supplier_id = ?1 AND status = 'credited' AND date(credited_at) < ?2)",
+        params![supplier_id, from_date],
+        |row| row.get(0),
+    )?;
+
+    let mut stmt = conn.prepare(
+        "SELECT date(purchase_date) AS day, 0 AS kind, invoice_number, total_paise, id
+         FROM purchases
+         WHERE supplier_id = ?1 AND date(purchase_date) BETWEEN ?2 AND ?3
+         UNION ALL
+         SELECT date(payment_date), 1, reference, amount_paise, id
+         FROM supplier_payments
+         WHERE supplier_id = ?1 AND date(payment_date) BETWEEN ?2 AND ?3
+         UNION ALL
+         SELECT date(credited_at), 2, debit_note_number, total_paise, id
+         FROM purchase_returns
+         WHERE supplier_id = ?1 AND status = 'credited'
+             AND date(credited_at) BETWEEN ?2 AND ?3
+         ORDER BY day, kind, id",
+    )?;
+    let mut rows = stmt.query(params![supplier_id, from_date, to_date])?;
+
+    let mut balance = opening_balance_paise;
+    let mut entries = Vec::new();
+    while let Some(row) = rows.next()? {
+        let kind: i64 = row.get(1)?;
+        let amount: i64 = row.get(3)?;
+        let (kind, debit_paise, credit_paise) = match kind {
+            0 => (LedgerEntryKind::Purchase, amount, 0),
+            1 => (LedgerEntryKind::Payment, 0, amount),
+            _ => (LedgerEntryKind::DebitNote, 0, amount),
+        };
+        balance += debit_paise - credit_paise;
+        entries.push(LedgerEntry {
+            date: row.get(0)?,
+            kind,
+            reference: row.get(2)?,
+            debit_paise,
+            credit_paise,
+            balance_paise: balance,
+        });
+    }
+
+    Ok(SupplierLedger {
+        supplier_id,
+        supplier_name,
+        from_date: from_date.to_owned(),
+        to_date: to_date.to_owned(),
+        opening_balance_paise,
+        entries,
+        closing_balance_paise: balance,
+    })
+}
+
+fn supplier_name(conn: &Connection, supplier_id: i64) -> AppResult<String> {
+    conn.query_row(
+        "SELECT name FROM suppliers WHERE id = ?1",
+        params![supplier_id],
+        |row| row.get(0),
+    )
+    .optional()?
+    .ok_or_else(|| AppError::not_found(format!("Supplier {supplier_id}")))
+}
+
+/// Every purchase invoice of `supplier_id`, oldest first, with what is
+/// still due on it, and the payments and credits left over as an advance.
+fn settle_purchases(conn: &Connection, supplier_id: i64) -> AppResult<(Vec<OpenPurchase>, i64)> {
+    let mut advance: i64 = conn.query_row(
+        "SELECT
+             (SELECT COALESCE(SUM(amount_paise), 0) FROM supplier_payments
+              WHERE supplier_id = ?1)
+           - (SELECT COALESCE(SUM(a.amount_paise), 0) FROM supplier_payment_allocations a
+              JOIN supplier_payments p ON p.id = a.payment_id
+              WHERE p.supplier_id = ?1)
+           + (SELECT COALESCE(SUM(total_paise), 0) FROM purchase_returns
+              WHERE supplier_id = ?1 AND status = 'credited')",
+        params![supplier_id],
+        |row| row.get(0),
+    )?;
+    let mut stmt = conn.prepare(
+        "SELECT p.id, p.invoice_number, date(p.purchase_date),
+             CAST(julianday(date('now')) - julianday(date(p.purchase_date)) AS INTEGER),
+             p.total_paise,
+             (SELECT COALESCE(SUM(a.amount_paise), 0) FROM supplier_payment_allocations a
+              WHERE a.purchase_id = p.id)
+         FROM purchases p
+         WHERE p.supplier_id = ?1
+         ORDER BY p.purchase_date, p.id",
+    )?;
+    let rows = stmt.query_map(params![supplier_id], |row| {
+        Ok((
+            OpenPurchase {
+                purchase_id: row.get(0)?,
+                invoice_number: row.get(1)?,
+                purchase_date: row.get(2)?,
+                age_days: row.get(3)?,
+                total_paise: row.get(4)?,
+                due_paise: 0,
+            },
+            row.get::<_, i64>(5)?,
+        ))
+    })?;
+    let mut unpaid = Vec::new();
+    for row in rows {
+        let (purchase, allocated) = row?;
+        let owed = purchase.total_paise - allocated;
+        advance -= owed.min(0);
+        unpaid.push((purchase, owed.max(0)));
+    }
+    let purchases = unpaid
+        .into_iter()
+        .map(|(mut purchase, owed)| {
+            let settled = advance.min(owed);
+            advance -= settled;
+            purchase.due_paise = owed - settled;
+            purchase
+        })
+        .collect();
+    Ok((purchases, advance))
+}
+
+/// What we owe `supplier_id`: the open invoices, aged from their purchase
+/// date.
+pub fn supplier_payables(conn: &Connection, supplier_id: i64) -> AppResult<SupplierPayables> {
+    let supplier_name = supplier_name(conn, supplier_id)?;
+    let (purchases, advance_paise) = settle_purchases(conn, supplier_id)?;
+    let mut payables = SupplierPayables {
+        supplier_id,
+        supplier_name,
+        invoices: Vec::new(),
+        current_paise: 0,
+        days_31_to_60_paise: 0,
+        over_60_days_paise: 0,
+        advance_paise,
+        outstanding_paise: -advance_paise,
+    };
+    for purchase in purchases.into_iter().filter(|p| p.due_paise > 0) {
+        let bucket = match purchase.age_days {
+            ..=30 => &mut payables.current_paise,
+            31..=60 => &mut payables.days_31_to_60_paise,
+            _ => &mut payables.over_60_days_paise,
+        };
+        *bucket += purchase.due_paise;
+        payables.outstanding_paise += purchase.due_paise;
+        payables.invoices.push(purchase);
+    }
+    Ok(payables)
+}
+
+/// What we owe each supplier, leaving out those we are square with.
+pub fn payables_aging(conn: &Connection) -> AppResult<Vec<SupplierPayables>> {
+    let mut stmt = conn.prepare("SELECT id FROM suppliers ORDER BY name, id")?;
+    let ids = stmt
+        .query_map([], |row| row.get::<_, i64>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut aging = Vec::new();
+    for id in ids {
+        let payables = supplier_payables(conn, id)?;
+        if payables.outstanding_paise != 0 {
+            aging.push(payables);
+        }
+    }
+    Ok(aging)
+}
+
+/// Record a payment made to a supplier.
+///
+/// Returns what is owed to them after it.
+pub fn record_supplier_payment(
+    tx: &Tx,
+    payment: &NewSupplierPayment,
+) -> AppResult<SupplierPayables> {
+    auth::active_role(tx, payment.user_id)?;
+    supplier_name(tx, payment.supplier_id)?;
+    if payment.amount_paise <= 0 {
+        return Err(AppError::validation(
+            "Payment amount must be greater than 0",
+        ));
+    }
+    let payment_date: String = match &payment.payment_date {
+        Some(date) => {
+            validate_date_range(tx, date, date)?;
+            date.clone()
+        }
+        None => tx.query_row("SELECT date('now')", [], |row| row.get(0))?,
+    };
+
+    let allocations = allocate(tx, payment)?;
+
+    tx.execute(
+        "INSERT INTO supplier_payments
+             (supplier_id, amount_paise, payment_date, payment_mode, reference, notes, user_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            payment.supplier_id,
+            payment.amount_paise,
+            payment_date,
+            payment.payment_mode.as_str(),
+            payment.reference,
+            payment.notes,
+            payment.user_id
+        ],
+    )?;
+    let payment_id = tx.last_insert_rowid();
+    for allocation in &allocations {
+        tx.execute(
+            "INSERT INTO supplier_payment_allocations (payment_id, purchase_id, amount_paise)
+             VALUES (?1, ?2, ?3)",
+            params![payment_id, allocation.purchase_id, allocation.amount_paise],
+        )?;
+    }
+    tx.changed(Entity::SupplierPayment, ChangeOp::Insert, payment_id);
+    sync::enqueue(tx, "record_supplier_payment", &json!(payment))?;
+    supplier_payables(tx, payment.supplier_id)
+}
+
+/// The invoices `payment` settles: those it names, checked against what
+/// each still owes, or else the oldest open invoices.
+fn allocate(conn: &Connection, payment: &NewSupplierPayment) -> AppResult<Vec<Allocation>> {
+    let (purchases, _) = settle_purchases(conn, payment.supplier_id)?;
+    if payment.allocations.is_empty() {
+        let mut left = payment.amount_paise;
+        return Ok(purchases
+            .iter()
+            .filter(|purchase| purchase.due_paise > 0)
+            .map_while(|purchase| {
+                let amount_paise = left.min(purchase.due_paise);
+                left -= amount_paise;
+                (amount_paise > 0).then_some(Allocation {
+                    purchase_id: purchase.purchase_id,
+                    amount_paise,
+                })
+            })
+            .collect());
+    }
+
+    for (i, allocation) in payment.allocations.iter().enumerate() {
+        if payment.allocations[..i]
+            .iter()
+            .any(|a| a.purchase_id == allocation.purchase_id)
+        {
+            return Err(AppError::validation("An invoice is listed more than once"));
+        }
+        let purchase = purchases
+            .iter()
+            .find(|purchase| purchase.purchase_id == allocation.purchase_id)
+            .ok_or_else(|| {
+                AppError::validation(format!(
+                    "Purchase {} is not an invoice of this supplier",
+                    allocation.purchase_id
+                ))
+            })?;
+        if allocation.amount_paise <= 0 || allocation.amount_paise > purchase.due_paise {
+            return Err(AppError::validation(format!(
+                "Invoice {} has Rs. {} due; allocate between Rs. 0.01 and that",
+                purchase.invoice_number,
+                rupees(purchase.due_paise)
+            )));
+        }
+    }
+    let allocated: i64 = payment.allocations.iter().map(|a| a.amount_paise).sum();
+    if allocated > payment.amount_paise {
+        return Err(AppError::validation(format!(
+            "Rs. {} is allocated from a payment of Rs. {}",
+            rupees(allocated),
+            rupees(payment.amount_paise)
+        )));
+    }
+    Ok(payment.allocations.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::migrated_db;
+
+    fn purchase(conn: &Connection, supplier: i64, invoice: &str, paise: i64, date: &str) {
+        conn.execute(
+            "INSERT INTO purchases (supplier_id, invoice_number, total_paise, purchase_date)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![supplier, invoice, paise, date],
+        )
+        .unwrap();
+    }
+
+    fn payment(conn: &Connection, supplier: i64, paise: i64, date: &str) {
+        conn.execute(
+            "INSERT INTO supplier_payments (supplier_id, amount_paise, payment_date, payment_mode)
+             VALUES (?1, ?2, ?3, 'upi')",
+            params![supplier, paise, date],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn ledger_carries_the_opening_balance_and_orders_same_day_entries() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        conn.execute("INSERT INTO suppliers (name) VALUES ('Medline')", [])
+            .unwrap();
+        let supplier = conn.last_insert_rowid();
+        purchase(&conn, supplier, "P-1", 10_000, "2026-01-05");
+        payment(&conn, supplier, 4_000, "2026-01-20");
+        payment(&conn, supplier, 5_000, "2026-02-03");
+        purchase(&conn, supplier, "P-2", 8_000, "2026-02-03");
+        purchase(&conn, supplier, "P-3", 1_000, "2026-03-01");
+
+        let ledger = supplier_ledger(&conn, supplier, "2026-02-01", "2026-02-28").unwrap();
+
+        assert_eq!(ledger.opening_balance_paise, 6_000);
+        let entries: Vec<_> = ledger
+            .entries
+            .iter()
+            .map(|e| (e.kind, e.balance_paise))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                (LedgerEntryKind::Purchase, 14_000),
+                (LedgerEntryKind::Payment, 9_000)
+            ]
+        );
+        assert_eq!(ledger.closing_balance_paise, 9_000);
+    }
+
+    #[test]
+    fn payments_settle_named_purchases_then_the_oldest_and_age_the_rest() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        conn.execute("INSERT INTO suppliers (name) VALUES ('Medline')", [])
+            .unwrap();
+        let supplier = conn.last_insert_rowid();
+        for (invoice, days) in [("P-1", 75), ("P-2", 40), ("P-3", 5)] {
+            conn.execute(
+                "INSERT INTO purchases (supplier_id, invoice_number, total_paise, purchase_date)
+                 VALUES (?1, ?2, 10000, date('now', ?3))",
+                params![supplier, invoice, format!("-{days} days")],
+            )
+            .unwrap();
+        }
+        let newest = conn.last_insert_rowid();
+        let pay = |amount_paise, allocations| {
+            db.with_tx(|tx| {
+                record_supplier_payment(
+                    tx,
+                    &NewSupplierPayment {
+                        supplier_id: supplier,
+                        amount_paise,
+                        payment_mode: PaymentMode::Upi,
+                        payment_date: None,
+                        reference: None,
+                        notes: None,
+                        allocations,
+                        user_id: 1,
+                    },
+                )
+            })
+        };
+
+        pay(
+            4_000,
+            vec![Allocation {
+                purchase_id: newest,
+                amount_paise: 4_000,
+            }],
+        )
+        .unwrap();
+        let after = pay(12_000, Vec::new()).unwrap();
+
+        let due: Vec<_> = after
+            .invoices
+            .iter()
+            .map(|p| (p.invoice_number.as_str(), p.due_paise))
+            .collect();
+        assert_eq!(due, [("P-2", 8_000), ("P-3", 6_000)]);
+        assert_eq!(
+            (
+                after.current_paise,
+                after.days_31_to_60_paise,
+                after.over_60_days_paise
+            ),
+            (6_000, 8_000, 0)
+        );
+        assert_eq!(after.outstanding_paise, 14_000);
+
+        let err = pay(
+            7_000,
+            vec![Allocation {
+                purchase_id: newest,
+                amount_paise: 7_000,
+            }],
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("Rs. 60.00 due"), "{err}");
+        assert_eq!(payables_aging(&conn).unwrap().len(), 1);
+    }
+}
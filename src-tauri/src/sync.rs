@@ -0,0 +1,122 @@
+//! Outbound operation queue for future multi-store sync.
+//!
+//! Every backend mutation appends its operation name and input payload to
+//! `sync_queue` inside the same transaction, so the queue never records an
+//! operation that rolled back. Until a central server exists the queue is
+//! flushed nowhere, but it doubles as a replayable journal of writes.
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+use crate::auth::{self, permission};
+use crate::db::Tx;
+use crate::error::{AppError, AppResult};
+
+/// Append an operation to the queue as part of `tx`.
+pub fn enqueue(tx: &Tx, operation: &str, payload: &impl Serialize) -> AppResult<()> {
+    let payload = serde_json::to_string(payload)
+        .map_err(|e| AppError::validation(format!("Cannot serialize {operation}: {e}")))?;
+    tx.execute(
+        "INSERT INTO sync_queue (operation, payload) VALUES (?1, ?2)",
+        params![operation, payload],
+    )?;
+    Ok(())
+}
+
+pub fn pending_sync_count(conn: &Connection) -> AppResult<i64> {
+    Ok(conn.query_row(
+        "SELECT COUNT(*) FROM sync_queue WHERE synced_at IS NULL",
+        [],
+        |row| row.get(0),
+    )?)
+}
+
+/// A queued operation as it would be sent to the server.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedOperation {
+    pub id: i64,
+    pub operation: String,
+    pub payload: serde_json::Value,
+    pub created_at: String,
+}
+
+/// Pending operations in the order they were committed.
+pub fn pending_operations(conn: &Connection, limit: i64) -> AppResult<Vec<QueuedOperation>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, operation, payload, created_at FROM sync_queue
+         WHERE synced_at IS NULL ORDER BY id LIMIT ?1",
+    )?;
+    let rows = stmt.query_map(params![limit], |row| {
+        let payload: String = row.get(2)?;
+        Ok(QueuedOperation {
+            id: row.get(0)?,
+            operation: row.get(1)?,
+            payload: serde_json::from_str(&payload).unwrap_or(serde_json::Value::String(payload)),
+            created_at: row.get(3)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlushResult {
+    /// Operations that would have been sent in this flush.
+    pub batch: Vec<QueuedOperation>,
+    pub synced: i64,
+    pub pending: i64,
+}
+
+/// Operations sent to the server per flush.
+const FLUSH_BATCH_SIZE: i64 = 100;
+
+/// Push pending operations to the sync server.
+///
+/// No server is configured yet, so this always behaves as offline: the next
+/// batch is serialized and returned for inspection, and nothing is marked
+/// as synced.
+pub fn flush_sync_queue(conn: &Connection, user_id: i64) -> AppResult<FlushResult> {
+    auth::require_permission(conn, user_id, permission::SYNC_FLUSH)?;
+    Ok(FlushResult {
+        batch: pending_operations(conn, FLUSH_BATCH_SIZE)?,
+        synced: 0,
+        pending: pending_sync_count(conn)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::db::test_support::{insert_cashier, migrated_db};
+
+    #[test]
+    fn only_committed_operations_are_queued() {
+        let (_dir, db) = migrated_db();
+        db.with_tx(|tx| enqueue(tx, "set_batch_status", &json!({ "batchId": 3 })))
+            .unwrap();
+        let _ = db.with_tx(|tx| -> AppResult<()> {
+            enqueue(tx, "amend_sale", &json!({ "saleId": 1 }))?;
+            Err(AppError::validation("rolled back"))
+        });
+
+        let conn = db.connect().unwrap();
+        assert_eq!(pending_sync_count(&conn).unwrap(), 1);
+        let flushed = flush_sync_queue(&conn, 1).unwrap();
+        assert_eq!(flushed.batch[0].operation, "set_batch_status");
+        assert_eq!(flushed.batch[0].payload, json!({ "batchId": 3 }));
+        assert_eq!((flushed.synced, flushed.pending), (0, 1));
+    }
+
+    #[test]
+    fn cashier_cannot_flush_the_queue() {
+        let (_dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let cashier = insert_cashier(&conn);
+
+        let err = flush_sync_queue(&conn, cashier).unwrap_err();
+        assert!(matches!(err, AppError::Unauthorized(_)), "{err}");
+    }
+}
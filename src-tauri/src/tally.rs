@@ -0,0 +1,678 @@
+//! Vouchers for Tally, as the XML its Import Data request takes, so the
+//! accountant can load the books instead of retyping them.
+//!
+//! The file creates the ledgers the vouchers post to before the vouchers
+//! themselves: sales and purchases by GST rate, output and input tax by
+//! duty head and rate, a ledger per customer, supplier and expense
+//! category, and one per mode money is taken in. Tally skips a ledger
+//! that already exists, so the same file can be imported twice over.
+//!
+//! Sales post their items' taxable value and tax as invoiced. Purchases
+//! from registered suppliers back the tax out of the cost price at each
+//! medicine's rate on the purchase date, the way GSTR-3B takes credit;
+//! purchases from unregistered suppliers, or by a composition dealer who
+//! takes no credit, are posted whole. Whatever an invoice's lines leave
+//! over goes to Round Off, so every voucher balances.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use rusqlite::{params, Connection};
+
+use crate::auth::{self, permission};
+use crate::error::AppResult;
+use crate::export::ExportSummary;
+use crate::format;
+use crate::gst;
+use crate::gst_history;
+use crate::reports;
+
+/// What a ledger is created under.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Group {
+    SalesAccounts,
+    PurchaseAccounts,
+    DutiesAndTaxes,
+    SundryDebtors,
+    SundryCreditors,
+    CashInHand,
+    BankAccounts,
+    IndirectExpenses,
+}
+
+impl Group {
+    fn name(self) -> &'static str {
+        match self {
+            Self::SalesAccounts => "Sales Accounts",
+            Self::PurchaseAccounts => "Purchase Accounts",
+            Self::DutiesAndTaxes => "Duties & Taxes",
+            Self::SundryDebtors => "Sundry Debtors",
+            Self::SundryCreditors => "Sundry Creditors",
+            Self::CashInHand => "Cash-in-Hand",
+            Self::BankAccounts => "Bank Accounts",
+            Self::IndirectExpenses => "Indirect Expenses",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Ledger {
+    group: Group,
+    /// Duty head and percentage of a GST ledger.
+    tax: Option<(&'static str, f64)>,
+    gstin: Option<String>,
+}
+
+/// One line of a voucher; positive amounts are debits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    ledger: String,
+    amount_paise: i64,
+}
+
+#[derive(Debug)]
+struct Voucher {
+    kind: &'static str,
+    /// `YYYY-MM-DD`.
+    date: String,
+    number: String,
+    party: String,
+    narration: Option<String>,
+    entries: Vec<Entry>,
+}
+
+const ROUND_OFF: &str = "Round Off";
+const WALK_IN: &str = "Walk-in Customers";
+const PURCHASES: &str = "Purchases";
+
+/// Ledgers and vouchers as they are gathered.
+#[derive(Default)]
+struct Books {
+    ledgers: BTreeMap<String, Ledger>,
+    vouchers: Vec<Voucher>,
+}
+
+impl Books {
+    fn ledger(&mut self, name: &str, group: Group) -> String {
+        self.ledgers.entry(name.to_owned()).or_insert(Ledger {
+            group,
+            tax: None,
+            gstin: None,
+        });
+        name.to_owned()
+    }
+
+    fn party(&mut self, name: &str, group: Group, gstin: Option<String>) -> String {
+        let ledger = self.ledgers.entry(name.to_owned()).or_insert(Ledger {
+            group,
+            tax: None,
+            gstin: None,
+        });
+        if ledger.gstin.is_none() {
+            ledger.gstin = gstin.filter(|g| !g.trim().is_empty());
+        }
+        name.to_owned()
+    }
+
+    /// The ledger money taken or paid in `mode` goes through.
+    fn mode(&mut self, mode: &str) -> String {
+        match mode {
+            "card" => self.ledger("Card", Group::BankAccounts),
+            "upi" => self.ledger("UPI", Group::BankAccounts),
+            _ => self.ledger("Cash", Group::CashInHand),
+        }
+    }
+
+    /// `Output CGST @ 6%` and the like.
+    fn tax(&mut self, direction: &str, head: &'static str, rate: f64) -> String {
+        let short = match head {
+            "Central Tax" => "CGST",
+            "State Tax" => "SGST",
+            _ => "IGST",
+        };
+        let name = format!("{direction} {short} @ {rate}%");
+        self.ledgers.entry(name.clone()).or_insert(Ledger {
+            group: Group::DutiesAndTaxes,
+            tax: Some((head, rate)),
+            gstin: None,
+        });
+        name
+    }
+
+    /// Add `voucher`, posting whatever leaves it out of balance to Round
+    /// Off and dropping lines of nothing.
+    fn push(&mut self, mut voucher: Voucher) {
+        let imbalance: i64 = voucher.entries.iter().map(|e| e.amount_paise).sum();
+        if imbalance != 0 {
+            let ledger = self.ledger(ROUND_OFF, Group::IndirectExpenses);
+            voucher.entries.push(Entry {
+                ledger,
+                amount_paise: -imbalance,
+            });
+        }
+        voucher.entries.retain(|e| e.amount_paise != 0);
+        self.vouchers.push(voucher);
+    }
+}
+
+fn sales(conn: &Connection, books: &mut Books, from: &str, to: &str) -> AppResult<()> {
+    let mut stmt = conn.prepare(
+        "SELECT s.id, date(s.sale_date), s.invoice_number, c.name, c.gstin, s.grand_total_paise,
+             s.payment_mode, s.bill_of_supply
+         FROM sales s
+         LEFT JOIN customers c ON c.id = s.customer_id
+         WHERE date(s.sale_date) BETWEEN ?1 AND ?2
+         ORDER BY s.sale_date, s.id",
+    )?;
+    let sales = stmt
+        .query_map(params![from, to], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, i64>(5)?,
+                row.get::<_, String>(6)?,
+                row.get::<_, bool>(7)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut payments = conn.prepare(
+        "SELECT payment_mode, amount_paise FROM sale_payments WHERE sale_id = ?1 ORDER BY id",
+    )?;
+    let mut items = conn.prepare(
+        "SELECT si.cgst_rate + si.sgst_rate + si.igst_rate, si.cgst_rate, si.sgst_rate,
+             si.igst_rate, SUM(si.taxable_amount_paise), SUM(si.cgst_amount_paise),
+             SUM(si.sgst_amount_paise), SUM(si.igst_amount_paise)
+         FROM sale_items si WHERE si.sale_id = ?1
+         GROUP BY 1, 2, 3, 4
+         ORDER BY 1",
+    )?;
+    for (sale_id, date, number, customer, gstin, grand_total, mode, bill_of_supply) in sales {
+        let mut entries = Vec::new();
+        let tenders = payments
+            .query_map(params![sale_id], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        let tenders = if tenders.is_empty() {
+            vec![(mode, grand_total)]
+        } else {
+            tenders
+        };
+        for (mode, amount_paise) in tenders {
+            let ledger = match (mode.as_str(), &customer) {
+                ("credit", Some(name)) => books.party(name, Group::SundryDebtors, gstin.clone()),
+                ("credit", None) => books.ledger(WALK_IN, Group::SundryDebtors),
+                _ => books.mode(&mode),
+            };
+            entries.push(Entry {
+                ledger,
+                amount_paise,
+            });
+        }
+
+        let mut rows = items.query(params![sale_id])?;
+        while let Some(row) = rows.next()? {
+            let rate: f64 = row.get(0)?;
+            let ledger = if bill_of_supply {
+                books.ledger("Sales - Composition", Group::SalesAccounts)
+            } else {
+                books.ledger(&format!("Sales @ {rate}%"), Group::SalesAccounts)
+            };
+            entries.push(Entry {
+                ledger,
+                amount_paise: -row.get::<_, i64>(4)?,
+            });
+            for (head, rate_column) in [("Central Tax", 1), ("State Tax", 2), ("Integrated Tax", 3)]
+            {
+                let amount: i64 = row.get(rate_column + 4)?;
+                if amount != 0 {
+                    entries.push(Entry {
+                        ledger: books.tax("Output", head, row.get(rate_column)?),
+                        amount_paise: -amount,
+                    });
+                }
+            }
+        }
+        books.push(Voucher {
+            kind: "Sales",
+            date,
+            party: entries[0].ledger.clone(),
+            number,
+            narration: None,
+            entries,
+        });
+    }
+    Ok(())
+}
+
+fn purchases(conn: &Connection, books: &mut Books, from: &str, to: &str) -> AppResult<()> {
+    let (home_state, composition): (String, bool) = conn.query_row(
+        "SELECT state_code, composition_scheme FROM pharmacy_settings WHERE id = 1",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    let mut stmt = conn.prepare(
+        "SELECT p.id, date(p.purchase_date), p.invoice_number, su.name,
+             TRIM(COALESCE(su.gst_in, '')), p.total_paise
+         FROM purchases p
+         JOIN suppliers su ON su.id = p.supplier_id
+         WHERE date(p.purchase_date) BETWEEN ?1 AND ?2
+         ORDER BY p.purchase_date, p.id",
+    )?;
+    let purchases = stmt
+        .query_map(params![from, to], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, i64>(5)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut items = conn.prepare(
+        "SELECT medicine_id, quantity, cost_price_paise FROM purchase_items
+         WHERE purchase_id = ?1 ORDER BY id",
+    )?;
+    for (purchase_id, date, number, supplier, gstin, total) in purchases {
+        let registered = !gstin.is_empty() && !composition;
+        let interstate = !home_state.is_empty() && gstin.get(..2) != Some(home_state.as_str());
+        let party = books.party(&supplier, Group::SundryCreditors, Some(gstin));
+        let mut entries = vec![Entry {
+            ledger: party.clone(),
+            amount_paise: -total,
+        }];
+
+        let lines = items
+            .query_map(params![purchase_id], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        if lines.is_empty() || !registered {
+            let cost = if lines.is_empty() {
+                total
+            } else {
+                lines.iter().map(|(_, qty, cost)| qty * cost).sum()
+            };
+            entries.push(Entry {
+                ledger: books.ledger(PURCHASES, Group::PurchaseAccounts),
+                amount_paise: cost,
+            });
+        }
+        if registered {
+            // Keyed by hundredths of a percent so the rates sort numerically.
+            let mut by_rate = BTreeMap::<i64, (f64, i64, [i64; 3])>::new();
+            for (medicine_id, quantity, cost_price_paise) in lines {
+                let rate = gst_history::rate_on(conn, medicine_id, &date)?;
+                let line = gst::calculate_line(cost_price_paise, quantity, rate, 0, interstate);
+                let (_, taxable, tax) = by_rate
+                    .entry((rate * 100.0).round() as i64)
+                    .or_insert((rate, 0, [0; 3]));
+                *taxable += line.taxable_amount_paise;
+                tax[0] += line.gst.cgst_paise;
+                tax[1] += line.gst.sgst_paise;
+                tax[2] += line.gst.igst_paise;
+            }
+            for (rate, taxable, [cgst, sgst, igst]) in by_rate.into_values() {
+                entries.push(Entry {
+                    ledger: books.ledger(&format!("Purchase @ {rate}%"), Group::PurchaseAccounts),
+                    amount_paise: taxable,
+                });
+                for (head, head_rate, amount_paise) in [
+                    ("Central Tax", rate / 2.0, cgst),
+                    ("State Tax", rate / 2.0, sgst),
+                    ("Integrated Tax", rate, igst),
+                ] {
+                    if amount_paise != 0 {
+                        entries.push(Entry {
+                            ledger: books.tax("Input", head, head_rate),
+                            amount_paise,
+                        });
+                    }
+                }
+            }
+        }
+        books.push(Voucher {
+            kind: "Purchase",
+            date,
+            number,
+            party,
+            narration: None,
+            entries,
+        });
+    }
+    Ok(())
+}
+
+/// Customer receipts, supplier payments and expenses, which each move
+/// money between a party and the ledger of their mode.
+fn receipts_and_payments(
+    conn: &Connection,
+    books: &mut Books,
+    from: &str,
+    to: &str,
+) -> AppResult<()> {
+    let mut stmt = conn.prepare(
+        "SELECT kind, day, reference, party, gstin, mode, amount, notes FROM (
+             SELECT 0 AS kind, date(p.payment_date) AS day, p.reference, c.name AS party,
+                 c.gstin, p.payment_mode AS mode, p.amount_paise AS amount, p.notes,
+                 p.created_at AS at
+             FROM customer_payments p JOIN customers c ON c.id = p.customer_id
+             UNION ALL
+             SELECT 1, date(p.payment_date), p.reference, su.name, su.gst_in, p.payment_mode,
+                 p.amount_paise, p.notes, p.created_at
+             FROM supplier_payments p JOIN suppliers su ON su.id = p.supplier_id
+             UNION ALL
+             SELECT 2, e.expense_date, e.reference, c.name, NULL, e.payment_mode,
+                 e.amount_paise, COALESCE(e.paid_to, e.notes), e.created_at
+             FROM expenses e JOIN expense_categories c ON c.id = e.category_id
+         )
+         WHERE day BETWEEN ?1 AND ?2 AND mode <> 'credit'
+         ORDER BY day, at",
+    )?;
+    let mut rows = stmt.query(params![from, to])?;
+    let mut numbers = [0; 2];
+    while let Some(row) = rows.next()? {
+        let kind: usize = row.get(0)?;
+        let reference: Option<String> = row.get(2)?;
+        let name: String = row.get(3)?;
+        let mode: String = row.get(5)?;
+        let amount: i64 = row.get(6)?;
+        let (party, sign) = match kind {
+            0 => (books.party(&name, Group::SundryDebtors, row.get(4)?), -1),
+            1 => (books.party(&name, Group::SundryCreditors, row.get(4)?), 1),
+            _ => (books.ledger(&name, Group::IndirectExpenses), 1),
+        };
+        let voucher_kind = if kind == 0 { "Receipt" } else { "Payment" };
+        let counter = &mut numbers[usize::from(kind != 0)];
+        *counter += 1;
+        let cash = books.mode(&mode);
+        books.push(Voucher {
+            kind: voucher_kind,
+            date: row.get(1)?,
+            number: reference
+                .filter(|r| !r.trim().is_empty())
+                .unwrap_or_else(|| counter.to_string()),
+            party: party.clone(),
+            narration: row.get(7)?,
+            entries: vec![
+                Entry {
+                    ledger: party,
+                    amount_paise: sign * amount,
+                },
+                Entry {
+                    ledger: cash,
+                    amount_paise: -sign * amount,
+                },
+            ],
+        });
+    }
+    Ok(())
+}
+
+/// `text` safe inside an element or attribute.
+fn xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn write_ledger(out: &mut impl Write, name: &str, ledger: &Ledger) -> std::io::Result<()> {
+    let name = xml(name);
+    writeln!(out, "<TALLYMESSAGE xmlns:UDF=\"TallyUDF\">")?;
+    writeln!(out, "<LEDGER NAME=\"{name}\" ACTION=\"Create\">")?;
+    writeln!(out, "<NAME.LIST><NAME>{name}</NAME></NAME.LIST>")?;
+    writeln!(out, "<PARENT>{}</PARENT>", xml(ledger.group.name()))?;
+    if let Some((head, rate)) = ledger.tax {
+        writeln!(out, "<TAXTYPE>GST</TAXTYPE>")?;
+        writeln!(out, "<GSTDUTYHEAD>{head}</GSTDUTYHEAD>")?;
+        writeln!(out, "<RATEOFTAXCALCULATION>{rate}</RATEOFTAXCALCULATION>")?;
+    }
+    if let Some(gstin) = &ledger.gstin {
+        writeln!(out, "<PARTYGSTIN>{}</PARTYGSTIN>", xml(gstin.trim()))?;
+    }
+    let bill_wise = matches!(ledger.group, Group::SundryDebtors | Group::SundryCreditors);
+    writeln!(
+        out,
+        "<ISBILLWISEON>{}</ISBILLWISEON>",
+        if bill_wise { "Yes" } else { "No" }
+    )?;
+    writeln!(out, "</LEDGER>")?;
+    writeln!(out, "</TALLYMESSAGE>")
+}
+
+fn write_voucher(out: &mut impl Write, voucher: &Voucher) -> std::io::Result<()> {
+    writeln!(out, "<TALLYMESSAGE xmlns:UDF=\"TallyUDF\">")?;
+    writeln!(
+        out,
+        "<VOUCHER VCHTYPE=\"{0}\" ACTION=\"Create\" OBJVIEW=\"Accounting Voucher View\">",
+        voucher.kind
+    )?;
+    writeln!(out, "<DATE>{}</DATE>", voucher.date.replace('-', ""))?;
+    writeln!(out, "<VOUCHERTYPENAME>{}</VOUCHERTYPENAME>", voucher.kind)?;
+    writeln!(
+        out,
+        "<VOUCHERNUMBER>{}</VOUCHERNUMBER>",
+        xml(&voucher.number)
+    )?;
+    writeln!(
+        out,
+        "<PARTYLEDGERNAME>{}</PARTYLEDGERNAME>",
+        xml(&voucher.party)
+    )?;
+    if let Some(narration) = voucher.narration.as_deref().filter(|n| !n.is_empty()) {
+        writeln!(out, "<NARRATION>{}</NARRATION>", xml(narration))?;
+    }
+    writeln!(
+        out,
+        "<PERSISTEDVIEW>Accounting Voucher View</PERSISTEDVIEW>"
+    )?;
+    for entry in &voucher.entries {
+        // Tally writes debits as negative amounts that are deemed positive.
+        let debit = entry.amount_paise > 0;
+        writeln!(out, "<ALLLEDGERENTRIES.LIST>")?;
+        writeln!(out, "<LEDGERNAME>{}</LEDGERNAME>", xml(&entry.ledger))?;
+        writeln!(
+            out,
+            "<ISDEEMEDPOSITIVE>{}</ISDEEMEDPOSITIVE>",
+            if debit { "Yes" } else { "No" }
+        )?;
+        writeln!(
+            out,
+            "<AMOUNT>{}</AMOUNT>",
+            format::rupees(-entry.amount_paise)
+        )?;
+        writeln!(out, "</ALLLEDGERENTRIES.LIST>")?;
+    }
+    writeln!(out, "</VOUCHER>")?;
+    writeln!(out, "</TALLYMESSAGE>")
+}
+
+/// Write the sales, purchase, receipt and payment vouchers dated
+/// `from_date` to `to_date`, inclusive, with the ledgers they post to, to
+/// `dest` as a Tally import file. The rows written are the vouchers.
+pub fn export_tally_xml(
+    conn: &Connection,
+    from_date: &str,
+    to_date: &str,
+    dest: &Path,
+    user_id: i64,
+) -> AppResult<ExportSummary> {
+    auth::require_permission(conn, user_id, permission::REPORTS_EXPORT)?;
+    reports::validate_date_range(conn, from_date, to_date)?;
+    let company: String = conn.query_row(
+        "SELECT name FROM pharmacy_settings WHERE id = 1",
+        [],
+        |row| row.get(0),
+    )?;
+
+    let mut books = Books::default();
+    sales(conn, &mut books, from_date, to_date)?;
+    purchases(conn, &mut books, from_date, to_date)?;
+    receipts_and_payments(conn, &mut books, from_date, to_date)?;
+
+    let mut out = BufWriter::new(File::create(dest)?);
+    writeln!(out, "<ENVELOPE>")?;
+    writeln!(
+        out,
+        "<HEADER><TALLYREQUEST>Import Data</TALLYREQUEST></HEADER>"
+    )?;
+    writeln!(out, "<BODY><IMPORTDATA>")?;
+    writeln!(out, "<REQUESTDESC><REPORTNAME>Vouchers</REPORTNAME>")?;
+    writeln!(
+        out,
+        "<STATICVARIABLES><SVCURRENTCOMPANY>{}</SVCURRENTCOMPANY></STATICVARIABLES>",
+        xml(&company)
+    )?;
+    writeln!(out, "</REQUESTDESC>")?;
+    writeln!(out, "<REQUESTDATA>")?;
+    for (name, ledger) in &books.ledgers {
+        write_ledger(&mut out, name, ledger)?;
+    }
+    for voucher in &books.vouchers {
+        write_voucher(&mut out, voucher)?;
+    }
+    writeln!(out, "</REQUESTDATA>")?;
+    writeln!(out, "</IMPORTDATA></BODY>")?;
+    writeln!(out, "</ENVELOPE>")?;
+    out.flush()?;
+
+    Ok(ExportSummary {
+        path: dest.to_string_lossy().into_owned(),
+        rows_written: books.vouchers.len() as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::{
+        insert_batch, insert_cashier, insert_medicine, migrated_db, paid_sale,
+    };
+    use crate::error::AppError;
+    use crate::sales::{create_sale, PaymentMode};
+
+    #[test]
+    fn vouchers_balance_and_post_tax_to_gst_ledgers() {
+        let (dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        conn.execute(
+            "UPDATE pharmacy_settings SET name = 'Shah & Sons', state_code = '27'",
+            [],
+        )
+        .unwrap();
+        let para = insert_medicine(&conn, "Paracetamol", 12.0);
+        let batch = insert_batch(&conn, para, "+90 days", 5_000, 10);
+        let sale = paid_sale(&conn, None, PaymentMode::Cash, batch, 1);
+        db.with_tx(|tx| create_sale(tx, &sale)).unwrap();
+        conn.execute(
+            "INSERT INTO suppliers (name, gst_in) VALUES ('Medline', '27AAAAA0000A1Z5')",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO purchases (supplier_id, invoice_number, total_paise)
+             VALUES (1, 'MD-1', 11201)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO purchase_items (purchase_id, batch_id, medicine_id, quantity,
+                 cost_price_paise, mrp_paise, selling_price_paise)
+             VALUES (1, ?1, ?2, 10, 1120, 5000, 5000)",
+            params![batch, para],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO supplier_payments (supplier_id, amount_paise, payment_date, payment_mode)
+             VALUES (1, 11201, date('now'), 'upi')",
+            [],
+        )
+        .unwrap();
+        let today: String = conn
+            .query_row("SELECT date('now')", [], |r| r.get(0))
+            .unwrap();
+
+        let mut books = Books::default();
+        sales(&conn, &mut books, &today, &today).unwrap();
+        purchases(&conn, &mut books, &today, &today).unwrap();
+        receipts_and_payments(&conn, &mut books, &today, &today).unwrap();
+
+        let kinds: Vec<_> = books.vouchers.iter().map(|v| v.kind).collect();
+        assert_eq!(kinds, ["Sales", "Purchase", "Payment"]);
+        for voucher in &books.vouchers {
+            let sum: i64 = voucher.entries.iter().map(|e| e.amount_paise).sum();
+            assert_eq!(sum, 0, "{voucher:?}");
+        }
+        let amount = |voucher: &Voucher, ledger: &str| {
+            voucher
+                .entries
+                .iter()
+                .find(|e| e.ledger == ledger)
+                .map(|e| e.amount_paise)
+        };
+        let sale = &books.vouchers[0];
+        assert_eq!(sale.party, "Cash");
+        assert_eq!(amount(sale, "Cash"), Some(5_000));
+        assert_eq!(amount(sale, "Output CGST @ 6%"), Some(-268));
+        let purchase = &books.vouchers[1];
+        assert_eq!(amount(purchase, "Medline"), Some(-11_201));
+        assert_eq!(amount(purchase, "Purchase @ 12%"), Some(10_000));
+        assert_eq!(amount(purchase, "Input SGST @ 6%"), Some(600));
+        assert_eq!(amount(purchase, ROUND_OFF), Some(1));
+        assert_eq!(amount(&books.vouchers[2], "UPI"), Some(-11_201));
+        assert_eq!(
+            books.ledgers["Input CGST @ 6%"].tax,
+            Some(("Central Tax", 6.0))
+        );
+        assert_eq!(
+            books.ledgers["Medline"].gstin.as_deref(),
+            Some("27AAAAA0000A1Z5")
+        );
+
+        let dest = dir.path().join("tally.xml");
+        let summary = export_tally_xml(&conn, &today, &today, &dest, 1).unwrap();
+        assert_eq!(summary.rows_written, 3);
+        let file = std::fs::read_to_string(&dest).unwrap();
+        assert!(file.contains("<SVCURRENTCOMPANY>Shah &amp; Sons</SVCURRENTCOMPANY>"));
+        assert!(file.contains("<PARENT>Duties &amp; Taxes</PARENT>"));
+        assert!(file.contains(&format!("<DATE>{}</DATE>", today.replace('-', ""))));
+        assert!(file.contains("<ISDEEMEDPOSITIVE>Yes</ISDEEMEDPOSITIVE>\n<AMOUNT>-50.00</AMOUNT>"));
+    }
+
+    #[test]
+    fn cashier_cannot_export_to_tally() {
+        let (dir, db) = migrated_db();
+        let conn = db.connect().unwrap();
+        let cashier = insert_cashier(&conn);
+        let today: String = conn
+            .query_row("SELECT date('now')", [], |r| r.get(0))
+            .unwrap();
+        let dest = dir.path().join("tally.xml");
+
+        let err = export_tally_xml(&conn, &today, &today, &dest, cashier).unwrap_err();
+        assert!(matches!(err, AppError::Unauthorized(_)), "{err}");
+        assert!(!dest.exists());
+    }
+}
@@ -4,6 +4,7 @@ import { AuthProvider, useAuth } from "@/features/auth/AuthContext";
 import { ProtectedRoute } from "@/features/auth/ProtectedRoute";
 import LoginPage from "@/features/auth/LoginPage";
 import FirstLaunchWizard from "@/features/auth/FirstLaunchWizard";
+import UnlockDatabasePage from "@/features/auth/UnlockDatabasePage";
 import { AppLayout } from "@/components/layout/AppLayout";
 import DashboardPage from "@/pages/DashboardPage";
 import MedicinesPage from "@/pages/inventory/MedicinesPage";
@@ -14,6 +15,7 @@ import ExpiryDashboardPage from "@/pages/inventory/ExpiryDashboardPage";
 import NewSalePage from "@/pages/sales/NewSalePage";
 import SalesHistoryPage from "@/pages/sales/SalesHistoryPage";
 import InvoicePage from "@/pages/sales/InvoicePage";
+import ShiftPage from "@/pages/sales/ShiftPage";
 import CustomersPage from "@/pages/customers/CustomersPage";
 import CustomerDetailPage from "@/pages/customers/CustomerDetailPage";
 import SuppliersPage from "@/pages/suppliers/SuppliersPage";
@@ -22,11 +24,15 @@ import SalesReportPage from "@/pages/reports/SalesReportPage";
 import StockReportPage from "@/pages/reports/StockReportPage";
 import ProfitLossPage from "@/pages/reports/ProfitLossPage";
 import ExpiryReportPage from "@/pages/reports/ExpiryReportPage";
+import GstReturnsPage from "@/pages/reports/GstReturnsPage";
+import DayBookPage from "@/pages/reports/DayBookPage";
+import AnalyticsPage from "@/pages/reports/AnalyticsPage";
+import ExpensesPage from "@/pages/expenses/ExpensesPage";
 import UsersPage from "@/pages/users/UsersPage";
 import SettingsPage from "@/pages/settings/SettingsPage";
 
 function ProtectedApp() {
-  const { user, isLoading, isFirstLaunch } = useAuth();
+  const { user, isLoading, isFirstLaunch, isLocked } = useAuth();
 
   if (isLoading) {
     return (
@@ -39,6 +45,10 @@ function ProtectedApp() {
     );
   }
 
+  if (isLocked) {
+    return <UnlockDatabasePage />;
+  }
+
   if (isFirstLaunch) {
     return <FirstLaunchWizard />;
   }
@@ -64,6 +74,7 @@ function ProtectedApp() {
           <Route path="sales/new" element={<NewSalePage />} />
           <Route path="sales/history" element={<SalesHistoryPage />} />
           <Route path="sales/invoice/:id" element={<InvoicePage />} />
+          <Route path="sales/shift" element={<ShiftPage />} />
         </Route>
 
         <Route element={<ProtectedRoute permission="customers:view" />}>
@@ -81,6 +92,13 @@ function ProtectedApp() {
           <Route path="reports/stock" element={<StockReportPage />} />
           <Route path="reports/profit-loss" element={<ProfitLossPage />} />
           <Route path="reports/expiry" element={<ExpiryReportPage />} />
+          <Route path="reports/gst" element={<GstReturnsPage />} />
+          <Route path="reports/day-book" element={<DayBookPage />} />
+          <Route path="reports/analytics" element={<AnalyticsPage />} />
+        </Route>
+
+        <Route element={<ProtectedRoute permission="expenses:manage" />}>
+          <Route path="expenses" element={<ExpensesPage />} />
         </Route>
 
         <Route element={<ProtectedRoute permission="users:manage" />}>
@@ -11,12 +11,17 @@ const PAGE_TITLES: Record<string, string> = {
   "/inventory/expiry": "Expiry Dashboard",
   "/sales/new": "New Sale",
   "/sales/history": "Sales History",
+  "/sales/shift": "Shift",
   "/customers": "Customers",
   "/suppliers": "Suppliers",
   "/reports/sales": "Sales Report",
   "/reports/stock": "Stock Report",
   "/reports/profit-loss": "Profit & Loss",
   "/reports/expiry": "Expiry Report",
+  "/reports/gst": "GST Returns",
+  "/reports/day-book": "Day Book",
+  "/reports/analytics": "Analytics",
+  "/expenses": "Expenses",
   "/users": "Users",
   "/settings": "Settings",
 };
@@ -14,6 +14,11 @@ import {
   Package,
   IndianRupee,
   Clock,
+  FileText,
+  BookOpen,
+  BarChart3,
+  Wallet,
+  Banknote,
   UserCog,
   Settings,
   LogOut,
@@ -62,6 +67,7 @@ const navigation: NavEntry[] = [
     children: [
       { label: "New Sale", path: "/sales/new", icon: <ShoppingCart size={18} /> },
       { label: "Sales History", path: "/sales/history", icon: <Receipt size={18} /> },
+      { label: "Shift", path: "/sales/shift", icon: <Banknote size={18} /> },
     ],
   },
   { label: "Customers", path: "/customers", icon: <Users size={20} /> },
@@ -74,8 +80,12 @@ const navigation: NavEntry[] = [
       { label: "Stock Report", path: "/reports/stock", icon: <Package size={18} /> },
       { label: "Profit & Loss", path: "/reports/profit-loss", icon: <IndianRupee size={18} /> },
       { label: "Expiry Report", path: "/reports/expiry", icon: <Clock size={18} /> },
+      { label: "GST Returns", path: "/reports/gst", icon: <FileText size={18} /> },
+      { label: "Day Book", path: "/reports/day-book", icon: <BookOpen size={18} /> },
+      { label: "Analytics", path: "/reports/analytics", icon: <BarChart3 size={18} /> },
     ],
   },
+  { label: "Expenses", path: "/expenses", icon: <Wallet size={20} /> },
   { label: "Users", path: "/users", icon: <UserCog size={20} /> },
   { label: "Settings", path: "/settings", icon: <Settings size={20} /> },
 ];
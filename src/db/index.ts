@@ -1,4 +1,5 @@
 import Database from '@tauri-apps/plugin-sql';
+import { getDatabaseStatus } from './queries/encryption';
 
 const DB_PATH = 'sqlite:pharmacare.db';
 
@@ -7,10 +8,18 @@ let db: Database | null = null;
 /**
  * Get a singleton Database connection.
  * First call triggers async connection; subsequent calls return cached instance.
+ *
+ * An encrypted database must be unlocked first. The backend then hands the
+ * plugin a pool keyed with the passphrase, which `Database.get` picks up;
+ * `Database.load` would replace it with an unkeyed one.
  */
 export async function getDb(): Promise<Database> {
   if (!db) {
-    db = await Database.load(DB_PATH);
+    const status = await getDatabaseStatus();
+    if (status.encrypted && !status.unlocked) {
+      throw new Error('The database is locked');
+    }
+    db = status.encrypted ? Database.get(DB_PATH) : await Database.load(DB_PATH);
   }
   return db;
 }
@@ -21,7 +30,10 @@ export async function getDb(): Promise<Database> {
  */
 export async function closeDb(): Promise<void> {
   if (db) {
-    await db.close();
+    // An encrypted database's pool is opened by the backend, which has the key
+    if (!(await getDatabaseStatus()).encrypted) {
+      await db.close();
+    }
     db = null;
   }
 }
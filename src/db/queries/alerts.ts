@@ -0,0 +1,38 @@
+import { invoke } from '@tauri-apps/api/core';
+import { sessionToken } from '../session';
+
+export type AlertKind = 'low_stock' | 'near_expiry';
+
+export interface Alert {
+  id: number;
+  kind: AlertKind;
+  medicineId: number;
+  medicineName: string;
+  /** The expiring batch; null for low-stock alerts */
+  batchId: number | null;
+  message: string;
+  createdAt: string;
+  acknowledgedAt: string | null;
+  acknowledgedBy: number | null;
+}
+
+/**
+ * Alerts raised by the background stock and expiry check that still hold.
+ */
+export async function getAlerts(includeAcknowledged = false): Promise<Alert[]> {
+  return invoke<Alert[]>('list_alerts', { includeAcknowledged });
+}
+
+/**
+ * Mark an alert as seen; it is not raised again until its condition clears.
+ */
+export async function acknowledgeAlert(alertId: number): Promise<Alert> {
+  return invoke<Alert>('acknowledge_alert', { alertId, token: sessionToken() });
+}
+
+/**
+ * Run the stock and expiry check now instead of waiting for the next pass.
+ */
+export async function checkAlerts(): Promise<Alert[]> {
+  return invoke<Alert[]>('check_alerts', { token: sessionToken() });
+}
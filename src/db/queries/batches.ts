@@ -1,6 +1,8 @@
+import { invoke } from '@tauri-apps/api/core';
+import { sessionToken } from '../session';
 import { getDb } from '../index';
-import { toCamelCase } from '../utils';
-import type { Batch, BatchWithMedicine } from '@/types';
+import { toCamelCase, UNEXPIRED } from '../utils';
+import type { Batch, BatchStatus, BatchWithMedicine } from '@/types';
 
 interface BatchRow {
   id: number;
@@ -12,16 +14,21 @@ interface BatchRow {
   selling_price_paise: number;
   quantity: number;
   manufacturing_date: string | null;
+  status: string;
+  barcode: string | null;
   created_at: string;
 }
 
 function mapBatchRow(row: BatchRow): Batch {
-  return toCamelCase<Batch>(row);
+  return {
+    ...toCamelCase<Batch>(row),
+    status: row.status as BatchStatus,
+  };
 }
 
 export async function getBatchesByMedicine(medicineId: number, includeExpired = false): Promise<Batch[]> {
   const db = await getDb();
-  const expiryClause = includeExpired ? '' : "AND expiry_date > date('now')";
+  const expiryClause = includeExpired ? '' : `AND ${UNEXPIRED}`;
   const rows = await db.select<BatchRow[]>(
     `SELECT * FROM batches WHERE medicine_id = $1 ${expiryClause} ORDER BY expiry_date ASC`,
     [medicineId]
@@ -52,19 +59,75 @@ export async function getBatchById(id: number): Promise<Batch | null> {
 
 /**
  * Get batches ordered by First Expiry First Out.
- * Only returns non-expired batches with quantity > 0.
+ * Only returns available (not quarantined/blocked), non-expired batches with quantity > 0.
  */
 export async function getBatchesFEFO(medicineId: number): Promise<Batch[]> {
   const db = await getDb();
   const rows = await db.select<BatchRow[]>(
     `SELECT * FROM batches 
-     WHERE medicine_id = $1 AND quantity > 0 AND expiry_date > date('now')
+     WHERE medicine_id = $1 AND quantity > 0 AND ${UNEXPIRED} AND status = 'available'
      ORDER BY expiry_date ASC`,
     [medicineId]
   );
   return rows.map(mapBatchRow);
 }
 
+/** Loose units the backend takes from one batch for a sale. */
+export interface BatchAllocation {
+  batchId: number;
+  batchNumber: string;
+  expiryDate: string;
+  sellingPricePaise: number;
+  quantity: number;
+}
+
+/**
+ * The batches a sale of `quantity` (in the medicine's default sale unit)
+ * would be dispensed from, earliest expiry first, as the backend will pick
+ * them. Rejects with the shortfall when the stock cannot cover it.
+ */
+export async function allocateBatches(
+  medicineId: number,
+  quantity: number
+): Promise<BatchAllocation[]> {
+  return invoke<BatchAllocation[]>('pick_batches_fefo', { medicineId, quantity });
+}
+
+export type AdjustmentReason = 'breakage' | 'theft' | 'count_error' | 'other';
+
+/** One entry in the stock adjustment register. */
+export interface StockAdjustment {
+  id: number;
+  batchId: number;
+  medicineId: number;
+  reason: AdjustmentReason;
+  remarks: string;
+  previousQuantity: number;
+  newQuantity: number;
+  /** Negative when stock was written off */
+  changeQty: number;
+  userId: number;
+  createdAt: string;
+}
+
+/**
+ * Set a batch to its counted quantity under a reason code. Breakage and
+ * theft can only reduce stock; the user needs the inventory.adjust permission.
+ */
+export async function adjustStock(data: {
+  batchId: number;
+  newQuantity: number;
+  reason: AdjustmentReason;
+  remarks: string;
+}): Promise<StockAdjustment> {
+  return invoke<StockAdjustment>('adjust_stock', { ...data, token: sessionToken() });
+}
+
+export async function getStockAdjustments(fromDate: string, toDate: string): Promise<StockAdjustment[]> {
+  return invoke<StockAdjustment[]>('list_stock_adjustments', { fromDate, toDate });
+}
+
+/** Add a batch by hand; its opening stock is the first stock ledger entry. */
 export async function createBatch(data: {
   medicineId: number;
   batchNumber: string;
@@ -74,46 +137,51 @@ export async function createBatch(data: {
   sellingPricePaise: number;
   quantity: number;
   manufacturingDate?: string | null;
+  barcode?: string | null;
 }): Promise<number> {
-  const db = await getDb();
-  const result = await db.execute(
-    `INSERT INTO batches (medicine_id, batch_number, expiry_date, cost_price_paise, mrp_paise, selling_price_paise, quantity, manufacturing_date)
-     VALUES ($1, $2, $3, $4, $5, $6, $7, $8)`,
-    [
-      data.medicineId,
-      data.batchNumber,
-      data.expiryDate,
-      data.costPricePaise,
-      data.mrpPaise,
-      data.sellingPricePaise,
-      data.quantity,
-      data.manufacturingDate ?? null,
-    ]
-  );
-  return result.lastInsertId ?? 0;
+  return invoke<number>('create_batch', { batch: data, token: sessionToken() });
 }
 
 /**
- * Update batch quantity (decrement after sale, increment on return).
- * Uses atomic SQL to prevent race conditions.
- */
-export async function updateBatchQuantity(batchId: number, quantityChange: number): Promise<void> {
-  const db = await getDb();
-  await db.execute(
-    'UPDATE batches SET quantity = quantity + $1 WHERE id = $2',
-    [quantityChange, batchId]
-  );
-}
-
-/**
- * Get total stock for a medicine (sum of all non-expired batch quantities).
+ * Get sellable stock for a medicine (sum of available, non-expired batch quantities).
  */
 export async function getMedicineStock(medicineId: number): Promise<number> {
   const db = await getDb();
   const rows = await db.select<{ total: number | null }[]>(
     `SELECT SUM(quantity) as total FROM batches 
-     WHERE medicine_id = $1 AND expiry_date > date('now') AND quantity > 0`,
+     WHERE medicine_id = $1 AND ${UNEXPIRED} AND quantity > 0 AND status = 'available'`,
     [medicineId]
   );
   return rows[0]?.total ?? 0;
 }
+
+export type BarcodeSymbology = 'code128' | 'ean13';
+/** Label roll, width x height in mm */
+export type LabelSize = '38x25' | '50x25' | '50x38';
+
+export interface LabelSheet {
+  batchId: number;
+  /** What the labels encode */
+  barcode: string;
+  /** True when the batch had no barcode and was given an in-store EAN-13 */
+  assigned: boolean;
+  labels: number;
+}
+
+/** Write barcode labels for a batch to a PDF, one label per page. */
+export async function generateBatchLabels(
+  batchId: number,
+  copies: number,
+  symbology: BarcodeSymbology,
+  size: LabelSize,
+  destPath: string
+): Promise<LabelSheet> {
+  return invoke<LabelSheet>('generate_batch_labels', {
+    batchId,
+    copies,
+    symbology,
+    size,
+    destPath,
+    token: sessionToken(),
+  });
+}
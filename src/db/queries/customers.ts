@@ -1,6 +1,8 @@
+import { invoke } from '@tauri-apps/api/core';
+import { sessionToken } from '../session';
 import { getDb } from '../index';
 import { toCamelCase } from '../utils';
-import type { Customer } from '@/types';
+import type { Customer, PaymentMode } from '@/types';
 
 interface CustomerRow {
   id: number;
@@ -8,6 +10,12 @@ interface CustomerRow {
   phone: string | null;
   email: string | null;
   address: string | null;
+  gstin: string | null;
+  state_code: string | null;
+  city: string | null;
+  pincode: string | null;
+  loyalty_points: number;
+  credit_limit_paise: number | null;
   created_at: string;
   updated_at: string;
 }
@@ -41,60 +49,25 @@ export async function searchCustomers(term: string): Promise<Customer[]> {
   return rows.map(mapCustomerRow);
 }
 
-export async function createCustomer(data: {
+export interface CustomerInput {
   name: string;
   phone?: string | null;
   email?: string | null;
   address?: string | null;
-}): Promise<number> {
-  const db = await getDb();
-  const result = await db.execute(
-    'INSERT INTO customers (name, phone, email, address) VALUES ($1, $2, $3, $4)',
-    [data.name, data.phone ?? null, data.email ?? null, data.address ?? null]
-  );
-  return result.lastInsertId ?? 0;
-}
-
-export async function updateCustomer(
-  id: number,
-  data: {
-    name?: string;
-    phone?: string | null;
-    email?: string | null;
-    address?: string | null;
-  }
-): Promise<void> {
-  const db = await getDb();
-  const setClauses: string[] = [];
-  const values: unknown[] = [];
-  let paramIdx = 1;
-
-  if (data.name !== undefined) {
-    setClauses.push(`name = $${paramIdx++}`);
-    values.push(data.name);
-  }
-  if (data.phone !== undefined) {
-    setClauses.push(`phone = $${paramIdx++}`);
-    values.push(data.phone);
-  }
-  if (data.email !== undefined) {
-    setClauses.push(`email = $${paramIdx++}`);
-    values.push(data.email);
-  }
-  if (data.address !== undefined) {
-    setClauses.push(`address = $${paramIdx++}`);
-    values.push(data.address);
-  }
-
-  if (setClauses.length === 0) return;
-
-  setClauses.push(`updated_at = datetime('now')`);
-  values.push(id);
-
-  await db.execute(
-    `UPDATE customers SET ${setClauses.join(', ')} WHERE id = $${paramIdx}`,
-    values
-  );
+  gstin?: string | null;
+  stateCode?: string | null;
+  city?: string | null;
+  pincode?: string | null;
+  creditLimitPaise?: number | null;
+}
+
+export async function createCustomer(data: CustomerInput): Promise<number> {
+  return invoke<number>('create_customer', { customer: data, token: sessionToken() });
+}
+
+/** Replace a customer's details. */
+export async function updateCustomer(id: number, data: CustomerInput): Promise<void> {
+  await invoke('update_customer', { customerId: id, customer: data, token: sessionToken() });
 }
 
 export interface CustomerWithStats extends Customer {
@@ -119,3 +92,83 @@ export async function getCustomersWithStats(): Promise<CustomerWithStats[]> {
     lastPurchaseDate: row.last_purchase_date,
   }));
 }
+
+export interface CustomerOutstanding {
+  customerId: number;
+  creditSalesPaise: number;
+  creditReturnsPaise: number;
+  paymentsPaise: number;
+  outstandingPaise: number;
+}
+
+export interface OpenInvoice {
+  saleId: number;
+  invoiceNumber: string;
+  saleDate: string;
+  ageDays: number;
+  billedPaise: number;
+  duePaise: number;
+}
+
+export interface LedgerEntry {
+  date: string;
+  kind: 'sale' | 'return' | 'payment';
+  reference: string;
+  debitPaise: number;
+  creditPaise: number;
+  balancePaise: number;
+}
+
+export interface CustomerLedger {
+  customerId: number;
+  openingBalancePaise: number;
+  entries: LedgerEntry[];
+  closingBalancePaise: number;
+}
+
+export interface ReceivableAging {
+  customerId: number;
+  name: string;
+  phone: string | null;
+  creditLimitPaise: number | null;
+  currentPaise: number;
+  days31To60Paise: number;
+  days61To90Paise: number;
+  over90DaysPaise: number;
+  advancePaise: number;
+  outstandingPaise: number;
+  oldestInvoiceDate: string | null;
+}
+
+export async function getCustomerOutstanding(customerId: number): Promise<CustomerOutstanding> {
+  return invoke<CustomerOutstanding>('customer_outstanding', { customerId });
+}
+
+export async function getOpenInvoices(customerId: number): Promise<OpenInvoice[]> {
+  return invoke<OpenInvoice[]>('open_invoices', { customerId });
+}
+
+export async function getCustomerLedger(
+  customerId: number,
+  from: string,
+  to: string
+): Promise<CustomerLedger> {
+  return invoke<CustomerLedger>('customer_ledger', { customerId, from, to });
+}
+
+export async function getReceivablesAging(): Promise<ReceivableAging[]> {
+  return invoke<ReceivableAging[]>('receivables_aging');
+}
+
+/** Record money received; without allocations it settles the oldest invoices first */
+export async function recordCustomerPayment(payload: {
+  customerId: number;
+  amountPaise: number;
+  paymentMode: Exclude<PaymentMode, 'credit'>;
+  paymentDate?: string | null;
+  reference?: string | null;
+  notes?: string | null;
+  allocations?: { saleId: number; amountPaise: number }[];
+}): Promise<CustomerOutstanding> {
+  return invoke<CustomerOutstanding>('record_customer_payment', { payload, token: sessionToken() });
+}
@@ -0,0 +1,43 @@
+import { invoke } from '@tauri-apps/api/core';
+import { sessionToken } from '../session';
+import type { ExportSummary } from './gstReturns';
+
+export type DayBookKind =
+  | 'sale'
+  | 'customer_receipt'
+  | 'sale_return'
+  | 'supplier_payment'
+  | 'expense';
+
+export interface ModeTotals {
+  cashPaise: number;
+  cardPaise: number;
+  upiPaise: number;
+  creditPaise: number;
+}
+
+export interface DayBookEntry {
+  kind: DayBookKind;
+  reference: string | null;
+  party: string | null;
+  mode: string;
+  receiptPaise: number;
+  paymentPaise: number;
+}
+
+export interface DayBook {
+  date: string;
+  openingCashPaise: number;
+  entries: DayBookEntry[];
+  receipts: ModeTotals;
+  payments: ModeTotals;
+  closingCashPaise: number;
+}
+
+export async function getDayBook(date: string): Promise<DayBook> {
+  return invoke<DayBook>('day_book', { date });
+}
+
+export async function exportDayBookCsv(date: string, destPath: string): Promise<ExportSummary> {
+  return invoke<ExportSummary>('export_day_book_csv', { date, destPath, token: sessionToken() });
+}
@@ -0,0 +1,35 @@
+import { invoke } from '@tauri-apps/api/core';
+import { sessionToken } from '../session';
+
+export interface DatabaseStatus {
+  /** The file is SQLCipher-encrypted and needs a passphrase */
+  encrypted: boolean;
+  /** A passphrase has been given since the app started */
+  unlocked: boolean;
+}
+
+export async function getDatabaseStatus(): Promise<DatabaseStatus> {
+  return invoke<DatabaseStatus>('database_status');
+}
+
+/**
+ * Unlock the encrypted database at startup. Once it is open, unlocking again
+ * needs a signed-in user allowed to manage encryption.
+ */
+export async function unlockDatabase(key: string): Promise<void> {
+  const token = sessionToken();
+  await invoke('unlock_database', { key, token: token || null });
+}
+
+export async function verifyDbKey(key: string): Promise<boolean> {
+  return invoke<boolean>('verify_db_key', { key, token: sessionToken() });
+}
+
+/** One-time conversion of the plaintext database to an encrypted one. */
+export async function encryptDatabase(key: string): Promise<void> {
+  await invoke('encrypt_database', { key, token: sessionToken() });
+}
+
+export async function changeDbKey(oldKey: string, newKey: string): Promise<void> {
+  await invoke('change_db_key', { oldKey, newKey, token: sessionToken() });
+}
@@ -0,0 +1,98 @@
+import { invoke } from '@tauri-apps/api/core';
+import { sessionToken } from '../session';
+import type { PaymentMode } from '@/types';
+
+export interface ExpenseCategory {
+  id: number;
+  name: string;
+  isActive: boolean;
+}
+
+export interface Expense {
+  id: number;
+  categoryId: number;
+  category: string;
+  amountPaise: number;
+  expenseDate: string;
+  paymentMode: string;
+  paidTo: string | null;
+  reference: string | null;
+  notes: string | null;
+  userId: number;
+  createdAt: string;
+}
+
+export interface NewExpense {
+  categoryId: number;
+  amountPaise: number;
+  paymentMode: Exclude<PaymentMode, 'credit'>;
+  expenseDate: string | null;
+  paidTo: string | null;
+  reference: string | null;
+  notes: string | null;
+}
+
+export interface CategoryExpense {
+  categoryId: number;
+  category: string;
+  expenseCount: number;
+  totalPaise: number;
+}
+
+export interface ExpenseReport {
+  fromDate: string;
+  toDate: string;
+  categories: CategoryExpense[];
+  totalPaise: number;
+}
+
+export interface ProfitSummary {
+  fromDate: string;
+  toDate: string;
+  revenuePaise: number;
+  costPaise: number;
+  grossProfitPaise: number;
+  expenses: CategoryExpense[];
+  expensesPaise: number;
+  netProfitPaise: number;
+}
+
+export async function getExpenseCategories(): Promise<ExpenseCategory[]> {
+  return invoke<ExpenseCategory[]>('list_expense_categories');
+}
+
+export async function createExpenseCategory(name: string): Promise<ExpenseCategory> {
+  return invoke<ExpenseCategory>('create_expense_category', { name, token: sessionToken() });
+}
+
+export async function setExpenseCategoryActive(categoryId: number, isActive: boolean): Promise<void> {
+  return invoke('set_expense_category_active', { categoryId, isActive, token: sessionToken() });
+}
+
+export async function getExpenses(
+  fromDate: string,
+  toDate: string,
+  categoryId: number | null = null
+): Promise<Expense[]> {
+  return invoke<Expense[]>('list_expenses', { fromDate, toDate, categoryId });
+}
+
+export async function createExpense(payload: NewExpense): Promise<Expense> {
+  return invoke<Expense>('create_expense', { payload, token: sessionToken() });
+}
+
+export async function updateExpense(expenseId: number, payload: NewExpense): Promise<Expense> {
+  return invoke<Expense>('update_expense', { expenseId, payload, token: sessionToken() });
+}
+
+export async function deleteExpense(expenseId: number): Promise<void> {
+  return invoke('delete_expense', { expenseId, token: sessionToken() });
+}
+
+export async function getExpenseReport(fromDate: string, toDate: string): Promise<ExpenseReport> {
+  return invoke<ExpenseReport>('expense_report', { fromDate, toDate });
+}
+
+export async function getProfitSummary(fromDate: string, toDate: string): Promise<ProfitSummary> {
+  return invoke<ProfitSummary>('profit_summary', { token: sessionToken(), fromDate, toDate });
+}
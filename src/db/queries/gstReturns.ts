@@ -0,0 +1,112 @@
+import { invoke } from '@tauri-apps/api/core';
+import { sessionToken } from '../session';
+
+/** What a backend export wrote. */
+export interface ExportSummary {
+  path: string;
+  rowsWritten: number;
+}
+
+/** Tax on one rate; amounts are in rupees, as filed. */
+export interface Gstr1ItemDetail {
+  txval: number;
+  rt: number;
+  iamt: number;
+  camt: number;
+  samt: number;
+  csamt: number;
+}
+
+export interface Gstr1Invoice {
+  inum: string;
+  idt: string;
+  val: number;
+  pos?: string;
+  itms: { num: number; itm_det: Gstr1ItemDetail }[];
+}
+
+export interface Gstr1B2csRow extends Gstr1ItemDetail {
+  sply_ty: 'INTRA' | 'INTER';
+  pos: string;
+  typ: string;
+}
+
+export interface Gstr1HsnRow extends Gstr1ItemDetail {
+  num: number;
+  hsn_sc: string;
+  uqc: string;
+  qty: number;
+}
+
+export interface Gstr1DocRange {
+  num: number;
+  from: string;
+  to: string;
+  totnum: number;
+  cancel: number;
+  net_issue: number;
+}
+
+/** GSTR-1 in the portal's JSON layout. */
+export interface Gstr1 {
+  gstin: string;
+  fp: string;
+  b2b: { ctin: string; inv: Gstr1Invoice[] }[];
+  b2cl: { pos: string; inv: Gstr1Invoice[] }[];
+  b2cs: Gstr1B2csRow[];
+  hsn: { data: Gstr1HsnRow[] };
+  doc_issue: { doc_det: { doc_num: number; docs: Gstr1DocRange[] }[] };
+}
+
+/** GSTR-1 for a `YYYY-MM` period. */
+export async function getGstr1(period: string): Promise<Gstr1> {
+  return invoke<Gstr1>('gstr1_report', { period });
+}
+
+export async function exportGstr1Json(period: string, destPath: string): Promise<ExportSummary> {
+  return invoke<ExportSummary>('export_gstr1_json', { period, destPath, token: sessionToken() });
+}
+
+/** Writes the offline tool's sheets (b2b.csv, b2cs.csv, …) into a folder. */
+export async function exportGstr1Csv(period: string, destDir: string): Promise<ExportSummary[]> {
+  return invoke<ExportSummary[]>('export_gstr1_csv', { period, destDir, token: sessionToken() });
+}
+
+/** Taxable value and tax in paise. */
+export interface TaxValues {
+  taxablePaise: number;
+  igstPaise: number;
+  cgstPaise: number;
+  sgstPaise: number;
+}
+
+/** GSTR-3B figures for a month; outward supplies are net of credit notes. */
+export interface Gstr3bSummary {
+  month: string;
+  outwardTaxable: TaxValues;
+  outwardNilExemptPaise: number;
+  interstateUnregistered: { placeOfSupply: string; taxablePaise: number; igstPaise: number }[];
+  itcAvailable: TaxValues;
+  itcReversed: TaxValues;
+  netItc: TaxValues;
+  /** Purchases with no lines recorded, whose credit is not counted. */
+  purchasesWithoutLines: number;
+}
+
+export async function getGstr3bSummary(month: string): Promise<Gstr3bSummary> {
+  return invoke<Gstr3bSummary>('gstr3b_summary', { month });
+}
+
+/** Sales, purchase, receipt and payment vouchers for Tally, with their ledgers. */
+export async function exportTallyXml(
+  fromDate: string,
+  toDate: string,
+  destPath: string
+): Promise<ExportSummary> {
+  return invoke<ExportSummary>('export_tally_xml', {
+    fromDate,
+    toDate,
+    destPath,
+    token: sessionToken(),
+  });
+}
@@ -0,0 +1,62 @@
+import { invoke } from '@tauri-apps/api/core';
+import { sessionToken } from '../session';
+
+export interface Location {
+  id: number;
+  branchId: number;
+  name: string;
+  /** The shop floor sales are made from; one per branch */
+  isSelling: boolean;
+  isActive: boolean;
+}
+
+/** How much of a batch is kept at one location */
+export interface LocationQuantity {
+  locationId: number;
+  locationName: string;
+  isSelling: boolean;
+  quantity: number;
+}
+
+export interface LocationStock {
+  locationId: number;
+  branchId: number;
+  locationName: string;
+  isSelling: boolean;
+  batchCount: number;
+  quantity: number;
+  costValuePaise: number;
+  mrpValuePaise: number;
+}
+
+export async function listLocations(branchId?: number): Promise<Location[]> {
+  return invoke<Location[]>('list_locations', { branchId: branchId ?? null });
+}
+
+export async function createLocation(branchId: number, name: string): Promise<Location> {
+  return invoke<Location>('create_location', { branchId, name, token: sessionToken() });
+}
+
+export async function getBatchLocations(batchId: number): Promise<LocationQuantity[]> {
+  return invoke<LocationQuantity[]>('batch_locations', { batchId });
+}
+
+/** Move units of a batch between two locations of its branch. */
+export async function transferBetweenLocations(
+  batchId: number,
+  fromLocationId: number,
+  toLocationId: number,
+  quantity: number
+): Promise<LocationQuantity[]> {
+  return invoke<LocationQuantity[]>('transfer_between_locations', {
+    batchId,
+    fromLocationId,
+    toLocationId,
+    quantity,
+    token: sessionToken(),
+  });
+}
+
+export async function getStockByLocation(branchId?: number): Promise<LocationStock[]> {
+  return invoke<LocationStock[]>('stock_by_location', { branchId: branchId ?? null });
+}
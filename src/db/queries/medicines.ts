@@ -1,6 +1,8 @@
+import { invoke } from '@tauri-apps/api/core';
+import { sessionToken } from '../session';
 import { getDb } from '../index';
 import { toCamelCase, toBool } from '../utils';
-import type { Medicine, MedicineWithGst } from '@/types';
+import type { BatchStatus, DrugSchedule, Medicine, MedicineWithGst, SaleUnit } from '@/types';
 
 interface MedicineRow {
   id: number;
@@ -11,7 +13,11 @@ interface MedicineRow {
   dosage_form: string;
   strength: string | null;
   category: string | null;
+  schedule: DrugSchedule | null;
+  pack_size: number;
+  sale_unit: SaleUnit | null;
   hsn_code: string;
+  gtin: string | null;
   gst_slab_id: number;
   reorder_level: number;
   is_active: number;
@@ -74,7 +80,7 @@ export async function searchMedicines(term: string): Promise<MedicineWithGst[]>
   }));
 }
 
-export async function createMedicine(data: {
+export interface MedicineInput {
   name: string;
   genericName?: string | null;
   brandName?: string | null;
@@ -82,84 +88,85 @@ export async function createMedicine(data: {
   dosageForm: string;
   strength?: string | null;
   category?: string | null;
+  schedule?: DrugSchedule | null;
+  packSize?: number;
+  saleUnit?: SaleUnit | null;
   hsnCode: string;
+  gtin?: string | null;
   gstSlabId: number;
   reorderLevel: number;
-}): Promise<number> {
-  const db = await getDb();
-  const result = await db.execute(
-    `INSERT INTO medicines (name, generic_name, brand_name, manufacturer, dosage_form, strength, category, hsn_code, gst_slab_id, reorder_level)
-     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)`,
-    [
-      data.name,
-      data.genericName ?? null,
-      data.brandName ?? null,
-      data.manufacturer ?? null,
-      data.dosageForm,
-      data.strength ?? null,
-      data.category ?? null,
-      data.hsnCode,
-      data.gstSlabId,
-      data.reorderLevel,
-    ]
-  );
-  return result.lastInsertId ?? 0;
 }
 
-export async function updateMedicine(
-  id: number,
-  data: {
-    name?: string;
-    genericName?: string | null;
-    brandName?: string | null;
-    manufacturer?: string | null;
-    dosageForm?: string;
-    strength?: string | null;
-    category?: string | null;
-    hsnCode?: string;
-    gstSlabId?: number;
-    reorderLevel?: number;
-    isActive?: boolean;
-  }
-): Promise<void> {
-  const db = await getDb();
-  const setClauses: string[] = [];
-  const values: unknown[] = [];
-  let paramIdx = 1;
-
-  const fieldMap: Record<string, string> = {
-    name: 'name',
-    genericName: 'generic_name',
-    brandName: 'brand_name',
-    manufacturer: 'manufacturer',
-    dosageForm: 'dosage_form',
-    strength: 'strength',
-    category: 'category',
-    hsnCode: 'hsn_code',
-    gstSlabId: 'gst_slab_id',
-    reorderLevel: 'reorder_level',
-  };
+export async function createMedicine(data: MedicineInput): Promise<number> {
+  return invoke<number>('create_medicine', { medicine: data, token: sessionToken() });
+}
+
+/** Replace a medicine's details; a slab change goes through the rate history. */
+export async function updateMedicine(id: number, data: MedicineInput): Promise<void> {
+  await invoke('update_medicine', { medicineId: id, medicine: data, token: sessionToken() });
+
+  // A slab change goes through the rate history so past sales keep their rate.
+  await invoke('set_medicine_gst', {
+    medicineId: id,
+    gstSlabId: data.gstSlabId,
+    effectiveFrom: new Date().toISOString().slice(0, 10),
+    token: sessionToken(),
+  });
+}
 
-  for (const [jsKey, sqlKey] of Object.entries(fieldMap)) {
-    const value = data[jsKey as keyof typeof data];
-    if (value !== undefined) {
-      setClauses.push(`${sqlKey} = $${paramIdx++}`);
-      values.push(value);
-    }
-  }
+/**
+ * Take a medicine off sale. Fails while unexpired stock is left unless
+ * `force` is set.
+ */
+export async function deactivateMedicine(id: number, force = false): Promise<void> {
+  await invoke('deactivate_medicine', { medicineId: id, force, token: sessionToken() });
+}
+
+export async function reactivateMedicine(id: number): Promise<void> {
+  await invoke('reactivate_medicine', { medicineId: id, token: sessionToken() });
+}
+
+export interface ScanResult {
+  medicineId: number;
+  medicineName: string;
+  batchId: number;
+  batchNumber: string;
+  expiryDate: string;
+  sellingPricePaise: number;
+  quantity: number;
+  status: BatchStatus;
+  /** Returned anyway so the till can warn */
+  expired: boolean;
+}
 
-  if (data.isActive !== undefined) {
-    setClauses.push(`is_active = $${paramIdx++}`);
-    values.push(data.isActive ? 1 : 0);
-  }
+/** What a GS1 DataMatrix said about the pack */
+export interface Gs1Data {
+  /** Fourteen digits */
+  gtin: string;
+  batchNumber: string | null;
+  /** YYYY-MM-DD */
+  expiryDate: string | null;
+  serial: string | null;
+}
 
-  if (setClauses.length === 0) return;
+export type BarcodeSource = 'batch_barcode' | 'gtin' | 'gs1';
 
-  setClauses.push(`updated_at = datetime('now')`);
-  values.push(id);
+export interface BarcodeMatch {
+  source: BarcodeSource;
+  medicineId: number;
+  medicineName: string;
+  /** The scanned batch, or for a product barcode the next batch by FEFO */
+  batch: ScanResult | null;
+  gs1: Gs1Data | null;
+}
 
-  await db.execute(
-    `UPDATE medicines SET ${setClauses.join(', ')} WHERE id = $${paramIdx}`,
-    values
-  );
+/**
+ * Resolve a scanned code: a batch barcode, a GS1 DataMatrix, or a product
+ * EAN-13/GTIN. Returns null when nothing matches.
+ */
+export async function lookupBarcode(
+  code: string,
+  branchId?: number
+): Promise<BarcodeMatch | null> {
+  return invoke<BarcodeMatch | null>('lookup_barcode', { code, branchId: branchId ?? null });
 }
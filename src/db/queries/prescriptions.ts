@@ -1,3 +1,5 @@
+import { invoke } from '@tauri-apps/api/core';
+import { sessionToken } from '../session';
 import { getDb } from '../index';
 import { toCamelCase } from '../utils';
 import type { Prescription } from '@/types';
@@ -10,6 +12,8 @@ interface PrescriptionRow {
   rx_number: string | null;
   prescription_date: string;
   notes: string | null;
+  refills_allowed: number;
+  refills_used: number;
   created_at: string;
 }
 
@@ -26,34 +30,19 @@ export async function getPrescriptionsByCustomer(customerId: number): Promise<Pr
   return rows.map(mapPrescriptionRow);
 }
 
+/** Record a prescription; sales are linked to it when they are made. */
 export async function createPrescription(data: {
   customerId: number;
-  saleId?: number | null;
   doctorName: string;
   rxNumber?: string | null;
   prescriptionDate: string;
   notes?: string | null;
+  refillsAllowed?: number;
 }): Promise<number> {
-  const db = await getDb();
-  const result = await db.execute(
-    `INSERT INTO prescriptions (customer_id, sale_id, doctor_name, rx_number, prescription_date, notes)
-     VALUES ($1, $2, $3, $4, $5, $6)`,
-    [
-      data.customerId,
-      data.saleId ?? null,
-      data.doctorName,
-      data.rxNumber ?? null,
-      data.prescriptionDate,
-      data.notes ?? null,
-    ]
-  );
-  return result.lastInsertId ?? 0;
+  return invoke<number>('create_prescription', { prescription: data, token: sessionToken() });
 }
 
+/** Checks the customer matches and counts a refill if already linked. */
 export async function linkPrescriptionToSale(prescriptionId: number, saleId: number): Promise<void> {
-  const db = await getDb();
-  await db.execute(
-    'UPDATE prescriptions SET sale_id = $1 WHERE id = $2',
-    [saleId, prescriptionId]
-  );
+  await invoke('link_prescription', { prescriptionId, saleId, token: sessionToken() });
 }
@@ -0,0 +1,132 @@
+import { invoke } from '@tauri-apps/api/core';
+import { sessionToken } from '../session';
+
+/** One batch line of a supplier invoice */
+export interface GoodsReceiptLine {
+  medicineId: number;
+  batchNumber: string;
+  /** YYYY-MM-DD */
+  expiryDate: string;
+  costPricePaise: number;
+  mrpPaise: number;
+  sellingPricePaise: number;
+  quantity: number;
+}
+
+export interface ReceivedBatch {
+  batchId: number;
+  medicineId: number;
+  batchNumber: string;
+  quantity: number;
+  /** The batch already existed and the quantity was added to it */
+  merged: boolean;
+}
+
+export interface GoodsReceiptResult {
+  purchaseId: number;
+  totalPaise: number;
+  batches: ReceivedBatch[];
+}
+
+/**
+ * Book a supplier invoice into stock in one backend transaction, creating or
+ * topping up batches and, when given, counting it against a purchase order.
+ */
+export async function receiveGoods(data: {
+  supplierId: number;
+  invoiceNumber: string;
+  purchaseOrderId?: number | null;
+  lines: GoodsReceiptLine[];
+}): Promise<GoodsReceiptResult> {
+  return invoke<GoodsReceiptResult>('receive_goods', {
+    supplierId: data.supplierId,
+    invoiceNumber: data.invoiceNumber,
+    purchaseOrderId: data.purchaseOrderId ?? null,
+    lines: data.lines,
+    token: sessionToken(),
+  });
+}
+
+export type PurchaseReturnReason = 'near_expiry' | 'expired' | 'damaged' | 'other';
+
+/** pending until the supplier credits us for the returned goods */
+export type DebitNoteStatus = 'pending' | 'credited';
+
+export interface DebitNoteItem {
+  batchId: number;
+  medicineId: number;
+  medicineName: string;
+  batchNumber: string;
+  expiryDate: string;
+  quantity: number;
+  costPricePaise: number;
+  gstRate: number;
+  taxableAmountPaise: number;
+  cgstAmountPaise: number;
+  sgstAmountPaise: number;
+  igstAmountPaise: number;
+  totalPaise: number;
+}
+
+export interface DebitNote {
+  id: number;
+  debitNoteNumber: string;
+  supplierId: number;
+  supplierName: string;
+  supplierGstin: string | null;
+  reason: PurchaseReturnReason;
+  notes: string | null;
+  status: DebitNoteStatus;
+  /** The supplier's credit note number, once credited */
+  creditReference: string | null;
+  creditedAt: string | null;
+  returnDate: string;
+  taxableAmountPaise: number;
+  totalCgstPaise: number;
+  totalSgstPaise: number;
+  totalIgstPaise: number;
+  totalGstPaise: number;
+  totalPaise: number;
+  items: DebitNoteItem[];
+}
+
+/** Take units out of their batches and raise a debit note on the supplier. */
+export async function createPurchaseReturn(data: {
+  supplierId: number;
+  reason: PurchaseReturnReason;
+  notes?: string | null;
+  lines: { batchId: number; quantity: number }[];
+}): Promise<DebitNote> {
+  return invoke<DebitNote>('create_purchase_return', {
+    supplierId: data.supplierId,
+    reason: data.reason,
+    notes: data.notes ?? null,
+    lines: data.lines,
+    token: sessionToken(),
+  });
+}
+
+export async function getDebitNote(returnId: number): Promise<DebitNote> {
+  return invoke<DebitNote>('get_debit_note', { returnId });
+}
+
+export async function listDebitNotes(
+  filters: { supplierId?: number; status?: DebitNoteStatus } = {}
+): Promise<DebitNote[]> {
+  return invoke<DebitNote[]>('list_debit_notes', {
+    supplierId: filters.supplierId ?? null,
+    status: filters.status ?? null,
+  });
+}
+
+/** Record the supplier's credit note against a pending debit note. */
+export async function markDebitNoteCredited(
+  returnId: number,
+  creditReference: string
+): Promise<DebitNote> {
+  return invoke<DebitNote>('mark_debit_note_credited', {
+    returnId,
+    creditReference,
+    token: sessionToken(),
+  });
+}
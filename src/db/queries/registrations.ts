@@ -0,0 +1,35 @@
+import { invoke } from '@tauri-apps/api/core';
+
+export interface Registrations {
+  gstin?: string | null;
+  stateCode?: string | null;
+  drugLicenseNo?: string | null;
+  /** The pharmacy's own licence is mandatory; a party's is not */
+  drugLicenseRequired?: boolean;
+}
+
+export interface FieldProblem {
+  field: 'gstin' | 'stateCode' | 'drugLicenseNo';
+  message: string;
+}
+
+export interface GstinDetails {
+  gstin: string;
+  legalName: string;
+  tradeName: string;
+  /** `ACT` while the registration is active */
+  status: string;
+  stateCode: string;
+  address: string;
+  pincode: string;
+}
+
+/** Check GSTIN (including its check character) and drug licence before saving. */
+export async function validateRegistrations(registrations: Registrations): Promise<FieldProblem[]> {
+  return invoke<FieldProblem[]>('validate_registrations', { registrations });
+}
+
+/** Look a GSTIN up through the e-invoicing GSP configured in settings. */
+export async function lookupGstin(gstin: string): Promise<GstinDetails> {
+  return invoke<GstinDetails>('lookup_gstin', { gstin });
+}
@@ -0,0 +1,54 @@
+import { invoke } from '@tauri-apps/api/core';
+import { sessionToken } from '../session';
+
+export interface OrderSuggestion {
+  medicineId: number;
+  medicineName: string;
+  currentStock: number;
+  reorderLevel: number;
+  meanDailyDemand: number;
+  /** Loose units, rounded up to whole packs */
+  suggestedQuantity: number;
+  lastCostPaise: number;
+}
+
+export interface SupplierOrder {
+  /** Null for medicines never bought from anyone */
+  supplierId: number | null;
+  supplierName: string | null;
+  items: OrderSuggestion[];
+}
+
+export interface DraftedOrder {
+  id: number;
+  poNumber: string;
+  supplierId: number;
+  supplierName: string;
+  expectedTotalPaise: number;
+}
+
+/**
+ * What to order, grouped by the supplier each medicine was last bought from.
+ */
+export async function getOrderSuggestions(
+  lookbackDays: number,
+  branchId: number | null = null
+): Promise<SupplierOrder[]> {
+  return invoke<SupplierOrder[]>('suggest_orders', { lookbackDays, branchId });
+}
+
+/**
+ * Draft purchase orders from the suggestions, for one supplier or all of them.
+ */
+export async function draftSuggestedOrders(
+  lookbackDays: number,
+  supplierId: number | null,
+  branchId: number | null = null
+): Promise<DraftedOrder[]> {
+  return invoke<DraftedOrder[]>('draft_suggested_orders', {
+    lookbackDays,
+    branchId,
+    supplierId,
+    token: sessionToken(),
+  });
+}
@@ -1,4 +1,8 @@
+import { invoke } from '@tauri-apps/api/core';
+import { sessionToken } from '../session';
 import { getDb } from '../index';
+import { UNEXPIRED } from '../utils';
+import type { ModeTotals } from './dayBook';
 import type { StockAlert, ExpiryAlert } from '@/types';
 
 /**
@@ -10,7 +14,7 @@ export async function getStockAlerts(): Promise<StockAlert[]> {
     `SELECT 
        m.id as medicine_id,
        m.name as medicine_name,
-       COALESCE(SUM(CASE WHEN b.expiry_date > date('now') AND b.quantity > 0 THEN b.quantity ELSE 0 END), 0) as current_stock,
+       COALESCE(SUM(CASE WHEN ${UNEXPIRED} AND b.quantity > 0 AND b.status = 'available' THEN b.quantity ELSE 0 END), 0) as current_stock,
        m.reorder_level
      FROM medicines m
      LEFT JOIN batches b ON m.id = b.medicine_id
@@ -51,7 +55,7 @@ export async function getExpiryAlerts(nearExpiryDays = 90): Promise<ExpiryAlert[
      FROM batches b
      JOIN medicines m ON b.medicine_id = m.id
      WHERE b.quantity > 0
-       AND b.expiry_date > date('now')
+       AND ${UNEXPIRED}
        AND julianday(b.expiry_date) - julianday('now') <= $1
      ORDER BY b.expiry_date ASC`,
     [nearExpiryDays]
@@ -85,7 +89,7 @@ export async function getDashboardCounts(): Promise<{
       `SELECT COUNT(*) as count FROM (
          SELECT m.id
          FROM medicines m
-         LEFT JOIN batches b ON m.id = b.medicine_id AND b.expiry_date > date('now') AND b.quantity > 0
+         LEFT JOIN batches b ON m.id = b.medicine_id AND ${UNEXPIRED} AND b.quantity > 0 AND b.status = 'available'
          WHERE m.is_active = 1
          GROUP BY m.id
          HAVING COALESCE(SUM(b.quantity), 0) < m.reorder_level
@@ -94,7 +98,7 @@ export async function getDashboardCounts(): Promise<{
     db.select<{ count: number }[]>(
       `SELECT COUNT(*) as count FROM batches b
        JOIN medicines m ON b.medicine_id = m.id
-       WHERE b.quantity > 0 AND b.expiry_date > date('now')
+       WHERE b.quantity > 0 AND ${UNEXPIRED}
          AND julianday(b.expiry_date) - julianday('now') <= 90`
     ),
     db.select<{ count: number; revenue: number | null }[]>(
@@ -111,3 +115,185 @@ export async function getDashboardCounts(): Promise<{
     todayRevenuePaise: todaySales[0]?.revenue ?? 0,
   };
 }
+
+/** Revenue (taxable value) against the cost of the batches sold, net of returns. */
+export interface Margin {
+  revenuePaise: number;
+  costPaise: number;
+  grossProfitPaise: number;
+  marginPercent: number | null;
+}
+
+export interface InvoiceMargin extends Margin {
+  saleId: number;
+  invoiceNumber: string;
+  saleDate: string;
+  customer: string | null;
+}
+
+export interface MedicineMargin extends Margin {
+  medicineId: number;
+  name: string;
+  quantitySold: number;
+}
+
+export interface BatchMargin extends MedicineMargin {
+  batchId: number;
+  batchNumber: string;
+}
+
+export interface DailyMargin extends Margin {
+  date: string;
+  invoiceCount: number;
+}
+
+export interface MarginReport {
+  fromDate: string;
+  toDate: string;
+  total: Margin;
+  invoices: InvoiceMargin[];
+  medicines: MedicineMargin[];
+  batches: BatchMargin[];
+  days: DailyMargin[];
+}
+
+/**
+ * Gross profit and margin by invoice, medicine, batch and day.
+ */
+export async function getMarginReport(fromDate: string, toDate: string): Promise<MarginReport> {
+  return invoke<MarginReport>('margin_report', { token: sessionToken(), fromDate, toDate });
+}
+
+export type CostMethod = 'batch' | 'weighted_average';
+
+/** Loose units on hand at cost, MRP and selling price. */
+export interface Valuation {
+  quantity: number;
+  costValuePaise: number;
+  mrpValuePaise: number;
+  sellingValuePaise: number;
+}
+
+export interface GroupValuation extends Valuation {
+  name: string;
+}
+
+export interface StockValuation {
+  method: CostMethod;
+  categories: GroupValuation[];
+  manufacturers: GroupValuation[];
+  total: Valuation;
+  /** Expired stock, kept out of `total`. */
+  expired: Valuation;
+}
+
+/**
+ * Stock on hand valued by category and manufacturer.
+ */
+export async function getStockValuation(
+  method: CostMethod = 'batch',
+  branchId: number | null = null
+): Promise<StockValuation> {
+  return invoke<StockValuation>('stock_valuation', { branchId, method });
+}
+
+/** A batch with stock that has not sold for a while. */
+export interface IdleBatch {
+  batchId: number;
+  medicineId: number;
+  medicineName: string;
+  batchNumber: string;
+  expiryDate: string;
+  /** Negative once the batch has expired. */
+  daysToExpiry: number;
+  quantity: number;
+  valuePaise: number;
+  lastSoldOn: string | null;
+}
+
+export interface DeadStockReport {
+  idleDays: number;
+  batches: IdleBatch[];
+  valuePaise: number;
+}
+
+/**
+ * Batches with stock and no sales in the last `idleDays` days.
+ */
+export async function getDeadStockReport(
+  idleDays: number,
+  branchId: number | null = null
+): Promise<DeadStockReport> {
+  return invoke<DeadStockReport>('dead_stock_report', { idleDays, branchId });
+}
+
+export type AbcClass = 'A' | 'B' | 'C';
+
+export interface AbcRow {
+  medicineId: number;
+  name: string;
+  quantitySold: number;
+  salesValuePaise: number;
+  sharePercent: number;
+  cumulativePercent: number;
+  class: AbcClass;
+}
+
+export interface AbcClassSummary {
+  class: AbcClass;
+  medicineCount: number;
+  salesValuePaise: number;
+  sharePercent: number;
+}
+
+export interface AbcReport {
+  fromDate: string;
+  toDate: string;
+  totalSalesPaise: number;
+  medicines: AbcRow[];
+  classes: AbcClassSummary[];
+}
+
+/**
+ * A/B/C classification of the medicines sold in a date range.
+ */
+export async function getAbcAnalysis(fromDate: string, toDate: string): Promise<AbcReport> {
+  return invoke<AbcReport>('abc_analysis', { fromDate, toDate });
+}
+
+/** Bills made in one hour of the day (0-23) or weekday (0-6 from Sunday). */
+export interface SalesBucket {
+  bucket: number;
+  invoiceCount: number;
+  totalPaise: number;
+}
+
+export interface TopSeller {
+  medicineId: number;
+  name: string;
+  quantitySold: number;
+  salesValuePaise: number;
+}
+
+export interface SalesAnalytics {
+  fromDate: string;
+  toDate: string;
+  invoiceCount: number;
+  totalPaise: number;
+  averageBillPaise: number;
+  topSellers: TopSeller[];
+  byHour: SalesBucket[];
+  byWeekday: SalesBucket[];
+  paymentMix: ModeTotals;
+}
+
+/**
+ * Top sellers, hourly and weekday pattern, average bill and payment mix.
+ */
+export async function getSalesAnalytics(
+  fromDate: string,
+  toDate: string,
+  limit = 10
+): Promise<SalesAnalytics> {
+  return invoke<SalesAnalytics>('sales_analytics', { fromDate, toDate, limit });
+}
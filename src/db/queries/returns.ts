@@ -0,0 +1,50 @@
+import { invoke } from '@tauri-apps/api/core';
+import { sessionToken } from '../session';
+import type { PaymentMode } from '@/types';
+
+export interface SaleReturnItem {
+  saleItemId: number;
+  quantity: number;
+  /** Damaged or unfit to resell: credited but not put back into stock */
+  nonSaleable?: boolean;
+}
+
+export interface SaleReturnLine {
+  saleItemId: number;
+  batchId: number;
+  quantity: number;
+  taxableAmountPaise: number;
+  cgstAmountPaise: number;
+  sgstAmountPaise: number;
+  igstAmountPaise: number;
+  totalPaise: number;
+  restocked: boolean;
+  /** Restored to an expired batch, which should be quarantined */
+  batchExpired: boolean;
+}
+
+export interface SaleReturnResult {
+  returnId: number;
+  creditNoteNumber: string;
+  saleId: number;
+  totalGstPaise: number;
+  refundTotalPaise: number;
+  lines: SaleReturnLine[];
+}
+
+/**
+ * Take back sold units against their invoice and issue a credit note, with
+ * the GST of each line reversed in proportion to the units returned.
+ */
+export async function createSaleReturn(data: {
+  saleId: number;
+  items: SaleReturnItem[];
+  refundMode: PaymentMode;
+}): Promise<SaleReturnResult> {
+  return invoke<SaleReturnResult>('create_sale_return', {
+    saleId: data.saleId,
+    items: data.items,
+    refundMode: data.refundMode,
+    token: sessionToken(),
+  });
+}
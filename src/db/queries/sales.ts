@@ -1,6 +1,15 @@
+import { invoke } from '@tauri-apps/api/core';
+import { sessionToken } from '../session';
 import { getDb } from '../index';
 import { toCamelCase } from '../utils';
-import type { Sale, SaleItem, SaleWithDetails, SaleItemWithDetails, PaymentMode } from '@/types';
+import type {
+  Sale,
+  SaleItem,
+  SaleWithDetails,
+  SaleItemWithDetails,
+  PaymentMode,
+  SaleStatus,
+} from '@/types';
 
 interface SaleRow {
   id: number;
@@ -12,9 +21,15 @@ interface SaleRow {
   discount_paise: number;
   total_cgst_paise: number;
   total_sgst_paise: number;
+  total_igst_paise: number;
   total_gst_paise: number;
+  round_off_paise: number;
   grand_total_paise: number;
   payment_mode: string;
+  status: string;
+  is_interstate: number;
+  place_of_supply: string | null;
+  bill_of_supply: number;
   notes: string | null;
   created_at: string;
 }
@@ -32,6 +47,8 @@ interface SaleItemRow {
   cgst_amount_paise: number;
   sgst_rate: number;
   sgst_amount_paise: number;
+  igst_rate: number;
+  igst_amount_paise: number;
   total_paise: number;
   hsn_code: string;
 }
@@ -40,6 +57,9 @@ function mapSaleRow(row: SaleRow): Sale {
   return {
     ...toCamelCase<Sale>(row),
     paymentMode: row.payment_mode as PaymentMode,
+    status: row.status as SaleStatus,
+    isInterstate: row.is_interstate === 1,
+    billOfSupply: row.bill_of_supply === 1,
   };
 }
 
@@ -59,10 +79,21 @@ export async function getSales(limit = 100, offset = 0): Promise<Sale[]> {
 export async function getSaleById(id: number): Promise<SaleWithDetails | null> {
   const db = await getDb();
 
-  const saleRows = await db.select<(SaleRow & { customer_name: string | null; user_name: string })[]>(
-    `SELECT s.*, c.name as customer_name, u.full_name as user_name
+  const saleRows = await db.select<(SaleRow & {
+    customer_name: string | null;
+    customer_gstin: string | null;
+    customer_state_code: string | null;
+    user_name: string;
+    irn: string | null;
+    ewb_no: string | null;
+    ewb_valid_upto: string | null;
+  })[]>(
+    `SELECT s.*, c.name as customer_name, c.gstin as customer_gstin, c.state_code as customer_state_code,
+       u.full_name as user_name, e.irn, w.ewb_no, w.valid_upto as ewb_valid_upto
      FROM sales s
      LEFT JOIN customers c ON s.customer_id = c.id
+     LEFT JOIN sale_einvoices e ON e.sale_id = s.id
+     LEFT JOIN sale_eway_bills w ON w.sale_id = s.id AND w.cancelled_at IS NULL
      JOIN users u ON s.user_id = u.id
      WHERE s.id = $1`,
     [id]
@@ -91,7 +122,12 @@ export async function getSaleById(id: number): Promise<SaleWithDetails | null> {
   return {
     ...mapSaleRow(saleRow),
     customerName: saleRow.customer_name,
+    customerGstin: saleRow.customer_gstin,
+    customerStateCode: saleRow.customer_state_code,
     userName: saleRow.user_name,
+    irn: saleRow.irn,
+    ewbNo: saleRow.ewb_no,
+    ewbValidUpto: saleRow.ewb_valid_upto,
     items,
   };
 }
@@ -116,104 +152,119 @@ export async function getSalesWithDetails(limit = 100, offset = 0): Promise<(Sal
   }));
 }
 
-/**
- * Get and increment the next invoice number atomically.
- * Returns the formatted invoice number like "INV-000001".
- */
-export async function getNextInvoiceNumber(): Promise<string> {
-  const db = await getDb();
-
-  const rows = await db.select<{ invoice_prefix: string; next_invoice_number: number }[]>(
-    'SELECT invoice_prefix, next_invoice_number FROM pharmacy_settings WHERE id = 1'
-  );
-
-  const prefix = rows[0]?.invoice_prefix ?? 'INV';
-  const number = rows[0]?.next_invoice_number ?? 1;
-
-  // Increment for next use
-  await db.execute(
-    'UPDATE pharmacy_settings SET next_invoice_number = next_invoice_number + 1 WHERE id = 1'
-  );
-
-  return `${prefix}-${String(number).padStart(6, '0')}`;
+/** The part of a bill settled in one mode. */
+export interface SalePayment {
+  mode: PaymentMode;
+  amountPaise: number;
 }
 
 export interface CreateSaleData {
-  invoiceNumber?: string;
   customerId: number | null;
-  userId: number;
+  /** Must add up to the grand total exactly; one entry per mode */
+  payments: SalePayment[];
+  notes?: string | null;
+  /** Required when the cart holds a Schedule H, H1 or X medicine */
+  prescriptionId?: number | null;
+  /** Reused when retrying a checkout so it records at most one sale */
+  idempotencyKey?: string | null;
+  items: CreateSaleItemData[];
+}
+
+export interface CreateSaleItemData {
+  batchId: number;
+  quantity: number;
+  discountPaise: number;
+}
+
+export interface InvoiceTotals {
   subtotalPaise: number;
   discountPaise: number;
   totalCgstPaise: number;
   totalSgstPaise: number;
+  totalIgstPaise: number;
   totalGstPaise: number;
+  roundOffPaise: number;
   grandTotalPaise: number;
-  paymentMode: PaymentMode;
-  notes?: string | null;
-  items: CreateSaleItemData[];
 }
 
-export interface CreateSaleItemData {
-  batchId: number;
-  medicineId: number;
+export interface InvoiceLine {
+  medicineName: string;
+  batchNumber: string;
+  expiryDate: string;
+  hsnCode: string;
   quantity: number;
   unitPricePaise: number;
   discountPaise: number;
   taxableAmountPaise: number;
   cgstRate: number;
-  cgstAmountPaise: number;
+  cgstPaise: number;
   sgstRate: number;
-  sgstAmountPaise: number;
+  sgstPaise: number;
+  igstRate: number;
+  igstPaise: number;
   totalPaise: number;
-  hsnCode: string;
+}
+
+/** The printable invoice of a recorded sale, as the backend stores it. */
+export interface InvoiceDocument {
+  saleId: number;
+  invoiceNumber: string;
+  saleDate: string;
+  status: string;
+  cashier: string;
+  pharmacy: {
+    name: string;
+    address: string;
+    city: string;
+    pincode: string;
+    phone: string;
+    email: string | null;
+    gstin: string;
+    drugLicenseNo: string;
+    stateCode: string;
+  };
+  /** `null` for walk-in sales. */
+  customer: {
+    id: number;
+    name: string;
+    phone: string | null;
+    address: string | null;
+    gstin: string | null;
+    stateCode: string | null;
+  } | null;
+  isInterstate: boolean;
+  billOfSupply: boolean;
+  placeOfSupply: string;
+  items: InvoiceLine[];
+  totals: InvoiceTotals;
+  payments: SalePayment[];
+  amountInWords: string;
+  notes: string | null;
+  einvoice: { irn: string; ackNo: string; ackDate: string; signedQrCode: string } | null;
+  ewayBill: { ewbNo: string; ewbDate: string; validUpto: string } | null;
+}
+
+export interface CreateSaleResult {
+  saleId: number;
+  invoiceNumber: string;
+  totals: InvoiceTotals;
+  /** The finished invoice, ready to print. */
+  invoice: InvoiceDocument;
 }
 
 /**
- * Create a complete sale with all items in a single transaction.
- * Also deducts batch quantities.
+ * Create a complete sale with all items in a single backend transaction.
+ * The backend allocates the invoice number, prices each line from its batch
+ * and deducts stock; if any batch is short, nothing is written. The finished
+ * invoice comes back with the result.
  */
-export async function createSale(data: CreateSaleData): Promise<number> {
-  const db = await getDb();
-  // Step 1: Read invoice counter (read-only, no lock needed)
-  const settingsRows = await db.select<{ invoice_prefix: string; next_invoice_number: number }[]>(
-    'SELECT invoice_prefix, next_invoice_number FROM pharmacy_settings WHERE id = 1'
-  );
-  const prefix = settingsRows[0]?.invoice_prefix ?? 'INV';
-  const number = settingsRows[0]?.next_invoice_number ?? 1;
-  const invoiceNumber = data.invoiceNumber ?? `${prefix}-${String(number).padStart(6, '0')}`;
-  // Step 2: Increment invoice counter + insert sale header atomically.
-  // tauri-plugin-sql has no transaction() API; separate execute() calls go through SQLx
-  // connection pooling and cannot share a BEGIN/COMMIT across calls.
-  // We use a single multi-statement execute() for atomicity within each step.
-  const customerIdLiteral = data.customerId === null ? 'NULL' : String(data.customerId);
-  const notesLiteral = data.notes ? `'${data.notes.replace(/'/g, "''")}'` : 'NULL';
-  const headerSql = [
-    'BEGIN;',
-    `UPDATE pharmacy_settings SET next_invoice_number = next_invoice_number + 1 WHERE id = 1;`,
-    `INSERT INTO sales (invoice_number, customer_id, user_id, subtotal_paise, discount_paise, total_cgst_paise, total_sgst_paise, total_gst_paise, grand_total_paise, payment_mode, notes)`,
-    `VALUES ('${invoiceNumber}', ${customerIdLiteral}, ${data.userId}, ${data.subtotalPaise}, ${data.discountPaise}, ${data.totalCgstPaise}, ${data.totalSgstPaise}, ${data.totalGstPaise}, ${data.grandTotalPaise}, '${data.paymentMode}', ${notesLiteral});`,
-    'COMMIT;',
-  ].join('\n');
-  const headerResult = await db.execute(headerSql);
-  const saleId = headerResult.lastInsertId ?? 0;
-
-  // Step 3: Insert all sale items + deduct stock in one atomic execute().
-  // saleId is known, so no last_insert_rowid() ambiguity across items.
-  if (data.items.length > 0) {
-    const itemStatements = data.items.map((item) => {
-      const hsnLiteral = `'${item.hsnCode.replace(/'/g, "''")}'`;
-      return [
-        `INSERT INTO sale_items (sale_id, batch_id, medicine_id, quantity, unit_price_paise, discount_paise, taxable_amount_paise, cgst_rate, cgst_amount_paise, sgst_rate, sgst_amount_paise, total_paise, hsn_code)`,
-        `VALUES (${saleId}, ${item.batchId}, ${item.medicineId}, ${item.quantity}, ${item.unitPricePaise}, ${item.discountPaise}, ${item.taxableAmountPaise}, ${item.cgstRate}, ${item.cgstAmountPaise}, ${item.sgstRate}, ${item.sgstAmountPaise}, ${item.totalPaise}, ${hsnLiteral});`,
-        `UPDATE batches SET quantity = quantity - ${item.quantity} WHERE id = ${item.batchId};`,
-      ].join(' ');
-    });
-
-    const itemsSql = ['BEGIN;', ...itemStatements, 'COMMIT;'].join('\n');
-    await db.execute(itemsSql);
-  }
-
-  return saleId;
+export async function createSale(data: CreateSaleData): Promise<CreateSaleResult> {
+  return invoke<CreateSaleResult>('create_sale', { payload: data, token: sessionToken() });
+}
+
+/** How a sale was settled, in the order the payments were taken. */
+export async function getSalePaymentBreakdown(saleId: number): Promise<SalePayment[]> {
+  return invoke<SalePayment[]>('sale_payment_breakdown', { saleId });
 }
 
 export async function getSalesByDateRange(
@@ -0,0 +1,66 @@
+import { invoke } from '@tauri-apps/api/core';
+import { sessionToken } from '../session';
+
+export type RunSource = 'schedule' | 'manual';
+
+export type RunStatus = 'running' | 'succeeded' | 'failed';
+
+export interface JobRun {
+  id: number;
+  jobKey: string;
+  source: RunSource;
+  /** Who ran it by hand; null for scheduled runs */
+  triggeredBy: number | null;
+  /** UTC, YYYY-MM-DD HH:MM:SS */
+  startedAt: string;
+  finishedAt: string | null;
+  status: RunStatus;
+  /** What the job reported, or the error it failed with */
+  message: string | null;
+}
+
+export interface ScheduledJob {
+  key: string;
+  description: string;
+  /** Five-field cron expression, local time */
+  schedule: string;
+  enabled: boolean;
+  lastRunAt: string | null;
+  /** UTC; null while paused or when the schedule never matches */
+  nextRunAt: string | null;
+  lastRun: JobRun | null;
+}
+
+export async function getScheduledJobs(): Promise<ScheduledJob[]> {
+  return invoke<ScheduledJob[]>('list_scheduled_jobs');
+}
+
+export async function getJobRuns(jobKey: string, limit = 20): Promise<JobRun[]> {
+  return invoke<JobRun[]>('scheduled_job_runs', { jobKey, limit });
+}
+
+/**
+ * Pause or resume a job; resuming schedules it from now.
+ */
+export async function setJobEnabled(jobKey: string, enabled: boolean): Promise<ScheduledJob> {
+  return invoke<ScheduledJob>('set_scheduled_job_enabled', {
+    jobKey,
+    enabled,
+    token: sessionToken(),
+  });
+}
+
+export async function setJobSchedule(jobKey: string, schedule: string): Promise<ScheduledJob> {
+  return invoke<ScheduledJob>('set_scheduled_job_schedule', {
+    jobKey,
+    schedule,
+    token: sessionToken(),
+  });
+}
+
+/**
+ * Run a job now, whatever its schedule. Resolves once the run has finished.
+ */
+export async function triggerJob(jobKey: string): Promise<JobRun> {
+  return invoke<JobRun>('trigger_scheduled_job', { jobKey, token: sessionToken() });
+}
@@ -1,6 +1,8 @@
+import { invoke } from '@tauri-apps/api/core';
 import { getDb } from '../index';
+import { sessionToken } from '../session';
 import { toCamelCase } from '../utils';
-import type { PharmacySettings } from '@/types';
+import type { PharmacySettings, PrinterInterface, RemoteBackupTarget } from '@/types';
 
 interface SettingsRow {
   id: number;
@@ -11,10 +13,51 @@ interface SettingsRow {
   gstin: string;
   drug_license_no: string;
   state_code: string;
+  city: string;
+  pincode: string;
+  einvoice_enabled: number;
   invoice_prefix: string;
   next_invoice_number: number;
+  invoice_number_format: string;
+  fy_reset: number;
+  invoice_sequence_fy: string | null;
+  credit_note_prefix: string;
+  next_credit_note_number: number;
+  credit_note_sequence_fy: string | null;
+  debit_note_prefix: string;
+  next_debit_note_number: number;
+  debit_note_sequence_fy: string | null;
   low_stock_threshold: number;
   near_expiry_days: number;
+  reorder_lead_time_days: number;
+  prices_include_gst: number;
+  composition_scheme: number;
+  loyalty_spend_per_point_paise: number;
+  loyalty_point_value_paise: number;
+  idempotency_window_hours: number;
+  lockout_max_attempts: number;
+  lockout_window_minutes: number;
+  lockout_minutes: number;
+  printer_interface: string;
+  printer_address: string;
+  printer_line_width: number;
+  auto_backup_enabled: number;
+  auto_backup_time: string;
+  auto_backup_keep: number;
+  auto_backup_dir: string | null;
+  remote_backup_target: string;
+  remote_backup_url: string;
+  remote_backup_region: string;
+  remote_backup_username: string;
+  maintenance_interval_days: number;
+  einvoice_api_url: string;
+  einvoice_client_id: string;
+  einvoice_client_secret: string;
+  einvoice_username: string;
+  einvoice_password: string;
+  ewaybill_username: string;
+  ewaybill_password: string;
+  ewaybill_threshold_paise: number;
   created_at: string;
   updated_at: string;
 }
@@ -29,7 +72,14 @@ export async function getSettings(): Promise<PharmacySettings> {
     throw new Error('Pharmacy settings not found. Database may not be initialized.');
   }
 
-  return toCamelCase<PharmacySettings>(rows[0]);
+  return {
+    ...toCamelCase<PharmacySettings>(rows[0]),
+    einvoiceEnabled: rows[0].einvoice_enabled === 1,
+    pricesIncludeGst: rows[0].prices_include_gst === 1,
+    compositionScheme: rows[0].composition_scheme === 1,
+    fyReset: rows[0].fy_reset === 1,
+    autoBackupEnabled: rows[0].auto_backup_enabled === 1,
+  };
 }
 
 export async function updateSettings(data: {
@@ -40,42 +90,73 @@ export async function updateSettings(data: {
   gstin?: string;
   drugLicenseNo?: string;
   stateCode?: string;
+  city?: string;
+  pincode?: string;
+  einvoiceEnabled?: boolean;
   invoicePrefix?: string;
+  invoiceNumberFormat?: string;
+  fyReset?: boolean;
+  creditNotePrefix?: string;
+  debitNotePrefix?: string;
   lowStockThreshold?: number;
   nearExpiryDays?: number;
+  reorderLeadTimeDays?: number;
+  pricesIncludeGst?: boolean;
+  compositionScheme?: boolean;
+  loyaltySpendPerPointPaise?: number;
+  loyaltyPointValuePaise?: number;
+  idempotencyWindowHours?: number;
+  lockoutMaxAttempts?: number;
+  lockoutWindowMinutes?: number;
+  lockoutMinutes?: number;
+  printerInterface?: PrinterInterface;
+  printerAddress?: string;
+  printerLineWidth?: number;
+  autoBackupEnabled?: boolean;
+  autoBackupTime?: string;
+  autoBackupKeep?: number;
+  autoBackupDir?: string | null;
+  remoteBackupTarget?: RemoteBackupTarget;
+  remoteBackupUrl?: string;
+  remoteBackupRegion?: string;
+  remoteBackupUsername?: string;
+  maintenanceIntervalDays?: number;
+  einvoiceApiUrl?: string;
+  einvoiceClientId?: string;
+  einvoiceClientSecret?: string;
+  einvoiceUsername?: string;
+  einvoicePassword?: string;
+  ewaybillUsername?: string;
+  ewaybillPassword?: string;
+  ewaybillThresholdPaise?: number;
 }): Promise<void> {
-  const db = await getDb();
-  const setClauses: string[] = [];
-  const values: unknown[] = [];
-  let paramIdx = 1;
-
-  const fieldMap: Record<string, string> = {
-    name: 'name',
-    address: 'address',
-    phone: 'phone',
-    email: 'email',
-    gstin: 'gstin',
-    drugLicenseNo: 'drug_license_no',
-    stateCode: 'state_code',
-    invoicePrefix: 'invoice_prefix',
-    lowStockThreshold: 'low_stock_threshold',
-    nearExpiryDays: 'near_expiry_days',
-  };
-
-  for (const [jsKey, sqlKey] of Object.entries(fieldMap)) {
-    const value = data[jsKey as keyof typeof data];
-    if (value !== undefined) {
-      setClauses.push(`${sqlKey} = $${paramIdx++}`);
-      values.push(value);
-    }
-  }
+  await invoke('update_settings', { settings: data, token: sessionToken() });
+}
 
-  if (setClauses.length === 0) return;
+/** Which off-site backup secrets are saved; the backend never returns them */
+export interface RemoteBackupSecretsSet {
+  secret: boolean;
+  refreshToken: boolean;
+  passphrase: boolean;
+}
 
-  setClauses.push(`updated_at = datetime('now')`);
+export async function getRemoteBackupSecrets(): Promise<RemoteBackupSecretsSet> {
+  return invoke<RemoteBackupSecretsSet>('remote_backup_secrets', { token: sessionToken() });
+}
 
-  await db.execute(
-    `UPDATE pharmacy_settings SET ${setClauses.join(', ')} WHERE id = 1`,
-    values
-  );
+/**
+ * Save off-site backup secrets outside the database; fields left undefined
+ * keep their saved value.
+ */
+export async function setRemoteBackupSecrets(secrets: {
+  secret?: string;
+  refreshToken?: string;
+  passphrase?: string;
+}): Promise<RemoteBackupSecretsSet> {
+  return invoke<RemoteBackupSecretsSet>('set_remote_backup_secrets', {
+    secret: secrets.secret ?? null,
+    refreshToken: secrets.refreshToken ?? null,
+    passphrase: secrets.passphrase ?? null,
+    token: sessionToken(),
+  });
 }
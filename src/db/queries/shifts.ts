@@ -0,0 +1,62 @@
+import { invoke } from '@tauri-apps/api/core';
+import { sessionToken } from '../session';
+import type { ModeTotals } from './dayBook';
+
+export interface Shift {
+  id: number;
+  userId: number;
+  cashier: string;
+  openedAt: string;
+  openingFloatPaise: number;
+  closedAt: string | null;
+  closedBy: number | null;
+  notes: string | null;
+}
+
+export interface DrawerTotals {
+  cashPaise: number;
+  cardPaise: number;
+  upiPaise: number;
+}
+
+/** A closed shift's Z-report, or the running X-report of an open one */
+export interface ZReport {
+  shift: Shift;
+  saleCount: number;
+  salesTotalPaise: number;
+  sales: ModeTotals;
+  receipts: ModeTotals;
+  refunds: ModeTotals;
+  paidOut: ModeTotals;
+  expected: DrawerTotals;
+  counted: DrawerTotals | null;
+  variance: DrawerTotals | null;
+}
+
+export async function getCurrentShift(): Promise<Shift | null> {
+  return invoke<Shift | null>('current_shift', { token: sessionToken() });
+}
+
+export async function openShift(openingFloatPaise: number, notes: string | null): Promise<Shift> {
+  return invoke<Shift>('open_shift', { token: sessionToken(), openingFloatPaise, notes });
+}
+
+export async function closeShift(
+  shiftId: number,
+  counted: DrawerTotals,
+  notes: string | null
+): Promise<ZReport> {
+  return invoke<ZReport>('close_shift', { shiftId, counted, notes, token: sessionToken() });
+}
+
+export async function getShiftReport(shiftId: number): Promise<ZReport> {
+  return invoke<ZReport>('shift_report', { shiftId });
+}
+
+export async function getShifts(fromDate: string, toDate: string): Promise<Shift[]> {
+  return invoke<Shift[]>('list_shifts', { fromDate, toDate });
+}
+
+export async function printShiftReport(shiftId: number): Promise<void> {
+  return invoke('print_shift_report', { shiftId });
+}
@@ -0,0 +1,101 @@
+import { invoke } from '@tauri-apps/api/core';
+import { sessionToken } from '../session';
+
+export type StockTakeStatus = 'open' | 'finalized';
+
+/** One batch on a stock take sheet */
+export interface StockTakeLine {
+  batchId: number;
+  medicineId: number;
+  medicineName: string;
+  batchNumber: string;
+  /** What the books held when the take began */
+  expectedQuantity: number;
+  /** null until the batch has been counted */
+  countedQuantity: number | null;
+  countedAt: string | null;
+  adjustmentId: number | null;
+}
+
+export interface StockTake {
+  id: number;
+  /** null when every branch is being counted */
+  branchId: number | null;
+  status: StockTakeStatus;
+  notes: string | null;
+  startedBy: number;
+  startedAt: string;
+  finalizedAt: string | null;
+  lines: StockTakeLine[];
+}
+
+export interface VarianceLine {
+  batchId: number;
+  medicineName: string;
+  batchNumber: string;
+  expectedQuantity: number;
+  countedQuantity: number;
+  /** Negative for a shortage */
+  variance: number;
+  varianceValuePaise: number;
+}
+
+export interface VarianceReport {
+  stockTakeId: number;
+  countedBatches: number;
+  uncountedBatches: number;
+  lines: VarianceLine[];
+  shortageValuePaise: number;
+  excessValuePaise: number;
+}
+
+/** Snapshot every batch in stock, at one branch or all of them, for counting. */
+export async function startStockTake(
+  options: { branchId?: number | null; notes?: string | null } = {}
+): Promise<StockTake> {
+  return invoke<StockTake>('start_stock_take', {
+    branchId: options.branchId ?? null,
+    notes: options.notes ?? null,
+    token: sessionToken(),
+  });
+}
+
+export async function getStockTake(stockTakeId: number): Promise<StockTake> {
+  return invoke<StockTake>('get_stock_take', { stockTakeId });
+}
+
+export async function recordStockCount(
+  stockTakeId: number,
+  batchId: number,
+  countedQuantity: number
+): Promise<StockTakeLine> {
+  return invoke<StockTakeLine>('record_stock_count', {
+    stockTakeId,
+    batchId,
+    countedQuantity,
+    token: sessionToken(),
+  });
+}
+
+/** Record a count against the batch behind a scanned barcode. */
+export async function recordScannedCount(
+  stockTakeId: number,
+  barcode: string,
+  countedQuantity: number
+): Promise<StockTakeLine> {
+  return invoke<StockTakeLine>('record_scanned_count', {
+    stockTakeId,
+    barcode,
+    countedQuantity,
+    token: sessionToken(),
+  });
+}
+
+export async function getVarianceReport(stockTakeId: number): Promise<VarianceReport> {
+  return invoke<VarianceReport>('stock_take_variance_report', { stockTakeId });
+}
+
+/** Close the take and post every variance as a stock adjustment. */
+export async function finalizeStockTake(stockTakeId: number): Promise<VarianceReport> {
+  return invoke<VarianceReport>('finalize_stock_take', { stockTakeId, token: sessionToken() });
+}
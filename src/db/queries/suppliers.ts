@@ -1,3 +1,5 @@
+import { invoke } from '@tauri-apps/api/core';
+import { sessionToken } from '../session';
 import { getDb } from '../index';
 import { toCamelCase } from '../utils';
 import type { Supplier, SupplierPayment, PaymentMode } from '@/types';
@@ -43,71 +45,22 @@ export async function searchSuppliers(term: string): Promise<Supplier[]> {
   return rows.map(mapSupplierRow);
 }
 
-export async function createSupplier(data: {
+export interface SupplierInput {
   name: string;
   phone?: string | null;
   email?: string | null;
   address?: string | null;
   gstIn?: string | null;
   drugLicenseNo?: string | null;
-}): Promise<number> {
-  const db = await getDb();
-  const result = await db.execute(
-    'INSERT INTO suppliers (name, phone, email, address, gst_in, drug_license_no) VALUES ($1, $2, $3, $4, $5, $6)',
-    [
-      data.name,
-      data.phone ?? null,
-      data.email ?? null,
-      data.address ?? null,
-      data.gstIn ?? null,
-      data.drugLicenseNo ?? null,
-    ]
-  );
-  return result.lastInsertId ?? 0;
-}
-
-export async function updateSupplier(
-  id: number,
-  data: {
-    name?: string;
-    phone?: string | null;
-    email?: string | null;
-    address?: string | null;
-    gstIn?: string | null;
-    drugLicenseNo?: string | null;
-  }
-): Promise<void> {
-  const db = await getDb();
-  const setClauses: string[] = [];
-  const values: unknown[] = [];
-  let paramIdx = 1;
-
-  const fieldMap: Record<string, string> = {
-    name: 'name',
-    phone: 'phone',
-    email: 'email',
-    address: 'address',
-    gstIn: 'gst_in',
-    drugLicenseNo: 'drug_license_no',
-  };
-
-  for (const [jsKey, sqlKey] of Object.entries(fieldMap)) {
-    const value = data[jsKey as keyof typeof data];
-    if (value !== undefined) {
-      setClauses.push(`${sqlKey} = $${paramIdx++}`);
-      values.push(value);
-    }
-  }
-
-  if (setClauses.length === 0) return;
+}
 
-  setClauses.push(`updated_at = datetime('now')`);
-  values.push(id);
+export async function createSupplier(data: SupplierInput): Promise<number> {
+  return invoke<number>('create_supplier', { supplier: data, token: sessionToken() });
+}
 
-  await db.execute(
-    `UPDATE suppliers SET ${setClauses.join(', ')} WHERE id = $${paramIdx}`,
-    values
-  );
+/** Replace a supplier's details. */
+export async function updateSupplier(id: number, data: SupplierInput): Promise<void> {
+  await invoke('update_supplier', { supplierId: id, supplier: data, token: sessionToken() });
 }
 
 // Supplier Payments
@@ -139,6 +92,27 @@ export async function getSupplierPayments(supplierId: number): Promise<SupplierP
   return rows.map(mapPaymentRow);
 }
 
+export interface OpenPurchase {
+  purchaseId: number;
+  invoiceNumber: string;
+  purchaseDate: string;
+  ageDays: number;
+  totalPaise: number;
+  duePaise: number;
+}
+
+export interface SupplierPayables {
+  supplierId: number;
+  supplierName: string;
+  invoices: OpenPurchase[];
+  currentPaise: number;
+  days31To60Paise: number;
+  over60DaysPaise: number;
+  advancePaise: number;
+  outstandingPaise: number;
+}
+
+/** Record a payment; without allocations it settles the oldest invoices first */
 export async function createSupplierPayment(data: {
   supplierId: number;
   amountPaise: number;
@@ -146,21 +120,20 @@ export async function createSupplierPayment(data: {
   paymentMode: PaymentMode;
   reference?: string | null;
   notes?: string | null;
-}): Promise<number> {
-  const db = await getDb();
-  const result = await db.execute(
-    `INSERT INTO supplier_payments (supplier_id, amount_paise, payment_date, payment_mode, reference, notes)
-     VALUES ($1, $2, $3, $4, $5, $6)`,
-    [
-      data.supplierId,
-      data.amountPaise,
-      data.paymentDate,
-      data.paymentMode,
-      data.reference ?? null,
-      data.notes ?? null,
-    ]
-  );
-  return result.lastInsertId ?? 0;
+  allocations?: { purchaseId: number; amountPaise: number }[];
+}): Promise<SupplierPayables> {
+  return invoke<SupplierPayables>('record_supplier_payment', {
+    payload: { ...data, allocations: data.allocations ?? [] },
+    token: sessionToken(),
+  });
+}
+
+export async function getSupplierPayables(supplierId: number): Promise<SupplierPayables> {
+  return invoke<SupplierPayables>('supplier_payables', { supplierId });
+}
+
+export async function getPayablesAging(): Promise<SupplierPayables[]> {
+  return invoke<SupplierPayables[]>('payables_aging');
 }
 
 export interface SupplierWithPayments extends Supplier {
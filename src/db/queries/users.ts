@@ -1,6 +1,8 @@
+import { invoke } from '@tauri-apps/api/core';
+import { sessionToken } from '../session';
 import { getDb } from '../index';
 import { toCamelCase, toBool } from '../utils';
-import type { User, UserRole } from '@/types';
+import type { Session, User, UserRole } from '@/types';
 
 interface UserRow {
   id: number;
@@ -45,59 +47,64 @@ export async function getUserByUsername(username: string): Promise<User | null>
   return rows.length > 0 ? mapUserRow(rows[0]) : null;
 }
 
+/**
+ * Create a user; the backend hashes the password. Without a signed-in
+ * session this only succeeds for the first admin on an empty database.
+ */
 export async function createUser(
   username: string,
-  passwordHash: string,
+  password: string,
   fullName: string,
   role: UserRole
 ): Promise<number> {
-  const db = await getDb();
-  const result = await db.execute(
-    'INSERT INTO users (username, password_hash, full_name, role) VALUES ($1, $2, $3, $4)',
-    [username, passwordHash, fullName, role]
-  );
-  return result.lastInsertId ?? 0;
+  return invoke<number>('create_user', {
+    username,
+    password,
+    fullName,
+    role,
+    token: sessionToken() || null,
+  });
 }
 
+/** Replace a user's name, role and status; a password is only changed when given. */
 export async function updateUser(
   id: number,
-  data: { fullName?: string; role?: UserRole; isActive?: boolean; passwordHash?: string }
+  data: { fullName: string; role: UserRole; isActive: boolean; password?: string }
 ): Promise<void> {
+  await invoke('update_user', { targetUserId: id, user: data, token: sessionToken() });
+}
+
+export async function getUserCount(): Promise<number> {
   const db = await getDb();
-  const setClauses: string[] = [];
-  const values: unknown[] = [];
-  let paramIdx = 1;
+  const rows = await db.select<{ count: number }[]>('SELECT COUNT(*) as count FROM users');
+  return rows[0]?.count ?? 0;
+}
+
+/** Verify the password in the backend and open a session. */
+export async function login(username: string, password: string): Promise<Session> {
+  return invoke<Session>('login', { username, password });
+}
 
-  if (data.fullName !== undefined) {
-    setClauses.push(`full_name = $${paramIdx++}`);
-    values.push(data.fullName);
-  }
-  if (data.role !== undefined) {
-    setClauses.push(`role = $${paramIdx++}`);
-    values.push(data.role);
-  }
-  if (data.isActive !== undefined) {
-    setClauses.push(`is_active = $${paramIdx++}`);
-    values.push(data.isActive ? 1 : 0);
-  }
-  if (data.passwordHash !== undefined) {
-    setClauses.push(`password_hash = $${paramIdx++}`);
-    values.push(data.passwordHash);
-  }
+export async function logout(token: string): Promise<void> {
+  return invoke<void>('logout', { token });
+}
 
-  if (setClauses.length === 0) return;
+/** The session behind a stored token; null once it has expired. */
+export async function getCurrentSession(token: string): Promise<Session | null> {
+  return invoke<Session | null>('current_session', { token });
+}
 
-  setClauses.push(`updated_at = datetime('now')`);
-  values.push(id);
+/** Whether the backend's permission matrix lets the user do `permission`. */
+export async function checkPermission(permission: string): Promise<boolean> {
+  return invoke<boolean>('check_permission', { token: sessionToken(), permission });
+}
 
-  await db.execute(
-    `UPDATE users SET ${setClauses.join(', ')} WHERE id = $${paramIdx}`,
-    values
-  );
+/** Replace the permissions granted to a role; needs `users.permissions`. */
+export async function setRolePermissions(role: UserRole, permissions: string[]): Promise<void> {
+  return invoke<void>('set_role_permissions', { token: sessionToken(), role, permissions });
 }
 
-export async function getUserCount(): Promise<number> {
-  const db = await getDb();
-  const rows = await db.select<{ count: number }[]>('SELECT COUNT(*) as count FROM users');
-  return rows[0]?.count ?? 0;
+/** End a lockout early; needs `users.unlock`. */
+export async function unlockUser(targetUserId: number): Promise<void> {
+  return invoke<void>('unlock_user', { token: sessionToken(), targetUserId });
 }
@@ -0,0 +1,10 @@
+/** localStorage key holding the signed-in session's token. */
+export const SESSION_STORAGE_KEY = 'pharmacare_session_token';
+
+/**
+ * The signed-in session's token. Commands that act as a user take this and
+ * resolve the user in the backend, so they never trust a user id from here.
+ */
+export function sessionToken(): string {
+  return localStorage.getItem(SESSION_STORAGE_KEY) ?? '';
+}
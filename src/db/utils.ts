@@ -1,3 +1,10 @@
+/**
+ * SQL that is true while a batch may still be sold. Stock is good through its
+ * expiry date, so a batch expiring today is still sellable; this matches the
+ * backend's check. Only `batches` has an `expiry_date`, so no alias is needed.
+ */
+export const UNEXPIRED = "expiry_date >= date('now')";
+
 /**
  * Convert snake_case database row to camelCase TypeScript object.
  * Handles nested underscores correctly: "cost_price_paise" → "costPricePaise"
@@ -7,24 +7,25 @@ import {
   type ReactNode,
 } from "react";
 import { useNavigate } from "react-router-dom";
-import bcrypt from "bcryptjs";
-import type { User } from "@/types";
+import type { SessionUser } from "@/types";
 import {
-  getUserByUsername,
-  getUserById,
+  getCurrentSession,
   getUserCount,
+  login as openSession,
+  logout as closeSession,
 } from "@/db/queries/users";
-
-const STORAGE_KEY = "pharmacare_user_id";
+import { getDatabaseStatus, unlockDatabase } from "@/db/queries/encryption";
+import { SESSION_STORAGE_KEY as STORAGE_KEY } from "@/db/session";
 
 interface AuthContextValue {
-  user: User | null;
+  user: SessionUser | null;
   isLoading: boolean;
   isFirstLaunch: boolean;
+  /** The database is encrypted and waiting for its passphrase */
+  isLocked: boolean;
+  unlock: (passphrase: string) => Promise<void>;
   login: (username: string, password: string) => Promise<void>;
   logout: () => void;
-  /** Called by FirstLaunchWizard after it creates + auto-logs-in the admin */
-  setUser: (user: User) => void;
   /** Re-check first-launch state after wizard creates the first user */
   refreshFirstLaunch: () => Promise<void>;
 }
@@ -32,9 +33,10 @@ interface AuthContextValue {
 const AuthContext = createContext<AuthContextValue | null>(null);
 
 export function AuthProvider({ children }: { children: ReactNode }) {
-  const [user, setUserState] = useState<User | null>(null);
+  const [user, setUserState] = useState<SessionUser | null>(null);
   const [isLoading, setIsLoading] = useState(true);
   const [isFirstLaunch, setIsFirstLaunch] = useState(false);
+  const [isLocked, setIsLocked] = useState(false);
   const navigate = useNavigate();
 
   const refreshFirstLaunch = useCallback(async () => {
@@ -42,85 +44,93 @@ export function AuthProvider({ children }: { children: ReactNode }) {
     setIsFirstLaunch(count === 0);
   }, []);
 
-  // On mount: restore session from localStorage + check first-launch
-  useEffect(() => {
-    async function init() {
-      try {
-        // First-launch check
-        const count = await getUserCount();
-        if (count === 0) {
-          setIsFirstLaunch(true);
-          setIsLoading(false);
-          return;
-        }
+  // Restore session from localStorage + check first-launch
+  const restoreSession = useCallback(async () => {
+    try {
+      // Nothing can be read until an encrypted database is unlocked
+      const status = await getDatabaseStatus();
+      if (status.encrypted && !status.unlocked) {
+        setIsLocked(true);
+        return;
+      }
+      setIsLocked(false);
+
+      // First-launch check
+      const count = await getUserCount();
+      if (count === 0) {
+        setIsFirstLaunch(true);
+        return;
+      }
 
-        // Session restore
-        const storedId = localStorage.getItem(STORAGE_KEY);
-        if (storedId) {
-          const id = parseInt(storedId, 10);
-          if (!isNaN(id)) {
-            const found = await getUserById(id);
-            if (found && found.isActive) {
-              setUserState(found);
-            } else {
-              localStorage.removeItem(STORAGE_KEY);
-            }
-          }
+      // Session restore
+      const token = localStorage.getItem(STORAGE_KEY);
+      if (token) {
+        const session = await getCurrentSession(token);
+        if (session) {
+          setUserState(session.user);
+        } else {
+          localStorage.removeItem(STORAGE_KEY);
         }
-      } catch {
-        void 0; // DB might not be ready yet in tests; silently ignore
-      } finally {
-        setIsLoading(false);
       }
+    } catch {
+      void 0; // DB might not be ready yet in tests; silently ignore
+    } finally {
+      setIsLoading(false);
     }
-
-    init();
   }, []);
 
-  const login = useCallback(
-    async (username: string, password: string): Promise<void> => {
-      const found = await getUserByUsername(username);
-
-      if (!found) {
-        throw new Error("Invalid username or password");
-      }
+  useEffect(() => {
+    restoreSession();
+  }, [restoreSession]);
 
-      if (!found.isActive) {
-        throw new Error("Account is disabled");
+  const unlock = useCallback(
+    async (passphrase: string): Promise<void> => {
+      try {
+        await unlockDatabase(passphrase);
+      } catch (err) {
+        throw new Error(typeof err === "string" ? err : "Unlock failed");
       }
+      await restoreSession();
+    },
+    [restoreSession]
+  );
 
-      const valid = await bcrypt.compare(password, found.passwordHash);
-      if (!valid) {
-        throw new Error("Invalid username or password");
+  const login = useCallback(
+    async (username: string, password: string): Promise<void> => {
+      try {
+        const session = await openSession(username, password);
+        localStorage.setItem(STORAGE_KEY, session.token);
+        setUserState(session.user);
+        setIsFirstLaunch(false);
+      } catch (err) {
+        throw new Error(typeof err === "string" ? err : "Login failed");
       }
-
-      localStorage.setItem(STORAGE_KEY, String(found.id));
-      setUserState(found);
     },
     []
   );
 
   const logout = useCallback(() => {
+    const token = localStorage.getItem(STORAGE_KEY);
     localStorage.removeItem(STORAGE_KEY);
     setUserState(null);
+    if (token) {
+      closeSession(token).catch(() => {
+        void 0; // the session expires on its own
+      });
+    }
     navigate("/login");
   }, [navigate]);
 
-  const setUser = useCallback((u: User) => {
-    localStorage.setItem(STORAGE_KEY, String(u.id));
-    setUserState(u);
-    setIsFirstLaunch(false);
-  }, []);
-
   return (
     <AuthContext.Provider
       value={{
         user,
         isLoading,
         isFirstLaunch,
+        isLocked,
+        unlock,
         login,
         logout,
-        setUser,
         refreshFirstLaunch,
       }}
     >
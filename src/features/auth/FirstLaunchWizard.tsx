@@ -1,7 +1,6 @@
 import { useState, type FormEvent } from "react";
 import { toast } from "sonner";
 import { Loader2, Pill, ShieldCheck } from "lucide-react";
-import bcrypt from "bcryptjs";
 import { Button } from "@/components/ui/button";
 import { Input } from "@/components/ui/input";
 import { Label } from "@/components/ui/label";
@@ -12,11 +11,11 @@ import {
   CardHeader,
   CardTitle,
 } from "@/components/ui/card";
-import { createUser, getUserById } from "@/db/queries/users";
+import { createUser } from "@/db/queries/users";
 import { useAuth } from "./AuthContext";
 
 export default function FirstLaunchWizard() {
-  const { setUser, refreshFirstLaunch } = useAuth();
+  const { login, refreshFirstLaunch } = useAuth();
   const [isLoading, setIsLoading] = useState(false);
 
   async function handleSubmit(e: FormEvent<HTMLFormElement>) {
@@ -33,8 +32,8 @@ export default function FirstLaunchWizard() {
       toast.error("All fields are required");
       return;
     }
-    if (password.length < 6) {
-      toast.error("Password must be at least 6 characters");
+    if (password.length < 8) {
+      toast.error("Password must be at least 8 characters");
       return;
     }
     if (password !== confirmPassword) {
@@ -44,18 +43,17 @@ export default function FirstLaunchWizard() {
 
     setIsLoading(true);
     try {
-      const passwordHash = await bcrypt.hash(password, 10);
-      const newId = await createUser(username, passwordHash, fullName, "admin");
-      const newUser = await getUserById(newId);
-      if (!newUser) {
-        throw new Error("Failed to retrieve created user");
-      }
+      await createUser(username, password, fullName, "admin");
       await refreshFirstLaunch();
-      setUser(newUser);
-      toast.success(`Welcome, ${newUser.fullName}! Your admin account is ready.`);
+      await login(username, password);
+      toast.success(`Welcome, ${fullName}! Your admin account is ready.`);
     } catch (err) {
       const message =
-        err instanceof Error ? err.message : "Failed to create account";
+        typeof err === "string"
+          ? err
+          : err instanceof Error
+            ? err.message
+            : "Failed to create account";
       toast.error(message);
       setIsLoading(false);
     }
@@ -145,7 +143,7 @@ export default function FirstLaunchWizard() {
                     name="password"
                     type="password"
                     autoComplete="new-password"
-                    placeholder="Min. 6 characters"
+                    placeholder="Min. 8 characters"
                     disabled={isLoading}
                   />
                 </div>
@@ -0,0 +1,85 @@
+import { useState, type FormEvent } from "react";
+import { toast } from "sonner";
+import { Loader2, LockIcon } from "lucide-react";
+import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import { Label } from "@/components/ui/label";
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from "@/components/ui/card";
+import { useAuth } from "./AuthContext";
+
+/** Asks for the database passphrase before anything else can load. */
+export default function UnlockDatabasePage() {
+  const { unlock } = useAuth();
+  const [isLoading, setIsLoading] = useState(false);
+
+  async function handleSubmit(e: FormEvent<HTMLFormElement>) {
+    e.preventDefault();
+    const passphrase = new FormData(e.currentTarget).get("passphrase") as string;
+    if (!passphrase) {
+      toast.error("Please enter the database passphrase");
+      return;
+    }
+
+    setIsLoading(true);
+    try {
+      await unlock(passphrase);
+    } catch (err) {
+      toast.error(err instanceof Error ? err.message : "Unlock failed");
+    } finally {
+      setIsLoading(false);
+    }
+  }
+
+  return (
+    <div className="min-h-screen bg-gradient-to-br from-blue-50 via-white to-slate-100 flex items-center justify-center p-4">
+      <Card className="w-full max-w-sm shadow-xl shadow-slate-200/60 border-slate-200">
+        <CardHeader className="pb-4">
+          <CardTitle className="flex items-center gap-2 text-lg font-semibold text-slate-800">
+            <LockIcon size={18} />
+            Database locked
+          </CardTitle>
+          <CardDescription>
+            Enter the passphrase the pharmacy data is encrypted with
+          </CardDescription>
+        </CardHeader>
+
+        <CardContent>
+          <form onSubmit={handleSubmit} className="space-y-4">
+            <div className="space-y-1.5">
+              <Label htmlFor="passphrase">Passphrase</Label>
+              <Input
+                id="passphrase"
+                name="passphrase"
+                type="password"
+                autoComplete="off"
+                autoFocus
+                disabled={isLoading}
+              />
+            </div>
+
+            <Button
+              type="submit"
+              className="w-full bg-blue-600 hover:bg-blue-700 text-white"
+              disabled={isLoading}
+            >
+              {isLoading ? (
+                <>
+                  <Loader2 className="mr-2 h-4 w-4 animate-spin" />
+                  Unlocking…
+                </>
+              ) : (
+                "Unlock"
+              )}
+            </Button>
+          </form>
+        </CardContent>
+      </Card>
+    </div>
+  );
+}
@@ -12,6 +12,7 @@ export type Permission =
   | "customers:edit"
   | "suppliers:view"
   | "reports:view"
+  | "expenses:manage"
   | "users:manage"
   | "settings:manage";
 
@@ -28,6 +29,7 @@ export const ROLE_PERMISSIONS: Record<UserRole, Permission[]> = {
     "customers:edit",
     "suppliers:view",
     "reports:view",
+    "expenses:manage",
     "users:manage",
     "settings:manage",
   ],
@@ -41,6 +43,7 @@ export const ROLE_PERMISSIONS: Record<UserRole, Permission[]> = {
     "customers:edit",
     "suppliers:view",
     "reports:view",
+    "expenses:manage",
   ],
   cashier: [
     "dashboard:view",
@@ -69,6 +72,7 @@ export const ROUTE_PERMISSIONS: Record<string, Permission> = {
   "/customers": "customers:view",
   "/suppliers": "suppliers:view",
   "/reports/*": "reports:view",
+  "/expenses": "expenses:manage",
   "/users": "users:manage",
   "/settings": "settings:manage",
 };
@@ -82,6 +86,7 @@ export const SIDEBAR_PERMISSIONS: Record<string, Permission> = {
   Customers: "customers:view",
   Suppliers: "suppliers:view",
   Reports: "reports:view",
+  Expenses: "expenses:manage",
   Users: "users:manage",
   Settings: "settings:manage",
 };
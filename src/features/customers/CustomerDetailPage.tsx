@@ -1,5 +1,6 @@
 import { useState, useEffect, useCallback } from "react";
 import { useParams, useNavigate } from "react-router-dom";
+import { useDataChanged } from "@/hooks/useDataChanged";
 import { toast } from "sonner";
 import {
   ArrowLeftIcon,
@@ -8,9 +9,18 @@ import {
   MailIcon,
   MapPinIcon,
   FileTextIcon,
+  WalletIcon,
 } from "lucide-react";
 import type { Customer, Sale, Prescription } from "@/types";
-import { getCustomerById } from "@/db/queries/customers";
+import {
+  getCustomerById,
+  getCustomerLedger,
+  getCustomerOutstanding,
+  getOpenInvoices,
+  type CustomerLedger,
+  type CustomerOutstanding,
+  type OpenInvoice,
+} from "@/db/queries/customers";
 import { getSalesByCustomer } from "@/db/queries/sales";
 import { getPrescriptionsByCustomer } from "@/db/queries/prescriptions";
 import { formatPaiseToCurrency } from "@/lib/currency";
@@ -26,6 +36,16 @@ import {
   TableHeader,
   TableRow,
 } from "@/components/ui/table";
+import ReceivePaymentDialog from "./ReceivePaymentDialog";
+
+/** How far back the account statement goes */
+const LEDGER_DAYS = 90;
+
+function isoDaysAgo(days: number): string {
+  const date = new Date();
+  date.setDate(date.getDate() - days);
+  return date.toISOString().split("T")[0];
+}
 
 function formatDate(dateStr: string | null): string {
   if (!dateStr) return "\u2014";
@@ -46,6 +66,10 @@ export default function CustomerDetailPage() {
   const [customer, setCustomer] = useState<Customer | null>(null);
   const [sales, setSales] = useState<Sale[]>([]);
   const [prescriptions, setPrescriptions] = useState<Prescription[]>([]);
+  const [outstanding, setOutstanding] = useState<CustomerOutstanding | null>(null);
+  const [openInvoices, setOpenInvoices] = useState<OpenInvoice[]>([]);
+  const [ledger, setLedger] = useState<CustomerLedger | null>(null);
+  const [paymentOpen, setPaymentOpen] = useState(false);
   const [loading, setLoading] = useState(true);
 
   const loadData = useCallback(async () => {
@@ -58,10 +82,13 @@ export default function CustomerDetailPage() {
 
     try {
       setLoading(true);
-      const [cust, salesData, rxData] = await Promise.all([
+      const [cust, salesData, rxData, owed, invoices, statement] = await Promise.all([
         getCustomerById(customerId),
         getSalesByCustomer(customerId),
         getPrescriptionsByCustomer(customerId),
+        getCustomerOutstanding(customerId),
+        getOpenInvoices(customerId),
+        getCustomerLedger(customerId, isoDaysAgo(LEDGER_DAYS), isoDaysAgo(0)),
       ]);
 
       if (!cust) {
@@ -73,6 +100,9 @@ export default function CustomerDetailPage() {
       setCustomer(cust);
       setSales(salesData);
       setPrescriptions(rxData);
+      setOutstanding(owed);
+      setOpenInvoices(invoices);
+      setLedger(statement);
     } catch (err) {
       console.error(err);
       toast.error("Failed to load customer details");
@@ -85,6 +115,9 @@ export default function CustomerDetailPage() {
     loadData();
   }, [loadData]);
 
+  // Payments, sales and prescriptions recorded by backend commands
+  useDataChanged(["customer_payment", "sale", "prescription"], loadData);
+
   if (loading) {
     return (
       <div className="flex items-center justify-center py-24">
@@ -145,6 +178,146 @@ export default function CustomerDetailPage() {
         </div>
       </Card>
 
+      {/* Credit account */}
+      <div className="space-y-3">
+        <div className="flex items-center justify-between">
+          <h2 className="text-xl font-semibold text-slate-900">Credit Account</h2>
+          <Button size="sm" className="gap-1" onClick={() => setPaymentOpen(true)}>
+            <WalletIcon className="size-4" />
+            Receive Payment
+          </Button>
+        </div>
+
+        <Card className="p-6">
+          <div className="grid grid-cols-2 lg:grid-cols-4 gap-4">
+            <div>
+              <p className="text-xs text-muted-foreground">Outstanding</p>
+              <p
+                className={`text-lg font-semibold tabular-nums ${
+                  (outstanding?.outstandingPaise ?? 0) > 0 ? "text-destructive" : "text-slate-900"
+                }`}
+              >
+                {formatPaiseToCurrency(outstanding?.outstandingPaise ?? 0)}
+              </p>
+            </div>
+            <div>
+              <p className="text-xs text-muted-foreground">Credit Limit</p>
+              <p className="text-lg font-semibold tabular-nums text-slate-900">
+                {customer.creditLimitPaise === null
+                  ? "No limit"
+                  : formatPaiseToCurrency(customer.creditLimitPaise)}
+              </p>
+            </div>
+            <div>
+              <p className="text-xs text-muted-foreground">Billed on Credit</p>
+              <p className="text-lg font-semibold tabular-nums text-slate-900">
+                {formatPaiseToCurrency(
+                  (outstanding?.creditSalesPaise ?? 0) - (outstanding?.creditReturnsPaise ?? 0)
+                )}
+              </p>
+            </div>
+            <div>
+              <p className="text-xs text-muted-foreground">Received</p>
+              <p className="text-lg font-semibold tabular-nums text-slate-900">
+                {formatPaiseToCurrency(outstanding?.paymentsPaise ?? 0)}
+              </p>
+            </div>
+          </div>
+        </Card>
+
+        {openInvoices.length > 0 && (
+          <div className="rounded-lg border bg-card shadow-sm">
+            <Table>
+              <TableHeader>
+                <TableRow>
+                  <TableHead>Date</TableHead>
+                  <TableHead>Invoice #</TableHead>
+                  <TableHead className="text-right">Age (days)</TableHead>
+                  <TableHead className="text-right">Billed</TableHead>
+                  <TableHead className="text-right">Due</TableHead>
+                </TableRow>
+              </TableHeader>
+              <TableBody>
+                {openInvoices.map((invoice) => (
+                  <TableRow
+                    key={invoice.saleId}
+                    className="cursor-pointer hover:bg-muted/50"
+                    onClick={() => navigate(`/sales/invoice/${invoice.saleId}`)}
+                  >
+                    <TableCell className="text-slate-600 text-sm whitespace-nowrap">
+                      {formatDate(invoice.saleDate)}
+                    </TableCell>
+                    <TableCell className="font-medium font-mono text-sm">
+                      {invoice.invoiceNumber}
+                    </TableCell>
+                    <TableCell className="text-right tabular-nums text-sm">
+                      {invoice.ageDays}
+                    </TableCell>
+                    <TableCell className="text-right tabular-nums text-sm">
+                      {formatPaiseToCurrency(invoice.billedPaise)}
+                    </TableCell>
+                    <TableCell className="text-right tabular-nums text-sm font-medium">
+                      {formatPaiseToCurrency(invoice.duePaise)}
+                    </TableCell>
+                  </TableRow>
+                ))}
+              </TableBody>
+            </Table>
+          </div>
+        )}
+
+        {ledger && ledger.entries.length > 0 && (
+          <div className="rounded-lg border bg-card shadow-sm">
+            <Table>
+              <TableHeader>
+                <TableRow>
+                  <TableHead>Date</TableHead>
+                  <TableHead>Entry</TableHead>
+                  <TableHead>Reference</TableHead>
+                  <TableHead className="text-right">Debit</TableHead>
+                  <TableHead className="text-right">Credit</TableHead>
+                  <TableHead className="text-right">Balance</TableHead>
+                </TableRow>
+              </TableHeader>
+              <TableBody>
+                <TableRow>
+                  <TableCell colSpan={5} className="text-sm text-muted-foreground">
+                    Opening balance ({LEDGER_DAYS} days ago)
+                  </TableCell>
+                  <TableCell className="text-right tabular-nums text-sm">
+                    {formatPaiseToCurrency(ledger.openingBalancePaise)}
+                  </TableCell>
+                </TableRow>
+                {ledger.entries.map((entry, i) => (
+                  <TableRow key={i}>
+                    <TableCell className="text-slate-600 text-sm whitespace-nowrap">
+                      {formatDate(entry.date)}
+                    </TableCell>
+                    <TableCell>
+                      <Badge variant="outline" className="capitalize text-xs">
+                        {entry.kind}
+                      </Badge>
+                    </TableCell>
+                    <TableCell className="font-mono text-sm">{entry.reference}</TableCell>
+                    <TableCell className="text-right tabular-nums text-sm">
+                      {entry.debitPaise > 0 ? formatPaiseToCurrency(entry.debitPaise) : ""}
+                    </TableCell>
+                    <TableCell className="text-right tabular-nums text-sm">
+                      {entry.creditPaise > 0 ? formatPaiseToCurrency(entry.creditPaise) : ""}
+                    </TableCell>
+                    <TableCell className="text-right tabular-nums text-sm font-medium">
+                      {formatPaiseToCurrency(entry.balancePaise)}
+                    </TableCell>
+                  </TableRow>
+                ))}
+              </TableBody>
+            </Table>
+          </div>
+        )}
+      </div>
+
+      <Separator />
+
       {/* Purchase History */}
       <div className="space-y-3">
         <div className="flex items-center justify-between">
@@ -247,6 +420,14 @@ export default function CustomerDetailPage() {
           )}
         </div>
       </div>
+
+      <ReceivePaymentDialog
+        open={paymentOpen}
+        onOpenChange={setPaymentOpen}
+        customerId={customer.id}
+        openInvoices={openInvoices}
+        onSaved={loadData}
+      />
     </div>
   );
 }
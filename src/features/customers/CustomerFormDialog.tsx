@@ -2,6 +2,8 @@ import { useState, useEffect } from "react";
 import { toast } from "sonner";
 import type { Customer } from "@/types";
 import { createCustomer, updateCustomer } from "@/db/queries/customers";
+import { lookupGstin, validateRegistrations } from "@/db/queries/registrations";
+import { rupeesToPaise } from "@/lib/currency";
 import { Button } from "@/components/ui/button";
 import { Input } from "@/components/ui/input";
 import { Label } from "@/components/ui/label";
@@ -26,11 +28,20 @@ interface FormState {
   phone: string;
   email: string;
   address: string;
+  gstin: string;
+  stateCode: string;
+  city: string;
+  pincode: string;
+  creditLimit: string;
 }
 
 interface FormErrors {
   name?: string;
   email?: string;
+  gstin?: string;
+  stateCode?: string;
+  pincode?: string;
+  creditLimit?: string;
 }
 
 const DEFAULT_FORM: FormState = {
@@ -38,8 +49,17 @@ const DEFAULT_FORM: FormState = {
   phone: "",
   email: "",
   address: "",
+  gstin: "",
+  stateCode: "",
+  city: "",
+  pincode: "",
+  creditLimit: "",
 };
 
+const GSTIN_REGEX = /^[0-9]{2}[A-Z]{5}[0-9]{4}[A-Z]{1}[1-9A-Z]{1}Z[0-9A-Z]{1}$/;
+const STATE_CODE_REGEX = /^[0-9]{2}$/;
+const PINCODE_REGEX = /^[1-9][0-9]{5}$/;
+
 export default function CustomerFormDialog({
   open,
   onOpenChange,
@@ -50,6 +70,7 @@ export default function CustomerFormDialog({
   const [form, setForm] = useState<FormState>(DEFAULT_FORM);
   const [errors, setErrors] = useState<FormErrors>({});
   const [saving, setSaving] = useState(false);
+  const [lookingUp, setLookingUp] = useState(false);
 
   // Sync form when dialog opens or customer changes
   useEffect(() => {
@@ -60,6 +81,12 @@ export default function CustomerFormDialog({
           phone: customer.phone ?? "",
           email: customer.email ?? "",
           address: customer.address ?? "",
+          gstin: customer.gstin ?? "",
+          stateCode: customer.stateCode ?? "",
+          city: customer.city ?? "",
+          pincode: customer.pincode ?? "",
+          creditLimit:
+            customer.creditLimitPaise === null ? "" : String(customer.creditLimitPaise / 100),
         });
       } else {
         setForm(DEFAULT_FORM);
@@ -79,6 +106,26 @@ export default function CustomerFormDialog({
       newErrors.email = "Invalid email format";
     }
 
+    const gstin = form.gstin.trim().toUpperCase();
+    const stateCode = form.stateCode.trim();
+    if (gstin && !GSTIN_REGEX.test(gstin)) {
+      newErrors.gstin = "Invalid GSTIN format (15 characters, e.g. 29ABCDE1234F1Z5)";
+    }
+    if (stateCode && !STATE_CODE_REGEX.test(stateCode)) {
+      newErrors.stateCode = "State code must be 2 digits (e.g. 29)";
+    } else if (gstin && stateCode && gstin.slice(0, 2) !== stateCode) {
+      newErrors.stateCode = "State code must match the first two digits of the GSTIN";
+    }
+
+    if (form.pincode.trim() && !PINCODE_REGEX.test(form.pincode.trim())) {
+      newErrors.pincode = "PIN code must be 6 digits";
+    }
+
+    const creditLimit = form.creditLimit.trim();
+    if (creditLimit && (isNaN(Number(creditLimit)) || Number(creditLimit) < 0)) {
+      newErrors.creditLimit = "Credit limit must be an amount in rupees";
+    }
+
     setErrors(newErrors);
     return Object.keys(newErrors).length === 0;
   }
@@ -91,11 +138,29 @@ export default function CustomerFormDialog({
     try {
       setSaving(true);
 
+      // The backend also checks the GSTIN's check character
+      const problems = await validateRegistrations({
+        gstin: form.gstin,
+        stateCode: form.stateCode,
+      });
+      if (problems.length > 0) {
+        setErrors(Object.fromEntries(problems.map((p) => [p.field, p.message])));
+        return;
+      }
+
+      const gstin = form.gstin.trim().toUpperCase() || null;
       const payload = {
         name: form.name.trim(),
         phone: form.phone.trim() || null,
         email: form.email.trim() || null,
         address: form.address.trim() || null,
+        gstin,
+        // A registered customer's state is encoded in their GSTIN
+        stateCode: form.stateCode.trim() || gstin?.slice(0, 2) || null,
+        city: form.city.trim() || null,
+        pincode: form.pincode.trim() || null,
+        // Blank means the customer may owe any amount
+        creditLimitPaise: form.creditLimit.trim() ? rupeesToPaise(form.creditLimit) : null,
       };
 
       if (isEditMode && customer) {
@@ -118,6 +183,29 @@ export default function CustomerFormDialog({
     }
   }
 
+  async function handleLookup() {
+    try {
+      setLookingUp(true);
+      const found = await lookupGstin(form.gstin);
+      setForm((prev) => ({
+        ...prev,
+        name: prev.name.trim() ? prev.name : found.legalName,
+        address: prev.address.trim() ? prev.address : found.address,
+        pincode: prev.pincode.trim() ? prev.pincode : found.pincode,
+        stateCode: found.stateCode,
+      }));
+      if (found.status !== "ACT") {
+        toast.warning(`GSTIN ${found.gstin} is not active (status ${found.status})`);
+      } else {
+        toast.success(`Found ${found.legalName}`);
+      }
+    } catch (err) {
+      toast.error(String(err));
+    } finally {
+      setLookingUp(false);
+    }
+  }
+
   function handleFieldChange<K extends keyof FormState>(
     field: K,
     value: FormState[K]
@@ -206,6 +294,97 @@ export default function CustomerFormDialog({
             />
           </div>
 
+          {/* City + PIN code */}
+          <div className="grid grid-cols-3 gap-3">
+            <div className="col-span-2 space-y-1.5">
+              <Label htmlFor="cust-city">City</Label>
+              <Input
+                id="cust-city"
+                value={form.city}
+                onChange={(e) => handleFieldChange("city", e.target.value)}
+                placeholder="e.g. Bengaluru"
+                autoComplete="off"
+              />
+            </div>
+            <div className="space-y-1.5">
+              <Label htmlFor="cust-pincode">PIN Code</Label>
+              <Input
+                id="cust-pincode"
+                value={form.pincode}
+                onChange={(e) => handleFieldChange("pincode", e.target.value)}
+                placeholder="e.g. 560001"
+                maxLength={6}
+                aria-invalid={!!errors.pincode}
+                autoComplete="off"
+              />
+              {errors.pincode && (
+                <p className="text-sm text-destructive">{errors.pincode}</p>
+              )}
+            </div>
+          </div>
+
+          {/* GSTIN + State code (B2B) */}
+          <div className="grid grid-cols-3 gap-3">
+            <div className="col-span-2 space-y-1.5">
+              <Label htmlFor="cust-gstin">GSTIN</Label>
+              <div className="flex gap-2">
+                <Input
+                  id="cust-gstin"
+                  value={form.gstin}
+                  onChange={(e) => handleFieldChange("gstin", e.target.value.toUpperCase())}
+                  placeholder="e.g. 29ABCDE1234F1Z5"
+                  maxLength={15}
+                  aria-invalid={!!errors.gstin}
+                  className="font-mono"
+                  autoComplete="off"
+                />
+                <Button
+                  type="button"
+                  variant="outline"
+                  onClick={handleLookup}
+                  disabled={lookingUp || form.gstin.trim().length !== 15}
+                >
+                  {lookingUp ? "…" : "Look up"}
+                </Button>
+              </div>
+              {errors.gstin && (
+                <p className="text-sm text-destructive">{errors.gstin}</p>
+              )}
+            </div>
+            <div className="space-y-1.5">
+              <Label htmlFor="cust-state">State Code</Label>
+              <Input
+                id="cust-state"
+                value={form.stateCode}
+                onChange={(e) => handleFieldChange("stateCode", e.target.value)}
+                placeholder="e.g. 29"
+                maxLength={2}
+                aria-invalid={!!errors.stateCode}
+                autoComplete="off"
+              />
+              {errors.stateCode && (
+                <p className="text-sm text-destructive">{errors.stateCode}</p>
+              )}
+            </div>
+          </div>
+
+          <div className="space-y-1.5">
+            <Label htmlFor="cust-credit-limit">Credit Limit (₹)</Label>
+            <Input
+              id="cust-credit-limit"
+              type="number"
+              min={0}
+              step="0.01"
+              value={form.creditLimit}
+              onChange={(e) => handleFieldChange("creditLimit", e.target.value)}
+              placeholder="No limit"
+              aria-invalid={!!errors.creditLimit}
+            />
+            {errors.creditLimit && (
+              <p className="text-sm text-destructive">{errors.creditLimit}</p>
+            )}
+          </div>
+
           <DialogFooter className="pt-2">
             <Button
               type="button"
@@ -0,0 +1,258 @@
+import { useState, useEffect } from "react";
+import { toast } from "sonner";
+import { useAuth } from "@/features/auth/AuthContext";
+import { recordCustomerPayment, type OpenInvoice } from "@/db/queries/customers";
+import { formatPaiseToCurrency, rupeesToPaise } from "@/lib/currency";
+import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import { Label } from "@/components/ui/label";
+import {
+  Dialog,
+  DialogContent,
+  DialogDescription,
+  DialogFooter,
+  DialogHeader,
+  DialogTitle,
+} from "@/components/ui/dialog";
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from "@/components/ui/select";
+
+type ReceiptMode = "cash" | "upi" | "card";
+
+interface ReceivePaymentDialogProps {
+  open: boolean;
+  onOpenChange: (open: boolean) => void;
+  customerId: number;
+  openInvoices: OpenInvoice[];
+  onSaved: () => void;
+}
+
+interface FormErrors {
+  amount?: string;
+  paymentDate?: string;
+}
+
+const RECEIPT_MODE_OPTIONS: { value: ReceiptMode; label: string }[] = [
+  { value: "cash", label: "Cash" },
+  { value: "upi", label: "UPI" },
+  { value: "card", label: "Card" },
+];
+
+/** Select value that lets the backend settle the oldest invoices first */
+const OLDEST_FIRST = "oldest";
+
+function todayIso(): string {
+  return new Date().toISOString().split("T")[0];
+}
+
+export default function ReceivePaymentDialog({
+  open,
+  onOpenChange,
+  customerId,
+  openInvoices,
+  onSaved,
+}: ReceivePaymentDialogProps) {
+  const { user } = useAuth();
+  const [amount, setAmount] = useState("");
+  const [paymentDate, setPaymentDate] = useState(todayIso());
+  const [paymentMode, setPaymentMode] = useState<ReceiptMode>("cash");
+  const [against, setAgainst] = useState(OLDEST_FIRST);
+  const [reference, setReference] = useState("");
+  const [notes, setNotes] = useState("");
+  const [errors, setErrors] = useState<FormErrors>({});
+  const [saving, setSaving] = useState(false);
+
+  // Reset form when dialog opens
+  useEffect(() => {
+    if (open) {
+      setAmount("");
+      setPaymentDate(todayIso());
+      setPaymentMode("cash");
+      setAgainst(OLDEST_FIRST);
+      setReference("");
+      setNotes("");
+      setErrors({});
+    }
+  }, [open]);
+
+  function validate(): boolean {
+    const newErrors: FormErrors = {};
+
+    if (!amount.trim()) {
+      newErrors.amount = "Amount is required";
+    } else if (rupeesToPaise(amount) <= 0) {
+      newErrors.amount = "Amount must be greater than zero";
+    }
+
+    if (!paymentDate) {
+      newErrors.paymentDate = "Payment date is required";
+    }
+
+    setErrors(newErrors);
+    return Object.keys(newErrors).length === 0;
+  }
+
+  async function handleSubmit(e: React.FormEvent) {
+    e.preventDefault();
+    if (!user || !validate()) return;
+
+    const amountPaise = rupeesToPaise(amount);
+    const invoice = openInvoices.find((i) => String(i.saleId) === against);
+    try {
+      setSaving(true);
+      const after = await recordCustomerPayment({
+        customerId,
+        amountPaise,
+        paymentMode,
+        paymentDate,
+        reference: reference.trim() || null,
+        notes: notes.trim() || null,
+        // Anything beyond what the invoice owes settles the oldest ones
+        allocations: invoice
+          ? [{ saleId: invoice.saleId, amountPaise: Math.min(amountPaise, invoice.duePaise) }]
+          : [],
+      });
+      toast.success(
+        after.outstandingPaise > 0
+          ? `Payment recorded; ${formatPaiseToCurrency(after.outstandingPaise)} still due`
+          : "Payment recorded; nothing is due"
+      );
+      onOpenChange(false);
+      onSaved();
+    } catch (err) {
+      console.error(err);
+      toast.error(String(err));
+    } finally {
+      setSaving(false);
+    }
+  }
+
+  return (
+    <Dialog open={open} onOpenChange={onOpenChange}>
+      <DialogContent className="sm:max-w-md">
+        <DialogHeader>
+          <DialogTitle>Receive Payment</DialogTitle>
+          <DialogDescription>
+            Record money received from this customer against their credit invoices.
+          </DialogDescription>
+        </DialogHeader>
+
+        <form onSubmit={handleSubmit} className="space-y-4 py-2">
+          <div className="grid grid-cols-2 gap-3">
+            <div className="space-y-1.5">
+              <Label htmlFor="rcpt-amount">
+                Amount (₹) <span className="text-destructive">*</span>
+              </Label>
+              <Input
+                id="rcpt-amount"
+                type="number"
+                min="0.01"
+                step="0.01"
+                value={amount}
+                onChange={(e) => setAmount(e.target.value)}
+                aria-invalid={!!errors.amount}
+                autoComplete="off"
+              />
+              {errors.amount && (
+                <p className="text-sm text-destructive">{errors.amount}</p>
+              )}
+            </div>
+            <div className="space-y-1.5">
+              <Label htmlFor="rcpt-date">
+                Date <span className="text-destructive">*</span>
+              </Label>
+              <Input
+                id="rcpt-date"
+                type="date"
+                value={paymentDate}
+                onChange={(e) => setPaymentDate(e.target.value)}
+                aria-invalid={!!errors.paymentDate}
+              />
+              {errors.paymentDate && (
+                <p className="text-sm text-destructive">{errors.paymentDate}</p>
+              )}
+            </div>
+          </div>
+
+          <div className="grid grid-cols-2 gap-3">
+            <div className="space-y-1.5">
+              <Label htmlFor="rcpt-mode">Mode</Label>
+              <Select
+                value={paymentMode}
+                onValueChange={(value) => setPaymentMode(value as ReceiptMode)}
+              >
+                <SelectTrigger id="rcpt-mode" className="w-full">
+                  <SelectValue />
+                </SelectTrigger>
+                <SelectContent>
+                  {RECEIPT_MODE_OPTIONS.map((opt) => (
+                    <SelectItem key={opt.value} value={opt.value}>
+                      {opt.label}
+                    </SelectItem>
+                  ))}
+                </SelectContent>
+              </Select>
+            </div>
+            <div className="space-y-1.5">
+              <Label htmlFor="rcpt-against">Against</Label>
+              <Select value={against} onValueChange={setAgainst}>
+                <SelectTrigger id="rcpt-against" className="w-full">
+                  <SelectValue />
+                </SelectTrigger>
+                <SelectContent>
+                  <SelectItem value={OLDEST_FIRST}>Oldest invoices first</SelectItem>
+                  {openInvoices.map((invoice) => (
+                    <SelectItem key={invoice.saleId} value={String(invoice.saleId)}>
+                      {invoice.invoiceNumber} ({formatPaiseToCurrency(invoice.duePaise)})
+                    </SelectItem>
+                  ))}
+                </SelectContent>
+              </Select>
+            </div>
+          </div>
+
+          <div className="space-y-1.5">
+            <Label htmlFor="rcpt-reference">Reference / Transaction ID</Label>
+            <Input
+              id="rcpt-reference"
+              value={reference}
+              onChange={(e) => setReference(e.target.value)}
+              placeholder="e.g. UTR12345678 (optional)"
+              autoComplete="off"
+            />
+          </div>
+
+          <div className="space-y-1.5">
+            <Label htmlFor="rcpt-notes">Notes</Label>
+            <Input
+              id="rcpt-notes"
+              value={notes}
+              onChange={(e) => setNotes(e.target.value)}
+              placeholder="Optional notes"
+              autoComplete="off"
+            />
+          </div>
+
+          <DialogFooter className="pt-2">
+            <Button
+              type="button"
+              variant="outline"
+              onClick={() => onOpenChange(false)}
+              disabled={saving}
+            >
+              Cancel
+            </Button>
+            <Button type="submit" disabled={saving}>
+              {saving ? "Saving..." : "Record Payment"}
+            </Button>
+          </DialogFooter>
+        </form>
+      </DialogContent>
+    </Dialog>
+  );
+}
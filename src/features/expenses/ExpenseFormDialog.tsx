@@ -0,0 +1,289 @@
+import { useState, useEffect } from "react";
+import { toast } from "sonner";
+import { useAuth } from "@/features/auth/AuthContext";
+import {
+  createExpense,
+  updateExpense,
+  type Expense,
+  type ExpenseCategory,
+  type NewExpense,
+} from "@/db/queries/expenses";
+import { paiseToRupeesString, rupeesToPaise } from "@/lib/currency";
+import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import { Label } from "@/components/ui/label";
+import {
+  Dialog,
+  DialogContent,
+  DialogDescription,
+  DialogFooter,
+  DialogHeader,
+  DialogTitle,
+} from "@/components/ui/dialog";
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from "@/components/ui/select";
+
+type ExpenseMode = NewExpense["paymentMode"];
+
+interface ExpenseFormDialogProps {
+  open: boolean;
+  onOpenChange: (open: boolean) => void;
+  categories: ExpenseCategory[];
+  /** The expense to correct; a new one is recorded when absent */
+  expense?: Expense | null;
+  onSaved: () => void;
+}
+
+interface FormErrors {
+  categoryId?: string;
+  amount?: string;
+  expenseDate?: string;
+}
+
+const MODE_OPTIONS: { value: ExpenseMode; label: string }[] = [
+  { value: "cash", label: "Cash" },
+  { value: "upi", label: "UPI" },
+  { value: "card", label: "Card" },
+];
+
+function todayIso(): string {
+  return new Date().toISOString().split("T")[0];
+}
+
+export default function ExpenseFormDialog({
+  open,
+  onOpenChange,
+  categories,
+  expense,
+  onSaved,
+}: ExpenseFormDialogProps) {
+  const { user } = useAuth();
+  const [categoryId, setCategoryId] = useState("");
+  const [amount, setAmount] = useState("");
+  const [expenseDate, setExpenseDate] = useState(todayIso());
+  const [paymentMode, setPaymentMode] = useState<ExpenseMode>("cash");
+  const [paidTo, setPaidTo] = useState("");
+  const [reference, setReference] = useState("");
+  const [notes, setNotes] = useState("");
+  const [errors, setErrors] = useState<FormErrors>({});
+  const [saving, setSaving] = useState(false);
+
+  const activeCategories = categories.filter(
+    (c) => c.isActive || c.id === expense?.categoryId
+  );
+
+  // Reset form when dialog opens
+  useEffect(() => {
+    if (open) {
+      setCategoryId(expense ? String(expense.categoryId) : "");
+      setAmount(expense ? paiseToRupeesString(expense.amountPaise) : "");
+      setExpenseDate(expense?.expenseDate ?? todayIso());
+      setPaymentMode((expense?.paymentMode as ExpenseMode) ?? "cash");
+      setPaidTo(expense?.paidTo ?? "");
+      setReference(expense?.reference ?? "");
+      setNotes(expense?.notes ?? "");
+      setErrors({});
+    }
+  }, [open, expense]);
+
+  function validate(): boolean {
+    const newErrors: FormErrors = {};
+
+    if (!categoryId) {
+      newErrors.categoryId = "Category is required";
+    }
+
+    if (!amount.trim()) {
+      newErrors.amount = "Amount is required";
+    } else if (rupeesToPaise(amount) <= 0) {
+      newErrors.amount = "Amount must be greater than zero";
+    }
+
+    if (!expenseDate) {
+      newErrors.expenseDate = "Date is required";
+    }
+
+    setErrors(newErrors);
+    return Object.keys(newErrors).length === 0;
+  }
+
+  async function handleSubmit(e: React.FormEvent) {
+    e.preventDefault();
+    if (!user || !validate()) return;
+
+    const payload: NewExpense = {
+      categoryId: Number(categoryId),
+      amountPaise: rupeesToPaise(amount),
+      paymentMode,
+      expenseDate,
+      paidTo: paidTo.trim() || null,
+      reference: reference.trim() || null,
+      notes: notes.trim() || null,
+    };
+    try {
+      setSaving(true);
+      if (expense) {
+        await updateExpense(expense.id, payload);
+        toast.success("Expense updated");
+      } else {
+        await createExpense(payload);
+        toast.success("Expense recorded");
+      }
+      onOpenChange(false);
+      onSaved();
+    } catch (err) {
+      console.error(err);
+      toast.error(String(err));
+    } finally {
+      setSaving(false);
+    }
+  }
+
+  return (
+    <Dialog open={open} onOpenChange={onOpenChange}>
+      <DialogContent className="sm:max-w-md">
+        <DialogHeader>
+          <DialogTitle>{expense ? "Edit Expense" : "Record Expense"}</DialogTitle>
+          <DialogDescription>
+            Money paid out for running the pharmacy, such as rent or electricity.
+          </DialogDescription>
+        </DialogHeader>
+
+        <form onSubmit={handleSubmit} className="space-y-4 py-2">
+          <div className="space-y-1.5">
+            <Label htmlFor="exp-category">
+              Category <span className="text-destructive">*</span>
+            </Label>
+            <Select value={categoryId} onValueChange={setCategoryId}>
+              <SelectTrigger
+                id="exp-category"
+                className="w-full"
+                aria-invalid={!!errors.categoryId}
+              >
+                <SelectValue placeholder="Select a category" />
+              </SelectTrigger>
+              <SelectContent>
+                {activeCategories.map((c) => (
+                  <SelectItem key={c.id} value={String(c.id)}>
+                    {c.name}
+                  </SelectItem>
+                ))}
+              </SelectContent>
+            </Select>
+            {errors.categoryId && (
+              <p className="text-sm text-destructive">{errors.categoryId}</p>
+            )}
+          </div>
+
+          <div className="grid grid-cols-2 gap-3">
+            <div className="space-y-1.5">
+              <Label htmlFor="exp-amount">
+                Amount (₹) <span className="text-destructive">*</span>
+              </Label>
+              <Input
+                id="exp-amount"
+                type="number"
+                min="0.01"
+                step="0.01"
+                value={amount}
+                onChange={(e) => setAmount(e.target.value)}
+                aria-invalid={!!errors.amount}
+                autoComplete="off"
+              />
+              {errors.amount && (
+                <p className="text-sm text-destructive">{errors.amount}</p>
+              )}
+            </div>
+            <div className="space-y-1.5">
+              <Label htmlFor="exp-date">
+                Date <span className="text-destructive">*</span>
+              </Label>
+              <Input
+                id="exp-date"
+                type="date"
+                value={expenseDate}
+                onChange={(e) => setExpenseDate(e.target.value)}
+                aria-invalid={!!errors.expenseDate}
+              />
+              {errors.expenseDate && (
+                <p className="text-sm text-destructive">{errors.expenseDate}</p>
+              )}
+            </div>
+          </div>
+
+          <div className="grid grid-cols-2 gap-3">
+            <div className="space-y-1.5">
+              <Label htmlFor="exp-mode">Mode</Label>
+              <Select
+                value={paymentMode}
+                onValueChange={(value) => setPaymentMode(value as ExpenseMode)}
+              >
+                <SelectTrigger id="exp-mode" className="w-full">
+                  <SelectValue />
+                </SelectTrigger>
+                <SelectContent>
+                  {MODE_OPTIONS.map((opt) => (
+                    <SelectItem key={opt.value} value={opt.value}>
+                      {opt.label}
+                    </SelectItem>
+                  ))}
+                </SelectContent>
+              </Select>
+            </div>
+            <div className="space-y-1.5">
+              <Label htmlFor="exp-paid-to">Paid To</Label>
+              <Input
+                id="exp-paid-to"
+                value={paidTo}
+                onChange={(e) => setPaidTo(e.target.value)}
+                placeholder="Optional"
+                autoComplete="off"
+              />
+            </div>
+          </div>
+
+          <div className="space-y-1.5">
+            <Label htmlFor="exp-reference">Reference / Bill No.</Label>
+            <Input
+              id="exp-reference"
+              value={reference}
+              onChange={(e) => setReference(e.target.value)}
+              placeholder="Optional"
+              autoComplete="off"
+            />
+          </div>
+
+          <div className="space-y-1.5">
+            <Label htmlFor="exp-notes">Notes</Label>
+            <Input
+              id="exp-notes"
+              value={notes}
+              onChange={(e) => setNotes(e.target.value)}
+              placeholder="Optional notes"
+              autoComplete="off"
+            />
+          </div>
+
+          <DialogFooter className="pt-2">
+            <Button
+              type="button"
+              variant="outline"
+              onClick={() => onOpenChange(false)}
+              disabled={saving}
+            >
+              Cancel
+            </Button>
+            <Button type="submit" disabled={saving}>
+              {saving ? "Saving..." : expense ? "Save Changes" : "Record Expense"}
+            </Button>
+          </DialogFooter>
+        </form>
+      </DialogContent>
+    </Dialog>
+  );
+}
@@ -0,0 +1,381 @@
+import { useState, useEffect, useCallback } from "react";
+import { toast } from "sonner";
+import { PencilIcon, PlusIcon, Trash2Icon, WalletIcon } from "lucide-react";
+import { useAuth } from "@/features/auth/AuthContext";
+import { useDataChanged } from "@/hooks/useDataChanged";
+import { formatPaiseToCurrency } from "@/lib/currency";
+import {
+  getExpenses,
+  getExpenseCategories,
+  getExpenseReport,
+  createExpenseCategory,
+  setExpenseCategoryActive,
+  deleteExpense,
+  type Expense,
+  type ExpenseCategory,
+  type ExpenseReport,
+} from "@/db/queries/expenses";
+import ExpenseFormDialog from "./ExpenseFormDialog";
+import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import { Card, CardContent, CardHeader, CardTitle } from "@/components/ui/card";
+import { Badge } from "@/components/ui/badge";
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from "@/components/ui/select";
+import {
+  Table,
+  TableBody,
+  TableCell,
+  TableFooter,
+  TableHead,
+  TableHeader,
+  TableRow,
+} from "@/components/ui/table";
+
+/** Select value for expenses of every category */
+const ALL_CATEGORIES = "all";
+
+/** YYYY-MM-DD string for <input type="date"> */
+function toISODate(d: Date): string {
+  const year = d.getFullYear();
+  const month = String(d.getMonth() + 1).padStart(2, "0");
+  const day = String(d.getDate()).padStart(2, "0");
+  return `${year}-${month}-${day}`;
+}
+
+function firstOfMonth(): string {
+  const now = new Date();
+  return toISODate(new Date(now.getFullYear(), now.getMonth(), 1));
+}
+
+function today(): string {
+  return toISODate(new Date());
+}
+
+export default function ExpensesPage() {
+  const { user } = useAuth();
+  const [startDate, setStartDate] = useState(firstOfMonth);
+  const [endDate, setEndDate] = useState(today);
+  const [categoryFilter, setCategoryFilter] = useState(ALL_CATEGORIES);
+  const [categories, setCategories] = useState<ExpenseCategory[]>([]);
+  const [expenses, setExpenses] = useState<Expense[]>([]);
+  const [report, setReport] = useState<ExpenseReport | null>(null);
+  const [loading, setLoading] = useState(true);
+  const [dialogOpen, setDialogOpen] = useState(false);
+  const [editing, setEditing] = useState<Expense | null>(null);
+  const [newCategory, setNewCategory] = useState("");
+
+  const loadCategories = useCallback(async () => {
+    try {
+      setCategories(await getExpenseCategories());
+    } catch (err) {
+      console.error(err);
+      toast.error(String(err));
+    }
+  }, []);
+
+  const loadExpenses = useCallback(async () => {
+    try {
+      setLoading(true);
+      const categoryId =
+        categoryFilter === ALL_CATEGORIES ? null : Number(categoryFilter);
+      const [list, totals] = await Promise.all([
+        getExpenses(startDate, endDate, categoryId),
+        getExpenseReport(startDate, endDate),
+      ]);
+      setExpenses(list);
+      setReport(totals);
+    } catch (err) {
+      console.error(err);
+      toast.error(String(err));
+    } finally {
+      setLoading(false);
+    }
+  }, [startDate, endDate, categoryFilter]);
+
+  useEffect(() => {
+    loadCategories();
+  }, [loadCategories]);
+
+  useEffect(() => {
+    loadExpenses();
+  }, [loadExpenses]);
+
+  useDataChanged(["expense_category"], loadCategories);
+  useDataChanged(["expense"], loadExpenses);
+
+  const openDialog = (expense: Expense | null) => {
+    setEditing(expense);
+    setDialogOpen(true);
+  };
+
+  const handleDelete = async (expense: Expense) => {
+    if (!user) return;
+    if (!window.confirm(`Delete the ${expense.category} expense of ${formatPaiseToCurrency(expense.amountPaise)}?`)) {
+      return;
+    }
+    try {
+      await deleteExpense(expense.id);
+      toast.success("Expense deleted");
+      loadExpenses();
+    } catch (err) {
+      toast.error(String(err));
+    }
+  };
+
+  const handleAddCategory = async (e: React.FormEvent) => {
+    e.preventDefault();
+    if (!user || !newCategory.trim()) return;
+    try {
+      await createExpenseCategory(newCategory);
+      setNewCategory("");
+      loadCategories();
+    } catch (err) {
+      toast.error(String(err));
+    }
+  };
+
+  const toggleCategory = async (category: ExpenseCategory) => {
+    if (!user) return;
+    try {
+      await setExpenseCategoryActive(category.id, !category.isActive);
+      loadCategories();
+    } catch (err) {
+      toast.error(String(err));
+    }
+  };
+
+  const listedTotal = expenses.reduce((sum, e) => sum + e.amountPaise, 0);
+
+  return (
+    <div className="space-y-6">
+      {/* Header */}
+      <div className="flex items-start justify-between">
+        <div>
+          <h1 className="text-3xl font-bold text-slate-900">Expenses</h1>
+          <p className="text-slate-600 mt-1">
+            Rent, salaries, bills and other money paid out of the pharmacy
+          </p>
+        </div>
+        <Button onClick={() => openDialog(null)} className="gap-2">
+          <PlusIcon className="size-4" />
+          Record Expense
+        </Button>
+      </div>
+
+      {/* Filters */}
+      <div className="flex flex-wrap items-end gap-4">
+        <div className="space-y-1.5">
+          <label htmlFor="exp-start-date" className="text-sm font-medium text-slate-700">
+            From
+          </label>
+          <Input
+            id="exp-start-date"
+            type="date"
+            value={startDate}
+            onChange={(e) => setStartDate(e.target.value)}
+            className="w-40"
+          />
+        </div>
+        <div className="space-y-1.5">
+          <label htmlFor="exp-end-date" className="text-sm font-medium text-slate-700">
+            To
+          </label>
+          <Input
+            id="exp-end-date"
+            type="date"
+            value={endDate}
+            onChange={(e) => setEndDate(e.target.value)}
+            className="w-40"
+          />
+        </div>
+        <div className="space-y-1.5">
+          <label htmlFor="exp-category-filter" className="text-sm font-medium text-slate-700">
+            Category
+          </label>
+          <Select value={categoryFilter} onValueChange={setCategoryFilter}>
+            <SelectTrigger id="exp-category-filter" className="w-52">
+              <SelectValue />
+            </SelectTrigger>
+            <SelectContent>
+              <SelectItem value={ALL_CATEGORIES}>All categories</SelectItem>
+              {categories.map((c) => (
+                <SelectItem key={c.id} value={String(c.id)}>
+                  {c.name}
+                </SelectItem>
+              ))}
+            </SelectContent>
+          </Select>
+        </div>
+      </div>
+
+      <div className="grid grid-cols-1 gap-6 lg:grid-cols-3">
+        {/* Expense list */}
+        <div className="rounded-lg border bg-card shadow-sm lg:col-span-2">
+          {loading ? (
+            <div className="flex items-center justify-center py-16">
+              <p className="text-muted-foreground text-sm">Loading&hellip;</p>
+            </div>
+          ) : expenses.length === 0 ? (
+            <div className="flex flex-col items-center justify-center py-16 gap-3">
+              <WalletIcon className="size-10 text-muted-foreground/40" />
+              <p className="text-muted-foreground text-sm">
+                No expenses recorded for the selected period.
+              </p>
+            </div>
+          ) : (
+            <Table>
+              <TableHeader>
+                <TableRow>
+                  <TableHead>Date</TableHead>
+                  <TableHead>Category</TableHead>
+                  <TableHead>Paid To</TableHead>
+                  <TableHead>Mode</TableHead>
+                  <TableHead className="text-right">Amount</TableHead>
+                  <TableHead />
+                </TableRow>
+              </TableHeader>
+              <TableBody>
+                {expenses.map((expense) => (
+                  <TableRow key={expense.id}>
+                    <TableCell className="text-slate-600 text-sm whitespace-nowrap">
+                      {expense.expenseDate}
+                    </TableCell>
+                    <TableCell className="text-sm">
+                      {expense.category}
+                      {expense.reference && (
+                        <span className="ml-2 font-mono text-xs text-muted-foreground">
+                          {expense.reference}
+                        </span>
+                      )}
+                    </TableCell>
+                    <TableCell className="text-sm text-slate-600">
+                      {expense.paidTo ?? "—"}
+                    </TableCell>
+                    <TableCell>
+                      <Badge variant="outline" className="uppercase text-xs">
+                        {expense.paymentMode}
+                      </Badge>
+                    </TableCell>
+                    <TableCell className="text-right tabular-nums text-sm">
+                      {formatPaiseToCurrency(expense.amountPaise)}
+                    </TableCell>
+                    <TableCell className="text-right whitespace-nowrap">
+                      <Button
+                        variant="ghost"
+                        size="icon"
+                        onClick={() => openDialog(expense)}
+                        aria-label="Edit expense"
+                      >
+                        <PencilIcon className="size-4" />
+                      </Button>
+                      <Button
+                        variant="ghost"
+                        size="icon"
+                        onClick={() => handleDelete(expense)}
+                        aria-label="Delete expense"
+                      >
+                        <Trash2Icon className="size-4 text-red-600" />
+                      </Button>
+                    </TableCell>
+                  </TableRow>
+                ))}
+              </TableBody>
+              <TableFooter>
+                <TableRow className="font-bold">
+                  <TableCell colSpan={4}>
+                    Total ({expenses.length} expense{expenses.length !== 1 ? "s" : ""})
+                  </TableCell>
+                  <TableCell className="text-right tabular-nums">
+                    {formatPaiseToCurrency(listedTotal)}
+                  </TableCell>
+                  <TableCell />
+                </TableRow>
+              </TableFooter>
+            </Table>
+          )}
+        </div>
+
+        <div className="space-y-6">
+          {/* Totals by category */}
+          <Card>
+            <CardHeader>
+              <CardTitle className="text-base">By Category</CardTitle>
+            </CardHeader>
+            <CardContent>
+              {!report || report.categories.length === 0 ? (
+                <p className="text-muted-foreground text-sm">Nothing spent yet.</p>
+              ) : (
+                <Table>
+                  <TableBody>
+                    {report.categories.map((c) => (
+                      <TableRow key={c.categoryId}>
+                        <TableCell className="text-sm">{c.category}</TableCell>
+                        <TableCell className="text-right tabular-nums text-sm">
+                          {formatPaiseToCurrency(c.totalPaise)}
+                        </TableCell>
+                      </TableRow>
+                    ))}
+                  </TableBody>
+                  <TableFooter>
+                    <TableRow>
+                      <TableCell className="text-sm font-medium">Total</TableCell>
+                      <TableCell className="text-right tabular-nums text-sm font-medium">
+                        {formatPaiseToCurrency(report.totalPaise)}
+                      </TableCell>
+                    </TableRow>
+                  </TableFooter>
+                </Table>
+              )}
+            </CardContent>
+          </Card>
+
+          {/* Categories */}
+          <Card>
+            <CardHeader>
+              <CardTitle className="text-base">Categories</CardTitle>
+            </CardHeader>
+            <CardContent className="space-y-3">
+              <form onSubmit={handleAddCategory} className="flex gap-2">
+                <Input
+                  value={newCategory}
+                  onChange={(e) => setNewCategory(e.target.value)}
+                  placeholder="New category"
+                  autoComplete="off"
+                />
+                <Button type="submit" variant="outline" disabled={!newCategory.trim()}>
+                  Add
+                </Button>
+              </form>
+              <ul className="space-y-1">
+                {categories.map((c) => (
+                  <li key={c.id} className="flex items-center justify-between text-sm">
+                    <span className={c.isActive ? "" : "text-muted-foreground line-through"}>
+                      {c.name}
+                    </span>
+                    <Button variant="link" size="sm" onClick={() => toggleCategory(c)}>
+                      {c.isActive ? "Retire" : "Restore"}
+                    </Button>
+                  </li>
+                ))}
+              </ul>
+            </CardContent>
+          </Card>
+        </div>
+      </div>
+
+      <ExpenseFormDialog
+        open={dialogOpen}
+        onOpenChange={setDialogOpen}
+        categories={categories}
+        expense={editing}
+        onSaved={loadExpenses}
+      />
+    </div>
+  );
+}
@@ -24,6 +24,7 @@ interface BatchFormDialogProps {
 
 interface FormState {
   batchNumber: string;
+  barcode: string;
   manufacturingDate: string;
   expiryDate: string;
   costPrice: string;
@@ -43,6 +44,7 @@ interface FormErrors {
 
 const DEFAULT_FORM: FormState = {
   batchNumber: "",
+  barcode: "",
   manufacturingDate: "",
   expiryDate: "",
   costPrice: "",
@@ -131,6 +133,7 @@ export default function BatchFormDialog({
         sellingPricePaise: rupeesToPaise(form.sellingPrice),
         quantity: parseInt(form.quantity, 10),
         manufacturingDate: form.manufacturingDate || null,
+        barcode: form.barcode.trim() || null,
       });
 
       toast.success("Batch added successfully");
@@ -139,7 +142,9 @@ export default function BatchFormDialog({
     } catch (err) {
       console.error(err);
       const errMsg = String(err);
-      if (errMsg.includes("CHECK")) {
+      if (errMsg.includes("UNIQUE") && errMsg.includes("barcode")) {
+        toast.error("This barcode is already assigned to another batch");
+      } else if (errMsg.includes("CHECK")) {
         toast.error("Selling price cannot exceed MRP (database constraint)");
       } else {
         toast.error("Failed to add batch");
@@ -196,6 +201,18 @@ export default function BatchFormDialog({
             )}
           </div>
 
+          {/* Barcode */}
+          <div className="space-y-1.5">
+            <Label htmlFor="batch-barcode">Barcode</Label>
+            <Input
+              id="batch-barcode"
+              value={form.barcode}
+              onChange={(e) => handleFieldChange("barcode", e.target.value)}
+              placeholder="Scan or type the pack barcode"
+              autoComplete="off"
+            />
+          </div>
+
           {/* Dates row */}
           <div className="grid grid-cols-2 gap-3">
             <div className="space-y-1.5">
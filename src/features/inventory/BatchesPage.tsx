@@ -12,6 +12,7 @@ import {
 import { getMedicineById, getMedicinesWithGst } from "@/db/queries/medicines";
 import { formatPaiseToCurrency } from "@/lib/currency";
 import { useSettings } from "@/hooks/useSettings";
+import { useDataChanged } from "@/hooks/useDataChanged";
 import { Button } from "@/components/ui/button";
 import { Badge } from "@/components/ui/badge";
 import {
@@ -104,6 +105,9 @@ export default function BatchesPage() {
     fetchBatches();
   }, [fetchBatches]);
 
+  // Status changes etc. made by backend commands
+  useDataChanged(["batch"], fetchBatches);
+
   function handleMedicineSelect(value: string) {
     if (value === "none") {
       setSearchParams({});
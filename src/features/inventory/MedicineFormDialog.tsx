@@ -1,7 +1,17 @@
 import { useState, useEffect } from "react";
 import { toast } from "sonner";
-import type { MedicineWithGst, GstSlab, DosageForm } from "@/types";
-import { createMedicine, updateMedicine } from "@/db/queries/medicines";
+import type {
+  MedicineWithGst,
+  GstSlab,
+  DosageForm,
+  DrugSchedule,
+} from "@/types";
+import {
+  createMedicine,
+  deactivateMedicine,
+  reactivateMedicine,
+  updateMedicine,
+} from "@/db/queries/medicines";
 import { getGstSlabs } from "@/db/queries/gstSlabs";
 import { Button } from "@/components/ui/button";
 import { Input } from "@/components/ui/input";
@@ -37,7 +47,11 @@ interface FormState {
   dosageForm: DosageForm;
   strength: string;
   category: string;
+  /** "otc" stands for no schedule, since a select item cannot be empty */
+  schedule: DrugSchedule | "otc";
+  packSize: string;
   hsnCode: string;
+  gtin: string;
   gstSlabId: string;
   reorderLevel: string;
   isActive: boolean;
@@ -46,7 +60,9 @@ interface FormState {
 interface FormErrors {
   name?: string;
   hsnCode?: string;
+  gtin?: string;
   gstSlabId?: string;
+  packSize?: string;
   reorderLevel?: string;
 }
 
@@ -93,6 +109,13 @@ const CATEGORY_OPTIONS = [
   { value: "other", label: "Other" },
 ];
 
+const SCHEDULE_OPTIONS: { value: DrugSchedule | "otc"; label: string }[] = [
+  { value: "otc", label: "Not scheduled (OTC)" },
+  { value: "H", label: "Schedule H" },
+  { value: "H1", label: "Schedule H1" },
+  { value: "X", label: "Schedule X" },
+];
+
 const DEFAULT_FORM: FormState = {
   name: "",
   genericName: "",
@@ -101,7 +124,10 @@ const DEFAULT_FORM: FormState = {
   dosageForm: "tablet",
   strength: "",
   category: "",
+  schedule: "otc",
+  packSize: "1",
   hsnCode: "3004",
+  gtin: "",
   gstSlabId: "",
   reorderLevel: "20",
   isActive: true,
@@ -146,7 +172,10 @@ export default function MedicineFormDialog({
           dosageForm: medicine.dosageForm,
           strength: medicine.strength ?? "",
           category: medicine.category ?? "",
+          schedule: medicine.schedule ?? "otc",
+          packSize: String(medicine.packSize),
           hsnCode: medicine.hsnCode,
+          gtin: medicine.gtin ?? "",
           gstSlabId: String(medicine.gstSlabId),
           reorderLevel: String(medicine.reorderLevel),
           isActive: medicine.isActive,
@@ -171,10 +200,19 @@ export default function MedicineFormDialog({
       newErrors.hsnCode = "HSN code must be 4 to 8 digits";
     }
 
+    if (form.gtin.trim() && !/^\d{8,14}$/.test(form.gtin.trim())) {
+      newErrors.gtin = "Product barcode must be 8 to 14 digits";
+    }
+
     if (!form.gstSlabId) {
       newErrors.gstSlabId = "GST rate is required";
     }
 
+    const packNum = Number(form.packSize);
+    if (form.packSize !== "" && (!Number.isInteger(packNum) || packNum < 1)) {
+      newErrors.packSize = "Pack size must be a whole number of at least 1";
+    }
+
     const reorderNum = Number(form.reorderLevel);
     if (form.reorderLevel !== "" && (isNaN(reorderNum) || reorderNum < 0)) {
       newErrors.reorderLevel = "Reorder level must be a non-negative number";
@@ -200,16 +238,24 @@ export default function MedicineFormDialog({
         dosageForm: form.dosageForm,
         strength: form.strength.trim() || null,
         category: form.category || null,
+        schedule: form.schedule === "otc" ? null : form.schedule,
+        packSize: form.packSize === "" ? 1 : Number(form.packSize),
+        saleUnit: medicine?.saleUnit ?? null,
         hsnCode: form.hsnCode.trim(),
+        gtin: form.gtin.trim() || null,
         gstSlabId: Number(form.gstSlabId),
         reorderLevel: form.reorderLevel === "" ? 20 : Number(form.reorderLevel),
       };
 
       if (isEditMode && medicine) {
-        await updateMedicine(medicine.id, {
-          ...payload,
-          isActive: form.isActive,
-        });
+        await updateMedicine(medicine.id, payload);
+        if (form.isActive !== medicine.isActive) {
+          if (form.isActive) {
+            await reactivateMedicine(medicine.id);
+          } else {
+            await deactivateMedicine(medicine.id);
+          }
+        }
         toast.success("Medicine updated successfully");
       } else {
         await createMedicine(payload);
@@ -221,7 +267,11 @@ export default function MedicineFormDialog({
     } catch (err) {
       console.error(err);
       toast.error(
-        isEditMode ? "Failed to update medicine" : "Failed to create medicine"
+        typeof err === "string"
+          ? err
+          : isEditMode
+            ? "Failed to update medicine"
+            : "Failed to create medicine"
       );
     } finally {
       setSaving(false);
@@ -370,6 +420,28 @@ export default function MedicineFormDialog({
             </Select>
           </div>
 
+          {/* Drug schedule */}
+          <div className="space-y-1.5">
+            <Label htmlFor="med-schedule">Drug Schedule</Label>
+            <Select
+              value={form.schedule}
+              onValueChange={(val) =>
+                handleFieldChange("schedule", val as FormState["schedule"])
+              }
+            >
+              <SelectTrigger id="med-schedule" className="w-full">
+                <SelectValue />
+              </SelectTrigger>
+              <SelectContent>
+                {SCHEDULE_OPTIONS.map((s) => (
+                  <SelectItem key={s.value} value={s.value}>
+                    {s.label}
+                  </SelectItem>
+                ))}
+              </SelectContent>
+            </Select>
+          </div>
+
           {/* HSN Code + GST Rate row */}
           <div className="grid grid-cols-2 gap-3">
             <div className="space-y-1.5">
@@ -421,6 +493,39 @@ export default function MedicineFormDialog({
             </div>
           </div>
 
+          {/* Product barcode */}
+          <div className="space-y-1.5">
+            <Label htmlFor="med-gtin">Product Barcode (EAN/GTIN)</Label>
+            <Input
+              id="med-gtin"
+              value={form.gtin}
+              onChange={(e) => handleFieldChange("gtin", e.target.value)}
+              placeholder="Scan the barcode on the pack"
+              aria-invalid={!!errors.gtin}
+              autoComplete="off"
+            />
+            {errors.gtin && (
+              <p className="text-sm text-destructive">{errors.gtin}</p>
+            )}
+          </div>
+
+          {/* Pack Size */}
+          <div className="space-y-1.5">
+            <Label htmlFor="med-pack-size">Units per Pack</Label>
+            <Input
+              id="med-pack-size"
+              type="number"
+              min="1"
+              value={form.packSize}
+              onChange={(e) => handleFieldChange("packSize", e.target.value)}
+              placeholder="1"
+              aria-invalid={!!errors.packSize}
+            />
+            {errors.packSize && (
+              <p className="text-sm text-destructive">{errors.packSize}</p>
+            )}
+          </div>
+
           {/* Reorder Level */}
           <div className="space-y-1.5">
             <Label htmlFor="med-reorder">Reorder Level</Label>
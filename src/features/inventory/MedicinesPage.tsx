@@ -12,7 +12,8 @@ import type { MedicineWithGst, GstSlab, DosageForm } from "@/types";
 import {
   getMedicinesWithGst,
   searchMedicines,
-  updateMedicine,
+  deactivateMedicine,
+  reactivateMedicine,
 } from "@/db/queries/medicines";
 import { getGstSlabs } from "@/db/queries/gstSlabs";
 import { Button } from "@/components/ui/button";
@@ -150,7 +151,11 @@ export default function MedicinesPage() {
   async function handleToggleActive(medicine: MedicineWithGst) {
     setTogglingId(medicine.id);
     try {
-      await updateMedicine(medicine.id, { isActive: !medicine.isActive });
+      if (medicine.isActive) {
+        await deactivateMedicine(medicine.id);
+      } else {
+        await reactivateMedicine(medicine.id);
+      }
       toast.success(
         medicine.isActive
           ? `${medicine.name} has been deactivated`
@@ -159,7 +164,9 @@ export default function MedicinesPage() {
       await fetchMedicines();
     } catch (err) {
       console.error(err);
-      toast.error("Failed to update medicine status");
+      toast.error(
+        typeof err === "string" ? err : "Failed to update medicine status"
+      );
     } finally {
       setTogglingId(null);
     }
@@ -1,9 +1,21 @@
 import { useState, useEffect, useCallback } from "react";
 import { toast } from "sonner";
-import { PackageIcon } from "lucide-react";
+import { BellIcon, CheckIcon, FilePlusIcon, PackageIcon, RefreshCwIcon } from "lucide-react";
 import { getDb } from "@/db/index";
+import { UNEXPIRED } from "@/db/utils";
 import type { StockAlert } from "@/types";
+import { useAuth } from "@/features/auth/AuthContext";
+import { useDataChanged } from "@/hooks/useDataChanged";
+import { acknowledgeAlert, checkAlerts, getAlerts, type Alert } from "@/db/queries/alerts";
+import {
+  draftSuggestedOrders,
+  getOrderSuggestions,
+  type SupplierOrder,
+} from "@/db/queries/reorder";
+import { formatPaiseToCurrency } from "@/lib/currency";
 import { Badge } from "@/components/ui/badge";
+import { Button } from "@/components/ui/button";
+import { Card, CardContent, CardHeader, CardTitle } from "@/components/ui/card";
 import {
   Table,
   TableBody,
@@ -26,7 +38,7 @@ async function getStockAlerts(): Promise<StockAlert[]> {
     `SELECT 
        m.id as medicine_id,
        m.name as medicine_name,
-       COALESCE(SUM(CASE WHEN b.expiry_date > date('now') AND b.quantity > 0 THEN b.quantity ELSE 0 END), 0) as current_stock,
+       COALESCE(SUM(CASE WHEN ${UNEXPIRED} AND b.quantity > 0 AND b.status = 'available' THEN b.quantity ELSE 0 END), 0) as current_stock,
        m.reorder_level
      FROM medicines m
      LEFT JOIN batches b ON m.id = b.medicine_id
@@ -43,6 +55,224 @@ async function getStockAlerts(): Promise<StockAlert[]> {
   }));
 }
 
+/** Days of sales the suggested quantities are based on */
+const LOOKBACK_DAYS = 30;
+
+function AlertFeed() {
+  const { user } = useAuth();
+  const [alerts, setAlerts] = useState<Alert[]>([]);
+  const [checking, setChecking] = useState(false);
+
+  const load = useCallback(async () => {
+    try {
+      setAlerts(await getAlerts());
+    } catch (err) {
+      console.error(err);
+      toast.error(String(err));
+    }
+  }, []);
+
+  useEffect(() => {
+    load();
+  }, [load]);
+
+  useDataChanged(["alert"], load);
+
+  const handleCheck = async () => {
+    try {
+      setChecking(true);
+      const raised = await checkAlerts();
+      if (raised.length === 0) toast.success("No new alerts");
+      load();
+    } catch (err) {
+      toast.error(String(err));
+    } finally {
+      setChecking(false);
+    }
+  };
+
+  const handleAcknowledge = async (alert: Alert) => {
+    if (!user) return;
+    try {
+      await acknowledgeAlert(alert.id);
+      load();
+    } catch (err) {
+      toast.error(String(err));
+    }
+  };
+
+  return (
+    <Card>
+      <CardHeader className="flex flex-row items-center justify-between">
+        <CardTitle className="flex items-center gap-2 text-base">
+          <BellIcon className="size-4" />
+          Alerts
+        </CardTitle>
+        <Button
+          variant="outline"
+          size="sm"
+          onClick={handleCheck}
+          disabled={checking}
+          className="gap-2"
+        >
+          <RefreshCwIcon className="size-4" />
+          Check Now
+        </Button>
+      </CardHeader>
+      <CardContent>
+        {alerts.length === 0 ? (
+          <p className="text-muted-foreground text-sm">No unacknowledged alerts.</p>
+        ) : (
+          <ul className="divide-y">
+            {alerts.map((alert) => (
+              <li key={alert.id} className="flex items-center justify-between gap-4 py-2 text-sm">
+                <div className="flex items-center gap-3">
+                  {alert.kind === "low_stock" ? (
+                    <Badge className="bg-amber-100 text-amber-700 border-amber-200 hover:bg-amber-100">
+                      Low Stock
+                    </Badge>
+                  ) : (
+                    <Badge className="bg-red-100 text-red-700 border-red-200 hover:bg-red-100">
+                      Near Expiry
+                    </Badge>
+                  )}
+                  <span>{alert.message}</span>
+                </div>
+                <div className="flex shrink-0 items-center gap-3">
+                  <span className="text-muted-foreground">{alert.createdAt}</span>
+                  <Button
+                    variant="ghost"
+                    size="icon"
+                    onClick={() => handleAcknowledge(alert)}
+                    aria-label="Acknowledge alert"
+                  >
+                    <CheckIcon className="size-4" />
+                  </Button>
+                </div>
+              </li>
+            ))}
+          </ul>
+        )}
+      </CardContent>
+    </Card>
+  );
+}
+
+function SuggestedOrders() {
+  const { user } = useAuth();
+  const [orders, setOrders] = useState<SupplierOrder[]>([]);
+  const [drafting, setDrafting] = useState(false);
+
+  const load = useCallback(async () => {
+    try {
+      setOrders(await getOrderSuggestions(LOOKBACK_DAYS));
+    } catch (err) {
+      console.error(err);
+      toast.error(String(err));
+    }
+  }, []);
+
+  useEffect(() => {
+    load();
+  }, [load]);
+
+  const handleDraft = async (supplierId: number | null) => {
+    if (!user) return;
+    try {
+      setDrafting(true);
+      const drafts = await draftSuggestedOrders(LOOKBACK_DAYS, supplierId);
+      toast.success(
+        drafts.length === 1
+          ? `Drafted ${drafts[0].poNumber} for ${drafts[0].supplierName}`
+          : `Drafted ${drafts.length} purchase orders`
+      );
+    } catch (err) {
+      toast.error(String(err));
+    } finally {
+      setDrafting(false);
+    }
+  };
+
+  if (orders.length === 0) return null;
+  const hasSuppliers = orders.some((o) => o.supplierId !== null);
+
+  return (
+    <div className="space-y-4">
+      <div className="flex items-start justify-between">
+        <div>
+          <h2 className="text-xl font-semibold text-slate-900">Suggested Orders</h2>
+          <p className="text-sm text-slate-600">
+            Back to the reorder level plus lead-time demand at the last {LOOKBACK_DAYS} days'
+            rate, by the supplier last bought from
+          </p>
+        </div>
+        {hasSuppliers && (
+          <Button onClick={() => handleDraft(null)} disabled={drafting} className="gap-2">
+            <FilePlusIcon className="size-4" />
+            Draft All
+          </Button>
+        )}
+      </div>
+
+      {orders.map((order) => (
+        <Card key={order.supplierId ?? "none"}>
+          <CardHeader className="flex flex-row items-center justify-between">
+            <CardTitle className="text-base">
+              {order.supplierName ?? "No supplier on record"}
+            </CardTitle>
+            {order.supplierId !== null && (
+              <Button
+                variant="outline"
+                size="sm"
+                onClick={() => handleDraft(order.supplierId)}
+                disabled={drafting}
+                className="gap-2"
+              >
+                <FilePlusIcon className="size-4" />
+                Draft PO
+              </Button>
+            )}
+          </CardHeader>
+          <CardContent>
+            <Table>
+              <TableHeader>
+                <TableRow>
+                  <TableHead>Medicine</TableHead>
+                  <TableHead className="text-right">Stock</TableHead>
+                  <TableHead className="text-right">Reorder Level</TableHead>
+                  <TableHead className="text-right">Sold / Day</TableHead>
+                  <TableHead className="text-right">Order Qty</TableHead>
+                  <TableHead className="text-right">Last Cost</TableHead>
+                </TableRow>
+              </TableHeader>
+              <TableBody>
+                {order.items.map((item) => (
+                  <TableRow key={item.medicineId}>
+                    <TableCell className="font-medium">{item.medicineName}</TableCell>
+                    <TableCell className="text-right tabular-nums">{item.currentStock}</TableCell>
+                    <TableCell className="text-right tabular-nums text-slate-600">
+                      {item.reorderLevel}
+                    </TableCell>
+                    <TableCell className="text-right tabular-nums text-slate-600">
+                      {item.meanDailyDemand.toFixed(1)}
+                    </TableCell>
+                    <TableCell className="text-right tabular-nums font-medium">
+                      {item.suggestedQuantity}
+                    </TableCell>
+                    <TableCell className="text-right tabular-nums">
+                      {item.lastCostPaise > 0 ? formatPaiseToCurrency(item.lastCostPaise) : "—"}
+                    </TableCell>
+                  </TableRow>
+                ))}
+              </TableBody>
+            </Table>
+          </CardContent>
+        </Card>
+      ))}
+    </div>
+  );
+}
+
 export default function StockAlertsPage() {
   const [alerts, setAlerts] = useState<StockAlert[]>([]);
   const [loading, setLoading] = useState(true);
@@ -74,6 +304,8 @@ export default function StockAlertsPage() {
         </p>
       </div>
 
+      <AlertFeed />
+
       {/* Table */}
       <div className="rounded-lg border bg-card shadow-sm">
         {loading ? (
@@ -131,6 +363,8 @@ export default function StockAlertsPage() {
           </Table>
         )}
       </div>
+
+      <SuggestedOrders />
     </div>
   );
 }
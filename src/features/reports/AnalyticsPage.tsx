@@ -0,0 +1,366 @@
+import { useState, useEffect, useCallback } from "react";
+import { toast } from "sonner";
+import { BarChart3 } from "lucide-react";
+import { formatPaiseToCurrency } from "@/lib/currency";
+import {
+  getAbcAnalysis,
+  getSalesAnalytics,
+  type AbcClass,
+  type AbcReport,
+  type SalesAnalytics,
+  type SalesBucket,
+} from "@/db/queries/reports";
+import type { ModeTotals } from "@/db/queries/dayBook";
+import { Input } from "@/components/ui/input";
+import { Badge } from "@/components/ui/badge";
+import { Card, CardContent, CardHeader, CardTitle } from "@/components/ui/card";
+import {
+  Table,
+  TableBody,
+  TableCell,
+  TableHead,
+  TableHeader,
+  TableRow,
+} from "@/components/ui/table";
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+/** YYYY-MM-DD string for <input type="date"> */
+function toISODate(d: Date): string {
+  const year = d.getFullYear();
+  const month = String(d.getMonth() + 1).padStart(2, "0");
+  const day = String(d.getDate()).padStart(2, "0");
+  return `${year}-${month}-${day}`;
+}
+
+function daysAgo(days: number): string {
+  const d = new Date();
+  d.setDate(d.getDate() - days);
+  return toISODate(d);
+}
+
+function today(): string {
+  return toISODate(new Date());
+}
+
+const CLASS_STYLES: Record<AbcClass, string> = {
+  A: "bg-emerald-100 text-emerald-700 border-emerald-200 hover:bg-emerald-100",
+  B: "bg-amber-100 text-amber-700 border-amber-200 hover:bg-amber-100",
+  C: "bg-slate-100 text-slate-700 border-slate-200 hover:bg-slate-100",
+};
+
+const CLASS_HINTS: Record<AbcClass, string> = {
+  A: "First 80% of sales value",
+  B: "Next 15%",
+  C: "Remaining 5%",
+};
+
+const WEEKDAYS = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+const PAYMENT_MODES: { key: keyof ModeTotals; label: string }[] = [
+  { key: "cashPaise", label: "Cash" },
+  { key: "cardPaise", label: "Card" },
+  { key: "upiPaise", label: "UPI" },
+  { key: "creditPaise", label: "Credit" },
+];
+
+// ---------------------------------------------------------------------------
+// Sales analytics
+// ---------------------------------------------------------------------------
+
+function StatCard({ label, value }: { label: string; value: string }) {
+  return (
+    <Card>
+      <CardHeader className="pb-2">
+        <CardTitle className="text-sm font-medium text-muted-foreground">{label}</CardTitle>
+      </CardHeader>
+      <CardContent>
+        <p className="text-2xl font-bold tabular-nums">{value}</p>
+      </CardContent>
+    </Card>
+  );
+}
+
+/** Horizontal bars of bill value per bucket, scaled to the busiest one */
+function BucketBars({
+  title,
+  buckets,
+  label,
+}: {
+  title: string;
+  buckets: SalesBucket[];
+  label: (bucket: number) => string;
+}) {
+  const max = Math.max(1, ...buckets.map((b) => b.totalPaise));
+  return (
+    <Card>
+      <CardHeader>
+        <CardTitle className="text-base">{title}</CardTitle>
+      </CardHeader>
+      <CardContent className="space-y-1">
+        {buckets.map((b) => (
+          <div key={b.bucket} className="flex items-center gap-3 text-sm">
+            <span className="w-12 shrink-0 text-muted-foreground tabular-nums">
+              {label(b.bucket)}
+            </span>
+            <div className="h-3 flex-1 rounded bg-slate-100">
+              <div
+                className="h-3 rounded bg-slate-700"
+                style={{ width: `${(b.totalPaise / max) * 100}%` }}
+              />
+            </div>
+            <span className="w-28 shrink-0 text-right tabular-nums">
+              {formatPaiseToCurrency(b.totalPaise)}
+            </span>
+            <span className="w-10 shrink-0 text-right text-muted-foreground tabular-nums">
+              {b.invoiceCount}
+            </span>
+          </div>
+        ))}
+      </CardContent>
+    </Card>
+  );
+}
+
+function SalesSection({ report }: { report: SalesAnalytics }) {
+  const paid = PAYMENT_MODES.reduce((sum, m) => sum + report.paymentMix[m.key], 0);
+  return (
+    <div className="space-y-4">
+      <h2 className="text-xl font-semibold text-slate-900">Sales</h2>
+
+      <div className="grid grid-cols-1 gap-4 sm:grid-cols-3">
+        <StatCard label="Bills" value={String(report.invoiceCount)} />
+        <StatCard label="Sales" value={formatPaiseToCurrency(report.totalPaise)} />
+        <StatCard label="Average Bill" value={formatPaiseToCurrency(report.averageBillPaise)} />
+      </div>
+
+      <div className="grid grid-cols-1 gap-6 lg:grid-cols-2">
+        <Card>
+          <CardHeader>
+            <CardTitle className="text-base">Top Sellers</CardTitle>
+          </CardHeader>
+          <CardContent>
+            {report.topSellers.length === 0 ? (
+              <p className="text-muted-foreground text-sm">No sales in the period.</p>
+            ) : (
+              <Table>
+                <TableHeader>
+                  <TableRow>
+                    <TableHead>Medicine</TableHead>
+                    <TableHead className="text-right">Qty</TableHead>
+                    <TableHead className="text-right">Value</TableHead>
+                  </TableRow>
+                </TableHeader>
+                <TableBody>
+                  {report.topSellers.map((t) => (
+                    <TableRow key={t.medicineId}>
+                      <TableCell className="text-sm">{t.name}</TableCell>
+                      <TableCell className="text-right tabular-nums text-sm">
+                        {t.quantitySold}
+                      </TableCell>
+                      <TableCell className="text-right tabular-nums text-sm">
+                        {formatPaiseToCurrency(t.salesValuePaise)}
+                      </TableCell>
+                    </TableRow>
+                  ))}
+                </TableBody>
+              </Table>
+            )}
+          </CardContent>
+        </Card>
+
+        <Card>
+          <CardHeader>
+            <CardTitle className="text-base">Payment Mix</CardTitle>
+          </CardHeader>
+          <CardContent className="space-y-2">
+            {PAYMENT_MODES.map((m) => {
+              const amount = report.paymentMix[m.key];
+              return (
+                <div key={m.key} className="flex justify-between text-sm">
+                  <span>{m.label}</span>
+                  <span className="tabular-nums">
+                    {formatPaiseToCurrency(amount)}
+                    <span className="ml-2 text-muted-foreground">
+                      {paid > 0 ? ((amount / paid) * 100).toFixed(1) : "0.0"}%
+                    </span>
+                  </span>
+                </div>
+              );
+            })}
+          </CardContent>
+        </Card>
+      </div>
+
+      <div className="grid grid-cols-1 gap-6 lg:grid-cols-2">
+        <BucketBars
+          title="By Hour"
+          buckets={report.byHour}
+          label={(h) => `${String(h).padStart(2, "0")}:00`}
+        />
+        <BucketBars title="By Day of Week" buckets={report.byWeekday} label={(d) => WEEKDAYS[d]} />
+      </div>
+    </div>
+  );
+}
+
+// ---------------------------------------------------------------------------
+// ABC analysis
+// ---------------------------------------------------------------------------
+
+function AbcSection({ report }: { report: AbcReport }) {
+  return (
+    <div className="space-y-4">
+      <h2 className="text-xl font-semibold text-slate-900">ABC Analysis</h2>
+
+      <div className="grid grid-cols-1 gap-4 sm:grid-cols-3">
+        {report.classes.map((c) => (
+          <Card key={c.class}>
+            <CardHeader className="pb-2">
+              <CardTitle className="flex items-center gap-2 text-sm font-medium text-muted-foreground">
+                <Badge className={CLASS_STYLES[c.class]}>{c.class}</Badge>
+                {CLASS_HINTS[c.class]}
+              </CardTitle>
+            </CardHeader>
+            <CardContent>
+              <p className="text-2xl font-bold tabular-nums">
+                {c.medicineCount} medicine{c.medicineCount !== 1 ? "s" : ""}
+              </p>
+              <p className="text-sm text-muted-foreground tabular-nums">
+                {formatPaiseToCurrency(c.salesValuePaise)} &middot; {c.sharePercent.toFixed(1)}%
+              </p>
+            </CardContent>
+          </Card>
+        ))}
+      </div>
+
+      <div className="rounded-lg border bg-card shadow-sm">
+        {report.medicines.length === 0 ? (
+          <div className="flex flex-col items-center justify-center py-16 gap-3">
+            <BarChart3 className="size-10 text-muted-foreground/40" />
+            <p className="text-muted-foreground text-sm">
+              No sales found for the selected period.
+            </p>
+          </div>
+        ) : (
+          <Table>
+            <TableHeader>
+              <TableRow>
+                <TableHead>Class</TableHead>
+                <TableHead>Medicine</TableHead>
+                <TableHead className="text-right">Qty Sold</TableHead>
+                <TableHead className="text-right">Sales Value</TableHead>
+                <TableHead className="text-right">Share</TableHead>
+                <TableHead className="text-right">Cumulative</TableHead>
+              </TableRow>
+            </TableHeader>
+            <TableBody>
+              {report.medicines.map((m) => (
+                <TableRow key={m.medicineId}>
+                  <TableCell>
+                    <Badge className={CLASS_STYLES[m.class]}>{m.class}</Badge>
+                  </TableCell>
+                  <TableCell className="text-sm">{m.name}</TableCell>
+                  <TableCell className="text-right tabular-nums text-sm">{m.quantitySold}</TableCell>
+                  <TableCell className="text-right tabular-nums text-sm">
+                    {formatPaiseToCurrency(m.salesValuePaise)}
+                  </TableCell>
+                  <TableCell className="text-right tabular-nums text-sm">
+                    {m.sharePercent.toFixed(2)}%
+                  </TableCell>
+                  <TableCell className="text-right tabular-nums text-sm">
+                    {m.cumulativePercent.toFixed(2)}%
+                  </TableCell>
+                </TableRow>
+              ))}
+            </TableBody>
+          </Table>
+        )}
+      </div>
+    </div>
+  );
+}
+
+// ---------------------------------------------------------------------------
+// Component
+// ---------------------------------------------------------------------------
+
+export default function AnalyticsPage() {
+  const [startDate, setStartDate] = useState(() => daysAgo(90));
+  const [endDate, setEndDate] = useState(today);
+  const [abc, setAbc] = useState<AbcReport | null>(null);
+  const [sales, setSales] = useState<SalesAnalytics | null>(null);
+  const [loading, setLoading] = useState(true);
+
+  const loadReport = useCallback(async () => {
+    try {
+      setLoading(true);
+      const [classes, analytics] = await Promise.all([
+        getAbcAnalysis(startDate, endDate),
+        getSalesAnalytics(startDate, endDate),
+      ]);
+      setAbc(classes);
+      setSales(analytics);
+    } catch (err) {
+      console.error(err);
+      toast.error(String(err));
+    } finally {
+      setLoading(false);
+    }
+  }, [startDate, endDate]);
+
+  useEffect(() => {
+    loadReport();
+  }, [loadReport]);
+
+  return (
+    <div className="space-y-6">
+      {/* Header */}
+      <div>
+        <h1 className="text-3xl font-bold text-slate-900">Analytics</h1>
+        <p className="text-slate-600 mt-1">
+          Top sellers, trading pattern and which medicines carry the business
+        </p>
+      </div>
+
+      {/* Filters */}
+      <div className="flex flex-wrap items-end gap-4">
+        <div className="space-y-1.5">
+          <label htmlFor="an-start-date" className="text-sm font-medium text-slate-700">
+            From
+          </label>
+          <Input
+            id="an-start-date"
+            type="date"
+            value={startDate}
+            onChange={(e) => setStartDate(e.target.value)}
+            className="w-40"
+          />
+        </div>
+        <div className="space-y-1.5">
+          <label htmlFor="an-end-date" className="text-sm font-medium text-slate-700">
+            To
+          </label>
+          <Input
+            id="an-end-date"
+            type="date"
+            value={endDate}
+            onChange={(e) => setEndDate(e.target.value)}
+            className="w-40"
+          />
+        </div>
+      </div>
+
+      {loading && !abc ? (
+        <p className="text-muted-foreground text-sm">Loading&hellip;</p>
+      ) : (
+        <>
+          {sales && <SalesSection report={sales} />}
+          {abc && <AbcSection report={abc} />}
+        </>
+      )}
+    </div>
+  );
+}
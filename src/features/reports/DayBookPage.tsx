@@ -0,0 +1,244 @@
+import { useState, useEffect, useCallback } from "react";
+import { toast } from "sonner";
+import { DownloadIcon, SearchIcon } from "lucide-react";
+import { save } from "@tauri-apps/plugin-dialog";
+import { useDataChanged } from "@/hooks/useDataChanged";
+import { formatPaiseToCurrency } from "@/lib/currency";
+import {
+  getDayBook,
+  exportDayBookCsv,
+  type DayBook,
+  type DayBookKind,
+  type ModeTotals,
+} from "@/db/queries/dayBook";
+import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import { Card, CardContent } from "@/components/ui/card";
+import { Badge } from "@/components/ui/badge";
+import {
+  Table,
+  TableBody,
+  TableCell,
+  TableFooter,
+  TableHead,
+  TableHeader,
+  TableRow,
+} from "@/components/ui/table";
+
+const KIND_LABELS: Record<DayBookKind, string> = {
+  sale: "Sale",
+  customer_receipt: "Customer receipt",
+  sale_return: "Sale return",
+  supplier_payment: "Supplier payment",
+  expense: "Expense",
+};
+
+const MODES: { key: keyof ModeTotals; label: string }[] = [
+  { key: "cashPaise", label: "Cash" },
+  { key: "cardPaise", label: "Card" },
+  { key: "upiPaise", label: "UPI" },
+  { key: "creditPaise", label: "Credit" },
+];
+
+function today(): string {
+  return new Date().toISOString().split("T")[0];
+}
+
+export default function DayBookPage() {
+  const [date, setDate] = useState(today());
+  const [book, setBook] = useState<DayBook | null>(null);
+  const [loading, setLoading] = useState(true);
+  const [exporting, setExporting] = useState(false);
+
+  const loadBook = useCallback(async () => {
+    try {
+      setLoading(true);
+      setBook(await getDayBook(date));
+    } catch (err) {
+      console.error(err);
+      toast.error(String(err));
+      setBook(null);
+    } finally {
+      setLoading(false);
+    }
+  }, [date]);
+
+  useEffect(() => {
+    loadBook();
+  }, [loadBook]);
+
+  // Every receipt and payment the day book lists
+  useDataChanged(["sale", "customer_payment", "supplier_payment", "expense"], loadBook);
+
+  const handleExport = async () => {
+    const destPath = await save({
+      defaultPath: `day-book-${date}.csv`,
+      filters: [{ name: "CSV", extensions: ["csv"] }],
+    });
+    if (!destPath) return;
+    try {
+      setExporting(true);
+      await exportDayBookCsv(date, destPath);
+      toast.success("Day book exported");
+    } catch (err) {
+      toast.error(String(err));
+    } finally {
+      setExporting(false);
+    }
+  };
+
+  return (
+    <div className="space-y-6">
+      {/* Header */}
+      <div className="flex items-start justify-between">
+        <div>
+          <h1 className="text-3xl font-bold text-slate-900">Day Book</h1>
+          <p className="text-slate-600 mt-1">
+            Receipts and payments of a day, with the cash in hand
+          </p>
+        </div>
+        <Button onClick={handleExport} disabled={exporting || !book} className="gap-2">
+          <DownloadIcon className="size-4" />
+          {exporting ? "Exporting…" : "Export CSV"}
+        </Button>
+      </div>
+
+      {/* Filters */}
+      <div className="flex flex-wrap items-end gap-4">
+        <div className="space-y-1.5">
+          <label htmlFor="book-date" className="text-sm font-medium text-slate-700">
+            Date
+          </label>
+          <Input
+            id="book-date"
+            type="date"
+            value={date}
+            onChange={(e) => setDate(e.target.value)}
+            className="w-44"
+          />
+        </div>
+        <Button variant="outline" onClick={loadBook} className="gap-2">
+          <SearchIcon className="size-4" />
+          Apply
+        </Button>
+      </div>
+
+      {loading ? (
+        <p className="text-muted-foreground text-sm">Loading&hellip;</p>
+      ) : !book ? (
+        <p className="text-muted-foreground text-sm">The day book could not be prepared.</p>
+      ) : (
+        <>
+          {/* Cash in hand and totals by mode */}
+          <div className="grid grid-cols-2 lg:grid-cols-3 gap-4">
+            <Card>
+              <CardContent className="pt-6">
+                <p className="text-xs text-muted-foreground">Opening Cash</p>
+                <p className="text-2xl font-bold tabular-nums text-slate-900">
+                  {formatPaiseToCurrency(book.openingCashPaise)}
+                </p>
+              </CardContent>
+            </Card>
+            <Card>
+              <CardContent className="pt-6">
+                <p className="text-xs text-muted-foreground">Closing Cash</p>
+                <p className="text-2xl font-bold tabular-nums text-slate-900">
+                  {formatPaiseToCurrency(book.closingCashPaise)}
+                </p>
+              </CardContent>
+            </Card>
+            <Card className="col-span-2 lg:col-span-1">
+              <CardContent className="pt-6">
+                <Table>
+                  <TableHeader>
+                    <TableRow>
+                      <TableHead>Mode</TableHead>
+                      <TableHead className="text-right">Receipts</TableHead>
+                      <TableHead className="text-right">Payments</TableHead>
+                    </TableRow>
+                  </TableHeader>
+                  <TableBody>
+                    {MODES.map(({ key, label }) => (
+                      <TableRow key={key}>
+                        <TableCell className="text-sm">{label}</TableCell>
+                        <TableCell className="text-right tabular-nums text-sm">
+                          {formatPaiseToCurrency(book.receipts[key])}
+                        </TableCell>
+                        <TableCell className="text-right tabular-nums text-sm">
+                          {formatPaiseToCurrency(book.payments[key])}
+                        </TableCell>
+                      </TableRow>
+                    ))}
+                  </TableBody>
+                </Table>
+              </CardContent>
+            </Card>
+          </div>
+
+          {/* Entries */}
+          <div className="rounded-lg border bg-card shadow-sm">
+            {book.entries.length === 0 ? (
+              <p className="text-muted-foreground text-sm text-center py-12">
+                Nothing was received or paid on this day.
+              </p>
+            ) : (
+              <Table>
+                <TableHeader>
+                  <TableRow>
+                    <TableHead>Type</TableHead>
+                    <TableHead>Reference</TableHead>
+                    <TableHead>Party</TableHead>
+                    <TableHead>Mode</TableHead>
+                    <TableHead className="text-right">Receipt</TableHead>
+                    <TableHead className="text-right">Payment</TableHead>
+                  </TableRow>
+                </TableHeader>
+                <TableBody>
+                  {book.entries.map((entry, i) => (
+                    <TableRow key={i}>
+                      <TableCell className="text-sm">{KIND_LABELS[entry.kind]}</TableCell>
+                      <TableCell className="font-mono text-sm">
+                        {entry.reference ?? "—"}
+                      </TableCell>
+                      <TableCell className="text-sm text-slate-600">
+                        {entry.party ?? "Walk-in"}
+                      </TableCell>
+                      <TableCell>
+                        <Badge variant="outline" className="uppercase text-xs">
+                          {entry.mode}
+                        </Badge>
+                      </TableCell>
+                      <TableCell className="text-right tabular-nums text-sm">
+                        {entry.receiptPaise > 0 ? formatPaiseToCurrency(entry.receiptPaise) : ""}
+                      </TableCell>
+                      <TableCell className="text-right tabular-nums text-sm">
+                        {entry.paymentPaise > 0 ? formatPaiseToCurrency(entry.paymentPaise) : ""}
+                      </TableCell>
+                    </TableRow>
+                  ))}
+                </TableBody>
+                <TableFooter>
+                  <TableRow>
+                    <TableCell colSpan={4} className="text-sm font-medium">
+                      Total
+                    </TableCell>
+                    <TableCell className="text-right tabular-nums text-sm font-medium">
+                      {formatPaiseToCurrency(
+                        MODES.reduce((sum, { key }) => sum + book.receipts[key], 0)
+                      )}
+                    </TableCell>
+                    <TableCell className="text-right tabular-nums text-sm font-medium">
+                      {formatPaiseToCurrency(
+                        MODES.reduce((sum, { key }) => sum + book.payments[key], 0)
+                      )}
+                    </TableCell>
+                  </TableRow>
+                </TableFooter>
+              </Table>
+            )}
+          </div>
+        </>
+      )}
+    </div>
+  );
+}
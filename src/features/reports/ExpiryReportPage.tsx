@@ -4,7 +4,9 @@ import { Clock, DownloadIcon, AlertTriangle } from "lucide-react";
 import { save } from "@tauri-apps/plugin-dialog";
 import { writeTextFile } from "@tauri-apps/plugin-fs";
 import { getDb } from "@/db/index";
+import { UNEXPIRED } from "@/db/utils";
 import { formatPaiseToCurrency, paiseToRupeesString } from "@/lib/currency";
+import { getDeadStockReport, type DeadStockReport } from "@/db/queries/reports";
 import { Button } from "@/components/ui/button";
 import {
   Select,
@@ -22,7 +24,8 @@ import {
   TableHeader,
   TableRow,
 } from "@/components/ui/table";
-import { Card, CardContent } from "@/components/ui/card";
+import { Card, CardContent, CardHeader, CardTitle } from "@/components/ui/card";
+import { Input } from "@/components/ui/input";
 import { Label } from "@/components/ui/label";
 
 // ---------------------------------------------------------------------------
@@ -82,7 +85,7 @@ async function fetchExpiryReport(
   if (includeExpired) {
     whereClause += ` AND julianday(b.expiry_date) - julianday('now') <= $1`;
   } else {
-    whereClause += ` AND b.expiry_date > date('now') AND julianday(b.expiry_date) - julianday('now') <= $1`;
+    whereClause += ` AND ${UNEXPIRED} AND julianday(b.expiry_date) - julianday('now') <= $1`;
   }
 
   return db.select<ExpiryReportRow[]>(
@@ -162,6 +165,103 @@ const EXPIRY_WINDOWS = [
 // Component
 // ---------------------------------------------------------------------------
 
+// ---------------------------------------------------------------------------
+// Dead stock
+// ---------------------------------------------------------------------------
+
+function DeadStockCard() {
+  const [idleDays, setIdleDays] = useState("90");
+  const [report, setReport] = useState<DeadStockReport | null>(null);
+
+  useEffect(() => {
+    const days = Number(idleDays);
+    if (!Number.isInteger(days) || days < 1) return;
+    getDeadStockReport(days)
+      .then(setReport)
+      .catch((err) => {
+        console.error(err);
+        toast.error(String(err));
+      });
+  }, [idleDays]);
+
+  return (
+    <Card>
+      <CardHeader className="flex flex-row items-end justify-between gap-4">
+        <div>
+          <CardTitle className="text-base">Dead &amp; Slow-Moving Stock</CardTitle>
+          <p className="text-sm text-muted-foreground mt-1">
+            Batches with no sales in the period, to return or discount before they expire
+          </p>
+        </div>
+        <div className="space-y-1.5">
+          <Label htmlFor="idle-days" className="text-sm font-medium text-slate-700">
+            No sales for (days)
+          </Label>
+          <Input
+            id="idle-days"
+            type="number"
+            min="1"
+            value={idleDays}
+            onChange={(e) => setIdleDays(e.target.value)}
+            className="w-32"
+          />
+        </div>
+      </CardHeader>
+      <CardContent>
+        {!report || report.batches.length === 0 ? (
+          <p className="text-muted-foreground text-sm">No idle batches.</p>
+        ) : (
+          <Table>
+            <TableHeader>
+              <TableRow>
+                <TableHead>Medicine</TableHead>
+                <TableHead>Batch</TableHead>
+                <TableHead>Last Sold</TableHead>
+                <TableHead className="text-right">Days to Expiry</TableHead>
+                <TableHead className="text-right">Qty</TableHead>
+                <TableHead className="text-right">Value Locked</TableHead>
+              </TableRow>
+            </TableHeader>
+            <TableBody>
+              {report.batches.map((b) => (
+                <TableRow key={b.batchId}>
+                  <TableCell className="text-sm">{b.medicineName}</TableCell>
+                  <TableCell className="font-mono text-sm">{b.batchNumber}</TableCell>
+                  <TableCell className="text-sm text-slate-600">
+                    {b.lastSoldOn ? formatExpiryDate(b.lastSoldOn) : "Never"}
+                  </TableCell>
+                  <TableCell
+                    className={`text-right tabular-nums text-sm ${
+                      b.daysToExpiry <= 0 ? "text-red-600 font-medium" : ""
+                    }`}
+                  >
+                    {formatDaysRemaining(b.daysToExpiry)}
+                  </TableCell>
+                  <TableCell className="text-right tabular-nums text-sm">{b.quantity}</TableCell>
+                  <TableCell className="text-right tabular-nums text-sm">
+                    {formatPaiseToCurrency(b.valuePaise)}
+                  </TableCell>
+                </TableRow>
+              ))}
+            </TableBody>
+            <TableFooter>
+              <TableRow className="font-bold">
+                <TableCell colSpan={5}>
+                  Total ({report.batches.length} batch
+                  {report.batches.length !== 1 ? "es" : ""})
+                </TableCell>
+                <TableCell className="text-right tabular-nums">
+                  {formatPaiseToCurrency(report.valuePaise)}
+                </TableCell>
+              </TableRow>
+            </TableFooter>
+          </Table>
+        )}
+      </CardContent>
+    </Card>
+  );
+}
+
 export default function ExpiryReportPage() {
   const [expiryDays, setExpiryDays] = useState("90");
   const [includeExpired, setIncludeExpired] = useState(false);
@@ -435,6 +535,8 @@ export default function ExpiryReportPage() {
           </Table>
         )}
       </div>
+
+      <DeadStockCard />
     </div>
   );
 }
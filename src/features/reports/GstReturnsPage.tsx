@@ -0,0 +1,375 @@
+import { useState, useEffect, useCallback, useMemo } from "react";
+import { toast } from "sonner";
+import { DownloadIcon, FileCode, FileJson, SearchIcon } from "lucide-react";
+import { save, open } from "@tauri-apps/plugin-dialog";
+import { formatPaiseToCurrency } from "@/lib/currency";
+import {
+  getGstr1,
+  exportGstr1Csv,
+  exportGstr1Json,
+  exportTallyXml,
+  getGstr3bSummary,
+  type Gstr1,
+  type Gstr1ItemDetail,
+  type Gstr3bSummary,
+  type TaxValues,
+} from "@/db/queries/gstReturns";
+import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import { Card, CardContent } from "@/components/ui/card";
+import {
+  Table,
+  TableBody,
+  TableCell,
+  TableHead,
+  TableHeader,
+  TableRow,
+} from "@/components/ui/table";
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+function lastMonth(): string {
+  const d = new Date();
+  d.setDate(1);
+  d.setMonth(d.getMonth() - 1);
+  return `${d.getFullYear()}-${String(d.getMonth() + 1).padStart(2, "0")}`;
+}
+
+/** Report amounts are in rupees; show them like every other amount. */
+function formatRupees(rupees: number): string {
+  return formatPaiseToCurrency(Math.round(rupees * 100));
+}
+
+function taxOf(d: Gstr1ItemDetail): number {
+  return d.iamt + d.camt + d.samt + d.csamt;
+}
+
+interface SectionTotals {
+  count: number;
+  taxable: number;
+  tax: number;
+}
+
+function summarise(report: Gstr1) {
+  const add = (t: SectionTotals, d: Gstr1ItemDetail) => {
+    t.taxable += d.txval;
+    t.tax += taxOf(d);
+  };
+  const b2b: SectionTotals = { count: 0, taxable: 0, tax: 0 };
+  for (const party of report.b2b) {
+    for (const inv of party.inv) {
+      b2b.count += 1;
+      inv.itms.forEach((i) => add(b2b, i.itm_det));
+    }
+  }
+  const b2cl: SectionTotals = { count: 0, taxable: 0, tax: 0 };
+  for (const state of report.b2cl) {
+    for (const inv of state.inv) {
+      b2cl.count += 1;
+      inv.itms.forEach((i) => add(b2cl, i.itm_det));
+    }
+  }
+  const b2cs: SectionTotals = { count: report.b2cs.length, taxable: 0, tax: 0 };
+  report.b2cs.forEach((row) => add(b2cs, row));
+  return { b2b, b2cl, b2cs };
+}
+
+function Gstr3bTable({ summary }: { summary: Gstr3bSummary }) {
+  const rows: [string, TaxValues][] = [
+    ["3.1(a) Outward taxable supplies", summary.outwardTaxable],
+    ["4(A)(5) Input tax credit", summary.itcAvailable],
+    ["4(B)(2) ITC reversed", summary.itcReversed],
+    ["4(C) Net ITC", summary.netItc],
+  ];
+  return (
+    <div className="space-y-2">
+      <h2 className="text-lg font-semibold text-slate-900">GSTR-3B</h2>
+      <div className="rounded-lg border bg-card shadow-sm">
+        <Table>
+          <TableHeader>
+            <TableRow>
+              <TableHead>Table</TableHead>
+              <TableHead className="text-right">Taxable</TableHead>
+              <TableHead className="text-right">IGST</TableHead>
+              <TableHead className="text-right">CGST</TableHead>
+              <TableHead className="text-right">SGST</TableHead>
+            </TableRow>
+          </TableHeader>
+          <TableBody>
+            {rows.map(([label, v]) => (
+              <TableRow key={label}>
+                <TableCell className="text-sm">{label}</TableCell>
+                <TableCell className="text-right tabular-nums text-sm">
+                  {formatPaiseToCurrency(v.taxablePaise)}
+                </TableCell>
+                <TableCell className="text-right tabular-nums text-sm">
+                  {formatPaiseToCurrency(v.igstPaise)}
+                </TableCell>
+                <TableCell className="text-right tabular-nums text-sm">
+                  {formatPaiseToCurrency(v.cgstPaise)}
+                </TableCell>
+                <TableCell className="text-right tabular-nums text-sm">
+                  {formatPaiseToCurrency(v.sgstPaise)}
+                </TableCell>
+              </TableRow>
+            ))}
+            <TableRow>
+              <TableCell className="text-sm">3.1(c) Nil-rated and exempt</TableCell>
+              <TableCell className="text-right tabular-nums text-sm">
+                {formatPaiseToCurrency(summary.outwardNilExemptPaise)}
+              </TableCell>
+              <TableCell colSpan={3} />
+            </TableRow>
+          </TableBody>
+        </Table>
+      </div>
+      {summary.purchasesWithoutLines > 0 && (
+        <p className="text-sm text-amber-700">
+          {summary.purchasesWithoutLines} purchase(s) this month have no recorded
+          lines; their input tax credit is not included.
+        </p>
+      )}
+    </div>
+  );
+}
+
+// ---------------------------------------------------------------------------
+// Component
+// ---------------------------------------------------------------------------
+
+export default function GstReturnsPage() {
+  const [period, setPeriod] = useState(lastMonth);
+  const [report, setReport] = useState<Gstr1 | null>(null);
+  const [summary3b, setSummary3b] = useState<Gstr3bSummary | null>(null);
+  const [loading, setLoading] = useState(true);
+  const [exporting, setExporting] = useState(false);
+
+  const loadReport = useCallback(async () => {
+    try {
+      setLoading(true);
+      const [gstr1, gstr3b] = await Promise.allSettled([
+        getGstr1(period),
+        getGstr3bSummary(period),
+      ]);
+      setReport(gstr1.status === "fulfilled" ? gstr1.value : null);
+      setSummary3b(gstr3b.status === "fulfilled" ? gstr3b.value : null);
+      for (const result of [gstr1, gstr3b]) {
+        if (result.status === "rejected") toast.error(String(result.reason));
+      }
+    } finally {
+      setLoading(false);
+    }
+  }, [period]);
+
+  useEffect(() => {
+    loadReport();
+  }, [loadReport]);
+
+  const totals = useMemo(() => (report ? summarise(report) : null), [report]);
+
+  const handleExportJson = async () => {
+    const destPath = await save({
+      defaultPath: `GSTR1-${period}.json`,
+      filters: [{ name: "JSON", extensions: ["json"] }],
+    });
+    if (!destPath) return;
+    try {
+      setExporting(true);
+      await exportGstr1Json(period, destPath);
+      toast.success("GSTR-1 JSON exported");
+    } catch (err) {
+      toast.error(String(err));
+    } finally {
+      setExporting(false);
+    }
+  };
+
+  const handleExportCsv = async () => {
+    const destDir = await open({ directory: true });
+    if (!destDir || Array.isArray(destDir)) return;
+    try {
+      setExporting(true);
+      const sheets = await exportGstr1Csv(period, destDir);
+      toast.success(`Wrote ${sheets.length} GSTR-1 sheets`);
+    } catch (err) {
+      toast.error(String(err));
+    } finally {
+      setExporting(false);
+    }
+  };
+
+  const handleExportTally = async () => {
+    const destPath = await save({
+      defaultPath: `tally-vouchers-${period}.xml`,
+      filters: [{ name: "XML", extensions: ["xml"] }],
+    });
+    if (!destPath) return;
+    const [year, month] = period.split("-").map(Number);
+    const lastDay = new Date(year, month, 0).getDate();
+    try {
+      setExporting(true);
+      const summary = await exportTallyXml(
+        `${period}-01`,
+        `${period}-${String(lastDay).padStart(2, "0")}`,
+        destPath
+      );
+      toast.success(`Wrote ${summary.rowsWritten} Tally vouchers`);
+    } catch (err) {
+      toast.error(String(err));
+    } finally {
+      setExporting(false);
+    }
+  };
+
+  return (
+    <div className="space-y-6">
+      {/* Header */}
+      <div className="flex items-start justify-between">
+        <div>
+          <h1 className="text-3xl font-bold text-slate-900">GST Returns</h1>
+          <p className="text-slate-600 mt-1">
+            Prepare GSTR-1 and GSTR-3B figures for a month
+          </p>
+        </div>
+        <div className="flex gap-2">
+          <Button
+            variant="outline"
+            onClick={handleExportTally}
+            disabled={exporting}
+            className="gap-2"
+          >
+            <FileCode className="size-4" />
+            Tally XML
+          </Button>
+          <Button
+            variant="outline"
+            onClick={handleExportCsv}
+            disabled={exporting || !report}
+            className="gap-2"
+          >
+            <DownloadIcon className="size-4" />
+            Offline Tool CSV
+          </Button>
+          <Button
+            onClick={handleExportJson}
+            disabled={exporting || !report}
+            className="gap-2"
+          >
+            <FileJson className="size-4" />
+            {exporting ? "Exporting…" : "Export JSON"}
+          </Button>
+        </div>
+      </div>
+
+      {/* Filters */}
+      <div className="flex flex-wrap items-end gap-4">
+        <div className="space-y-1.5">
+          <label htmlFor="period" className="text-sm font-medium text-slate-700">
+            Return Period
+          </label>
+          <Input
+            id="period"
+            type="month"
+            value={period}
+            onChange={(e) => setPeriod(e.target.value)}
+            className="w-44"
+          />
+        </div>
+        <Button variant="outline" onClick={loadReport} className="gap-2">
+          <SearchIcon className="size-4" />
+          Apply
+        </Button>
+      </div>
+
+      {!loading && summary3b && <Gstr3bTable summary={summary3b} />}
+
+      {loading ? (
+        <p className="text-muted-foreground text-sm">Loading&hellip;</p>
+      ) : !report || !totals ? (
+        <p className="text-muted-foreground text-sm">
+          GSTR-1 could not be prepared for this period.
+        </p>
+      ) : (
+        <>
+          <div className="grid gap-4 md:grid-cols-3">
+            {(
+              [
+                ["B2B invoices", totals.b2b],
+                ["B2C large invoices", totals.b2cl],
+                ["B2C small (summary rows)", totals.b2cs],
+              ] as const
+            ).map(([label, t]) => (
+              <Card key={label} className="py-4">
+                <CardContent className="space-y-1">
+                  <p className="text-sm text-muted-foreground">
+                    {label}: {t.count}
+                  </p>
+                  <p className="text-xl font-bold tabular-nums">
+                    {formatRupees(t.taxable)}
+                  </p>
+                  <p className="text-xs text-muted-foreground tabular-nums">
+                    Tax {formatRupees(t.tax)}
+                  </p>
+                </CardContent>
+              </Card>
+            ))}
+          </div>
+
+          <div className="rounded-lg border bg-card shadow-sm">
+            <Table>
+              <TableHeader>
+                <TableRow>
+                  <TableHead>HSN</TableHead>
+                  <TableHead className="text-right">Rate</TableHead>
+                  <TableHead className="text-right">Qty</TableHead>
+                  <TableHead className="text-right">Taxable</TableHead>
+                  <TableHead className="text-right">IGST</TableHead>
+                  <TableHead className="text-right">CGST</TableHead>
+                  <TableHead className="text-right">SGST</TableHead>
+                </TableRow>
+              </TableHeader>
+              <TableBody>
+                {report.hsn.data.map((row) => (
+                  <TableRow key={row.num}>
+                    <TableCell className="font-mono text-sm">{row.hsn_sc}</TableCell>
+                    <TableCell className="text-right tabular-nums text-sm">
+                      {row.rt}%
+                    </TableCell>
+                    <TableCell className="text-right tabular-nums text-sm">
+                      {row.qty}
+                    </TableCell>
+                    <TableCell className="text-right tabular-nums text-sm">
+                      {formatRupees(row.txval)}
+                    </TableCell>
+                    <TableCell className="text-right tabular-nums text-sm">
+                      {formatRupees(row.iamt)}
+                    </TableCell>
+                    <TableCell className="text-right tabular-nums text-sm">
+                      {formatRupees(row.camt)}
+                    </TableCell>
+                    <TableCell className="text-right tabular-nums text-sm">
+                      {formatRupees(row.samt)}
+                    </TableCell>
+                  </TableRow>
+                ))}
+              </TableBody>
+            </Table>
+          </div>
+
+          <div className="space-y-1 text-sm text-slate-600">
+            {report.doc_issue.doc_det.flatMap((det) =>
+              det.docs.map((range) => (
+                <p key={`${det.doc_num}-${range.num}`}>
+                  {det.doc_num === 1 ? "Invoices" : "Credit notes"} {range.from}{" "}
+                  to {range.to}: {range.net_issue} issued
+                </p>
+              ))
+            )}
+          </div>
+        </>
+      )}
+    </div>
+  );
+}
@@ -6,14 +6,18 @@ import {
   TrendingUp,
   TrendingDown,
   ReceiptIcon,
+  WalletIcon,
 } from "lucide-react";
 import { save } from "@tauri-apps/plugin-dialog";
 import { writeTextFile } from "@tauri-apps/plugin-fs";
 import { getDb } from "@/db/index";
 import { formatPaiseToCurrency, paiseToRupeesString } from "@/lib/currency";
+import { getProfitSummary, type ProfitSummary } from "@/db/queries/expenses";
+import { getMarginReport, type Margin, type MarginReport } from "@/db/queries/reports";
 import { Button } from "@/components/ui/button";
 import { Input } from "@/components/ui/input";
 import { Card, CardContent } from "@/components/ui/card";
+import { Tabs, TabsContent, TabsList, TabsTrigger } from "@/components/ui/tabs";
 import {
   Table,
   TableBody,
@@ -174,6 +178,73 @@ function SummaryCard({ label, value, icon, accent = "default" }: SummaryCardProp
   );
 }
 
+// ---------------------------------------------------------------------------
+// Margin table component
+// ---------------------------------------------------------------------------
+
+interface MarginTableProps<T extends Margin> {
+  rows: T[];
+  /** Leading columns identifying each row */
+  columns: { header: string; cell: (row: T) => React.ReactNode }[];
+  rowKey: (row: T) => string | number;
+}
+
+function formatMargin(percent: number | null): string {
+  return percent === null ? "\u2014" : `${percent.toFixed(1)}%`;
+}
+
+function MarginTable<T extends Margin>({ rows, columns, rowKey }: MarginTableProps<T>) {
+  if (rows.length === 0) {
+    return (
+      <p className="text-muted-foreground text-sm text-center py-12">
+        No sales found for the selected period.
+      </p>
+    );
+  }
+  return (
+    <Table>
+      <TableHeader>
+        <TableRow>
+          {columns.map((c) => (
+            <TableHead key={c.header}>{c.header}</TableHead>
+          ))}
+          <TableHead className="text-right">Revenue (₹)</TableHead>
+          <TableHead className="text-right">Cost (₹)</TableHead>
+          <TableHead className="text-right">Profit (₹)</TableHead>
+          <TableHead className="text-right">Margin</TableHead>
+        </TableRow>
+      </TableHeader>
+      <TableBody>
+        {rows.map((row) => (
+          <TableRow key={rowKey(row)}>
+            {columns.map((c) => (
+              <TableCell key={c.header} className="text-sm">
+                {c.cell(row)}
+              </TableCell>
+            ))}
+            <TableCell className="text-right tabular-nums text-sm">
+              {formatPaiseToCurrency(row.revenuePaise)}
+            </TableCell>
+            <TableCell className="text-right tabular-nums text-sm">
+              {formatPaiseToCurrency(row.costPaise)}
+            </TableCell>
+            <TableCell
+              className={`text-right tabular-nums text-sm font-medium ${
+                row.grossProfitPaise >= 0 ? "text-emerald-600" : "text-red-600"
+              }`}
+            >
+              {formatPaiseToCurrency(row.grossProfitPaise)}
+            </TableCell>
+            <TableCell className="text-right tabular-nums text-sm">
+              {formatMargin(row.marginPercent)}
+            </TableCell>
+          </TableRow>
+        ))}
+      </TableBody>
+    </Table>
+  );
+}
+
 // ---------------------------------------------------------------------------
 // Component
 // ---------------------------------------------------------------------------
@@ -182,14 +253,22 @@ export default function ProfitLossPage() {
   const [startDate, setStartDate] = useState(firstOfMonth);
   const [endDate, setEndDate] = useState(today);
   const [rows, setRows] = useState<ProfitLossRow[]>([]);
+  const [profit, setProfit] = useState<ProfitSummary | null>(null);
+  const [margins, setMargins] = useState<MarginReport | null>(null);
   const [loading, setLoading] = useState(true);
   const [exporting, setExporting] = useState(false);
 
   const loadReport = useCallback(async () => {
     try {
       setLoading(true);
-      const data = await fetchProfitLossReport(startDate, endDate);
+      const [data, totals, margin] = await Promise.all([
+        fetchProfitLossReport(startDate, endDate),
+        getProfitSummary(startDate, endDate),
+        getMarginReport(startDate, endDate),
+      ]);
       setRows(data);
+      setProfit(totals);
+      setMargins(margin);
     } catch (err) {
       console.error(err);
       toast.error("Failed to load profit & loss report");
@@ -332,97 +411,197 @@ export default function ProfitLossPage() {
         </div>
       )}
 
-      {/* Table */}
-      <div className="rounded-lg border bg-card shadow-sm">
-        {loading ? (
-          <div className="flex items-center justify-center py-16">
-            <p className="text-muted-foreground text-sm">Loading&hellip;</p>
-          </div>
-        ) : rows.length === 0 ? (
-          <div className="flex flex-col items-center justify-center py-16 gap-3">
-            <ReceiptIcon className="size-10 text-muted-foreground/40" />
-            <p className="text-muted-foreground text-sm">
-              No sales found for the selected period.
-            </p>
+      {/* Expenses and net profit */}
+      {!loading && profit && (profit.expensesPaise > 0 || rows.length > 0) && (
+        <div className="grid grid-cols-1 gap-4 sm:grid-cols-2 lg:grid-cols-4">
+          <SummaryCard
+            label="Expenses"
+            value={formatPaiseToCurrency(profit.expensesPaise)}
+            icon={<WalletIcon className="size-5 text-slate-600" />}
+          />
+          <SummaryCard
+            label="Net Profit"
+            value={formatPaiseToCurrency(profit.netProfitPaise)}
+            icon={
+              profit.netProfitPaise >= 0 ? (
+                <TrendingUp className="size-5 text-emerald-600" />
+              ) : (
+                <TrendingDown className="size-5 text-red-600" />
+              )
+            }
+            accent={profit.netProfitPaise >= 0 ? "green" : "red"}
+          />
+          {profit.expenses.length > 0 && (
+            <Card className="py-4 sm:col-span-2">
+              <CardContent className="space-y-1">
+                {profit.expenses.map((e) => (
+                  <div key={e.categoryId} className="flex justify-between text-sm">
+                    <span className="text-muted-foreground">{e.category}</span>
+                    <span className="tabular-nums">{formatPaiseToCurrency(e.totalPaise)}</span>
+                  </div>
+                ))}
+              </CardContent>
+            </Card>
+          )}
+        </div>
+      )}
+
+      <Tabs defaultValue="items">
+        <TabsList>
+          <TabsTrigger value="items">Items</TabsTrigger>
+          <TabsTrigger value="invoices">By Invoice</TabsTrigger>
+          <TabsTrigger value="medicines">By Medicine</TabsTrigger>
+          <TabsTrigger value="batches">By Batch</TabsTrigger>
+          <TabsTrigger value="days">By Day</TabsTrigger>
+        </TabsList>
+
+        <TabsContent value="items">
+          {/* Table */}
+          <div className="rounded-lg border bg-card shadow-sm">
+            {loading ? (
+              <div className="flex items-center justify-center py-16">
+                <p className="text-muted-foreground text-sm">Loading&hellip;</p>
+              </div>
+            ) : rows.length === 0 ? (
+              <div className="flex flex-col items-center justify-center py-16 gap-3">
+                <ReceiptIcon className="size-10 text-muted-foreground/40" />
+                <p className="text-muted-foreground text-sm">
+                  No sales found for the selected period.
+                </p>
+              </div>
+            ) : (
+              <Table>
+                <TableHeader>
+                  <TableRow>
+                    <TableHead>Date</TableHead>
+                    <TableHead>Invoice #</TableHead>
+                    <TableHead>Medicine Name</TableHead>
+                    <TableHead className="text-center">Qty Sold</TableHead>
+                    <TableHead className="text-right">
+                      Cost Price (₹/unit)
+                    </TableHead>
+                    <TableHead className="text-right">
+                      Taxable Amount (₹)
+                    </TableHead>
+                    <TableHead className="text-right">Profit (₹)</TableHead>
+                  </TableRow>
+                </TableHeader>
+
+                <TableBody>
+                  {rows.map((row, idx) => (
+                    <TableRow key={`${row.invoice_number}-${row.medicine_name}-${idx}`}>
+                      <TableCell className="text-slate-600 text-sm whitespace-nowrap">
+                        {formatDate(row.sale_date)}
+                      </TableCell>
+                      <TableCell className="font-mono font-medium text-sm">
+                        {row.invoice_number}
+                      </TableCell>
+                      <TableCell className="text-sm">
+                        {row.medicine_name}
+                      </TableCell>
+                      <TableCell className="text-center tabular-nums text-sm">
+                        {row.quantity}
+                      </TableCell>
+                      <TableCell className="text-right tabular-nums text-sm">
+                        {formatPaiseToCurrency(row.cost_price_paise)}
+                      </TableCell>
+                      <TableCell className="text-right tabular-nums text-sm">
+                        {formatPaiseToCurrency(row.taxable_amount_paise)}
+                      </TableCell>
+                      <TableCell
+                        className={`text-right tabular-nums text-sm font-medium ${
+                          row.profit_paise >= 0
+                            ? "text-emerald-600"
+                            : "text-red-600"
+                        }`}
+                      >
+                        {formatPaiseToCurrency(row.profit_paise)}
+                      </TableCell>
+                    </TableRow>
+                  ))}
+                </TableBody>
+
+                <TableFooter>
+                  <TableRow className="font-bold">
+                    <TableCell colSpan={3}>
+                      Total ({rows.length} item{rows.length !== 1 ? "s" : ""})
+                    </TableCell>
+                    <TableCell />
+                    <TableCell className="text-right tabular-nums">
+                      {formatPaiseToCurrency(summary.totalCost)}
+                    </TableCell>
+                    <TableCell className="text-right tabular-nums">
+                      {formatPaiseToCurrency(summary.totalRevenue)}
+                    </TableCell>
+                    <TableCell
+                      className={`text-right tabular-nums ${
+                        summary.grossProfit >= 0
+                          ? "text-emerald-600"
+                          : "text-red-600"
+                      }`}
+                    >
+                      {formatPaiseToCurrency(summary.grossProfit)}
+                    </TableCell>
+                  </TableRow>
+                </TableFooter>
+              </Table>
+            )}
           </div>
-        ) : (
-          <Table>
-            <TableHeader>
-              <TableRow>
-                <TableHead>Date</TableHead>
-                <TableHead>Invoice #</TableHead>
-                <TableHead>Medicine Name</TableHead>
-                <TableHead className="text-center">Qty Sold</TableHead>
-                <TableHead className="text-right">
-                  Cost Price (₹/unit)
-                </TableHead>
-                <TableHead className="text-right">
-                  Taxable Amount (₹)
-                </TableHead>
-                <TableHead className="text-right">Profit (₹)</TableHead>
-              </TableRow>
-            </TableHeader>
-
-            <TableBody>
-              {rows.map((row, idx) => (
-                <TableRow key={`${row.invoice_number}-${row.medicine_name}-${idx}`}>
-                  <TableCell className="text-slate-600 text-sm whitespace-nowrap">
-                    {formatDate(row.sale_date)}
-                  </TableCell>
-                  <TableCell className="font-mono font-medium text-sm">
-                    {row.invoice_number}
-                  </TableCell>
-                  <TableCell className="text-sm">
-                    {row.medicine_name}
-                  </TableCell>
-                  <TableCell className="text-center tabular-nums text-sm">
-                    {row.quantity}
-                  </TableCell>
-                  <TableCell className="text-right tabular-nums text-sm">
-                    {formatPaiseToCurrency(row.cost_price_paise)}
-                  </TableCell>
-                  <TableCell className="text-right tabular-nums text-sm">
-                    {formatPaiseToCurrency(row.taxable_amount_paise)}
-                  </TableCell>
-                  <TableCell
-                    className={`text-right tabular-nums text-sm font-medium ${
-                      row.profit_paise >= 0
-                        ? "text-emerald-600"
-                        : "text-red-600"
-                    }`}
-                  >
-                    {formatPaiseToCurrency(row.profit_paise)}
-                  </TableCell>
-                </TableRow>
-              ))}
-            </TableBody>
-
-            <TableFooter>
-              <TableRow className="font-bold">
-                <TableCell colSpan={3}>
-                  Total ({rows.length} item{rows.length !== 1 ? "s" : ""})
-                </TableCell>
-                <TableCell />
-                <TableCell className="text-right tabular-nums">
-                  {formatPaiseToCurrency(summary.totalCost)}
-                </TableCell>
-                <TableCell className="text-right tabular-nums">
-                  {formatPaiseToCurrency(summary.totalRevenue)}
-                </TableCell>
-                <TableCell
-                  className={`text-right tabular-nums ${
-                    summary.grossProfit >= 0
-                      ? "text-emerald-600"
-                      : "text-red-600"
-                  }`}
-                >
-                  {formatPaiseToCurrency(summary.grossProfit)}
-                </TableCell>
-              </TableRow>
-            </TableFooter>
-          </Table>
+        </TabsContent>
+
+        {margins && (
+          <>
+            <TabsContent value="invoices" className="rounded-lg border bg-card shadow-sm">
+              <MarginTable
+                rows={margins.invoices}
+                rowKey={(r) => r.saleId}
+                columns={[
+                  { header: "Date", cell: (r) => formatDate(r.saleDate) },
+                  {
+                    header: "Invoice #",
+                    cell: (r) => <span className="font-mono font-medium">{r.invoiceNumber}</span>,
+                  },
+                  { header: "Customer", cell: (r) => r.customer ?? "Walk-in" },
+                ]}
+              />
+            </TabsContent>
+            <TabsContent value="medicines" className="rounded-lg border bg-card shadow-sm">
+              <MarginTable
+                rows={margins.medicines}
+                rowKey={(r) => r.medicineId}
+                columns={[
+                  { header: "Medicine", cell: (r) => r.name },
+                  { header: "Qty Sold", cell: (r) => r.quantitySold },
+                ]}
+              />
+            </TabsContent>
+            <TabsContent value="batches" className="rounded-lg border bg-card shadow-sm">
+              <MarginTable
+                rows={margins.batches}
+                rowKey={(r) => r.batchId}
+                columns={[
+                  { header: "Medicine", cell: (r) => r.name },
+                  {
+                    header: "Batch",
+                    cell: (r) => <span className="font-mono">{r.batchNumber}</span>,
+                  },
+                  { header: "Qty Sold", cell: (r) => r.quantitySold },
+                ]}
+              />
+            </TabsContent>
+            <TabsContent value="days" className="rounded-lg border bg-card shadow-sm">
+              <MarginTable
+                rows={margins.days}
+                rowKey={(r) => r.date}
+                columns={[
+                  { header: "Date", cell: (r) => formatDate(r.date) },
+                  { header: "Invoices", cell: (r) => r.invoiceCount },
+                ]}
+              />
+            </TabsContent>
+          </>
         )}
-      </div>
+      </Tabs>
     </div>
   );
 }
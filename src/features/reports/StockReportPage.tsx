@@ -4,7 +4,14 @@ import { DownloadIcon, PackageIcon } from "lucide-react";
 import { save } from "@tauri-apps/plugin-dialog";
 import { writeTextFile } from "@tauri-apps/plugin-fs";
 import { getDb } from "@/db/index";
+import { UNEXPIRED } from "@/db/utils";
 import { formatPaiseToCurrency, paiseToRupeesString } from "@/lib/currency";
+import {
+  getStockValuation,
+  type CostMethod,
+  type GroupValuation,
+  type StockValuation,
+} from "@/db/queries/reports";
 import { Button } from "@/components/ui/button";
 import { Badge } from "@/components/ui/badge";
 import { Card, CardContent, CardHeader, CardTitle } from "@/components/ui/card";
@@ -19,6 +26,7 @@ import {
   Table,
   TableBody,
   TableCell,
+  TableFooter,
   TableHead,
   TableHeader,
   TableRow,
@@ -48,9 +56,9 @@ async function fetchStockReport(): Promise<StockReportRow[]> {
   const db = await getDb();
   return db.select<StockReportRow[]>(`
     SELECT m.id as medicine_id, m.name as medicine_name, m.category, m.reorder_level,
-      COALESCE(SUM(CASE WHEN b.expiry_date > date('now') AND b.quantity > 0 THEN b.quantity ELSE 0 END), 0) as total_stock,
-      COALESCE(SUM(CASE WHEN b.expiry_date > date('now') AND b.quantity > 0 THEN b.quantity * b.cost_price_paise ELSE 0 END), 0) as total_cost_value,
-      COALESCE(SUM(CASE WHEN b.expiry_date > date('now') AND b.quantity > 0 THEN b.quantity * b.mrp_paise ELSE 0 END), 0) as total_mrp_value
+      COALESCE(SUM(CASE WHEN ${UNEXPIRED} AND b.quantity > 0 AND b.status = 'available' THEN b.quantity ELSE 0 END), 0) as total_stock,
+      COALESCE(SUM(CASE WHEN ${UNEXPIRED} AND b.quantity > 0 THEN b.quantity * b.cost_price_paise ELSE 0 END), 0) as total_cost_value,
+      COALESCE(SUM(CASE WHEN ${UNEXPIRED} AND b.quantity > 0 THEN b.quantity * b.mrp_paise ELSE 0 END), 0) as total_mrp_value
     FROM medicines m
     LEFT JOIN batches b ON m.id = b.medicine_id
     WHERE m.is_active = 1
@@ -138,6 +146,75 @@ async function exportToCsv(rows: StockReportRow[]) {
   toast.success("Report exported successfully");
 }
 
+// ---------------------------------------------------------------------------
+// Valuation table component
+// ---------------------------------------------------------------------------
+
+interface ValuationTableProps {
+  title: string;
+  groups: GroupValuation[];
+  total: StockValuation["total"];
+}
+
+function ValuationTable({ title, groups, total }: ValuationTableProps) {
+  return (
+    <Card>
+      <CardHeader>
+        <CardTitle className="text-base">{title}</CardTitle>
+      </CardHeader>
+      <CardContent>
+        {groups.length === 0 ? (
+          <p className="text-muted-foreground text-sm">No stock on hand.</p>
+        ) : (
+          <Table>
+            <TableHeader>
+              <TableRow>
+                <TableHead>Name</TableHead>
+                <TableHead className="text-right">Qty</TableHead>
+                <TableHead className="text-right">At Cost</TableHead>
+                <TableHead className="text-right">At MRP</TableHead>
+                <TableHead className="text-right">At Selling</TableHead>
+              </TableRow>
+            </TableHeader>
+            <TableBody>
+              {groups.map((g) => (
+                <TableRow key={g.name}>
+                  <TableCell className="text-sm">{g.name}</TableCell>
+                  <TableCell className="text-right tabular-nums text-sm">{g.quantity}</TableCell>
+                  <TableCell className="text-right tabular-nums text-sm">
+                    {formatPaiseToCurrency(g.costValuePaise)}
+                  </TableCell>
+                  <TableCell className="text-right tabular-nums text-sm">
+                    {formatPaiseToCurrency(g.mrpValuePaise)}
+                  </TableCell>
+                  <TableCell className="text-right tabular-nums text-sm">
+                    {formatPaiseToCurrency(g.sellingValuePaise)}
+                  </TableCell>
+                </TableRow>
+              ))}
+            </TableBody>
+            <TableFooter>
+              <TableRow className="font-bold">
+                <TableCell>Total</TableCell>
+                <TableCell className="text-right tabular-nums">{total.quantity}</TableCell>
+                <TableCell className="text-right tabular-nums">
+                  {formatPaiseToCurrency(total.costValuePaise)}
+                </TableCell>
+                <TableCell className="text-right tabular-nums">
+                  {formatPaiseToCurrency(total.mrpValuePaise)}
+                </TableCell>
+                <TableCell className="text-right tabular-nums">
+                  {formatPaiseToCurrency(total.sellingValuePaise)}
+                </TableCell>
+              </TableRow>
+            </TableFooter>
+          </Table>
+        )}
+      </CardContent>
+    </Card>
+  );
+}
+
 // ---------------------------------------------------------------------------
 // Component
 // ---------------------------------------------------------------------------
@@ -148,6 +225,8 @@ export default function StockReportPage() {
   const [exporting, setExporting] = useState(false);
   const [categoryFilter, setCategoryFilter] = useState("all");
   const [statusFilter, setStatusFilter] = useState("all");
+  const [costMethod, setCostMethod] = useState<CostMethod>("batch");
+  const [valuation, setValuation] = useState<StockValuation | null>(null);
 
   const loadReport = useCallback(async () => {
     try {
@@ -166,6 +245,15 @@ export default function StockReportPage() {
     loadReport();
   }, [loadReport]);
 
+  useEffect(() => {
+    getStockValuation(costMethod)
+      .then(setValuation)
+      .catch((err) => {
+        console.error(err);
+        toast.error("Failed to load stock valuation");
+      });
+  }, [costMethod]);
+
   // Distinct categories from data
   const categories = useMemo(() => {
     const set = new Set<string>();
@@ -398,6 +486,58 @@ export default function StockReportPage() {
           </Table>
         )}
       </div>
+
+      {/* Valuation */}
+      <div className="space-y-4">
+        <div className="flex flex-wrap items-end justify-between gap-4">
+          <div>
+            <h2 className="text-xl font-semibold text-slate-900">Stock Valuation</h2>
+            <p className="text-sm text-slate-600">
+              Unexpired stock at cost, MRP and selling price, per pack
+            </p>
+          </div>
+          <div className="space-y-1.5">
+            <label htmlFor="valuation-method" className="text-sm font-medium text-slate-700">
+              Cost
+            </label>
+            <Select
+              value={costMethod}
+              onValueChange={(value) => setCostMethod(value as CostMethod)}
+            >
+              <SelectTrigger id="valuation-method" className="w-52">
+                <SelectValue />
+              </SelectTrigger>
+              <SelectContent>
+                <SelectItem value="batch">Batch cost</SelectItem>
+                <SelectItem value="weighted_average">Weighted average cost</SelectItem>
+              </SelectContent>
+            </Select>
+          </div>
+        </div>
+
+        {valuation && (
+          <>
+            <div className="grid grid-cols-1 gap-6 xl:grid-cols-2">
+              <ValuationTable
+                title="By Category"
+                groups={valuation.categories}
+                total={valuation.total}
+              />
+              <ValuationTable
+                title="By Manufacturer"
+                groups={valuation.manufacturers}
+                total={valuation.total}
+              />
+            </div>
+            {valuation.expired.quantity > 0 && (
+              <p className="text-sm text-muted-foreground">
+                Expired stock not included: {valuation.expired.quantity} units,{" "}
+                {formatPaiseToCurrency(valuation.expired.costValuePaise)} at cost.
+              </p>
+            )}
+          </>
+        )}
+      </div>
     </div>
   );
 }
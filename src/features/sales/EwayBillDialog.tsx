@@ -0,0 +1,243 @@
+import { useState, useEffect } from "react";
+import { toast } from "sonner";
+import { invoke } from "@tauri-apps/api/core";
+import { sessionToken } from "@/db/session";
+import { useAuth } from "@/features/auth/AuthContext";
+import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import { Label } from "@/components/ui/label";
+import {
+  Dialog,
+  DialogContent,
+  DialogDescription,
+  DialogFooter,
+  DialogHeader,
+  DialogTitle,
+} from "@/components/ui/dialog";
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from "@/components/ui/select";
+
+type TransportMode = "road" | "rail" | "air" | "ship";
+type CancelReason = "duplicate" | "order_cancelled" | "data_entry_mistake" | "others";
+
+const MODE_LABELS: Record<TransportMode, string> = {
+  road: "Road",
+  rail: "Rail",
+  air: "Air",
+  ship: "Ship",
+};
+
+const CANCEL_LABELS: Record<CancelReason, string> = {
+  duplicate: "Duplicate",
+  order_cancelled: "Order cancelled",
+  data_entry_mistake: "Data entry mistake",
+  others: "Others",
+};
+
+interface EwayBillDialogProps {
+  open: boolean;
+  onOpenChange: (open: boolean) => void;
+  saleId: number;
+  /** The sale's active e-way bill; the dialog then cancels it */
+  ewbNo: string | null;
+  onSaved: () => void;
+}
+
+export default function EwayBillDialog({
+  open,
+  onOpenChange,
+  saleId,
+  ewbNo,
+  onSaved,
+}: EwayBillDialogProps) {
+  const { user } = useAuth();
+  const [mode, setMode] = useState<TransportMode>("road");
+  const [distanceKm, setDistanceKm] = useState("0");
+  const [vehicleNo, setVehicleNo] = useState("");
+  const [transporterId, setTransporterId] = useState("");
+  const [transporterName, setTransporterName] = useState("");
+  const [transportDocNo, setTransportDocNo] = useState("");
+  const [reason, setReason] = useState<CancelReason>("data_entry_mistake");
+  const [remark, setRemark] = useState("");
+  const [saving, setSaving] = useState(false);
+
+  useEffect(() => {
+    if (open) {
+      setRemark("");
+    }
+  }, [open]);
+
+  async function handleSubmit(e: React.FormEvent) {
+    e.preventDefault();
+    if (!user) return;
+    try {
+      setSaving(true);
+      if (ewbNo) {
+        await invoke("cancel_eway_bill", { saleId, reason, remark, token: sessionToken() });
+        toast.success(`E-way bill ${ewbNo} cancelled`);
+      } else {
+        const bill = await invoke<{ ewbNo: string; validUpto: string }>("generate_eway_bill", {
+          saleId,
+          dispatch: {
+            mode,
+            distanceKm: parseInt(distanceKm, 10) || 0,
+            vehicleNo: vehicleNo.trim() || null,
+            transporterId: transporterId.trim() || null,
+            transporterName: transporterName.trim() || null,
+            transportDocNo: transportDocNo.trim() || null,
+          },
+          token: sessionToken(),
+        });
+        toast.success(`E-way bill ${bill.ewbNo} valid until ${bill.validUpto}`);
+      }
+      onOpenChange(false);
+      onSaved();
+    } catch (err) {
+      console.error(err);
+      toast.error(String(err));
+    } finally {
+      setSaving(false);
+    }
+  }
+
+  return (
+    <Dialog open={open} onOpenChange={onOpenChange}>
+      <DialogContent className="sm:max-w-md">
+        <DialogHeader>
+          <DialogTitle>{ewbNo ? "Cancel E-way Bill" : "Generate E-way Bill"}</DialogTitle>
+          <DialogDescription>
+            {ewbNo
+              ? `E-way bill ${ewbNo} can be cancelled within 24 hours of generation.`
+              : "Enter how the goods are dispatched."}
+          </DialogDescription>
+        </DialogHeader>
+
+        <form onSubmit={handleSubmit} className="space-y-4 py-2">
+          {ewbNo ? (
+            <>
+              <div className="space-y-1.5">
+                <Label htmlFor="ewb-reason">Reason</Label>
+                <Select value={reason} onValueChange={(val) => setReason(val as CancelReason)}>
+                  <SelectTrigger id="ewb-reason" className="w-full">
+                    <SelectValue />
+                  </SelectTrigger>
+                  <SelectContent>
+                    {(Object.keys(CANCEL_LABELS) as CancelReason[]).map((r) => (
+                      <SelectItem key={r} value={r}>
+                        {CANCEL_LABELS[r]}
+                      </SelectItem>
+                    ))}
+                  </SelectContent>
+                </Select>
+              </div>
+              <div className="space-y-1.5">
+                <Label htmlFor="ewb-remark">Remark</Label>
+                <Input
+                  id="ewb-remark"
+                  value={remark}
+                  onChange={(e) => setRemark(e.target.value)}
+                  maxLength={50}
+                  autoComplete="off"
+                />
+              </div>
+            </>
+          ) : (
+            <>
+              <div className="grid grid-cols-2 gap-3">
+                <div className="space-y-1.5">
+                  <Label htmlFor="ewb-mode">Mode</Label>
+                  <Select value={mode} onValueChange={(val) => setMode(val as TransportMode)}>
+                    <SelectTrigger id="ewb-mode" className="w-full">
+                      <SelectValue />
+                    </SelectTrigger>
+                    <SelectContent>
+                      {(Object.keys(MODE_LABELS) as TransportMode[]).map((m) => (
+                        <SelectItem key={m} value={m}>
+                          {MODE_LABELS[m]}
+                        </SelectItem>
+                      ))}
+                    </SelectContent>
+                  </Select>
+                </div>
+                <div className="space-y-1.5">
+                  <Label htmlFor="ewb-distance">Distance (km)</Label>
+                  <Input
+                    id="ewb-distance"
+                    type="number"
+                    min={0}
+                    max={4000}
+                    value={distanceKm}
+                    onChange={(e) => setDistanceKm(e.target.value)}
+                  />
+                </div>
+              </div>
+              {mode === "road" ? (
+                <div className="space-y-1.5">
+                  <Label htmlFor="ewb-vehicle">Vehicle Number</Label>
+                  <Input
+                    id="ewb-vehicle"
+                    value={vehicleNo}
+                    onChange={(e) => setVehicleNo(e.target.value.toUpperCase())}
+                    placeholder="e.g. KA01AB1234"
+                    className="font-mono"
+                    autoComplete="off"
+                  />
+                </div>
+              ) : (
+                <div className="space-y-1.5">
+                  <Label htmlFor="ewb-doc">Transport Document No.</Label>
+                  <Input
+                    id="ewb-doc"
+                    value={transportDocNo}
+                    onChange={(e) => setTransportDocNo(e.target.value)}
+                    autoComplete="off"
+                  />
+                </div>
+              )}
+              <div className="grid grid-cols-2 gap-3">
+                <div className="space-y-1.5">
+                  <Label htmlFor="ewb-transporter-id">Transporter ID</Label>
+                  <Input
+                    id="ewb-transporter-id"
+                    value={transporterId}
+                    onChange={(e) => setTransporterId(e.target.value.toUpperCase())}
+                    className="font-mono"
+                    autoComplete="off"
+                  />
+                </div>
+                <div className="space-y-1.5">
+                  <Label htmlFor="ewb-transporter-name">Transporter Name</Label>
+                  <Input
+                    id="ewb-transporter-name"
+                    value={transporterName}
+                    onChange={(e) => setTransporterName(e.target.value)}
+                    autoComplete="off"
+                  />
+                </div>
+              </div>
+            </>
+          )}
+
+          <DialogFooter className="pt-2">
+            <Button
+              type="button"
+              variant="outline"
+              onClick={() => onOpenChange(false)}
+              disabled={saving}
+            >
+              Close
+            </Button>
+            <Button type="submit" disabled={saving} variant={ewbNo ? "destructive" : "default"}>
+              {saving ? "Sending…" : ewbNo ? "Cancel E-way Bill" : "Generate"}
+            </Button>
+          </DialogFooter>
+        </form>
+      </DialogContent>
+    </Dialog>
+  );
+}
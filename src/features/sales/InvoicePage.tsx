@@ -1,19 +1,35 @@
 import { useState, useEffect, useCallback } from "react";
 import { useParams, useNavigate } from "react-router-dom";
 import { toast } from "sonner";
-import { ArrowLeftIcon, PrinterIcon } from "lucide-react";
+import {
+  ArrowLeftIcon,
+  BadgeCheckIcon,
+  FileDownIcon,
+  FileJsonIcon,
+  PrinterIcon,
+  TruckIcon,
+} from "lucide-react";
+import { invoke } from "@tauri-apps/api/core";
+import { save } from "@tauri-apps/plugin-dialog";
+import { writeTextFile } from "@tauri-apps/plugin-fs";
 import type { SaleWithDetails } from "@/types";
 import { getSaleById } from "@/db/queries/sales";
+import { sessionToken } from "@/db/session";
 import { useSettings } from "@/hooks/useSettings";
+import { useAuth } from "@/features/auth/AuthContext";
 import { Button } from "@/components/ui/button";
 import ReceiptPrintView from "./ReceiptPrintView";
+import EwayBillDialog from "./EwayBillDialog";
 
 export default function InvoicePage() {
   const { id } = useParams<{ id: string }>();
   const navigate = useNavigate();
   const { settings } = useSettings();
+  const { user } = useAuth();
   const [sale, setSale] = useState<SaleWithDetails | null>(null);
   const [loading, setLoading] = useState(true);
+  const [registering, setRegistering] = useState(false);
+  const [ewayBillOpen, setEwayBillOpen] = useState(false);
 
   const loadSale = useCallback(async () => {
     const saleId = Number(id);
@@ -44,6 +60,76 @@ export default function InvoicePage() {
     loadSale();
   }, [loadSale]);
 
+  const handleExportEInvoice = async () => {
+    if (!sale) return;
+    try {
+      const payload = await invoke<unknown>("generate_einvoice_json", { saleId: sale.id });
+      const path = await save({
+        defaultPath: `${sale.invoiceNumber.replace(/\//g, "-")}-einvoice.json`,
+        filters: [{ name: "JSON", extensions: ["json"] }],
+      });
+      if (!path) return;
+      await writeTextFile(path, JSON.stringify(payload, null, 2));
+      toast.success("E-invoice JSON exported");
+    } catch (err) {
+      console.error(err);
+      toast.error(String(err));
+    }
+  };
+
+  const handleGenerateIrn = async () => {
+    if (!sale || !user) return;
+    try {
+      setRegistering(true);
+      const result = await invoke<{ irn: string; ackNo: string }>("generate_irn", {
+        saleId: sale.id,
+        token: sessionToken(),
+      });
+      toast.success(`IRN generated (Ack no ${result.ackNo})`);
+      await loadSale();
+    } catch (err) {
+      console.error(err);
+      toast.error(String(err));
+    } finally {
+      setRegistering(false);
+    }
+  };
+
+  const handleDownloadPdf = async () => {
+    if (!sale) return;
+    try {
+      const path = await save({
+        defaultPath: `${sale.invoiceNumber.replace(/\//g, "-")}.pdf`,
+        filters: [{ name: "PDF", extensions: ["pdf"] }],
+      });
+      if (!path) return;
+      await invoke<number>("generate_invoice_pdf", {
+        saleId: sale.id,
+        destPath: path,
+        token: sessionToken(),
+      });
+      toast.success("Tax invoice PDF saved");
+    } catch (err) {
+      console.error(err);
+      toast.error(String(err));
+    }
+  };
+
+  const handlePrint = async () => {
+    if (!sale || !settings) return;
+    if (settings.printerInterface === "none") {
+      window.print();
+      return;
+    }
+    try {
+      await invoke("print_receipt", { saleId: sale.id });
+      toast.success("Receipt sent to printer");
+    } catch (err) {
+      console.error(err);
+      toast.error(String(err));
+    }
+  };
+
   if (loading) {
     return (
       <div className="flex items-center justify-center py-24">
@@ -67,16 +153,54 @@ export default function InvoicePage() {
           <ArrowLeftIcon className="size-4" />
           Sales History
         </Button>
-        <Button
-          onClick={() => window.print()}
-          className="gap-2 print-visible"
-        >
-          <PrinterIcon className="size-4" />
-          Print Invoice
-        </Button>
+        <div className="flex items-center gap-2">
+          {settings.einvoiceEnabled && sale.customerGstin && (
+            <Button variant="outline" onClick={handleExportEInvoice} className="gap-2">
+              <FileJsonIcon className="size-4" />
+              Export e-Invoice
+            </Button>
+          )}
+          {settings.einvoiceEnabled && sale.customerGstin && !sale.irn && settings.einvoiceApiUrl && (
+            <Button
+              variant="outline"
+              onClick={handleGenerateIrn}
+              disabled={registering}
+              className="gap-2"
+            >
+              <BadgeCheckIcon className="size-4" />
+              {registering ? "Generating…" : "Generate IRN"}
+            </Button>
+          )}
+          {sale.customerId !== null &&
+            (sale.ewbNo || sale.grandTotalPaise > settings.ewaybillThresholdPaise) && (
+              <Button variant="outline" onClick={() => setEwayBillOpen(true)} className="gap-2">
+                <TruckIcon className="size-4" />
+                {sale.ewbNo ? "Cancel E-way Bill" : "E-way Bill"}
+              </Button>
+            )}
+          <Button variant="outline" onClick={handleDownloadPdf} className="gap-2">
+            <FileDownIcon className="size-4" />
+            Download PDF
+          </Button>
+          <Button
+            onClick={handlePrint}
+            className="gap-2 print-visible"
+          >
+            <PrinterIcon className="size-4" />
+            Print Invoice
+          </Button>
+        </div>
       </div>
 
       <ReceiptPrintView sale={sale} settings={settings} />
+
+      <EwayBillDialog
+        open={ewayBillOpen}
+        onOpenChange={setEwayBillOpen}
+        saleId={sale.id}
+        ewbNo={sale.ewbNo}
+        onSaved={loadSale}
+      />
     </div>
   );
 }
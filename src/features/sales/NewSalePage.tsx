@@ -1,5 +1,5 @@
 import { useState, useCallback, useRef, useEffect } from "react";
-import { useNavigate } from "react-router-dom";
+import { Link, useNavigate } from "react-router-dom";
 import { toast } from "sonner";
 import {
   SearchIcon,
@@ -11,14 +11,21 @@ import {
 } from "lucide-react";
 import type { Customer, PaymentMode, MedicineWithGst } from "@/types";
 import { searchMedicines } from "@/db/queries/medicines";
-import { getBatchesFEFO } from "@/db/queries/batches";
+import {
+  allocateBatches,
+  getBatchById,
+  getBatchesFEFO,
+} from "@/db/queries/batches";
 import { searchCustomers } from "@/db/queries/customers";
 import {
   createSale,
   type CreateSaleData,
   type CreateSaleItemData,
+  type SalePayment,
 } from "@/db/queries/sales";
 import { createPrescription } from "@/db/queries/prescriptions";
+import { getSettings } from "@/db/queries/settings";
+import { getCurrentShift } from "@/db/queries/shifts";
 import {
   calculateLineItem,
   calculateInvoiceTotal,
@@ -77,6 +84,18 @@ interface PrescriptionData {
   notes: string;
 }
 
+type TenderMode = "cash" | "upi" | "card" | "credit";
+
+/** Modes taken at the till; credit only once a customer is chosen. */
+const TENDER_MODES: TenderMode[] = ["cash", "upi", "card"];
+
+const TENDER_LABELS: Record<TenderMode, string> = {
+  cash: "Cash",
+  upi: "UPI",
+  card: "Card",
+  credit: "Credit",
+};
+
 function formatExpiry(dateStr: string): string {
   try {
     return new Date(dateStr).toLocaleDateString("en-IN", {
@@ -124,11 +143,45 @@ export default function NewSalePage() {
 
   // Payment
   const [paymentMode, setPaymentMode] = useState<PaymentMode>("cash");
+  const [splitTender, setSplitTender] = useState(false);
+  const [splitAmounts, setSplitAmounts] = useState<Record<TenderMode, string>>({
+    cash: "",
+    upi: "",
+    card: "",
+    credit: "",
+  });
+  // The backend checks the customer's credit limit
+  const tenderModes: TenderMode[] = selectedCustomer
+    ? [...TENDER_MODES, "credit"]
+    : TENDER_MODES;
 
   // Checkout
   const [confirmOpen, setConfirmOpen] = useState(false);
   const [processing, setProcessing] = useState(false);
 
+  // Must match the backend, which prices the sale from the same settings
+  const [pricesIncludeGst, setPricesIncludeGst] = useState(true);
+  const [compositionScheme, setCompositionScheme] = useState(false);
+
+  useEffect(() => {
+    getSettings()
+      .then((settings) => {
+        setPricesIncludeGst(settings.pricesIncludeGst);
+        setCompositionScheme(settings.compositionScheme);
+      })
+      .catch((err) => console.error(err));
+  }, []);
+
+  // Sales are tagged with the cashier's open shift, if any
+  const [hasShift, setHasShift] = useState(true);
+
+  useEffect(() => {
+    if (!user) return;
+    getCurrentShift()
+      .then((shift) => setHasShift(shift !== null))
+      .catch((err) => console.error(err));
+  }, [user]);
+
   // Close dropdowns on outside click
   useEffect(() => {
     function handleClickOutside(e: MouseEvent) {
@@ -210,43 +263,46 @@ export default function NewSalePage() {
         return;
       }
 
-      // Check if already in cart
-      const existing = cart.find((item) => item.medicineId === medicine.id);
-      if (existing) {
-        // Increment quantity if possible
-        if (existing.quantity < existing.maxQuantity) {
-          updateQuantity(
-            cart.indexOf(existing),
-            existing.quantity + 1
-          );
-        } else {
-          toast.error(`Maximum stock reached for ${medicine.name}`);
-        }
+      // Top up a line with room left before opening another batch
+      const inCart = cart.filter((item) => item.medicineId === medicine.id);
+      const open = inCart.find((item) => item.quantity < item.maxQuantity);
+      if (open) {
+        updateQuantity(cart.indexOf(open), open.quantity + 1);
         setSearchTerm("");
         setShowResults(false);
         return;
       }
 
       try {
-        const batches = await getBatchesFEFO(medicine.id);
-        if (batches.length === 0) {
-          toast.error(`No available batches for ${medicine.name}`);
+        // The backend's FEFO plan for one more unit names the next batch to
+        // dispense from once the batches already in the cart run out.
+        const wanted = inCart.reduce((sum, item) => sum + item.quantity, 0) + 1;
+        const plan = await allocateBatches(medicine.id, wanted);
+        const next = plan.find(
+          (allocation) => !inCart.some((item) => item.batchId === allocation.batchId)
+        );
+        const batch = next ? await getBatchById(next.batchId) : null;
+        if (!batch) {
+          toast.error(`Maximum stock reached for ${medicine.name}`);
+          setSearchTerm("");
+          setShowResults(false);
           return;
         }
 
-        const batch = batches[0];
-
         if (!validateNotAboveMrp(batch.sellingPricePaise, batch.mrpPaise)) {
           toast.error(
             `${medicine.name}: Selling price exceeds MRP. Update batch before selling.`
           );
           return;
         }
+        // A bill of supply charges no tax on any line
+        const gstRate = compositionScheme ? 0 : medicine.gstRate;
         const calc = calculateLineItem(
           batch.sellingPricePaise,
           1,
-          medicine.gstRate,
-          0
+          gstRate,
+          0,
+          pricesIncludeGst
         );
         const newItem: CartItem = {
           medicineId: medicine.id,
@@ -259,7 +315,7 @@ export default function NewSalePage() {
           costPricePaise: batch.costPricePaise,
           quantity: 1,
           maxQuantity: batch.quantity,
-          gstRate: medicine.gstRate,
+          gstRate,
           discountPaise: 0,
           hsnCode: medicine.hsnCode,
           calculation: calc,
@@ -271,10 +327,10 @@ export default function NewSalePage() {
 
       } catch (err) {
         console.error(err);
-        toast.error("Failed to add item");
+        toast.error(typeof err === "string" ? err : "Failed to add item");
       }
     },
-    [cart]
+    [cart, pricesIncludeGst, compositionScheme]
   );
 
   // Update item quantity
@@ -289,13 +345,14 @@ export default function NewSalePage() {
           item.unitPricePaise,
           qty,
           item.gstRate,
-          item.discountPaise
+          item.discountPaise,
+          pricesIncludeGst
         );
         updated[index] = item;
         return updated;
       });
     },
-    []
+    [pricesIncludeGst]
   );
 
   // Update item discount
@@ -311,13 +368,14 @@ export default function NewSalePage() {
           item.unitPricePaise,
           item.quantity,
           item.gstRate,
-          item.discountPaise
+          item.discountPaise,
+          pricesIncludeGst
         );
         updated[index] = item;
         return updated;
       });
     },
-    []
+    [pricesIncludeGst]
   );
 
   // Remove item from cart
@@ -330,7 +388,23 @@ export default function NewSalePage() {
     cart.map((item) => item.calculation)
   );
 
-  // Checkout
+  // The backend rejects payments that do not add up to the grand total.
+  const payments: SalePayment[] = splitTender
+    ? tenderModes.map((mode) => ({
+        mode,
+        amountPaise: rupeesToPaise(splitAmounts[mode]),
+      })).filter((p) => p.amountPaise > 0)
+    : invoiceTotals.grandTotalPaise > 0
+      ? [{ mode: paymentMode, amountPaise: invoiceTotals.grandTotalPaise }]
+      : [];
+  const unpaidPaise =
+    invoiceTotals.grandTotalPaise -
+    payments.reduce((sum, p) => sum + p.amountPaise, 0);
+
+  // Checkout. One key per checkout, kept across retries, so a retried
+  // request returns the sale already recorded instead of billing twice.
+  const checkoutKeyRef = useRef<string | null>(null);
+
   async function handleCheckout() {
     if (!user) {
       toast.error("Not authenticated");
@@ -354,43 +428,20 @@ export default function NewSalePage() {
 
       const items: CreateSaleItemData[] = cart.map((item) => ({
         batchId: item.batchId,
-        medicineId: item.medicineId,
         quantity: item.quantity,
-        unitPricePaise: item.unitPricePaise,
         discountPaise: item.discountPaise,
-        taxableAmountPaise: item.calculation.taxableAmountPaise,
-        cgstRate: item.calculation.cgstRate,
-        cgstAmountPaise: item.calculation.cgstAmountPaise,
-        sgstRate: item.calculation.sgstRate,
-        sgstAmountPaise: item.calculation.sgstAmountPaise,
-        totalPaise: item.calculation.totalPaise,
-        hsnCode: item.hsnCode,
       }));
 
-      const saleData: CreateSaleData = {
-        customerId: selectedCustomer?.id ?? null,
-        userId: user.id,
-        subtotalPaise: invoiceTotals.subtotalPaise,
-        discountPaise: invoiceTotals.discountPaise,
-        totalCgstPaise: invoiceTotals.totalCgstPaise,
-        totalSgstPaise: invoiceTotals.totalSgstPaise,
-        totalGstPaise: invoiceTotals.totalGstPaise,
-        grandTotalPaise: invoiceTotals.grandTotalPaise,
-        paymentMode,
-        items,
-      };
-
-      const saleId = await createSale(saleData);
-
-      // Create prescription if filled
+      // Scheduled medicines are only sold against a prescription; the
+      // backend links it to the sale.
+      let prescriptionId: number | null = null;
       if (
         showPrescription &&
         selectedCustomer &&
         prescription.doctorName.trim()
       ) {
-        await createPrescription({
+        prescriptionId = await createPrescription({
           customerId: selectedCustomer.id,
-          saleId,
           doctorName: prescription.doctorName.trim(),
           rxNumber: prescription.rxNumber.trim() || null,
           prescriptionDate: prescription.prescriptionDate,
@@ -398,10 +449,24 @@ export default function NewSalePage() {
         });
       }
 
-      toast.success(`Sale completed`);
+      const saleData: CreateSaleData = {
+        customerId: selectedCustomer?.id ?? null,
+        payments,
+        prescriptionId,
+        idempotencyKey: (checkoutKeyRef.current ??= crypto.randomUUID()),
+        items,
+      };
+
+      const { saleId, invoiceNumber } = await createSale(saleData);
+      checkoutKeyRef.current = null;
+
+      toast.success(`Sale ${invoiceNumber} completed`);
       setConfirmOpen(false);
       setCart([]);
       setSelectedCustomer(null);
+      setSplitTender(false);
+      setPaymentMode("cash");
+      setSplitAmounts({ cash: "", upi: "", card: "", credit: "" });
       setShowPrescription(false);
       setPrescription({
         doctorName: "",
@@ -412,7 +477,7 @@ export default function NewSalePage() {
       navigate(`/sales/invoice/${saleId}`);
     } catch (err) {
       console.error(err);
-      toast.error("Failed to complete sale");
+      toast.error(typeof err === "string" ? err : "Failed to complete sale");
     } finally {
       setProcessing(false);
     }
@@ -422,6 +487,15 @@ export default function NewSalePage() {
     <div className="space-y-4">
       <h1 className="text-3xl font-bold text-slate-900">New Sale</h1>
 
+      {!hasShift && (
+        <p className="rounded-md border border-amber-200 bg-amber-50 px-3 py-2 text-sm text-amber-800">
+          No shift is open, so these sales will not be counted in a drawer.{" "}
+          <Link to="/sales/shift" className="font-medium underline">
+            Open a shift
+          </Link>
+        </p>
+      )}
+
       <div className="flex gap-6 items-start">
         {/* Left panel — search + cart */}
         <div className="flex-1 min-w-0 space-y-4">
@@ -621,6 +695,9 @@ export default function NewSalePage() {
                   onClick={() => {
                     setSelectedCustomer(null);
                     setShowPrescription(false);
+                    // A walk-in cannot buy on credit
+                    if (paymentMode === "credit") setPaymentMode("cash");
+                    setSplitAmounts((prev) => ({ ...prev, credit: "" }));
                   }}
                 >
                   <XIcon className="size-3" />
@@ -733,21 +810,73 @@ export default function NewSalePage() {
               Payment Mode
             </Label>
             <div className="flex gap-2">
-              {(["cash", "upi", "card"] as const).map((mode) => (
+              {tenderModes.map((mode) => (
                 <button
                   key={mode}
                   type="button"
                   className={`flex-1 py-2 px-3 rounded-md text-sm font-medium border transition-colors ${
-                    paymentMode === mode
+                    !splitTender && paymentMode === mode
                       ? "bg-blue-600 text-white border-blue-600"
                       : "bg-white text-slate-700 border-slate-200 hover:bg-slate-50"
                   }`}
-                  onClick={() => setPaymentMode(mode)}
+                  onClick={() => {
+                    setPaymentMode(mode);
+                    setSplitTender(false);
+                  }}
                 >
-                  {mode === "cash" ? "Cash" : mode === "upi" ? "UPI" : "Card"}
+                  {TENDER_LABELS[mode]}
                 </button>
               ))}
+              <button
+                type="button"
+                className={`flex-1 py-2 px-3 rounded-md text-sm font-medium border transition-colors ${
+                  splitTender
+                    ? "bg-blue-600 text-white border-blue-600"
+                    : "bg-white text-slate-700 border-slate-200 hover:bg-slate-50"
+                }`}
+                onClick={() => setSplitTender(true)}
+              >
+                Split
+              </button>
             </div>
+            {splitTender && (
+              <div className="space-y-2">
+                {tenderModes.map((mode) => (
+                  <div key={mode} className="flex items-center gap-2">
+                    <Label
+                      htmlFor={`tender-${mode}`}
+                      className="w-12 text-sm text-slate-600"
+                    >
+                      {TENDER_LABELS[mode]}
+                    </Label>
+                    <Input
+                      id={`tender-${mode}`}
+                      type="number"
+                      min="0"
+                      step="0.01"
+                      value={splitAmounts[mode]}
+                      onChange={(e) =>
+                        setSplitAmounts((prev) => ({
+                          ...prev,
+                          [mode]: e.target.value,
+                        }))
+                      }
+                      className="text-sm h-8"
+                    />
+                  </div>
+                ))}
+                <div
+                  className={`flex justify-between text-sm ${
+                    unpaidPaise === 0 ? "text-green-600" : "text-destructive"
+                  }`}
+                >
+                  <span>{unpaidPaise < 0 ? "Overpaid" : "Remaining"}</span>
+                  <span className="tabular-nums">
+                    {formatPaiseToCurrency(Math.abs(unpaidPaise))}
+                  </span>
+                </div>
+              </div>
+            )}
           </Card>
 
           {/* Cart summary */}
@@ -782,6 +911,14 @@ export default function NewSalePage() {
                   {formatPaiseToCurrency(invoiceTotals.totalSgstPaise)}
                 </span>
               </div>
+              {invoiceTotals.roundOffPaise !== 0 && (
+                <div className="flex justify-between text-slate-600">
+                  <span>Round Off</span>
+                  <span className="tabular-nums">
+                    {formatPaiseToCurrency(invoiceTotals.roundOffPaise)}
+                  </span>
+                </div>
+              )}
               <Separator />
               <div className="flex justify-between font-bold text-lg pt-1">
                 <span className="text-slate-900">Grand Total</span>
@@ -795,7 +932,7 @@ export default function NewSalePage() {
           {/* Checkout button */}
           <Button
             className="w-full h-12 text-base font-semibold gap-2"
-            disabled={cart.length === 0 || processing}
+            disabled={cart.length === 0 || processing || unpaidPaise !== 0}
             onClick={() => setConfirmOpen(true)}
           >
             <ShoppingCartIcon className="size-5" />
@@ -1,6 +1,7 @@
 import type { SaleWithDetails, PharmacySettings } from "@/types";
 import { formatPaiseToCurrency } from "@/lib/currency";
 import { paiseToWords } from "@/lib/numberToWords";
+import { isInterstateSupply } from "@/lib/gst";
 import { Separator } from "@/components/ui/separator";
 
 function formatDate(dateStr: string): string {
@@ -17,6 +18,7 @@ function formatDate(dateStr: string): string {
 
 interface HsnSummary {
   hsnCode: string;
+  gstRate: number;
   taxableValue: number;
   cgstRate: number;
   cgstAmount: number;
@@ -28,22 +30,25 @@ interface HsnSummary {
 function buildHsnSummary(sale: SaleWithDetails): HsnSummary[] {
   const hsnMap = new Map<string, HsnSummary>();
   for (const item of sale.items) {
-    const key = `${item.hsnCode}-${item.cgstRate + item.sgstRate}`;
+    const gstRate = item.cgstRate + item.sgstRate + item.igstRate;
+    const gst = item.cgstAmountPaise + item.sgstAmountPaise + item.igstAmountPaise;
+    const key = `${item.hsnCode}-${gstRate}`;
     const existing = hsnMap.get(key);
     if (existing) {
       existing.taxableValue += item.taxableAmountPaise;
       existing.cgstAmount += item.cgstAmountPaise;
       existing.sgstAmount += item.sgstAmountPaise;
-      existing.totalGst += item.cgstAmountPaise + item.sgstAmountPaise;
+      existing.totalGst += gst;
     } else {
       hsnMap.set(key, {
         hsnCode: item.hsnCode,
+        gstRate,
         taxableValue: item.taxableAmountPaise,
         cgstRate: item.cgstRate,
         cgstAmount: item.cgstAmountPaise,
         sgstRate: item.sgstRate,
         sgstAmount: item.sgstAmountPaise,
-        totalGst: item.cgstAmountPaise + item.sgstAmountPaise,
+        totalGst: gst,
       });
     }
   }
@@ -56,7 +61,13 @@ interface ReceiptPrintViewProps {
 }
 
 export default function ReceiptPrintView({ sale, settings }: ReceiptPrintViewProps) {
-  const hsnSummary = buildHsnSummary(sale);
+  const hsnSummary = sale.billOfSupply ? [] : buildHsnSummary(sale);
+  const taxColumns = !sale.billOfSupply;
+  // Inter-state lines store IGST. Sales recorded before that carry CGST/SGST
+  // halves, so a differing place of supply still shows their sum as IGST.
+  const placeOfSupply = sale.placeOfSupply || sale.customerStateCode || settings.stateCode;
+  const interstate =
+    sale.isInterstate || isInterstateSupply(settings.stateCode, sale.placeOfSupply ?? sale.customerStateCode);
 
   return (
     <div
@@ -87,6 +98,9 @@ export default function ReceiptPrintView({ sale, settings }: ReceiptPrintViewPro
             </span>
           </span>
         </div>
+        <p className="mt-2 text-sm font-semibold tracking-wider text-slate-700">
+          {sale.billOfSupply ? "BILL OF SUPPLY" : "TAX INVOICE"}
+        </p>
       </div>
 
       {/* Invoice details */}
@@ -94,13 +108,22 @@ export default function ReceiptPrintView({ sale, settings }: ReceiptPrintViewPro
         <div>
           <p className="text-xs text-slate-500">Invoice Number</p>
           <p className="font-mono font-bold text-lg">{sale.invoiceNumber}</p>
+          {sale.irn && (
+            <p className="text-xs text-slate-500 font-mono break-all">IRN: {sale.irn}</p>
+          )}
+          {sale.ewbNo && (
+            <p className="text-xs text-slate-500">
+              E-way bill <span className="font-mono">{sale.ewbNo}</span>, valid until{" "}
+              {sale.ewbValidUpto}
+            </p>
+          )}
         </div>
         <div className="text-right">
           <p className="text-xs text-slate-500">Date</p>
           <p className="font-medium">{formatDate(sale.saleDate)}</p>
-          {settings.stateCode && (
+          {placeOfSupply && (
             <p className="text-xs text-slate-500 mt-1">
-              Place of Supply: State Code {settings.stateCode}
+              Place of Supply: State Code {placeOfSupply}
             </p>
           )}
         </div>
@@ -111,6 +134,11 @@ export default function ReceiptPrintView({ sale, settings }: ReceiptPrintViewPro
         <div className="mb-4 p-3 bg-slate-50 rounded-md print:bg-transparent print:p-0 print:border-b print:pb-2">
           <p className="text-xs text-slate-500">Bill To</p>
           <p className="font-medium text-sm">{sale.customerName}</p>
+          {sale.customerGstin && (
+            <p className="text-xs text-slate-600">
+              GSTIN: <span className="font-mono font-medium">{sale.customerGstin}</span>
+            </p>
+          )}
         </div>
       )}
 
@@ -126,9 +154,17 @@ export default function ReceiptPrintView({ sale, settings }: ReceiptPrintViewPro
             <th className="text-center py-2 px-1 text-xs font-semibold text-slate-600">Qty</th>
             <th className="text-right py-2 px-1 text-xs font-semibold text-slate-600">Price</th>
             <th className="text-right py-2 px-1 text-xs font-semibold text-slate-600">Disc.</th>
-            <th className="text-right py-2 px-1 text-xs font-semibold text-slate-600">Taxable</th>
-            <th className="text-center py-2 px-1 text-xs font-semibold text-slate-600">CGST</th>
-            <th className="text-center py-2 px-1 text-xs font-semibold text-slate-600">SGST</th>
+            {taxColumns && (
+              <th className="text-right py-2 px-1 text-xs font-semibold text-slate-600">Taxable</th>
+            )}
+            {!taxColumns ? null : interstate ? (
+              <th className="text-center py-2 px-1 text-xs font-semibold text-slate-600">IGST</th>
+            ) : (
+              <>
+                <th className="text-center py-2 px-1 text-xs font-semibold text-slate-600">CGST</th>
+                <th className="text-center py-2 px-1 text-xs font-semibold text-slate-600">SGST</th>
+              </>
+            )}
             <th className="text-right py-2 px-1 text-xs font-semibold text-slate-600">Total</th>
           </tr>
         </thead>
@@ -145,15 +181,30 @@ export default function ReceiptPrintView({ sale, settings }: ReceiptPrintViewPro
               <td className="py-1.5 px-1 text-right tabular-nums">
                 {item.discountPaise > 0 ? formatPaiseToCurrency(item.discountPaise) : "\u2014"}
               </td>
-              <td className="py-1.5 px-1 text-right tabular-nums">{formatPaiseToCurrency(item.taxableAmountPaise)}</td>
-              <td className="py-1.5 px-1 text-center tabular-nums text-xs">
-                <div>{item.cgstRate}%</div>
-                <div className="text-slate-500">{formatPaiseToCurrency(item.cgstAmountPaise)}</div>
-              </td>
-              <td className="py-1.5 px-1 text-center tabular-nums text-xs">
-                <div>{item.sgstRate}%</div>
-                <div className="text-slate-500">{formatPaiseToCurrency(item.sgstAmountPaise)}</div>
-              </td>
+              {taxColumns && (
+                <td className="py-1.5 px-1 text-right tabular-nums">{formatPaiseToCurrency(item.taxableAmountPaise)}</td>
+              )}
+              {!taxColumns ? null : interstate ? (
+                <td className="py-1.5 px-1 text-center tabular-nums text-xs">
+                  <div>{item.cgstRate + item.sgstRate + item.igstRate}%</div>
+                  <div className="text-slate-500">
+                    {formatPaiseToCurrency(
+                      item.cgstAmountPaise + item.sgstAmountPaise + item.igstAmountPaise
+                    )}
+                  </div>
+                </td>
+              ) : (
+                <>
+                  <td className="py-1.5 px-1 text-center tabular-nums text-xs">
+                    <div>{item.cgstRate}%</div>
+                    <div className="text-slate-500">{formatPaiseToCurrency(item.cgstAmountPaise)}</div>
+                  </td>
+                  <td className="py-1.5 px-1 text-center tabular-nums text-xs">
+                    <div>{item.sgstRate}%</div>
+                    <div className="text-slate-500">{formatPaiseToCurrency(item.sgstAmountPaise)}</div>
+                  </td>
+                </>
+              )}
               <td className="py-1.5 px-1 text-right tabular-nums font-medium">{formatPaiseToCurrency(item.totalPaise)}</td>
             </tr>
           ))}
@@ -171,8 +222,14 @@ export default function ReceiptPrintView({ sale, settings }: ReceiptPrintViewPro
               <tr className="border-b border-slate-200 bg-slate-50">
                 <th className="text-left py-1.5 px-2">HSN</th>
                 <th className="text-right py-1.5 px-2">Taxable Value</th>
-                <th className="text-right py-1.5 px-2">CGST</th>
-                <th className="text-right py-1.5 px-2">SGST</th>
+                {interstate ? (
+                  <th className="text-right py-1.5 px-2">IGST</th>
+                ) : (
+                  <>
+                    <th className="text-right py-1.5 px-2">CGST</th>
+                    <th className="text-right py-1.5 px-2">SGST</th>
+                  </>
+                )}
                 <th className="text-right py-1.5 px-2">Total GST</th>
               </tr>
             </thead>
@@ -181,12 +238,20 @@ export default function ReceiptPrintView({ sale, settings }: ReceiptPrintViewPro
                 <tr key={idx} className="border-b border-slate-100">
                   <td className="py-1 px-2 font-mono">{hsn.hsnCode}</td>
                   <td className="py-1 px-2 text-right tabular-nums">{formatPaiseToCurrency(hsn.taxableValue)}</td>
-                  <td className="py-1 px-2 text-right tabular-nums">
-                    {formatPaiseToCurrency(hsn.cgstAmount)} ({hsn.cgstRate}%)
-                  </td>
-                  <td className="py-1 px-2 text-right tabular-nums">
-                    {formatPaiseToCurrency(hsn.sgstAmount)} ({hsn.sgstRate}%)
-                  </td>
+                  {interstate ? (
+                    <td className="py-1 px-2 text-right tabular-nums">
+                      {formatPaiseToCurrency(hsn.totalGst)} ({hsn.gstRate}%)
+                    </td>
+                  ) : (
+                    <>
+                      <td className="py-1 px-2 text-right tabular-nums">
+                        {formatPaiseToCurrency(hsn.cgstAmount)} ({hsn.cgstRate}%)
+                      </td>
+                      <td className="py-1 px-2 text-right tabular-nums">
+                        {formatPaiseToCurrency(hsn.sgstAmount)} ({hsn.sgstRate}%)
+                      </td>
+                    </>
+                  )}
                   <td className="py-1 px-2 text-right tabular-nums font-medium">{formatPaiseToCurrency(hsn.totalGst)}</td>
                 </tr>
               ))}
@@ -208,14 +273,29 @@ export default function ReceiptPrintView({ sale, settings }: ReceiptPrintViewPro
               <span className="tabular-nums">-{formatPaiseToCurrency(sale.discountPaise)}</span>
             </div>
           )}
-          <div className="flex justify-between">
-            <span className="text-slate-600">CGST</span>
-            <span className="tabular-nums">{formatPaiseToCurrency(sale.totalCgstPaise)}</span>
-          </div>
-          <div className="flex justify-between">
-            <span className="text-slate-600">SGST</span>
-            <span className="tabular-nums">{formatPaiseToCurrency(sale.totalSgstPaise)}</span>
-          </div>
+          {!taxColumns ? null : interstate ? (
+            <div className="flex justify-between">
+              <span className="text-slate-600">IGST</span>
+              <span className="tabular-nums">{formatPaiseToCurrency(sale.totalGstPaise)}</span>
+            </div>
+          ) : (
+            <>
+              <div className="flex justify-between">
+                <span className="text-slate-600">CGST</span>
+                <span className="tabular-nums">{formatPaiseToCurrency(sale.totalCgstPaise)}</span>
+              </div>
+              <div className="flex justify-between">
+                <span className="text-slate-600">SGST</span>
+                <span className="tabular-nums">{formatPaiseToCurrency(sale.totalSgstPaise)}</span>
+              </div>
+            </>
+          )}
+          {sale.roundOffPaise !== 0 && (
+            <div className="flex justify-between">
+              <span className="text-slate-600">Round Off</span>
+              <span className="tabular-nums">{formatPaiseToCurrency(sale.roundOffPaise)}</span>
+            </div>
+          )}
           <Separator />
           <div className="flex justify-between font-bold text-lg pt-1">
             <span>Grand Total</span>
@@ -224,6 +304,12 @@ export default function ReceiptPrintView({ sale, settings }: ReceiptPrintViewPro
         </div>
       </div>
 
+      {sale.billOfSupply && (
+        <p className="mb-4 text-xs font-medium text-slate-700">
+          Composition taxable person, not eligible to collect tax on supplies
+        </p>
+      )}
+
       {/* Amount in words */}
       <div className="border-t border-b py-2 mb-4">
         <p className="text-xs text-slate-500">Amount in Words</p>
@@ -0,0 +1,398 @@
+import { useState, useEffect, useCallback } from "react";
+import { toast } from "sonner";
+import { LockIcon, PrinterIcon, UnlockIcon } from "lucide-react";
+import { useAuth } from "@/features/auth/AuthContext";
+import { useDataChanged } from "@/hooks/useDataChanged";
+import { getSettings } from "@/db/queries/settings";
+import {
+  getCurrentShift,
+  getShiftReport,
+  getShifts,
+  openShift,
+  closeShift,
+  printShiftReport,
+  type DrawerTotals,
+  type Shift,
+  type ZReport,
+} from "@/db/queries/shifts";
+import type { ModeTotals } from "@/db/queries/dayBook";
+import { formatPaiseToCurrency, rupeesToPaise } from "@/lib/currency";
+import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import { Label } from "@/components/ui/label";
+import { Badge } from "@/components/ui/badge";
+import { Card, CardContent, CardHeader, CardTitle } from "@/components/ui/card";
+import {
+  Table,
+  TableBody,
+  TableCell,
+  TableHead,
+  TableHeader,
+  TableRow,
+} from "@/components/ui/table";
+
+const DRAWER_MODES: { key: keyof DrawerTotals; label: string }[] = [
+  { key: "cashPaise", label: "Cash" },
+  { key: "cardPaise", label: "Card" },
+  { key: "upiPaise", label: "UPI" },
+];
+
+const FLOW_ROWS: { key: keyof ZReport; label: string }[] = [
+  { key: "sales", label: "Taken on sales" },
+  { key: "receipts", label: "Received on account" },
+  { key: "refunds", label: "Refunded" },
+  { key: "paidOut", label: "Paid out" },
+];
+
+function today(): string {
+  return new Date().toISOString().split("T")[0];
+}
+
+function daysAgo(days: number): string {
+  const d = new Date();
+  d.setDate(d.getDate() - days);
+  return d.toISOString().split("T")[0];
+}
+
+function ShiftReportCard({ report, onPrint }: { report: ZReport; onPrint: () => void }) {
+  const closed = report.shift.closedAt !== null;
+  return (
+    <Card>
+      <CardHeader className="flex flex-row items-center justify-between">
+        <CardTitle className="text-base">
+          {closed ? "Z-Report" : "X-Report"} &middot; Shift {report.shift.id}
+        </CardTitle>
+        <Button variant="outline" size="sm" onClick={onPrint} className="gap-2">
+          <PrinterIcon className="size-4" />
+          Print
+        </Button>
+      </CardHeader>
+      <CardContent className="space-y-4 text-sm">
+        <div className="text-muted-foreground">
+          <p>Cashier: {report.shift.cashier}</p>
+          <p>Opened: {report.shift.openedAt}</p>
+          {closed && <p>Closed: {report.shift.closedAt}</p>}
+        </div>
+
+        <div className="flex justify-between font-medium">
+          <span>Sales ({report.saleCount})</span>
+          <span className="tabular-nums">{formatPaiseToCurrency(report.salesTotalPaise)}</span>
+        </div>
+
+        <Table>
+          <TableHeader>
+            <TableRow>
+              <TableHead />
+              <TableHead className="text-right">Cash</TableHead>
+              <TableHead className="text-right">Card</TableHead>
+              <TableHead className="text-right">UPI</TableHead>
+            </TableRow>
+          </TableHeader>
+          <TableBody>
+            {FLOW_ROWS.map(({ key, label }) => {
+              const totals = report[key] as ModeTotals;
+              return (
+                <TableRow key={key}>
+                  <TableCell>{label}</TableCell>
+                  {DRAWER_MODES.map((m) => (
+                    <TableCell key={m.key} className="text-right tabular-nums">
+                      {formatPaiseToCurrency(totals[m.key])}
+                    </TableCell>
+                  ))}
+                </TableRow>
+              );
+            })}
+            <TableRow>
+              <TableCell>Opening float</TableCell>
+              <TableCell className="text-right tabular-nums">
+                {formatPaiseToCurrency(report.shift.openingFloatPaise)}
+              </TableCell>
+              <TableCell />
+              <TableCell />
+            </TableRow>
+            <TableRow className="font-medium">
+              <TableCell>Expected</TableCell>
+              {DRAWER_MODES.map((m) => (
+                <TableCell key={m.key} className="text-right tabular-nums">
+                  {formatPaiseToCurrency(report.expected[m.key])}
+                </TableCell>
+              ))}
+            </TableRow>
+            {report.counted && report.variance && (
+              <>
+                <TableRow>
+                  <TableCell>Counted</TableCell>
+                  {DRAWER_MODES.map((m) => (
+                    <TableCell key={m.key} className="text-right tabular-nums">
+                      {formatPaiseToCurrency(report.counted![m.key])}
+                    </TableCell>
+                  ))}
+                </TableRow>
+                <TableRow className="font-bold">
+                  <TableCell>Variance</TableCell>
+                  {DRAWER_MODES.map((m) => {
+                    const variance = report.variance![m.key];
+                    return (
+                      <TableCell
+                        key={m.key}
+                        className={`text-right tabular-nums ${
+                          variance < 0 ? "text-red-600" : variance > 0 ? "text-amber-600" : ""
+                        }`}
+                      >
+                        {formatPaiseToCurrency(variance)}
+                      </TableCell>
+                    );
+                  })}
+                </TableRow>
+              </>
+            )}
+          </TableBody>
+        </Table>
+
+        {report.shift.notes && <p className="text-muted-foreground">{report.shift.notes}</p>}
+      </CardContent>
+    </Card>
+  );
+}
+
+export default function ShiftPage() {
+  const { user } = useAuth();
+  const [current, setCurrent] = useState<Shift | null>(null);
+  const [report, setReport] = useState<ZReport | null>(null);
+  const [history, setHistory] = useState<Shift[]>([]);
+  const [loading, setLoading] = useState(true);
+  const [saving, setSaving] = useState(false);
+  const [openingFloat, setOpeningFloat] = useState("");
+  const [counted, setCounted] = useState<Record<keyof DrawerTotals, string>>({
+    cashPaise: "",
+    cardPaise: "",
+    upiPaise: "",
+  });
+  const [notes, setNotes] = useState("");
+
+  const load = useCallback(async () => {
+    if (!user) return;
+    try {
+      setLoading(true);
+      const [shift, shifts] = await Promise.all([
+        getCurrentShift(),
+        getShifts(daysAgo(30), today()),
+      ]);
+      setCurrent(shift);
+      setHistory(shifts);
+      if (shift) {
+        setReport(await getShiftReport(shift.id));
+      }
+    } catch (err) {
+      console.error(err);
+      toast.error(String(err));
+    } finally {
+      setLoading(false);
+    }
+  }, [user]);
+
+  useEffect(() => {
+    load();
+  }, [load]);
+
+  // Shifts closed elsewhere and sales rung up into this one
+  useDataChanged(["shift", "sale"], load);
+
+  const handleOpen = async (e: React.FormEvent) => {
+    e.preventDefault();
+    if (!user) return;
+    try {
+      setSaving(true);
+      await openShift(openingFloat.trim() ? rupeesToPaise(openingFloat) : 0, null);
+      setOpeningFloat("");
+      toast.success("Shift opened");
+      load();
+    } catch (err) {
+      toast.error(String(err));
+    } finally {
+      setSaving(false);
+    }
+  };
+
+  const handleClose = async (e: React.FormEvent) => {
+    e.preventDefault();
+    if (!user || !current) return;
+    const totals: DrawerTotals = {
+      cashPaise: rupeesToPaise(counted.cashPaise || "0"),
+      cardPaise: rupeesToPaise(counted.cardPaise || "0"),
+      upiPaise: rupeesToPaise(counted.upiPaise || "0"),
+    };
+    try {
+      setSaving(true);
+      const closed = await closeShift(current.id, totals, notes.trim() || null);
+      setCounted({ cashPaise: "", cardPaise: "", upiPaise: "" });
+      setNotes("");
+      toast.success("Shift closed");
+      await load();
+      setReport(closed);
+    } catch (err) {
+      toast.error(String(err));
+    } finally {
+      setSaving(false);
+    }
+  };
+
+  const handlePrint = async (shiftId: number) => {
+    try {
+      const settings = await getSettings();
+      if (settings.printerInterface === "none") {
+        window.print();
+        return;
+      }
+      await printShiftReport(shiftId);
+      toast.success("Report sent to printer");
+    } catch (err) {
+      console.error(err);
+      toast.error(String(err));
+    }
+  };
+
+  const showReport = async (shiftId: number) => {
+    try {
+      setReport(await getShiftReport(shiftId));
+    } catch (err) {
+      toast.error(String(err));
+    }
+  };
+
+  if (loading) {
+    return <p className="text-muted-foreground text-sm">Loading&hellip;</p>;
+  }
+
+  return (
+    <div className="space-y-6">
+      {/* Header */}
+      <div>
+        <h1 className="text-3xl font-bold text-slate-900">Shift</h1>
+        <p className="text-slate-600 mt-1">
+          Open the cash drawer with a float and reconcile it at the end of the shift
+        </p>
+      </div>
+
+      <div className="grid grid-cols-1 gap-6 lg:grid-cols-2">
+        <div className="space-y-6">
+          {current ? (
+            <Card>
+              <CardHeader>
+                <CardTitle className="text-base">Close Shift {current.id}</CardTitle>
+              </CardHeader>
+              <CardContent>
+                <form onSubmit={handleClose} className="space-y-4">
+                  <p className="text-sm text-muted-foreground">
+                    Count the drawer and enter what it holds in each mode.
+                  </p>
+                  <div className="grid grid-cols-3 gap-3">
+                    {DRAWER_MODES.map((m) => (
+                      <div key={m.key} className="space-y-1.5">
+                        <Label htmlFor={`count-${m.key}`}>{m.label} (₹)</Label>
+                        <Input
+                          id={`count-${m.key}`}
+                          type="number"
+                          min="0"
+                          step="0.01"
+                          value={counted[m.key]}
+                          onChange={(e) =>
+                            setCounted((prev) => ({ ...prev, [m.key]: e.target.value }))
+                          }
+                          autoComplete="off"
+                        />
+                      </div>
+                    ))}
+                  </div>
+                  <div className="space-y-1.5">
+                    <Label htmlFor="close-notes">Notes</Label>
+                    <Input
+                      id="close-notes"
+                      value={notes}
+                      onChange={(e) => setNotes(e.target.value)}
+                      placeholder="e.g. reason for a shortfall (optional)"
+                      autoComplete="off"
+                    />
+                  </div>
+                  <Button type="submit" disabled={saving} className="gap-2">
+                    <LockIcon className="size-4" />
+                    {saving ? "Closing..." : "Close Shift"}
+                  </Button>
+                </form>
+              </CardContent>
+            </Card>
+          ) : (
+            <Card>
+              <CardHeader>
+                <CardTitle className="text-base">Open a Shift</CardTitle>
+              </CardHeader>
+              <CardContent>
+                <form onSubmit={handleOpen} className="space-y-4">
+                  <div className="space-y-1.5">
+                    <Label htmlFor="opening-float">Opening float (₹)</Label>
+                    <Input
+                      id="opening-float"
+                      type="number"
+                      min="0"
+                      step="0.01"
+                      value={openingFloat}
+                      onChange={(e) => setOpeningFloat(e.target.value)}
+                      placeholder="Cash counted into the drawer"
+                      autoComplete="off"
+                    />
+                  </div>
+                  <Button type="submit" disabled={saving} className="gap-2">
+                    <UnlockIcon className="size-4" />
+                    {saving ? "Opening..." : "Open Shift"}
+                  </Button>
+                </form>
+              </CardContent>
+            </Card>
+          )}
+
+          {/* Recent shifts */}
+          <div className="rounded-lg border bg-card shadow-sm">
+            {history.length === 0 ? (
+              <p className="text-muted-foreground text-sm text-center py-12">
+                No shifts in the last 30 days.
+              </p>
+            ) : (
+              <Table>
+                <TableHeader>
+                  <TableRow>
+                    <TableHead>Shift</TableHead>
+                    <TableHead>Cashier</TableHead>
+                    <TableHead>Opened</TableHead>
+                    <TableHead>Status</TableHead>
+                  </TableRow>
+                </TableHeader>
+                <TableBody>
+                  {history.map((shift) => (
+                    <TableRow
+                      key={shift.id}
+                      className="cursor-pointer"
+                      onClick={() => showReport(shift.id)}
+                    >
+                      <TableCell className="font-mono text-sm">{shift.id}</TableCell>
+                      <TableCell className="text-sm">{shift.cashier}</TableCell>
+                      <TableCell className="text-sm text-slate-600">{shift.openedAt}</TableCell>
+                      <TableCell>
+                        <Badge variant={shift.closedAt ? "outline" : "default"}>
+                          {shift.closedAt ? "Closed" : "Open"}
+                        </Badge>
+                      </TableCell>
+                    </TableRow>
+                  ))}
+                </TableBody>
+              </Table>
+            )}
+          </div>
+        </div>
+
+        {report && (
+          <ShiftReportCard report={report} onPrint={() => handlePrint(report.shift.id)} />
+        )}
+      </div>
+    </div>
+  );
+}
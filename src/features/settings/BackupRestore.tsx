@@ -1,15 +1,19 @@
-import { useState } from "react";
+import { useCallback, useEffect, useState } from "react";
 import { toast } from "sonner";
 import {
   DownloadIcon,
   UploadIcon,
   AlertTriangleIcon,
+  ArchiveIcon,
+  LockIcon,
+  WrenchIcon,
 } from "lucide-react";
+import { invoke } from "@tauri-apps/api/core";
 import { save, open } from "@tauri-apps/plugin-dialog";
-import { copyFile } from "@tauri-apps/plugin-fs";
-import { relaunch } from "@tauri-apps/plugin-process";
-import { appDataDir, join } from "@tauri-apps/api/path";
+import { sessionToken } from "@/db/session";
 import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import { Label } from "@/components/ui/label";
 import {
   Card,
   CardContent,
@@ -28,7 +32,51 @@ import {
 } from "@/components/ui/dialog";
 
 const LAST_BACKUP_KEY = "lastBackupTime";
-const DB_FILENAME = "pharmacy.db";
+
+interface BackupInfo {
+  path: string;
+  sizeBytes: number;
+  tables: { table: string; rows: number }[];
+}
+
+interface BackupFile {
+  path: string;
+  fileName: string;
+  sizeBytes: number;
+  /** Local time, YYYY-MM-DD HH:MM:SS */
+  takenAt: string;
+}
+
+interface ArchiveInfo {
+  path: string;
+  sizeBytes: number;
+  /** Database size before compression */
+  databaseBytes: number;
+}
+
+interface BackupStatus {
+  latestLocal: BackupFile | null;
+  remoteTarget: "none" | "s3" | "gdrive" | "webdav";
+  lastAttemptAt: string | null;
+  lastSuccessAt: string | null;
+  lastUploaded: string | null;
+  lastError: string | null;
+  failures: number;
+}
+
+interface MaintenanceRun {
+  integrityOk: boolean;
+  problems: string[];
+  report: { sizeBeforeBytes: number; sizeAfterBytes: number; reclaimedBytes: number } | null;
+}
+
+/** The backend asks for confirmation with this phrase before restoring an older schema. */
+const OLDER_SCHEMA_HINT = "confirm to restore anyway";
+
+function formatSize(bytes: number): string {
+  if (bytes < 1024 * 1024) return `${(bytes / 1024).toFixed(1)} KB`;
+  return `${(bytes / (1024 * 1024)).toFixed(1)} MB`;
+}
 
 function formatTimestamp(iso: string): string {
   const d = new Date(iso);
@@ -53,13 +101,42 @@ export default function BackupRestore() {
   const [lastBackup, setLastBackup] = useState<string | null>(
     () => localStorage.getItem(LAST_BACKUP_KEY),
   );
+  const [snapshots, setSnapshots] = useState<BackupFile[]>([]);
+  const [status, setStatus] = useState<BackupStatus | null>(null);
+  const [passphrase, setPassphrase] = useState("");
+  const [maintaining, setMaintaining] = useState(false);
+  const [olderPrompt, setOlderPrompt] = useState<{ path: string; message: string } | null>(null);
 
-  const handleBackup = async () => {
+  const loadSnapshots = useCallback(async () => {
+    try {
+      setSnapshots(await invoke<BackupFile[]>("list_backups"));
+      setStatus(await invoke<BackupStatus>("backup_status", { token: sessionToken() }));
+    } catch (err) {
+      console.error("Failed to list backups:", err);
+    }
+  }, []);
+
+  useEffect(() => {
+    loadSnapshots();
+  }, [loadSnapshots]);
+
+  const handleBackupNow = async () => {
     try {
       setBackingUp(true);
+      const info = await invoke<BackupInfo>("backup_now", { token: sessionToken() });
+      toast.success(`Backup saved to ${info.path} (${formatSize(info.sizeBytes)})`);
+      await loadSnapshots();
+    } catch (err) {
+      console.error("Backup failed:", err);
+      toast.error(typeof err === "string" ? err : "Failed to create backup. Please try again.");
+    } finally {
+      setBackingUp(false);
+    }
+  };
 
-      const dataDir = await appDataDir();
-      const dbPath = await join(dataDir, DB_FILENAME);
+  const handleBackup = async () => {
+    try {
+      setBackingUp(true);
 
       const destPath = await save({
         title: "Save Backup",
@@ -69,21 +146,107 @@ export default function BackupRestore() {
 
       if (!destPath) return; // user cancelled
 
-      await copyFile(dbPath, destPath);
+      // The backend snapshots the live database (WAL included), so the
+      // copy is consistent even while other connections are writing.
+      const info = await invoke<BackupInfo>("backup_database", {
+        destPath,
+        token: sessionToken(),
+      });
 
       const now = new Date().toISOString();
       localStorage.setItem(LAST_BACKUP_KEY, now);
       setLastBackup(now);
 
-      toast.success(`Backup saved to ${destPath}`);
+      toast.success(`Backup saved to ${info.path} (${formatSize(info.sizeBytes)})`);
     } catch (err) {
       console.error("Backup failed:", err);
-      toast.error("Failed to create backup. Please try again.");
+      toast.error(typeof err === "string" ? err : "Failed to create backup. Please try again.");
+    } finally {
+      setBackingUp(false);
+    }
+  };
+
+  const handleMaintenance = async () => {
+    try {
+      setMaintaining(true);
+      const run = await invoke<MaintenanceRun>("db_maintenance", { token: sessionToken() });
+      if (!run.integrityOk) {
+        toast.error(
+          `The database failed its integrity check (${run.problems[0]}). Restore a recent backup.`,
+        );
+      } else if (run.report) {
+        toast.success(
+          `Database checked and compacted: ${formatSize(run.report.sizeBeforeBytes)} → ${formatSize(run.report.sizeAfterBytes)}`,
+        );
+      }
+    } catch (err) {
+      console.error("Maintenance failed:", err);
+      toast.error(typeof err === "string" ? err : "Maintenance failed. Please try again.");
+    } finally {
+      setMaintaining(false);
+    }
+  };
+
+  const handleExportArchive = async () => {
+    try {
+      setBackingUp(true);
+
+      const destPath = await save({
+        title: "Save Encrypted Backup",
+        defaultPath: generateBackupFilename().replace(/\.db$/, ".pcbak"),
+        filters: [{ name: "PharmaCare Backup", extensions: ["pcbak"] }],
+      });
+
+      if (!destPath) return; // user cancelled
+
+      const info = await invoke<ArchiveInfo>("export_backup", {
+        destPath,
+        passphrase,
+        token: sessionToken(),
+      });
+      toast.success(`Encrypted backup saved to ${info.path} (${formatSize(info.sizeBytes)})`);
+    } catch (err) {
+      console.error("Encrypted backup failed:", err);
+      toast.error(typeof err === "string" ? err : "Failed to create backup. Please try again.");
     } finally {
       setBackingUp(false);
     }
   };
 
+  const restoreArchive = async (srcPath: string, allowOlder: boolean) => {
+    try {
+      setRestoring(true);
+      await invoke("restore_backup", {
+        srcPath,
+        passphrase,
+        allowOlder,
+        token: sessionToken(),
+      });
+      toast.success("Backup restored successfully. Reloading...");
+      window.location.reload();
+    } catch (err) {
+      if (!allowOlder && typeof err === "string" && err.includes(OLDER_SCHEMA_HINT)) {
+        setOlderPrompt({ path: srcPath, message: err });
+        return;
+      }
+      console.error("Restore failed:", err);
+      toast.error(typeof err === "string" ? err : "Failed to restore backup. Please try again.");
+    } finally {
+      setRestoring(false);
+    }
+  };
+
+  const handleRestoreArchive = async () => {
+    const selectedPath = await open({
+      title: "Select Encrypted Backup",
+      filters: [{ name: "PharmaCare Backup", extensions: ["pcbak"] }],
+      multiple: false,
+    });
+
+    if (!selectedPath) return; // user cancelled
+    await restoreArchive(selectedPath, false);
+  };
+
   const handleRestore = async () => {
     setRestoreDialogOpen(false);
 
@@ -98,15 +261,15 @@ export default function BackupRestore() {
 
       if (!selectedPath) return; // user cancelled
 
-      const dataDir = await appDataDir();
-      const dbPath = await join(dataDir, DB_FILENAME);
-
-      await copyFile(selectedPath, dbPath);
-      toast.success("Backup restored successfully. Restarting application...");
-      await relaunch();
+      // The backend closes the SQL plugin's pool around the restore and
+      // reopens it migrated; reload so every page reads the restored data
+      // and the session is checked against it.
+      await invoke("restore_database", { srcPath: selectedPath, token: sessionToken() });
+      toast.success("Backup restored successfully. Reloading...");
+      window.location.reload();
     } catch (err) {
       console.error("Restore failed:", err);
-      toast.error("Failed to restore backup. Please try again.");
+      toast.error(typeof err === "string" ? err : "Failed to restore backup. Please try again.");
     } finally {
       setRestoring(false);
     }
@@ -151,6 +314,152 @@ export default function BackupRestore() {
         {/* Divider */}
         <div className="border-t border-slate-200" />
 
+        {/* Scheduled Backups Section */}
+        <div className="space-y-3">
+          <h3 className="text-sm font-semibold text-slate-900">
+            Automatic Backups
+          </h3>
+          <p className="text-sm text-slate-500">
+            A snapshot is taken daily at the time set under Pharmacy Details,
+            and only the newest are kept.
+          </p>
+          <Button
+            type="button"
+            variant="outline"
+            onClick={handleBackupNow}
+            disabled={backingUp || restoring}
+          >
+            <ArchiveIcon className="mr-2 size-4" />
+            Back Up Now
+          </Button>
+          {status && status.remoteTarget !== "none" && (
+            <p className={`text-sm ${status.lastError ? "text-destructive" : "text-slate-500"}`}>
+              Off-site:{" "}
+              {status.lastSuccessAt
+                ? `last uploaded ${formatTimestamp(status.lastSuccessAt.replace(" ", "T") + "Z")}`
+                : "nothing uploaded yet"}
+              {status.lastError && ` — latest attempt failed (${status.lastError}); retrying`}
+            </p>
+          )}
+          {snapshots.length === 0 ? (
+            <p className="text-sm text-slate-500">No automatic backups yet.</p>
+          ) : (
+            <ul className="divide-y divide-slate-100 rounded-md border border-slate-200 text-sm">
+              {snapshots.map((file) => (
+                <li key={file.path} className="flex justify-between px-3 py-2" title={file.path}>
+                  <span>{formatTimestamp(file.takenAt.replace(" ", "T"))}</span>
+                  <span className="text-slate-500">{formatSize(file.sizeBytes)}</span>
+                </li>
+              ))}
+            </ul>
+          )}
+        </div>
+
+        {/* Divider */}
+        <div className="border-t border-slate-200" />
+
+        {/* Encrypted Archive Section */}
+        <div className="space-y-3">
+          <h3 className="text-sm font-semibold text-slate-900">
+            Encrypted Backup
+          </h3>
+          <p className="text-sm text-slate-500">
+            A compressed, passphrase-protected archive that is safe to keep
+            on a USB drive or in the cloud. The passphrase cannot be
+            recovered, so store it somewhere safe.
+          </p>
+          <div className="space-y-2 max-w-sm">
+            <Label htmlFor="backupPassphrase">Passphrase</Label>
+            <Input
+              id="backupPassphrase"
+              type="password"
+              value={passphrase}
+              onChange={(e) => setPassphrase(e.target.value)}
+              placeholder="At least 8 characters"
+              autoComplete="new-password"
+            />
+          </div>
+          <div className="flex items-center gap-4">
+            <Button
+              type="button"
+              onClick={handleExportArchive}
+              disabled={backingUp || restoring || passphrase.length < 8}
+            >
+              <LockIcon className="mr-2 size-4" />
+              Export Encrypted Backup
+            </Button>
+            <Button
+              type="button"
+              variant="outline"
+              onClick={handleRestoreArchive}
+              disabled={backingUp || restoring || passphrase.length === 0}
+            >
+              <UploadIcon className="mr-2 size-4" />
+              Restore Encrypted Backup
+            </Button>
+          </div>
+
+          <Dialog
+            open={olderPrompt !== null}
+            onOpenChange={(isOpen) => !isOpen && setOlderPrompt(null)}
+          >
+            <DialogContent>
+              <DialogHeader>
+                <DialogTitle className="flex items-center gap-2">
+                  <AlertTriangleIcon className="size-5 text-amber-500" />
+                  Restore an Older Backup?
+                </DialogTitle>
+                <DialogDescription>{olderPrompt?.message}</DialogDescription>
+              </DialogHeader>
+
+              <DialogFooter>
+                <DialogClose asChild>
+                  <Button type="button" variant="outline">
+                    Cancel
+                  </Button>
+                </DialogClose>
+                <Button
+                  type="button"
+                  variant="destructive"
+                  onClick={() => {
+                    const path = olderPrompt?.path;
+                    setOlderPrompt(null);
+                    if (path) restoreArchive(path, true);
+                  }}
+                >
+                  Yes, Restore
+                </Button>
+              </DialogFooter>
+            </DialogContent>
+          </Dialog>
+        </div>
+
+        {/* Divider */}
+        <div className="border-t border-slate-200" />
+
+        {/* Maintenance Section */}
+        <div className="space-y-3">
+          <h3 className="text-sm font-semibold text-slate-900">
+            Database Maintenance
+          </h3>
+          <p className="text-sm text-slate-500">
+            Check the database for damage, fold the write-ahead log back in
+            and reclaim unused space. Other windows must be idle meanwhile.
+          </p>
+          <Button
+            type="button"
+            variant="outline"
+            onClick={handleMaintenance}
+            disabled={maintaining || backingUp || restoring}
+          >
+            <WrenchIcon className="mr-2 size-4" />
+            {maintaining ? "Running..." : "Run Maintenance"}
+          </Button>
+        </div>
+
+        {/* Divider */}
+        <div className="border-t border-slate-200" />
+
         {/* Restore Section */}
         <div className="space-y-3">
           <h3 className="text-sm font-semibold text-slate-900">
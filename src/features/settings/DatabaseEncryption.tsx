@@ -0,0 +1,125 @@
+import { useCallback, useEffect, useState, type FormEvent } from "react";
+import { toast } from "sonner";
+import { LockIcon } from "lucide-react";
+import {
+  changeDbKey,
+  encryptDatabase,
+  getDatabaseStatus,
+  type DatabaseStatus,
+} from "@/db/queries/encryption";
+import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import { Label } from "@/components/ui/label";
+import { Card, CardContent, CardDescription, CardHeader, CardTitle } from "@/components/ui/card";
+
+/** Encrypt the database with a passphrase, or change the passphrase. */
+export default function DatabaseEncryption() {
+  const [status, setStatus] = useState<DatabaseStatus | null>(null);
+  const [currentKey, setCurrentKey] = useState("");
+  const [newKey, setNewKey] = useState("");
+  const [confirmKey, setConfirmKey] = useState("");
+  const [busy, setBusy] = useState(false);
+
+  const load = useCallback(async () => {
+    try {
+      setStatus(await getDatabaseStatus());
+    } catch (err) {
+      toast.error(String(err));
+    }
+  }, []);
+
+  useEffect(() => {
+    load();
+  }, [load]);
+
+  const handleSubmit = async (e: FormEvent) => {
+    e.preventDefault();
+    if (!status) return;
+    if (!newKey) {
+      toast.error("Enter a passphrase");
+      return;
+    }
+    if (newKey !== confirmKey) {
+      toast.error("The passphrases do not match");
+      return;
+    }
+    try {
+      setBusy(true);
+      if (status.encrypted) {
+        await changeDbKey(currentKey, newKey);
+        toast.success("Passphrase changed");
+      } else {
+        await encryptDatabase(newKey);
+        toast.success("Database encrypted. Keep the passphrase safe: it cannot be recovered.");
+      }
+      setCurrentKey("");
+      setNewKey("");
+      setConfirmKey("");
+      await load();
+    } catch (err) {
+      toast.error(typeof err === "string" ? err : "Failed to update encryption");
+    } finally {
+      setBusy(false);
+    }
+  };
+
+  if (!status) return null;
+
+  return (
+    <Card className="mt-6">
+      <CardHeader>
+        <CardTitle className="flex items-center gap-2">
+          <LockIcon size={18} />
+          Database Encryption
+        </CardTitle>
+        <CardDescription>
+          {status.encrypted
+            ? "The database is encrypted and asks for its passphrase on every start."
+            : "The database is stored unencrypted. Encrypting it asks for a passphrase on every start."}
+        </CardDescription>
+      </CardHeader>
+      <CardContent>
+        <form onSubmit={handleSubmit} className="max-w-sm space-y-4">
+          {status.encrypted && (
+            <div className="space-y-1.5">
+              <Label htmlFor="current-db-key">Current passphrase</Label>
+              <Input
+                id="current-db-key"
+                type="password"
+                autoComplete="off"
+                value={currentKey}
+                onChange={(e) => setCurrentKey(e.target.value)}
+                disabled={busy}
+              />
+            </div>
+          )}
+          <div className="space-y-1.5">
+            <Label htmlFor="new-db-key">New passphrase</Label>
+            <Input
+              id="new-db-key"
+              type="password"
+              autoComplete="off"
+              value={newKey}
+              onChange={(e) => setNewKey(e.target.value)}
+              disabled={busy}
+            />
+          </div>
+          <div className="space-y-1.5">
+            <Label htmlFor="confirm-db-key">Confirm passphrase</Label>
+            <Input
+              id="confirm-db-key"
+              type="password"
+              autoComplete="off"
+              value={confirmKey}
+              onChange={(e) => setConfirmKey(e.target.value)}
+              disabled={busy}
+            />
+          </div>
+          <Button type="submit" disabled={busy}>
+            {status.encrypted ? "Change Passphrase" : "Encrypt Database"}
+          </Button>
+        </form>
+      </CardContent>
+    </Card>
+  );
+}
@@ -0,0 +1,240 @@
+import { useCallback, useEffect, useState } from "react";
+import { toast } from "sonner";
+import { PauseIcon, PlayIcon, RotateCwIcon } from "lucide-react";
+import { useAuth } from "@/features/auth/AuthContext";
+import {
+  getJobRuns,
+  getScheduledJobs,
+  setJobEnabled,
+  setJobSchedule,
+  triggerJob,
+  type JobRun,
+  type RunStatus,
+  type ScheduledJob,
+} from "@/db/queries/scheduler";
+import { Badge } from "@/components/ui/badge";
+import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import { Card, CardContent, CardHeader, CardTitle } from "@/components/ui/card";
+import {
+  Table,
+  TableBody,
+  TableCell,
+  TableHead,
+  TableHeader,
+  TableRow,
+} from "@/components/ui/table";
+
+const STATUS_STYLES: Record<RunStatus, string> = {
+  running: "bg-sky-100 text-sky-700 border-sky-200 hover:bg-sky-100",
+  succeeded: "bg-emerald-100 text-emerald-700 border-emerald-200 hover:bg-emerald-100",
+  failed: "bg-red-100 text-red-700 border-red-200 hover:bg-red-100",
+};
+
+/** Backend timestamps are UTC `YYYY-MM-DD HH:MM:SS` */
+function formatUtc(timestamp: string | null): string {
+  if (!timestamp) return "—";
+  return new Date(timestamp.replace(" ", "T") + "Z").toLocaleString("en-IN", {
+    dateStyle: "medium",
+    timeStyle: "short",
+  });
+}
+
+function JobRow({
+  job,
+  selected,
+  onSelect,
+  onChanged,
+}: {
+  job: ScheduledJob;
+  selected: boolean;
+  onSelect: () => void;
+  onChanged: () => void;
+}) {
+  const { user } = useAuth();
+  const [schedule, setSchedule] = useState(job.schedule);
+  const [busy, setBusy] = useState(false);
+
+  useEffect(() => {
+    setSchedule(job.schedule);
+  }, [job.schedule]);
+
+  const act = async (action: () => Promise<unknown>, done: string) => {
+    try {
+      setBusy(true);
+      await action();
+      toast.success(done);
+      onChanged();
+    } catch (err) {
+      toast.error(String(err));
+    } finally {
+      setBusy(false);
+    }
+  };
+
+  if (!user) return null;
+
+  return (
+    <TableRow className={selected ? "bg-slate-50" : "cursor-pointer"} onClick={onSelect}>
+      <TableCell>
+        <p className="font-medium text-sm">{job.description}</p>
+        <p className="font-mono text-xs text-muted-foreground">{job.key}</p>
+      </TableCell>
+      <TableCell onClick={(e) => e.stopPropagation()}>
+        <form
+          className="flex gap-2"
+          onSubmit={(e) => {
+            e.preventDefault();
+            act(() => setJobSchedule(job.key, schedule), "Schedule saved");
+          }}
+        >
+          <Input
+            value={schedule}
+            onChange={(e) => setSchedule(e.target.value)}
+            className="w-36 font-mono text-sm"
+            aria-label={`Schedule for ${job.key}`}
+            autoComplete="off"
+          />
+          {schedule.trim() !== job.schedule && (
+            <Button type="submit" variant="outline" size="sm" disabled={busy}>
+              Save
+            </Button>
+          )}
+        </form>
+      </TableCell>
+      <TableCell className="text-sm">
+        {job.lastRun ? (
+          <Badge className={STATUS_STYLES[job.lastRun.status]}>{job.lastRun.status}</Badge>
+        ) : (
+          <span className="text-muted-foreground">Never run</span>
+        )}
+      </TableCell>
+      <TableCell className="text-sm text-slate-600 whitespace-nowrap">
+        {job.enabled ? formatUtc(job.nextRunAt) : "Paused"}
+      </TableCell>
+      <TableCell className="text-right whitespace-nowrap" onClick={(e) => e.stopPropagation()}>
+        <Button
+          variant="ghost"
+          size="icon"
+          disabled={busy}
+          onClick={() =>
+            act(
+              () => setJobEnabled(job.key, !job.enabled),
+              job.enabled ? "Job paused" : "Job resumed"
+            )
+          }
+          aria-label={job.enabled ? "Pause job" : "Resume job"}
+        >
+          {job.enabled ? <PauseIcon className="size-4" /> : <PlayIcon className="size-4" />}
+        </Button>
+        <Button
+          variant="ghost"
+          size="icon"
+          disabled={busy}
+          onClick={() => act(() => triggerJob(job.key), "Job finished")}
+          aria-label="Run job now"
+        >
+          <RotateCwIcon className="size-4" />
+        </Button>
+      </TableCell>
+    </TableRow>
+  );
+}
+
+export default function ScheduledJobs() {
+  const [jobs, setJobs] = useState<ScheduledJob[]>([]);
+  const [selected, setSelected] = useState<string | null>(null);
+  const [runs, setRuns] = useState<JobRun[]>([]);
+
+  const load = useCallback(async () => {
+    try {
+      setJobs(await getScheduledJobs());
+      if (selected) setRuns(await getJobRuns(selected));
+    } catch (err) {
+      console.error(err);
+      toast.error(String(err));
+    }
+  }, [selected]);
+
+  useEffect(() => {
+    load();
+  }, [load]);
+
+  return (
+    <div className="space-y-6">
+      <Card>
+        <CardHeader>
+          <CardTitle>Scheduled Jobs</CardTitle>
+          <p className="text-sm text-slate-600">
+            Schedules are cron expressions in local time: minute, hour, day of month, month and
+            day of week, e.g. <span className="font-mono">30 22 * * *</span> for 10:30 pm daily.
+          </p>
+        </CardHeader>
+        <CardContent>
+          <Table>
+            <TableHeader>
+              <TableRow>
+                <TableHead>Job</TableHead>
+                <TableHead>Schedule</TableHead>
+                <TableHead>Last Run</TableHead>
+                <TableHead>Next Run</TableHead>
+                <TableHead />
+              </TableRow>
+            </TableHeader>
+            <TableBody>
+              {jobs.map((job) => (
+                <JobRow
+                  key={job.key}
+                  job={job}
+                  selected={job.key === selected}
+                  onSelect={() => setSelected(job.key)}
+                  onChanged={load}
+                />
+              ))}
+            </TableBody>
+          </Table>
+        </CardContent>
+      </Card>
+
+      {selected && (
+        <Card>
+          <CardHeader>
+            <CardTitle className="text-base">
+              Run History &middot; <span className="font-mono">{selected}</span>
+            </CardTitle>
+          </CardHeader>
+          <CardContent>
+            {runs.length === 0 ? (
+              <p className="text-muted-foreground text-sm">No runs yet.</p>
+            ) : (
+              <Table>
+                <TableHeader>
+                  <TableRow>
+                    <TableHead>Started</TableHead>
+                    <TableHead>Source</TableHead>
+                    <TableHead>Status</TableHead>
+                    <TableHead>Message</TableHead>
+                  </TableRow>
+                </TableHeader>
+                <TableBody>
+                  {runs.map((run) => (
+                    <TableRow key={run.id}>
+                      <TableCell className="text-sm text-slate-600 whitespace-nowrap">
+                        {formatUtc(run.startedAt)}
+                      </TableCell>
+                      <TableCell className="text-sm capitalize">{run.source}</TableCell>
+                      <TableCell>
+                        <Badge className={STATUS_STYLES[run.status]}>{run.status}</Badge>
+                      </TableCell>
+                      <TableCell className="text-sm text-slate-600">{run.message ?? "—"}</TableCell>
+                    </TableRow>
+                  ))}
+                </TableBody>
+              </Table>
+            )}
+          </CardContent>
+        </Card>
+      )}
+    </div>
+  );
+}
@@ -2,16 +2,26 @@ import { useState, useEffect } from 'react';
 import { useNavigate } from 'react-router-dom';
 import { toast } from 'sonner';
 import { Users as UsersIcon } from 'lucide-react';
-import type { PharmacySettings } from '@/types';
-import { getSettings, updateSettings } from '@/db/queries/settings';
+import type { PharmacySettings, PrinterInterface, RemoteBackupTarget } from '@/types';
+import {
+  getRemoteBackupSecrets,
+  getSettings,
+  setRemoteBackupSecrets,
+  updateSettings,
+  type RemoteBackupSecretsSet,
+} from '@/db/queries/settings';
+import { validateRegistrations } from '@/db/queries/registrations';
 import { Button } from '@/components/ui/button';
 import { Card, CardContent, CardHeader, CardTitle } from '@/components/ui/card';
 import { Input } from '@/components/ui/input';
 import { Label } from '@/components/ui/label';
 import { Tabs, TabsContent, TabsList, TabsTrigger } from '@/components/ui/tabs';
 import BackupRestore from './BackupRestore';
+import DatabaseEncryption from './DatabaseEncryption';
+import ScheduledJobs from './ScheduledJobs';
 
 const GSTIN_REGEX = /^[0-9]{2}[A-Z]{5}[0-9]{4}[A-Z]{1}[1-9A-Z]{1}Z[0-9A-Z]{1}$/;
+const PINCODE_REGEX = /^[1-9][0-9]{5}$/;
 
 type FormData = {
   name?: string;
@@ -21,15 +31,60 @@ type FormData = {
   gstin?: string;
   drugLicenseNo?: string;
   stateCode?: string;
+  city?: string;
+  pincode?: string;
+  einvoiceEnabled?: boolean;
   invoicePrefix?: string;
+  invoiceNumberFormat?: string;
+  fyReset?: boolean;
+  creditNotePrefix?: string;
+  debitNotePrefix?: string;
   lowStockThreshold?: number;
   nearExpiryDays?: number;
+  reorderLeadTimeDays?: number;
+  pricesIncludeGst?: boolean;
+  compositionScheme?: boolean;
+  loyaltySpendPerPointPaise?: number;
+  loyaltyPointValuePaise?: number;
+  idempotencyWindowHours?: number;
+  lockoutMaxAttempts?: number;
+  lockoutWindowMinutes?: number;
+  lockoutMinutes?: number;
+  printerInterface?: PrinterInterface;
+  printerAddress?: string;
+  printerLineWidth?: number;
+  autoBackupEnabled?: boolean;
+  autoBackupTime?: string;
+  autoBackupKeep?: number;
+  autoBackupDir?: string | null;
+  remoteBackupTarget?: RemoteBackupTarget;
+  remoteBackupUrl?: string;
+  remoteBackupRegion?: string;
+  remoteBackupUsername?: string;
+  maintenanceIntervalDays?: number;
+  einvoiceApiUrl?: string;
+  einvoiceClientId?: string;
+  einvoiceClientSecret?: string;
+  einvoiceUsername?: string;
+  einvoicePassword?: string;
+  ewaybillUsername?: string;
+  ewaybillPassword?: string;
+  ewaybillThresholdPaise?: number;
+};
+
+/** Off-site backup secrets being typed in; saved by the backend, never read back */
+type RemoteSecrets = {
+  secret?: string;
+  refreshToken?: string;
+  passphrase?: string;
 };
 
 export default function SettingsPage() {
   const navigate = useNavigate();
   const [settings, setSettings] = useState<PharmacySettings | null>(null);
   const [formData, setFormData] = useState<FormData>({});
+  const [remoteSecrets, setRemoteSecrets] = useState<RemoteSecrets>({});
+  const [remoteSecretsSet, setRemoteSecretsSet] = useState<RemoteBackupSecretsSet | null>(null);
   const [errors, setErrors] = useState<Record<string, string>>({});
   const [loading, setLoading] = useState(true);
   const [saving, setSaving] = useState(false);
@@ -41,6 +96,8 @@ export default function SettingsPage() {
         const data = await getSettings();
         setSettings(data);
         setFormData(data);
+        // Only users who may take backups see which secrets are saved
+        setRemoteSecretsSet(await getRemoteBackupSecrets().catch(() => null));
       } catch (err) {
         toast.error('Failed to load pharmacy settings');
         console.error(err);
@@ -56,7 +113,7 @@ export default function SettingsPage() {
     return GSTIN_REGEX.test(gstin);
   };
 
-  const handleChange = (field: keyof FormData, value: string | number | null) => {
+  const handleChange = (field: keyof FormData, value: string | number | boolean | null) => {
     setFormData((prev) => ({
       ...prev,
       [field]: value,
@@ -82,6 +139,10 @@ export default function SettingsPage() {
     }
   };
 
+  const handleSecretChange = (field: keyof RemoteSecrets, value: string) => {
+    setRemoteSecrets((prev) => ({ ...prev, [field]: value }));
+  };
+
   const validateForm = (): boolean => {
     const newErrors: Record<string, string> = {};
 
@@ -114,6 +175,28 @@ export default function SettingsPage() {
       newErrors.stateCode = 'State code must be 2 digits';
     }
 
+    if (formData.pincode?.trim() && !PINCODE_REGEX.test(formData.pincode.trim())) {
+      newErrors.pincode = 'PIN code must be 6 digits';
+    }
+
+    // Mirrors the checks made when an invoice number is allocated
+    const invoiceFormat = formData.invoiceNumberFormat ?? '';
+    if (!/\{seq(:\d+)?\}/.test(invoiceFormat)) {
+      newErrors.invoiceNumberFormat = 'The format must include {seq}';
+    } else if (formData.fyReset && !invoiceFormat.includes('{fy}')) {
+      newErrors.invoiceNumberFormat = 'The format must include {fy} when numbering restarts each year';
+    }
+
+    // The IRP rejects e-invoices without a seller location
+    if (formData.einvoiceEnabled) {
+      if (!formData.city?.trim()) {
+        newErrors.city = 'City is required for e-invoicing';
+      }
+      if (!formData.pincode?.trim()) {
+        newErrors.pincode = 'PIN code is required for e-invoicing';
+      }
+    }
+
     setErrors(newErrors);
     return Object.keys(newErrors).length === 0;
   };
@@ -129,6 +212,18 @@ export default function SettingsPage() {
     try {
       setSaving(true);
 
+      const problems = await validateRegistrations({
+        gstin: formData.gstin,
+        stateCode: formData.stateCode,
+        drugLicenseNo: formData.drugLicenseNo,
+        drugLicenseRequired: true,
+      });
+      if (problems.length > 0) {
+        setErrors(Object.fromEntries(problems.map((p) => [p.field, p.message])));
+        toast.error('Please fix the errors below');
+        return;
+      }
+
       // Only send changed fields
       const fieldsToCheck: (keyof FormData)[] = [
         'name',
@@ -138,9 +233,45 @@ export default function SettingsPage() {
         'gstin',
         'drugLicenseNo',
         'stateCode',
+        'city',
+        'pincode',
+        'einvoiceEnabled',
         'invoicePrefix',
+        'invoiceNumberFormat',
+        'fyReset',
+        'creditNotePrefix',
+        'debitNotePrefix',
         'lowStockThreshold',
         'nearExpiryDays',
+        'reorderLeadTimeDays',
+        'pricesIncludeGst',
+        'compositionScheme',
+        'loyaltySpendPerPointPaise',
+        'loyaltyPointValuePaise',
+        'idempotencyWindowHours',
+        'lockoutMaxAttempts',
+        'lockoutWindowMinutes',
+        'lockoutMinutes',
+        'printerInterface',
+        'printerAddress',
+        'printerLineWidth',
+        'autoBackupEnabled',
+        'autoBackupTime',
+        'autoBackupKeep',
+        'autoBackupDir',
+        'remoteBackupTarget',
+        'remoteBackupUrl',
+        'remoteBackupRegion',
+        'remoteBackupUsername',
+        'maintenanceIntervalDays',
+        'einvoiceApiUrl',
+        'einvoiceClientId',
+        'einvoiceClientSecret',
+        'einvoiceUsername',
+        'einvoicePassword',
+        'ewaybillUsername',
+        'ewaybillPassword',
+        'ewaybillThresholdPaise',
       ];
 
       const changedFields: Record<string, unknown> = {};
@@ -153,12 +284,27 @@ export default function SettingsPage() {
         }
       });
 
-      if (!hasChanges) {
+      // Blank secret inputs keep the saved value
+      const secretsChanged = Object.values(remoteSecrets).some((value) => value);
+
+      if (!hasChanges && !secretsChanged) {
         toast.info('No changes to save');
         return;
       }
 
-      await updateSettings(changedFields as Parameters<typeof updateSettings>[0]);
+      if (hasChanges) {
+        await updateSettings(changedFields as Parameters<typeof updateSettings>[0]);
+      }
+      if (secretsChanged) {
+        setRemoteSecretsSet(
+          await setRemoteBackupSecrets({
+            secret: remoteSecrets.secret || undefined,
+            refreshToken: remoteSecrets.refreshToken || undefined,
+            passphrase: remoteSecrets.passphrase || undefined,
+          })
+        );
+        setRemoteSecrets({});
+      }
       toast.success('Settings saved successfully');
 
       // Reload settings to confirm
@@ -193,6 +339,7 @@ export default function SettingsPage() {
           <TabsTrigger value="pharmacy">Pharmacy Details</TabsTrigger>
           <TabsTrigger value="users">User Management</TabsTrigger>
           <TabsTrigger value="backup">Backup & Restore</TabsTrigger>
+          <TabsTrigger value="jobs">Scheduled Jobs</TabsTrigger>
           <TabsTrigger value="about">About</TabsTrigger>
         </TabsList>
 
@@ -245,6 +392,34 @@ export default function SettingsPage() {
                   {errors.address && <p className="text-sm text-red-600">{errors.address}</p>}
                 </div>
 
+                {/* City and PIN Code */}
+                <div className="grid grid-cols-1 md:grid-cols-2 gap-6">
+                  <div className="space-y-2">
+                    <Label htmlFor="city">City</Label>
+                    <Input
+                      id="city"
+                      value={formData.city || ''}
+                      onChange={(e) => handleChange('city', e.target.value)}
+                      placeholder="E.g., Bengaluru"
+                      aria-invalid={!!errors.city}
+                    />
+                    {errors.city && <p className="text-sm text-red-600">{errors.city}</p>}
+                  </div>
+
+                  <div className="space-y-2">
+                    <Label htmlFor="pincode">PIN Code</Label>
+                    <Input
+                      id="pincode"
+                      value={formData.pincode || ''}
+                      onChange={(e) => handleChange('pincode', e.target.value)}
+                      placeholder="E.g., 560001"
+                      maxLength={6}
+                      aria-invalid={!!errors.pincode}
+                    />
+                    {errors.pincode && <p className="text-sm text-red-600">{errors.pincode}</p>}
+                  </div>
+                </div>
+
                 {/* Email */}
                 <div className="space-y-2">
                   <Label htmlFor="email">Email</Label>
@@ -313,6 +488,65 @@ export default function SettingsPage() {
                   </div>
                 </div>
 
+                {/* Invoice numbering */}
+                <div className="grid grid-cols-1 md:grid-cols-2 gap-6">
+                  <div className="space-y-2">
+                    <Label htmlFor="invoiceNumberFormat">Invoice Number Format</Label>
+                    <Input
+                      id="invoiceNumberFormat"
+                      value={formData.invoiceNumberFormat || ''}
+                      onChange={(e) => handleChange('invoiceNumberFormat', e.target.value)}
+                      placeholder="E.g., {prefix}/{fy}/{seq}"
+                      aria-invalid={!!errors.invoiceNumberFormat}
+                    />
+                    {errors.invoiceNumberFormat && (
+                      <p className="text-sm text-red-600">{errors.invoiceNumberFormat}</p>
+                    )}
+                    <p className="text-sm text-slate-600">
+                      {'{prefix}'} is the invoice prefix, {'{fy}'} the financial year (2024-25) and {'{seq}'} the
+                      sequence, padded to 4 digits or to N with {'{seq:N}'}.
+                    </p>
+                  </div>
+
+                  <div className="flex items-start gap-3 md:pt-8">
+                    <input
+                      id="fyReset"
+                      type="checkbox"
+                      checked={!!formData.fyReset}
+                      onChange={(e) => handleChange('fyReset', e.target.checked)}
+                      className="mt-1 h-4 w-4"
+                    />
+                    <div>
+                      <Label htmlFor="fyReset">Restart numbering each financial year</Label>
+                      <p className="text-sm text-slate-600">The format must include {'{fy}'} to keep numbers unique.</p>
+                    </div>
+                  </div>
+
+                  <div className="space-y-2">
+                    <Label htmlFor="creditNotePrefix">Credit Note Prefix</Label>
+                    <Input
+                      id="creditNotePrefix"
+                      value={formData.creditNotePrefix || ''}
+                      onChange={(e) => handleChange('creditNotePrefix', e.target.value.toUpperCase())}
+                      placeholder="E.g., CN"
+                    />
+                    <p className="text-sm text-slate-600">
+                      Customer returns are numbered in the invoice format with this prefix, in a series of their own.
+                    </p>
+                  </div>
+
+                  <div className="space-y-2">
+                    <Label htmlFor="debitNotePrefix">Debit Note Prefix</Label>
+                    <Input
+                      id="debitNotePrefix"
+                      value={formData.debitNotePrefix || ''}
+                      onChange={(e) => handleChange('debitNotePrefix', e.target.value.toUpperCase())}
+                      placeholder="E.g., DN"
+                    />
+                    <p className="text-sm text-slate-600">Used for stock returned to suppliers.</p>
+                  </div>
+                </div>
+
                 {/* Stock and Expiry Thresholds */}
                 <div className="grid grid-cols-1 md:grid-cols-2 gap-6">
                   <div className="space-y-2">
@@ -342,6 +576,463 @@ export default function SettingsPage() {
                   </div>
                 </div>
 
+                {/* Reorder Lead Time */}
+                <div className="grid grid-cols-1 md:grid-cols-2 gap-6">
+                  <div className="space-y-2">
+                    <Label htmlFor="reorderLeadTimeDays">Supplier Lead Time (days)</Label>
+                    <Input
+                      id="reorderLeadTimeDays"
+                      type="number"
+                      value={formData.reorderLeadTimeDays || 7}
+                      onChange={(e) => handleChange('reorderLeadTimeDays', parseInt(e.target.value, 10))}
+                      min="1"
+                    />
+                    <p className="text-sm text-slate-600">Used when suggesting reorder levels from sales history.</p>
+                  </div>
+
+                  <div className="space-y-2">
+                    <Label htmlFor="idempotencyWindowHours">Checkout Retry Window (hours)</Label>
+                    <Input
+                      id="idempotencyWindowHours"
+                      type="number"
+                      value={formData.idempotencyWindowHours || 24}
+                      onChange={(e) => handleChange('idempotencyWindowHours', parseInt(e.target.value, 10))}
+                      min="1"
+                    />
+                    <p className="text-sm text-slate-600">How long a retried checkout returns the sale it already recorded.</p>
+                  </div>
+                </div>
+
+                {/* Login lockout */}
+                <div className="grid grid-cols-1 md:grid-cols-3 gap-6">
+                  <div className="space-y-2">
+                    <Label htmlFor="lockoutMaxAttempts">Failed Logins Before Lockout</Label>
+                    <Input
+                      id="lockoutMaxAttempts"
+                      type="number"
+                      value={formData.lockoutMaxAttempts || 5}
+                      onChange={(e) => handleChange('lockoutMaxAttempts', parseInt(e.target.value, 10))}
+                      min="1"
+                    />
+                  </div>
+
+                  <div className="space-y-2">
+                    <Label htmlFor="lockoutWindowMinutes">Counted Over (minutes)</Label>
+                    <Input
+                      id="lockoutWindowMinutes"
+                      type="number"
+                      value={formData.lockoutWindowMinutes || 15}
+                      onChange={(e) => handleChange('lockoutWindowMinutes', parseInt(e.target.value, 10))}
+                      min="1"
+                    />
+                  </div>
+
+                  <div className="space-y-2">
+                    <Label htmlFor="lockoutMinutes">Lockout Duration (minutes)</Label>
+                    <Input
+                      id="lockoutMinutes"
+                      type="number"
+                      value={formData.lockoutMinutes || 30}
+                      onChange={(e) => handleChange('lockoutMinutes', parseInt(e.target.value, 10))}
+                      min="1"
+                    />
+                  </div>
+                </div>
+
+                {/* Receipt printer */}
+                <div className="grid grid-cols-1 md:grid-cols-3 gap-6">
+                  <div className="space-y-2">
+                    <Label htmlFor="printerInterface">Receipt Printer</Label>
+                    <select
+                      id="printerInterface"
+                      value={formData.printerInterface || 'none'}
+                      onChange={(e) => handleChange('printerInterface', e.target.value as PrinterInterface)}
+                      className="h-9 w-full rounded-md border border-slate-200 bg-white px-3 text-sm"
+                    >
+                      <option value="none">None (browser print)</option>
+                      <option value="usb">USB</option>
+                      <option value="serial">Serial</option>
+                      <option value="network">Network</option>
+                    </select>
+                  </div>
+
+                  <div className="space-y-2">
+                    <Label htmlFor="printerAddress">Printer Address</Label>
+                    <Input
+                      id="printerAddress"
+                      value={formData.printerAddress || ''}
+                      onChange={(e) => handleChange('printerAddress', e.target.value)}
+                      placeholder="E.g., /dev/usb/lp0, COM3 or 192.168.1.50:9100"
+                      disabled={(formData.printerInterface || 'none') === 'none'}
+                    />
+                  </div>
+
+                  <div className="space-y-2">
+                    <Label htmlFor="printerLineWidth">Characters per Line</Label>
+                    <Input
+                      id="printerLineWidth"
+                      type="number"
+                      value={formData.printerLineWidth || 48}
+                      onChange={(e) => handleChange('printerLineWidth', parseInt(e.target.value, 10))}
+                      min="24"
+                      max="64"
+                    />
+                    <p className="text-sm text-slate-600">32 for 58 mm paper, 48 for 80 mm.</p>
+                  </div>
+                </div>
+
+                {/* Scheduled backups */}
+                <div className="grid grid-cols-1 md:grid-cols-3 gap-6">
+                  <div className="flex items-start gap-3 md:pt-8">
+                    <input
+                      id="autoBackupEnabled"
+                      type="checkbox"
+                      checked={!!formData.autoBackupEnabled}
+                      onChange={(e) => handleChange('autoBackupEnabled', e.target.checked)}
+                      className="mt-1 h-4 w-4"
+                    />
+                    <div>
+                      <Label htmlFor="autoBackupEnabled">Daily automatic backup</Label>
+                      <p className="text-sm text-slate-600">Missed days are caught up at the next launch.</p>
+                    </div>
+                  </div>
+
+                  <div className="space-y-2">
+                    <Label htmlFor="autoBackupTime">Backup Time</Label>
+                    <Input
+                      id="autoBackupTime"
+                      type="time"
+                      value={formData.autoBackupTime || '21:00'}
+                      onChange={(e) => handleChange('autoBackupTime', e.target.value)}
+                      disabled={!formData.autoBackupEnabled}
+                    />
+                  </div>
+
+                  <div className="space-y-2">
+                    <Label htmlFor="autoBackupKeep">Backups to Keep</Label>
+                    <Input
+                      id="autoBackupKeep"
+                      type="number"
+                      value={formData.autoBackupKeep || 14}
+                      onChange={(e) => handleChange('autoBackupKeep', parseInt(e.target.value, 10))}
+                      min="1"
+                      max="365"
+                    />
+                  </div>
+
+                  <div className="space-y-2">
+                    <Label htmlFor="maintenanceIntervalDays">Maintenance Every (days)</Label>
+                    <Input
+                      id="maintenanceIntervalDays"
+                      type="number"
+                      value={formData.maintenanceIntervalDays ?? 7}
+                      onChange={(e) => handleChange('maintenanceIntervalDays', parseInt(e.target.value, 10))}
+                      min="0"
+                      max="365"
+                    />
+                    <p className="text-sm text-slate-600">Integrity check and compaction after the nightly backup; 0 turns it off.</p>
+                  </div>
+
+                  <div className="space-y-2 md:col-span-2">
+                    <Label htmlFor="autoBackupDir">Backup Folder</Label>
+                    <Input
+                      id="autoBackupDir"
+                      value={formData.autoBackupDir || ''}
+                      onChange={(e) => handleChange('autoBackupDir', e.target.value || null)}
+                      placeholder="Leave blank to keep backups beside the database"
+                    />
+                  </div>
+                </div>
+
+                {/* Off-site backups */}
+                <div className="grid grid-cols-1 md:grid-cols-3 gap-6">
+                  <div className="space-y-2">
+                    <Label htmlFor="remoteBackupTarget">Off-site Backup</Label>
+                    <select
+                      id="remoteBackupTarget"
+                      value={formData.remoteBackupTarget || 'none'}
+                      onChange={(e) => handleChange('remoteBackupTarget', e.target.value as RemoteBackupTarget)}
+                      className="h-9 w-full rounded-md border border-slate-200 bg-white px-3 text-sm"
+                    >
+                      <option value="none">None</option>
+                      <option value="s3">S3-compatible storage</option>
+                      <option value="gdrive">Google Drive</option>
+                      <option value="webdav">WebDAV</option>
+                    </select>
+                    <p className="text-sm text-slate-600">Needs curl, which ships with Windows 10 and later.</p>
+                  </div>
+
+                  {(formData.remoteBackupTarget || 'none') !== 'none' && (
+                    <>
+                      <div className="space-y-2 md:col-span-2">
+                        <Label htmlFor="remoteBackupUrl">
+                          {formData.remoteBackupTarget === 'gdrive' ? 'Folder ID (optional)' : 'URL'}
+                        </Label>
+                        <Input
+                          id="remoteBackupUrl"
+                          value={formData.remoteBackupUrl || ''}
+                          onChange={(e) => handleChange('remoteBackupUrl', e.target.value)}
+                          placeholder={
+                            formData.remoteBackupTarget === 's3'
+                              ? 'E.g., https://s3.ap-south-1.amazonaws.com/my-bucket'
+                              : formData.remoteBackupTarget === 'webdav'
+                                ? 'E.g., https://cloud.example.com/remote.php/dav/files/me/backups'
+                                : ''
+                          }
+                        />
+                      </div>
+
+                      {formData.remoteBackupTarget === 's3' && (
+                        <div className="space-y-2">
+                          <Label htmlFor="remoteBackupRegion">Region</Label>
+                          <Input
+                            id="remoteBackupRegion"
+                            value={formData.remoteBackupRegion || ''}
+                            onChange={(e) => handleChange('remoteBackupRegion', e.target.value)}
+                            placeholder="E.g., ap-south-1"
+                          />
+                        </div>
+                      )}
+
+                      <div className="space-y-2">
+                        <Label htmlFor="remoteBackupUsername">
+                          {formData.remoteBackupTarget === 's3'
+                            ? 'Access Key ID'
+                            : formData.remoteBackupTarget === 'gdrive'
+                              ? 'OAuth Client ID'
+                              : 'Username'}
+                        </Label>
+                        <Input
+                          id="remoteBackupUsername"
+                          value={formData.remoteBackupUsername || ''}
+                          onChange={(e) => handleChange('remoteBackupUsername', e.target.value)}
+                          autoComplete="off"
+                        />
+                      </div>
+
+                      <div className="space-y-2">
+                        <Label htmlFor="remoteBackupSecret">
+                          {formData.remoteBackupTarget === 's3'
+                            ? 'Secret Access Key'
+                            : formData.remoteBackupTarget === 'gdrive'
+                              ? 'OAuth Client Secret'
+                              : 'Password'}
+                        </Label>
+                        <Input
+                          id="remoteBackupSecret"
+                          type="password"
+                          value={remoteSecrets.secret || ''}
+                          onChange={(e) => handleSecretChange('secret', e.target.value)}
+                          placeholder={remoteSecretsSet?.secret ? 'Saved; leave blank to keep' : ''}
+                          autoComplete="off"
+                        />
+                      </div>
+
+                      {formData.remoteBackupTarget === 'gdrive' && (
+                        <div className="space-y-2">
+                          <Label htmlFor="remoteBackupRefreshToken">OAuth Refresh Token</Label>
+                          <Input
+                            id="remoteBackupRefreshToken"
+                            type="password"
+                            value={remoteSecrets.refreshToken || ''}
+                            onChange={(e) => handleSecretChange('refreshToken', e.target.value)}
+                            placeholder={remoteSecretsSet?.refreshToken ? 'Saved; leave blank to keep' : ''}
+                            autoComplete="off"
+                          />
+                        </div>
+                      )}
+
+                      <div className="space-y-2">
+                        <Label htmlFor="remoteBackupPassphrase">Archive Passphrase</Label>
+                        <Input
+                          id="remoteBackupPassphrase"
+                          type="password"
+                          value={remoteSecrets.passphrase || ''}
+                          onChange={(e) => handleSecretChange('passphrase', e.target.value)}
+                          placeholder={remoteSecretsSet?.passphrase ? 'Saved; leave blank to keep' : ''}
+                          autoComplete="new-password"
+                        />
+                        <p className="text-sm text-slate-600">Needed to restore; keep a copy away from this PC.</p>
+                      </div>
+                    </>
+                  )}
+                </div>
+
+                {/* Loyalty points */}
+                <div className="grid grid-cols-1 md:grid-cols-2 gap-6">
+                  <div className="space-y-2">
+                    <Label htmlFor="loyaltySpendPerPoint">Spend per Loyalty Point (₹)</Label>
+                    <Input
+                      id="loyaltySpendPerPoint"
+                      type="number"
+                      value={(formData.loyaltySpendPerPointPaise ?? 10000) / 100}
+                      onChange={(e) =>
+                        handleChange('loyaltySpendPerPointPaise', Math.round((parseFloat(e.target.value) || 0) * 100))
+                      }
+                      min="0"
+                      step="0.01"
+                    />
+                    <p className="text-sm text-slate-600">Set to 0 to stop customers earning points.</p>
+                  </div>
+
+                  <div className="space-y-2">
+                    <Label htmlFor="loyaltyPointValue">Value of a Point (₹)</Label>
+                    <Input
+                      id="loyaltyPointValue"
+                      type="number"
+                      value={(formData.loyaltyPointValuePaise ?? 100) / 100}
+                      onChange={(e) =>
+                        handleChange('loyaltyPointValuePaise', Math.round((parseFloat(e.target.value) || 0) * 100))
+                      }
+                      min="0"
+                      step="0.01"
+                    />
+                    <p className="text-sm text-slate-600">Set to 0 to stop points being redeemed.</p>
+                  </div>
+                </div>
+
+                {/* Tax-inclusive pricing */}
+                <div className="flex items-start gap-3">
+                  <input
+                    id="pricesIncludeGst"
+                    type="checkbox"
+                    checked={!!formData.pricesIncludeGst}
+                    onChange={(e) => handleChange('pricesIncludeGst', e.target.checked)}
+                    className="mt-1 h-4 w-4"
+                  />
+                  <div>
+                    <Label htmlFor="pricesIncludeGst">Selling prices include GST</Label>
+                    <p className="text-sm text-slate-600">
+                      When off, GST is added on top of the selling price at checkout.
+                    </p>
+                  </div>
+                </div>
+
+                {/* Composition scheme */}
+                <div className="flex items-start gap-3">
+                  <input
+                    id="compositionScheme"
+                    type="checkbox"
+                    checked={!!formData.compositionScheme}
+                    onChange={(e) => handleChange('compositionScheme', e.target.checked)}
+                    className="mt-1 h-4 w-4"
+                  />
+                  <div>
+                    <Label htmlFor="compositionScheme">Composition scheme</Label>
+                    <p className="text-sm text-slate-600">
+                      Issue bills of supply without tax. Purchases still record the GST paid;
+                      inter-state sales are refused.
+                    </p>
+                  </div>
+                </div>
+
+                {/* E-invoicing */}
+                <div className="flex items-start gap-3">
+                  <input
+                    id="einvoiceEnabled"
+                    type="checkbox"
+                    checked={!!formData.einvoiceEnabled}
+                    onChange={(e) => handleChange('einvoiceEnabled', e.target.checked)}
+                    className="mt-1 h-4 w-4"
+                  />
+                  <div>
+                    <Label htmlFor="einvoiceEnabled">Enable e-invoicing</Label>
+                    <p className="text-sm text-slate-600">
+                      Export B2B invoices as GST e-invoice JSON (schema 1.1) for upload to the IRP.
+                    </p>
+                  </div>
+                </div>
+
+                <div className="grid grid-cols-1 md:grid-cols-2 gap-6">
+                  <div className="space-y-2">
+                    <Label htmlFor="einvoiceApiUrl">GSP API URL</Label>
+                    <Input
+                      id="einvoiceApiUrl"
+                      type="url"
+                      value={formData.einvoiceApiUrl || ''}
+                      onChange={(e) => handleChange('einvoiceApiUrl', e.target.value)}
+                      placeholder="https://gsp.example.com"
+                      autoComplete="off"
+                    />
+                  </div>
+                  <div className="space-y-2">
+                    <Label htmlFor="einvoiceClientId">Client ID</Label>
+                    <Input
+                      id="einvoiceClientId"
+                      type="text"
+                      value={formData.einvoiceClientId || ''}
+                      onChange={(e) => handleChange('einvoiceClientId', e.target.value)}
+                      autoComplete="off"
+                    />
+                  </div>
+                  <div className="space-y-2">
+                    <Label htmlFor="einvoiceClientSecret">Client Secret</Label>
+                    <Input
+                      id="einvoiceClientSecret"
+                      type="password"
+                      value={formData.einvoiceClientSecret || ''}
+                      onChange={(e) => handleChange('einvoiceClientSecret', e.target.value)}
+                      autoComplete="off"
+                    />
+                  </div>
+                  <div className="space-y-2">
+                    <Label htmlFor="einvoiceUsername">API Username</Label>
+                    <Input
+                      id="einvoiceUsername"
+                      type="text"
+                      value={formData.einvoiceUsername || ''}
+                      onChange={(e) => handleChange('einvoiceUsername', e.target.value)}
+                      autoComplete="off"
+                    />
+                  </div>
+                  <div className="space-y-2">
+                    <Label htmlFor="einvoicePassword">API Password</Label>
+                    <Input
+                      id="einvoicePassword"
+                      type="password"
+                      value={formData.einvoicePassword || ''}
+                      onChange={(e) => handleChange('einvoicePassword', e.target.value)}
+                      autoComplete="new-password"
+                    />
+                  </div>
+                  <div className="space-y-2">
+                    <Label htmlFor="ewaybillUsername">E-way Bill Username</Label>
+                    <Input
+                      id="ewaybillUsername"
+                      type="text"
+                      value={formData.ewaybillUsername || ''}
+                      onChange={(e) => handleChange('ewaybillUsername', e.target.value)}
+                      autoComplete="off"
+                    />
+                  </div>
+                  <div className="space-y-2">
+                    <Label htmlFor="ewaybillPassword">E-way Bill Password</Label>
+                    <Input
+                      id="ewaybillPassword"
+                      type="password"
+                      value={formData.ewaybillPassword || ''}
+                      onChange={(e) => handleChange('ewaybillPassword', e.target.value)}
+                      autoComplete="new-password"
+                    />
+                  </div>
+                  <div className="space-y-2">
+                    <Label htmlFor="ewaybillThreshold">E-way Bill Above (₹)</Label>
+                    <Input
+                      id="ewaybillThreshold"
+                      type="number"
+                      value={(formData.ewaybillThresholdPaise ?? 5000000) / 100}
+                      onChange={(e) =>
+                        handleChange('ewaybillThresholdPaise', Math.round((parseFloat(e.target.value) || 0) * 100))
+                      }
+                      min="0"
+                      step="1"
+                    />
+                  </div>
+                  <p className="text-sm text-slate-600 md:col-span-2">
+                    Credentials from your GST Suvidha Provider, used to generate IRNs and e-way bills from the invoice page.
+                  </p>
+                </div>
+
                 {/* Submit Button */}
                 <div className="pt-4">
                   <Button type="submit" disabled={saving} className="w-full md:w-auto">
@@ -370,6 +1061,11 @@ export default function SettingsPage() {
 
         <TabsContent value="backup">
           <BackupRestore />
+          <DatabaseEncryption />
+        </TabsContent>
+
+        <TabsContent value="jobs">
+          <ScheduledJobs />
         </TabsContent>
 
         <TabsContent value="about">
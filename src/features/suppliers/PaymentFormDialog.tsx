@@ -2,8 +2,9 @@ import { useState, useEffect } from "react";
 import { toast } from "sonner";
 import type { PaymentMode } from "@/types";
 import type { SupplierPaymentFormData } from "@/types/forms";
-import { createSupplierPayment } from "@/db/queries/suppliers";
-import { rupeesToPaise } from "@/lib/currency";
+import { useAuth } from "@/features/auth/AuthContext";
+import { createSupplierPayment, type OpenPurchase } from "@/db/queries/suppliers";
+import { formatPaiseToCurrency, rupeesToPaise } from "@/lib/currency";
 import { Button } from "@/components/ui/button";
 import { Input } from "@/components/ui/input";
 import { Label } from "@/components/ui/label";
@@ -27,6 +28,8 @@ interface PaymentFormDialogProps {
   open: boolean;
   onOpenChange: (open: boolean) => void;
   supplierId: number;
+  /** Invoices the payment may be set against */
+  openInvoices: OpenPurchase[];
   onSaved: () => void;
 }
 
@@ -42,6 +45,9 @@ const PAYMENT_MODE_OPTIONS: { value: PaymentMode; label: string }[] = [
   { value: "credit", label: "NEFT" },
 ];
 
+/** Select value that settles the oldest invoices first */
+const OLDEST_FIRST = "oldest";
+
 function todayIso(): string {
   return new Date().toISOString().split("T")[0];
 }
@@ -59,12 +65,15 @@ export default function PaymentFormDialog({
   open,
   onOpenChange,
   supplierId,
+  openInvoices,
   onSaved,
 }: PaymentFormDialogProps) {
+  const { user } = useAuth();
   const [form, setForm] = useState<SupplierPaymentFormData>({
     ...DEFAULT_FORM,
     supplierId,
   });
+  const [against, setAgainst] = useState(OLDEST_FIRST);
   const [errors, setErrors] = useState<FormErrors>({});
   const [saving, setSaving] = useState(false);
 
@@ -72,6 +81,7 @@ export default function PaymentFormDialog({
   useEffect(() => {
     if (open) {
       setForm({ ...DEFAULT_FORM, supplierId, paymentDate: todayIso() });
+      setAgainst(OLDEST_FIRST);
       setErrors({});
     }
   }, [open, supplierId]);
@@ -96,24 +106,30 @@ export default function PaymentFormDialog({
 
   async function handleSubmit(e: React.FormEvent) {
     e.preventDefault();
-    if (!validate()) return;
+    if (!user || !validate()) return;
 
+    const amountPaise = rupeesToPaise(form.amount);
+    const invoice = openInvoices.find((i) => String(i.purchaseId) === against);
     try {
       setSaving(true);
       await createSupplierPayment({
         supplierId,
-        amountPaise: rupeesToPaise(form.amount),
+        amountPaise,
         paymentDate: form.paymentDate,
         paymentMode: form.paymentMode,
         reference: form.reference.trim() || null,
         notes: form.notes.trim() || null,
+        // Anything beyond what the invoice owes settles the oldest ones
+        allocations: invoice
+          ? [{ purchaseId: invoice.purchaseId, amountPaise: Math.min(amountPaise, invoice.duePaise) }]
+          : [],
       });
       toast.success("Payment recorded");
       onOpenChange(false);
       onSaved();
     } catch (err) {
       console.error(err);
-      toast.error("Failed to record payment");
+      toast.error(String(err));
     } finally {
       setSaving(false);
     }
@@ -204,6 +220,26 @@ export default function PaymentFormDialog({
             </Select>
           </div>
 
+          {/* Invoice the payment is against */}
+          {openInvoices.length > 0 && (
+            <div className="space-y-1.5">
+              <Label htmlFor="pay-against">Against Invoice</Label>
+              <Select value={against} onValueChange={setAgainst}>
+                <SelectTrigger id="pay-against">
+                  <SelectValue />
+                </SelectTrigger>
+                <SelectContent>
+                  <SelectItem value={OLDEST_FIRST}>Oldest invoices first</SelectItem>
+                  {openInvoices.map((invoice) => (
+                    <SelectItem key={invoice.purchaseId} value={String(invoice.purchaseId)}>
+                      {invoice.invoiceNumber} ({formatPaiseToCurrency(invoice.duePaise)} due)
+                    </SelectItem>
+                  ))}
+                </SelectContent>
+              </Select>
+            </div>
+          )}
+
           {/* Reference / Transaction ID */}
           <div className="space-y-1.5">
             <Label htmlFor="pay-reference">Reference / Transaction ID</Label>
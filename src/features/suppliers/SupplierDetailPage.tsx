@@ -1,11 +1,14 @@
 import { useState, useEffect, useCallback } from "react";
 import { useParams, useNavigate } from "react-router-dom";
+import { useDataChanged } from "@/hooks/useDataChanged";
 import { toast } from "sonner";
 import { ArrowLeftIcon, PlusIcon, TruckIcon } from "lucide-react";
 import type { Supplier, SupplierPayment } from "@/types";
 import {
   getSupplierById,
+  getSupplierPayables,
   getSupplierPayments,
+  type SupplierPayables,
 } from "@/db/queries/suppliers";
 import { formatPaiseToCurrency } from "@/lib/currency";
 import { Button } from "@/components/ui/button";
@@ -48,6 +51,7 @@ export default function SupplierDetailPage() {
 
   const [supplier, setSupplier] = useState<Supplier | null>(null);
   const [payments, setPayments] = useState<SupplierPayment[]>([]);
+  const [payables, setPayables] = useState<SupplierPayables | null>(null);
   const [loading, setLoading] = useState(true);
   const [notFound, setNotFound] = useState(false);
   const [paymentDialogOpen, setPaymentDialogOpen] = useState(false);
@@ -63,9 +67,10 @@ export default function SupplierDetailPage() {
 
     try {
       setLoading(true);
-      const [sup, pays] = await Promise.all([
+      const [sup, pays, owed] = await Promise.all([
         getSupplierById(supplierId),
         getSupplierPayments(supplierId),
+        getSupplierPayables(supplierId),
       ]);
 
       if (!sup) {
@@ -73,6 +78,7 @@ export default function SupplierDetailPage() {
       } else {
         setSupplier(sup);
         setPayments(pays);
+        setPayables(owed);
       }
     } catch (err) {
       console.error(err);
@@ -86,6 +92,9 @@ export default function SupplierDetailPage() {
     fetchData();
   }, [fetchData]);
 
+  // Payments, goods receipts and debit notes recorded by backend commands
+  useDataChanged(["supplier_payment", "purchase", "debit_note"], fetchData);
+
   const totalPayments = payments.reduce((sum, p) => sum + p.amountPaise, 0);
 
   if (loading) {
@@ -214,6 +223,62 @@ export default function SupplierDetailPage() {
         </Card>
       </div>
 
+      {/* Open invoices, aged from the purchase date */}
+      {payables && (
+        <div>
+          <h2 className="text-lg font-semibold text-slate-900 mb-3">Payables</h2>
+          <div className="grid grid-cols-2 md:grid-cols-4 gap-4 mb-3">
+            {[
+              ["Outstanding", payables.outstandingPaise],
+              ["0–30 days", payables.currentPaise],
+              ["31–60 days", payables.days31To60Paise],
+              ["Over 60 days", payables.over60DaysPaise],
+            ].map(([label, paise]) => (
+              <Card key={label} className="p-4">
+                <p className="text-xs text-muted-foreground">{label}</p>
+                <p className="text-lg font-semibold tabular-nums text-slate-900">
+                  {formatPaiseToCurrency(Number(paise))}
+                </p>
+              </Card>
+            ))}
+          </div>
+          {payables.invoices.length > 0 && (
+            <div className="rounded-lg border bg-card shadow-sm">
+              <Table>
+                <TableHeader>
+                  <TableRow>
+                    <TableHead>Date</TableHead>
+                    <TableHead>Invoice #</TableHead>
+                    <TableHead className="text-right">Age (days)</TableHead>
+                    <TableHead className="text-right">Total (₹)</TableHead>
+                    <TableHead className="text-right">Due (₹)</TableHead>
+                  </TableRow>
+                </TableHeader>
+                <TableBody>
+                  {payables.invoices.map((invoice) => (
+                    <TableRow key={invoice.purchaseId}>
+                      <TableCell className="text-sm text-slate-600">
+                        {formatDate(invoice.purchaseDate)}
+                      </TableCell>
+                      <TableCell className="font-mono text-sm">{invoice.invoiceNumber}</TableCell>
+                      <TableCell className="text-right tabular-nums text-sm">
+                        {invoice.ageDays}
+                      </TableCell>
+                      <TableCell className="text-right tabular-nums text-sm">
+                        {formatPaiseToCurrency(invoice.totalPaise)}
+                      </TableCell>
+                      <TableCell className="text-right tabular-nums text-sm font-medium">
+                        {formatPaiseToCurrency(invoice.duePaise)}
+                      </TableCell>
+                    </TableRow>
+                  ))}
+                </TableBody>
+              </Table>
+            </div>
+          )}
+        </div>
+      )}
+
       {/* Payment history */}
       <div>
         <h2 className="text-lg font-semibold text-slate-900 mb-3">
@@ -276,6 +341,7 @@ export default function SupplierDetailPage() {
         open={paymentDialogOpen}
         onOpenChange={setPaymentDialogOpen}
         supplierId={supplier.id}
+        openInvoices={payables?.invoices ?? []}
         onSaved={fetchData}
       />
     </div>
@@ -2,6 +2,7 @@ import { useState, useEffect } from "react";
 import { toast } from "sonner";
 import type { Supplier } from "@/types";
 import { createSupplier, updateSupplier } from "@/db/queries/suppliers";
+import { lookupGstin, validateRegistrations } from "@/db/queries/registrations";
 import { Button } from "@/components/ui/button";
 import { Input } from "@/components/ui/input";
 import { Label } from "@/components/ui/label";
@@ -34,6 +35,7 @@ interface FormErrors {
   name?: string;
   email?: string;
   gstIn?: string;
+  drugLicenseNo?: string;
 }
 
 const GSTIN_REGEX = /^[0-9]{2}[A-Z]{5}[0-9]{4}[A-Z]{1}[1-9A-Z]{1}Z[0-9A-Z]{1}$/;
@@ -57,6 +59,7 @@ export default function SupplierFormDialog({
   const [form, setForm] = useState<FormState>(DEFAULT_FORM);
   const [errors, setErrors] = useState<FormErrors>({});
   const [saving, setSaving] = useState(false);
+  const [lookingUp, setLookingUp] = useState(false);
 
   // Sync form when dialog opens or supplier changes
   useEffect(() => {
@@ -104,12 +107,25 @@ export default function SupplierFormDialog({
     try {
       setSaving(true);
 
+      const problems = await validateRegistrations({
+        gstin: form.gstIn,
+        drugLicenseNo: form.drugLicenseNo,
+      });
+      if (problems.length > 0) {
+        setErrors(
+          Object.fromEntries(
+            problems.map((p) => [p.field === "gstin" ? "gstIn" : p.field, p.message])
+          )
+        );
+        return;
+      }
+
       const payload = {
         name: form.name.trim(),
         phone: form.phone.trim() || null,
         email: form.email.trim() || null,
         address: form.address.trim() || null,
-        gstIn: form.gstIn.trim() || null,
+        gstIn: form.gstIn.trim().toUpperCase() || null,
         drugLicenseNo: form.drugLicenseNo.trim() || null,
       };
 
@@ -133,6 +149,27 @@ export default function SupplierFormDialog({
     }
   }
 
+  async function handleLookup() {
+    try {
+      setLookingUp(true);
+      const found = await lookupGstin(form.gstIn);
+      setForm((prev) => ({
+        ...prev,
+        name: prev.name.trim() ? prev.name : found.legalName,
+        address: prev.address.trim() ? prev.address : found.address,
+      }));
+      if (found.status !== "ACT") {
+        toast.warning(`GSTIN ${found.gstin} is not active (status ${found.status})`);
+      } else {
+        toast.success(`Found ${found.legalName}`);
+      }
+    } catch (err) {
+      toast.error(String(err));
+    } finally {
+      setLookingUp(false);
+    }
+  }
+
   function handleFieldChange<K extends keyof FormState>(
     field: K,
     value: FormState[K]
@@ -1,5 +1,4 @@
 import { useState, useEffect } from "react";
-import bcrypt from "bcryptjs";
 import { toast } from "sonner";
 import type { User, UserRole } from "@/types";
 import { createUser, updateUser } from "@/db/queries/users";
@@ -101,11 +100,11 @@ export default function UserFormDialog({
     if (!isEditMode) {
       if (!form.password) {
         newErrors.password = "Password is required";
-      } else if (form.password.length < 6) {
-        newErrors.password = "Password must be at least 6 characters";
+      } else if (form.password.length < 8) {
+        newErrors.password = "Password must be at least 8 characters";
       }
-    } else if (form.password && form.password.length < 6) {
-      newErrors.password = "Password must be at least 6 characters";
+    } else if (form.password && form.password.length < 8) {
+      newErrors.password = "Password must be at least 8 characters";
     }
 
     setErrors(newErrors);
@@ -121,28 +120,17 @@ export default function UserFormDialog({
       setSaving(true);
 
       if (isEditMode && user) {
-        const payload: {
-          fullName?: string;
-          role?: UserRole;
-          isActive?: boolean;
-          passwordHash?: string;
-        } = {
+        await updateUser(user.id, {
           fullName: form.fullName.trim(),
           role: form.role,
           isActive: form.isActive,
-        };
-
-        if (form.password) {
-          payload.passwordHash = await bcrypt.hash(form.password, 10);
-        }
-
-        await updateUser(user.id, payload);
+          password: form.password || undefined,
+        });
         toast.success("User updated successfully");
       } else {
-        const passwordHash = await bcrypt.hash(form.password, 10);
         await createUser(
           form.username.trim(),
-          passwordHash,
+          form.password,
           form.fullName.trim(),
           form.role
         );
@@ -154,7 +142,11 @@ export default function UserFormDialog({
     } catch (err) {
       console.error(err);
       toast.error(
-        isEditMode ? "Failed to update user" : "Failed to create user"
+        typeof err === "string"
+          ? err
+          : isEditMode
+            ? "Failed to update user"
+            : "Failed to create user"
       );
     } finally {
       setSaving(false);
@@ -238,7 +230,7 @@ export default function UserFormDialog({
               value={form.password}
               onChange={(e) => handleFieldChange("password", e.target.value)}
               placeholder={
-                isEditMode ? "Leave blank to keep current" : "Min. 6 characters"
+                isEditMode ? "Leave blank to keep current" : "Min. 8 characters"
               }
               aria-invalid={!!errors.password}
               autoComplete="new-password"
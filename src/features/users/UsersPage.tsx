@@ -58,7 +58,11 @@ export default function UsersPage() {
   async function handleToggleActive(user: User) {
     setTogglingId(user.id);
     try {
-      await updateUser(user.id, { isActive: !user.isActive });
+      await updateUser(user.id, {
+        fullName: user.fullName,
+        role: user.role,
+        isActive: !user.isActive,
+      });
       toast.success(
         user.isActive
           ? `${user.fullName} has been deactivated`
@@ -0,0 +1,29 @@
+import { useEffect, useRef } from 'react';
+import { listen } from '@tauri-apps/api/event';
+import type { DataChangedEvent, DataEntity } from '@/types';
+
+/**
+ * Subscribe to backend `data-changed` events.
+ * Backend commands emit one event per changed row after their transaction commits,
+ * so views can refresh only when an entity they display actually changed.
+ */
+export function useDataChanged(
+  entities: DataEntity[],
+  onChange: (event: DataChangedEvent) => void
+) {
+  const callbackRef = useRef(onChange);
+  callbackRef.current = onChange;
+  const entityKey = entities.join(',');
+
+  useEffect(() => {
+    const watched = new Set(entityKey.split(','));
+    const unlisten = listen<DataChangedEvent>('data-changed', ({ payload }) => {
+      if (watched.has(payload.entity)) {
+        callbackRef.current(payload);
+      }
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [entityKey]);
+}
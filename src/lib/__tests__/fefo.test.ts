@@ -114,6 +114,14 @@ describe("filterNonExpired", () => {
     expect(result).toHaveLength(2);
   });
 
+  it("keeps a batch expiring today", () => {
+    const today = new Date();
+    today.setHours(0, 0, 0, 0);
+    const batches = [{ expiryDate: today.toISOString().split("T")[0] }];
+    const result = filterNonExpired(batches);
+    expect(result).toHaveLength(1);
+  });
+
   it("returns empty array when all are expired", () => {
     const batches = [
       { expiryDate: "2020-01-01" },
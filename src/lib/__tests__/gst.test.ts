@@ -5,6 +5,7 @@ import {
   calculateLineItem,
   calculateInvoiceTotal,
   validateNotAboveMrp,
+  isInterstateSupply,
 } from '../gst';
 
 describe('GST Calculation Engine', () => {
@@ -108,6 +109,16 @@ describe('GST Calculation Engine', () => {
       expect(result.totalGstPaise).toBe(gst.totalGstPaise);
       expect(result.totalPaise).toBe(8952 + gst.totalGstPaise);
     });
+
+    it('adds GST on top when prices exclude tax', () => {
+      const inclusive = calculateLineItem(10500, 1, 5, 0);
+      const exclusive = calculateLineItem(10500, 1, 5, 0, false);
+      // Inclusive: 10500 * 100 / 105 = 10000 taxable, 500 GST — totals the MRP
+      expect(inclusive.totalPaise).toBe(10500);
+      expect(exclusive.taxableAmountPaise).toBe(10500);
+      expect(exclusive.totalGstPaise).toBe(525);
+      expect(exclusive.totalPaise).toBe(11025);
+    });
   });
 
   describe('calculateInvoiceTotal', () => {
@@ -124,9 +135,21 @@ describe('GST Calculation Engine', () => {
       expect(totals.totalCgstPaise).toBe(item1.cgstAmountPaise + item2.cgstAmountPaise);
       expect(totals.totalSgstPaise).toBe(item1.sgstAmountPaise + item2.sgstAmountPaise);
       expect(totals.totalGstPaise).toBe(item1.totalGstPaise + item2.totalGstPaise);
+      expect(totals.roundOffPaise).toBe(0);
       expect(totals.grandTotalPaise).toBe(item1.totalPaise + item2.totalPaise);
     });
 
+    it('rounds the grand total to the nearest rupee', () => {
+      const down = calculateInvoiceTotal([calculateLineItem(4725, 1, 5, 0)]);
+      expect(down.roundOffPaise).toBe(-25);
+      expect(down.grandTotalPaise).toBe(4700);
+      expect(down.subtotalPaise).toBe(4725);
+
+      const half = calculateInvoiceTotal([calculateLineItem(4750, 1, 5, 0)]);
+      expect(half.roundOffPaise).toBe(50);
+      expect(half.grandTotalPaise).toBe(4800);
+    });
+
     it('handles empty items array', () => {
       const totals = calculateInvoiceTotal([]);
       expect(totals.subtotalPaise).toBe(0);
@@ -134,6 +157,7 @@ describe('GST Calculation Engine', () => {
       expect(totals.totalCgstPaise).toBe(0);
       expect(totals.totalSgstPaise).toBe(0);
       expect(totals.totalGstPaise).toBe(0);
+      expect(totals.roundOffPaise).toBe(0);
       expect(totals.grandTotalPaise).toBe(0);
     });
   });
@@ -152,6 +176,21 @@ describe('GST Calculation Engine', () => {
     });
   });
 
+  describe('isInterstateSupply', () => {
+    it('is inter-state when customer state differs from pharmacy state', () => {
+      expect(isInterstateSupply('29', '33')).toBe(true);
+    });
+
+    it('is intra-state when states match', () => {
+      expect(isInterstateSupply('29', '29')).toBe(false);
+    });
+
+    it('treats a missing state code on either side as intra-state', () => {
+      expect(isInterstateSupply('29', null)).toBe(false);
+      expect(isInterstateSupply('', '33')).toBe(false);
+    });
+  });
+
   describe('Edge cases', () => {
     it('handles 1 paise item with 5% GST without NaN or Infinity', () => {
       const result = calculateLineItem(1, 1, 5, 0);
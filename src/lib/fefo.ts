@@ -56,7 +56,7 @@ export function allocateFEFO(
 
 /**
  * Filter out expired batches from a list.
- * A batch is expired if its expiryDate <= today.
+ * A batch is good through its expiry date, so one expiring today is kept.
  */
 export function filterNonExpired(
   batches: Pick<Batch, "expiryDate">[]
@@ -65,5 +65,5 @@ export function filterNonExpired(
   today.setHours(0, 0, 0, 0);
   const todayStr = today.toISOString().split("T")[0];
 
-  return batches.filter((b) => b.expiryDate > todayStr);
+  return batches.filter((b) => b.expiryDate >= todayStr);
 }
@@ -2,7 +2,7 @@
  * GST Calculation Engine — Pure functions for Indian GST compliance.
  *
  * All monetary values are integers in paise (1/100 INR).
- * CGST + SGST split (intra-state only).
+ * CGST + SGST split is stored per line; inter-state supplies are shown as IGST.
  * MRP is GST-inclusive; taxable value is back-calculated.
  */
 
@@ -33,6 +33,8 @@ export interface InvoiceTotals {
   totalCgstPaise: number;
   totalSgstPaise: number;
   totalGstPaise: number;
+  /** Added to the line total to reach a whole rupee; may be negative */
+  roundOffPaise: number;
   grandTotalPaise: number;
 }
 
@@ -93,16 +95,22 @@ export function calculateGst(
  * afterDiscount = lineSubtotal - discount  (GST-inclusive amount)
  * taxable = back-calculated from afterDiscount
  * total = taxable + gst
+ *
+ * When the pharmacy prices before tax (pricesIncludeGst = false),
+ * afterDiscount is the taxable amount and GST is added on top.
  */
 export function calculateLineItem(
   unitPricePaise: number,
   quantity: number,
   gstRate: number,
-  discountPaise: number
+  discountPaise: number,
+  pricesIncludeGst = true
 ): SaleItemCalculation {
   const lineSubtotal = unitPricePaise * quantity;
   const afterDiscount = lineSubtotal - discountPaise;
-  const taxableAmount = calculateTaxableAmount(afterDiscount, gstRate);
+  const taxableAmount = pricesIncludeGst
+    ? calculateTaxableAmount(afterDiscount, gstRate)
+    : afterDiscount;
   const gst = calculateGst(taxableAmount, gstRate);
 
   return {
@@ -119,8 +127,16 @@ export function calculateLineItem(
   };
 }
 
+/**
+ * Round a payable amount to the nearest rupee; 50 paise rounds up.
+ */
+export function roundToRupee(paise: number): number {
+  return Math.floor((paise + 50) / 100) * 100;
+}
+
 /**
  * Aggregate line-item calculations into invoice totals.
+ * Only the grand total is rounded to the rupee; tax figures stay exact.
  */
 export function calculateInvoiceTotal(
   items: SaleItemCalculation[]
@@ -141,13 +157,16 @@ export function calculateInvoiceTotal(
     grandTotalPaise += item.totalPaise;
   }
 
+  const roundedPaise = roundToRupee(grandTotalPaise);
+
   return {
     subtotalPaise,
     discountPaise,
     totalCgstPaise,
     totalSgstPaise,
     totalGstPaise,
-    grandTotalPaise,
+    roundOffPaise: roundedPaise - grandTotalPaise,
+    grandTotalPaise: roundedPaise,
   };
 }
 
@@ -161,3 +180,18 @@ export function validateNotAboveMrp(
 ): boolean {
   return sellingPricePaise <= mrpPaise;
 }
+
+/**
+ * Decide whether a supply is inter-state (IGST) or intra-state (CGST + SGST).
+ *
+ * Place of supply is the customer's state. Walk-in customers without a state
+ * code are treated as intra-state, as are pharmacies that have not configured
+ * their own state code.
+ */
+export function isInterstateSupply(
+  pharmacyStateCode: string | null | undefined,
+  customerStateCode: string | null | undefined
+): boolean {
+  if (!pharmacyStateCode || !customerStateCode) return false;
+  return pharmacyStateCode !== customerStateCode;
+}
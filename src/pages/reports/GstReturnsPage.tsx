@@ -0,0 +1 @@
+export { default } from "@/features/reports/GstReturnsPage";
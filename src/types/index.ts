@@ -5,6 +5,14 @@ export type UserRole = 'admin' | 'pharmacist' | 'cashier';
 
 export type PaymentMode = 'cash' | 'card' | 'upi' | 'credit';
 
+export type BatchStatus = 'available' | 'quarantine' | 'blocked';
+
+/** Drugs and Cosmetics Rules schedule; null for over-the-counter */
+export type DrugSchedule = 'H' | 'H1' | 'X';
+
+/** Whether a sale quantity counts whole packs or loose units */
+export type SaleUnit = 'pack' | 'loose';
+
 export type DosageForm = 'tablet' | 'capsule' | 'syrup' | 'injection' | 'cream' | 'ointment' | 'drops' | 'inhaler' | 'powder' | 'gel' | 'lotion' | 'suspension' | 'other';
 
 export interface User {
@@ -18,6 +26,16 @@ export interface User {
   updatedAt: string;
 }
 
+/** A user as the backend hands it out, without the password hash */
+export type SessionUser = Omit<User, 'passwordHash'>;
+
+export interface Session {
+  /** Opaque token kept by the frontend to restore the session */
+  token: string;
+  expiresAt: string;
+  user: SessionUser;
+}
+
 export interface GstSlab {
   id: number;
   /** GST rate as percentage, e.g. 5, 12, 18 */
@@ -34,8 +52,16 @@ export interface Medicine {
   dosageForm: DosageForm;
   strength: string | null;
   category: string | null;
+  /** Scheduled medicines are only sold against a prescription */
+  schedule: DrugSchedule | null;
+  /** Loose units per pack, e.g. 10 tablets to a strip; stock is counted in loose units */
+  packSize: number;
+  /** Unit a sale line uses when it does not say; null means packs */
+  saleUnit: SaleUnit | null;
   /** HSN code — typically 3004 for retail medicines */
   hsnCode: string;
+  /** EAN-13/GTIN printed on the manufacturer's pack */
+  gtin: string | null;
   gstSlabId: number;
   /** Minimum stock level before alert triggers */
   reorderLevel: number;
@@ -58,6 +84,10 @@ export interface Batch {
   /** Current quantity in stock */
   quantity: number;
   manufacturingDate: string | null;
+  /** Only 'available' batches are sellable; quarantined/blocked stock still has value */
+  status: BatchStatus;
+  /** Scanned at the till; unique across batches */
+  barcode: string | null;
   createdAt: string;
 }
 
@@ -67,6 +97,17 @@ export interface Customer {
   phone: string | null;
   email: string | null;
   address: string | null;
+  /** 15-digit GSTIN for B2B customers (clinics, hospitals) */
+  gstin: string | null;
+  /** 2-digit state code; decides IGST vs CGST/SGST on the invoice */
+  stateCode: string | null;
+  city: string | null;
+  /** 6-digit PIN code, required on e-invoices */
+  pincode: string | null;
+  /** Loyalty points available to redeem */
+  loyaltyPoints: number;
+  /** Most the customer may owe on credit; null for no limit */
+  creditLimitPaise: Paise | null;
   createdAt: string;
   updatedAt: string;
 }
@@ -79,6 +120,9 @@ export interface Prescription {
   rxNumber: string | null;
   prescriptionDate: string;
   notes: string | null;
+  /** Further sales the doctor authorized after the first */
+  refillsAllowed: number;
+  refillsUsed: number;
   createdAt: string;
 }
 
@@ -106,6 +150,9 @@ export interface SupplierPayment {
   createdAt: string;
 }
 
+/** `amended` sales were corrected after finalization; see `sale_amendments` */
+export type SaleStatus = 'completed' | 'amended' | 'refunded';
+
 export interface Sale {
   id: number;
   invoiceNumber: string;
@@ -120,11 +167,22 @@ export interface Sale {
   totalCgstPaise: Paise;
   /** Total SGST in paise */
   totalSgstPaise: Paise;
-  /** Total GST (CGST + SGST) in paise */
+  /** Total IGST in paise (inter-state sales only) */
+  totalIgstPaise: Paise;
+  /** Total GST (CGST + SGST + IGST) in paise */
   totalGstPaise: Paise;
-  /** Grand total including GST in paise */
+  /** Rounding to the nearest rupee in paise; may be negative */
+  roundOffPaise: Paise;
+  /** Grand total including GST and round-off in paise */
   grandTotalPaise: Paise;
   paymentMode: PaymentMode;
+  status: SaleStatus;
+  /** Customer's state differed from the pharmacy's when the sale was taxed */
+  isInterstate: boolean;
+  /** State code the sale was taxed for */
+  placeOfSupply: string | null;
+  /** Billed under the composition scheme, without tax */
+  billOfSupply: boolean;
   notes: string | null;
   createdAt: string;
 }
@@ -149,6 +207,10 @@ export interface SaleItem {
   sgstRate: number;
   /** SGST amount in paise */
   sgstAmountPaise: Paise;
+  /** IGST rate as percentage (full GST rate on inter-state lines, else 0) */
+  igstRate: number;
+  /** IGST amount in paise */
+  igstAmountPaise: Paise;
   /** Total for this line item in paise */
   totalPaise: Paise;
 }
@@ -164,16 +226,116 @@ export interface PharmacySettings {
   drugLicenseNo: string;
   /** 2-digit state code for GST */
   stateCode: string;
+  city: string;
+  /** 6-digit PIN code */
+  pincode: string;
+  /** Whether B2B invoices can be exported as e-invoice JSON */
+  einvoiceEnabled: boolean;
   invoicePrefix: string;
+  /** Template for invoice numbers using {prefix}, {fy} and {seq} or {seq:N} */
+  invoiceNumberFormat: string;
+  /** Restart the invoice sequence at 1 every April; the format must include {fy} */
+  fyReset: boolean;
   nextInvoiceNumber: number;
+  /** Prefix of credit notes, numbered in their own series in the invoice format */
+  creditNotePrefix: string;
+  nextCreditNoteNumber: number;
+  /** Prefix of debit notes raised on returns to suppliers */
+  debitNotePrefix: string;
+  nextDebitNoteNumber: number;
   /** Default threshold for low stock alerts */
   lowStockThreshold: number;
   /** Days before expiry to trigger alert */
   nearExpiryDays: number;
+  /** Supplier lead time used when suggesting reorder levels */
+  reorderLeadTimeDays: number;
+  /** Selling prices include GST (MRP convention); otherwise GST is added on top */
+  pricesIncludeGst: boolean;
+  /** Registered under the composition scheme: sales are bills of supply without tax */
+  compositionScheme: boolean;
+  /** Spend in paise that earns one loyalty point; 0 turns accrual off */
+  loyaltySpendPerPointPaise: number;
+  /** What one loyalty point is worth in paise when redeemed; 0 turns redemption off */
+  loyaltyPointValuePaise: number;
+  /** Hours a checkout's idempotency key is remembered */
+  idempotencyWindowHours: number;
+  /** Failed logins within the window that lock an account */
+  lockoutMaxAttempts: number;
+  lockoutWindowMinutes: number;
+  /** How long a locked account stays locked */
+  lockoutMinutes: number;
+  /** How the receipt printer is attached; 'none' prints through the browser */
+  printerInterface: PrinterInterface;
+  /** Device path (/dev/usb/lp0, COM3) or host:port of a network printer */
+  printerAddress: string;
+  /** Characters per receipt line: 32 on 58 mm paper, 48 on 80 mm */
+  printerLineWidth: number;
+  /** Take a snapshot into the backups folder every day */
+  autoBackupEnabled: boolean;
+  /** Local time of the daily snapshot, HH:MM */
+  autoBackupTime: string;
+  /** Snapshots kept before the oldest are deleted */
+  autoBackupKeep: number;
+  /** Backups folder; null keeps them beside the database */
+  autoBackupDir: string | null;
+  /** Where new snapshots are uploaded, as encrypted archives */
+  remoteBackupTarget: RemoteBackupTarget;
+  /** S3 bucket URL, WebDAV collection URL, or Google Drive folder id */
+  remoteBackupUrl: string;
+  /** S3 region */
+  remoteBackupRegion: string;
+  /** S3 access key id, WebDAV user, or Google OAuth client id */
+  remoteBackupUsername: string;
+  /** Days between integrity check + VACUUM runs after the nightly backup; 0 = never */
+  maintenanceIntervalDays: number;
+  /** GSP base URL for e-invoice (IRN) generation */
+  einvoiceApiUrl: string;
+  einvoiceClientId: string;
+  einvoiceClientSecret: string;
+  /** E-invoice API user created on the IRP portal */
+  einvoiceUsername: string;
+  einvoicePassword: string;
+  /** E-way bill API user; requests go through the e-invoicing GSP */
+  ewaybillUsername: string;
+  ewaybillPassword: string;
+  /** Consignment value above which a dispatch needs an e-way bill */
+  ewaybillThresholdPaise: number;
   createdAt: string;
   updatedAt: string;
 }
 
+export type PrinterInterface = 'none' | 'usb' | 'serial' | 'network';
+
+export type RemoteBackupTarget = 'none' | 's3' | 'gdrive' | 'webdav';
+
+// Backend change notifications (`data-changed` event)
+export type DataEntity =
+  | 'alert'
+  | 'batch'
+  | 'customer'
+  | 'customer_payment'
+  | 'debit_note'
+  | 'expense'
+  | 'expense_category'
+  | 'location'
+  | 'medicine'
+  | 'prescription'
+  | 'purchase'
+  | 'purchase_order'
+  | 'sale'
+  | 'shift'
+  | 'stock_take'
+  | 'supplier'
+  | 'supplier_payment';
+
+export type DataChangeOp = 'insert' | 'update' | 'delete';
+
+export interface DataChangedEvent {
+  entity: DataEntity;
+  op: DataChangeOp;
+  id: number;
+}
+
 // View/computed types (not stored directly)
 export interface StockAlert {
   medicineId: number;
@@ -205,7 +367,14 @@ export interface BatchWithMedicine extends Batch {
 // Type for Sale with joined info
 export interface SaleWithDetails extends Sale {
   customerName: string | null;
+  customerGstin: string | null;
+  customerStateCode: string | null;
   userName: string;
+  /** IRN once the sale is registered as an e-invoice */
+  irn: string | null;
+  /** Active e-way bill, if the goods travel on one */
+  ewbNo: string | null;
+  ewbValidUpto: string | null;
   items: SaleItemWithDetails[];
 }
 